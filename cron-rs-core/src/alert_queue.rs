@@ -0,0 +1,232 @@
+use anyhow::{anyhow, Result};
+use log::error;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Condvar, Mutex};
+use tokio::sync::{oneshot, Notify};
+
+/// Alerts waiting to be sent when `alerts.queue.size` is not set
+pub const DEFAULT_QUEUE_SIZE: usize = 100;
+
+/// Alerts sent at the same time when `alerts.queue.workers` is not set
+pub const DEFAULT_QUEUE_WORKERS: usize = 4;
+
+/// The alerts of the scheduler, see [queue_alert]
+static ALERT_QUEUE: AlertQueue = AlertQueue::new();
+
+/// How the alerts are sent, `alerts.queue` in the config file
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AlertQueueConfig {
+    /// Alerts waiting to be sent, [DEFAULT_QUEUE_SIZE] by default
+    pub size: Option<usize>,
+    /// Alerts sent at the same time, [DEFAULT_QUEUE_WORKERS] by default. The emails to the same SMTP server share its
+    /// connections
+    pub workers: Option<usize>,
+    /// What happens to an alert when the queue is full, `wait` by default
+    pub queue_full_policy: Option<QueueFullPolicy>,
+}
+
+/// What happens to an alert when the queue is full, the dropped alerts fail with an error
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueFullPolicy {
+    /// Waits until there is room, which delays whatever sends the alert
+    #[default]
+    Wait,
+    /// Drops the new alert
+    DropNewest,
+    /// Drops the alert that has been waiting the longest to make room for the new one
+    DropOldest,
+}
+
+type Job = Box<dyn FnOnce() -> Result<()> + Send>;
+
+struct QueuedAlert {
+    send: Job,
+    result: oneshot::Sender<Result<()>>,
+}
+
+struct QueueState {
+    alerts: VecDeque<QueuedAlert>,
+    size: usize,
+    workers: usize,
+    policy: QueueFullPolicy,
+    /// Worker threads started, the ones above `workers` stop once they are idle
+    running: usize,
+}
+
+/// Bounded queue of alerts sent by a few threads, so a burst of failures doesn't send all its alerts at the same time
+pub struct AlertQueue {
+    state: Mutex<QueueState>,
+    /// Wakes up the workers when an alert is added
+    queued: Condvar,
+    /// Wakes up the alerts waiting for room
+    space: Notify,
+}
+
+impl AlertQueue {
+    const fn new() -> Self {
+        Self {
+            state: Mutex::new(QueueState {
+                alerts: VecDeque::new(),
+                size: DEFAULT_QUEUE_SIZE,
+                workers: DEFAULT_QUEUE_WORKERS,
+                policy: QueueFullPolicy::Wait,
+                running: 0,
+            }),
+            queued: Condvar::new(),
+            space: Notify::const_new(),
+        }
+    }
+
+    fn configure(&self, config: &AlertQueueConfig) {
+        let mut state = self.state.lock().unwrap();
+        state.size = config.size.unwrap_or(DEFAULT_QUEUE_SIZE).max(1);
+        state.workers = config.workers.unwrap_or(DEFAULT_QUEUE_WORKERS).max(1);
+        state.policy = config.queue_full_policy.unwrap_or_default();
+        // The extra workers stop
+        self.queued.notify_all();
+        drop(state);
+        // A bigger queue has room for the waiting alerts
+        self.space.notify_waiters();
+    }
+
+    async fn send(&'static self, send: Job) -> Result<()> {
+        let (sender, result) = oneshot::channel();
+        let mut alert = Some(QueuedAlert { send, result: sender });
+
+        while let Some(queued) = alert.take() {
+            let space = self.space.notified();
+            tokio::pin!(space);
+            // Registered before checking the queue, so the room made in between isn't missed
+            space.as_mut().enable();
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.alerts.len() >= state.size {
+                    match state.policy {
+                        QueueFullPolicy::Wait => {}
+                        QueueFullPolicy::DropNewest => return Err(anyhow!("The alert queue is full, the alert was dropped")),
+                        QueueFullPolicy::DropOldest => {
+                            if let Some(oldest) = state.alerts.pop_front() {
+                                let _ = oldest.result.send(Err(anyhow!("The alert queue is full, the alert was dropped")));
+                            }
+                        }
+                    }
+                }
+                if state.alerts.len() < state.size {
+                    state.alerts.push_back(queued);
+                    while state.running < state.workers {
+                        state.running += 1;
+                        self.start_worker();
+                    }
+                    self.queued.notify_one();
+                    break;
+                }
+                alert = Some(queued);
+            }
+            space.await;
+        }
+
+        result.await.unwrap_or_else(|_| Err(anyhow!("The alert was not sent")))
+    }
+
+    fn start_worker(&'static self) {
+        let spawned = std::thread::Builder::new().name("cron-rs-alerts".to_string()).spawn(move || self.work());
+        if let Err(e) = spawned {
+            error!("Failed to start a thread to send the alerts: {}", e);
+            self.state.lock().unwrap().running -= 1;
+        }
+    }
+
+    fn work(&self) {
+        loop {
+            let alert = {
+                let mut state = self.state.lock().unwrap();
+                loop {
+                    if state.running > state.workers {
+                        state.running -= 1;
+                        return;
+                    }
+                    if let Some(alert) = state.alerts.pop_front() {
+                        break alert;
+                    }
+                    state = self.queued.wait(state).unwrap();
+                }
+            };
+            self.space.notify_waiters();
+            let _ = alert.result.send((alert.send)());
+        }
+    }
+}
+
+/// Applies `alerts.queue`, called when the config is read
+pub fn configure_alert_queue(config: &AlertQueueConfig) {
+    ALERT_QUEUE.configure(config);
+}
+
+/// Sends an alert from one of the threads of the queue, waiting for the result. Sending an alert blocks, the queue
+/// keeps it out of the runtime and limits how many are sent at the same time
+pub async fn queue_alert(send: impl FnOnce() -> Result<()> + Send + 'static) -> Result<()> {
+    ALERT_QUEUE.send(Box::new(send)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    /// A queue with a single worker busy with an alert until the returned sender is used
+    async fn busy_queue(policy: QueueFullPolicy) -> (&'static AlertQueue, mpsc::Sender<()>, tokio::task::JoinHandle<Result<()>>) {
+        let queue: &'static AlertQueue = Box::leak(Box::new(AlertQueue::new()));
+        queue.configure(&AlertQueueConfig { size: Some(1), workers: Some(1), queue_full_policy: Some(policy) });
+
+        let (release, wait) = mpsc::channel();
+        let (started, mut is_started) = tokio::sync::mpsc::channel(1);
+        let busy = tokio::spawn(queue.send(Box::new(move || {
+            started.blocking_send(()).unwrap();
+            wait.recv().unwrap();
+            Ok(())
+        })));
+        is_started.recv().await;
+        (queue, release, busy)
+    }
+
+    fn ok() -> Job {
+        Box::new(|| Ok(()))
+    }
+
+    #[tokio::test]
+    async fn test_queue_full_policy() {
+        // The worker is busy and the queued alert fills the queue
+        let (queue, release, busy) = busy_queue(QueueFullPolicy::DropNewest).await;
+        let queued = tokio::spawn(queue.send(ok()));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(queue.send(ok()).await.unwrap_err().to_string().contains("queue is full"));
+        release.send(()).unwrap();
+        assert!(busy.await.unwrap().is_ok());
+        assert!(queued.await.unwrap().is_ok());
+
+        let (queue, release, busy) = busy_queue(QueueFullPolicy::DropOldest).await;
+        let oldest = tokio::spawn(queue.send(ok()));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let newest = tokio::spawn(queue.send(ok()));
+        assert!(oldest.await.unwrap().is_err());
+        release.send(()).unwrap();
+        assert!(busy.await.unwrap().is_ok());
+        assert!(newest.await.unwrap().is_ok());
+
+        // The new alert waits for room
+        let (queue, release, busy) = busy_queue(QueueFullPolicy::Wait).await;
+        let first = tokio::spawn(queue.send(ok()));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let second = tokio::spawn(queue.send(Box::new(|| Err(anyhow!("webhook down")))));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!second.is_finished());
+        release.send(()).unwrap();
+        assert!(busy.await.unwrap().is_ok());
+        assert!(first.await.unwrap().is_ok());
+        assert_eq!(second.await.unwrap().unwrap_err().to_string(), "webhook down");
+    }
+}
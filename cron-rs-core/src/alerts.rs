@@ -0,0 +1,1139 @@
+use crate::alert_queue::AlertQueueConfig;
+use crate::config::secrets::resolve_secrets;
+use crate::config::{Config, Schedule, TaskConfig};
+use crate::executor::{shell_process, RunContext};
+use crate::plugins::send_plugin_alert;
+use crate::resource_usage::ResourceUsage;
+use crate::schedule_display::ScheduleDisplay;
+use crate::scheduler::Scheduler;
+use crate::config::file::StopSignal;
+use crate::utils::{format_duration, format_size, hostname, new_run_id, set_process_group, signal_process_group};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use chrono_tz::Tz;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use log::info;
+use reqwest::blocking::Client;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::io::{Read, Write};
+use std::ops::Add;
+use std::process::{Child, ExitStatus, Stdio};
+use std::sync::{mpsc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct AlertConfig {
+    #[serde(default)]
+    pub on_failure: Vec<Alert>,
+    #[serde(default)]
+    pub on_success: Vec<Alert>,
+    /// Sent on the failures next to `on_failure`, see [AlertConfig::escalate]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub escalation: Vec<EscalationStep>,
+    /// Sent when the scheduler itself fails, like when it loses track of an execution, see [send_internal_error_alert]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub on_internal_error: Vec<Alert>,
+    /// Sent when the wall clock jumps, see [send_clock_change_alert]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub on_clock_change: Vec<Alert>,
+    /// Sent when a task uses its `max_monthly_runtime`, see [send_budget_alert]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub on_budget_exhausted: Vec<Alert>,
+    /// How many alerts the scheduler sends at the same time and how many can wait, see [crate::alert_queue::queue_alert]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub queue: Option<AlertQueueConfig>,
+}
+
+/// A step of the escalation of the failures
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscalationStep {
+    pub alert: Alert,
+    /// Failed runs of a task in a row that reach this step, the value of the previous step by default, 1 for the first
+    pub after_failures: Option<u32>,
+}
+
+impl AlertConfig {
+    /// The `after_failures` of each step of the escalation, with the defaults filled in
+    pub fn escalation_levels(&self) -> Vec<u32> {
+        let mut level = 1;
+        self.escalation
+            .iter()
+            .map(|step| {
+                level = step.after_failures.unwrap_or(level);
+                level
+            })
+            .collect()
+    }
+
+    /// Sends a failure through the escalation, `failures` is the number of failed runs of the task in a row. It starts
+    /// at the first step of the highest level reached and falls through to the next steps until one is sent. Returns
+    /// false if the steps reached couldn't be sent
+    pub fn escalate(&self, failures: u32, mut send: impl FnMut(&Alert) -> Result<()>) -> bool {
+        let steps = self.escalation_steps(failures);
+        steps.is_empty() || steps.iter().any(|step| send(&step.alert).is_ok())
+    }
+
+    /// The steps of the escalation a failure goes through, from the first step of the highest level reached. Empty if
+    /// no level is reached
+    pub fn escalation_steps(&self, failures: u32) -> &[EscalationStep] {
+        let levels = self.escalation_levels();
+        let Some(level) = levels.iter().copied().filter(|level| *level <= failures).max() else { return &[] };
+        let start = levels.iter().position(|l| *l == level).unwrap_or_default();
+        &self.escalation[start..]
+    }
+
+    /// The levels of the escalation can't go down, or the later steps would be reached before the earlier ones
+    pub fn check_escalation(&self) -> Result<()> {
+        let levels = self.escalation_levels();
+        if let Some(i) = (1..levels.len()).find(|i| levels[*i] < levels[i - 1]) {
+            bail!("after_failures of step {} is lower than the one of the previous step", i);
+        }
+        if levels.first() == Some(&0) {
+            bail!("after_failures must be at least 1");
+        }
+        Ok(())
+    }
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Alert {
+    #[serde(rename = "email")]
+    Email {
+        to: EmailAddresses,
+        #[serde(default)]
+        cc: Option<EmailAddresses>,
+        #[serde(default)]
+        bcc: Option<EmailAddresses>,
+        #[serde(default)]
+        reply_to: Option<String>,
+        #[serde(default)]
+        subject: Option<String>,
+        #[serde(default)]
+        body: Option<String>,
+        #[serde(default)]
+        from: Option<String>,
+        #[serde(default)]
+        smtp_server: Option<String>,
+        #[serde(default)]
+        smtp_port: Option<u16>,
+        #[serde(default)]
+        smtp_username: Option<String>,
+        #[serde(default)]
+        smtp_password: Option<String>,
+        #[serde(default = "default_escape_email")]
+        escape: EscapeStrategy,
+    },
+    #[serde(rename = "cmd")]
+    Cmd {
+        cmd: String,
+        #[serde(default = "default_escape_cmd")]
+        escape: EscapeStrategy,
+        /// Runs the command as a plugin, with the details of the alert as JSON on stdin and a meaning for its exit
+        /// codes, see [run_alert_cmd]
+        #[serde(default)]
+        plugin: Option<bool>,
+        /// Time the command has to finish before it's killed, [DEFAULT_PLUGIN_TIMEOUT] for the plugins, no limit for
+        /// the rest of the commands
+        #[serde(default)]
+        timeout: Option<String>,
+    },
+    #[serde(rename = "webhook")]
+    Webhook {
+        url: String,
+        #[serde(default)]
+        method: Option<String>,
+        #[serde(default)]
+        body: Option<String>,
+        #[serde(default)]
+        headers: HashMap<String, String>,
+        /// Status codes of a delivered alert, any 2xx by default
+        #[serde(default)]
+        expect_status: Option<StatusCodes>,
+        /// Text the response must contain, for the APIs that answer errors with a 200
+        #[serde(default)]
+        expect_body_contains: Option<String>,
+        #[serde(default = "default_escape_webhook")]
+        escape: EscapeStrategy,
+    },
+    /// Sent by a WASM plugin of the `plugins` section, see [crate::plugins::send_plugin_alert]
+    #[serde(rename = "plugin")]
+    Plugin { plugin: String },
+    /// Name of an alert of `alert_channels`, replaced by it when the config is read
+    #[serde(untagged)]
+    Channel(String),
+}
+
+/// Recipients of an email, one address or a list of them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum EmailAddresses {
+    One(String),
+    List(Vec<String>),
+}
+
+impl EmailAddresses {
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        let addresses = match self {
+            EmailAddresses::One(address) => std::slice::from_ref(address),
+            EmailAddresses::List(addresses) => addresses.as_slice(),
+        };
+        addresses.iter().map(String::as_str)
+    }
+}
+
+impl From<&str> for EmailAddresses {
+    fn from(address: &str) -> Self {
+        EmailAddresses::One(address.to_string())
+    }
+}
+
+impl From<Vec<String>> for EmailAddresses {
+    fn from(mut addresses: Vec<String>) -> Self {
+        match addresses.len() {
+            1 => EmailAddresses::One(addresses.remove(0)),
+            _ => EmailAddresses::List(addresses),
+        }
+    }
+}
+
+impl Display for EmailAddresses {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.iter().collect::<Vec<_>>().join(", "))
+    }
+}
+
+/// HTTP status codes, one or a list of them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StatusCodes {
+    One(u16),
+    List(Vec<u16>),
+}
+
+impl StatusCodes {
+    pub fn contains(&self, status: u16) -> bool {
+        match self {
+            StatusCodes::One(code) => *code == status,
+            StatusCodes::List(codes) => codes.contains(&status),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct TaskExecutionDetails {
+    pub task_name: String,
+    pub task_id: u32,
+    pub run_id: String,
+    pub pid: u32,
+    pub exit_code: i32,
+    pub start_time: DateTime<Utc>,
+    pub duration: Duration,
+    pub error_message: String,
+    pub debug_info: String,
+    pub stdout: String,
+    pub stderr: String,
+    /// Max memory used by the task in bytes, only known when it runs in a cgroup
+    pub peak_memory: Option<u64>,
+    /// Only known for the commands on Linux
+    pub usage: Option<ResourceUsage>,
+    pub context: ExecutionContext,
+}
+
+/// Time limit of the plugins that don't set `timeout`
+pub const DEFAULT_PLUGIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Exit code of a plugin that failed temporarily, `EX_TEMPFAIL` of sysexits.h
+pub const PLUGIN_TEMPFAIL: i32 = 75;
+
+/// Runs of a plugin that fails temporarily before giving up
+const PLUGIN_ATTEMPTS: u32 = 3;
+
+/// Wait between the runs of a plugin that fails temporarily
+const PLUGIN_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Time to wait for the end of the stderr of an alert command without `timeout` once it exits
+const ALERT_STDERR_WAIT: Duration = Duration::from_secs(1);
+
+/// Fields of the payload passed to the alert commands as env vars
+const PAYLOAD_ENV: [(&str, &str); 4] = [
+    ("task_name", "CRON_RS_TASK_NAME"),
+    ("run_id", "CRON_RS_RUN_ID"),
+    ("exit_code", "CRON_RS_EXIT_CODE"),
+    ("hostname", "CRON_RS_HOSTNAME"),
+];
+
+/// Lines of the output in `{{ stdout_tail }}` and `{{ stderr_tail }}`
+pub const OUTPUT_TAIL_LINES: usize = 20;
+
+/// Where the task ran and when it runs again
+#[derive(Clone)]
+pub struct ExecutionContext {
+    pub hostname: String,
+    /// The schedule as shown by `list`, e.g. `Every 5 m`
+    pub schedule: String,
+    pub timezone: Tz,
+    /// None for the tasks that run when triggered
+    pub next_run: Option<DateTime<Tz>>,
+    /// Attempt number of the run, starting at 1
+    pub attempt: u32,
+    /// Failed runs of the task in a row before this one
+    pub prev_failures: u32,
+}
+
+impl ExecutionContext {
+    pub fn new(task: &TaskConfig, run: &RunContext) -> Self {
+        let now = Scheduler::get_current_datetime_at(task.timezone);
+        Self {
+            hostname: hostname(),
+            schedule: ScheduleDisplay::format_schedule(&task.schedule),
+            timezone: task.timezone,
+            next_run: ScheduleDisplay::get_next_execution_times(task, now, 1).first().copied(),
+            attempt: run.attempt,
+            prev_failures: run.prev_failures,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum EscapeStrategy {
+    #[serde(rename = "none")]
+    None,
+    #[serde(rename = "json")]
+    Json,
+    #[serde(rename = "html")]
+    Html,
+    #[serde(rename = "shell")]
+    Shell,
+}
+
+impl Alert {
+    /// Type of the alert as written in the config file
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Alert::Email { .. } => "email",
+            Alert::Cmd { .. } => "cmd",
+            Alert::Webhook { .. } => "webhook",
+            Alert::Plugin { .. } => "plugin",
+            Alert::Channel(_) => "channel",
+        }
+    }
+
+    /// Who the alert goes to, for the audit of the alerts: the address of the emails, the host of the webhooks and the
+    /// program of the commands. The rest of the URL and of the command is left out, it may have tokens
+    pub fn target(&self) -> String {
+        match self {
+            Alert::Email { to, .. } => to.to_string(),
+            Alert::Cmd { cmd, .. } => cmd.split_whitespace().next().unwrap_or_default().to_string(),
+            Alert::Webhook { url, .. } => {
+                reqwest::Url::parse(url).ok().and_then(|url| url.host_str().map(str::to_string)).unwrap_or_default()
+            }
+            Alert::Plugin { plugin } => plugin.clone(),
+            Alert::Channel(name) => name.clone(),
+        }
+    }
+
+    /// Command alert, with the values of the placeholders quoted for the shell
+    pub fn cmd(cmd: impl Into<String>) -> Self {
+        Alert::Cmd { cmd: cmd.into(), escape: default_escape_cmd(), plugin: None, timeout: None }
+    }
+
+    /// Email alert with the default subject, body and SMTP settings
+    pub fn email(to: impl Into<EmailAddresses>) -> Self {
+        Alert::Email {
+            to: to.into(),
+            cc: None,
+            bcc: None,
+            reply_to: None,
+            subject: None,
+            body: None,
+            from: None,
+            smtp_server: None,
+            smtp_port: None,
+            smtp_username: None,
+            smtp_password: None,
+            escape: default_escape_email(),
+        }
+    }
+}
+
+impl TaskExecutionDetails {
+    /// The execution as the payload of the alert plugins, `event` is `task`
+    pub fn to_json(&self) -> Value {
+        let end_time = self.start_time + TimeDelta::from_std(self.duration).unwrap_or_default();
+        let context = &self.context;
+        json!({
+            "event": "task",
+            "task_name": self.task_name,
+            "task_id": self.task_id,
+            "run_id": self.run_id,
+            "pid": self.pid,
+            "exit_code": self.exit_code,
+            "start_time": self.start_time.to_rfc3339(),
+            "end_time": end_time.to_rfc3339(),
+            "duration_seconds": self.duration.as_secs_f64(),
+            "error_message": self.error_message,
+            "debug_info": self.debug_info,
+            "stdout": self.stdout,
+            "stderr": self.stderr,
+            "peak_memory": self.peak_memory,
+            "usage": self.usage.map(|usage| usage.to_json()),
+            "hostname": context.hostname,
+            "schedule": context.schedule,
+            "timezone": context.timezone.name(),
+            "next_run": context.next_run.map(|time| time.to_rfc3339()),
+            "attempt": context.attempt,
+            "prev_failures": context.prev_failures,
+        })
+    }
+
+    /// Made up failed execution of the task, or of a task named `test-alert`, to check the settings of the alerts
+    pub fn test(task: Option<&TaskConfig>) -> Self {
+        let start_time = Utc::now();
+        let context = match task {
+            Some(task) => ExecutionContext::new(task, &RunContext::new(start_time)),
+            None => ExecutionContext {
+                hostname: hostname(),
+                schedule: String::new(),
+                timezone: Tz::UTC,
+                next_run: None,
+                attempt: 1,
+                prev_failures: 0,
+            },
+        };
+        Self {
+            task_name: task.map(|task| task.name.clone()).unwrap_or_else(|| "test-alert".to_string()),
+            task_id: 0,
+            run_id: new_run_id(start_time),
+            pid: 0,
+            exit_code: 1,
+            start_time,
+            duration: Duration::from_secs(1),
+            error_message: "Test alert sent by cron-rs test-alert, the task didn't run".to_string(),
+            debug_info: String::new(),
+            stdout: "Output of the task".to_string(),
+            stderr: "Errors of the task".to_string(),
+            peak_memory: None,
+            usage: None,
+            context,
+        }
+    }
+}
+
+/// An alert of the config file
+pub struct ConfiguredAlert<'a> {
+    /// Where it is defined, like `alerts.on_failure[0]` or `tasks.backup.on_success[1]`
+    pub name: String,
+    /// None for the alerts of all the tasks
+    pub task: Option<&'a TaskConfig>,
+    pub alert: &'a Alert,
+}
+
+/// All the alerts of the config file. The defaults are copied into each task, the alerts that are the same as an
+/// earlier one are left out
+pub fn configured_alerts(config: &Config) -> Vec<ConfiguredAlert<'_>> {
+    let escalation: Vec<&Alert> = config.alerts.escalation.iter().map(|step| &step.alert).collect();
+    let mut lists = vec![
+        ("alerts.on_failure".to_string(), None, config.alerts.on_failure.iter().collect()),
+        ("alerts.on_success".to_string(), None, config.alerts.on_success.iter().collect()),
+        ("alerts.escalation".to_string(), None, escalation),
+    ];
+    for task in &config.tasks {
+        lists.push((format!("tasks.{}.on_failure", task.name), Some(task.as_ref()), task.on_failure.iter().collect()));
+        lists.push((format!("tasks.{}.on_success", task.name), Some(task.as_ref()), task.on_success.iter().collect()));
+    }
+
+    let mut seen = vec![];
+    let mut alerts = vec![];
+    for (list_name, task, list) in lists {
+        for (index, alert) in list.iter().enumerate() {
+            let key = serde_json::to_string(alert).unwrap_or_default();
+            if seen.contains(&key) {
+                continue;
+            }
+            seen.push(key);
+            alerts.push(ConfiguredAlert { name: format!("{}[{}]", list_name, index), task, alert });
+        }
+    }
+    alerts
+}
+
+fn default_escape_email() -> EscapeStrategy {
+    EscapeStrategy::Html
+}
+
+fn default_escape_cmd() -> EscapeStrategy {
+    EscapeStrategy::Shell
+}
+
+fn default_escape_webhook() -> EscapeStrategy {
+    EscapeStrategy::Json
+}
+
+pub fn send_alert(alert: &Alert, details: &TaskExecutionDetails) -> Result<()> {
+    let default_body = "Task {{ task_name }} failed with exit code {{ exit_code }}";
+    deliver_alert(alert, "Task Failure Alert", default_body, &details.to_json(), &|template, escape| {
+        template_replace(template, details, escape)
+    })
+}
+
+/// Sends an error of the scheduler, with the `{{ error }}`, `{{ task_name }}` and `{{ hostname }}` placeholders. The task
+/// name is empty if the error is not about a task
+pub fn send_internal_error_alert(alert: &Alert, task_name: Option<&str>, error: &str) -> Result<()> {
+    let default_body = "cron-rs on {{ hostname }} failed: {{ error }}";
+    let payload = json!({ "event": "internal_error", "task_name": task_name, "error": error, "hostname": hostname() });
+    deliver_alert(alert, "cron-rs Internal Error", default_body, &payload, &|template, escape| {
+        let mut result = template.to_string();
+        replace_and_escape(&mut result, "error", error, escape);
+        replace_and_escape(&mut result, "task_name", task_name.unwrap_or_default(), escape);
+        replace_and_escape(&mut result, "hostname", &hostname(), escape);
+        result
+    })
+}
+
+/// Sends a jump of the wall clock, with the `{{ jump }}`, like `1 h forward`, and `{{ hostname }}` placeholders
+pub fn send_clock_change_alert(alert: &Alert, jump: &str) -> Result<()> {
+    let default_body = "The clock of {{ hostname }} jumped {{ jump }}, the next runs of the tasks were computed again";
+    let payload = json!({ "event": "clock_change", "jump": jump, "hostname": hostname() });
+    deliver_alert(alert, "cron-rs Clock Change", default_body, &payload, &|template, escape| {
+        let mut result = template.to_string();
+        replace_and_escape(&mut result, "jump", jump, escape);
+        replace_and_escape(&mut result, "hostname", &hostname(), escape);
+        result
+    })
+}
+
+/// Sends that a task used its monthly runtime budget, with the `{{ task_name }}`, `{{ budget }}`, `{{ used }}`, like
+/// `10 h, 2 m`, and `{{ hostname }}` placeholders
+pub fn send_budget_alert(alert: &Alert, task_name: &str, budget: Duration, used: Duration) -> Result<()> {
+    let default_body = "Task {{ task_name }} used {{ used }} of its monthly runtime budget of {{ budget }}, its next runs \
+                        this month are skipped";
+    let payload = json!({
+        "event": "budget_exhausted",
+        "task_name": task_name,
+        "budget_seconds": budget.as_secs_f64(),
+        "used_seconds": used.as_secs_f64(),
+        "hostname": hostname(),
+    });
+    deliver_alert(alert, "cron-rs Runtime Budget Exhausted", default_body, &payload, &|template, escape| {
+        let mut result = template.to_string();
+        replace_and_escape(&mut result, "task_name", task_name, escape);
+        replace_and_escape(&mut result, "budget", &format_duration(budget), escape);
+        replace_and_escape(&mut result, "used", &format_duration(used), escape);
+        replace_and_escape(&mut result, "hostname", &hostname(), escape);
+        result
+    })
+}
+
+/// Server, port and credentials of each SMTP transport, see [smtp_transport]
+type SmtpKey = (String, u16, Option<(String, String)>);
+
+/// The transports already built, they keep their connections open between the alerts
+static SMTP_TRANSPORTS: Mutex<Vec<(SmtpKey, SmtpTransport)>> = Mutex::new(Vec::new());
+
+/// The transport of the SMTP server, shared by the alerts that use the same server and credentials so a burst of
+/// emails reuses the pool of connections instead of opening one for each
+fn smtp_transport(server: String, port: u16, credentials: Option<(String, String)>) -> Result<SmtpTransport> {
+    let key = (server, port, credentials);
+    let mut transports = SMTP_TRANSPORTS.lock().unwrap();
+    if let Some((_, transport)) = transports.iter().find(|(k, _)| *k == key) {
+        return Ok(transport.clone());
+    }
+
+    let (server, port, credentials) = &key;
+    let mut mailer = if server == "localhost" || *port == 25 {
+        SmtpTransport::builder_dangerous(server).port(*port)
+    } else {
+        SmtpTransport::relay(server)?.port(*port)
+    };
+    if let Some((username, password)) = credentials {
+        mailer = mailer.credentials(Credentials::new(username.clone(), password.clone()));
+    }
+
+    let transport = mailer.build();
+    transports.push((key, transport.clone()));
+    Ok(transport)
+}
+
+/// Sends the alert with the placeholders of its fields replaced by `render`, the defaults are used for the subject and
+/// the body it doesn't set. `payload` is what the plugins get on stdin, with the kind of alert in `event`
+pub(crate) fn deliver_alert(
+    alert: &Alert,
+    default_subject: &str,
+    default_body: &str,
+    payload: &Value,
+    render: &dyn Fn(&str, &EscapeStrategy) -> String,
+) -> Result<()> {
+    // The secrets are read when the alert is sent, not when the config is loaded
+    let alert = resolve_secrets(alert)?;
+    match &alert {
+        Alert::Email {
+            from,
+            to,
+            cc,
+            bcc,
+            reply_to,
+            subject,
+            body,
+            smtp_server,
+            smtp_port,
+            smtp_username,
+            smtp_password,
+            escape,
+        } => {
+            let from = from.clone().unwrap_or_else(|| "cron-rs@localhost".to_string());
+            let body = render(body.as_deref().unwrap_or(default_body), escape);
+            let subject = render(subject.as_deref().unwrap_or(default_subject), escape);
+
+            let mut email = Message::builder().from(from.parse()?).subject(subject);
+            for address in to.iter() {
+                email = email.to(address.parse()?);
+            }
+            for address in cc.iter().flat_map(EmailAddresses::iter) {
+                email = email.cc(address.parse()?);
+            }
+            for address in bcc.iter().flat_map(EmailAddresses::iter) {
+                email = email.bcc(address.parse()?);
+            }
+            if let Some(reply_to) = reply_to {
+                email = email.reply_to(reply_to.parse()?);
+            }
+            let email = email.body(body)?;
+
+            let server = smtp_server.clone().unwrap_or_else(|| "localhost".to_string());
+            let port = smtp_port.unwrap_or(25);
+            let credentials = smtp_username.clone().zip(smtp_password.clone());
+
+            smtp_transport(server, port, credentials)?.send(&email).context("Failed to send email")?;
+            info!("Email sent successfully");
+        }
+        Alert::Cmd { cmd, escape, plugin, timeout } => {
+            let cmd = render(cmd, escape);
+            let timeout = match timeout {
+                Some(timeout) => Some(Schedule::parse_time_duration(timeout).context("Malformed field: timeout")?.0),
+                None if *plugin == Some(true) => Some(DEFAULT_PLUGIN_TIMEOUT),
+                None => None,
+            };
+            run_alert_cmd(&cmd, payload, *plugin == Some(true), timeout)?;
+        }
+        Alert::Webhook {
+            url,
+            method,
+            body,
+            headers,
+            expect_status,
+            expect_body_contains,
+            escape,
+        } => {
+            let body = render(body.as_deref().unwrap_or(default_body), escape);
+
+            let client = Client::new();
+            let mut request = match method.as_deref() {
+                Some("GET") => client.get(url),
+                Some("POST") => client.post(url),
+                Some("PUT") => client.put(url),
+                Some("PATCH") => client.patch(url),
+                Some("DELETE") => client.delete(url),
+                _ => client.post(url),
+            };
+
+            let mut header_map = HeaderMap::new();
+            for (key, value) in headers {
+                header_map.insert(
+                    HeaderName::from_bytes(key.trim().as_bytes())?,
+                    HeaderValue::from_str(value.trim())?,
+                );
+            }
+            request = request.headers(header_map).body(body);
+
+            let response = request.send().context("Failed to send webhook")?;
+            let status = response.status();
+            let text = response.text().unwrap_or_default();
+            check_webhook_response(status.as_u16(), &text, expect_status.as_ref(), expect_body_contains.as_deref())?;
+        }
+        Alert::Plugin { plugin } => send_plugin_alert(plugin, payload)?,
+        // The channels are replaced when the config is read
+        Alert::Channel(name) => bail!("Unknown alert channel '{}'", name),
+    }
+    Ok(())
+}
+
+/// Runs the command of an alert with `CRON_RS_ALERT_EVENT` and, when the alert has them, `CRON_RS_TASK_NAME`,
+/// `CRON_RS_RUN_ID`, `CRON_RS_EXIT_CODE` and `CRON_RS_HOSTNAME` in its env. A plugin also gets `payload` as JSON on
+/// stdin and its exit code tells what happened: 0 is delivered, [PLUGIN_TEMPFAIL] is a temporary failure that is tried
+/// again [PLUGIN_ATTEMPTS] times in total and the rest are failures, explained by the last line of stderr
+fn run_alert_cmd(cmd: &str, payload: &Value, plugin: bool, timeout: Option<Duration>) -> Result<()> {
+    let mut env = vec![("CRON_RS_ALERT_EVENT", payload["event"].as_str().unwrap_or_default().to_string())];
+    for (field, name) in PAYLOAD_ENV {
+        match &payload[field] {
+            Value::String(value) => env.push((name, value.clone())),
+            Value::Number(value) => env.push((name, value.to_string())),
+            _ => {}
+        }
+    }
+    let stdin = plugin.then(|| payload.to_string());
+
+    for attempt in 1..=PLUGIN_ATTEMPTS {
+        let (status, stderr) = run_with_timeout(cmd, &env, stdin.clone(), timeout)?;
+        if status.success() {
+            return Ok(());
+        }
+        if !plugin {
+            bail!("Alert command failed with {}: {}", status, stderr.trim());
+        }
+        let error = stderr.trim().lines().last().unwrap_or_default().to_string();
+        if status.code() != Some(PLUGIN_TEMPFAIL) {
+            bail!("Alert plugin failed with {}: {}", status, error);
+        }
+        if attempt == PLUGIN_ATTEMPTS {
+            bail!("Alert plugin failed temporarily {} times: {}", PLUGIN_ATTEMPTS, error);
+        }
+        info!("Alert plugin failed temporarily, trying again in {}: {}", format_duration(PLUGIN_RETRY_DELAY), error);
+        std::thread::sleep(PLUGIN_RETRY_DELAY);
+    }
+    Ok(())
+}
+
+/// Runs the command with its stdout discarded, killing it and the processes it started if it takes longer than
+/// `timeout`. Returns its status and stderr
+fn run_with_timeout(
+    cmd: &str,
+    env: &[(&str, String)],
+    stdin: Option<String>,
+    timeout: Option<Duration>,
+) -> Result<(ExitStatus, String)> {
+    let mut command = shell_process(None, false, cmd);
+    command.envs(env.iter().map(|(name, value)| (name, value)));
+    command.stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() });
+    command.stdout(Stdio::null()).stderr(Stdio::piped());
+    set_process_group(&mut command);
+    let mut child = command.spawn().context("Failed to run the alert command")?;
+
+    // From other threads, the command may not read all its stdin before exiting or fill the pipe of stderr
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        std::thread::spawn(move || pipe.write_all(input.as_bytes()));
+    }
+    let (sender, stderr) = mpsc::channel();
+    if let Some(mut pipe) = child.stderr.take() {
+        std::thread::spawn(move || {
+            let mut text = String::new();
+            let _ = pipe.read_to_string(&mut text);
+            let _ = sender.send(text);
+        });
+    }
+
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            kill_process_group(&mut child);
+            let _ = child.wait();
+            bail!("Alert command timed out after {}", format_duration(timeout.unwrap_or_default()));
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+
+    // A process started by the command can keep stderr open after it exits, it's killed at the deadline
+    let left = deadline.map_or(ALERT_STDERR_WAIT, |deadline| deadline.saturating_duration_since(Instant::now()));
+    let stderr = match stderr.recv_timeout(left) {
+        Ok(text) => text,
+        Err(_) => {
+            kill_process_group(&mut child);
+            stderr.recv_timeout(ALERT_STDERR_WAIT).unwrap_or_default()
+        }
+    };
+    Ok((status, stderr))
+}
+
+fn kill_process_group(child: &mut Child) {
+    signal_process_group(child.id(), StopSignal::Sigkill);
+    let _ = child.kill();
+}
+
+/// Checks that the response of a webhook means it was delivered, by default any 2xx status
+fn check_webhook_response(status: u16, body: &str, expect_status: Option<&StatusCodes>, expect_body_contains: Option<&str>) -> Result<()> {
+    let delivered = match expect_status {
+        Some(codes) => codes.contains(status),
+        None => (200..300).contains(&status),
+    };
+    if !delivered {
+        bail!("Webhook request failed with status: {}, '{}'", status, body);
+    }
+    if let Some(expected) = expect_body_contains {
+        if !body.contains(expected) {
+            bail!("Webhook response doesn't contain '{}': '{}'", expected, body);
+        }
+    }
+    Ok(())
+}
+
+pub fn template_replace(template: &str, details: &TaskExecutionDetails, escape: &EscapeStrategy) -> String {
+    let mut result = template.to_string();
+
+    replace_and_escape(&mut result, "task_id", &details.task_id.to_string(), escape);
+    replace_and_escape(&mut result, "run_id", &details.run_id, escape);
+    replace_and_escape(&mut result, "pid", &details.pid.to_string(), escape);
+    replace_and_escape(&mut result, "task_name", &details.task_name, escape);
+    replace_and_escape(&mut result, "exit_code", &details.exit_code.to_string(), escape);
+    replace_and_escape(&mut result, "start_time", &details.start_time.to_rfc3339(), escape);
+    replace_and_escape(&mut result, "duration", &format_duration(details.duration), escape);
+    replace_and_escape(
+        &mut result,
+        "end_time",
+        &details
+            .start_time
+            .add(TimeDelta::from_std(details.duration).unwrap_or_default())
+            .to_rfc3339(),
+        escape,
+    );
+    replace_and_escape(&mut result, "error_message", &details.error_message, escape);
+    replace_and_escape(&mut result, "debug_info", &details.debug_info, escape);
+    replace_and_escape(&mut result, "stdout", details.stdout.trim(), escape);
+    replace_and_escape(&mut result, "stderr", details.stderr.trim(), escape);
+    let peak_memory = details.peak_memory.map(format_size).unwrap_or_default();
+    replace_and_escape(&mut result, "peak_memory", &peak_memory, escape);
+    let max_rss = details.usage.map(|usage| format_size(usage.max_rss)).unwrap_or_default();
+    replace_and_escape(&mut result, "max_rss", &max_rss, escape);
+    replace_and_escape(&mut result, "stdout_tail", tail(&details.stdout, OUTPUT_TAIL_LINES), escape);
+    replace_and_escape(&mut result, "stderr_tail", tail(&details.stderr, OUTPUT_TAIL_LINES), escape);
+    let context = &details.context;
+    replace_and_escape(&mut result, "hostname", &context.hostname, escape);
+    replace_and_escape(&mut result, "schedule", &context.schedule, escape);
+    replace_and_escape(&mut result, "timezone", context.timezone.name(), escape);
+    let next_run = context.next_run.map(|time| time.to_rfc3339()).unwrap_or_default();
+    replace_and_escape(&mut result, "next_run", &next_run, escape);
+    replace_and_escape(&mut result, "attempt", &context.attempt.to_string(), escape);
+
+    result
+}
+
+/// Replaces `{{ placeholder }}` and `{{placeholder}}` with the escaped value
+pub(crate) fn replace_and_escape(result: &mut String, placeholder: &str, value: &str, escape: &EscapeStrategy) {
+    let start = "{{";
+    let end = "}}";
+    let with_spaces = format!("{} {} {}", start, placeholder, end);
+    if result.contains(&with_spaces) {
+        let escaped_value = template_escape(value, escape);
+        *result = result.replace(&with_spaces, &escaped_value);
+    }
+
+    let without_spaces = format!("{}{}{}", start, placeholder, end);
+    if result.contains(&without_spaces) {
+        let escaped_value = template_escape(value, escape);
+        *result = result.replace(&without_spaces, &escaped_value);
+    }
+}
+
+/// The last lines of the output, without the trailing newlines
+pub(crate) fn tail(output: &str, lines: usize) -> &str {
+    let output = output.trim_end();
+    match output.rmatch_indices('\n').nth(lines - 1) {
+        Some((index, _)) => &output[index + 1..],
+        None => output,
+    }
+}
+
+fn template_escape(value: &str, strategy: &EscapeStrategy) -> String {
+    match strategy {
+        EscapeStrategy::None => value.trim().to_string(),
+        EscapeStrategy::Json => escape_json_string(value.trim()),
+        EscapeStrategy::Html => escape_html_string(value.trim()),
+        EscapeStrategy::Shell => escape_shell_arg_string(value),
+    }
+}
+
+pub fn escape_json_string(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '"' => result.push_str(r#"\""#),
+            '\\' => result.push_str(r"\\"),
+            '\n' => result.push_str(r"\n"),
+            '\r' => result.push_str(r"\r"),
+            '\t' => result.push_str(r"\t"),
+            '\x08' => result.push_str(r"\b"), // backspace
+            '\x0C' => result.push_str(r"\f"), // form feed
+            c if c.is_control() => {
+                result.push_str(&format!(r"\u{:04x}", c as u32));
+            }
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+pub fn escape_html_string(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            '/' => result.push_str("&#x2F;"),
+            '`' => result.push_str("&#x60;"),
+            '=' => result.push_str("&#x3D;"),
+            c => result.push(c),
+        }
+    }
+
+    result
+}
+
+pub fn escape_shell_arg_string(s: &str) -> String {
+    if s.is_empty() {
+        return "''".to_string();
+    }
+
+    let needs_quoting = s.chars().any(|c| {
+        matches!(
+            c,
+            ' ' | '\t'
+                | '\n'
+                | '\r'
+                | '\''
+                | '"'
+                | '\\'
+                | '$'
+                | '`'
+                | '!'
+                | '*'
+                | '?'
+                | '['
+                | ']'
+                | '{'
+                | '}'
+                | '('
+                | ')'
+                | '<'
+                | '>'
+                | '|'
+                | '&'
+                | ';'
+                | '#'
+                | '~'
+                | '\x00'..='\x1F' | '\x7F'
+        )
+    }) || s.starts_with('-');
+
+    if !needs_quoting {
+        return s.to_string();
+    }
+
+    let mut result = String::from("'");
+
+    for c in s.chars() {
+        match c {
+            '\'' => result.push_str(r"'\''"),
+            '\0' => {
+                // ignore null bytes
+            }
+            c => result.push(c),
+        }
+    }
+
+    result.push('\'');
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_template_replace() {
+        let details = TaskExecutionDetails {
+            task_name: "backup".to_string(),
+            task_id: 1,
+            run_id: "20250101-120000-3fa2c19b".to_string(),
+            pid: 42,
+            exit_code: 2,
+            start_time: Utc::now(),
+            duration: Duration::from_secs(1),
+            error_message: String::new(),
+            debug_info: String::new(),
+            stdout: (1..=30).map(|i| format!("line {}\n", i)).collect(),
+            stderr: "error\n".to_string(),
+            peak_memory: None,
+            usage: None,
+            context: ExecutionContext {
+                hostname: "db-1".to_string(),
+                schedule: "Every 1 h".to_string(),
+                timezone: chrono_tz::Europe::Madrid,
+                next_run: Some(chrono_tz::Europe::Madrid.with_ymd_and_hms(2025, 1, 1, 13, 0, 0).unwrap()),
+                attempt: 1,
+                prev_failures: 0,
+            },
+        };
+
+        let text = template_replace("{{ task_name }} ({{run_id}}) exited with {{ exit_code }}", &details, &EscapeStrategy::None);
+        assert_eq!(text, "backup (20250101-120000-3fa2c19b) exited with 2");
+
+        let text = template_replace(
+            "{{ task_name }} on {{ hostname }}, {{ schedule }} ({{ timezone }}), next at {{ next_run }}, attempt {{ attempt }}",
+            &details,
+            &EscapeStrategy::None,
+        );
+        assert_eq!(text, "backup on db-1, Every 1 h (Europe/Madrid), next at 2025-01-01T13:00:00+01:00, attempt 1");
+
+        let text = template_replace("{{ stdout_tail }}", &details, &EscapeStrategy::None);
+        assert_eq!(text.lines().count(), OUTPUT_TAIL_LINES);
+        assert!(text.starts_with("line 11\n") && text.ends_with("line 30"), "{}", text);
+        assert_eq!(template_replace("{{ stderr_tail }}", &details, &EscapeStrategy::None), "error");
+
+        // Empty when the usage is not known
+        assert_eq!(template_replace("[{{ max_rss }}]", &details, &EscapeStrategy::None), "[]");
+        let details = TaskExecutionDetails { usage: Some(ResourceUsage { max_rss: 4 << 20, ..Default::default() }), ..details };
+        assert_eq!(template_replace("{{ max_rss }}", &details, &EscapeStrategy::None), format_size(4 << 20));
+    }
+
+    #[test]
+    fn test_configured_alerts() {
+        let yaml = "
+alerts:
+  on_failure: [{ type: cmd, cmd: 'notify' }]
+defaults:
+  on_success: [{ type: cmd, cmd: 'notify-success' }]
+tasks:
+  - { name: a, cmd: 'true', every: 1 hour }
+  - { name: b, cmd: 'true', every: 1 hour, on_failure: [{ type: cmd, cmd: 'page' }] }
+";
+        let config = crate::config::parse_config_file(&serde_yml::from_str(yaml).unwrap()).unwrap();
+        let names: Vec<String> = configured_alerts(&config).into_iter().map(|alert| alert.name).collect();
+        // The alert of the defaults is only sent once
+        assert_eq!(names, ["alerts.on_failure[0]", "tasks.a.on_success[0]", "tasks.b.on_failure[0]"]);
+    }
+
+    #[test]
+    fn test_escalation() {
+        let yaml = "
+escalation:
+  - alert: { type: cmd, cmd: chat }
+  - alert: { type: cmd, cmd: email }
+  - alert: { type: cmd, cmd: pager }
+    after_failures: 3
+";
+        let alerts: AlertConfig = serde_yml::from_str(yaml).unwrap();
+        assert_eq!(alerts.escalation_levels(), [1, 1, 3]);
+        assert!(alerts.check_escalation().is_ok());
+
+        // The alerts that are tried, `down` fails to send
+        let escalate = |failures: u32, down: &[&str]| {
+            let mut sent = vec![];
+            let delivered = alerts.escalate(failures, |alert| {
+                let Alert::Cmd { cmd, .. } = alert else { unreachable!() };
+                sent.push(cmd.clone());
+                if down.contains(&cmd.as_str()) { bail!("{} is down", cmd) } else { Ok(()) }
+            });
+            (sent, delivered)
+        };
+        assert_eq!(escalate(1, &[]), (vec!["chat".to_string()], true));
+        assert_eq!(escalate(2, &["chat"]), (vec!["chat".to_string(), "email".to_string()], true));
+        assert_eq!(escalate(5, &[]), (vec!["pager".to_string()], true));
+        assert!(!escalate(1, &["chat", "email", "pager"]).1);
+
+        let alerts: AlertConfig = serde_yml::from_str("escalation: [{ alert: a, after_failures: 3 }, { alert: b, after_failures: 2 }]").unwrap();
+        assert!(alerts.check_escalation().is_err());
+        assert!(alerts.escalate(1, |_| unreachable!()));
+    }
+
+    #[test]
+    fn test_alert_target() {
+        assert_eq!(Alert::email("ops@example.com").target(), "ops@example.com");
+        let cmd = Alert::cmd("notify-send --urgency critical 'failed'");
+        assert_eq!(cmd.target(), "notify-send");
+        let webhook: Alert = serde_yml::from_str("{ type: webhook, url: 'https://hooks.slack.com/services/T000/B000/XXXX' }").unwrap();
+        assert_eq!(webhook.target(), "hooks.slack.com");
+    }
+
+    #[test]
+    fn test_webhook_response() {
+        assert!(check_webhook_response(204, "", None, None).is_ok());
+        assert!(check_webhook_response(500, "down", None, None).is_err());
+
+        let Alert::Webhook { expect_status, expect_body_contains, .. } =
+            serde_yml::from_str("{ type: webhook, url: 'https://slack.com/api/chat.postMessage', expect_status: [200, 201], expect_body_contains: '\"ok\":true' }").unwrap()
+        else {
+            unreachable!()
+        };
+        let check = |status: u16, body: &str| check_webhook_response(status, body, expect_status.as_ref(), expect_body_contains.as_deref());
+        assert!(check(200, r#"{"ok":true}"#).is_ok());
+        // Chat APIs answer the errors with a 200
+        let error = check(200, r#"{"ok":false,"error":"invalid_auth"}"#).unwrap_err();
+        assert_eq!(error.to_string(), r#"Webhook response doesn't contain '"ok":true': '{"ok":false,"error":"invalid_auth"}'"#);
+        assert!(check(202, r#"{"ok":true}"#).is_err());
+
+        let Alert::Webhook { expect_status: Some(status), .. } = serde_yml::from_str("{ type: webhook, url: x, expect_status: 202 }").unwrap() else {
+            unreachable!()
+        };
+        assert!(status.contains(202) && !status.contains(200));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_alert_plugin() {
+        let dir = std::env::temp_dir().join(format!("cron-rs-test-plugin-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let output = dir.join("payload.json");
+        let details = TaskExecutionDetails::test(None);
+        let plugin = |cmd: &str| {
+            serde_yml::from_str::<Alert>(&format!("{{ type: cmd, plugin: true, timeout: 1 second, cmd: '{}' }}", cmd)).unwrap()
+        };
+
+        // The payload on stdin and the env vars
+        let cmd = format!("cat > {} && test \"$CRON_RS_TASK_NAME $CRON_RS_ALERT_EVENT\" = \"test-alert task\"", output.display());
+        send_alert(&plugin(&cmd), &details).unwrap();
+        let payload: Value = serde_json::from_str(&std::fs::read_to_string(&output).unwrap()).unwrap();
+        assert_eq!(payload["run_id"], details.run_id);
+        assert_eq!(payload["exit_code"], 1);
+
+        // A temporary failure is tried again, the rest fail with the last line of stderr
+        let marker = dir.join("failed-once");
+        let cmd = format!("test -f {0} || {{ touch {0}; exit 75; }}", marker.display());
+        send_alert(&plugin(&cmd), &details).unwrap();
+        let error = send_alert(&plugin("echo starting >&2; echo invalid token >&2; exit 1"), &details).unwrap_err();
+        assert_eq!(error.to_string(), "Alert plugin failed with exit status: 1: invalid token");
+        let error = send_alert(&plugin("sleep 5"), &details).unwrap_err();
+        assert_eq!(error.to_string(), "Alert command timed out after 1 s");
+
+        // The processes it started are killed with it, and the ones that keep stderr open don't block the alert
+        let pid_file = dir.join("pid");
+        let cmd = format!("sleep 30 & echo $! > {}; wait", pid_file.display());
+        assert!(send_alert(&plugin(&cmd), &details).is_err());
+        let pid = std::fs::read_to_string(&pid_file).unwrap();
+        let stopped = || {
+            let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid.trim())).unwrap_or_default();
+            stat.is_empty() || stat.contains(") Z ")
+        };
+        let start = Instant::now();
+        while !stopped() && start.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert!(stopped());
+        let start = Instant::now();
+        send_alert(&plugin("sleep 30 & exit 0"), &details).unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5), "{:?}", start.elapsed());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
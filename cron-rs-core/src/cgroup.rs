@@ -0,0 +1,86 @@
+use crate::config::limits::CgroupLimits;
+use anyhow::{bail, Context, Result};
+use log::{debug, warn};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Parent of the cgroups of the executions when `cgroup_root` is not set
+pub const DEFAULT_CGROUP_ROOT: &str = "/sys/fs/cgroup/cron-rs";
+
+/// Period of `cpu.max` in microseconds, the quota is a part of it
+const CPU_PERIOD: u64 = 100_000;
+
+/// Transient cgroup v2 of an execution, it must be removed with [ExecutionCgroup::remove] once the task ends
+#[derive(Debug)]
+pub struct ExecutionCgroup {
+    path: PathBuf,
+}
+
+impl ExecutionCgroup {
+    /// Creates `{root}/{name}` with the limits. The root can't have processes of its own, cgroup v2 only allows
+    /// processes in the leaves once the controllers are enabled
+    pub fn create(root: &Path, name: &str, limits: &CgroupLimits) -> Result<Self> {
+        fs::create_dir_all(root).with_context(|| format!("Failed to create the cgroup {}", root.display()))?;
+        if !root.join("cgroup.controllers").exists() {
+            bail!("{} is not a cgroup v2", root.display());
+        }
+
+        // The memory controller is always enabled to record the peak usage
+        let controllers = if limits.cpu_max.is_some() { "+memory +cpu" } else { "+memory" };
+        fs::write(root.join("cgroup.subtree_control"), controllers)
+            .with_context(|| format!("Failed to enable the controllers in {}", root.display()))?;
+
+        let path = root.join(name);
+        fs::create_dir(&path).with_context(|| format!("Failed to create the cgroup {}", path.display()))?;
+        let cgroup = Self { path };
+
+        let mut files = vec![];
+        if let Some(memory_max) = limits.memory_max {
+            files.push(("memory.max", memory_max.to_string()));
+        }
+        if let Some(cpu_max) = limits.cpu_max {
+            files.push(("cpu.max", format!("{} {}", u64::from(cpu_max) * CPU_PERIOD / 100, CPU_PERIOD)));
+        }
+        for (file, value) in files {
+            if let Err(e) = fs::write(cgroup.path.join(file), &value) {
+                cgroup.remove();
+                return Err(e).with_context(|| format!("Failed to set {} to '{}'", file, value));
+            }
+        }
+
+        Ok(cgroup)
+    }
+
+    /// Moves a process into the cgroup, the processes it starts afterwards are also in it
+    pub fn add_process(&self, pid: u32) -> Result<()> {
+        fs::write(self.path.join("cgroup.procs"), pid.to_string())
+            .with_context(|| format!("Failed to move process {} to the cgroup {}", pid, self.path.display()))
+    }
+
+    /// Max memory used by the processes of the cgroup, in bytes. Needs Linux 5.19
+    pub fn peak_memory(&self) -> Option<u64> {
+        fs::read_to_string(self.path.join("memory.peak")).ok()?.trim().parse().ok()
+    }
+
+    /// Kills the processes left in the cgroup and deletes it
+    pub fn remove(&self) {
+        if !self.path.exists() {
+            return;
+        }
+        // Needs Linux 5.14, without it the cgroup can't be removed until the processes exit
+        let _ = fs::write(self.path.join("cgroup.kill"), "1");
+
+        // The processes take a moment to leave the cgroup after they are killed
+        for _ in 0..10 {
+            match fs::remove_dir(&self.path) {
+                Ok(_) => {
+                    debug!("Removed the cgroup {}", self.path.display());
+                    return;
+                }
+                Err(_) => std::thread::sleep(Duration::from_millis(50)),
+            }
+        }
+        warn!("Failed to remove the cgroup {}, some processes are still running", self.path.display());
+    }
+}
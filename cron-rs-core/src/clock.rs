@@ -0,0 +1,81 @@
+use crate::utils::format_duration;
+use chrono::{DateTime, TimeDelta, Utc};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// How often the wall clock is compared with the monotonic clock
+pub const CLOCK_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Smaller differences are the slow corrections of NTP and the delays of the checks
+const CLOCK_JUMP_THRESHOLD: Duration = Duration::from_secs(5);
+
+/// Wakes up the sleeps toward a wall clock time, see [clock_changed]
+static CLOCK_CHANGED: Notify = Notify::const_new();
+
+/// Detects the jumps of the wall clock, like an NTP step, a manual change or a suspend. The monotonic clock doesn't
+/// follow them, so the time that passed on both clocks differs
+#[derive(Debug)]
+pub struct ClockWatch {
+    instant: Instant,
+    wall: DateTime<Utc>,
+}
+
+impl Default for ClockWatch {
+    fn default() -> Self {
+        Self { instant: Instant::now(), wall: Utc::now() }
+    }
+}
+
+impl ClockWatch {
+    /// How far the wall clock jumped since the last check, positive if it went forward
+    pub fn check(&mut self) -> Option<TimeDelta> {
+        self.check_at(Instant::now(), Utc::now())
+    }
+
+    fn check_at(&mut self, instant: Instant, wall: DateTime<Utc>) -> Option<TimeDelta> {
+        let elapsed = TimeDelta::from_std(instant.duration_since(self.instant)).unwrap_or_default();
+        let jump = (wall - self.wall) - elapsed;
+        self.instant = instant;
+        self.wall = wall;
+        (jump.abs() > TimeDelta::from_std(CLOCK_JUMP_THRESHOLD).unwrap_or_default()).then_some(jump)
+    }
+}
+
+/// Wakes up the ones waiting on [clock_changed], their targets must be computed again
+pub fn notify_clock_change() {
+    CLOCK_CHANGED.notify_waiters();
+}
+
+/// Resolves at the next jump of the wall clock. The sleeps only follow the monotonic clock, a sleep toward a wall clock
+/// time waits for this too
+pub async fn clock_changed() {
+    CLOCK_CHANGED.notified().await
+}
+
+/// `1 h forward` or `5 m back`
+pub fn describe_jump(jump: TimeDelta) -> String {
+    let direction = if jump < TimeDelta::zero() { "back" } else { "forward" };
+    format!("{} {}", format_duration(Duration::from_secs(jump.abs().num_seconds() as u64)), direction)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock_jumps() {
+        let start = Instant::now();
+        let wall = Utc::now();
+        let mut watch = ClockWatch { instant: start, wall };
+
+        // Both clocks went the same way, a small difference is not a jump
+        assert_eq!(watch.check_at(start + Duration::from_secs(60), wall + TimeDelta::seconds(61)), None);
+        // A suspend, the monotonic clock didn't count the hour
+        let jump = watch.check_at(start + Duration::from_secs(61), wall + TimeDelta::seconds(3662)).unwrap();
+        assert_eq!(jump, TimeDelta::seconds(3600));
+        assert_eq!(describe_jump(jump), "1 h forward");
+        // Set back by hand
+        let jump = watch.check_at(start + Duration::from_secs(62), wall + TimeDelta::seconds(3063)).unwrap();
+        assert_eq!(describe_jump(jump), "10 m back");
+    }
+}
@@ -0,0 +1,118 @@
+use crate::utils::{hostname, new_run_id};
+use anyhow::{bail, Context, Result};
+use chrono::Utc;
+use log::warn;
+use redis::aio::MultiplexedConnection;
+use redis::{AsyncConnectionConfig, Script};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// The key expires after this time if the node that holds it stops renewing it, like when it crashes
+const LOCK_TTL: Duration = Duration::from_secs(30);
+
+/// The lock is renewed several times before it expires, a slow renewal doesn't lose it
+const RENEW_INTERVAL: Duration = Duration::from_secs(10);
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Extends the lock only if it's still held by this node
+const RENEW_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("PEXPIRE", KEYS[1], ARGV[2])
+end
+return 0
+"#;
+
+/// Deletes the lock only if it's still held by this node, an expired lock may be held by another one already
+const RELEASE_SCRIPT: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
+/// Lock of a task in redis, held by one node of the cluster while it runs the task. It's renewed in the background
+/// until it's released
+pub struct ClusterLock {
+    key: String,
+    /// Identifies the node and the execution that holds the lock, the value of the key
+    owner: String,
+    connection: MultiplexedConnection,
+    renewal: JoinHandle<()>,
+}
+
+impl ClusterLock {
+    /// Takes the lock of the task in the redis server of `url`. The error says why it can't run, like when another
+    /// node holds the lock
+    pub async fn acquire(url: &str, task_name: &str) -> Result<Self> {
+        let client = redis::Client::open(url)?;
+        let config = AsyncConnectionConfig::new().set_connection_timeout(TIMEOUT).set_response_timeout(TIMEOUT);
+        let mut connection = client
+            .get_multiplexed_async_connection_with_config(&config)
+            .await
+            .context("failed to connect to the cluster_lock server")?;
+
+        let key = format!("cron-rs:lock:{}", task_name);
+        let owner = format!("{}:{}", hostname(), new_run_id(Utc::now()));
+        let taken: Option<String> = redis::cmd("SET")
+            .arg(&key)
+            .arg(&owner)
+            .arg("NX")
+            .arg("PX")
+            .arg(LOCK_TTL.as_millis() as u64)
+            .query_async(&mut connection)
+            .await
+            .context("failed to take the cluster lock")?;
+        if taken.is_none() {
+            let holder: Option<String> = redis::cmd("GET").arg(&key).query_async(&mut connection).await.unwrap_or_default();
+            match holder {
+                Some(holder) => bail!("the cluster lock is held by {}", holder),
+                None => bail!("the cluster lock is held by another node"),
+            }
+        }
+
+        let renewal = tokio::spawn(renew(connection.clone(), key.clone(), owner.clone()));
+        Ok(Self { key, owner, connection, renewal })
+    }
+
+    /// Gives the lock back, so the next run of any node can take it without waiting for it to expire
+    pub async fn release(&self) {
+        self.renewal.abort();
+        let result: redis::RedisResult<i64> =
+            Script::new(RELEASE_SCRIPT).key(&self.key).arg(&self.owner).invoke_async(&mut self.connection.clone()).await;
+        if let Err(e) = result {
+            warn!("Failed to release the cluster lock {}, it expires in {} s: {}", self.key, LOCK_TTL.as_secs(), e);
+        }
+    }
+}
+
+impl std::fmt::Debug for ClusterLock {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClusterLock").field("key", &self.key).field("owner", &self.owner).finish()
+    }
+}
+
+impl Drop for ClusterLock {
+    fn drop(&mut self) {
+        // Without a release, the lock expires once it's not renewed
+        self.renewal.abort();
+    }
+}
+
+async fn renew(mut connection: MultiplexedConnection, key: String, owner: String) {
+    let script = Script::new(RENEW_SCRIPT);
+    loop {
+        tokio::time::sleep(RENEW_INTERVAL).await;
+        let result: redis::RedisResult<i64> =
+            script.key(&key).arg(&owner).arg(LOCK_TTL.as_millis() as u64).invoke_async(&mut connection).await;
+        match result {
+            Ok(0) => {
+                warn!("The cluster lock {} expired while the task was running, other nodes may run it", key);
+                return;
+            }
+            Ok(_) => {}
+            // It's retried while the lock has not expired
+            Err(e) => warn!("Failed to renew the cluster lock {}: {}", key, e),
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use crate::config::conditions::TaskConditions;
+use crate::plugins::check_plugin_condition;
+use crate::utils::format_size;
+use log::warn;
+use std::fs;
+use std::path::Path;
+use sysinfo::Disks;
+
+/// Power supplies of the host on Linux
+const POWER_SUPPLY_DIR: &str = "/sys/class/power_supply";
+
+/// State of the power supply of the host, unknown values are None
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PowerStatus {
+    pub on_ac_power: Option<bool>,
+    /// Average charge of the batteries of the host
+    pub battery_percent: Option<u8>,
+}
+
+/// Checks the conditions of the task, returns why it can't run now, used as the reason of the skipped run. The plugins
+/// may block, call it outside of the async runtime
+pub fn unmet_condition(conditions: &TaskConditions, task_name: &str) -> Option<String> {
+    if conditions.needs_power_status() {
+        if let Some(reason) = check_power(conditions, read_power_status()) {
+            return Some(reason);
+        }
+    }
+    check_paths(conditions).or_else(|| check_plugin(conditions, task_name))
+}
+
+/// The `plugin` of `only_if`, a plugin that fails is an unmet condition
+fn check_plugin(conditions: &TaskConditions, task_name: &str) -> Option<String> {
+    let plugin = conditions.plugin.as_ref()?;
+    match check_plugin_condition(plugin, task_name) {
+        Ok(reason) => reason,
+        Err(e) => {
+            warn!("Task '{}': {:#}", task_name, e);
+            Some(format!("plugin '{}' failed", plugin))
+        }
+    }
+}
+
+/// Conditions of `only_if`
+fn check_paths(conditions: &TaskConditions) -> Option<String> {
+    if let Some(path) = conditions.path_exists.as_ref().filter(|path| !path.exists()) {
+        return Some(format!("{} doesn't exist", path.display()));
+    }
+
+    if let Some((path, than)) = &conditions.path_newer_than {
+        let Ok(modified) = fs::metadata(path).and_then(|metadata| metadata.modified()) else {
+            return Some(format!("{} doesn't exist", path.display()));
+        };
+        // Like make, a missing target is always older
+        if fs::metadata(than).and_then(|metadata| metadata.modified()).is_ok_and(|than_modified| modified <= than_modified) {
+            return Some(format!("{} is not newer than {}", path.display(), than.display()));
+        }
+    }
+
+    if let Some((path, min_free)) = &conditions.disk_free_above {
+        match available_space(path) {
+            Some(free) if free <= *min_free => {
+                return Some(format!("{} free in {}, not above {}", format_size(free), path.display(), format_size(*min_free)));
+            }
+            Some(_) => {}
+            None => warn!("Unable to find the disk of {}, the disk_free_above condition is not checked", path.display()),
+        }
+    }
+    None
+}
+
+/// Available space in the disk mounted at the longest prefix of the path
+fn available_space(path: &Path) -> Option<u64> {
+    let path = fs::canonicalize(path).ok()?;
+    let disks = Disks::new_with_refreshed_list();
+    disks
+        .list()
+        .iter()
+        .filter(|disk| path.starts_with(disk.mount_point()))
+        .max_by_key(|disk| disk.mount_point().as_os_str().len())
+        .map(|disk| disk.available_space())
+}
+
+fn check_power(conditions: &TaskConditions, power: PowerStatus) -> Option<String> {
+    // Without a reading the conditions pass, like on a host without a battery
+    match power.on_ac_power {
+        Some(false) if conditions.only_on_ac_power => Some("not on AC power".to_string()),
+        Some(false) => match (conditions.min_battery_percent, power.battery_percent) {
+            (Some(min), Some(percent)) if percent < min => Some(format!("battery at {}%, below {}%", percent, min)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Reads the power supply from sysfs, the status is unknown on other systems
+pub fn read_power_status() -> PowerStatus {
+    if cfg!(target_os = "linux") {
+        read_power_supplies(Path::new(POWER_SUPPLY_DIR))
+    } else {
+        PowerStatus::default()
+    }
+}
+
+/// Reads the supplies of a directory like /sys/class/power_supply. The host is on AC power if a mains or USB supply is
+/// online, or if it only has batteries and none of them is discharging
+fn read_power_supplies(dir: &Path) -> PowerStatus {
+    let Ok(entries) = fs::read_dir(dir) else { return PowerStatus::default() };
+    let read = |path: &Path, name: &str| fs::read_to_string(path.join(name)).map(|value| value.trim().to_string()).ok();
+
+    let mut external_supplies = false;
+    let mut online = false;
+    let mut discharging = false;
+    let mut capacities = vec![];
+    for entry in entries.flatten() {
+        let path = entry.path();
+        // Batteries of peripherals like mice, they don't power the host
+        if read(&path, "scope").as_deref() == Some("Device") {
+            continue;
+        }
+        match read(&path, "type").as_deref() {
+            Some("Battery") => {
+                discharging |= read(&path, "status").as_deref() == Some("Discharging");
+                capacities.extend(read(&path, "capacity").and_then(|capacity| capacity.parse::<u32>().ok()));
+            }
+            Some(_) => {
+                external_supplies = true;
+                online |= read(&path, "online").as_deref() == Some("1");
+            }
+            None => {}
+        }
+    }
+
+    let on_ac_power = if online {
+        Some(true)
+    } else if external_supplies {
+        Some(false)
+    } else if !capacities.is_empty() {
+        Some(!discharging)
+    } else {
+        None
+    };
+    let battery_percent = (!capacities.is_empty())
+        .then(|| (capacities.iter().sum::<u32>() / capacities.len() as u32).min(100) as u8);
+
+    PowerStatus { on_ac_power, battery_percent }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supply(dir: &Path, name: &str, values: &[(&str, &str)]) {
+        let path = dir.join(name);
+        fs::create_dir_all(&path).unwrap();
+        for (file, value) in values {
+            fs::write(path.join(file), format!("{}\n", value)).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_read_power_supplies() {
+        let dir = std::env::temp_dir().join(format!("cron-rs-test-power-{}", std::process::id()));
+        assert_eq!(read_power_supplies(&dir), PowerStatus::default());
+
+        supply(&dir, "BAT0", &[("type", "Battery"), ("status", "Discharging"), ("capacity", "40")]);
+        supply(&dir, "BAT1", &[("type", "Battery"), ("status", "Discharging"), ("capacity", "20")]);
+        supply(&dir, "hidpp_battery_0", &[("type", "Battery"), ("scope", "Device"), ("capacity", "5")]);
+        assert_eq!(read_power_supplies(&dir), PowerStatus { on_ac_power: Some(false), battery_percent: Some(30) });
+
+        supply(&dir, "AC", &[("type", "Mains"), ("online", "0")]);
+        assert_eq!(read_power_supplies(&dir).on_ac_power, Some(false));
+        supply(&dir, "AC", &[("type", "Mains"), ("online", "1")]);
+        assert_eq!(read_power_supplies(&dir).on_ac_power, Some(true));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_check_power() {
+        let conditions = TaskConditions { min_battery_percent: Some(25), ..Default::default() };
+        let on_battery = |percent| PowerStatus { on_ac_power: Some(false), battery_percent: Some(percent) };
+
+        assert_eq!(check_power(&conditions, on_battery(30)), None);
+        assert_eq!(check_power(&conditions, on_battery(20)), Some("battery at 20%, below 25%".to_string()));
+        // The battery is not drained while it charges
+        assert_eq!(check_power(&conditions, PowerStatus { on_ac_power: Some(true), battery_percent: Some(20) }), None);
+        assert_eq!(check_power(&conditions, PowerStatus::default()), None);
+
+        let conditions = TaskConditions { only_on_ac_power: true, ..Default::default() };
+        assert_eq!(check_power(&conditions, on_battery(100)), Some("not on AC power".to_string()));
+        assert_eq!(check_power(&conditions, PowerStatus::default()), None);
+    }
+
+    #[test]
+    fn test_check_paths() {
+        let dir = std::env::temp_dir().join(format!("cron-rs-test-only-if-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let input = dir.join("input.csv");
+        let report = dir.join("report.pdf");
+
+        let conditions = TaskConditions { path_exists: Some(input.clone()), ..Default::default() };
+        assert_eq!(check_paths(&conditions), Some(format!("{} doesn't exist", input.display())));
+        fs::write(&input, "1,2").unwrap();
+        assert_eq!(check_paths(&conditions), None);
+
+        // The report doesn't exist yet, it must be built
+        let conditions = TaskConditions { path_newer_than: Some((input.clone(), report.clone())), ..Default::default() };
+        assert_eq!(check_paths(&conditions), None);
+        fs::write(&report, "").unwrap();
+        let input_file = fs::File::options().write(true).open(&input).unwrap();
+        input_file.set_modified(std::time::SystemTime::now() - std::time::Duration::from_secs(60)).unwrap();
+        assert!(check_paths(&conditions).is_some_and(|reason| reason.contains("is not newer than")));
+        input_file.set_modified(std::time::SystemTime::now() + std::time::Duration::from_secs(60)).unwrap();
+        assert_eq!(check_paths(&conditions), None);
+
+        let conditions = TaskConditions { disk_free_above: Some((dir.clone(), u64::MAX)), ..Default::default() };
+        assert!(check_paths(&conditions).is_some_and(|reason| reason.contains("not above")));
+        let conditions = TaskConditions { disk_free_above: Some((dir.clone(), 0)), ..Default::default() };
+        assert_eq!(check_paths(&conditions), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
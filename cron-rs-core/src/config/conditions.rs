@@ -0,0 +1,91 @@
+use super::file::TaskDefinition;
+use super::retention::parse_size;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use std::path::{Path, PathBuf};
+
+/// Conditions on files checked before each run, as written in the config file
+#[skip_serializing_none]
+#[derive(Debug, Clone, Deserialize, Serialize, Default, PartialEq)]
+pub struct OnlyIfConfig {
+    /// The file or directory must exist
+    pub path_exists: Option<String>,
+    /// `path` must have been modified after `than`, or `than` must not exist
+    pub path_newer_than: Option<PathNewerThanConfig>,
+    /// The disk of `path` must have more than `size` available
+    pub disk_free_above: Option<DiskFreeConfig>,
+    /// Name of a plugin of `plugins` whose `check_condition` must pass
+    pub plugin: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct PathNewerThanConfig {
+    pub path: String,
+    pub than: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct DiskFreeConfig {
+    pub path: String,
+    /// e.g. `10 GB`
+    pub size: String,
+}
+
+/// Conditions on the state of the host, checked right before each execution. The run is skipped when one isn't met
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TaskConditions {
+    /// Only run while the host is on AC power, hosts without a battery always are
+    pub only_on_ac_power: bool,
+    /// Only run on battery power if the charge is at least this percentage
+    pub min_battery_percent: Option<u8>,
+    /// Relative paths are resolved from the working directory of the task
+    pub path_exists: Option<PathBuf>,
+    /// The first path must be newer than the second one
+    pub path_newer_than: Option<(PathBuf, PathBuf)>,
+    /// Path in the disk and min available bytes
+    pub disk_free_above: Option<(PathBuf, u64)>,
+    /// Name of the plugin that checks the condition
+    pub plugin: Option<String>,
+}
+
+impl TaskConditions {
+    pub fn parse(config: &TaskDefinition) -> Result<Self> {
+        if let Some(percent) = config.min_battery_percent.filter(|percent| *percent > 100) {
+            bail!("Malformed field: min_battery_percent, {} is not a percentage", percent);
+        }
+
+        let only_if = config.only_if.clone().unwrap_or_default();
+        let dir = config.working_directory.as_deref().map(Path::new);
+        let resolve = |path: &str| match dir {
+            Some(dir) => dir.join(path),
+            None => PathBuf::from(path),
+        };
+        let disk_free_above = match &only_if.disk_free_above {
+            Some(disk) => {
+                let size = parse_size(&disk.size).context("Malformed field: only_if.disk_free_above.size")?;
+                Some((resolve(&disk.path), size))
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            only_on_ac_power: config.only_on_ac_power.unwrap_or(false),
+            min_battery_percent: config.min_battery_percent,
+            path_exists: only_if.path_exists.as_deref().map(resolve),
+            path_newer_than: only_if.path_newer_than.as_ref().map(|newer| (resolve(&newer.path), resolve(&newer.than))),
+            disk_free_above,
+            plugin: only_if.plugin,
+        })
+    }
+
+    /// Any of the conditions of `only_if`
+    pub fn has_only_if(&self) -> bool {
+        self.path_exists.is_some() || self.path_newer_than.is_some() || self.disk_free_above.is_some() || self.plugin.is_some()
+    }
+
+    /// The power supply is only read for the tasks that depend on it
+    pub fn needs_power_status(&self) -> bool {
+        self.only_on_ac_power || self.min_battery_percent.is_some()
+    }
+}
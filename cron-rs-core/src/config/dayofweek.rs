@@ -0,0 +1,157 @@
+use super::TimePatternField;
+use anyhow::{Result, anyhow, bail};
+use chrono::Weekday;
+use nom::{branch::alt, bytes::complete::tag_no_case, combinator::value};
+use serde::{Deserialize, Serialize};
+
+/// How the numbers of the `day_of_week` fields are read, the names are the same days with both
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub enum DayOfWeekNumbering {
+    /// Like cron, 0 is Sunday, 1 is Monday, ... 6 is Saturday and 7 is also Sunday
+    #[default]
+    #[serde(rename = "sunday_0")]
+    Sunday0,
+    /// Like ISO 8601, 1 is Monday, ... 7 is Sunday, so `Sun` is the last day in ranges like `Fri..Sun`
+    #[serde(rename = "monday_1")]
+    Monday1,
+}
+
+impl DayOfWeekNumbering {
+    /// Converts a field read with this numbering into the one used to match dates, the numbering of cron
+    pub fn normalize(self, field: &TimePatternField) -> Result<TimePatternField> {
+        if self == Self::Sunday0 {
+            return Ok(field.clone());
+        }
+        // `Sun` is parsed as 0, here it comes after Saturday
+        let sunday_last = |v: u32| if v == 0 { 7 } else { v };
+        Ok(match field {
+            TimePatternField::Any => TimePatternField::Any,
+            TimePatternField::Value(v) => TimePatternField::Value(sunday_last(*v)),
+            TimePatternField::Range(start, end) => {
+                let (start, end) = (sunday_last(*start), sunday_last(*end));
+                if start > end {
+                    bail!("Range {}..{} is empty, with monday_1 numbering the week ends on Sunday", start, end);
+                }
+                TimePatternField::Range(start, end)
+            }
+            TimePatternField::List(values) => TimePatternField::list(values.iter().copied().map(sunday_last).collect()),
+            // Sunday is 7 when matching the steps
+            TimePatternField::Ratio(..) => TimePatternField::list((1..=7).filter(|v| field.matches_value(*v)).collect()),
+        })
+    }
+}
+
+/// Ranges that end on Sunday, like `Fri..Sun`, include the days until Sunday instead of being empty
+pub fn sunday_ends_ranges(field: TimePatternField) -> TimePatternField {
+    match field {
+        TimePatternField::Range(start, 0) if start > 0 => TimePatternField::Range(start, 7),
+        field => field,
+    }
+}
+
+/// Checks the day of the week of a date, Sunday matches both 0 and 7
+pub fn matches_weekday(field: &TimePatternField, weekday: Weekday) -> bool {
+    let value = DayOfWeek::from(weekday).to_u32();
+    field.matches_value(value) || (value == 0 && field.matches_value(7))
+}
+
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum DayOfWeek {
+    Mon,
+    Tue,
+    Wed,
+    Thu,
+    Fri,
+    Sat,
+    Sun,
+}
+
+impl DayOfWeek {
+    pub fn parse(input: &str) -> nom::IResult<&str, Self> {
+        alt((
+            value(Self::Mon, tag_no_case("mon")),
+            value(Self::Tue, tag_no_case("tue")),
+            value(Self::Wed, tag_no_case("wed")),
+            value(Self::Thu, tag_no_case("thu")),
+            value(Self::Fri, tag_no_case("fri")),
+            value(Self::Sat, tag_no_case("sat")),
+            value(Self::Sun, tag_no_case("sun")),
+        ))(input)
+    }
+
+    pub fn to_u32(self) -> u32 {
+        match self {
+            Self::Sun => 0,
+            Self::Mon => 1,
+            Self::Tue => 2,
+            Self::Wed => 3,
+            Self::Thu => 4,
+            Self::Fri => 5,
+            Self::Sat => 6,
+        }
+    }
+
+    pub fn from_u32(n: u32) -> Self {
+        match n {
+            0 => Self::Sun,
+            1 => Self::Mon,
+            2 => Self::Tue,
+            3 => Self::Wed,
+            4 => Self::Thu,
+            5 => Self::Fri,
+            6 => Self::Sat,
+            7 => Self::Sun,
+            _ => panic!("Invalid day of week: {}", n),
+        }
+    }
+}
+
+impl From<Weekday> for DayOfWeek {
+    fn from(weekday: Weekday) -> Self {
+        match weekday {
+            Weekday::Mon => Self::Mon,
+            Weekday::Tue => Self::Tue,
+            Weekday::Wed => Self::Wed,
+            Weekday::Thu => Self::Thu,
+            Weekday::Fri => Self::Fri,
+            Weekday::Sat => Self::Sat,
+            Weekday::Sun => Self::Sun,
+        }
+    }
+}
+
+impl TryFrom<u32> for DayOfWeek {
+    type Error = anyhow::Error;
+
+    fn try_from(n: u32) -> std::result::Result<Self, Self::Error> {
+        match n {
+            0 => Ok(Self::Sun),
+            1 => Ok(Self::Mon),
+            2 => Ok(Self::Tue),
+            3 => Ok(Self::Wed),
+            4 => Ok(Self::Thu),
+            5 => Ok(Self::Fri),
+            6 => Ok(Self::Sat),
+            7 => Ok(Self::Sun),
+            _ => Err(anyhow!("Invalid day of week: {}", n)),
+        }
+    }
+}
+
+impl TryFrom<&str> for DayOfWeek {
+    type Error = anyhow::Error;
+
+    fn try_from(input: &str) -> std::result::Result<Self, Self::Error> {
+        match input {
+            "mon" => Ok(Self::Mon),
+            "tue" => Ok(Self::Tue),
+            "wed" => Ok(Self::Wed),
+            "thu" => Ok(Self::Thu),
+            "fri" => Ok(Self::Fri),
+            "sat" => Ok(Self::Sat),
+            "sun" => Ok(Self::Sun),
+            _ => Err(anyhow!("Invalid day of week: {}", input))
+        }
+    }
+}
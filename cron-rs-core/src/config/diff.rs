@@ -0,0 +1,243 @@
+use super::file::ConfigFile;
+use super::{parse_config_file, TaskConfig};
+use crate::scheduler::{PendingTask, Scheduler};
+use anyhow::{Context, Result};
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+/// Differences between two config files, the output of `cron-rs diff`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ConfigDiff {
+    /// The tasks and pipelines that were added, removed or changed
+    pub tasks: Vec<TaskDiff>,
+    /// Other top-level options that changed, like `logging` or `alerts`
+    pub settings: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskChange {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskDiff {
+    pub name: String,
+    pub change: TaskChange,
+    /// The options of the task that changed, after applying its template and the defaults
+    pub fields: Vec<FieldDiff>,
+    /// None if the task is disabled, waits for a trigger or doesn't exist in that config
+    pub old_next_run: Option<DateTime<FixedOffset>>,
+    pub new_next_run: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldDiff {
+    pub field: String,
+    pub old: Option<Value>,
+    pub new: Option<Value>,
+}
+
+/// A task or pipeline of a config, with its options as written once resolved
+struct DiffedTask {
+    definition: BTreeMap<String, Value>,
+    next_run: Option<DateTime<FixedOffset>>,
+}
+
+/// Compares the tasks of both configs by name, with their next run from `now`
+pub fn diff_configs(old: &ConfigFile, new: &ConfigFile, now: DateTime<Utc>) -> Result<ConfigDiff> {
+    let old_tasks = diffed_tasks(old, now).context("Invalid old config")?;
+    let new_tasks = diffed_tasks(new, now).context("Invalid new config")?;
+
+    let mut tasks = vec![];
+    for (name, old_task) in &old_tasks {
+        let Some((_, new_task)) = new_tasks.iter().find(|(new_name, _)| new_name == name) else {
+            tasks.push(TaskDiff {
+                name: name.clone(),
+                change: TaskChange::Removed,
+                fields: vec![],
+                old_next_run: old_task.next_run,
+                new_next_run: None,
+            });
+            continue;
+        };
+
+        let mut fields: Vec<&String> = old_task.definition.keys().chain(new_task.definition.keys()).collect();
+        fields.sort();
+        fields.dedup();
+        let fields: Vec<FieldDiff> = fields
+            .into_iter()
+            .filter(|field| old_task.definition.get(*field) != new_task.definition.get(*field))
+            .map(|field| FieldDiff {
+                field: field.clone(),
+                old: old_task.definition.get(field).cloned(),
+                new: new_task.definition.get(field).cloned(),
+            })
+            .collect();
+        if !fields.is_empty() || old_task.next_run != new_task.next_run {
+            tasks.push(TaskDiff {
+                name: name.clone(),
+                change: TaskChange::Changed,
+                fields,
+                old_next_run: old_task.next_run,
+                new_next_run: new_task.next_run,
+            });
+        }
+    }
+    for (name, new_task) in &new_tasks {
+        if !old_tasks.iter().any(|(old_name, _)| old_name == name) {
+            tasks.push(TaskDiff {
+                name: name.clone(),
+                change: TaskChange::Added,
+                fields: vec![],
+                old_next_run: None,
+                new_next_run: new_task.next_run,
+            });
+        }
+    }
+
+    // The defaults and templates are part of the tasks that use them
+    let (old_settings, new_settings) = (settings(old)?, settings(new)?);
+    let mut settings: Vec<String> = old_settings.keys().chain(new_settings.keys()).cloned().collect();
+    settings.sort();
+    settings.dedup();
+    settings.retain(|setting| old_settings.get(setting) != new_settings.get(setting));
+
+    Ok(ConfigDiff { tasks, settings })
+}
+
+/// The tasks and then the pipelines, in the order of the config file
+fn diffed_tasks(file: &ConfigFile, now: DateTime<Utc>) -> Result<Vec<(String, DiffedTask)>> {
+    let config = parse_config_file(file)?;
+    let next_run = |name: &str| config.tasks.iter().find(|task| task.name == name).and_then(|task| next_run(task, now));
+
+    let mut tasks = vec![];
+    for task in file.resolved_tasks()? {
+        let definition = object(serde_json::to_value(&task)?);
+        tasks.push((task.name.clone(), DiffedTask { definition, next_run: next_run(&task.name) }));
+    }
+    for pipeline in &file.pipelines {
+        let definition = object(serde_json::to_value(pipeline)?);
+        tasks.push((pipeline.name.clone(), DiffedTask { definition, next_run: next_run(&pipeline.name) }));
+    }
+    Ok(tasks)
+}
+
+fn next_run(task: &Arc<TaskConfig>, now: DateTime<Utc>) -> Option<DateTime<FixedOffset>> {
+    if !task.enabled {
+        return None;
+    }
+    let pending_task = PendingTask::new(task.clone());
+    Scheduler::get_next_run(&pending_task, now.with_timezone(&task.timezone)).map(|next_run| next_run.fixed_offset())
+}
+
+fn settings(file: &ConfigFile) -> Result<BTreeMap<String, Value>> {
+    let mut settings = object(serde_json::to_value(file)?);
+    for key in ["include", "defaults", "templates", "tasks", "pipelines"] {
+        settings.remove(key);
+    }
+    Ok(settings)
+}
+
+/// The fields of a serialized struct, without the ones that are not set
+fn object(value: Value) -> BTreeMap<String, Value> {
+    match value {
+        Value::Object(fields) => fields.into_iter().filter(|(_, value)| !value.is_null()).collect(),
+        _ => BTreeMap::new(),
+    }
+}
+
+impl ConfigDiff {
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty() && self.settings.is_empty()
+    }
+
+    /// A line for each added or removed task, and the changed options of the other ones, as shown by `cron-rs diff`
+    pub fn text(&self) -> String {
+        if self.is_empty() {
+            return "No changes\n".to_string();
+        }
+        let time = |time: Option<DateTime<FixedOffset>>| {
+            time.map(|time| time.format("%Y-%m-%d %H:%M:%S %:z").to_string()).unwrap_or_else(|| "-".to_string())
+        };
+        let value = |value: &Option<Value>| value.as_ref().map(|value| value.to_string()).unwrap_or_else(|| "-".to_string());
+
+        let mut output = String::new();
+        for task in &self.tasks {
+            match task.change {
+                TaskChange::Added => output.push_str(&format!("+ {}: added, next run {}\n", task.name, time(task.new_next_run))),
+                TaskChange::Removed => output.push_str(&format!("- {}: removed\n", task.name)),
+                TaskChange::Changed => {
+                    output.push_str(&format!("~ {}: changed\n", task.name));
+                    for field in &task.fields {
+                        output.push_str(&format!("    {}: {} -> {}\n", field.field, value(&field.old), value(&field.new)));
+                    }
+                    if task.old_next_run != task.new_next_run {
+                        output.push_str(&format!("    next run: {} -> {}\n", time(task.old_next_run), time(task.new_next_run)));
+                    }
+                }
+            }
+        }
+        if !self.settings.is_empty() {
+            output.push_str(&format!("Other options changed: {}\n", self.settings.join(", ")));
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_diff_configs() {
+        let old = "
+defaults: { time_limit: 1 hour }
+tasks:
+  - { name: backup, cmd: ./backup.sh, when: { hour: 2, minute: 0 }, timezone: UTC }
+  - { name: cleanup, cmd: ./cleanup.sh, every: 1 day }
+  - { name: report, cmd: ./report.sh, every: 1 day, timezone: UTC }
+";
+        let new = "
+defaults: { time_limit: 2 hours }
+control_socket: /run/cron-rs.sock
+tasks:
+  - { name: backup, cmd: ./backup.sh, when: { hour: 3, minute: 0 }, timezone: UTC }
+  - { name: report, cmd: ./report.sh, every: 1 day, timezone: UTC, enabled: false }
+  - { name: upload, cmd: ./upload.sh, after_task: { name: backup } }
+";
+        let old: ConfigFile = serde_yml::from_str(old).unwrap();
+        let new: ConfigFile = serde_yml::from_str(new).unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        let diff = diff_configs(&old, &new, now).unwrap();
+
+        let changes: Vec<(&str, TaskChange)> = diff.tasks.iter().map(|task| (task.name.as_str(), task.change)).collect();
+        assert_eq!(
+            changes,
+            vec![("backup", TaskChange::Changed), ("cleanup", TaskChange::Removed), ("report", TaskChange::Changed), ("upload", TaskChange::Added)]
+        );
+        assert_eq!(diff.settings, vec!["control_socket"]);
+
+        // The defaults are part of the tasks
+        let backup = &diff.tasks[0];
+        let fields: Vec<&str> = backup.fields.iter().map(|field| field.field.as_str()).collect();
+        assert_eq!(fields, vec!["time_limit", "when"]);
+        let next_run = |hour| Some(Utc.with_ymd_and_hms(2025, 1, 2, hour, 0, 0).unwrap().fixed_offset());
+        assert_eq!((backup.old_next_run, backup.new_next_run), (next_run(2), next_run(3)));
+        // A disabled task doesn't run
+        assert!(diff.tasks[2].old_next_run.is_some() && diff.tasks[2].new_next_run.is_none());
+
+        let text = diff.text();
+        assert!(text.contains("~ backup: changed\n    time_limit: \"1 hour\" -> \"2 hours\"\n"), "{}", text);
+        assert!(text.contains("    next run: 2025-01-02 02:00:00 +00:00 -> 2025-01-02 03:00:00 +00:00\n"), "{}", text);
+        assert!(text.contains("- cleanup: removed\n~ report: changed\n    enabled: - -> false\n"), "{}", text);
+        assert!(text.ends_with("+ upload: added, next run -\nOther options changed: control_socket\n"), "{}", text);
+        assert!(diff_configs(&old, &old, now).unwrap().is_empty());
+    }
+}
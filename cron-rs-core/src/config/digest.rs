@@ -0,0 +1,55 @@
+use super::file::ConfigFile;
+use super::Schedule;
+use crate::alerts::Alert;
+use crate::utils::{parse_timezone, system_timezone};
+use anyhow::{bail, Context, Result};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use std::time::Duration;
+
+/// Summary of the executions sent once per interval, as written in the config file
+#[skip_serializing_none]
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct DigestConfig {
+    /// e.g. `1 day` or `1 hour`, the digests are sent at the start of each interval in the timezone
+    pub every: String,
+    /// The `timezone` of the `defaults` by default, or else the one of the system
+    pub timezone: Option<String>,
+    /// Alerts that send the summary, with the `{{ summary }}` placeholder
+    pub alerts: Vec<Alert>,
+    /// Also send the digest of the intervals without executions
+    #[serde(default)]
+    pub send_empty: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Digest {
+    pub interval: Duration,
+    pub timezone: Tz,
+    pub alerts: Vec<Alert>,
+    pub send_empty: bool,
+}
+
+impl Digest {
+    pub fn parse(config: &DigestConfig, file: &ConfigFile) -> Result<Self> {
+        let (interval, _) = Schedule::parse_time_duration(&config.every).context("Malformed field: digest.every")?;
+        if interval.as_secs() == 0 {
+            bail!("Malformed field: digest.every, the interval must be at least 1 second");
+        }
+        let default_timezone = file.defaults.as_ref().and_then(|defaults| defaults.timezone.as_ref());
+        let timezone = match config.timezone.as_ref().or(default_timezone) {
+            Some(timezone) => parse_timezone(timezone).context("Malformed field: digest.timezone")?,
+            None => system_timezone(),
+        };
+        if config.alerts.is_empty() {
+            bail!("Malformed field: digest.alerts, the digest needs at least one alert");
+        }
+        Ok(Self {
+            interval,
+            timezone,
+            alerts: file.resolve_alerts(&config.alerts).context("Malformed field: digest.alerts")?,
+            send_empty: config.send_empty,
+        })
+    }
+}
@@ -0,0 +1,194 @@
+use super::pipeline::Pipeline;
+use super::Schedule;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use std::fmt::{Debug, Formatter};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// Where the command of a task runs, as written in the config file
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutorKind {
+    /// A shell on this machine
+    #[default]
+    Local,
+    /// A new container for each execution, configured with `docker`
+    Docker,
+    /// Another machine, configured with `ssh`
+    Ssh,
+}
+
+/// Container of the tasks with `executor: docker`
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct DockerConfig {
+    pub image: Option<String>,
+    /// `host_path:container_path[:ro]`, like `docker run -v`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub volumes: Vec<String>,
+    /// Network of the container, by default the `bridge` network of docker or `none` with `network: none`
+    pub network: Option<String>,
+    pub pull_policy: Option<PullPolicy>,
+    /// `docker` by default, e.g. `podman`
+    pub binary: Option<String>,
+}
+
+/// Remote machine of the tasks with `executor: ssh`
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct SshConfig {
+    pub host: Option<String>,
+    /// The one of the ssh config by default
+    pub user: Option<String>,
+    pub port: Option<u16>,
+    pub identity_file: Option<String>,
+    /// e.g. `10 seconds`
+    pub connect_timeout: Option<String>,
+}
+
+/// When the image is pulled before the container starts, like `docker run --pull`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PullPolicy {
+    Always,
+    Missing,
+    Never,
+}
+
+impl PullPolicy {
+    pub fn name(self) -> &'static str {
+        match self {
+            PullPolicy::Always => "always",
+            PullPolicy::Missing => "missing",
+            PullPolicy::Never => "never",
+        }
+    }
+}
+
+/// Runs the command in a container, see [DockerConfig]
+#[derive(Debug, Clone, PartialEq)]
+pub struct DockerExecutor {
+    pub image: String,
+    pub volumes: Vec<String>,
+    pub network: Option<String>,
+    pub pull_policy: Option<PullPolicy>,
+    pub binary: String,
+}
+
+/// Runs the command on another machine, see [SshConfig]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SshExecutor {
+    pub host: String,
+    pub user: Option<String>,
+    pub port: Option<u16>,
+    pub identity_file: Option<String>,
+    /// Seconds
+    pub connect_timeout: Option<u64>,
+}
+
+/// Future of a [TaskFn], an error fails the execution like a non-zero exit code
+pub type TaskFuture = Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
+/// Async closure that runs instead of a command, for the tasks added with [crate::SchedulerHandle::add_closure_task]
+#[derive(Clone)]
+pub struct TaskFn(Arc<dyn Fn() -> TaskFuture + Send + Sync>);
+
+impl TaskFn {
+    pub fn new<F, Fut>(f: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        TaskFn(Arc::new(move || Box::pin(f())))
+    }
+
+    pub fn call(&self) -> TaskFuture {
+        (self.0)()
+    }
+}
+
+impl Debug for TaskFn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("TaskFn")
+    }
+}
+
+impl PartialEq for TaskFn {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Where the command of a task runs
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Executor {
+    #[default]
+    Local,
+    Docker(DockerExecutor),
+    Ssh(SshExecutor),
+    /// Inside the scheduler, only for library users
+    Closure(TaskFn),
+    /// The steps of a pipeline, run by the scheduler
+    Pipeline(Arc<Pipeline>),
+}
+
+impl Executor {
+    pub fn parse(kind: Option<ExecutorKind>, docker: Option<&DockerConfig>, ssh: Option<&SshConfig>) -> Result<Self> {
+        match kind.unwrap_or_default() {
+            ExecutorKind::Local => Ok(Executor::Local),
+            ExecutorKind::Docker => {
+                let Some(docker) = docker else {
+                    bail!("executor: docker needs the `docker` options with at least an image");
+                };
+                let Some(image) = docker.image.clone().filter(|image| !image.is_empty()) else {
+                    bail!("Missing field: docker.image");
+                };
+                for volume in &docker.volumes {
+                    if !volume.contains(':') {
+                        bail!("Malformed field: docker.volumes, expected 'host_path:container_path', got '{}'", volume);
+                    }
+                }
+                Ok(Executor::Docker(DockerExecutor {
+                    image,
+                    volumes: docker.volumes.clone(),
+                    network: docker.network.clone(),
+                    pull_policy: docker.pull_policy,
+                    binary: docker.binary.clone().unwrap_or_else(|| "docker".to_string()),
+                }))
+            }
+            ExecutorKind::Ssh => {
+                let Some(ssh) = ssh else {
+                    bail!("executor: ssh needs the `ssh` options with at least a host");
+                };
+                let Some(host) = ssh.host.clone().filter(|host| !host.is_empty()) else {
+                    bail!("Missing field: ssh.host");
+                };
+                let connect_timeout = match &ssh.connect_timeout {
+                    Some(timeout) => Some(
+                        Schedule::parse_time_duration(timeout).context("Malformed field: ssh.connect_timeout")?.0.as_secs().max(1),
+                    ),
+                    None => None,
+                };
+                Ok(Executor::Ssh(SshExecutor {
+                    host,
+                    user: ssh.user.clone(),
+                    port: ssh.port,
+                    identity_file: ssh.identity_file.clone(),
+                    connect_timeout,
+                }))
+            }
+        }
+    }
+
+    pub fn is_local(&self) -> bool {
+        *self == Executor::Local
+    }
+
+    pub fn is_pipeline(&self) -> bool {
+        matches!(self, Executor::Pipeline(_))
+    }
+}
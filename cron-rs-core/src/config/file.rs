@@ -0,0 +1,872 @@
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use std::path::{Path, PathBuf};
+use std::collections::HashMap;
+use crate::alerts::{Alert, AlertConfig};
+use crate::plugins::PluginConfig;
+use crate::sentry::SentryConfig;
+use super::conditions::OnlyIfConfig;
+use super::dayofweek::DayOfWeekNumbering;
+use super::digest::DigestConfig;
+use super::executor::{DockerConfig, ExecutorKind, SshConfig};
+use super::limits::{CgroupConfig, ResourceLimitsConfig};
+use super::logging::LoggingConfig;
+use super::period::SchedulePeriod;
+use super::pipeline::PipelineDefinition;
+use super::retention::OutputRetentionConfig;
+use super::success::SuccessCriteriaConfig;
+use super::trigger::{AfterTaskConfig, OnChangeConfig};
+use super::secrets::mark_secrets;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct ConfigFile {
+    /// Other config files to merge into this one, glob patterns relative to this file
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub defaults: Option<TaskDefaults>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub templates: HashMap<String, TaskDefaults>,
+    pub tasks: Vec<TaskDefinition>,
+    /// Steps that run one after the other with a single schedule, each pipeline runs as a task
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub pipelines: Vec<PipelineDefinition>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logging: Option<LoggingConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alerts: Option<AlertConfig>,
+    /// Alerts that the alert lists can use by name, so their settings are written once
+    #[serde(default)]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub alert_channels: HashMap<String, Alert>,
+    /// WASM modules that send alerts or check the conditions of the tasks, by name
+    #[serde(default)]
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub plugins: HashMap<String, PluginConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub control_socket: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_retention: Option<OutputRetentionConfig>,
+    /// Summary of the executions sent once per interval
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub digest: Option<DigestConfig>,
+    /// Sentry project where the failed executions and the internal errors are reported
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sentry: Option<SentryConfig>,
+    /// cgroup v2 where the cgroups of the executions are created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cgroup_root: Option<PathBuf>,
+    /// How the numbers of the days of the week are read, `sunday_0` by default
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub day_of_week_numbering: Option<DayOfWeekNumbering>,
+    /// Where the time of the last run of the tasks with a `period` is kept
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period_stamp_dir: Option<PathBuf>,
+    /// File with the Prometheus metrics of the tasks, for the textfile collector of node_exporter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metrics_file: Option<PathBuf>,
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TaskDefinition {
+    pub name: String,
+    pub cmd: String,
+    /// Name of the template this task inherits from
+    #[serde(default)]
+    pub extends: Option<String>,
+    #[serde(default = "default_true")]
+    #[serde(skip_serializing_if = "skip_if_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub when: Option<TimePatternConfig>,
+    #[serde(default)]
+    pub min_interval: Option<String>,
+    #[serde(default)]
+    pub every: Option<String>,
+    /// Runs once per day, week or month whenever cron-rs is running, instead of at a given time
+    #[serde(default)]
+    pub period: Option<SchedulePeriod>,
+    /// Runs when the files or directories change, instead of at given times
+    #[serde(default)]
+    pub on_change: Option<OnChangeConfig>,
+    /// Runs when an execution of another task finishes, instead of at given times
+    #[serde(default)]
+    pub after_task: Option<AfterTaskConfig>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "skip_if_false")]
+    pub align: bool,
+    #[serde(default)]
+    pub between: Option<String>,
+    #[serde(default)]
+    pub on: Option<ExplodedTimePatternFieldConfig>,
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "skip_if_false")]
+    pub avoid_overlapping: bool,
+    /// Skips the runs while another process holds a flock on the file, like the `flock -n` wrappers of cron
+    #[serde(default)]
+    pub lock_file: Option<String>,
+    /// Redis server where the runs take a lock, e.g. `redis://redis:6379`, so only one node of a cluster runs the task
+    #[serde(default)]
+    pub cluster_lock: Option<String>,
+    /// Skips the runs while the host is on battery power
+    #[serde(default)]
+    pub only_on_ac_power: Option<bool>,
+    /// Skips the runs while the host is on battery power with less charge than this percentage
+    #[serde(default)]
+    pub min_battery_percent: Option<u8>,
+    /// Conditions on files checked before each run, the run is skipped if one isn't met
+    #[serde(default)]
+    pub only_if: Option<OnlyIfConfig>,
+    #[serde(default)]
+    pub run_as: Option<String>,
+    /// Sets HOME, USER, LOGNAME, SHELL and PATH from the passwd entry of the run_as user, like cron does
+    #[serde(default)]
+    pub login_env: Option<bool>,
+    /// Runs the command in a login shell, which reads /etc/profile and the ~/.profile of the user
+    #[serde(default)]
+    pub login_shell: Option<bool>,
+    /// Runs the command with this directory as `/`, only works when cron-rs runs as root
+    #[serde(default)]
+    pub chroot: Option<String>,
+    /// `none` runs the command without network access, only works when cron-rs runs as root
+    #[serde(default)]
+    pub network: Option<TaskNetwork>,
+    /// `docker` runs the command in a container, configured with `docker`, and `ssh` in another machine, configured
+    /// with `ssh`
+    #[serde(default)]
+    pub executor: Option<ExecutorKind>,
+    #[serde(default)]
+    pub docker: Option<DockerConfig>,
+    #[serde(default)]
+    pub ssh: Option<SshConfig>,
+    #[serde(default)]
+    pub time_limit: Option<String>,
+    /// Time between SIGTERM and SIGKILL when the task exceeds its time limit
+    #[serde(default)]
+    pub kill_grace: Option<String>,
+    /// Signal sent when the task exceeds its time limit, SIGTERM by default
+    #[serde(default)]
+    pub time_limit_signal: Option<StopSignal>,
+    /// Resource limits of the processes of the task
+    #[serde(default)]
+    pub limits: Option<ResourceLimitsConfig>,
+    /// Runs each execution in its own cgroup v2 with these limits
+    #[serde(default)]
+    pub cgroup: Option<CgroupConfig>,
+    /// Niceness of the processes of the task, from -20 to 19
+    #[serde(default)]
+    pub nice: Option<i32>,
+    /// I/O scheduling class, e.g. `idle` or `best-effort:7`
+    #[serde(default)]
+    pub ionice: Option<String>,
+    /// CPUs the task can run on, e.g. `0-3,6`
+    #[serde(default)]
+    pub cpu_affinity: Option<String>,
+    #[serde(default)]
+    pub shell: Option<String>,
+    #[serde(default)]
+    pub working_directory: Option<String>,
+    #[serde(default)]
+    pub env: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub env_file: Option<String>,
+    /// File passed as the standard input of the command
+    #[serde(default)]
+    pub stdin: Option<String>,
+    /// Text passed as the standard input of the command
+    #[serde(default)]
+    pub stdin_text: Option<String>,
+    #[serde(default)]
+    pub stdout: Option<String>,
+    #[serde(default)]
+    pub stderr: Option<String>,
+    #[serde(default)]
+    pub output_dir: Option<String>,
+    #[serde(default)]
+    pub output_mode: Option<OutputMode>,
+    /// Also write each line of the output to the log of cron-rs
+    #[serde(default)]
+    pub log_output: Option<bool>,
+    /// Write stderr to the stdout file, like `2>&1`, also enabled by `stderr: "&stdout"`
+    #[serde(default)]
+    pub combine_output: Option<bool>,
+    /// Conditions for an execution to count as successful, by default exit code 0
+    #[serde(default)]
+    pub success: Option<SuccessCriteriaConfig>,
+    /// Commands that run before the main command, if one fails the execution is aborted
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub before: Vec<String>,
+    /// Commands that run after the main command, even if it fails
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub after: Vec<String>,
+    /// Command that runs when the task fails, before the alerts are sent
+    #[serde(default)]
+    pub on_failure_cmd: Option<String>,
+    #[serde(default)]
+    pub max_runs: Option<u32>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "skip_if_false")]
+    pub max_runs_only_successes: bool,
+    /// Runtime the executions of a calendar month can add up to, the next runs of the month are skipped once it's used
+    #[serde(default)]
+    pub max_monthly_runtime: Option<String>,
+    /// What to do with a run missed while the host was suspended or cron-rs was paused, `run_once` by default
+    #[serde(default)]
+    pub misfire: Option<MisfirePolicy>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub on_failure: Vec<Alert>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub on_success: Vec<Alert>,
+}
+
+/// Values used by all tasks that don't define them, also used for the named templates in `templates`
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct TaskDefaults {
+    #[serde(default)]
+    pub timezone: Option<String>,
+    #[serde(default)]
+    pub time_limit: Option<String>,
+    #[serde(default)]
+    pub kill_grace: Option<String>,
+    #[serde(default)]
+    pub time_limit_signal: Option<StopSignal>,
+    #[serde(default)]
+    pub limits: Option<ResourceLimitsConfig>,
+    #[serde(default)]
+    pub cgroup: Option<CgroupConfig>,
+    #[serde(default)]
+    pub nice: Option<i32>,
+    #[serde(default)]
+    pub ionice: Option<String>,
+    #[serde(default)]
+    pub cpu_affinity: Option<String>,
+    #[serde(default)]
+    pub shell: Option<String>,
+    #[serde(default)]
+    pub only_on_ac_power: Option<bool>,
+    #[serde(default)]
+    pub min_battery_percent: Option<u8>,
+    #[serde(default)]
+    pub run_as: Option<String>,
+    #[serde(default)]
+    pub login_env: Option<bool>,
+    #[serde(default)]
+    pub login_shell: Option<bool>,
+    #[serde(default)]
+    pub chroot: Option<String>,
+    #[serde(default)]
+    pub network: Option<TaskNetwork>,
+    #[serde(default)]
+    pub executor: Option<ExecutorKind>,
+    #[serde(default)]
+    pub docker: Option<DockerConfig>,
+    #[serde(default)]
+    pub ssh: Option<SshConfig>,
+    #[serde(default)]
+    pub working_directory: Option<String>,
+    /// Merged with the task's env, the task's values take precedence
+    #[serde(default)]
+    pub env: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub env_file: Option<String>,
+    #[serde(default)]
+    pub output_dir: Option<String>,
+    #[serde(default)]
+    pub output_mode: Option<OutputMode>,
+    #[serde(default)]
+    pub log_output: Option<bool>,
+    #[serde(default)]
+    pub combine_output: Option<bool>,
+    #[serde(default)]
+    pub success: Option<SuccessCriteriaConfig>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub before: Vec<String>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub after: Vec<String>,
+    #[serde(default)]
+    pub on_failure_cmd: Option<String>,
+    #[serde(default)]
+    pub misfire: Option<MisfirePolicy>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub on_failure: Vec<Alert>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub on_success: Vec<Alert>,
+}
+
+impl TaskDefaults {
+    /// Fills the fields the task doesn't define with the default values
+    pub fn apply(&self, task: &TaskDefinition) -> TaskDefinition {
+        let mut task = task.clone();
+
+        fn inherit<T: Clone>(field: &mut Option<T>, default: &Option<T>) {
+            if field.is_none() {
+                *field = default.clone();
+            }
+        }
+
+        inherit(&mut task.timezone, &self.timezone);
+        inherit(&mut task.time_limit, &self.time_limit);
+        inherit(&mut task.kill_grace, &self.kill_grace);
+        inherit(&mut task.time_limit_signal, &self.time_limit_signal);
+        inherit(&mut task.limits, &self.limits);
+        inherit(&mut task.cgroup, &self.cgroup);
+        inherit(&mut task.nice, &self.nice);
+        inherit(&mut task.ionice, &self.ionice);
+        inherit(&mut task.cpu_affinity, &self.cpu_affinity);
+        inherit(&mut task.shell, &self.shell);
+        inherit(&mut task.only_on_ac_power, &self.only_on_ac_power);
+        inherit(&mut task.min_battery_percent, &self.min_battery_percent);
+        inherit(&mut task.run_as, &self.run_as);
+        inherit(&mut task.login_env, &self.login_env);
+        inherit(&mut task.login_shell, &self.login_shell);
+        inherit(&mut task.chroot, &self.chroot);
+        inherit(&mut task.network, &self.network);
+        inherit(&mut task.executor, &self.executor);
+        inherit(&mut task.docker, &self.docker);
+        inherit(&mut task.ssh, &self.ssh);
+        inherit(&mut task.working_directory, &self.working_directory);
+        inherit(&mut task.output_dir, &self.output_dir);
+        inherit(&mut task.output_mode, &self.output_mode);
+        inherit(&mut task.log_output, &self.log_output);
+        inherit(&mut task.combine_output, &self.combine_output);
+        inherit(&mut task.success, &self.success);
+        inherit(&mut task.on_failure_cmd, &self.on_failure_cmd);
+        inherit(&mut task.env_file, &self.env_file);
+        inherit(&mut task.misfire, &self.misfire);
+
+        if let Some(default_env) = &self.env {
+            let mut env = default_env.clone();
+            env.extend(task.env.take().unwrap_or_default());
+            task.env = Some(env);
+        }
+
+        if task.before.is_empty() {
+            task.before = self.before.clone();
+        }
+        if task.after.is_empty() {
+            task.after = self.after.clone();
+        }
+        if task.on_failure.is_empty() {
+            task.on_failure = self.on_failure.clone();
+        }
+        if task.on_success.is_empty() {
+            task.on_success = self.on_success.clone();
+        }
+
+        task
+    }
+}
+
+impl ConfigFile {
+    /// Adds the tasks and templates of another file, the rest of the options are only taken
+    /// if this file doesn't define them
+    pub fn merge(&mut self, other: ConfigFile) {
+        self.tasks.extend(other.tasks);
+        self.pipelines.extend(other.pipelines);
+        self.templates.extend(other.templates);
+        self.alert_channels.extend(other.alert_channels);
+        self.plugins.extend(other.plugins);
+        self.defaults = self.defaults.take().or(other.defaults);
+        self.logging = self.logging.take().or(other.logging);
+        self.alerts = self.alerts.take().or(other.alerts);
+        self.control_socket = self.control_socket.take().or(other.control_socket);
+        self.cgroup_root = self.cgroup_root.take().or(other.cgroup_root);
+        self.day_of_week_numbering = self.day_of_week_numbering.take().or(other.day_of_week_numbering);
+        self.period_stamp_dir = self.period_stamp_dir.take().or(other.period_stamp_dir);
+        self.metrics_file = self.metrics_file.take().or(other.metrics_file);
+        self.output_retention = self.output_retention.take().or(other.output_retention);
+        self.digest = self.digest.take().or(other.digest);
+        self.sentry = self.sentry.take().or(other.sentry);
+    }
+
+    /// Task definitions with their template and the defaults applied
+    pub fn resolved_tasks(&self) -> Result<Vec<TaskDefinition>> {
+        self.tasks.iter().map(|t| self.resolve_task(t)).collect()
+    }
+
+    /// Applies the task's template and then the defaults, the task's own values always win
+    pub fn resolve_task(&self, task: &TaskDefinition) -> Result<TaskDefinition> {
+        let mut task = task.clone();
+
+        if let Some(name) = &task.extends {
+            let Some(template) = self.templates.get(name) else {
+                bail!("Task '{}': unknown template '{}'", task.name, name);
+            };
+            task = template.apply(&task);
+        }
+
+        if let Some(defaults) = &self.defaults {
+            task = defaults.apply(&task);
+        }
+
+        if let Some(plugin) = task.only_if.as_ref().and_then(|only_if| only_if.plugin.as_ref()) {
+            if !self.plugins.contains_key(plugin) {
+                bail!("Task '{}': unknown plugin '{}'", task.name, plugin);
+            }
+        }
+
+        task.on_failure = self.resolve_alerts(&task.on_failure).with_context(|| format!("Task '{}'", task.name))?;
+        task.on_success = self.resolve_alerts(&task.on_success).with_context(|| format!("Task '{}'", task.name))?;
+        Ok(task)
+    }
+
+    /// Replaces the names of `alert_channels` in the list with the alerts they refer to, and checks that the plugins
+    /// they use are defined
+    pub fn resolve_alerts(&self, alerts: &[Alert]) -> Result<Vec<Alert>> {
+        alerts
+            .iter()
+            .map(|alert| {
+                let alert = match alert {
+                    Alert::Channel(name) => match self.alert_channels.get(name) {
+                        Some(Alert::Channel(_)) => bail!("Alert channel '{}' refers to another channel", name),
+                        Some(channel) => channel.clone(),
+                        None => bail!("Unknown alert channel '{}'", name),
+                    },
+                    alert => alert.clone(),
+                };
+                match &alert {
+                    Alert::Plugin { plugin } if !self.plugins.contains_key(plugin) => bail!("Unknown plugin '{}'", plugin),
+                    _ => Ok(alert),
+                }
+            })
+            .collect()
+    }
+}
+
+/// How the stdout/stderr files are written on each execution
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputMode {
+    /// The file is overwritten by each execution
+    Truncate,
+    /// The output of each execution is added at the end of the file
+    Append,
+    /// Each execution writes to its own file, named with the run id
+    PerRun,
+}
+
+/// Signal used to stop a task, the ones that aren't SIGKILL are followed by a SIGKILL after `kill_grace`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum StopSignal {
+    Sigint,
+    #[default]
+    Sigterm,
+    Sighup,
+    Sigkill,
+}
+
+impl StopSignal {
+    #[cfg(unix)]
+    pub fn number(self) -> libc::c_int {
+        match self {
+            StopSignal::Sigint => libc::SIGINT,
+            StopSignal::Sigterm => libc::SIGTERM,
+            StopSignal::Sighup => libc::SIGHUP,
+            StopSignal::Sigkill => libc::SIGKILL,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StopSignal::Sigint => "SIGINT",
+            StopSignal::Sigterm => "SIGTERM",
+            StopSignal::Sighup => "SIGHUP",
+            StopSignal::Sigkill => "SIGKILL",
+        }
+    }
+}
+
+/// What happens to a run whose time passed while the scheduler wasn't running, e.g. during a suspend of the host
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MisfirePolicy {
+    /// The missed run starts as soon as the scheduler wakes up, only once however many were missed
+    #[default]
+    RunOnce,
+    /// The missed run is reported as skipped and the task waits for its next run
+    Skip,
+}
+
+/// Network access of the processes of a task
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskNetwork {
+    /// The network of the host
+    #[default]
+    Host,
+    /// A new network namespace with only the loopback interface
+    None,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum TimePatternConfig {
+    Short(String),
+    Long(ExplodedTimePatternConfig),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExplodedTimePatternConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub second: Option<ExplodedTimePatternFieldConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub minute: Option<ExplodedTimePatternFieldConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hour: Option<ExplodedTimePatternFieldConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub day: Option<ExplodedTimePatternFieldConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub month: Option<ExplodedTimePatternFieldConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<ExplodedTimePatternFieldConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub day_of_week: Option<ExplodedTimePatternFieldConfig>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum ExplodedTimePatternFieldConfig {
+    Number(u32),
+    Text(String),
+    List(Vec<String>),
+}
+
+/// Validates that the config file exists, is a regular file, and has secure permissions.
+pub fn validate_config_path(config_path: &Path) -> anyhow::Result<()> {
+    match std::fs::metadata(config_path) {
+        Ok(metadata) => {
+            if !metadata.is_file() && !metadata.is_dir() {
+                return Err(anyhow::anyhow!(
+                    "Config path {} is not a file or directory",
+                    config_path.to_string_lossy()
+                ));
+            }
+            #[cfg(unix)]
+            {
+                use std::os::unix::fs::MetadataExt;
+
+                if metadata.mode() & 0o002 != 0 {
+                    let error = anyhow::anyhow!(
+                        concat!(
+                            "Config file {} is globally writable by any user.\n",
+                            "###\n",
+                            "THIS IS MAYOR SECURITY RISK\n",
+                            "###\n",
+                            "Any user can alter the config file and add tasks that will be run with the current user's permissions.\n",
+                            "Refusing to run with insecure file permissions (mod {:o})"
+                        ),
+                        config_path.to_string_lossy(),
+                        (metadata.mode() & 0o777)
+                    );
+                    return Err(error);
+                }
+            }
+            Ok(())
+        }
+        Err(e) => Err(anyhow::anyhow!(
+            "Failed to read config file {}: {}",
+            config_path.to_string_lossy(),
+            e
+        )),
+    }
+}
+
+/// Reads a config file and the files it includes, or all the YAML files of a directory
+pub fn read_config_file<P: AsRef<Path>>(path: P) -> anyhow::Result<ConfigFile> {
+    read_config_path(path.as_ref(), &mut IncludeState::default())
+}
+
+/// Files of the current chain of includes, to find cycles, and all the files read so far
+#[derive(Default)]
+struct IncludeState {
+    stack: Vec<PathBuf>,
+    read: Vec<PathBuf>,
+}
+
+fn read_config_path(path: &Path, state: &mut IncludeState) -> anyhow::Result<ConfigFile> {
+    if path.is_dir() {
+        return read_config_dir(path, state);
+    }
+
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    if state.stack.contains(&canonical) {
+        bail!("Config file {} includes itself", path.display());
+    }
+    // A file included from several files, like a shared one, is only merged once
+    if state.read.contains(&canonical) {
+        return Ok(ConfigFile::default());
+    }
+    state.read.push(canonical.clone());
+    state.stack.push(canonical);
+
+    let mut config = parse_config_text(path)?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+
+    for pattern in std::mem::take(&mut config.include) {
+        for file in expand_include(base_dir, &pattern)? {
+            validate_config_path(&file)?;
+            let included = read_config_path(&file, state)
+                .with_context(|| format!("Failed to include config file {}", file.display()))?;
+            config.merge(included);
+        }
+    }
+
+    state.stack.pop();
+    Ok(config)
+}
+
+/// Merges all the `.yml` and `.yaml` files in the directory, in alphabetical order
+/// The YAML files of a config directory, in the order they are merged
+pub fn list_config_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read config directory {}", dir.display()))? {
+        let path = entry?.path();
+        let is_yaml = matches!(path.extension().and_then(|e| e.to_str()), Some("yml" | "yaml"));
+        if is_yaml && path.is_file() {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    if files.is_empty() {
+        bail!("No config files found in {}", dir.display());
+    }
+    Ok(files)
+}
+
+fn read_config_dir(dir: &Path, state: &mut IncludeState) -> anyhow::Result<ConfigFile> {
+    let mut config = ConfigFile::default();
+    for file in list_config_files(dir)? {
+        validate_config_path(&file)?;
+        let other = read_config_path(&file, state)
+            .with_context(|| format!("Failed to read config file {}", file.display()))?;
+        config.merge(other);
+    }
+
+    Ok(config)
+}
+
+fn expand_include(base_dir: &Path, pattern: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let full_pattern = base_dir.join(pattern);
+    let full_pattern = full_pattern.to_string_lossy();
+
+    let mut files = vec![];
+    for entry in glob::glob(&full_pattern).with_context(|| format!("Invalid include pattern '{}'", pattern))? {
+        files.push(entry?);
+    }
+    files.sort();
+
+    // A pattern without wildcards must point to an existing file
+    if files.is_empty() && !pattern.contains(['*', '?', '[']) {
+        bail!("Included config file {} not found", full_pattern);
+    }
+
+    Ok(files)
+}
+
+/// Reads a single config file, without its includes
+pub fn parse_config_text(path: &Path) -> anyhow::Result<ConfigFile> {
+    let content = std::fs::read_to_string(path).context("Failed to read config file")?;
+    let mut value: serde_yml::Value = serde_yml::from_str(&content).context("Failed to parse config file")?;
+
+    // Parsing from the text keeps the line and column of the errors
+    let config = if mark_secrets(&mut value).context("Failed to parse secrets in config file")? {
+        serde_yml::from_value(value).context("Failed to parse config file")?
+    } else {
+        serde_yml::from_str(&content).context("Failed to parse config file")?
+    };
+
+    Ok(config)
+}
+
+fn skip_if_false(arg: &bool) -> bool {
+    !*arg
+}
+
+fn skip_if_true(arg: &bool) -> bool {
+    *arg
+}
+
+pub(super) fn default_true() -> bool {
+    true
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(yaml: &str) -> ConfigFile {
+        serde_yml::from_str(yaml).unwrap()
+    }
+
+    fn write_files(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cron-rs-test-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for (file, content) in files {
+            std::fs::write(dir.join(file), content).unwrap();
+        }
+        dir
+    }
+
+    fn task_names(config: &ConfigFile) -> Vec<&str> {
+        config.tasks.iter().map(|t| t.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_includes() {
+        // main includes a and b, both include shared
+        let dir = write_files(
+            "include",
+            &[
+                ("main.yml", "include: [a.yml, b.yml]\ncontrol_socket: main.sock\ntasks: [{ name: main, cmd: 'true', every: 1 hour }]"),
+                ("a.yml", "include: [shared.yml]\ntasks: [{ name: a, cmd: 'true', every: 1 hour }]"),
+                ("b.yml", "include: [shared.yml]\ncontrol_socket: b.sock\ntasks: [{ name: b, cmd: 'true', every: 1 hour }]"),
+                ("shared.yml", "tasks: [{ name: shared, cmd: 'true', every: 1 hour }]"),
+            ],
+        );
+        let config = read_config_file(dir.join("main.yml")).unwrap();
+        assert_eq!(task_names(&config), ["main", "a", "shared", "b"]);
+        assert_eq!(config.control_socket, Some(PathBuf::from("main.sock")));
+
+        // A cycle is an error, also when it goes through other files
+        std::fs::write(dir.join("shared.yml"), "include: [a.yml]\ntasks: []").unwrap();
+        let error = format!("{:#}", read_config_file(dir.join("main.yml")).unwrap_err());
+        assert!(error.contains("a.yml includes itself"), "{}", error);
+
+        std::fs::write(dir.join("shared.yml"), "include: [missing.yml]\ntasks: []").unwrap();
+        assert!(read_config_file(dir.join("main.yml")).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_config_dir() {
+        let dir = write_files(
+            "config-dir",
+            &[
+                ("20-b.yaml", "tasks: [{ name: b, cmd: 'true', every: 1 hour }]"),
+                ("10-a.yml", "include: [common.inc]\ntasks: [{ name: a, cmd: 'true', every: 1 hour }]"),
+                ("common.inc", "tasks: [{ name: common, cmd: 'true', every: 1 hour }]"),
+                ("notes.txt", "not a config file"),
+            ],
+        );
+        // In alphabetical order, only the YAML files and the files they include
+        let config = read_config_file(&dir).unwrap();
+        assert_eq!(task_names(&config), ["a", "common", "b"]);
+
+        let empty = dir.join("empty");
+        std::fs::create_dir_all(&empty).unwrap();
+        assert!(read_config_file(&empty).unwrap_err().to_string().starts_with("No config files found"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_defaults() {
+        let file = parse(
+            "
+             defaults:
+               shell: bash
+               timezone: Europe/Madrid
+               time_limit: 60 seconds
+               env: { A: default, B: default }
+               before: ['echo default']
+             tasks:
+               - { name: plain, cmd: 'true', every: 1 hour }
+               - name: custom
+                 cmd: 'true'
+                 every: 1 hour
+                 shell: zsh
+                 time_limit: 5 seconds
+                 env: { B: task, C: task }
+                 before: ['echo task']",
+        );
+        let tasks = file.resolved_tasks().unwrap();
+
+        assert_eq!(tasks[0].shell.as_deref(), Some("bash"));
+        assert_eq!(tasks[0].timezone.as_deref(), Some("Europe/Madrid"));
+        assert_eq!(tasks[0].time_limit.as_deref(), Some("60 seconds"));
+        assert_eq!(tasks[0].env, Some(HashMap::from([("A".into(), "default".into()), ("B".into(), "default".into())])));
+        assert_eq!(tasks[0].before, ["echo default"]);
+
+        // The values of the task win, the env is merged with the task values on top
+        assert_eq!(tasks[1].shell.as_deref(), Some("zsh"));
+        assert_eq!(tasks[1].timezone.as_deref(), Some("Europe/Madrid"));
+        assert_eq!(tasks[1].time_limit.as_deref(), Some("5 seconds"));
+        let env = HashMap::from([("A".into(), "default".into()), ("B".into(), "task".into()), ("C".into(), "task".into())]);
+        assert_eq!(tasks[1].env, Some(env));
+        assert_eq!(tasks[1].before, ["echo task"]);
+    }
+
+    #[test]
+    fn test_templates() {
+        let file = parse(
+            "
+             defaults:
+               shell: bash
+               time_limit: 1 hour
+               env: { A: default }
+             templates:
+               db_job:
+                 shell: zsh
+                 time_limit: 30 minutes
+                 env: { A: template, B: template }
+             tasks:
+               - { name: vacuum, cmd: 'true', every: 1 hour, extends: db_job }
+               - { name: dump, cmd: 'true', every: 1 hour, extends: db_job, shell: sh, env: { B: task } }",
+        );
+        let tasks = file.resolved_tasks().unwrap();
+
+        // The template goes before the defaults, and the task before both
+        assert_eq!(tasks[0].shell.as_deref(), Some("zsh"));
+        assert_eq!(tasks[0].time_limit.as_deref(), Some("30 minutes"));
+        assert_eq!(tasks[0].env, Some(HashMap::from([("A".into(), "template".into()), ("B".into(), "template".into())])));
+        assert_eq!(tasks[1].shell.as_deref(), Some("sh"));
+        assert_eq!(tasks[1].env, Some(HashMap::from([("A".into(), "template".into()), ("B".into(), "task".into())])));
+
+        let file = parse("tasks: [{ name: orphan, cmd: 'true', every: 1 hour, extends: missing }]");
+        let error = file.resolved_tasks().unwrap_err().to_string();
+        assert_eq!(error, "Task 'orphan': unknown template 'missing'");
+    }
+
+    #[test]
+    fn test_alert_channels() {
+        let file = parse(
+            "
+             alert_channels:
+               ops_email: { type: email, to: ops@example.com, smtp_server: smtp.example.com }
+               pager: { type: cmd, cmd: page }
+             defaults:
+               on_failure: [ops_email]
+             tasks:
+               - { name: a, cmd: 'true', every: 1 hour }
+               - { name: b, cmd: 'true', every: 1 hour, on_failure: [pager, { type: cmd, cmd: notify }] }
+               - { name: c, cmd: 'true', every: 1 hour, on_success: [missing] }
+            ",
+        );
+        let a = file.resolve_task(&file.tasks[0]).unwrap();
+        assert!(matches!(&a.on_failure[..], [Alert::Email { to, .. }] if to.to_string() == "ops@example.com"));
+        let b = file.resolve_task(&file.tasks[1]).unwrap();
+        assert_eq!(b.on_failure.iter().map(Alert::kind).collect::<Vec<_>>(), ["cmd", "cmd"]);
+
+        let error = format!("{:#}", file.resolve_task(&file.tasks[2]).unwrap_err());
+        assert_eq!(error, "Task 'c': Unknown alert channel 'missing'");
+    }
+}
@@ -0,0 +1,353 @@
+use super::dayofweek::DayOfWeek;
+use super::file::{ConfigFile, TimePatternConfig};
+use super::secrets::secret_reference;
+use super::timeunit::TimeUnit;
+use super::{number, TimePattern, TimePatternField};
+use anyhow::Result;
+use nom::bytes::complete::tag;
+use nom::character::complete::space0;
+use nom::combinator::{all_consuming, opt};
+use nom::sequence::{delimited, separated_pair};
+use serde_yml::value::{Tag, TaggedValue};
+use serde_yml::{Mapping, Value};
+
+/// Keys whose values are maps written by the user, like `env`, their keys are sorted. The rest of the mappings are the
+/// options, kept in the order of the documentation
+const MAP_KEYS: [&str; 5] = ["templates", "alert_channels", "plugins", "env", "headers"];
+
+/// Options of `cron-rs fmt`
+#[derive(Debug, Clone, Default)]
+pub struct FormatOptions {
+    /// Writes the templates and the defaults into each task, and removes them
+    pub expand_defaults: bool,
+}
+
+/// Writes the config in a canonical form: the options in the same order, the maps sorted by key, the `when` patterns
+/// and the durations of `every` and `min_interval` written the same way. Formatting the result again changes nothing.
+/// Values that don't parse are kept as they are, validating them is left to `cron-rs validate`
+pub fn format_config(file: &ConfigFile, options: &FormatOptions) -> Result<String> {
+    let mut file = file.clone();
+
+    if options.expand_defaults {
+        file.tasks = file.resolved_tasks()?;
+        for task in &mut file.tasks {
+            task.extends = None;
+        }
+        file.defaults = None;
+        file.templates.clear();
+    }
+
+    for task in &mut file.tasks {
+        normalize_when(&mut task.when);
+        normalize_interval(&mut task.every);
+        normalize_interval(&mut task.min_interval);
+    }
+    for pipeline in &mut file.pipelines {
+        normalize_when(&mut pipeline.when);
+        normalize_interval(&mut pipeline.every);
+    }
+
+    let mut value = serde_yml::to_value(&file)?;
+    canonical_value(&mut value, false);
+    Ok(serde_yml::to_string(&value)?)
+}
+
+/// The text without its comments and blank lines, which [format_config] doesn't keep. `cron-rs fmt --check` compares
+/// it with the formatted config so the comments don't count as differences
+pub fn strip_comments(text: &str) -> String {
+    code_lines(text)
+        .into_iter()
+        .filter(|(code, in_block)| *in_block || !code.trim().is_empty())
+        .map(|(code, _)| format!("{}\n", code))
+        .collect()
+}
+
+/// Whether the text has comments, which `cron-rs fmt --write` would remove
+pub fn has_comments(text: &str) -> bool {
+    code_lines(text)
+        .into_iter()
+        .zip(text.lines())
+        .any(|((code, in_block), line)| !in_block && code.len() < line.trim_end().len())
+}
+
+/// The lines up to their comments, and whether they are the content of a block scalar, after a `|` or a `>`. That
+/// content is kept whole, a `#` in it is text. Its blank lines are kept too, except the ones at its end
+fn code_lines(text: &str) -> Vec<(&str, bool)> {
+    let mut lines = vec![];
+    let mut block_indent = None;
+    let mut blank_lines = vec![];
+    for line in text.lines() {
+        let indent = line.len() - line.trim_start().len();
+        if let Some(parent_indent) = block_indent {
+            if line.trim().is_empty() {
+                blank_lines.push(("", true));
+                continue;
+            }
+            if indent > parent_indent {
+                lines.append(&mut blank_lines);
+                lines.push((line.trim_end(), true));
+                continue;
+            }
+            block_indent = None;
+            lines.extend(blank_lines.drain(..).map(|_| ("", false)));
+        }
+        let code = code_of_line(line);
+        if starts_block_scalar(code) {
+            block_indent = Some(indent);
+        }
+        lines.push((code, false));
+    }
+    lines.extend(blank_lines.into_iter().map(|_| ("", false)));
+    lines
+}
+
+/// Whether the line ends with the indicator of a block scalar, like `cmd: |`, `- >-` or `cmd: !secret |2`
+fn starts_block_scalar(code: &str) -> bool {
+    let Some(start) = code.rfind(['|', '>']) else {
+        return false;
+    };
+    let (before, indicator) = code.split_at(start);
+    let last_token = before.split_whitespace().last().unwrap_or("");
+    indicator[1..].chars().all(|c| c.is_ascii_digit() || c == '-' || c == '+')
+        && (before.is_empty() || before.ends_with([' ', '\t']))
+        && (last_token.is_empty() || last_token.ends_with([':', '-']) || last_token.starts_with(['!', '&']))
+}
+
+/// The line up to its comment, a `#` at the start or after a space that is not quoted
+fn code_of_line(line: &str) -> &str {
+    let mut quote = None;
+    let mut previous = ' ';
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            // A quote only starts a quoted value at its start, `it's` is not quoted
+            None if matches!(c, '"' | '\'') && matches!(previous, ' ' | '\t' | ':' | '-' | '[' | '{' | ',') => {
+                quote = Some(c)
+            }
+            None if c == '#' && previous.is_whitespace() => return line[..index].trim_end(),
+            None => {}
+        }
+        previous = c;
+    }
+    line.trim_end()
+}
+
+fn normalize_when(when: &mut Option<TimePatternConfig>) {
+    if let Some(TimePatternConfig::Short(text)) = when {
+        if let Some(normalized) = normalize_pattern(text) {
+            *text = normalized;
+        }
+    }
+}
+
+/// `[Mon,Fri] *-*-01 09:00:00`, with the names of the days when they mean the same, otherwise their numbers
+fn normalize_pattern(text: &str) -> Option<String> {
+    let pattern = TimePattern::parse_short(text).ok()?;
+    let same = |candidate: &String| {
+        TimePattern::parse_short(candidate).is_ok_and(|parsed| parsed.to_string() == pattern.to_string())
+    };
+    // With `monday_1` numbering 7 is Sunday but `Sun` is read as 0, the names can't always be used
+    [true, false].into_iter().map(|names| write_pattern(&pattern, names)).find(same)
+}
+
+fn write_pattern(pattern: &TimePattern, day_names: bool) -> String {
+    let date = format!("{}-{}-{}", write_field(&pattern.year), write_field(&pattern.month), write_field(&pattern.day));
+    let time = format!("{}:{}:{}", write_field(&pattern.hour), write_field(&pattern.minute), write_field(&pattern.second));
+    match &pattern.day_of_week {
+        TimePatternField::Any => format!("{} {}", date, time),
+        days if day_names => format!("{} {} {}", write_days(days), date, time),
+        days => format!("{} {} {}", write_values(days, |value| value.to_string()), date, time),
+    }
+}
+
+fn write_field(field: &TimePatternField) -> String {
+    write_values(field, |value| format!("{:02}", value))
+}
+
+fn write_days(field: &TimePatternField) -> String {
+    write_values(field, |value| format!("{:?}", DayOfWeek::from_u32(value)))
+}
+
+fn write_values(field: &TimePatternField, value: impl Fn(u32) -> String) -> String {
+    match field {
+        TimePatternField::Any => "*".to_string(),
+        TimePatternField::Value(v) => value(*v),
+        TimePatternField::Range(start, end) => format!("{}..{}", value(*start), value(*end)),
+        TimePatternField::List(values) => format!("[{}]", values.iter().map(|v| value(*v)).collect::<Vec<_>>().join(",")),
+        TimePatternField::Ratio(divisor, 0) => format!("*/{}", divisor),
+        TimePatternField::Ratio(divisor, offset) => format!("*/{}+{}", divisor, offset),
+    }
+}
+
+/// `15m` or `15 minute` become `15 minutes`
+fn normalize_interval(interval: &mut Option<String>) {
+    let Some(text) = interval else {
+        return;
+    };
+    let parsed = all_consuming(separated_pair(
+        delimited(space0, number, space0),
+        space0,
+        separated_pair(TimeUnit::parse, space0, opt(tag("aligned"))),
+    ))(text.as_str());
+
+    if let Ok((_, (amount, (unit, aligned)))) = parsed {
+        let plural = if amount == 1 { "" } else { "s" };
+        let aligned = if aligned.is_some() { " aligned" } else { "" };
+        *text = format!("{} {}{}{}", amount, unit.name(), plural, aligned);
+    }
+}
+
+/// Sorts the maps of [MAP_KEYS] and writes the secrets back as `!secret` tags
+fn canonical_value(value: &mut Value, sort: bool) {
+    match value {
+        Value::String(text) => {
+            if let Some(reference) = secret_reference(text) {
+                let tag = Tag::new("secret");
+                *value = Value::Tagged(Box::new(TaggedValue { tag, value: Value::String(reference.to_string()) }));
+            }
+        }
+        Value::Sequence(items) => items.iter_mut().for_each(|item| canonical_value(item, false)),
+        Value::Mapping(mapping) => {
+            let mut entries: Vec<(Value, Value)> = std::mem::take(mapping).into_iter().collect();
+            if sort {
+                entries.sort_by(|(a, _), (b, _)| a.as_str().cmp(&b.as_str()));
+            }
+            let mut sorted = Mapping::new();
+            for (key, mut item) in entries {
+                let is_map = key.as_str().is_some_and(|key| MAP_KEYS.contains(&key));
+                canonical_value(&mut item, is_map);
+                sorted.insert(key, item);
+            }
+            *mapping = sorted;
+        }
+        Value::Tagged(tagged) => canonical_value(&mut tagged.value, sort),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::secrets::mark_secrets;
+
+    fn format(yaml: &str, expand_defaults: bool) -> String {
+        let mut value: Value = serde_yml::from_str(yaml).unwrap();
+        mark_secrets(&mut value).unwrap();
+        let file: ConfigFile = serde_yml::from_value(value).unwrap();
+        format_config(&file, &FormatOptions { expand_defaults }).unwrap()
+    }
+
+    #[test]
+    fn test_format_config() {
+        let config = r#"
+tasks:
+  - cmd: echo hi
+    name: hi
+    when: "mon..fri *-*-1 9:0:0"
+    env:
+      B: "2"
+      A: "1"
+  - name: interval
+    cmd: echo
+    every: 15m aligned
+  - name: sunday
+    cmd: echo
+    when: "7 *-*-* 0:0:0"
+logging:
+  output: stdout
+  level: info
+"#;
+        let formatted = format(config, false);
+        let expected = r#"tasks:
+- name: hi
+  cmd: echo hi
+  when: Mon..Fri *-*-01 09:00:00
+  env:
+    A: '1'
+    B: '2'
+- name: interval
+  cmd: echo
+  every: '15 minutes aligned'
+- name: sunday
+  cmd: echo
+  when: '7 *-*-* 00:00:00'
+logging:
+  output: stdout
+  level: info
+"#;
+        assert_eq!(formatted, expected);
+        // The result is already formatted
+        assert_eq!(format(&formatted, false), formatted);
+    }
+
+    #[test]
+    fn test_strip_comments() {
+        let config = r#"# Backups
+tasks:
+  - name: backup # nightly
+
+    cmd: "echo '#1' # not a comment"
+    when: it's # a comment
+"#;
+        assert!(has_comments(config));
+        let expected = "tasks:\n  - name: backup\n    cmd: \"echo '#1' # not a comment\"\n    when: it's\n";
+        assert_eq!(strip_comments(config), expected);
+        assert!(!has_comments("tasks:\n  - cmd: echo 'a # b'\n    env: { A: \"#\" }\n"));
+    }
+
+    #[test]
+    fn test_block_scalars_have_no_comments() {
+        let config = r#"tasks:
+  - name: start
+    cmd: |
+      echo start # note
+
+      # not a comment
+    every: 1 hour
+"#;
+        assert!(!has_comments(config));
+        assert_eq!(strip_comments(config), config);
+
+        // `cron-rs fmt --check` passes on the formatted config
+        let formatted = format(config, false);
+        assert!(formatted.contains("echo start # note\n"), "{}", formatted);
+        assert_eq!(strip_comments(&formatted), formatted);
+        assert!(!has_comments(&formatted));
+
+        // After the block the comments are back
+        let commented = "tasks:\n- name: start\n  cmd: >-\n    a # b\n\n  # every day\n  every: 1 day # or 2\n";
+        assert!(has_comments(commented));
+        assert_eq!(strip_comments(commented), "tasks:\n- name: start\n  cmd: >-\n    a # b\n  every: 1 day\n");
+        assert!(!starts_block_scalar("cmd: echo a | grep b"));
+        assert!(!starts_block_scalar("cmd: echo a >file"));
+    }
+
+    #[test]
+    fn test_format_expand_defaults() {
+        let config = r#"
+defaults:
+  timezone: UTC
+templates:
+  quiet:
+    output_mode: append
+tasks:
+  - name: backup
+    cmd: backup
+    extends: quiet
+    every: 1 hour
+    env:
+      SECRET: !secret file:/run/secret
+"#;
+        let formatted = format(config, false);
+        assert!(formatted.contains("SECRET: !secret file:/run/secret"), "{}", formatted);
+        assert!(formatted.contains("extends: quiet"), "{}", formatted);
+
+        let formatted = format(config, true);
+        assert!(!formatted.contains("defaults:") && !formatted.contains("templates:"), "{}", formatted);
+        assert!(formatted.contains("timezone: UTC") && formatted.contains("output_mode: append"), "{}", formatted);
+    }
+}
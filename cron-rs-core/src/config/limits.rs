@@ -0,0 +1,291 @@
+use super::retention::parse_size;
+use super::Schedule;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+#[cfg(unix)]
+use std::io;
+
+/// Resource limits of the processes of a task, as written in the config file
+#[skip_serializing_none]
+#[derive(Debug, Clone, Deserialize, Serialize, Default, PartialEq)]
+pub struct ResourceLimitsConfig {
+    /// Max size of the address space of each process, e.g. `2 GB`
+    pub memory: Option<String>,
+    /// Max CPU time of each process, e.g. `10 minutes`. It gets a SIGXCPU when reached
+    pub cpu_time: Option<String>,
+    /// Max number of open file descriptors
+    pub open_files: Option<u64>,
+    /// Max size of the core dumps, `0` disables them
+    pub core_size: Option<String>,
+}
+
+/// Limits applied with setrlimit, both the soft and the hard limit get the same value
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ResourceLimits {
+    pub memory: Option<u64>,
+    pub cpu_time: Option<u64>,
+    pub open_files: Option<u64>,
+    pub core_size: Option<u64>,
+}
+
+impl ResourceLimits {
+    pub fn parse(config: &ResourceLimitsConfig) -> Result<Self> {
+        let size = |value: &Option<String>, field: &str| -> Result<Option<u64>> {
+            value.as_deref().map(parse_size).transpose().context(format!("Malformed field: limits.{}", field))
+        };
+
+        Ok(Self {
+            memory: size(&config.memory, "memory")?,
+            cpu_time: config
+                .cpu_time
+                .as_deref()
+                .map(|v| Schedule::parse_time_duration(v).map(|(duration, _)| duration.as_secs().max(1)))
+                .transpose()
+                .context("Malformed field: limits.cpu_time")?,
+            open_files: config.open_files,
+            core_size: size(&config.core_size, "core_size")?,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Sets the limits on the current process. Called in the child between fork and exec, so it must not allocate
+    #[cfg(unix)]
+    pub fn apply(&self) -> io::Result<()> {
+        let limits = [
+            (libc::RLIMIT_AS, self.memory),
+            (libc::RLIMIT_CPU, self.cpu_time),
+            (libc::RLIMIT_NOFILE, self.open_files),
+            (libc::RLIMIT_CORE, self.core_size),
+        ];
+        for (resource, value) in limits {
+            let Some(value) = value else { continue };
+            let limit = libc::rlimit { rlim_cur: value as libc::rlim_t, rlim_max: value as libc::rlim_t };
+            // SAFETY: the rlimit struct is valid for the duration of the call
+            if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Limits of the transient cgroup of each execution, as written in the config file
+#[skip_serializing_none]
+#[derive(Debug, Clone, Deserialize, Serialize, Default, PartialEq)]
+pub struct CgroupConfig {
+    /// Max memory of all the processes of the task together, e.g. `1 GB`
+    pub memory_max: Option<String>,
+    /// Max CPU usage as a percentage of one CPU, e.g. `50%` or `200%` for two CPUs
+    pub cpu_max: Option<String>,
+}
+
+/// Values for `memory.max` and `cpu.max` of the cgroup v2 of each execution
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CgroupLimits {
+    pub memory_max: Option<u64>,
+    /// Percentage of one CPU
+    pub cpu_max: Option<u32>,
+}
+
+impl CgroupLimits {
+    pub fn parse(config: &CgroupConfig) -> Result<Self> {
+        Ok(Self {
+            memory_max: config.memory_max.as_deref().map(parse_size).transpose().context("Malformed field: cgroup.memory_max")?,
+            cpu_max: config.cpu_max.as_deref().map(parse_percentage).transpose().context("Malformed field: cgroup.cpu_max")?,
+        })
+    }
+}
+
+fn parse_percentage(input: &str) -> Result<u32> {
+    let amount = input.trim().strip_suffix('%').ok_or_else(|| anyhow!("Expected a percentage like '50%', got '{}'", input))?;
+    match amount.trim().parse::<u32>() {
+        Ok(0) => bail!("The percentage must be greater than 0"),
+        Ok(percentage) => Ok(percentage),
+        Err(_) => bail!("Expected a percentage like '50%', got '{}'", input),
+    }
+}
+
+/// I/O scheduling class and priority, like `ionice`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum IoPriority {
+    Realtime(u8),
+    BestEffort(u8),
+    Idle,
+}
+
+impl IoPriority {
+    /// `idle`, `best-effort` or `realtime`, with an optional priority from 0 (highest) to 7, e.g. `best-effort:7`
+    pub fn parse(input: &str) -> Result<Self> {
+        let (class, level) = match input.trim().split_once(':') {
+            Some((class, level)) => {
+                let level: u8 = level.trim().parse().map_err(|_| anyhow!("Invalid ionice priority '{}'", level))?;
+                if level > 7 {
+                    bail!("The ionice priority must be between 0 and 7");
+                }
+                (class.trim(), Some(level))
+            }
+            None => (input.trim(), None),
+        };
+
+        match (class, level) {
+            ("realtime", level) => Ok(IoPriority::Realtime(level.unwrap_or(4))),
+            ("best-effort", level) => Ok(IoPriority::BestEffort(level.unwrap_or(4))),
+            ("idle", None) => Ok(IoPriority::Idle),
+            ("idle", Some(_)) => bail!("The idle ionice class has no priority"),
+            (other, _) => bail!("Unknown ionice class '{}', expected idle, best-effort or realtime", other),
+        }
+    }
+
+    /// Name of the class, as accepted by [IoPriority::parse]
+    pub fn class(self) -> &'static str {
+        match self {
+            IoPriority::Realtime(_) => "realtime",
+            IoPriority::BestEffort(_) => "best-effort",
+            IoPriority::Idle => "idle",
+        }
+    }
+
+    pub fn level(self) -> Option<u8> {
+        match self {
+            IoPriority::Realtime(level) | IoPriority::BestEffort(level) => Some(level),
+            IoPriority::Idle => None,
+        }
+    }
+
+    /// Value for ioprio_set, the class goes in the upper bits
+    #[cfg(target_os = "linux")]
+    fn ioprio(self) -> libc::c_int {
+        const CLASS_SHIFT: libc::c_int = 13;
+        match self {
+            IoPriority::Realtime(level) => (1 << CLASS_SHIFT) | level as libc::c_int,
+            IoPriority::BestEffort(level) => (2 << CLASS_SHIFT) | level as libc::c_int,
+            IoPriority::Idle => 3 << CLASS_SHIFT,
+        }
+    }
+}
+
+/// CPU and I/O priority of the processes of a task
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProcessPriority {
+    pub nice: Option<i32>,
+    pub ionice: Option<IoPriority>,
+    /// CPUs the task can run on
+    pub cpu_affinity: Vec<usize>,
+}
+
+impl ProcessPriority {
+    pub fn parse(nice: Option<i32>, ionice: Option<&str>, cpu_affinity: Option<&str>) -> Result<Self> {
+        if let Some(nice) = nice {
+            if !(-20..=19).contains(&nice) {
+                bail!("Malformed field: nice, it must be between -20 and 19");
+            }
+        }
+
+        Ok(Self {
+            nice,
+            ionice: ionice.map(IoPriority::parse).transpose().context("Malformed field: ionice")?,
+            cpu_affinity: cpu_affinity.map(parse_cpu_list).transpose().context("Malformed field: cpu_affinity")?.unwrap_or_default(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Sets the priority of the current process, called in the child between fork and exec. `cpu_set` is built
+    /// before the fork from `cpu_affinity` because it can't allocate here
+    #[cfg(target_os = "linux")]
+    pub fn apply(&self, cpu_set: Option<&libc::cpu_set_t>) -> io::Result<()> {
+        let check = |result: libc::c_long| if result < 0 { Err(io::Error::last_os_error()) } else { Ok(()) };
+
+        if let Some(nice) = self.nice {
+            // SAFETY: setpriority only changes the scheduling of this process
+            check(unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, nice) } as libc::c_long)?;
+        }
+        if let Some(ionice) = self.ionice {
+            const IOPRIO_WHO_PROCESS: libc::c_int = 1;
+            // SAFETY: ioprio_set has no wrapper in libc, the arguments match the ones of the syscall
+            check(unsafe { libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0, ionice.ioprio()) })?;
+        }
+        if let Some(cpu_set) = cpu_set {
+            // SAFETY: the cpu_set_t is valid for the duration of the call
+            check(unsafe { libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), cpu_set) } as libc::c_long)?;
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn cpu_set(&self) -> Option<libc::cpu_set_t> {
+        if self.cpu_affinity.is_empty() {
+            return None;
+        }
+        // SAFETY: cpu_set_t is a bit mask, all zeroes is an empty set
+        let mut cpu_set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+        for cpu in &self.cpu_affinity {
+            // SAFETY: CPU_SET ignores the CPUs that don't fit in the set
+            unsafe { libc::CPU_SET(*cpu, &mut cpu_set) };
+        }
+        Some(cpu_set)
+    }
+}
+
+/// Lists of CPUs like the ones of `taskset -c`, e.g. `0-3,6`
+/// Size of the `cpu_set_t` of sched_setaffinity, cpu_affinity is only applied on Linux
+#[cfg(target_os = "linux")]
+const MAX_CPUS: usize = libc::CPU_SETSIZE as usize;
+#[cfg(not(target_os = "linux"))]
+const MAX_CPUS: usize = 1024;
+
+fn parse_cpu_list(input: &str) -> Result<Vec<usize>> {
+    let mut cpus = vec![];
+    for part in input.split(',').map(str::trim) {
+        let parse = |cpu: &str| cpu.trim().parse::<usize>().map_err(|_| anyhow!("Invalid CPU '{}' in '{}'", cpu, input));
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse(start)?, parse(end)?);
+                if start > end {
+                    bail!("Invalid CPU range '{}'", part);
+                }
+                cpus.extend(start..=end);
+            }
+            None => cpus.push(parse(part)?),
+        }
+    }
+    if let Some(cpu) = cpus.iter().find(|cpu| **cpu >= MAX_CPUS) {
+        bail!("CPU {} is out of range", cpu);
+    }
+    Ok(cpus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cgroup_limits() {
+        let config = CgroupConfig { memory_max: Some("512 MB".to_string()), cpu_max: Some("150%".to_string()) };
+        let limits = CgroupLimits::parse(&config).unwrap();
+        assert_eq!(limits, CgroupLimits { memory_max: Some(512 << 20), cpu_max: Some(150) });
+
+        for cpu_max in ["0%", "50", "half"] {
+            let config = CgroupConfig { cpu_max: Some(cpu_max.to_string()), ..Default::default() };
+            assert!(CgroupLimits::parse(&config).is_err(), "{}", cpu_max);
+        }
+    }
+
+    #[test]
+    fn test_parse_process_priority() {
+        let priority = ProcessPriority::parse(Some(10), Some("best-effort:7"), Some("0-2, 5")).unwrap();
+        assert_eq!(priority.ionice, Some(IoPriority::BestEffort(7)));
+        assert_eq!(priority.cpu_affinity, vec![0, 1, 2, 5]);
+
+        assert_eq!(IoPriority::parse("idle").unwrap(), IoPriority::Idle);
+        assert!(IoPriority::parse("idle:3").is_err());
+        assert!(ProcessPriority::parse(Some(20), None, None).is_err());
+        assert!(ProcessPriority::parse(None, None, Some("3-1")).is_err());
+    }
+}
@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use crate::sqlite_logger::SqliteLoggerConfig;
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default, Ord, PartialOrd, Eq, PartialEq)]
+pub enum LogOutput {
+    #[serde(rename = "stdout")]
+    #[default]
+    Stdout,
+    #[serde(rename = "file")]
+    File,
+    #[serde(rename = "syslog")]
+    Syslog,
+    /// Native protocol of systemd-journald, with the task and the run id as fields of the entries
+    #[serde(rename = "journald")]
+    Journald,
+}
+
+/// Format of the lines of the `stdout` and `file` outputs
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Default, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    /// A JSON object for each line, with the timestamp, the level, the target, the message and the task fields
+    Json,
+}
+
+impl LogFormat {
+    fn is_text(&self) -> bool {
+        *self == LogFormat::Text
+    }
+}
+
+/// Rotation of the log file of `output: file`, done by the scheduler itself
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct LogRotationConfig {
+    /// The file is rotated before it grows past this size, e.g. `10 MB`
+    pub max_size: String,
+    /// Number of rotated files kept, `cron-rs.log.1` is the newest
+    #[serde(default = "default_max_files")]
+    pub max_files: usize,
+    /// Compress the rotated files with gzip
+    #[serde(default)]
+    pub compress: bool,
+}
+
+fn default_max_files() -> usize {
+    5
+}
+
+/// Settings of `output: syslog`, without them the records go to the local socket with the `user` facility
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct SyslogConfig {
+    #[serde(default)]
+    pub facility: SyslogFacility,
+    /// Remote server like `logs.example.com:514`, instead of the local socket
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub server: Option<String>,
+    /// How the records are sent to `server`
+    #[serde(default)]
+    pub protocol: SyslogProtocol,
+    #[serde(default)]
+    pub format: SyslogFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SyslogFacility {
+    #[default]
+    User,
+    Daemon,
+    Cron,
+    Local0,
+    Local1,
+    Local2,
+    Local3,
+    Local4,
+    Local5,
+    Local6,
+    Local7,
+}
+
+impl SyslogFacility {
+    /// Number of the facility in the priority of the messages
+    pub fn code(&self) -> u8 {
+        match self {
+            SyslogFacility::User => 1,
+            SyslogFacility::Daemon => 3,
+            SyslogFacility::Cron => 9,
+            SyslogFacility::Local0 => 16,
+            SyslogFacility::Local1 => 17,
+            SyslogFacility::Local2 => 18,
+            SyslogFacility::Local3 => 19,
+            SyslogFacility::Local4 => 20,
+            SyslogFacility::Local5 => 21,
+            SyslogFacility::Local6 => 22,
+            SyslogFacility::Local7 => 23,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SyslogProtocol {
+    #[default]
+    Udp,
+    /// Each message ends with a new line
+    Tcp,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SyslogFormat {
+    /// The BSD format, understood by every syslog daemon
+    #[default]
+    Rfc3164,
+    /// With the fields of the records, like the task and the run id, as structured data
+    Rfc5424,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct LoggingConfig {
+    pub output: LogOutput,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "LogFormat::is_text")]
+    pub format: LogFormat,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<LogRotationConfig>,
+    pub level: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sqlite: Option<SqliteLoggerConfig>,
+    /// JSON lines file with the events of the scheduler, see [crate::events::SchedulerEvent]
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub events: Option<PathBuf>,
+    /// Directory with a `{task}.log` file for each task, with the lines of the log about it
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_task_dir: Option<PathBuf>,
+    /// Facility, server and format of `output: syslog`
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub syslog: Option<SyslogConfig>,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            output: LogOutput::Stdout,
+            format: LogFormat::Text,
+            file: None,
+            rotation: None,
+            level: "info".to_string(),
+            sqlite: None,
+            events: None,
+            per_task_dir: None,
+            syslog: None,
+        }
+    }
+} 
\ No newline at end of file
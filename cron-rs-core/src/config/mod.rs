@@ -0,0 +1,1230 @@
+pub mod conditions;
+pub mod dayofweek;
+pub mod diff;
+pub mod digest;
+pub mod executor;
+pub mod file;
+pub mod format;
+pub mod limits;
+pub mod logging;
+pub mod period;
+pub mod pipeline;
+pub mod retention;
+pub mod scaffold;
+pub mod secrets;
+pub mod shorthand;
+pub mod success;
+pub mod timeunit;
+pub mod trigger;
+pub mod validation;
+pub mod window;
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, Timelike, Utc};
+use chrono_tz::Tz;
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete::{digit1, multispace0, space0};
+use nom::combinator::{all_consuming, map, map_res, opt};
+use nom::error::ParseError;
+use nom::sequence::{delimited, separated_pair};
+use nom::{error, AsChar, IResult, InputTakeAtPosition, Parser};
+
+use self::conditions::TaskConditions;
+use self::dayofweek::{DayOfWeek, DayOfWeekNumbering};
+use self::digest::Digest;
+use self::executor::Executor;
+use self::file::ExplodedTimePatternFieldConfig;
+use self::file::{ConfigFile, ExplodedTimePatternConfig, MisfirePolicy, OutputMode, StopSignal, TaskDefinition, TaskNetwork, TimePatternConfig};
+use self::limits::{CgroupLimits, ProcessPriority, ResourceLimits};
+use self::logging::LoggingConfig;
+use self::period::SchedulePeriod;
+use self::retention::OutputRetention;
+use self::success::SuccessCriteria;
+use self::timeunit::TimeUnit;
+use self::trigger::{AfterTaskOn, DEFAULT_DEBOUNCE};
+use self::window::TimeWindow;
+
+use log::warn;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::fs::{File, OpenOptions};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::alerts::{Alert, AlertConfig};
+use crate::plugins::PluginConfig;
+use crate::sentry::SentryConfig;
+use crate::utils::{parse_timezone, refresh_system_timezone, system_timezone};
+
+/// Documented config file with all the options, written by `cron-rs generate-config`
+pub const DEFAULT_CONFIG: &str = include_str!("default_config.yml");
+
+#[derive(Debug, Clone)]
+pub struct TaskConfig {
+    pub name: String,
+    pub cmd: String,
+    pub enabled: bool,
+    pub tags: Vec<String>,
+    pub schedule: Schedule,
+    pub timezone: Tz,
+    pub avoid_overlapping: bool,
+    /// Locked with flock during each execution, the runs are skipped while another process holds it
+    pub lock_file: Option<PathBuf>,
+    /// Url of the redis server where each execution takes the lock of the task, the runs are skipped while another
+    /// node holds it
+    pub cluster_lock: Option<String>,
+    pub conditions: TaskConditions,
+    pub run_as: Option<String>,
+    pub login_env: bool,
+    pub login_shell: bool,
+    /// The working directory is inside the chroot
+    pub chroot: Option<String>,
+    pub network: TaskNetwork,
+    pub executor: Executor,
+    pub time_limit: Option<u64>,
+    /// Seconds between SIGTERM and SIGKILL, see [DEFAULT_KILL_GRACE]
+    pub kill_grace: Option<u64>,
+    pub time_limit_signal: StopSignal,
+    pub limits: ResourceLimits,
+    /// Runs each execution in its own cgroup when set
+    pub cgroup: Option<CgroupLimits>,
+    /// nice, ionice and cpu_affinity
+    pub priority: ProcessPriority,
+    pub working_directory: Option<String>,
+    /// The secrets in the values are not read yet, see [TaskConfig::resolved_env]
+    pub env: Option<HashMap<String, String>>,
+    pub env_file: Option<String>,
+    pub shell: Option<String>,
+    pub stdin: Option<TaskStdin>,
+    pub stdout: Option<String>,
+    pub stderr: Option<String>,
+    pub output_dir: Option<String>,
+    pub output_mode: Option<OutputMode>,
+    pub log_output: bool,
+    /// stderr goes to the stdout file, `stderr` is always None
+    pub combine_output: bool,
+    pub success: SuccessCriteria,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+    pub on_failure_cmd: Option<String>,
+    pub max_runs: Option<u32>,
+    pub max_runs_only_successes: bool,
+    /// Runtime of the executions of a calendar month, in the timezone of the task, before the next ones are skipped
+    pub max_monthly_runtime: Option<Duration>,
+    pub misfire: MisfirePolicy,
+    pub on_failure: Vec<Alert>,
+    pub on_success: Vec<Alert>,
+}
+
+/// Standard input of the command, without it the command inherits the one of cron-rs
+#[derive(Debug, Clone, PartialEq)]
+pub enum TaskStdin {
+    File(String),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Config {
+    pub tasks: Vec<Arc<TaskConfig>>,
+    pub logging: LoggingConfig,
+    pub alerts: AlertConfig,
+    pub control_socket: Option<PathBuf>,
+    pub output_retention: Option<OutputRetention>,
+    pub digest: Option<Digest>,
+    pub cgroup_root: Option<PathBuf>,
+    pub period_stamp_dir: Option<PathBuf>,
+    pub metrics_file: Option<PathBuf>,
+    pub plugins: HashMap<String, PluginConfig>,
+    pub sentry: Option<SentryConfig>,
+}
+
+impl Config {
+    /// Path of the control socket used by `cron-rs ctl`
+    pub fn control_socket_path(&self) -> PathBuf {
+        self.control_socket
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(crate::control::DEFAULT_SOCKET_PATH))
+    }
+
+    /// Parent of the cgroups of the executions of tasks with `cgroup`
+    pub fn cgroup_root_path(&self) -> PathBuf {
+        self.cgroup_root
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(crate::cgroup::DEFAULT_CGROUP_ROOT))
+    }
+
+    /// Directory with the time of the last run of each task with a `period`, so restarts don't run them again
+    pub fn period_stamp_dir_path(&self) -> PathBuf {
+        self.period_stamp_dir
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(DEFAULT_PERIOD_STAMP_DIR))
+    }
+}
+
+/// Selects the tasks to run from the config file, an empty filter selects all tasks
+#[derive(Debug, Clone, Default)]
+pub struct TaskFilter {
+    pub tags: Vec<String>,
+    pub only: Vec<String>,
+}
+
+impl TaskFilter {
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty() && self.only.is_empty()
+    }
+
+    /// A task matches if its name is in the `only` list or it has any of the tags
+    pub fn matches(&self, task: &TaskConfig) -> bool {
+        if self.is_empty() {
+            return true;
+        }
+
+        self.only.contains(&task.name) || task.tags.iter().any(|tag| self.tags.contains(tag))
+    }
+
+    /// Removes from the config the tasks that don't match the filter
+    pub fn apply(&self, config: &mut Config) {
+        for name in &self.only {
+            if !config.tasks.iter().any(|t| &t.name == name) {
+                warn!("Task '{}' selected with --only not found in the config", name);
+            }
+        }
+
+        config.tasks.retain(|t| self.matches(t));
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Schedule {
+    Every { interval: Duration, aligned: bool, window: Option<TimeWindow> },
+    When { time: TimePattern, min_interval: Option<Duration> },
+    /// Once per calendar period, whenever the scheduler is running, see [Config::period_stamp_dir_path]
+    Period { period: SchedulePeriod },
+    /// When the files change, once they stop changing for `debounce`
+    OnChange { paths: Vec<PathBuf>, debounce: Duration },
+    /// When an execution of another task finishes with the given result
+    AfterTask { task: String, on: AfterTaskOn },
+}
+
+impl Schedule {
+    /// Runs when something happens instead of at given times, it has no next execution time until then
+    pub fn is_triggered(&self) -> bool {
+        matches!(self, Schedule::OnChange { .. } | Schedule::AfterTask { .. })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TimePattern {
+    pub second: TimePatternField,
+    pub minute: TimePatternField,
+    pub hour: TimePatternField,
+    pub day_of_week: TimePatternField,
+    pub day: TimePatternField,
+    pub month: TimePatternField,
+    pub year: TimePatternField,
+}
+
+// OnCalendar=[Mon,Tue] *-*/2-01..04 12:00:00
+
+#[derive(Debug, Clone)]
+pub enum TimePatternField {
+    Any,             // * or missing
+    Value(u32),      // 12
+    Range(u32, u32), // 01..04 or 01..=04
+    List(Vec<u32>),  // [Mon,Tue]
+    Ratio(u32, u32), // */5+2
+}
+
+/// Parses the tasks and the options of the config. The timezone of the system is read again, so a reload follows its
+/// changes
+pub fn parse_config_file(file: &ConfigFile) -> Result<Config> {
+    refresh_system_timezone();
+    let mut tasks: Vec<Arc<TaskConfig>> = Vec::with_capacity(file.tasks.len());
+
+    for (i, config) in file.resolved_tasks()?.iter().enumerate() {
+        let task = TaskConfig::parse(config)
+            .and_then(|task| task.with_day_of_week_numbering(file.day_of_week_numbering.unwrap_or_default()))
+            .context(format!("Malformed task '{}' at position {}", &config.name, i + 1))?;
+        tasks.push(Arc::new(task));
+    }
+
+    // After the tasks, the steps can refer to them
+    let mut pipeline_tasks = Vec::with_capacity(file.pipelines.len());
+    for pipeline in &file.pipelines {
+        let task = pipeline
+            .parse(file, &tasks)
+            .and_then(|task| task.with_day_of_week_numbering(file.day_of_week_numbering.unwrap_or_default()))
+            .context(format!("Malformed pipeline '{}'", pipeline.name))?;
+        pipeline_tasks.push(Arc::new(task));
+    }
+    tasks.extend(pipeline_tasks);
+
+    let logging_config = file.logging.clone().unwrap_or_default();
+    let mut alerts = file.alerts.clone().unwrap_or_default();
+    alerts.on_failure = file.resolve_alerts(&alerts.on_failure).context("Malformed field: alerts.on_failure")?;
+    alerts.on_success = file.resolve_alerts(&alerts.on_success).context("Malformed field: alerts.on_success")?;
+    alerts.on_internal_error =
+        file.resolve_alerts(&alerts.on_internal_error).context("Malformed field: alerts.on_internal_error")?;
+    alerts.on_clock_change = file.resolve_alerts(&alerts.on_clock_change).context("Malformed field: alerts.on_clock_change")?;
+    alerts.on_budget_exhausted =
+        file.resolve_alerts(&alerts.on_budget_exhausted).context("Malformed field: alerts.on_budget_exhausted")?;
+    let escalation: Vec<Alert> = alerts.escalation.iter().map(|step| step.alert.clone()).collect();
+    let escalation = file.resolve_alerts(&escalation).context("Malformed field: alerts.escalation")?;
+    for (step, alert) in alerts.escalation.iter_mut().zip(escalation) {
+        step.alert = alert;
+    }
+    alerts.check_escalation().context("Malformed field: alerts.escalation")?;
+
+    Ok(Config {
+        tasks,
+        logging: logging_config,
+        alerts,
+        control_socket: file.control_socket.clone(),
+        cgroup_root: file.cgroup_root.clone(),
+        period_stamp_dir: file.period_stamp_dir.clone(),
+        metrics_file: file.metrics_file.clone(),
+        output_retention: file.output_retention.as_ref().map(OutputRetention::parse).transpose()?,
+        digest: file.digest.as_ref().map(|digest| Digest::parse(digest, file)).transpose()?,
+        plugins: file.plugins.clone(),
+        sentry: file.sentry.clone(),
+    })
+}
+
+impl TaskConfig {
+    /// Reads the numbers of the days of the week of the schedule with the numbering of the config file, [TaskConfig::parse]
+    /// reads them like cron
+    pub fn with_day_of_week_numbering(mut self, numbering: DayOfWeekNumbering) -> Result<Self> {
+        match &mut self.schedule {
+            Schedule::When { time, .. } => {
+                time.day_of_week = numbering.normalize(&time.day_of_week).context("Malformed field: day_of_week")?;
+            }
+            Schedule::Every { window: Some(window), .. } => {
+                window.days = numbering.normalize(&window.days).context("Malformed field: on")?;
+            }
+            Schedule::Every { window: None, .. } | Schedule::Period { .. } | Schedule::OnChange { .. } | Schedule::AfterTask { .. } => {}
+        }
+        Ok(self)
+    }
+
+    pub fn parse(config: &TaskDefinition) -> Result<Self> {
+        if config.align && config.every.is_none() {
+            bail!("Task '{}' defines 'align' without 'every'.", config.name);
+        }
+
+        crate::template::check_cmd(&config.cmd).context("Malformed field: cmd")?;
+
+        if config.when.is_some() && config.every.is_some() {
+            bail!(
+                "Task '{}' defines both 'when' and 'every'. Only one is allowed.",
+                config.name
+            );
+        }
+
+        if config.period.is_some() && (config.when.is_some() || config.every.is_some()) {
+            bail!("Task '{}' defines 'period' with 'when' or 'every'. Only one is allowed.", config.name);
+        }
+
+        if config.on_change.is_some() && (config.when.is_some() || config.every.is_some() || config.period.is_some()) {
+            bail!("Task '{}' defines 'on_change' with 'when', 'every' or 'period'. Only one is allowed.", config.name);
+        }
+
+        let other_schedules = [config.when.is_some(), config.every.is_some(), config.period.is_some(), config.on_change.is_some()];
+        if config.after_task.is_some() && other_schedules.contains(&true) {
+            bail!("Task '{}' defines 'after_task' with another schedule. Only one is allowed.", config.name);
+        }
+
+        let schedule = if let Some(when) = &config.when {
+            if config.between.is_some() || config.on.is_some() {
+                bail!(
+                    "Task '{}' defines 'between' or 'on' with 'when'. They can only be used with 'every'.",
+                    config.name
+                );
+            }
+            Schedule::parse_when(when, config.min_interval.as_deref())?
+        } else if let Some(every) = &config.every {
+            if config.min_interval.is_some() {
+                bail!(
+                    "Task '{}' defines 'min_interval' with 'every'. It can only be used with 'when'.",
+                    config.name
+                );
+            }
+            Schedule::parse_every(every.as_str(), config.align, config.between.as_deref(), config.on.as_ref())?
+        } else if let Some(period) = config.period {
+            if config.between.is_some() || config.on.is_some() || config.min_interval.is_some() {
+                bail!(
+                    "Task '{}' defines 'between', 'on' or 'min_interval' with 'period'. They can't be used together.",
+                    config.name
+                );
+            }
+            Schedule::Period { period }
+        } else if let Some(on_change) = &config.on_change {
+            if config.between.is_some() || config.on.is_some() || config.min_interval.is_some() {
+                bail!(
+                    "Task '{}' defines 'between', 'on' or 'min_interval' with 'on_change'. They can't be used together.",
+                    config.name
+                );
+            }
+            if on_change.paths.is_empty() {
+                bail!("Task '{}' defines 'on_change' without 'paths'.", config.name);
+            }
+            let debounce = match &on_change.debounce {
+                Some(debounce) => Schedule::parse_time_duration(debounce).context("Malformed field: on_change.debounce")?.0,
+                None => DEFAULT_DEBOUNCE,
+            };
+            // Relative to the working directory, like the paths of only_if
+            let paths = on_change
+                .paths
+                .iter()
+                .map(|path| match &config.working_directory {
+                    Some(dir) => Path::new(dir).join(path),
+                    None => PathBuf::from(path),
+                })
+                .collect();
+            Schedule::OnChange { paths, debounce }
+        } else if let Some(after_task) = &config.after_task {
+            if config.between.is_some() || config.on.is_some() || config.min_interval.is_some() {
+                bail!(
+                    "Task '{}' defines 'between', 'on' or 'min_interval' with 'after_task'. They can't be used together.",
+                    config.name
+                );
+            }
+            if after_task.name == config.name {
+                bail!("Task '{}' can't run after itself.", config.name);
+            }
+            Schedule::AfterTask { task: after_task.name.clone(), on: after_task.on }
+        } else {
+            bail!("No schedule specified for task '{}'", config.name);
+        };
+
+        let timezone = match &config.timezone {
+            Some(timezone_name) => parse_timezone(timezone_name)?,
+            None => system_timezone(),
+        };
+
+        let time_limit = if let Some(def) = &config.time_limit {
+            let duration = Schedule::parse_time_duration(def)?.0;
+            if duration.as_secs() < 1 {
+                warn!("Task '{}': cannot have a time limit of less than 1 second. Changed to 1 second", config.name);
+            }
+            Some(duration.as_secs().max(1))
+        } else {
+            None
+        };
+        let kill_grace = match &config.kill_grace {
+            Some(def) => Some(Schedule::parse_time_duration(def).context("Malformed field: kill_grace")?.0.as_secs()),
+            None => None,
+        };
+
+        if let Some(url) = &config.cluster_lock {
+            redis::Client::open(url.as_str()).context("Malformed field: cluster_lock")?;
+        }
+
+        let stdin = match (&config.stdin, &config.stdin_text) {
+            (Some(_), Some(_)) => bail!("Task '{}' defines both 'stdin' and 'stdin_text'. Only one is allowed.", config.name),
+            (Some(path), None) => Some(TaskStdin::File(path.clone())),
+            (None, Some(text)) => Some(TaskStdin::Text(text.clone())),
+            (None, None) => None,
+        };
+
+        let combine_output = config.combine_output.unwrap_or(false) || config.stderr.as_deref() == Some(STDERR_TO_STDOUT);
+        if combine_output && config.stderr.as_deref().is_some_and(|stderr| stderr != STDERR_TO_STDOUT) {
+            warn!("Task '{}': 'stderr' is ignored, 'combine_output' writes stderr to the stdout file", config.name);
+        }
+
+        Ok(Self {
+            name: config.name.clone(),
+            cmd: config.cmd.clone(),
+            enabled: config.enabled,
+            tags: config.tags.clone(),
+            schedule,
+            timezone,
+            avoid_overlapping: config.avoid_overlapping,
+            lock_file: config.lock_file.as_ref().map(PathBuf::from),
+            cluster_lock: config.cluster_lock.clone(),
+            conditions: TaskConditions::parse(config)?,
+            run_as: config.run_as.clone(),
+            // The profile of the user is read from the HOME of the login environment
+            login_env: config.login_env.unwrap_or(false) || config.login_shell.unwrap_or(false),
+            login_shell: config.login_shell.unwrap_or(false),
+            chroot: config.chroot.clone(),
+            network: config.network.unwrap_or_default(),
+            executor: Executor::parse(config.executor, config.docker.as_ref(), config.ssh.as_ref())?,
+            time_limit,
+            kill_grace,
+            time_limit_signal: config.time_limit_signal.unwrap_or_default(),
+            limits: config.limits.as_ref().map(ResourceLimits::parse).transpose()?.unwrap_or_default(),
+            cgroup: config.cgroup.as_ref().map(CgroupLimits::parse).transpose()?,
+            priority: ProcessPriority::parse(config.nice, config.ionice.as_deref(), config.cpu_affinity.as_deref())?,
+            shell: config.shell.clone(),
+            working_directory: config.working_directory.clone(),
+            env: config.env.clone(),
+            env_file: config.env_file.clone(),
+            stdin,
+            stdout: config.stdout.clone(),
+            stderr: if combine_output { None } else { config.stderr.clone() },
+            output_dir: config.output_dir.clone(),
+            output_mode: config.output_mode,
+            log_output: config.log_output.unwrap_or(false),
+            combine_output,
+            success: config.success.as_ref().map(SuccessCriteria::parse).transpose()?.unwrap_or_default(),
+            before: config.before.clone(),
+            after: config.after.clone(),
+            on_failure_cmd: config.on_failure_cmd.clone(),
+            max_runs: config.max_runs,
+            max_runs_only_successes: config.max_runs_only_successes,
+            max_monthly_runtime: config
+                .max_monthly_runtime
+                .as_deref()
+                .map(|runtime| Schedule::parse_time_duration(runtime).map(|(duration, _)| duration))
+                .transpose()
+                .context("Malformed field: max_monthly_runtime")?,
+            misfire: config.misfire.unwrap_or_default(),
+            on_failure: config.on_failure.clone(),
+            on_success: config.on_success.clone()
+        })
+    }
+}
+
+/// Time a task has to exit after SIGTERM when it exceeds its time limit, before it gets a SIGKILL
+pub const DEFAULT_KILL_GRACE: Duration = Duration::from_secs(10);
+
+/// Directory of the period stamps when the config doesn't set `period_stamp_dir`
+pub const DEFAULT_PERIOD_STAMP_DIR: &str = "./cron-rs_periods";
+
+/// Value of `stderr` that sends it to the stdout file, like `2>&1`
+pub const STDERR_TO_STDOUT: &str = "&stdout";
+
+/// Last year a `when` pattern can match
+pub const MAX_YEAR: u32 = 9999;
+
+/// Placeholders that can be used in the stdout and stderr paths
+pub const OUTPUT_PLACEHOLDERS: [&str; 5] = ["date", "time", "task", "run_id", "pid"];
+
+/// Points the `latest` file of `output_mode: per_run` to the file of the execution, `target` is relative to the link
+#[cfg(unix)]
+fn link_latest(target: &Path, _file_path: &Path, latest: &Path) -> std::io::Result<()> {
+    std::os::unix::fs::symlink(target, latest)
+}
+
+/// Symbolic links need the developer mode or admin rights on Windows, a hard link doesn't and shows the same content
+/// while the file is written
+#[cfg(windows)]
+fn link_latest(_target: &Path, file_path: &Path, latest: &Path) -> std::io::Result<()> {
+    std::fs::hard_link(file_path, latest)
+}
+
+/// Adds the label before the extension, `{stem}.{label}.{extension}`
+fn with_label(path: &Path, label: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}.{}.{}", stem, label, extension.to_string_lossy()),
+        None => format!("{}.{}", stem, label),
+    };
+    path.with_file_name(name)
+}
+
+impl TaskConfig {
+    pub fn kill_grace(&self) -> Duration {
+        self.kill_grace.map(Duration::from_secs).unwrap_or(DEFAULT_KILL_GRACE)
+    }
+
+    /// Environment of the executions, the `env_file` with the `env` on top and the secrets read. Read on each
+    /// execution, so the changes to the files apply
+    pub fn resolved_env(&self) -> Result<HashMap<String, String>> {
+        let mut env = match &self.env_file {
+            Some(path) => secrets::read_env_file(Path::new(path))?,
+            None => HashMap::new(),
+        };
+        if let Some(task_env) = &self.env {
+            env.extend(secrets::resolve_secrets(task_env).with_context(|| format!("Task '{}'", self.name))?);
+        }
+        Ok(env)
+    }
+
+    /// The `env` for the exporters, sorted. The secrets are left out, they are only read when cron-rs runs the task
+    pub fn exported_env(&self) -> Vec<(&String, &String)> {
+        let mut vars: Vec<_> = self.env.iter().flatten().collect();
+        vars.sort();
+        let (secrets, vars): (Vec<_>, Vec<_>) = vars.into_iter().partition(|(_, value)| secrets::secret_reference(value).is_some());
+        if !secrets.is_empty() {
+            let keys: Vec<&str> = secrets.iter().map(|(key, _)| key.as_str()).collect();
+            warn!("Task '{}': the secrets in env are not exported: {}", self.name, keys.join(", "));
+        }
+        vars
+    }
+
+    /// File where the stdout of the execution is stored, by default `.tmp/{task_name}_{run_id}_stdout.log`
+    pub fn stdout_path(&self, run_id: &str, start_time: DateTime<Utc>) -> PathBuf {
+        self.output_path(self.stdout.as_deref(), run_id, start_time, "stdout")
+    }
+
+    /// File where the stderr of the execution is stored, by default `.tmp/{task_name}_{run_id}_stderr.log`.
+    /// The stdout file with `combine_output`
+    pub fn stderr_path(&self, run_id: &str, start_time: DateTime<Utc>) -> PathBuf {
+        if self.combine_output {
+            return self.stdout_path(run_id, start_time);
+        }
+        self.output_path(self.stderr.as_deref(), run_id, start_time, "stderr")
+    }
+
+    /// Stdin for the command, with `stdin_text` it's a pipe where the text must be written once the command starts
+    pub fn open_stdin(&self) -> std::io::Result<Stdio> {
+        match &self.stdin {
+            None => Ok(Stdio::inherit()),
+            Some(TaskStdin::File(path)) => File::open(path).map(Stdio::from),
+            Some(TaskStdin::Text(_)) => Ok(Stdio::piped()),
+        }
+    }
+
+    /// Opens the stdout file of the execution as the output mode requires
+    pub fn open_stdout(&self, run_id: &str, start_time: DateTime<Utc>) -> std::io::Result<File> {
+        self.open_output(self.stdout.as_deref(), run_id, start_time, "stdout")
+    }
+
+    /// Opens the stderr file of the execution as the output mode requires, with `combine_output` the stdout file must
+    /// be shared instead so both streams write at the same position
+    pub fn open_stderr(&self, run_id: &str, start_time: DateTime<Utc>) -> std::io::Result<File> {
+        self.open_output(self.stderr.as_deref(), run_id, start_time, "stderr")
+    }
+
+    /// Replaces the placeholders of an explicit output path, dates use the timezone of the task.
+    /// The file is created before the task starts, so `{pid}` is the PID of cron-rs
+    fn expand_output_path(&self, path: &str, run_id: &str, start_time: DateTime<Utc>) -> String {
+        let start_time = start_time.with_timezone(&self.timezone);
+        path.replace("{date}", &start_time.format("%Y-%m-%d").to_string())
+            .replace("{time}", &start_time.format("%H-%M-%S").to_string())
+            .replace("{task}", &sanitise_file_name::sanitise(&self.name))
+            .replace("{run_id}", run_id)
+            .replace("{pid}", &std::process::id().to_string())
+    }
+
+    /// Explicit files are overwritten by default, the default ones are per run so concurrent executions don't share them
+    fn output_mode(&self, path: Option<&str>) -> OutputMode {
+        match (self.output_mode, path) {
+            (Some(mode), _) => mode,
+            (None, Some(_)) => OutputMode::Truncate,
+            (None, None) => OutputMode::PerRun,
+        }
+    }
+
+    /// With `per_run` the label, the run id or `latest`, is added to the file name:
+    /// `.tmp/{task_name}_{label}_stdout.log` for the default files or `{stem}.{label}.{extension}` for explicit ones.
+    /// Explicit paths that contain `{run_id}` already use the label there
+    fn output_path(&self, path: Option<&str>, label: &str, start_time: DateTime<Utc>, stream: &str) -> PathBuf {
+        let per_run = self.output_mode(path) == OutputMode::PerRun;
+        match path {
+            Some(path) if per_run && !path.contains("{run_id}") => {
+                with_label(Path::new(&self.expand_output_path(path, label, start_time)), label)
+            }
+            Some(path) => PathBuf::from(self.expand_output_path(path, label, start_time)),
+            None => {
+                let dir = self.output_dir.as_deref().unwrap_or(".tmp");
+                let name = sanitise_file_name::sanitise(&self.name);
+                if per_run {
+                    PathBuf::from(dir).join(format!("{}_{}_{}.log", name, label, stream))
+                } else {
+                    PathBuf::from(dir).join(format!("{}_{}.log", name, stream))
+                }
+            }
+        }
+    }
+
+    /// Glob patterns that match the output files of all the executions of the task, in any output mode
+    pub fn output_patterns(&self) -> Vec<String> {
+        let mut patterns = self.stream_patterns(self.stdout.as_deref(), "stdout");
+        patterns.extend(self.stream_patterns(self.stderr.as_deref(), "stderr"));
+        patterns
+    }
+
+    fn stream_patterns(&self, path: Option<&str>, stream: &str) -> Vec<String> {
+        match path {
+            Some(path) => {
+                let mut pattern = glob::Pattern::escape(path);
+                for placeholder in OUTPUT_PLACEHOLDERS {
+                    pattern = pattern.replace(&format!("{{{}}}", placeholder), "*");
+                }
+                vec![with_label(Path::new(&pattern), "*").to_string_lossy().to_string(), pattern]
+            }
+            None => {
+                let dir = glob::Pattern::escape(self.output_dir.as_deref().unwrap_or(".tmp"));
+                let name = glob::Pattern::escape(&sanitise_file_name::sanitise(&self.name));
+                vec![format!("{}/{}_*_{}.log", dir, name, stream), format!("{}/{}_{}.log", dir, name, stream)]
+            }
+        }
+    }
+
+    /// The file `stream`, `stdout` or `stderr`, of the last execution of the task: the `latest` link in `per_run` mode
+    /// or the file of the output path. When placeholders give each execution its own file, the newest one that matches
+    /// the path. None if the task hasn't written it yet
+    pub fn latest_output_path(&self, stream: &str) -> Option<PathBuf> {
+        let path = if stream == "stdout" { self.stdout.as_deref() } else { self.stderr.as_deref() };
+        let label = if self.output_mode(path) == OutputMode::PerRun { "latest" } else { "" };
+        let current = self.output_path(path, label, Utc::now(), stream);
+        if current.is_file() {
+            return Some(current);
+        }
+
+        let mut newest: Option<(std::time::SystemTime, PathBuf)> = None;
+        for pattern in self.stream_patterns(path, stream) {
+            for file in glob::glob(&pattern).into_iter().flatten().flatten() {
+                let Ok(modified) = std::fs::metadata(&file).and_then(|metadata| metadata.modified()) else { continue };
+                if newest.as_ref().is_none_or(|(time, _)| modified > *time) {
+                    newest = Some((modified, file));
+                }
+            }
+        }
+        newest.map(|(_, file)| file)
+    }
+
+    fn open_output(&self, path: Option<&str>, run_id: &str, start_time: DateTime<Utc>, stream: &str) -> std::io::Result<File> {
+        let file_path = self.output_path(path, run_id, start_time, stream);
+        match self.output_mode(path) {
+            OutputMode::Truncate => File::create(&file_path),
+            OutputMode::Append => OpenOptions::new().create(true).append(true).open(&file_path),
+            OutputMode::PerRun => {
+                let file = File::create(&file_path)?;
+
+                // Relative link when both files are in the same directory, placeholders in the directories can
+                // put them in different ones
+                let latest = self.output_path(path, "latest", start_time, stream);
+                let target = if latest.parent() == file_path.parent() {
+                    PathBuf::from(file_path.file_name().unwrap_or_default())
+                } else {
+                    if let Some(parent) = latest.parent() {
+                        let _ = std::fs::create_dir_all(parent);
+                    }
+                    std::path::absolute(&file_path)?
+                };
+                let _ = std::fs::remove_file(&latest);
+                if let Err(e) = link_latest(&target, &file_path, &latest) {
+                    warn!("Task '{}': failed to update the link {}: {}", self.name, latest.display(), e);
+                }
+                Ok(file)
+            }
+        }
+    }
+
+    /// The calendar pattern equivalent to the schedule, aligned intervals are converted into the times of the day they
+    /// run at. None if the task runs at intervals from its last execution
+    pub fn calendar_pattern(&self) -> Option<TimePattern> {
+        let (interval, window) = match &self.schedule {
+            Schedule::When { time, .. } => return Some(time.clone()),
+            Schedule::Period { period } => return Some(period.calendar_pattern()),
+            Schedule::Every { aligned: false, .. } | Schedule::OnChange { .. } | Schedule::AfterTask { .. } => return None,
+            Schedule::Every { interval, window, .. } => (interval, window),
+        };
+
+        let secs = interval.as_secs();
+        let step = |n: u64| if n == 1 { TimePatternField::Any } else { TimePatternField::Ratio(n as u32, 0) };
+        let mut time = TimePattern {
+            second: TimePatternField::Value(0),
+            minute: TimePatternField::Value(0),
+            hour: TimePatternField::Value(0),
+            day_of_week: TimePatternField::Any,
+            day: TimePatternField::Any,
+            month: TimePatternField::Any,
+            year: TimePatternField::Any,
+        };
+
+        let minutes = secs / 60;
+        let hours = minutes / 60;
+        if secs == 0 {
+            return None;
+        } else if secs < 60 && 60 % secs == 0 {
+            time.second = step(secs);
+            time.minute = TimePatternField::Any;
+            time.hour = TimePatternField::Any;
+        } else if secs % 60 == 0 && minutes < 60 && 60 % minutes == 0 {
+            time.minute = step(minutes);
+            time.hour = TimePatternField::Any;
+        } else if minutes % 60 == 0 && hours < 24 && 24 % hours == 0 {
+            time.hour = step(hours);
+        } else if secs == 7 * 24 * 3600 {
+            time.day_of_week = TimePatternField::Value(1);
+        } else if secs != 24 * 3600 {
+            warn!("Task '{}': the interval doesn't fit in a day, it can't be aligned to the clock", self.name);
+            return None;
+        }
+
+        if let Some(window) = window {
+            if matches!(time.day_of_week, TimePatternField::Any) {
+                time.day_of_week = window.days.clone();
+            } else if !matches!(window.days, TimePatternField::Any) {
+                warn!("Task '{}': the 'on' days are ignored for weekly tasks", self.name);
+            }
+
+            let whole_hours = window.start.minute() == 0 && window.start.second() == 0;
+            let end_hour = if window.end.minute() == 0 && window.end.second() == 0 {
+                window.end.hour().checked_sub(1)
+            } else {
+                Some(window.end.hour())
+            };
+            let is_full_day = window.start == NaiveTime::MIN && end_hour == Some(23);
+
+            match end_hour {
+                _ if is_full_day => {}
+                Some(end_hour) if whole_hours && window.start.hour() <= end_hour && matches!(time.hour, TimePatternField::Any) => {
+                    time.hour = TimePatternField::Range(window.start.hour(), end_hour);
+                }
+                _ => warn!("Task '{}': the 'between' time range can't be represented, it's ignored", self.name),
+            }
+        }
+
+        Some(time)
+    }
+}
+
+impl Schedule {
+    pub(crate) fn parse_time_duration(input: &str) -> Result<(Duration, bool)> {
+        pub fn parse_line<'s>() -> impl FnMut(&'s str) -> IResult<&'s str, (u32, TimeUnit, bool), error::Error<&'s str>>
+        {
+            move |input: &str| {
+                let (input, _) = space0.parse(input)?;
+                let (input, o2) = separated_pair(number, space0, TimeUnit::parse).parse(input)?;
+                let (input, _) = space0.parse(input)?;
+                let (input, o3) = opt(tag("aligned")).parse(input)?;
+
+                Ok((input, (o2.0, o2.1, o3.is_some())))
+            }
+        }
+
+        let result = all_consuming(parse_line())(input);
+
+        let (amount, unit, aligned) = result.map_err(|e| anyhow!("Failed to parse: {}", e))?.1;
+        let interval = unit.to_duration(amount);
+
+        Ok((interval, aligned))
+    }
+
+    fn parse_every(
+        input: &str,
+        align: bool,
+        between: Option<&str>,
+        on: Option<&ExplodedTimePatternFieldConfig>,
+    ) -> Result<Self> {
+        let (interval, aligned) = Self::parse_time_duration(input)?;
+        let window = Self::parse_window(between, on)?;
+        Ok(Self::Every { interval, aligned: aligned || align, window })
+    }
+
+    fn parse_window(between: Option<&str>, on: Option<&ExplodedTimePatternFieldConfig>) -> Result<Option<TimeWindow>> {
+        if between.is_none() && on.is_none() {
+            return Ok(None);
+        }
+
+        let (start, end) = match between {
+            Some(between) => TimeWindow::parse_between(between).context("Malformed field: between")?,
+            None => (NaiveTime::MIN, NaiveTime::from_hms_opt(23, 59, 59).unwrap()),
+        };
+
+        let days = match on {
+            Some(on) => {
+                let days = TimePatternField::parse_exploded_field(on, true).context("Malformed field: on")?;
+                let days = dayofweek::sunday_ends_ranges(days);
+                days.check_range(0, 7).context("Malformed field: on")?;
+                days
+            }
+            None => TimePatternField::Any,
+        };
+
+        Ok(Some(TimeWindow { start, end, days }))
+    }
+
+    fn parse_when(config: &TimePatternConfig, min_interval: Option<&str>) -> Result<Self> {
+        let time = match config {
+            TimePatternConfig::Short(s) => TimePattern::parse_short(s)?,
+            TimePatternConfig::Long(c) => TimePattern::parse_long(c)?,
+        };
+        let min_interval = match min_interval {
+            Some(def) => Some(Self::parse_time_duration(def).context("Malformed field: min_interval")?.0),
+            None => None,
+        };
+        Ok(Schedule::When { time, min_interval })
+    }
+}
+
+impl TimePattern {
+    /// Checks the year, month, day and day of the week of the pattern
+    pub fn matches_date(&self, date: NaiveDate) -> bool {
+        self.year.matches_value(date.year() as u32)
+            && self.month.matches_value(date.month())
+            && self.day.matches_value(date.day())
+            && dayofweek::matches_weekday(&self.day_of_week, date.weekday())
+    }
+
+    fn parse_short(config: &str) -> Result<Self> {
+        let mut pattern = shorthand::parse_shorthand(config)?;
+        pattern.day_of_week = dayofweek::sunday_ends_ranges(pattern.day_of_week);
+        pattern.check_ranges()?;
+        Ok(pattern)
+    }
+
+    fn parse_long(config: &ExplodedTimePatternConfig) -> Result<Self> {
+        fn field(
+            opt: &Option<ExplodedTimePatternFieldConfig>,
+            allow_dow: bool,
+        ) -> Result<TimePatternField> {
+            if let Some(field) = opt {
+                TimePatternField::parse_exploded_field(field, allow_dow)
+            } else {
+                Ok(TimePatternField::Any)
+            }
+        }
+        fn field_second(
+            opt: &Option<ExplodedTimePatternFieldConfig>,
+            allow_dow: bool,
+        ) -> Result<TimePatternField> {
+            if let Some(field) = opt {
+                TimePatternField::parse_exploded_field(field, allow_dow)
+            } else {
+                Ok(TimePatternField::Value(0))
+            }
+        }
+
+        let mut pattern = TimePattern {
+            year: field(&config.year, false).context("Malformed field: year")?,
+            month: field(&config.month, false).context("Malformed field: month")?,
+            day: field(&config.day, false).context("Malformed field: day")?,
+            hour: field(&config.hour, false).context("Malformed field: hour")?,
+            minute: field(&config.minute, false).context("Malformed field: minute")?,
+            second: field_second(&config.second, false).context("Malformed field: second")?,
+            day_of_week: field(&config.day_of_week, true)
+                .context("Malformed field: day_of_week")?,
+        };
+        pattern.day_of_week = dayofweek::sunday_ends_ranges(pattern.day_of_week);
+        pattern.check_ranges()?;
+        Ok(pattern)
+    }
+
+    /// Checks that every value is inside the domain of its field, a value out of range would never match
+    fn check_ranges(&self) -> Result<()> {
+        self.second.check_range(0, 59).context("Malformed field: second")?;
+        self.minute.check_range(0, 59).context("Malformed field: minute")?;
+        self.hour.check_range(0, 23).context("Malformed field: hour")?;
+        self.day.check_range(1, 31).context("Malformed field: day")?;
+        self.month.check_range(1, 12).context("Malformed field: month")?;
+        self.year.check_range(1970, MAX_YEAR).context("Malformed field: year")?;
+        // Sunday can be 0 or 7
+        self.day_of_week.check_range(0, 7).context("Malformed field: day_of_week")?;
+        Ok(())
+    }
+}
+
+impl TimePatternField {
+    /// List of values, sorted and without duplicates so the next value is the first one that isn't smaller
+    pub fn list(mut values: Vec<u32>) -> Self {
+        values.sort();
+        values.dedup();
+        TimePatternField::List(values)
+    }
+
+    /// Checks that all the values of the field are between min and max, both included
+    pub fn check_range(&self, min: u32, max: u32) -> Result<()> {
+        let check = |value: u32| {
+            if value < min || value > max {
+                bail!("Value {} is out of range, must be between {} and {}", value, min, max);
+            }
+            Ok(())
+        };
+
+        match self {
+            TimePatternField::Any => Ok(()),
+            TimePatternField::Value(v) => check(*v),
+            TimePatternField::Range(start, end) => {
+                check(*start)?;
+                check(*end)?;
+                if start > end {
+                    bail!("Range {}..{} is empty, the start must not be greater than the end", start, end);
+                }
+                Ok(())
+            }
+            TimePatternField::List(values) => values.iter().try_for_each(|v| check(*v)),
+            TimePatternField::Ratio(divisor, offset) => {
+                if *divisor == 0 || *divisor > max {
+                    bail!("Step {} is out of range, must be between 1 and {}", divisor, max);
+                }
+                if *offset > max {
+                    bail!("Offset {} is out of range, must be at most {}", offset, max);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Checks if the field matches a given value
+    pub fn matches_value(&self, value: u32) -> bool {
+        match self {
+            TimePatternField::Any => true,
+            TimePatternField::Value(v) => value == *v,
+            TimePatternField::Range(start, end) => value >= *start && value <= *end,
+            TimePatternField::List(values) => values.contains(&value),
+            TimePatternField::Ratio(divisor, offset) => value >= *offset && (value - offset).is_multiple_of(*divisor),
+        }
+    }
+    
+    /// Returns the first value from `value` up to `max` that matches, None if there is none and the search must carry
+    /// over to the next unit, e.g. the next hour when no minute is left. It never wraps around to the start of the field,
+    /// so it also works for the year, which doesn't repeat: a year range that ended matches nothing
+    pub fn get_next_valid_value(&self, value: u32, max: u32) -> Option<u32> {
+        let next = match self {
+            TimePatternField::Any => value,
+            TimePatternField::Value(v) => Some(*v).filter(|v| *v >= value)?,
+            TimePatternField::Range(start, end) => Some(value.max(*start)).filter(|v| v <= end)?,
+            TimePatternField::List(values) => *values.iter().find(|v| **v >= value)?,
+            TimePatternField::Ratio(divisor, offset) => {
+                let from = value.max(*offset);
+                from + (divisor - (from - offset) % divisor) % divisor
+            }
+        };
+        Some(next).filter(|next| *next <= max)
+    }
+    
+    pub fn parse_exploded_field(
+        config: &ExplodedTimePatternFieldConfig,
+        allow_dow: bool,
+    ) -> Result<Self> {
+        match config {
+            ExplodedTimePatternFieldConfig::Number(n) => Ok(TimePatternField::Value(*n)),
+            ExplodedTimePatternFieldConfig::Text(s) => {
+                Self::parse_exploded_text_field(s, allow_dow)
+            }
+            ExplodedTimePatternFieldConfig::List(list) => {
+                Self::parse_exploded_list_field(list, allow_dow)
+            }
+        }
+    }
+
+    fn parse_exploded_list_field(input: &Vec<String>, allow_dow: bool) -> Result<Self> {
+        let mut output: Vec<u32> = Vec::with_capacity(input.len());
+        for s in input {
+            // Items can be single values or ranges, like [Mon..Wed, Fri]
+            let res = all_consuming(ws(alt((
+                map(shorthand::range(allow_dow), Some),
+                map(time_atom(allow_dow), |n| Some(TimePatternField::Value(n))),
+            ))))(s);
+            match res.map_err(|e| anyhow!("{}", e))? {
+                (_, Some(TimePatternField::Range(start, end))) => output.extend(start..=end),
+                (_, Some(TimePatternField::Value(n))) => output.push(n),
+                _ => {}
+            }
+        }
+        Ok(TimePatternField::list(output))
+    }
+
+    fn parse_exploded_text_field(i: &str, allow_dow: bool) -> Result<Self> {
+        let res = all_consuming(shorthand::single_field(allow_dow))(i);
+        let (_, field) = res.map_err(|e| anyhow!("{}", e))?;
+        Ok(field)
+    }
+}
+
+impl Display for TimePattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}-{}-{} {}:{}:{}",
+               &self.day_of_week,
+               &self.year,
+               &self.month,
+               &self.day,
+               &self.hour,
+               &self.minute,
+               &self.second
+        )
+    }
+}
+
+impl Display for TimePatternField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimePatternField::Any => write!(f, "*"),
+            TimePatternField::Value(v) => write!(f, "{}", v),
+            TimePatternField::Range(start, end) => write!(f,"{}..{}", start, end),
+            TimePatternField::List(values) => write!(f,"[{}]", values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(",")),
+            TimePatternField::Ratio(divisor, 0) => write!(f, "*/{}", divisor),
+            TimePatternField::Ratio(divisor, offset) => write!(f, "*/{}+{}", divisor, offset),
+        }
+    }
+}
+
+fn number(input: &str) -> IResult<&str, u32> {
+    map_res(digit1, str::parse::<u32>)(input)
+}
+
+fn number_or_daw(i: &str) -> IResult<&str, u32> {
+    alt((number, map(DayOfWeek::parse, DayOfWeek::to_u32)))(i)
+}
+
+fn time_atom<'a>(allow_dow: bool) -> impl FnMut(&'a str) -> IResult<&'a str, u32> {
+    match allow_dow {
+        true => number_or_daw,
+        false => number,
+    }
+}
+
+pub fn ws<I, O, E: ParseError<I>, F>(inner: F) -> impl FnMut(I) -> IResult<I, O, E>
+where
+    F: Parser<I, O, E>,
+    I: InputTakeAtPosition,
+    <I as InputTakeAtPosition>::Item: AsChar + Clone,
+{
+    delimited(multispace0, inner, multispace0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_task_filter() {
+        let yaml = "tasks:
+          - { name: backup, cmd: 'true', every: 1 hour, tags: [backup, prod] }
+          - { name: report, cmd: 'true', every: 1 hour, tags: [prod] }
+          - { name: cleanup, cmd: 'true', every: 1 hour }";
+        let config = parse_config_file(&serde_yml::from_str(yaml).unwrap()).unwrap();
+        let selected = |filter: TaskFilter| -> Vec<String> {
+            let mut config = config.clone();
+            filter.apply(&mut config);
+            config.tasks.iter().map(|t| t.name.clone()).collect()
+        };
+        let filter = |tags: &[&str], only: &[&str]| TaskFilter {
+            tags: tags.iter().map(|s| s.to_string()).collect(),
+            only: only.iter().map(|s| s.to_string()).collect(),
+        };
+
+        assert_eq!(selected(filter(&[], &[])), ["backup", "report", "cleanup"]);
+        assert_eq!(selected(filter(&["backup"], &[])), ["backup"]);
+        assert_eq!(selected(filter(&["prod"], &[])), ["backup", "report"]);
+        assert!(selected(filter(&["staging"], &[])).is_empty());
+        // A task is kept if it's selected by name or by any of the tags
+        assert_eq!(selected(filter(&[], &["cleanup", "missing"])), ["cleanup"]);
+        assert_eq!(selected(filter(&["backup"], &["cleanup"])), ["backup", "cleanup"]);
+    }
+
+    #[test]
+    fn test_latest_output_path() {
+        let dir = std::env::temp_dir().join(format!("cron-rs-test-latest-output-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let yaml = format!(
+            "tasks:
+  - {{ name: report, cmd: 'true', every: 1 hour, output_dir: '{0}' }}
+  - {{ name: export, cmd: 'true', every: 1 hour, stdout: '{0}/export-{{date}}.log' }}
+",
+            dir.display()
+        );
+        let config = parse_config_file(&serde_yml::from_str(&yaml).unwrap()).unwrap();
+        let (report, export) = (&config.tasks[0], &config.tasks[1]);
+        assert_eq!(report.latest_output_path("stdout"), None);
+
+        // The latest link of per_run
+        report.open_stdout("run-1", Utc::now()).unwrap();
+        report.open_stdout("run-2", Utc::now()).unwrap();
+        let latest = report.latest_output_path("stdout").unwrap();
+        assert_eq!(latest.file_name().unwrap(), "report_latest_stdout.log");
+        assert_eq!(std::fs::canonicalize(latest).unwrap(), std::fs::canonicalize(dir.join("report_run-2_stdout.log")).unwrap());
+        assert_eq!(report.latest_output_path("stderr"), None);
+
+        // A file of another day is found through the pattern of the path
+        std::fs::write(dir.join("export-2025-01-01.log"), "").unwrap();
+        assert_eq!(export.latest_output_path("stdout"), Some(dir.join("export-2025-01-01.log")));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_default_timezone() {
+        let yaml = "
+defaults: { timezone: utc }
+digest: { every: 1 day, alerts: [{ type: cmd, cmd: 'true' }] }
+tasks:
+  - { name: fleet, cmd: 'true', every: 1 hour }
+  - { name: local, cmd: 'true', every: 1 hour, timezone: local }
+  - { name: madrid, cmd: 'true', every: 1 hour, timezone: Europe/Madrid }
+";
+        let config = parse_config_file(&serde_yml::from_str(yaml).unwrap()).unwrap();
+        let timezones: Vec<Tz> = config.tasks.iter().map(|task| task.timezone).collect();
+        assert_eq!(timezones, vec![Tz::UTC, system_timezone(), chrono_tz::Europe::Madrid]);
+        // The digest follows the defaults too, nothing depends on the timezone of the host
+        assert_eq!(config.digest.unwrap().timezone, Tz::UTC);
+    }
+
+    #[test]
+    fn test_ratio_with_offset() {
+        let time = TimePattern::parse_short("* *-*-* *:*/5+2:00").unwrap();
+        assert!(matches!(time.minute, TimePatternField::Ratio(5, 2)));
+        assert_eq!(time.minute.to_string(), "*/5+2");
+        let parsed = TimePattern::parse_short(&time.to_string()).unwrap();
+        assert!(matches!(parsed.minute, TimePatternField::Ratio(5, 2)));
+
+        let matching: Vec<u32> = (0..60).filter(|v| time.minute.matches_value(*v)).collect();
+        assert_eq!(matching, [2, 7, 12, 17, 22, 27, 32, 37, 42, 47, 52, 57]);
+        assert_eq!(time.minute.get_next_valid_value(0, 59), Some(2));
+        assert_eq!(time.minute.get_next_valid_value(3, 59), Some(7));
+        assert_eq!(time.minute.get_next_valid_value(58, 59), None);
+
+        // Without offset the steps start at 0, even in fields that start at 1
+        let time = TimePattern::parse_short("* *-*/3-* 00:00:00").unwrap();
+        assert_eq!(time.month.to_string(), "*/3");
+        assert_eq!((1..=12).filter(|v| time.month.matches_value(*v)).collect::<Vec<_>>(), [3, 6, 9, 12]);
+    }
+
+    #[test]
+    fn test_get_next_valid_value() {
+        use TimePatternField::*;
+
+        let list = TimePattern::parse_short("* *-*-* *:[45, 5, 30, 5]:00").unwrap().minute;
+        assert!(matches!(&list, List(values) if values == &[5, 30, 45]));
+
+        let cases = [
+            (Any, [(0, Some(0)), (59, Some(59))]),
+            (Value(30), [(0, Some(30)), (31, None)]),
+            (Range(10, 20), [(5, Some(10)), (21, None)]),
+            (list.clone(), [(6, Some(30)), (46, None)]),
+            (Ratio(20, 0), [(1, Some(20)), (41, None)]),
+        ];
+        for (field, values) in cases {
+            for (value, next) in values {
+                assert_eq!(field.get_next_valid_value(value, 59), next, "{} from {}", field, value);
+            }
+            // The next value is always a match, and no value in between matches
+            for value in 0..=59 {
+                if let Some(next) = field.get_next_valid_value(value, 59) {
+                    assert!(field.matches_value(next) && !(value..next).any(|v| field.matches_value(v)), "{} from {}", field, value);
+                }
+            }
+        }
+
+        // Values past the end of the field carry over, like day 31 in a month of 30 days
+        assert_eq!(Value(31).get_next_valid_value(1, 30), None);
+        assert_eq!(list.get_next_valid_value(31, 40), None);
+    }
+
+    #[test]
+    fn test_day_of_week_numbering() {
+        use TimePatternField::*;
+
+        // 2024-01-01 was a Monday
+        let monday = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+        let week: Vec<NaiveDate> = (0..7).map(|i| monday + chrono::Duration::days(i)).collect();
+        let days = |time: &TimePattern| -> Vec<u32> {
+            week.iter().filter(|d| time.matches_date(**d)).map(|d| d.weekday().number_from_monday()).collect()
+        };
+
+        let time = TimePattern::parse_short("[Mon,Tue] *-*-* 00:00:00").unwrap();
+        assert_eq!(days(&time), [1, 2]);
+        let time = TimePattern::parse_short("[0,7] *-*-* 00:00:00").unwrap();
+        assert_eq!(days(&time), [7]);
+        let time = TimePattern::parse_short("Fri..Sun *-*-* 00:00:00").unwrap();
+        assert_eq!(days(&time), [5, 6, 7]);
+
+        let numbering = DayOfWeekNumbering::Monday1;
+        let mut time = TimePattern::parse_short("Fri..Sun *-*-* 00:00:00").unwrap();
+        time.day_of_week = numbering.normalize(&time.day_of_week).unwrap();
+        assert!(matches!(time.day_of_week, Range(5, 7)));
+        assert_eq!(days(&time), [5, 6, 7]);
+
+        time.day_of_week = numbering.normalize(&Ratio(2, 0)).unwrap();
+        assert_eq!(days(&time), [2, 4, 6]);
+        assert!(numbering.normalize(&Range(0, 3)).is_err());
+    }
+}
@@ -0,0 +1,78 @@
+use chrono::{DateTime, Datelike, NaiveDate, NaiveTime, TimeDelta, TimeZone};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+use super::{TimePattern, TimePatternField};
+
+/// Calendar period of a `period` schedule, the task runs once in each of them whenever the machine is up, like anacron.
+/// Weeks start on Monday
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SchedulePeriod {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl SchedulePeriod {
+    /// First day of the period that contains the date
+    pub fn start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            SchedulePeriod::Daily => date,
+            SchedulePeriod::Weekly => date - TimeDelta::days(date.weekday().num_days_from_monday() as i64),
+            SchedulePeriod::Monthly => date.with_day(1).unwrap_or(date),
+        }
+    }
+
+    /// First day of the period after the one that contains the date
+    pub fn next_start(self, date: NaiveDate) -> NaiveDate {
+        match self {
+            SchedulePeriod::Daily => date + TimeDelta::days(1),
+            SchedulePeriod::Weekly => self.start(date) + TimeDelta::days(7),
+            SchedulePeriod::Monthly => self.start(self.start(date) + TimeDelta::days(31)),
+        }
+    }
+
+    /// True if both dates, in the same timezone, are in the same period
+    pub fn contains(self, date: DateTime<Tz>, other: DateTime<Tz>) -> bool {
+        self.start(date.date_naive()) == self.start(other.date_naive())
+    }
+
+    /// Midnight at the start of the next period, or the first time after it when midnight doesn't exist (DST gap)
+    pub fn next_start_time(self, date: DateTime<Tz>) -> DateTime<Tz> {
+        let start = self.next_start(date.date_naive()).and_time(NaiveTime::MIN);
+        (0..24)
+            .find_map(|hour| date.timezone().from_local_datetime(&(start + TimeDelta::hours(hour))).earliest())
+            .unwrap_or(date)
+    }
+
+    /// The start of each period as a calendar pattern, for the exporters
+    pub fn calendar_pattern(self) -> TimePattern {
+        TimePattern {
+            second: TimePatternField::Value(0),
+            minute: TimePatternField::Value(0),
+            hour: TimePatternField::Value(0),
+            day_of_week: match self {
+                SchedulePeriod::Weekly => TimePatternField::Value(1),
+                _ => TimePatternField::Any,
+            },
+            day: match self {
+                SchedulePeriod::Monthly => TimePatternField::Value(1),
+                _ => TimePatternField::Any,
+            },
+            month: TimePatternField::Any,
+            year: TimePatternField::Any,
+        }
+    }
+}
+
+impl Display for SchedulePeriod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchedulePeriod::Daily => write!(f, "daily"),
+            SchedulePeriod::Weekly => write!(f, "weekly"),
+            SchedulePeriod::Monthly => write!(f, "monthly"),
+        }
+    }
+}
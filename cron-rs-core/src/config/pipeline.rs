@@ -0,0 +1,247 @@
+use super::executor::Executor;
+use super::file::{default_true, ConfigFile, TaskDefinition, TimePatternConfig};
+use super::period::SchedulePeriod;
+use super::trigger::{AfterTaskConfig, AfterTaskOn};
+use super::TaskConfig;
+use crate::alerts::Alert;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Steps that run one after the other with a single schedule, as written in the config file
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PipelineDefinition {
+    pub name: String,
+    pub steps: Vec<PipelineStepConfig>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub when: Option<TimePatternConfig>,
+    #[serde(default)]
+    pub every: Option<String>,
+    #[serde(default)]
+    pub period: Option<SchedulePeriod>,
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Used by the `cmd` steps, and by the referenced tasks without their own
+    #[serde(default)]
+    pub working_directory: Option<String>,
+    /// Added to the environment of all the steps, the values of a referenced task take precedence
+    #[serde(default)]
+    pub env: Option<HashMap<String, String>>,
+    /// Sent once for the whole pipeline, after the step that fails or after the last one
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub on_failure: Vec<Alert>,
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub on_success: Vec<Alert>,
+}
+
+/// A step of a pipeline, one of the tasks, a command or a group of steps that run at the same time
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum PipelineStepConfig {
+    Task { task: String },
+    /// Named `<pipeline>.<name>`, or `<pipeline>.step-<position>` without a name
+    Cmd { cmd: String, name: Option<String> },
+    /// The group fails if any of its steps fails, once all of them finish
+    Parallel { parallel: Vec<PipelineStepConfig> },
+}
+
+/// Steps of a pipeline, the scheduler runs them as the execution of the pipeline task
+#[derive(Debug)]
+pub struct Pipeline {
+    /// The steps of a stage run at the same time, each stage starts once the previous one succeeds
+    pub stages: Vec<Vec<Arc<TaskConfig>>>,
+}
+
+impl PartialEq for Pipeline {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl PipelineDefinition {
+    /// The task that runs the pipeline, with the tasks of the config file the steps refer to
+    pub fn parse(&self, file: &ConfigFile, tasks: &[Arc<TaskConfig>]) -> Result<TaskConfig> {
+        if tasks.iter().any(|task| task.name == self.name) {
+            bail!("Pipeline '{}' has the name of a task, the names must be unique", self.name);
+        }
+        if self.steps.is_empty() {
+            bail!("Pipeline '{}' has no steps", self.name);
+        }
+
+        let mut position = 0;
+        let mut stages = vec![];
+        for step in &self.steps {
+            let stage = match step {
+                PipelineStepConfig::Parallel { parallel } => {
+                    if parallel.is_empty() {
+                        bail!("Pipeline '{}' has an empty parallel group", self.name);
+                    }
+                    let mut stage = vec![];
+                    for step in parallel {
+                        position += 1;
+                        stage.push(self.parse_step(step, position, file, tasks)?);
+                    }
+                    stage
+                }
+                step => {
+                    position += 1;
+                    vec![self.parse_step(step, position, file, tasks)?]
+                }
+            };
+            stages.push(stage);
+        }
+
+        let mut task = TaskConfig::parse(&self.task_definition(file)?)?;
+        task.executor = Executor::Pipeline(Arc::new(Pipeline { stages }));
+        Ok(task)
+    }
+
+    /// Definition of the task that runs the pipeline. It only takes the defaults about the schedule, the output and
+    /// the alerts, the rest apply to the steps
+    pub fn task_definition(&self, file: &ConfigFile) -> Result<TaskDefinition> {
+        let defaults = file.defaults.clone().unwrap_or_default();
+        let on_failure = if self.on_failure.is_empty() { defaults.on_failure } else { self.on_failure.clone() };
+        let on_success = if self.on_success.is_empty() { defaults.on_success } else { self.on_success.clone() };
+        Ok(TaskDefinition {
+            name: self.name.clone(),
+            cmd: self.description(),
+            enabled: self.enabled,
+            when: self.when.clone(),
+            every: self.every.clone(),
+            period: self.period,
+            timezone: self.timezone.clone().or(defaults.timezone),
+            // The next run waits for the steps of the previous one
+            avoid_overlapping: true,
+            output_dir: defaults.output_dir,
+            output_mode: defaults.output_mode,
+            misfire: defaults.misfire,
+            on_failure: file.resolve_alerts(&on_failure)?,
+            on_success: file.resolve_alerts(&on_success)?,
+            ..Default::default()
+        })
+    }
+
+    fn parse_step(&self, step: &PipelineStepConfig, position: usize, file: &ConfigFile, tasks: &[Arc<TaskConfig>]) -> Result<Arc<TaskConfig>> {
+        match step {
+            PipelineStepConfig::Task { task: name } => {
+                let Some(task) = tasks.iter().find(|task| task.name == *name) else {
+                    bail!("Pipeline '{}' refers to an unknown task '{}'", self.name, name);
+                };
+                let mut task = TaskConfig::clone(task);
+                if task.working_directory.is_none() {
+                    task.working_directory = self.working_directory.clone();
+                }
+                if let Some(pipeline_env) = &self.env {
+                    let mut env = pipeline_env.clone();
+                    env.extend(task.env.take().unwrap_or_default());
+                    task.env = Some(env);
+                }
+                Ok(Arc::new(task))
+            }
+            PipelineStepConfig::Cmd { cmd, name } => {
+                let name = format!("{}.{}", self.name, name.clone().unwrap_or_else(|| format!("step-{}", position)));
+                let definition = TaskDefinition {
+                    name: name.clone(),
+                    cmd: cmd.clone(),
+                    enabled: true,
+                    // Steps are not scheduled, they run when the pipeline reaches them
+                    after_task: Some(AfterTaskConfig { name: self.name.clone(), on: AfterTaskOn::Always }),
+                    working_directory: self.working_directory.clone(),
+                    env: self.env.clone(),
+                    ..Default::default()
+                };
+                let task = TaskConfig::parse(&file.resolve_task(&definition)?).with_context(|| format!("Malformed step '{}'", name))?;
+                Ok(Arc::new(task))
+            }
+            PipelineStepConfig::Parallel { .. } => bail!("Pipeline '{}' has a parallel group inside another one", self.name),
+        }
+    }
+
+    /// The steps, like `extract -> [load-a, load-b] -> report`, shown as the command of the pipeline
+    pub fn description(&self) -> String {
+        let mut position = 0;
+        let mut step_name = |step: &PipelineStepConfig| {
+            position += 1;
+            match step {
+                PipelineStepConfig::Task { task } => task.clone(),
+                PipelineStepConfig::Cmd { name, .. } => name.clone().unwrap_or_else(|| format!("step-{}", position)),
+                PipelineStepConfig::Parallel { .. } => "[...]".to_string(),
+            }
+        };
+        let stages: Vec<String> = self
+            .steps
+            .iter()
+            .map(|step| match step {
+                PipelineStepConfig::Parallel { parallel } => {
+                    format!("[{}]", parallel.iter().map(&mut step_name).collect::<Vec<_>>().join(", "))
+                }
+                step => step_name(step),
+            })
+            .collect();
+        stages.join(" -> ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_config_file;
+
+    #[test]
+    fn test_parse_pipeline() {
+        let yaml = "
+defaults:
+  output_dir: /tmp/cron-rs
+tasks:
+  - { name: extract, cmd: ./extract.sh, every: 1 day, env: { MODE: full } }
+pipelines:
+  - name: etl
+    every: 1 hour
+    working_directory: /srv/etl
+    env: { MODE: fast, TARGET: db }
+    steps:
+      - task: extract
+      - parallel:
+          - cmd: ./load_a.sh
+          - { name: load-b, cmd: ./load_b.sh }
+      - cmd: ./report.sh
+";
+        let config = parse_config_file(&serde_yml::from_str(yaml).unwrap()).unwrap();
+        let pipeline = config.tasks.iter().find(|task| task.name == "etl").unwrap();
+        assert_eq!(pipeline.cmd, "extract -> [step-2, load-b] -> step-4");
+        assert!(pipeline.avoid_overlapping);
+
+        let Executor::Pipeline(steps) = &pipeline.executor else { panic!("{:?}", pipeline.executor) };
+        let names: Vec<Vec<&str>> = steps.stages.iter().map(|stage| stage.iter().map(|step| step.name.as_str()).collect()).collect();
+        assert_eq!(names, vec![vec!["extract"], vec!["etl.step-2", "etl.load-b"], vec!["etl.step-4"]]);
+
+        // The values of the task take precedence over the ones of the pipeline
+        let extract = &steps.stages[0][0];
+        assert_eq!(extract.working_directory.as_deref(), Some("/srv/etl"));
+        let env = extract.env.clone().unwrap();
+        assert_eq!((env["MODE"].as_str(), env["TARGET"].as_str()), ("full", "db"));
+        assert_eq!(steps.stages[2][0].output_dir.as_deref(), Some("/tmp/cron-rs"));
+    }
+
+    #[test]
+    fn test_malformed_pipelines() {
+        let parse = |pipelines: &str| {
+            let yaml = format!("tasks: [{{ name: a, cmd: 'true', every: 1 hour }}]\npipelines: {}", pipelines);
+            parse_config_file(&serde_yml::from_str(&yaml).unwrap()).map(|_| ())
+        };
+        assert!(parse("[{ name: p, every: 1 hour, steps: [{ task: a }] }]").is_ok());
+        assert!(parse("[{ name: p, every: 1 hour, steps: [{ task: b }] }]").is_err());
+        assert!(parse("[{ name: a, every: 1 hour, steps: [{ task: a }] }]").is_err());
+        assert!(parse("[{ name: p, every: 1 hour, steps: [] }]").is_err());
+        assert!(parse("[{ name: p, steps: [{ task: a }] }]").is_err());
+        assert!(parse("[{ name: p, every: 1 hour, steps: [{ parallel: [{ parallel: [{ task: a }] }] }] }]").is_err());
+    }
+}
@@ -0,0 +1,66 @@
+use super::Schedule;
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use std::time::Duration;
+
+/// Limits for the output files of the tasks, as written in the config file
+#[skip_serializing_none]
+#[derive(Debug, Clone, Deserialize, Serialize, Default, PartialEq)]
+pub struct OutputRetentionConfig {
+    /// Files not modified for this long are deleted, e.g. `30 days`
+    pub max_age: Option<String>,
+    /// The oldest files are deleted until the output of all the tasks fits, e.g. `500 MB`
+    pub max_total_size: Option<String>,
+    /// Files not modified for this long are compressed with gzip, e.g. `1 day`
+    pub compress_after: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OutputRetention {
+    pub max_age: Option<Duration>,
+    pub max_total_size: Option<u64>,
+    pub compress_after: Option<Duration>,
+}
+
+impl OutputRetention {
+    pub fn parse(config: &OutputRetentionConfig) -> Result<Self> {
+        let duration = |value: &Option<String>, field: &str| -> Result<Option<Duration>> {
+            value
+                .as_deref()
+                .map(|v| Schedule::parse_time_duration(v).map(|(duration, _)| duration))
+                .transpose()
+                .context(format!("Malformed field: output_retention.{}", field))
+        };
+
+        Ok(Self {
+            max_age: duration(&config.max_age, "max_age")?,
+            max_total_size: config
+                .max_total_size
+                .as_deref()
+                .map(parse_size)
+                .transpose()
+                .context("Malformed field: output_retention.max_total_size")?,
+            compress_after: duration(&config.compress_after, "compress_after")?,
+        })
+    }
+}
+
+/// Sizes in bytes with an optional unit, `B`, `KB`, `MB`, `GB` or `TB`, in multiples of 1024
+pub fn parse_size(input: &str) -> Result<u64> {
+    let input = input.trim();
+    let split = input.find(|c: char| !c.is_ascii_digit()).unwrap_or(input.len());
+    let (amount, unit) = input.split_at(split);
+    let amount: u64 = amount.parse().map_err(|_| anyhow!("Expected a number at the start of '{}'", input))?;
+
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KB" => 1 << 10,
+        "M" | "MB" => 1 << 20,
+        "G" | "GB" => 1 << 30,
+        "T" | "TB" => 1 << 40,
+        other => return Err(anyhow!("Unknown size unit '{}', expected B, KB, MB, GB or TB", other)),
+    };
+
+    amount.checked_mul(multiplier).ok_or_else(|| anyhow!("Size '{}' is too large", input))
+}
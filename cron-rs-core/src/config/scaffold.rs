@@ -0,0 +1,160 @@
+use super::DEFAULT_CONFIG;
+use anyhow::{bail, Result};
+use std::str::FromStr;
+
+/// Kinds of alerts `cron-rs generate-config --with-alerts` can add
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScaffoldAlert {
+    Email,
+    Webhook,
+    Cmd,
+}
+
+impl ScaffoldAlert {
+    fn kind(self) -> &'static str {
+        match self {
+            ScaffoldAlert::Email => "email",
+            ScaffoldAlert::Webhook => "webhook",
+            ScaffoldAlert::Cmd => "cmd",
+        }
+    }
+}
+
+impl FromStr for ScaffoldAlert {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "email" => Ok(ScaffoldAlert::Email),
+            "webhook" => Ok(ScaffoldAlert::Webhook),
+            "cmd" => Ok(ScaffoldAlert::Cmd),
+            _ => bail!("Unknown alert '{}', must be email, webhook or cmd", s),
+        }
+    }
+}
+
+/// Options of `cron-rs generate-config`, the default ones write [DEFAULT_CONFIG] as it is
+#[derive(Debug, Clone)]
+pub struct Scaffold {
+    /// Sample tasks to write, the first one is the documented sample task unless `minimal` is set
+    pub tasks: u32,
+    /// The `on_failure` alerts to keep, all the samples if None
+    pub alerts: Option<Vec<ScaffoldAlert>>,
+    /// Only the options that are set, without the commented out ones
+    pub minimal: bool,
+}
+
+impl Default for Scaffold {
+    fn default() -> Self {
+        Self { tasks: 1, alerts: None, minimal: false }
+    }
+}
+
+/// Writes a config file to start from, the documented default config or a minimal one with the chosen alerts and
+/// number of tasks
+pub fn generate_config(scaffold: &Scaffold) -> String {
+    let (head, alerts, tail) = split_alerts(DEFAULT_CONFIG);
+    let alerts: Vec<&str> = match &scaffold.alerts {
+        Some(kinds) => alerts.into_iter().filter(|alert| kinds.iter().any(|kind| is_alert_of(alert, *kind))).collect(),
+        None => alerts,
+    };
+
+    if scaffold.minimal {
+        let mut config = "logging:\n  output: stdout\n  level: info\n".to_string();
+        if !alerts.is_empty() {
+            config.push_str("\nalerts:\n  on_failure:\n");
+            for alert in &alerts {
+                let lines = alert.lines().filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'));
+                lines.for_each(|line| config.push_str(&format!("{}\n", line)));
+            }
+        }
+        config.push_str("\ntasks:");
+        if scaffold.tasks == 0 {
+            config.push_str(" []\n");
+        }
+        for i in 1..=scaffold.tasks {
+            config.push_str(&format!("\n  - name: {}\n    cmd: echo 'hello from task {}'\n    every: 1 hour\n", task_name(i), i));
+        }
+        return config;
+    }
+
+    let mut config = head.to_string();
+    if alerts.is_empty() {
+        config.push_str(" []\n");
+    } else {
+        let alerts: Vec<&str> = alerts.iter().map(|alert| alert.trim_end()).collect();
+        config.push('\n');
+        config.push_str(&alerts.join("\n\n"));
+        config.push('\n');
+    }
+    config.push_str(tail);
+    // The documented sample task is the first one
+    if scaffold.tasks == 0 {
+        if let Some((before, _)) = config.split_once("\ntasks:\n") {
+            config = format!("{}\ntasks: []\n", before);
+        }
+    }
+    for i in 2..=scaffold.tasks {
+        config.push_str(&format!("\n  - name: {}\n    cmd: echo 'hello from task {}'\n    every: 1 hour\n", task_name(i), i));
+    }
+    config
+}
+
+fn task_name(i: u32) -> String {
+    if i == 1 { "Sample task".to_string() } else { format!("Sample task {}", i) }
+}
+
+fn is_alert_of(alert: &str, kind: ScaffoldAlert) -> bool {
+    alert.lines().any(|line| line.trim() == format!("- type: {}", kind.kind()))
+}
+
+/// The config up to `on_failure:`, the sample alerts of the list, each with its comment, and the rest of the config
+fn split_alerts(config: &str) -> (&str, Vec<&str>, &str) {
+    const START: &str = "\n  on_failure:";
+    const END: &str = "\n  # Notify when a task succeeds";
+    let (Some(start), Some(end)) = (config.find(START), config.find(END)) else {
+        return (config, vec![], "");
+    };
+    let head = &config[..start + START.len()];
+    let alerts = config[start + START.len()..end].trim_start_matches('\n').split("\n\n").collect();
+    (head, alerts, &config[end..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::file::ConfigFile;
+    use crate::config::parse_config_file;
+
+    fn parse(config: &str) -> crate::Config {
+        let file: ConfigFile = serde_yml::from_str(config).unwrap_or_else(|e| panic!("{}\n{}", e, config));
+        parse_config_file(&file).unwrap_or_else(|e| panic!("{:#}\n{}", e, config))
+    }
+
+    #[test]
+    fn test_generate_config() {
+        assert_eq!(generate_config(&Scaffold::default()), DEFAULT_CONFIG);
+        parse(DEFAULT_CONFIG);
+
+        let config = generate_config(&Scaffold { tasks: 3, alerts: Some(vec![ScaffoldAlert::Webhook]), minimal: false });
+        assert!(config.contains("# Send a webhook when a task fails") && !config.contains("- type: email"), "{}", config);
+        let parsed = parse(&config);
+        let kinds: Vec<&str> = parsed.alerts.on_failure.iter().map(|alert| alert.kind()).collect();
+        assert_eq!(kinds, vec!["webhook"]);
+        let names: Vec<&str> = parsed.tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, vec!["Sample task", "Sample task 2", "Sample task 3"]);
+
+        let config = generate_config(&Scaffold { tasks: 2, alerts: Some(vec![ScaffoldAlert::Email, ScaffoldAlert::Cmd]), minimal: true });
+        assert!(!config.contains('#'), "{}", config);
+        let parsed = parse(&config);
+        let kinds: Vec<&str> = parsed.alerts.on_failure.iter().map(|alert| alert.kind()).collect();
+        assert_eq!(kinds, vec!["email", "cmd"]);
+        assert_eq!(parsed.tasks.len(), 2);
+
+        // Without alerts nor tasks
+        assert!(parse(&generate_config(&Scaffold { tasks: 0, alerts: Some(vec![]), minimal: false })).tasks.is_empty());
+        let config = generate_config(&Scaffold { tasks: 0, alerts: Some(vec![]), minimal: true });
+        assert_eq!(config, "logging:\n  output: stdout\n  level: info\n\ntasks: []\n");
+        assert!("sms".parse::<ScaffoldAlert>().is_err());
+    }
+}
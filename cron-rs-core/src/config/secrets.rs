@@ -0,0 +1,213 @@
+use crate::executor::shell_process;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_yml::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Secrets are kept in the config as strings with this prefix and the reference, like `!secret file:/path`
+const SECRET_PREFIX: &str = "!secret ";
+
+/// Sections of the config where the secrets are read when they are used: the `env` of the tasks, read when the task
+/// runs, the alerts and the DSN of `sentry`, read when they are sent, and the options of the plugins, read when they are
+/// called
+const SECRET_SECTIONS: [&str; 7] = ["env", "alerts", "alert_channels", "on_failure", "on_success", "plugins", "sentry"];
+
+/// Replaces every `!secret file:/path` and `!secret cmd:command` in the config with a string that keeps the reference,
+/// the secrets are read by [resolve_secrets] when they are used. Returns true if any secret was found
+pub fn mark_secrets(value: &mut Value) -> Result<bool> {
+    mark_secrets_in(value, false)
+}
+
+fn mark_secrets_in(value: &mut Value, in_section: bool) -> Result<bool> {
+    let mut found = false;
+    match value {
+        Value::Tagged(tagged) if tagged.tag == "secret" => {
+            let Value::String(reference) = &tagged.value else {
+                bail!("A !secret value must be a string like 'file:/path' or 'cmd:command'");
+            };
+            if !in_section {
+                bail!("Secrets are only supported in env, in the alerts, in the plugins and in sentry, found '!secret {}'", reference);
+            }
+            *value = Value::String(format!("{}{}", SECRET_PREFIX, reference));
+            found = true;
+        }
+        Value::Tagged(tagged) => found = mark_secrets_in(&mut tagged.value, in_section)?,
+        Value::Sequence(items) => {
+            for item in items {
+                found |= mark_secrets_in(item, in_section)?;
+            }
+        }
+        Value::Mapping(mapping) => {
+            for (key, item) in mapping.iter_mut() {
+                let in_section = in_section || key.as_str().is_some_and(|key| SECRET_SECTIONS.contains(&key));
+                found |= mark_secrets_in(item, in_section)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(found)
+}
+
+/// The reference of a secret value, like `file:/path`
+pub fn secret_reference(value: &str) -> Option<&str> {
+    value.strip_prefix(SECRET_PREFIX)
+}
+
+/// Returns a copy of the value with the secrets in its strings read
+pub fn resolve_secrets<T: Serialize + DeserializeOwned>(value: &T) -> Result<T> {
+    let mut value = serde_yml::to_value(value)?;
+    resolve_value(&mut value)?;
+    Ok(serde_yml::from_value(value)?)
+}
+
+fn resolve_value(value: &mut Value) -> Result<()> {
+    match value {
+        Value::String(string) => {
+            if let Some(reference) = secret_reference(string) {
+                *string = read_secret(reference)?;
+            }
+        }
+        Value::Tagged(tagged) => resolve_value(&mut tagged.value)?,
+        Value::Sequence(items) => {
+            for item in items {
+                resolve_value(item)?;
+            }
+        }
+        Value::Mapping(mapping) => {
+            for (_, item) in mapping.iter_mut() {
+                resolve_value(item)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Reads a secret from a file (`file:/path`) or from the stdout of a command (`cmd:command`), run with the default
+/// shell. The trailing newline is removed
+pub fn read_secret(reference: &str) -> Result<String> {
+    let secret = if let Some(path) = reference.strip_prefix("file:") {
+        std::fs::read_to_string(path.trim()).with_context(|| format!("Failed to read secret file {}", path.trim()))?
+    } else if let Some(cmd) = reference.strip_prefix("cmd:") {
+        let output = shell_process(None, false, cmd.trim())
+            .output()
+            .with_context(|| format!("Failed to run secret command '{}'", cmd.trim()))?;
+
+        if !output.status.success() {
+            bail!(
+                "Secret command '{}' failed with {}: {}",
+                cmd.trim(),
+                output.status,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        String::from_utf8(output.stdout).map_err(|_| anyhow!("Secret command '{}' returned invalid UTF-8", cmd.trim()))?
+    } else {
+        bail!("Unknown secret '{}', expected 'file:/path' or 'cmd:command'", reference);
+    };
+
+    Ok(secret.trim_end_matches(['\n', '\r']).to_string())
+}
+
+/// Reads a file with `KEY=VALUE` lines, empty lines and lines starting with `#` are ignored
+pub fn read_env_file(path: &Path) -> Result<HashMap<String, String>> {
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read env file {}", path.display()))?;
+    let mut env = HashMap::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            bail!("Invalid line {} in env file {}, expected KEY=VALUE", i + 1, path.display());
+        };
+
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+            .unwrap_or(value);
+
+        env.insert(key.trim().to_string(), value.to_string());
+    }
+
+    Ok(env)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("cron-rs-test-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_read_secret() {
+        let dir = test_dir("secret");
+        let path = dir.join("password");
+        std::fs::write(&path, "hunter2\n").unwrap();
+
+        assert_eq!(read_secret(&format!("file:{}", path.display())).unwrap(), "hunter2");
+        assert_eq!(read_secret("cmd: echo token").unwrap(), "token");
+        assert!(read_secret("cmd:exit 3").unwrap_err().to_string().starts_with("Secret command 'exit 3' failed"));
+        assert!(read_secret(&format!("file:{}", dir.join("missing").display())).is_err());
+        assert!(read_secret("vault:password").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_secrets() {
+        let mut value: Value = serde_yml::from_str(
+            "
+             tasks:
+               - name: backup
+                 cmd: 'true'
+                 env: { TOKEN: !secret 'cmd:echo token', USER: admin }",
+        )
+        .unwrap();
+        assert!(mark_secrets(&mut value).unwrap());
+
+        // Loading the config doesn't read the secrets, using them does
+        let env = &value["tasks"][0]["env"];
+        assert_eq!(env["TOKEN"].as_str(), Some("!secret cmd:echo token"));
+        let env: HashMap<String, String> = serde_yml::from_value(env.clone()).unwrap();
+        let env = resolve_secrets(&env).unwrap();
+        assert_eq!(env["TOKEN"], "token");
+        assert_eq!(env["USER"], "admin");
+
+        // Only where they are read when used
+        let mut value: Value = serde_yml::from_str("tasks: [{ name: backup, cmd: !secret 'cmd:echo true' }]").unwrap();
+        assert!(mark_secrets(&mut value).is_err());
+        let mut value: Value = serde_yml::from_str("tasks: [{ name: backup, cmd: 'true' }]").unwrap();
+        assert!(!mark_secrets(&mut value).unwrap());
+    }
+
+    #[test]
+    fn test_read_env_file() {
+        let dir = test_dir("env-file");
+        let path = dir.join("backup.env");
+        std::fs::write(&path, "# comment\n\nA=1\nexport B = \"two words\"\nC='3'\nD=a=b\n").unwrap();
+
+        let env = read_env_file(&path).unwrap();
+        assert_eq!(env.len(), 4);
+        assert_eq!(env["A"], "1");
+        assert_eq!(env["B"], "two words");
+        assert_eq!(env["C"], "3");
+        assert_eq!(env["D"], "a=b");
+
+        std::fs::write(&path, "A=1\nINVALID\n").unwrap();
+        let error = read_env_file(&path).unwrap_err().to_string();
+        assert!(error.starts_with("Invalid line 2 in env file"), "{}", error);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
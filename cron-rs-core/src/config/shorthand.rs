@@ -1,15 +1,15 @@
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, Result};
 use nom::{
     branch::alt,
     bytes::complete::tag,
-    character::complete::{digit1, space0, space1},
-    combinator::{all_consuming, complete, cond, cut, map, map_res, opt, success, value},
+    character::complete::{space0, space1},
+    combinator::{all_consuming, cut, map, map_res, opt, value},
     multi::separated_list1,
-    sequence::{delimited, pair, preceded, separated_pair, terminated, tuple},
+    sequence::{delimited, preceded, separated_pair, terminated, tuple},
     IResult,
 };
 
-use super::{dayofweek::DayOfWeek, number, time_atom, ws, TimePattern, TimePatternField};
+use super::{number, time_atom, ws, TimePattern, TimePatternField};
 
 // "[Mon,Tue] *-*/2-01..04 12:00:00"
 
@@ -34,7 +34,7 @@ pub fn parse_shorthand(i: &str) -> Result<TimePattern> {
         },
     )))(i)
     .map_err(|e| match e {
-        nom::Err::Incomplete(needed) => anyhow!("Unexpected EOF"),
+        nom::Err::Incomplete(_) => anyhow!("Unexpected EOF"),
         nom::Err::Error(f) | nom::Err::Failure(f) => {
             let err_pos = i.len() - f.input.len();
             let msg = some_kind_of_uppercase_first_letter(&format!("{f}"));
@@ -103,7 +103,7 @@ pub fn any<'a>() -> impl FnMut(&'a str) -> IResult<&'a str, TimePatternField> {
 }
 
 pub fn simple<'a>(allow_dow: bool) -> impl FnMut(&'a str) -> IResult<&'a str, TimePatternField> {
-    map(time_atom(allow_dow), |value| TimePatternField::Value(value))
+    map(time_atom(allow_dow), TimePatternField::Value)
 }
 
 pub fn list<'a>(allow_dow: bool) -> impl FnMut(&'a str) -> IResult<&'a str, TimePatternField> {
@@ -113,7 +113,7 @@ pub fn list<'a>(allow_dow: bool) -> impl FnMut(&'a str) -> IResult<&'a str, Time
             cut(separated_list1(ws(tag(",")), ws(time_atom(allow_dow)))),
             tuple((space0, tag("]"))),
         ),
-        |elements| TimePatternField::List(elements),
+        TimePatternField::list,
     )
 }
 
@@ -0,0 +1,89 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+
+/// Conditions for an execution to be successful, as written in the config file
+#[skip_serializing_none]
+#[derive(Debug, Clone, Deserialize, Serialize, Default, PartialEq)]
+pub struct SuccessCriteriaConfig {
+    /// Exit codes that count as a success, by default only 0
+    pub exit_codes: Option<Vec<i32>>,
+    /// Regex that must match some part of stdout
+    pub stdout_matches: Option<String>,
+    /// Regex that must not match any part of stdout
+    pub stdout_not_matches: Option<String>,
+    /// Regex that must match some part of stderr
+    pub stderr_matches: Option<String>,
+    /// Regex that must not match any part of stderr
+    pub stderr_not_matches: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SuccessCriteria {
+    pub exit_codes: Vec<i32>,
+    pub stdout_matches: Option<Regex>,
+    pub stdout_not_matches: Option<Regex>,
+    pub stderr_matches: Option<Regex>,
+    pub stderr_not_matches: Option<Regex>,
+}
+
+impl Default for SuccessCriteria {
+    fn default() -> Self {
+        Self {
+            exit_codes: vec![0],
+            stdout_matches: None,
+            stdout_not_matches: None,
+            stderr_matches: None,
+            stderr_not_matches: None,
+        }
+    }
+}
+
+impl SuccessCriteria {
+    pub fn parse(config: &SuccessCriteriaConfig) -> Result<Self> {
+        let regex = |value: &Option<String>, field: &str| -> Result<Option<Regex>> {
+            value
+                .as_deref()
+                .map(Regex::new)
+                .transpose()
+                .context(format!("Malformed field: success.{}", field))
+        };
+
+        Ok(Self {
+            exit_codes: config.exit_codes.clone().unwrap_or_else(|| vec![0]),
+            stdout_matches: regex(&config.stdout_matches, "stdout_matches")?,
+            stdout_not_matches: regex(&config.stdout_not_matches, "stdout_not_matches")?,
+            stderr_matches: regex(&config.stderr_matches, "stderr_matches")?,
+            stderr_not_matches: regex(&config.stderr_not_matches, "stderr_not_matches")?,
+        })
+    }
+
+    /// Checks the result of an execution, the error is the reason it failed. The exit code is None if the process
+    /// was killed by a signal
+    pub fn check(&self, exit_code: Option<i32>, stdout: &str, stderr: &str) -> Result<(), String> {
+        let Some(exit_code) = exit_code else {
+            return Err("killed by a signal".to_string());
+        };
+        if !self.exit_codes.contains(&exit_code) {
+            return Err(format!("exit code {}", exit_code));
+        }
+
+        let checks = [
+            (&self.stdout_matches, stdout, true, "stdout"),
+            (&self.stdout_not_matches, stdout, false, "stdout"),
+            (&self.stderr_matches, stderr, true, "stderr"),
+            (&self.stderr_not_matches, stderr, false, "stderr"),
+        ];
+        for (regex, output, must_match, stream) in checks {
+            let Some(regex) = regex else { continue };
+            match (regex.is_match(output), must_match) {
+                (false, true) => return Err(format!("{} doesn't match '{}'", stream, regex)),
+                (true, false) => return Err(format!("{} matches '{}'", stream, regex)),
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
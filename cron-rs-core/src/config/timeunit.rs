@@ -0,0 +1,60 @@
+use std::time::Duration;
+use nom::{branch::alt, bytes::complete::tag, combinator::{opt, value}, sequence::terminated};
+
+
+#[derive(Debug, Clone, Copy)]
+pub enum TimeUnit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+impl TimeUnit {
+    pub fn parse(input: &str) -> nom::IResult<&str, Self> {
+        alt((
+            value(Self::Second, terminated(tag("second"), opt(tag("s")))),
+            value(Self::Second, tag("s")),
+            value(Self::Minute, terminated(tag("minute"), opt(tag("s")))),
+            value(Self::Minute, tag("m")),
+            value(Self::Hour, terminated(tag("hour"), opt(tag("s")))),
+            value(Self::Hour, tag("h")),
+            value(Self::Day, terminated(tag("day"), opt(tag("s")))),
+            value(Self::Day, tag("d")),
+            value(Self::Week, terminated(tag("week"), opt(tag("s")))),
+            value(Self::Week, tag("w")),
+            value(Self::Month, terminated(tag("month"), opt(tag("s")))),
+            value(Self::Month, tag("M")),
+            value(Self::Year, terminated(tag("year"), opt(tag("s")))),
+            value(Self::Year, tag("y")),
+        ))(input)
+    }
+
+    /// Singular name of the unit, like in `1 hour` or `15 minutes`
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Second => "second",
+            Self::Minute => "minute",
+            Self::Hour => "hour",
+            Self::Day => "day",
+            Self::Week => "week",
+            Self::Month => "month",
+            Self::Year => "year",
+        }
+    }
+
+    pub fn to_duration(self, amount: u32) -> std::time::Duration {
+        match self {
+            Self::Second => Duration::from_secs(amount as u64),
+            Self::Minute => Duration::from_secs(amount as u64 * 60),
+            Self::Hour => Duration::from_secs(amount as u64 * 60 * 60),
+            Self::Day => Duration::from_secs(amount as u64 * 60 * 60 * 24),
+            Self::Week => Duration::from_secs(amount as u64 * 60 * 60 * 24 * 7),
+            Self::Month => Duration::from_secs(amount as u64 * 60 * 60 * 24 * 30),
+            Self::Year => Duration::from_secs(amount as u64 * 60 * 60 * 24 * 365),
+        }
+    }
+}
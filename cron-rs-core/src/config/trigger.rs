@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use serde_with::skip_serializing_none;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// Time without new changes before an `on_change` task runs, when `debounce` is not set
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_secs(1);
+
+/// Runs the task when the files change, as written in the config file
+#[skip_serializing_none]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct OnChangeConfig {
+    /// Files or directories, the directories are watched recursively
+    pub paths: Vec<String>,
+    /// Time without changes before the task runs, so a burst of changes runs it once, e.g. `5 s`
+    pub debounce: Option<String>,
+}
+
+/// Runs the task when another one finishes, as written in the config file
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct AfterTaskConfig {
+    pub name: String,
+    #[serde(default)]
+    pub on: AfterTaskOn,
+}
+
+/// Which executions of the other task start this one
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum AfterTaskOn {
+    #[default]
+    Success,
+    Failure,
+    Always,
+}
+
+impl AfterTaskOn {
+    pub fn matches(self, success: bool) -> bool {
+        match self {
+            AfterTaskOn::Success => success,
+            AfterTaskOn::Failure => !success,
+            AfterTaskOn::Always => true,
+        }
+    }
+}
+
+impl Display for AfterTaskOn {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AfterTaskOn::Success => write!(f, "success"),
+            AfterTaskOn::Failure => write!(f, "failure"),
+            AfterTaskOn::Always => write!(f, "always"),
+        }
+    }
+}
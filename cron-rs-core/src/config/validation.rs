@@ -0,0 +1,1286 @@
+use crate::alerts::{Alert, StatusCodes};
+use crate::config::digest::Digest;
+use crate::config::executor::{Executor, ExecutorKind};
+use crate::config::file::{ConfigFile, TaskDefinition, TimePatternConfig};
+use crate::config::logging::{LogFormat, LogOutput, SyslogProtocol};
+use crate::config::pipeline::PipelineStepConfig;
+use crate::config::retention::parse_size;
+use crate::config::secrets::{read_env_file, secret_reference};
+use crate::config::window::TimeWindow;
+use crate::config::{Schedule, TaskConfig, TimePattern, TimePatternField, OUTPUT_PLACEHOLDERS, STDERR_TO_STDOUT};
+use crate::executor::{shell_process, DEFAULT_SHELL};
+use crate::plugins::{load_plugin_module, module_exports, ALERT_EXPORT, CONDITION_EXPORT};
+use crate::sentry::check_dsn;
+use crate::scheduler::Scheduler;
+use crate::utils::parse_timezone;
+use chrono::Utc;
+use lettre::message::Mailbox;
+use serde::Serialize;
+use serde_with::skip_serializing_none;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A problem found in the config file, with the task and field it refers to when known
+#[skip_serializing_none]
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationResult {
+    pub severity: Severity,
+    pub task: Option<String>,
+    pub field: Option<String>,
+    pub message: String,
+}
+
+impl ValidationResult {
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message.into())
+    }
+
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message.into())
+    }
+
+    fn new(severity: Severity, message: String) -> Self {
+        Self {
+            severity,
+            task: None,
+            field: None,
+            message,
+        }
+    }
+
+    pub fn task(mut self, task: &str) -> Self {
+        self.task = Some(task.to_string());
+        self
+    }
+
+    pub fn field(mut self, field: &str) -> Self {
+        self.field = Some(field.to_string());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+/// Outcome of the validation of a config file, the output of `cron-rs validate --format json`
+#[derive(Debug, Serialize)]
+pub struct ValidationReport {
+    pub valid: bool,
+    pub diagnostics: Vec<ValidationResult>,
+}
+
+impl ValidationReport {
+    /// The config is valid without errors, with `strict` without warnings either
+    pub fn new(diagnostics: Vec<ValidationResult>, strict: bool) -> Self {
+        let valid = !diagnostics.iter().any(|d| d.is_error() || strict);
+        Self { valid, diagnostics }
+    }
+}
+
+impl Display for ValidationResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.task {
+            Some(task) => write!(f, "Task '{}': {}", task, self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+pub fn validate_config(conf: &ConfigFile) -> Vec<ValidationResult> {
+    let mut result = validate_tasks(conf, vec![]);
+
+    // Validate logging config
+    result.extend(validate_logging_config(conf));
+
+    // Validate alerts
+    result.extend(validate_alerts_config(conf));
+    result.extend(validate_plugins(conf));
+    if let Some(sentry) = &conf.sentry {
+        if let Err(e) = check_dsn(sentry) {
+            result.push(ValidationResult::error(format!("{:#}", e)).field("sentry.dsn"));
+        }
+    }
+    if let Some(digest) = &conf.digest {
+        if let Err(e) = Digest::parse(digest, conf) {
+            result.push(ValidationResult::error(format!("{:#}", e)).field("digest"));
+        }
+    }
+
+    result
+}
+
+/// Checks tasks added to a running scheduler like the ones of a config file, they can use the templates, the defaults
+/// and the alert channels of `conf`. `task_names` are the tasks of the scheduler, for the `after_task` of the new ones
+pub fn validate_added_tasks(conf: &ConfigFile, tasks: &[TaskDefinition], task_names: Vec<String>) -> Vec<ValidationResult> {
+    let conf = ConfigFile { tasks: tasks.to_vec(), pipelines: vec![], ..conf.clone() };
+    validate_tasks(&conf, task_names)
+}
+
+/// The tasks and the pipelines of the config, `task_names` are other tasks they can refer to
+fn validate_tasks(conf: &ConfigFile, mut task_names: Vec<String>) -> Vec<ValidationResult> {
+    let mut result = vec![];
+    let mut after_tasks = vec![];
+
+    for task in &conf.tasks {
+        let task = match conf.resolve_task(task) {
+            Ok(task) => task,
+            Err(_) if task.extends.as_ref().is_some_and(|template| !conf.templates.contains_key(template)) => {
+                let template = task.extends.as_deref().unwrap_or_default();
+                result.push(
+                    ValidationResult::error(format!("Unknown template '{}'", template))
+                        .task(&task.name)
+                        .field("extends"),
+                );
+                continue;
+            }
+            Err(_) if only_if_plugin(task).is_some_and(|plugin| !conf.plugins.contains_key(plugin)) => {
+                let plugin = only_if_plugin(task).unwrap_or_default();
+                result.push(
+                    ValidationResult::error(format!("Unknown plugin '{}'", plugin)).task(&task.name).field("only_if.plugin"),
+                );
+                continue;
+            }
+            // The alerts refer to a channel that doesn't exist
+            Err(e) => {
+                result.push(ValidationResult::error(e.root_cause().to_string()).task(&task.name).field("alerts"));
+                continue;
+            }
+        };
+
+        // Non-empty and unique name
+        if task.name.is_empty() {
+            result.push(ValidationResult::error("Task name must not be empty").field("name"));
+        }
+        if task_names.contains(&task.name) {
+            result.push(ValidationResult::warning(format!("Non unique task name: '{}'", task.name)).field("name"));
+        }
+        task_names.push(task.name.to_string());
+
+        // Tags must not be empty
+        if task.tags.iter().any(|tag| tag.trim().is_empty()) {
+            result.push(ValidationResult::error("Tags must not be empty").task(&task.name).field("tags"));
+        }
+
+        // Valid timezone
+        if let Some(tz_name) = &task.timezone {
+            if parse_timezone(tz_name).is_err() {
+                result.push(
+                    ValidationResult::error(format!("Unable to parse timezone: '{}'", tz_name))
+                        .task(&task.name)
+                        .field("timezone"),
+                );
+            }
+        }
+
+        // Command must not be empty
+        if task.cmd.is_empty() {
+            result.push(ValidationResult::error("Command must not be empty").task(&task.name).field("cmd"));
+        }
+
+        // Must have exactly one of when, every, period, on_change or after_task
+        match (&task.when, &task.every, &task.period, &task.on_change) {
+            (None, None, None, None) if task.after_task.is_none() => {
+                result.push(
+                    ValidationResult::error("Must specify either 'when', 'every', 'period', 'on_change' or 'after_task'")
+                        .task(&task.name)
+                        .field("when"),
+                );
+            }
+            (None, None, None, None) => {}
+            _ if task.after_task.is_some() => {
+                result.push(ValidationResult::error("'after_task' cannot be combined with another schedule").task(&task.name).field("after_task"));
+            }
+            (Some(_), Some(_), _, _) => {
+                result.push(ValidationResult::error("Cannot specify both 'when' and 'every'").task(&task.name).field("every"));
+            }
+            (Some(_), None, Some(_), _) | (None, Some(_), Some(_), _) => {
+                result.push(ValidationResult::error("'period' cannot be combined with 'when' or 'every'").task(&task.name).field("period"));
+            }
+            (Some(_), _, _, Some(_)) | (_, Some(_), _, Some(_)) | (_, _, Some(_), Some(_)) => {
+                result.push(ValidationResult::error("'on_change' cannot be combined with 'when', 'every' or 'period'").task(&task.name).field("on_change"));
+            }
+            _ => {}
+        }
+
+        // Validate the watched paths, a missing one can't be watched
+        if let Some(on_change) = &task.on_change {
+            if on_change.paths.is_empty() {
+                result.push(ValidationResult::error("'on_change' must have at least one path").task(&task.name).field("on_change"));
+            }
+            for path in &on_change.paths {
+                let full_path = match &task.working_directory {
+                    Some(dir) => Path::new(dir).join(path),
+                    None => Path::new(path).to_path_buf(),
+                };
+                if !full_path.exists() {
+                    result.push(ValidationResult::error(format!("Watched path '{}' does not exist", full_path.display())).task(&task.name).field("on_change"));
+                }
+            }
+            if let Some(Err(e)) = on_change.debounce.as_deref().map(Schedule::parse_time_duration) {
+                result.push(ValidationResult::error(format!("Invalid 'debounce' format: {}", e)).task(&task.name).field("on_change"));
+            }
+        }
+
+        // The referenced task is checked once all the names are known
+        if let Some(after_task) = &task.after_task {
+            if after_task.name == task.name {
+                result.push(ValidationResult::error("A task can't run after itself").task(&task.name).field("after_task"));
+            } else {
+                after_tasks.push((task.name.to_string(), after_task.name.to_string()));
+            }
+        }
+
+        // Validate every format if present
+        if let Some(every) = &task.every {
+            if let Err(e) = Schedule::parse_time_duration(every) {
+                result.push(ValidationResult::error(format!("Invalid 'every' format: {}", e)).task(&task.name).field("every"));
+            }
+        }
+
+        // Validate the active window, only allowed with every
+        if task.between.is_some() || task.on.is_some() {
+            if task.when.is_some() || task.period.is_some() || task.on_change.is_some() || task.after_task.is_some() {
+                result.push(ValidationResult::error("'between' and 'on' can only be used with 'every'").task(&task.name).field("between"));
+            }
+            if let Some(between) = &task.between {
+                if let Err(e) = TimeWindow::parse_between(between) {
+                    result.push(ValidationResult::error(format!("Invalid 'between' format: {}", e)).task(&task.name).field("between"));
+                }
+            }
+            if let Some(on) = &task.on {
+                let days = TimePatternField::parse_exploded_field(on, true).and_then(|days| days.check_range(0, 7));
+                if let Err(e) = days {
+                    result.push(ValidationResult::error(format!("Invalid 'on' format: {:#}", e)).task(&task.name).field("on"));
+                }
+            }
+        }
+
+        // Validate when format if present
+        if let Some(when) = &task.when {
+            match when {
+                TimePatternConfig::Short(s) => {
+                    if let Err(e) = TimePattern::parse_short(s) {
+                        result.push(ValidationResult::error(format!("Invalid short time pattern: {:#}", e)).task(&task.name).field("when"));
+                    }
+                }
+                TimePatternConfig::Long(c) => {
+                    if let Err(e) = TimePattern::parse_long(c) {
+                        result.push(ValidationResult::error(format!("Invalid long time pattern: {:#}", e)).task(&task.name).field("when"));
+                    }
+                }
+            }
+        }
+
+        // The pattern must match some date in the future
+        let numbering = conf.day_of_week_numbering.unwrap_or_default();
+        let parsed = TaskConfig::parse(&task).and_then(|task| task.with_day_of_week_numbering(numbering));
+        if let Ok(TaskConfig { schedule: Schedule::When { time, .. }, timezone, .. }) = parsed {
+            let now = Utc::now().with_timezone(&timezone);
+            if Scheduler::find_next_matching_time(&time, now, true).is_none() {
+                result.push(
+                    ValidationResult::error("The 'when' pattern never matches a future date, the task will never run")
+                        .task(&task.name)
+                        .field("when"),
+                );
+            }
+        }
+
+        // Validate min_interval, only allowed with when
+        if let Some(min_interval) = &task.min_interval {
+            if task.every.is_some() || task.period.is_some() || task.on_change.is_some() || task.after_task.is_some() {
+                result.push(ValidationResult::error("'min_interval' can only be used with 'when'").task(&task.name).field("min_interval"));
+            }
+            if let Err(e) = Schedule::parse_time_duration(min_interval) {
+                result.push(ValidationResult::error(format!("Invalid 'min_interval' format: {}", e)).task(&task.name).field("min_interval"));
+            }
+        }
+
+        // Validate time_limit format if present
+        if let Some(limit) = &task.time_limit {
+            if let Err(e) = Schedule::parse_time_duration(limit) {
+                result.push(ValidationResult::error(format!("Invalid time limit format: {}", e)).task(&task.name).field("time_limit"));
+            }
+            // Validate time_limit is not too short
+            if let Ok((duration, _)) = Schedule::parse_time_duration(limit) {
+                if duration < Duration::from_secs(1) {
+                    result.push(ValidationResult::error("time_limit must be at least 1 second").task(&task.name).field("time_limit"));
+                }
+            }
+        }
+
+        if let Some(runtime) = &task.max_monthly_runtime {
+            if let Err(e) = Schedule::parse_time_duration(runtime) {
+                result.push(
+                    ValidationResult::error(format!("Invalid 'max_monthly_runtime' format: {}", e))
+                        .task(&task.name)
+                        .field("max_monthly_runtime"),
+                );
+            } else if conf.alerts.as_ref().is_none_or(|alerts| alerts.on_budget_exhausted.is_empty()) {
+                result.push(
+                    ValidationResult::warning("alerts.on_budget_exhausted is empty, no alert is sent when the budget is used")
+                        .task(&task.name)
+                        .field("max_monthly_runtime"),
+                );
+            }
+        }
+
+        if let Some(grace) = &task.kill_grace {
+            if let Err(e) = Schedule::parse_time_duration(grace) {
+                result.push(ValidationResult::error(format!("Invalid kill grace format: {}", e)).task(&task.name).field("kill_grace"));
+            } else if task.time_limit.is_none() {
+                result.push(ValidationResult::warning("kill_grace has no effect without time_limit").task(&task.name).field("kill_grace"));
+            }
+        }
+
+        // Validate max_runs
+        if task.max_runs == Some(0) {
+            result.push(ValidationResult::warning("max_runs is 0, the task will never run").task(&task.name).field("max_runs"));
+        }
+        if task.max_runs_only_successes && task.max_runs.is_none() {
+            result.push(ValidationResult::warning("max_runs_only_successes has no effect without max_runs").task(&task.name).field("max_runs_only_successes"));
+        }
+
+        // Validate the power conditions, the power supply is only read on Linux
+        if let Some(percent) = task.min_battery_percent.filter(|percent| *percent > 100) {
+            result.push(ValidationResult::error(format!("min_battery_percent must be at most 100, got {}", percent)).task(&task.name).field("min_battery_percent"));
+        }
+        if !cfg!(target_os = "linux") && (task.only_on_ac_power == Some(true) || task.min_battery_percent.is_some()) {
+            result.push(
+                ValidationResult::warning("only_on_ac_power and min_battery_percent are only supported on Linux and are ignored")
+                    .task(&task.name)
+                    .field("only_on_ac_power"),
+            );
+        }
+
+        // Validate the sizes of only_if, the paths are checked before each run
+        if let Some(disk) = task.only_if.as_ref().and_then(|only_if| only_if.disk_free_above.as_ref()) {
+            if let Err(e) = parse_size(&disk.size) {
+                result.push(ValidationResult::error(format!("Invalid 'disk_free_above' size: {:#}", e)).task(&task.name).field("only_if"));
+            }
+        }
+
+        if let Some(error) = only_if_plugin(&task).and_then(|plugin| missing_export(conf, plugin, CONDITION_EXPORT)) {
+            result.push(ValidationResult::error(error).task(&task.name).field("only_if.plugin"));
+        }
+
+        // Validate run_as format and existence
+        if let Some(run_as) = task.run_as.as_ref().filter(|_| cfg!(unix)) {
+            if let Some(err) = validate_user_group(run_as) {
+                result.push(ValidationResult::error(err).task(&task.name).field("run_as"));
+            }
+        }
+        if cfg!(windows) {
+            // A task without them would run with more access than it should
+            let unsupported = [
+                ("run_as", task.run_as.is_some()),
+                ("limits", task.limits.is_some()),
+                ("chroot", task.chroot.is_some()),
+                ("cgroup", task.cgroup.is_some()),
+            ];
+            for (option, is_set) in unsupported {
+                if is_set {
+                    result.push(ValidationResult::error(format!("{} is not supported on Windows", option)).task(&task.name).field(option));
+                }
+            }
+            let ignored: Vec<&str> = [
+                ("login_env", task.login_env == Some(true)),
+                ("network", task.network.is_some()),
+                ("nice/ionice/cpu_affinity", task.nice.is_some() || task.ionice.is_some() || task.cpu_affinity.is_some()),
+            ]
+            .into_iter()
+            .filter_map(|(option, is_set)| is_set.then_some(option))
+            .collect();
+            if !ignored.is_empty() {
+                let message = format!("{} are not supported on Windows and are ignored", ignored.join(", "));
+                result.push(ValidationResult::warning(message).task(&task.name).field(ignored[0]));
+            }
+        }
+        if task.login_env == Some(true) && task.run_as.is_none() {
+            result.push(ValidationResult::warning("login_env has no effect without run_as").task(&task.name).field("login_env"));
+        }
+
+        // Validate working_directory exists if specified
+        if let Some(root) = &task.chroot {
+            if !Path::new(root).is_dir() {
+                result.push(ValidationResult::error(format!("Chroot directory '{}' does not exist", root)).task(&task.name).field("chroot"));
+            }
+        }
+        // Validate the executor, the options for the processes of the task don't reach the container or the remote machine
+        let executor = task.executor.unwrap_or_default();
+        if let Err(e) = Executor::parse(task.executor, task.docker.as_ref(), task.ssh.as_ref()) {
+            let field = if executor == ExecutorKind::Ssh { "ssh" } else { "docker" };
+            result.push(ValidationResult::error(format!("{:#}", e)).task(&task.name).field(field));
+        }
+        if executor != ExecutorKind::Local {
+            let client = if executor == ExecutorKind::Ssh { "ssh" } else { "docker" };
+            let ignored: Vec<&str> = [
+                ("chroot", task.chroot.is_some()),
+                // The container has its own network
+                ("network", executor == ExecutorKind::Ssh && task.network.is_some()),
+                ("login_env", task.login_env == Some(true)),
+                ("limits", task.limits.is_some()),
+                ("cgroup", task.cgroup.is_some()),
+                ("nice/ionice/cpu_affinity", task.nice.is_some() || task.ionice.is_some() || task.cpu_affinity.is_some()),
+            ]
+            .into_iter()
+            .filter_map(|(option, is_set)| is_set.then_some(option))
+            .collect();
+            if !ignored.is_empty() {
+                let message = format!("{} only apply to the {} client, not to the command", ignored.join(", "), client);
+                result.push(ValidationResult::warning(message).task(&task.name).field("executor"));
+            }
+        }
+        if task.docker.is_some() && executor != ExecutorKind::Docker {
+            result.push(ValidationResult::warning("docker has no effect without executor: docker").task(&task.name).field("docker"));
+        }
+        if task.ssh.is_some() && executor != ExecutorKind::Ssh {
+            result.push(ValidationResult::warning("ssh has no effect without executor: ssh").task(&task.name).field("ssh"));
+        }
+
+        // The working directory of a container or a remote command is not on this machine
+        if let Some(dir) = task.working_directory.as_ref().filter(|_| executor == ExecutorKind::Local) {
+            // Relative to the chroot when there is one
+            let path = match &task.chroot {
+                Some(root) => Path::new(root).join(dir.trim_start_matches('/')),
+                None => Path::new(dir).to_path_buf(),
+            };
+            if !path.exists() {
+                result.push(ValidationResult::error(format!("Working directory '{}' does not exist", path.display())).task(&task.name).field("working_directory"));
+            }
+        }
+
+        // Validate stdin
+        if task.stdin.is_some() && task.stdin_text.is_some() {
+            result.push(ValidationResult::error("Cannot specify both 'stdin' and 'stdin_text'").task(&task.name).field("stdin_text"));
+        }
+        if let Some(path) = &task.stdin {
+            if !Path::new(path).is_file() {
+                result.push(ValidationResult::error(format!("Stdin file '{}' does not exist", path)).task(&task.name).field("stdin"));
+            }
+        }
+
+        // Validate shell executable
+        let shell = task.shell.as_deref().unwrap_or(DEFAULT_SHELL);
+        if let Some(err) = validate_shell(shell) {
+            result.push(ValidationResult::error(err).task(&task.name).field("shell"));
+        }
+
+        // Validate stdout and stderr paths
+        if let Some(path) = &task.stdout {
+            if let Some(err) = validate_output_path(path) {
+                result.push(ValidationResult::error(format!("Invalid stdout path: {}", err)).task(&task.name).field("stdout"));
+            }
+        }
+
+        if let Some(path) = task.stderr.as_ref().filter(|path| *path != STDERR_TO_STDOUT) {
+            if task.combine_output == Some(true) {
+                result.push(ValidationResult::warning("stderr is ignored, combine_output writes it to the stdout file").task(&task.name).field("stderr"));
+            } else if let Some(err) = validate_output_path(path) {
+                result.push(ValidationResult::error(format!("Invalid stderr path: {}", err)).task(&task.name).field("stderr"));
+            }
+        }
+
+        if let Some(path) = task.lock_file.as_deref() {
+            if Path::new(path).parent().is_some_and(|dir| !dir.as_os_str().is_empty() && !dir.is_dir()) {
+                let message = format!("The directory of the lock_file {} does not exist", path);
+                result.push(ValidationResult::error(message).task(&task.name).field("lock_file"));
+            }
+        }
+
+        if let Some(path) = &task.env_file {
+            if let Err(e) = read_env_file(Path::new(path)) {
+                result.push(ValidationResult::error(format!("Invalid env_file: {}", e)).task(&task.name).field("env_file"));
+            }
+        }
+        // The secret commands are not run, they only run with the task
+        for (key, value) in task.env.iter().flatten() {
+            let Some(path) = secret_reference(value).and_then(|reference| reference.strip_prefix("file:")) else {
+                continue;
+            };
+            if !Path::new(path.trim()).is_file() {
+                let message = format!("The secret file {} of env {} does not exist", path.trim(), key);
+                result.push(ValidationResult::error(message).task(&task.name).field("env"));
+            }
+        }
+    }
+
+    result.extend(validate_pipelines(conf, &task_names));
+    // A task can run after a pipeline
+    task_names.extend(conf.pipelines.iter().map(|pipeline| pipeline.name.clone()));
+    result.extend(validate_after_tasks(&task_names, &after_tasks));
+    result
+}
+
+fn validate_pipelines(conf: &ConfigFile, task_names: &[String]) -> Vec<ValidationResult> {
+    let mut result = vec![];
+    let mut pipeline_names = vec![];
+    for pipeline in &conf.pipelines {
+        if pipeline.name.is_empty() {
+            result.push(ValidationResult::error("Pipeline name must not be empty").field("name"));
+        }
+        if task_names.contains(&pipeline.name) || pipeline_names.contains(&&pipeline.name) {
+            result.push(ValidationResult::error("Pipelines must have a unique name, also among the tasks").task(&pipeline.name).field("name"));
+        }
+        pipeline_names.push(&pipeline.name);
+
+        if pipeline.steps.is_empty() {
+            result.push(ValidationResult::error("A pipeline must have at least one step").task(&pipeline.name).field("steps"));
+        }
+        for step in &pipeline.steps {
+            let group = match step {
+                PipelineStepConfig::Parallel { parallel } if parallel.is_empty() => {
+                    result.push(ValidationResult::error("Parallel groups must have at least one step").task(&pipeline.name).field("steps"));
+                    continue;
+                }
+                PipelineStepConfig::Parallel { parallel } => parallel.as_slice(),
+                step => std::slice::from_ref(step),
+            };
+            for step in group {
+                match step {
+                    PipelineStepConfig::Task { task } if !task_names.contains(task) => {
+                        result.push(ValidationResult::error(format!("Unknown task '{}'", task)).task(&pipeline.name).field("steps"));
+                    }
+                    PipelineStepConfig::Cmd { cmd, .. } if cmd.is_empty() => {
+                        result.push(ValidationResult::error("Command must not be empty").task(&pipeline.name).field("steps"));
+                    }
+                    PipelineStepConfig::Parallel { .. } => {
+                        result.push(ValidationResult::error("Parallel groups can't be nested").task(&pipeline.name).field("steps"));
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // The schedule, like the one of a task
+        if let Err(e) = pipeline.task_definition(conf).and_then(|task| TaskConfig::parse(&task)) {
+            result.push(ValidationResult::error(format!("{:#}", e)).task(&pipeline.name).field("when"));
+        }
+    }
+    result
+}
+
+/// The tasks of `after_task` must exist, and the chains must not loop, a loop would never start
+fn validate_after_tasks(task_names: &[String], after_tasks: &[(String, String)]) -> Vec<ValidationResult> {
+    let mut result = vec![];
+    for (name, after) in after_tasks {
+        if !task_names.contains(after) {
+            result.push(ValidationResult::error(format!("Unknown task '{}'", after)).task(name).field("after_task"));
+            continue;
+        }
+
+        let mut chain = vec![name.as_str()];
+        let mut current = after.as_str();
+        while let Some((_, next)) = after_tasks.iter().find(|(task, _)| task == current) {
+            if current == name {
+                chain.push(current);
+                let message = format!("The tasks run after each other in a loop: {}", chain.join(" -> "));
+                result.push(ValidationResult::error(message).task(name).field("after_task"));
+                break;
+            }
+            if chain.contains(&current) {
+                // A loop that doesn't include this task, reported by its own tasks
+                break;
+            }
+            chain.push(current);
+            current = next;
+        }
+    }
+    result
+}
+
+fn validate_logging_config(conf: &ConfigFile) -> Vec<ValidationResult> {
+    let mut result = vec![];
+
+    if let Some(logging) = &conf.logging {
+        // Validate log level
+        let valid_levels = ["error", "warn", "info", "debug", "trace"];
+        if !valid_levels.contains(&logging.level.as_str()) {
+            result.push(
+                ValidationResult::error(format!(
+                    "Invalid log level '{}'. Must be one of: {}",
+                    logging.level,
+                    valid_levels.join(", ")
+                ))
+                .field("logging.level"),
+            );
+        }
+
+        // Validate file path if output is file
+        if logging.output == LogOutput::File {
+            if let Some(path) = &logging.file {
+                if let Some(err) = validate_output_path(path.to_str().unwrap_or("")) {
+                    result.push(ValidationResult::error(format!("Invalid log file: {}", err)).field("logging.file"));
+                }
+            } else {
+                result.push(
+                    ValidationResult::warning("Log output is set to 'file' but no file path specified")
+                        .field("logging.file"),
+                );
+            }
+        }
+
+        if let Some(rotation) = &logging.rotation {
+            if let Err(e) = parse_size(&rotation.max_size) {
+                result.push(ValidationResult::error(format!("{:#}", e)).field("logging.rotation.max_size"));
+            }
+            if rotation.max_files == 0 {
+                result.push(ValidationResult::error("Must keep at least 1 file").field("logging.rotation.max_files"));
+            }
+            if logging.output != LogOutput::File {
+                result.push(
+                    ValidationResult::warning("rotation only applies to output: file").field("logging.rotation"),
+                );
+            }
+        }
+
+        if logging.format == LogFormat::Json && matches!(logging.output, LogOutput::Syslog | LogOutput::Journald) {
+            result.push(
+                ValidationResult::warning("format: json only applies to the stdout and file outputs")
+                    .field("logging.format"),
+            );
+        }
+
+        if let Some(syslog) = &logging.syslog {
+            if logging.output != LogOutput::Syslog {
+                result.push(ValidationResult::warning("syslog only applies to output: syslog").field("logging.syslog"));
+            }
+            match &syslog.server {
+                Some(server) => {
+                    let port = server.rsplit_once(':').map(|(host, port)| (host, port.parse::<u16>()));
+                    if !matches!(port, Some((host, Ok(_))) if !host.is_empty()) {
+                        result.push(
+                            ValidationResult::error(format!("Invalid syslog server '{}', expected host:port", server))
+                                .field("logging.syslog.server"),
+                        );
+                    }
+                }
+                None if syslog.protocol == SyslogProtocol::Tcp => {
+                    result.push(
+                        ValidationResult::warning("protocol only applies to a remote server")
+                            .field("logging.syslog.protocol"),
+                    );
+                }
+                None => {}
+            }
+        }
+    }
+
+    result
+}
+
+fn validate_user_group(user_group: &str) -> Option<String> {
+    let parts: Vec<&str> = user_group.split(':').collect();
+    let (user, group) = match parts.as_slice() {
+        [user] => (user, user), // Single value means same user and group
+        [user, group] => (user, group),
+        _ => return Some(format!("Invalid user:group format: '{}'", user_group)),
+    };
+
+    // Check if user exists (try both as name and uid)
+    let user_exists = if let Ok(uid) = user.parse::<u32>() {
+        Command::new("id")
+            .arg("-u")
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .arg(uid.to_string())
+            .status()
+            .map(|s| s.success())
+            .unwrap_or(false)
+    } else {
+        Command::new("id")
+            .arg(user)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map(|s| s.success())
+            .unwrap_or(false)
+    };
+
+    if !user_exists {
+        return Some(format!("User '{}' does not exist", user));
+    }
+
+    // Check if group exists (try both as name and gid)
+    let group_exists = Command::new("getent")
+        .args(["group", group])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false);
+
+    if !group_exists {
+        return Some(format!("Group '{}' does not exist", group));
+    }
+
+    None
+}
+
+fn validate_shell(shell: &str) -> Option<String> {
+    // Check if shell exists and is executable, names without a directory are searched in the PATH
+    let is_path = Path::new(shell).parent().is_some_and(|parent| !parent.as_os_str().is_empty());
+    if is_path && !Path::new(shell).exists() {
+        return Some(format!("Shell '{}' does not exist", shell));
+    }
+
+    if !shell_process(Some(shell), false, "exit 0")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+    {
+        return Some(format!("Shell '{}' is not executable or invalid", shell));
+    }
+
+    None
+}
+
+fn validate_output_path(path: &str) -> Option<String> {
+    let mut rest = path;
+    while let Some(start) = rest.find('{') {
+        let Some(end) = rest[start..].find('}') else { break };
+        let name = &rest[start + 1..start + end];
+        if !OUTPUT_PLACEHOLDERS.contains(&name) {
+            return Some(format!(
+                "Unknown placeholder '{{{}}}', valid placeholders are: {}",
+                name,
+                OUTPUT_PLACEHOLDERS.map(|p| format!("{{{}}}", p)).join(", ")
+            ));
+        }
+        rest = &rest[start + end..];
+    }
+
+    // Directories with placeholders are created before each execution, only the fixed part must exist
+    if let Some(start) = path.find('{') {
+        let dir = match path[..start].rfind('/') {
+            Some(0) => "/",
+            Some(end) => &path[..end],
+            None => ".",
+        };
+        return validate_output_dir(Path::new(path), Path::new(dir));
+    }
+
+    let path = Path::new(path);
+
+    // If path exists, it must be a file
+    if path.exists() && !path.is_file() {
+        return Some(format!(
+            "Path '{}' exists but is not a file",
+            path.display()
+        ));
+    }
+
+    // Check if parent directory exists and is writable
+    path.parent().and_then(|parent| validate_output_dir(path, parent))
+}
+
+fn validate_output_dir(path: &Path, parent: &Path) -> Option<String> {
+    if !parent.exists() {
+        return Some(format!(
+            "Parent directory '{}' does not exist",
+            parent.display()
+        ));
+    }
+
+    // Try to check if directory is writable
+    if !is_writable(parent) {
+        return Some(format!(
+            "Cannot create file '{}', parent directory '{}' is not writable",
+            path.display(),
+            parent.display()
+        ));
+    }
+
+    None
+}
+
+#[cfg(unix)]
+fn is_writable(dir: &Path) -> bool {
+    Command::new("test")
+        .args(["-w", &dir.to_string_lossy()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|s| s.success())
+        .unwrap_or(false)
+}
+
+/// Windows has no `test`, the read-only attribute is the closest check without creating a file
+#[cfg(windows)]
+fn is_writable(dir: &Path) -> bool {
+    std::fs::metadata(dir).map(|metadata| !metadata.permissions().readonly()).unwrap_or(false)
+}
+
+fn validate_alerts_config(conf: &ConfigFile) -> Vec<ValidationResult> {
+    let mut result = vec![];
+
+    let mut channels: Vec<_> = conf.alert_channels.iter().collect();
+    channels.sort_by_key(|(name, _)| *name);
+    for (name, alert) in channels {
+        let field = format!("alert_channels.{}", name);
+        if let Alert::Channel(_) = alert {
+            result.push(ValidationResult::error("An alert channel can't refer to another channel").field(&field));
+            continue;
+        }
+        result.extend(validate_alert(conf, alert, &field));
+    }
+
+    if let Some(alerts) = &conf.alerts {
+        for (i, alert) in alerts.on_failure.iter().enumerate() {
+            result.extend(validate_alert(conf, alert, &format!("alerts.on_failure[{}]", i)));
+        }
+        for (i, step) in alerts.escalation.iter().enumerate() {
+            result.extend(validate_alert(conf, &step.alert, &format!("alerts.escalation[{}].alert", i)));
+        }
+        for (i, alert) in alerts.on_internal_error.iter().enumerate() {
+            result.extend(validate_alert(conf, alert, &format!("alerts.on_internal_error[{}]", i)));
+        }
+        for (i, alert) in alerts.on_clock_change.iter().enumerate() {
+            result.extend(validate_alert(conf, alert, &format!("alerts.on_clock_change[{}]", i)));
+        }
+        for (i, alert) in alerts.on_budget_exhausted.iter().enumerate() {
+            result.extend(validate_alert(conf, alert, &format!("alerts.on_budget_exhausted[{}]", i)));
+        }
+        if let Err(e) = alerts.check_escalation() {
+            result.push(ValidationResult::error(e.to_string()).field("alerts.escalation"));
+        }
+        if let Some(queue) = &alerts.queue {
+            if queue.size == Some(0) {
+                result.push(ValidationResult::error("The alert queue size must be greater than 0").field("alerts.queue.size"));
+            }
+            if queue.workers == Some(0) {
+                result.push(ValidationResult::error("The alert queue must have at least one worker").field("alerts.queue.workers"));
+            }
+        }
+    }
+
+    result
+}
+
+/// The modules of the plugins must load and export a function cron-rs calls
+fn validate_plugins(conf: &ConfigFile) -> Vec<ValidationResult> {
+    let mut result = vec![];
+    let mut plugins: Vec<_> = conf.plugins.iter().collect();
+    plugins.sort_by_key(|(name, _)| *name);
+    for (name, plugin) in plugins {
+        let field = format!("plugins.{}.path", name);
+        match load_plugin_module(&plugin.path) {
+            Ok(module) if !module_exports(&module, ALERT_EXPORT) && !module_exports(&module, CONDITION_EXPORT) => {
+                let error = format!("The plugin exports neither {} nor {}", ALERT_EXPORT, CONDITION_EXPORT);
+                result.push(ValidationResult::error(error).field(&field));
+            }
+            Ok(_) => {}
+            Err(e) => result.push(ValidationResult::error(format!("{:#}", e)).field(&field)),
+        }
+    }
+    result
+}
+
+fn only_if_plugin(task: &TaskDefinition) -> Option<&str> {
+    task.only_if.as_ref().and_then(|only_if| only_if.plugin.as_deref())
+}
+
+/// The error of a plugin used for something it doesn't export. The plugins that don't load are reported by
+/// [validate_plugins]
+fn missing_export(conf: &ConfigFile, plugin: &str, export: &str) -> Option<String> {
+    let module = load_plugin_module(&conf.plugins.get(plugin)?.path).ok()?;
+    (!module_exports(&module, export)).then(|| format!("Plugin '{}' doesn't export {}", plugin, export))
+}
+
+/// Checks the settings of an alert, `prefix` is the field of the alert
+fn validate_alert(conf: &ConfigFile, alert: &Alert, prefix: &str) -> Vec<ValidationResult> {
+    let mut result = vec![];
+    let field = |name: &str| format!("{}.{}", prefix, name);
+
+    match alert {
+        Alert::Email {
+            from,
+            to,
+            cc,
+            bcc,
+            reply_to,
+            smtp_server,
+            smtp_port,
+            ..
+        } => {
+            if to.iter().next().is_none() {
+                result.push(ValidationResult::error("Email alert must have at least one recipient").field(&field("to")));
+            }
+            let addresses = [("to", Some(to)), ("cc", cc.as_ref()), ("bcc", bcc.as_ref())]
+                .into_iter()
+                .flat_map(|(name, addresses)| addresses.into_iter().flat_map(|a| a.iter()).map(move |address| (name, address)))
+                .chain(reply_to.as_deref().map(|address| ("reply_to", address)));
+            for (name, address) in addresses {
+                if let Err(e) = address.parse::<Mailbox>() {
+                    result.push(
+                        ValidationResult::error(format!("Invalid email address '{}': {}", address, e)).field(&field(name)),
+                    );
+                }
+            }
+            match from {
+                None => {
+                    result.push(
+                        ValidationResult::warning(
+                            "Email alert 'from' address is not set, defaulting to cron-rs@localhost",
+                        )
+                        .field(&field("from")),
+                    );
+                }
+                Some(from) => match from.parse::<Mailbox>() {
+                    Ok(_) => {}
+                    Err(e) => {
+                        result.push(
+                            ValidationResult::error(format!("Invalid email address '{}': {}", from, e))
+                                .field(&field("from")),
+                        );
+                    }
+                },
+            }
+
+            if let Some(smtp_server) = smtp_server {
+                if smtp_server.is_empty() {
+                    result.push(ValidationResult::error("SMTP server must not be empty").field(&field("smtp_server")));
+                }
+            } else {
+                result.push(
+                    ValidationResult::warning("SMTP server is not set, defaulting to localhost")
+                        .field(&field("smtp_server")),
+                );
+            }
+
+            if let Some(smtp_port) = smtp_port {
+                if *smtp_port == 0 {
+                    result.push(ValidationResult::error("SMTP port must be greater than 0").field(&field("smtp_port")));
+                }
+            } else {
+                result.push(ValidationResult::warning("SMTP port is not set, defaulting to 25").field(&field("smtp_port")));
+            }
+        }
+        Alert::Cmd { timeout, .. } => {
+            if let Some(Err(e)) = timeout.as_deref().map(Schedule::parse_time_duration) {
+                result.push(ValidationResult::error(format!("Invalid 'timeout' format: {}", e)).field(&field("timeout")));
+            }
+        }
+        Alert::Webhook {
+            url,
+            method,
+            expect_status,
+            ..
+        } => {
+            if url.is_empty() {
+                result.push(ValidationResult::error("Webhook URL must not be empty").field(&field("url")));
+            }
+
+            let codes = match expect_status {
+                Some(StatusCodes::One(code)) => vec![*code],
+                Some(StatusCodes::List(codes)) => codes.clone(),
+                None => vec![],
+            };
+            if matches!(expect_status, Some(StatusCodes::List(codes)) if codes.is_empty()) {
+                result.push(ValidationResult::error("expect_status must not be empty").field(&field("expect_status")));
+            }
+            for code in codes.into_iter().filter(|code| !(100..600).contains(code)) {
+                result.push(
+                    ValidationResult::error(format!("Invalid HTTP status code {}", code)).field(&field("expect_status")),
+                );
+            }
+
+            if let Some(method) = method {
+                if method.is_empty() {
+                    result.push(ValidationResult::error("Webhook method must not be empty").field(&field("method")));
+                }
+                if method != "POST"
+                    && method != "GET"
+                    && method != "PUT"
+                    && method != "PATCH"
+                    && method != "DELETE"
+                {
+                    result.push(
+                        ValidationResult::error(format!(
+                            "Invalid webhook method '{}', must be one of: POST, GET, PUT, PATCH, DELETE",
+                            method
+                        ))
+                        .field(&field("method")),
+                    );
+                }
+            }
+        }
+        Alert::Plugin { plugin } => {
+            if !conf.plugins.contains_key(plugin) {
+                result.push(ValidationResult::error(format!("Unknown plugin '{}'", plugin)).field(&field("plugin")));
+            } else if let Some(error) = missing_export(conf, plugin, ALERT_EXPORT) {
+                result.push(ValidationResult::error(error).field(&field("plugin")));
+            }
+        }
+        Alert::Channel(name) => {
+            if !conf.alert_channels.contains_key(name) {
+                result.push(ValidationResult::error(format!("Unknown alert channel '{}'", name)).field(prefix));
+            }
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Field and message of the errors of the config with the tasks
+    fn errors(tasks: &str) -> Vec<(Option<String>, String)> {
+        let conf: ConfigFile = serde_yml::from_str(&format!("tasks: {}", tasks)).expect(tasks);
+        validate_config(&conf).into_iter().filter(|d| d.is_error()).map(|d| (d.field, d.message)).collect()
+    }
+
+    #[test]
+    fn test_patterns_that_never_match() {
+        let never = (Some("when".to_string()), "The 'when' pattern never matches a future date, the task will never run".to_string());
+
+        assert!(errors("[{ name: a, cmd: 'true', when: '* *-02-28 00:00:00' }]").is_empty());
+        let feb_30 = errors("[{ name: a, cmd: 'true', when: '* *-02-30 00:00:00' }]");
+        assert_eq!(feb_30.len(), 1);
+        assert_eq!(feb_30[0], never);
+        assert!(errors("[{ name: a, cmd: 'true', when: '* *-04-31 00:00:00' }]").contains(&never));
+        assert!(errors("[{ name: a, cmd: 'true', when: '* 2020-*-* 00:00:00' }]").contains(&never));
+        // The 13th of a month falls on a Friday every year
+        assert!(errors("[{ name: a, cmd: 'true', when: 'Fri *-*-13 00:00:00' }]").is_empty());
+        assert!(errors("[{ name: a, cmd: 'true', when: { month: 2, day: 30 } }]").contains(&never));
+        assert!(errors("[{ name: a, cmd: 'true', when: { month: 2, day: 29 } }]").is_empty());
+    }
+
+    #[test]
+    fn test_pattern_ranges() {
+        let passing = [
+            "'* *-*-* 00:00:59'",
+            "'* *-*-* 00:59:00'",
+            "'* *-*-* 23:00:00'",
+            "'* *-*-31 00:00:00'",
+            "'* *-12-* 00:00:00'",
+            "'[0,7] *-*-* 00:00:00'",
+            "'* *-*-* 00:*/59:00'",
+            "{ minute: '10..20' }",
+        ];
+        for when in passing {
+            assert!(errors(&format!("[{{ name: a, cmd: 'true', when: {} }}]", when)).is_empty(), "{}", when);
+        }
+
+        let failing = [
+            ("'* *-*-* 00:00:60'", "second"),
+            ("'* *-*-* 00:75:00'", "minute"),
+            ("'* *-*-* 24:00:00'", "hour"),
+            ("'* *-*-32 00:00:00'", "day"),
+            ("'* *-13-* 00:00:00'", "month"),
+            ("'8 *-*-* 00:00:00'", "day_of_week"),
+            ("'* *-*-* */0:00:00'", "hour"),
+            ("'* *-*-* 00:*/5+60:00'", "minute"),
+            ("{ minute: '20..10' }", "minute"),
+            ("{ minute: 99 }", "minute"),
+        ];
+        for (when, field) in failing {
+            let errors = errors(&format!("[{{ name: a, cmd: 'true', when: {} }}]", when));
+            assert!(errors.iter().any(|(_, message)| message.contains(&format!("Malformed field: {}", field))), "{}: {:?}", when, errors);
+        }
+
+        assert!(errors("[{ name: a, cmd: 'true', every: 1 hour, on: [Mon, '7'] }]").is_empty());
+        let errors = errors("[{ name: a, cmd: 'true', every: 1 hour, on: [Mon, '8'] }]");
+        assert_eq!(errors, [(Some("on".to_string()), "Invalid 'on' format: Value 8 is out of range, must be between 0 and 7".to_string())]);
+    }
+
+    #[test]
+    fn test_after_task() {
+        assert!(errors("[{ name: a, cmd: 'true', every: 1 hour }, { name: b, cmd: 'true', after_task: { name: a } }]").is_empty());
+        assert_eq!(
+            errors("[{ name: b, cmd: 'true', after_task: { name: a, on: failure } }]"),
+            vec![(Some("after_task".to_string()), "Unknown task 'a'".to_string())]
+        );
+        assert_eq!(
+            errors("[{ name: a, cmd: 'true', after_task: { name: a } }]"),
+            vec![(Some("after_task".to_string()), "A task can't run after itself".to_string())]
+        );
+        let looped = errors("[{ name: a, cmd: 'true', after_task: { name: b } }, { name: b, cmd: 'true', after_task: { name: a } }]");
+        assert_eq!(looped.len(), 2);
+        assert_eq!(looped[0].1, "The tasks run after each other in a loop: a -> b -> a");
+        assert!(!errors("[{ name: a, cmd: 'true', every: 1 hour, after_task: { name: b } }, { name: b, cmd: 'true', every: 1 hour }]").is_empty());
+    }
+
+    #[test]
+    fn test_added_tasks() {
+        let conf: ConfigFile = serde_yml::from_str("{ tasks: [], templates: { limited: { time_limit: 1 hour } } }").unwrap();
+        let task = |yaml: &str| -> TaskDefinition { serde_yml::from_str(yaml).unwrap() };
+        let errors = |yaml: &str| -> Vec<String> {
+            let diagnostics = validate_added_tasks(&conf, &[task(yaml)], vec!["extract".to_string()]);
+            diagnostics.into_iter().filter(|d| d.is_error()).map(|d| d.message).collect()
+        };
+        // The templates of the config file and the tasks of the scheduler can be used
+        assert!(errors("{ name: backup, cmd: 'true', every: 1 day, extends: limited }").is_empty());
+        assert!(errors("{ name: load, cmd: 'true', after_task: { name: extract } }").is_empty());
+        assert_eq!(errors("{ name: load, cmd: 'true', after_task: { name: report } }"), vec!["Unknown task 'report'"]);
+        assert_eq!(errors("{ name: backup, cmd: 'true', extends: weekly }"), vec!["Unknown template 'weekly'"]);
+    }
+
+    #[test]
+    fn test_pipelines() {
+        let errors = |pipelines: &str| {
+            let yaml = format!("tasks: [{{ name: a, cmd: 'true', every: 1 hour }}]\npipelines: {}", pipelines);
+            let conf: ConfigFile = serde_yml::from_str(&yaml).unwrap();
+            validate_config(&conf).into_iter().filter(|d| d.is_error()).map(|d| d.message).collect::<Vec<_>>()
+        };
+        assert!(errors("[{ name: p, every: 1 day, steps: [{ task: a }, { parallel: [{ cmd: 'true' }, { task: a }] }] }]").is_empty());
+        assert_eq!(errors("[{ name: p, every: 1 day, steps: [{ task: b }] }]"), vec!["Unknown task 'b'"]);
+        assert_eq!(
+            errors("[{ name: a, every: 1 day, steps: [{ task: a }] }]"),
+            vec!["Pipelines must have a unique name, also among the tasks"]
+        );
+        assert_eq!(
+            errors("[{ name: p, every: 1 day, steps: [{ parallel: [{ parallel: [{ task: a }] }] }] }]"),
+            vec!["Parallel groups can't be nested"]
+        );
+        assert_eq!(errors("[{ name: p, steps: [{ task: a }] }]"), vec!["No schedule specified for task 'p'"]);
+    }
+
+    #[test]
+    fn test_alert_channels() {
+        let errors = |yaml: &str| {
+            let conf: ConfigFile = serde_yml::from_str(yaml).unwrap();
+            validate_config(&conf).into_iter().filter(|d| d.is_error()).map(|d| (d.field, d.message)).collect::<Vec<_>>()
+        };
+        let channels = "alert_channels: { pager: { type: cmd, cmd: page }, other: pager, broken: { type: webhook, url: '' } }\n";
+        let found = errors(&format!(
+            "{}alerts: {{ on_failure: [pager, missing] }}\ntasks: [{{ name: a, cmd: 'true', every: 1 hour, on_failure: [nope] }}]",
+            channels
+        ));
+        let expected = [
+            ("alert_channels.broken.url", "Webhook URL must not be empty"),
+            ("alert_channels.other", "An alert channel can't refer to another channel"),
+            ("alerts.on_failure[1]", "Unknown alert channel 'missing'"),
+            ("alerts", "Unknown alert channel 'nope'"),
+        ];
+        let found: Vec<(&str, &str)> = found.iter().map(|(field, message)| (field.as_deref().unwrap(), message.as_str())).collect();
+        for error in expected {
+            assert!(found.contains(&error), "{:?} not in {:?}", error, found);
+        }
+    }
+
+    #[test]
+    fn test_plugins() {
+        let path = std::env::temp_dir().join(format!("cron-rs-test-validate-plugin-{}.wat", std::process::id()));
+        std::fs::write(&path, r#"(module (func (export "send_alert") (param i32 i32) (result i32) (i32.const 0)))"#).unwrap();
+        let yaml = format!(
+            "plugins: {{ notify: {{ path: '{}' }}, broken: {{ path: /missing.wasm }} }}\n\
+             alerts: {{ on_failure: [{{ type: plugin, plugin: notify }}, {{ type: plugin, plugin: pager }}] }}\n\
+             tasks:\n\
+             \x20 - {{ name: a, cmd: 'true', every: 1 hour, only_if: {{ plugin: notify }} }}\n\
+             \x20 - {{ name: b, cmd: 'true', every: 1 hour, only_if: {{ plugin: gate }} }}",
+            path.display()
+        );
+        let conf: ConfigFile = serde_yml::from_str(&yaml).unwrap();
+        let found: Vec<_> = validate_config(&conf).into_iter().filter(|d| d.is_error()).map(|d| (d.task, d.field.unwrap(), d.message)).collect();
+        std::fs::remove_file(&path).unwrap();
+
+        let task = |name: &str| Some(name.to_string());
+        assert_eq!(found.len(), 4, "{:?}", found);
+        assert!(found.contains(&(task("a"), "only_if.plugin".to_string(), "Plugin 'notify' doesn't export check_condition".to_string())));
+        assert!(found.contains(&(task("b"), "only_if.plugin".to_string(), "Unknown plugin 'gate'".to_string())));
+        assert!(found.contains(&(None, "alerts.on_failure[1].plugin".to_string(), "Unknown plugin 'pager'".to_string())));
+        assert!(found.iter().any(|(_, field, message)| field == "plugins.broken.path" && message.contains("/missing.wasm")));
+    }
+
+    #[test]
+    fn test_sentry_dsn() {
+        let errors = |sentry: &str| {
+            let mut value: serde_yml::Value = serde_yml::from_str(&format!("sentry: {}\ntasks: []", sentry)).unwrap();
+            crate::config::secrets::mark_secrets(&mut value).unwrap();
+            let conf: ConfigFile = serde_yml::from_value(value).unwrap();
+            validate_config(&conf).into_iter().filter(|d| d.is_error()).map(|d| d.field.unwrap()).collect::<Vec<_>>()
+        };
+        assert!(errors("{ dsn: 'https://abc@o1.ingest.sentry.io/42', environment: production }").is_empty());
+        assert!(errors("{ dsn: !secret file:/run/secrets/sentry-dsn }").is_empty());
+        assert_eq!(errors("{ dsn: 'https://o1.ingest.sentry.io/42' }"), ["sentry.dsn"]);
+    }
+
+    #[test]
+    fn test_email_recipients() {
+        let errors = |alert: &str| {
+            let conf: ConfigFile = serde_yml::from_str(&format!("alerts: {{ on_failure: [{}] }}\ntasks: []", alert)).unwrap();
+            validate_config(&conf).into_iter().filter(|d| d.is_error()).map(|d| (d.field.unwrap(), d.message)).collect::<Vec<_>>()
+        };
+        let valid = "{ type: email, to: [a@example.com, b@example.com], cc: c@example.com, bcc: [d@example.com], reply_to: e@example.com }";
+        assert!(errors(valid).is_empty());
+
+        let found = errors("{ type: email, to: [a@example.com, nope], cc: [b@example.com, 'c@'], reply_to: wrong }");
+        let fields: Vec<&str> = found.iter().map(|(field, _)| field.as_str()).collect();
+        assert_eq!(fields, ["alerts.on_failure[0].to", "alerts.on_failure[0].cc", "alerts.on_failure[0].reply_to"]);
+        assert!(found[0].1.starts_with("Invalid email address 'nope'"), "{:?}", found);
+
+        assert_eq!(
+            errors("{ type: email, to: [] }"),
+            [("alerts.on_failure[0].to".to_string(), "Email alert must have at least one recipient".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_alert_queue() {
+        let errors = |queue: &str| {
+            let conf: ConfigFile = serde_yml::from_str(&format!("alerts: {{ queue: {} }}\ntasks: []", queue)).unwrap();
+            validate_config(&conf).into_iter().filter(|d| d.is_error()).map(|d| d.field.unwrap()).collect::<Vec<_>>()
+        };
+        assert!(errors("{ size: 10, workers: 2, queue_full_policy: drop_oldest }").is_empty());
+        assert_eq!(errors("{ size: 0, workers: 0 }"), ["alerts.queue.size", "alerts.queue.workers"]);
+    }
+
+    #[test]
+    fn test_webhook_expect_status() {
+        let errors = |alert: &str| {
+            let conf: ConfigFile = serde_yml::from_str(&format!("alerts: {{ on_failure: [{}] }}\ntasks: []", alert)).unwrap();
+            validate_config(&conf).into_iter().filter(|d| d.is_error()).map(|d| d.message).collect::<Vec<_>>()
+        };
+        assert!(errors("{ type: webhook, url: x, expect_status: [200, 202], expect_body_contains: ok }").is_empty());
+        assert_eq!(errors("{ type: webhook, url: x, expect_status: 2000 }"), ["Invalid HTTP status code 2000"]);
+        assert_eq!(errors("{ type: webhook, url: x, expect_status: [] }"), ["expect_status must not be empty"]);
+    }
+
+    #[test]
+    fn test_cmd_alert() {
+        let errors = |alert: &str| {
+            let conf: ConfigFile = serde_yml::from_str(&format!("alerts: {{ on_failure: [{}] }}\ntasks: []", alert)).unwrap();
+            validate_config(&conf).into_iter().filter(|d| d.is_error()).map(|d| d.message).collect::<Vec<_>>()
+        };
+        assert!(errors("{ type: cmd, cmd: ./pagerduty.py, plugin: true, timeout: 10 seconds }").is_empty());
+        assert_eq!(errors("{ type: cmd, cmd: x, timeout: soon }").len(), 1);
+    }
+
+    #[test]
+    fn test_validation_report() {
+        let warning = ValidationResult::warning("max_runs is 0, the task will never run").task("backup").field("max_runs");
+        let error = ValidationResult::error("Config file not found");
+
+        assert!(ValidationReport::new(vec![], true).valid);
+        assert!(ValidationReport::new(vec![warning.clone()], false).valid);
+        assert!(!ValidationReport::new(vec![warning.clone()], true).valid);
+        assert!(!ValidationReport::new(vec![error.clone()], false).valid);
+
+        // The task and the field are left out when unknown
+        let report = ValidationReport::new(vec![warning, error], false);
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "valid": false,
+                "diagnostics": [
+                    { "severity": "warning", "task": "backup", "field": "max_runs", "message": "max_runs is 0, the task will never run" },
+                    { "severity": "error", "message": "Config file not found" },
+                ],
+            })
+        );
+    }
+}
@@ -0,0 +1,132 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Timelike};
+use chrono_tz::Tz;
+use nom::bytes::complete::tag;
+use nom::combinator::{all_consuming, map_opt, opt};
+use nom::sequence::{preceded, separated_pair, tuple};
+use nom::IResult;
+use std::fmt::{Display, Formatter};
+
+use super::{dayofweek, number, ws, TimePatternField};
+
+/// Restricts an `every` schedule to a time range of the day and, optionally, to some days of the week
+/// e.g. `between: 08:00..20:00` and `on: [Mon..Fri]`
+#[derive(Debug, Clone)]
+pub struct TimeWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    /// Days of the week in which the window opens, 0 = Sunday, 1 = Monday, ..., 6 = Saturday
+    pub days: TimePatternField,
+}
+
+impl TimeWindow {
+    /// Parses a time range like `08:00..20:00` or `22:00:00..06:00:00`, the end is included
+    pub fn parse_between(input: &str) -> Result<(NaiveTime, NaiveTime)> {
+        let res = all_consuming(ws(separated_pair(time_of_day, ws(tag("..")), time_of_day)))(input);
+        let (_, (start, end)) = res.map_err(|e| anyhow!("Failed to parse time range '{}': {}", input, e))?;
+        Ok((start, end))
+    }
+
+    /// True if the window opens and closes on the same day
+    fn is_same_day(&self) -> bool {
+        self.start <= self.end
+    }
+
+    fn matches_day(&self, date: &DateTime<Tz>) -> bool {
+        dayofweek::matches_weekday(&self.days, date.weekday())
+    }
+
+    /// Checks if the given date is inside the window
+    pub fn contains(&self, date: &DateTime<Tz>) -> bool {
+        let time = date.time();
+
+        if self.is_same_day() {
+            return self.matches_day(date) && time >= self.start && time <= self.end;
+        }
+
+        // The window crosses midnight, the part after midnight belongs to the previous day
+        if time >= self.start {
+            self.matches_day(date)
+        } else if time <= self.end {
+            self.matches_day(&(*date - TimeDelta::days(1)))
+        } else {
+            false
+        }
+    }
+
+    /// Returns the given date if it's inside the window, or the next time the window opens. None if no day of the week
+    /// matches the window
+    pub fn next_open_time(&self, date: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        if self.contains(&date) {
+            return Some(date);
+        }
+
+        let timezone = date.timezone();
+        // Two weeks, a matching day may be skipped by a DST change and the next one is a week later
+        for offset in 0..=14 {
+            let day = date.date_naive() + TimeDelta::days(offset);
+            let Some(candidate) = first_valid_time(timezone, day.and_time(self.start)) else { continue };
+            if candidate > date && self.contains(&candidate) {
+                return Some(candidate);
+            }
+        }
+        None
+    }
+}
+
+/// The local time, or the end of the DST gap when the clocks skip it
+fn first_valid_time(timezone: Tz, time: NaiveDateTime) -> Option<DateTime<Tz>> {
+    // The gaps are shorter than a day, they are found by the minute
+    (0..=24 * 60)
+        .map(|minutes| time + TimeDelta::minutes(minutes))
+        .find_map(|time| timezone.from_local_datetime(&time).earliest())
+        .map(|found| if found.naive_local() == time { found } else { found.with_second(0).unwrap_or(found) })
+}
+
+fn time_of_day(input: &str) -> IResult<&str, NaiveTime> {
+    map_opt(
+        tuple((number, preceded(tag(":"), number), opt(preceded(tag(":"), number)))),
+        |(hour, minute, second)| NaiveTime::from_hms_opt(hour, minute, second.unwrap_or(0)),
+    )(input)
+}
+
+impl Display for TimeWindow {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "between {}..{}", self.start, self.end)?;
+        if !matches!(self.days, TimePatternField::Any) {
+            write!(f, " on {}", self.days)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::America::New_York;
+
+    #[test]
+    fn test_next_open_time() {
+        let window = |start: (u32, u32), end: (u32, u32), days: TimePatternField| TimeWindow {
+            start: NaiveTime::from_hms_opt(start.0, start.1, 0).unwrap(),
+            end: NaiveTime::from_hms_opt(end.0, end.1, 0).unwrap(),
+            days,
+        };
+        let at = |day: u32, hour: u32, minute: u32| New_York.with_ymd_and_hms(2025, 3, day, hour, minute, 0).unwrap();
+
+        let business = window((9, 0), (17, 0), TimePatternField::Range(1, 5));
+        assert_eq!(business.next_open_time(at(10, 12, 0)), Some(at(10, 12, 0)));
+        // Saturday, the window opens on Monday
+        assert_eq!(business.next_open_time(at(8, 12, 0)), Some(at(10, 9, 0)));
+
+        // 02:30 doesn't exist on Sunday 2025-03-09, the window opens when the clocks jump to 03:00
+        let sunday = window((2, 30), (4, 0), TimePatternField::Value(0));
+        assert_eq!(sunday.next_open_time(at(8, 12, 0)), Some(at(9, 3, 0)));
+        // The window is skipped by the DST change that day
+        let skipped = window((2, 15), (2, 45), TimePatternField::Value(0));
+        assert_eq!(skipped.next_open_time(at(8, 12, 0)), Some(at(16, 2, 15)));
+
+        let never = window((9, 0), (17, 0), TimePatternField::List(vec![]));
+        assert_eq!(never.next_open_time(at(8, 12, 0)), None);
+    }
+}
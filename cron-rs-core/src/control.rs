@@ -0,0 +1,451 @@
+use crate::config::file::TaskDefinition;
+use crate::logging;
+use crate::resource_usage::ResourceUsage;
+use crate::scheduler::Scheduler;
+use crate::utils::format_duration;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, FixedOffset, Utc};
+use log::{debug, error, info, LevelFilter};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt};
+use tokio::sync::Mutex;
+
+/// Default location of the control socket, next to the scheduler state file
+pub const DEFAULT_SOCKET_PATH: &str = "./cron-rs.sock";
+
+/// Requests accepted by the control socket, one JSON object per line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum ControlRequest {
+    /// Allow a disabled task to be scheduled again
+    Enable { task: String },
+    /// Keep a task in the scheduler, but skip its executions
+    Disable { task: String },
+    /// Change the level of the log until the scheduler restarts, e.g. `debug`
+    LogLevel { level: String },
+    /// The running executions and the next run of each task
+    Status,
+    /// Run a task now. With `wait` the response is sent once the execution finishes, with `force` the task runs even if
+    /// it's disabled or already running with `avoid_overlapping`
+    Trigger {
+        task: String,
+        #[serde(default)]
+        wait: bool,
+        #[serde(default)]
+        force: bool,
+    },
+    /// Schedule a new task, written like the tasks of the config file. It's lost when the config is reloaded
+    AddTask { task: Box<TaskDefinition> },
+    /// Stop scheduling a task until the config is reloaded, a running execution is left to finish
+    RemoveTask { task: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ControlResponse {
+    pub ok: bool,
+    pub message: String,
+    /// Set in the response to [ControlRequest::Status]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<SchedulerStatus>,
+    /// Set in the response to a [ControlRequest::Trigger] that waits for the execution
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run: Option<FinishedRunStatus>,
+}
+
+impl ControlResponse {
+    pub fn ok(message: impl Into<String>) -> Self {
+        Self {
+            ok: true,
+            message: message.into(),
+            status: None,
+            run: None,
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            message: message.into(),
+            status: None,
+            run: None,
+        }
+    }
+}
+
+/// What the scheduler is doing at the time of the request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchedulerStatus {
+    pub running: Vec<RunningTaskStatus>,
+    pub tasks: Vec<TaskStatus>,
+    /// The last executions that finished, the newest first
+    #[serde(default)]
+    pub recent: Vec<FinishedRunStatus>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunningTaskStatus {
+    pub task_name: String,
+    pub run_id: String,
+    /// 0 for closures and pipelines
+    pub pid: u32,
+    pub start_time: DateTime<Utc>,
+    pub elapsed_seconds: f64,
+    /// Time until the execution is stopped by its `time_limit`
+    pub time_left_seconds: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskStatus {
+    pub name: String,
+    pub enabled: bool,
+    /// None for the tasks that wait for a trigger and the ones that never run again
+    pub next_run: Option<DateTime<FixedOffset>>,
+    /// -1 if the last execution was killed
+    pub last_exit_code: Option<i32>,
+    /// Failed executions in a row, 0 after a successful one
+    #[serde(default)]
+    pub consecutive_failures: u32,
+    /// Runtime of the executions in the current month, see `max_monthly_runtime`
+    #[serde(default)]
+    pub monthly_runtime_seconds: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FinishedRunStatus {
+    pub task_name: String,
+    pub run_id: String,
+    pub end_time: DateTime<Utc>,
+    pub duration_seconds: f64,
+    /// -1 if the execution was killed
+    pub exit_code: i32,
+    /// Whether it met the success criteria of the task
+    pub success: bool,
+    /// Only known for the commands on Linux
+    #[serde(default)]
+    pub usage: Option<ResourceUsage>,
+}
+
+impl SchedulerStatus {
+    /// Tables of the running executions and of the tasks, as shown by `cron-rs status`
+    pub fn text(&self) -> String {
+        let mut output = String::new();
+        if self.running.is_empty() {
+            output.push_str("No running tasks\n");
+        } else {
+            let width = self.running.iter().map(|t| t.task_name.len()).max().unwrap_or(0).max(4);
+            output.push_str(&format!("{:<width$}  {:<24}  {:>7}  {:<11}  {}\n", "TASK", "RUN ID", "PID", "ELAPSED", "TIME LEFT"));
+            for task in &self.running {
+                output.push_str(&format!(
+                    "{:<width$}  {:<24}  {:>7}  {:<11}  {}\n",
+                    task.task_name,
+                    task.run_id,
+                    if task.pid == 0 { "-".to_string() } else { task.pid.to_string() },
+                    format_seconds(task.elapsed_seconds),
+                    task.time_left_seconds.map(format_seconds).unwrap_or_else(|| "-".to_string()),
+                ));
+            }
+        }
+
+        output.push('\n');
+        let width = self.tasks.iter().map(|t| t.name.len()).max().unwrap_or(0).max(4);
+        output.push_str(&format!("{:<width$}  {:<8}  {:<26}  {}\n", "TASK", "STATE", "NEXT RUN", "LAST EXIT"));
+        for task in &self.tasks {
+            output.push_str(&format!(
+                "{:<width$}  {:<8}  {:<26}  {}\n",
+                task.name,
+                if task.enabled { "enabled" } else { "disabled" },
+                task.next_run.map(|time| time.format("%Y-%m-%d %H:%M:%S %:z").to_string()).unwrap_or_else(|| "-".to_string()),
+                task.last_exit_code.map(|code| code.to_string()).unwrap_or_else(|| "-".to_string()),
+            ));
+        }
+        output
+    }
+}
+
+/// Whole seconds, like `1 m, 5 s`
+fn format_seconds(seconds: f64) -> String {
+    format_duration(Duration::from_secs(seconds.max(0.0) as u64))
+}
+
+/// Listen for control requests until the scheduler stops
+#[cfg(unix)]
+pub async fn listen(socket_path: PathBuf, scheduler: Arc<Mutex<Scheduler>>) {
+    use std::os::unix::fs::PermissionsExt;
+    use tokio::net::UnixListener;
+
+    // A previous run that didn't shut down cleanly leaves the socket file behind
+    if socket_path.exists() {
+        if let Err(e) = std::fs::remove_file(&socket_path) {
+            error!("Failed to remove stale control socket {}: {}", socket_path.display(), e);
+            return;
+        }
+    }
+
+    let listener = match UnixListener::bind(&socket_path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to bind control socket {}: {}", socket_path.display(), e);
+            return;
+        }
+    };
+    // The requests can add tasks, only the user of the scheduler can send them
+    if let Err(e) = std::fs::set_permissions(&socket_path, std::fs::Permissions::from_mode(0o600)) {
+        error!("Failed to restrict the permissions of the control socket {}: {}", socket_path.display(), e);
+        return;
+    }
+    info!("Listening for control requests on {}", socket_path.display());
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                let scheduler = scheduler.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = handle_connection(stream, scheduler).await {
+                        error!("Control connection failed: {}", e);
+                    }
+                });
+            }
+            Err(e) => {
+                error!("Failed to accept control connection: {}", e);
+            }
+        }
+    }
+}
+
+/// Windows has no unix sockets, the requests go through a named pipe, see [pipe_name]. Each connection takes an instance
+/// of the pipe, a new one is created for the next client before handling it
+#[cfg(windows)]
+pub async fn listen(socket_path: PathBuf, scheduler: Arc<Mutex<Scheduler>>) {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let name = pipe_name(&socket_path);
+    let mut server = match ServerOptions::new().first_pipe_instance(true).create(&name) {
+        Ok(server) => server,
+        Err(e) => {
+            error!("Failed to create the control pipe {}: {}", name, e);
+            return;
+        }
+    };
+    info!("Listening for control requests on {}", name);
+
+    loop {
+        if let Err(e) = server.connect().await {
+            error!("Failed to accept control connection: {}", e);
+            continue;
+        }
+        let stream = server;
+        server = match ServerOptions::new().create(&name) {
+            Ok(server) => server,
+            Err(e) => {
+                error!("Failed to create the control pipe {}: {}", name, e);
+                return;
+            }
+        };
+
+        let scheduler = scheduler.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, scheduler).await {
+                error!("Control connection failed: {}", e);
+            }
+        });
+    }
+}
+
+/// Named pipe of the scheduler with the given control socket, named after the absolute path so the schedulers of
+/// different directories don't share it
+#[cfg(windows)]
+fn pipe_name(socket_path: &Path) -> String {
+    let path = std::path::absolute(socket_path).unwrap_or_else(|_| socket_path.to_path_buf());
+    format!(r"\\.\pipe\cron-rs-{}", sanitise_file_name::sanitise(&path.to_string_lossy()))
+}
+
+async fn handle_connection<S>(stream: S, scheduler: Arc<Mutex<Scheduler>>) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = tokio::io::BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await? {
+        let response = match serde_json::from_str::<ControlRequest>(&line) {
+            Ok(request) => {
+                debug!("Received control request: {:?}", request);
+                handle_request(request, &scheduler).await
+            }
+            Err(e) => ControlResponse::error(format!("Invalid request: {}", e)),
+        };
+
+        let mut response = serde_json::to_string(&response)?;
+        response.push('\n');
+        writer.write_all(response.as_bytes()).await?;
+    }
+
+    Ok(())
+}
+
+async fn handle_request(request: ControlRequest, scheduler: &Arc<Mutex<Scheduler>>) -> ControlResponse {
+    match request {
+        ControlRequest::Enable { task } => set_task_enabled(scheduler, &task, true).await,
+        ControlRequest::Disable { task } => set_task_enabled(scheduler, &task, false).await,
+        ControlRequest::Status => {
+            let status = scheduler.lock().await.status().await;
+            ControlResponse { status: Some(status), ..ControlResponse::ok("") }
+        }
+        ControlRequest::AddTask { task } => add_task(scheduler, *task).await,
+        ControlRequest::RemoveTask { task } => match Scheduler::remove_task(scheduler, &task).await {
+            Ok(()) => {
+                info!("Task '{}' removed through the control socket", task);
+                ControlResponse::ok(format!("Task '{}' removed", task))
+            }
+            Err(e) => ControlResponse::error(e.to_string()),
+        },
+        ControlRequest::Trigger { task, wait, force } => trigger_task(scheduler, &task, wait, force).await,
+        ControlRequest::LogLevel { level } => match level.parse::<LevelFilter>() {
+            Ok(level) => {
+                logging::set_level(level);
+                let level = level.as_str().to_lowercase();
+                info!("Log level set to {} through the control socket", level);
+                ControlResponse::ok(format!("Log level set to {}", level))
+            }
+            Err(_) => ControlResponse::error(format!(
+                "Invalid log level '{}'. Must be one of: off, error, warn, info, debug, trace",
+                level
+            )),
+        },
+    }
+}
+
+async fn set_task_enabled(scheduler: &Arc<Mutex<Scheduler>>, task: &str, enabled: bool) -> ControlResponse {
+    let scheduler = scheduler.lock().await;
+    match scheduler.set_task_enabled(task, enabled).await {
+        Ok(()) => {
+            let state = if enabled { "enabled" } else { "disabled" };
+            info!("Task '{}' {} through the control socket", task, state);
+            ControlResponse::ok(format!("Task '{}' {}", task, state))
+        }
+        Err(e) => ControlResponse::error(e.to_string()),
+    }
+}
+
+/// The warnings of the new task follow the message, one per line
+async fn add_task(scheduler: &Arc<Mutex<Scheduler>>, task: TaskDefinition) -> ControlResponse {
+    let name = task.name.clone();
+    match Scheduler::add_task_definition(scheduler, task).await {
+        Ok(warnings) => {
+            info!("Task '{}' added through the control socket", name);
+            let mut message = format!("Task '{}' added", name);
+            for warning in warnings {
+                message.push_str(&format!("\nWarning: {}", warning));
+            }
+            ControlResponse::ok(message)
+        }
+        Err(e) => ControlResponse::error(format!("{:#}", e)),
+    }
+}
+
+async fn trigger_task(scheduler: &Arc<Mutex<Scheduler>>, task: &str, wait: bool, force: bool) -> ControlResponse {
+    let (run_id, finished) = match Scheduler::trigger_task(scheduler, task, force).await {
+        Ok(started) => started,
+        Err(e) => return ControlResponse::error(format!("{:#}", e)),
+    };
+    info!("Task '{}' triggered through the control socket, run {}", task, run_id);
+    if !wait {
+        return ControlResponse::ok(format!("Task '{}' started, run {}", task, run_id));
+    }
+
+    match finished.await {
+        Ok(run) => {
+            let message = if run.success {
+                format!("Task '{}' finished, run {}, exit code {}", task, run_id, run.exit_code)
+            } else {
+                format!("Task '{}' failed, run {}, exit code {}", task, run_id, run.exit_code)
+            };
+            ControlResponse { run: Some(run), ..ControlResponse::ok(message) }
+        }
+        Err(_) => ControlResponse::error(format!("Run {} of task '{}' was lost", run_id, task)),
+    }
+}
+
+/// Send a request to a running scheduler and wait for the response
+pub fn send_request(socket_path: &Path, request: &ControlRequest) -> Result<ControlResponse> {
+    let mut stream = connect(socket_path).with_context(|| {
+        format!(
+            "Failed to connect to the control socket {}, is cron-rs running?",
+            socket_path.display()
+        )
+    })?;
+
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+
+    let mut response = String::new();
+    BufReader::new(stream).read_line(&mut response)?;
+    if response.is_empty() {
+        return Err(anyhow!("The scheduler closed the connection without a response"));
+    }
+
+    Ok(serde_json::from_str(&response)?)
+}
+
+#[cfg(unix)]
+fn connect(socket_path: &Path) -> std::io::Result<impl Read + Write> {
+    std::os::unix::net::UnixStream::connect(socket_path)
+}
+
+#[cfg(windows)]
+fn connect(socket_path: &Path) -> std::io::Result<impl Read + Write> {
+    std::fs::OpenOptions::new().read(true).write(true).open(pipe_name(socket_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_status_text() {
+        let status = SchedulerStatus {
+            running: vec![RunningTaskStatus {
+                task_name: "backup".to_string(),
+                run_id: "20250101-120000-3fa2c19b".to_string(),
+                pid: 4242,
+                start_time: Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap(),
+                elapsed_seconds: 65.3,
+                time_left_seconds: Some(3535.0),
+            }],
+            tasks: vec![
+                TaskStatus {
+                    name: "backup".to_string(),
+                    enabled: true,
+                    next_run: Some(FixedOffset::east_opt(3600).unwrap().with_ymd_and_hms(2025, 1, 2, 3, 0, 0).unwrap()),
+                    last_exit_code: Some(0),
+                    consecutive_failures: 0,
+                    monthly_runtime_seconds: 0.0,
+                },
+                TaskStatus {
+                    name: "on-upload".to_string(),
+                    enabled: false,
+                    next_run: None,
+                    last_exit_code: None,
+                    consecutive_failures: 0,
+                    monthly_runtime_seconds: 0.0,
+                },
+            ],
+            recent: vec![],
+        };
+        assert_eq!(
+            status.text(),
+            "TASK    RUN ID                        PID  ELAPSED      TIME LEFT\n\
+             backup  20250101-120000-3fa2c19b     4242  1 m, 5 s     58 m, 55 s\n\
+             \n\
+             TASK       STATE     NEXT RUN                    LAST EXIT\n\
+             backup     enabled   2025-01-02 03:00:00 +01:00  0\n\
+             on-upload  disabled  -                           -\n"
+        );
+    }
+}
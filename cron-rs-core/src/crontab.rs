@@ -0,0 +1,909 @@
+use crate::alerts::{Alert, AlertConfig};
+use crate::config::file::{
+    ConfigFile, ExplodedTimePatternConfig, ExplodedTimePatternFieldConfig, TaskDefinition, TaskNetwork, TimePatternConfig,
+};
+use crate::config::logging::LoggingConfig;
+use crate::config::{Config, Schedule, TaskConfig, TaskStdin, TimePatternField};
+use crate::executor;
+use crate::schedule_display::ScheduleDisplay;
+use crate::utils::system_timezone;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{NaiveTime, Timelike};
+use log::warn;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const MONTH_NAMES: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+const DAY_NAMES: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+/// A field of a crontab line, with its range of values and the names that can be used instead of numbers
+#[derive(Debug, Clone, Copy)]
+struct CrontabField {
+    name: &'static str,
+    min: u32,
+    max: u32,
+    names: &'static [&'static str],
+}
+
+impl CrontabField {
+    fn is_day_of_week(&self) -> bool {
+        self.names == DAY_NAMES
+    }
+}
+
+const MINUTE: CrontabField = CrontabField { name: "minute", min: 0, max: 59, names: &[] };
+const HOUR: CrontabField = CrontabField { name: "hour", min: 0, max: 23, names: &[] };
+const DAY: CrontabField = CrontabField { name: "day", min: 1, max: 31, names: &[] };
+const MONTH: CrontabField = CrontabField { name: "month", min: 1, max: 12, names: &MONTH_NAMES };
+// 7 is also accepted as Sunday and converted to 0
+const DAY_OF_WEEK: CrontabField = CrontabField { name: "day of week", min: 0, max: 7, names: &DAY_NAMES };
+
+/// Variables set in the crontab, they apply to all the lines after them
+#[derive(Debug, Clone, Default)]
+struct CrontabEnv {
+    env: HashMap<String, String>,
+    shell: Option<String>,
+    timezone: Option<String>,
+    /// Comma separated list of addresses, empty to not send emails
+    mailto: Option<String>,
+}
+
+impl CrontabEnv {
+    /// Parses lines like `NAME=value` or `NAME = "value"`, returns false if the line is not a variable
+    fn parse_line(&mut self, line: &str) -> bool {
+        let Some((name, value)) = line.split_once('=') else {
+            return false;
+        };
+
+        let name = name.trim();
+        let is_identifier = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_identifier {
+            return false;
+        }
+
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+            .unwrap_or(value)
+            .to_string();
+
+        match name {
+            "SHELL" => self.shell = Some(value),
+            "MAILTO" => self.mailto = Some(value),
+            "CRON_TZ" => self.timezone = Some(value),
+            _ => {
+                self.env.insert(name.to_string(), value);
+            }
+        }
+        true
+    }
+
+    fn apply(&self, task: &mut TaskDefinition) {
+        if !self.env.is_empty() {
+            task.env = Some(self.env.clone());
+        }
+        task.shell = self.shell.clone();
+        task.timezone = self.timezone.clone();
+    }
+
+    fn recipients(&self) -> Vec<String> {
+        let Some(mailto) = &self.mailto else {
+            return vec![];
+        };
+        mailto.split(',').map(str::trim).filter(|to| !to.is_empty()).map(String::from).collect()
+    }
+}
+
+/// Format of the crontab lines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CrontabFormat {
+    /// User crontab, `min hour day month dow cmd`
+    User,
+    /// System crontab like `/etc/crontab` and `/etc/cron.d/*`, `min hour day month dow user cmd`
+    System,
+}
+
+/// Returns the path if it's a file, or the crontab files inside if it's a directory. Like cron, files with a `.` or
+/// ending with `~` in their name are ignored, which skips backups and package manager leftovers
+pub fn list_crontab_files(path: &Path) -> Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = vec![];
+    for entry in std::fs::read_dir(path).with_context(|| format!("Failed to read directory {}", path.display()))? {
+        let file = entry?.path();
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        if file.is_file() && !name.contains('.') && !name.ends_with('~') {
+            files.push(file);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Result of converting crontabs, the config file with the crontab line of each task and the constructs that didn't
+/// translate cleanly
+#[derive(Debug, Default)]
+pub struct CrontabImport {
+    pub config: ConfigFile,
+    /// The crontab line of each task, in the same order
+    pub sources: Vec<String>,
+    /// Skipped lines and differences in behavior
+    pub warnings: Vec<String>,
+}
+
+impl CrontabImport {
+    /// Writes the config file with the crontab line of each task as a comment before it
+    pub fn to_yaml(&self) -> Result<String> {
+        let rest = ConfigFile { tasks: vec![], ..self.config.clone() };
+        let mut yaml = serde_yml::to_string(&rest)?.replace("tasks: []\n", "");
+
+        yaml.push_str("tasks:");
+        if self.config.tasks.is_empty() {
+            yaml.push_str(" []\n");
+        }
+        for (task, source) in self.config.tasks.iter().zip(&self.sources) {
+            yaml.push_str(&format!("\n# {}\n", source));
+            yaml.push_str(&serde_yml::to_string(&[task])?);
+        }
+        Ok(yaml)
+    }
+}
+
+/// Converts a crontab file into a config file, see [parse_crontab_files]
+pub fn parse_crontab_file(crontab: &str, format: CrontabFormat) -> Result<ConfigFile> {
+    parse_crontab_files(&[crontab], format)
+}
+
+/// Converts several crontab files into a single config file, see [import_crontab_files]
+pub fn parse_crontab_files(crontabs: &[&str], format: CrontabFormat) -> Result<ConfigFile> {
+    Ok(import_crontab_files(crontabs, format)?.config)
+}
+
+/// Converts several crontab files into a single config file, the comment before a line is used as the task name.
+/// `MAILTO` is translated into email alerts for failed tasks, `SHELL`, `CRON_TZ` and other variables into task options.
+/// Variables only apply to the file that defines them
+pub fn import_crontab_files(crontabs: &[&str], format: CrontabFormat) -> Result<CrontabImport> {
+    let mut import = CrontabImport::default();
+    let mut task_recipients = vec![];
+
+    for crontab in crontabs {
+        parse_crontab_entries(crontab, format, &mut import, &mut task_recipients);
+    }
+
+    // A single MAILTO for all the tasks becomes a global alert, otherwise each task gets its own. Like cron, one email
+    // goes to all the recipients
+    let email_alerts = |recipients: &Vec<String>| match recipients.is_empty() {
+        true => vec![],
+        false => vec![Alert::email(recipients.clone())],
+    };
+    let mut alerts = AlertConfig::default();
+    if task_recipients.windows(2).all(|w| w[0] == w[1]) {
+        alerts.on_failure = task_recipients.first().map(email_alerts).unwrap_or_default();
+    } else {
+        for (task, recipients) in import.config.tasks.iter_mut().zip(&task_recipients) {
+            task.on_failure = email_alerts(recipients);
+        }
+    }
+
+    import.config.logging = Some(LoggingConfig::default());
+    import.config.alerts = Some(alerts);
+    Ok(import)
+}
+
+fn parse_crontab_entries(
+    crontab: &str,
+    format: CrontabFormat,
+    import: &mut CrontabImport,
+    task_recipients: &mut Vec<Vec<String>>,
+) {
+    let mut last_comment = String::new();
+    let mut env = CrontabEnv::default();
+
+    for line in crontab.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            last_comment.clear();
+            continue;
+        }
+
+        if let Some(comment) = line.strip_prefix('#') {
+            last_comment.push(' ');
+            last_comment.push_str(comment.trim());
+            continue;
+        }
+
+        if env.parse_line(line) {
+            continue;
+        }
+
+        let name = if last_comment.trim().is_empty() {
+            format!("Crontab: {}", line)
+        } else {
+            last_comment.trim().to_string()
+        };
+        last_comment.clear();
+
+        match parse_crontab_line(line, name, format, &mut import.warnings) {
+            Ok(Some(mut task)) => {
+                env.apply(&mut task);
+                import.config.tasks.push(task);
+                import.sources.push(line.to_string());
+                task_recipients.push(env.recipients());
+            }
+            Ok(None) => {}
+            Err(e) => import.warnings.push(format!("Skipping crontab line '{}': {:#}", line, e)),
+        }
+    }
+}
+
+fn parse_crontab_line(
+    line: &str,
+    name: String,
+    format: CrontabFormat,
+    warnings: &mut Vec<String>,
+) -> Result<Option<TaskDefinition>> {
+    let (fields, rest) = if let Some(rest) = line.strip_prefix('@') {
+        let (macro_name, rest) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let fields = match macro_name {
+            "yearly" | "annually" => ["0", "0", "1", "1", "*"],
+            "monthly" => ["0", "0", "1", "*", "*"],
+            "weekly" => ["0", "0", "*", "*", "0"],
+            "daily" | "midnight" => ["0", "0", "*", "*", "*"],
+            "hourly" => ["0", "*", "*", "*", "*"],
+            "reboot" => bail!("@reboot has no equivalent schedule"),
+            _ => bail!("Unknown macro @{}", macro_name),
+        };
+        (fields.map(String::from), rest.trim_start())
+    } else {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if parts.len() < 6 {
+            // Not a task, e.g. a variable assignment
+            return Ok(None);
+        }
+        let fields = [parts[0], parts[1], parts[2], parts[3], parts[4]].map(String::from);
+        (fields, skip_fields(line, 5))
+    };
+
+    // System crontabs have the user that runs the command before it
+    let (run_as, cmd) = match format {
+        CrontabFormat::User => (None, rest),
+        CrontabFormat::System if rest.is_empty() => bail!("Missing user"),
+        CrontabFormat::System => (rest.split_whitespace().next().map(String::from), skip_fields(rest, 1)),
+    };
+
+    if cmd.is_empty() {
+        bail!("Missing command");
+    }
+    let (cmd, stdin_text) = split_stdin(cmd);
+
+    let [minute, hour, day, month, day_of_week] = &fields;
+
+    // Cron runs the task if either of them matches, cron-rs requires both to match
+    if day != "*" && day_of_week != "*" {
+        warnings.push(format!(
+            "Task '{}': restricts both day ({}) and day of week ({}), the task will only run when both match",
+            name, day, day_of_week
+        ));
+    }
+
+    let task = TaskDefinition {
+        name,
+        cmd,
+        stdin_text,
+        run_as,
+        enabled: true,
+        when: Some(TimePatternConfig::Long(ExplodedTimePatternConfig {
+            second: None,
+            minute: Some(convert_field(minute, MINUTE)?),
+            hour: Some(convert_field(hour, HOUR)?),
+            day: Some(convert_field(day, DAY)?),
+            month: Some(convert_field(month, MONTH)?),
+            year: None,
+            day_of_week: Some(convert_field(day_of_week, DAY_OF_WEEK)?),
+        })),
+        ..Default::default()
+    };
+
+    Ok(Some(task))
+}
+
+/// The line after its first `count` fields, keeping the spaces of the rest
+fn skip_fields(line: &str, count: usize) -> &str {
+    let mut rest = line.trim_start();
+    for _ in 0..count {
+        rest = rest.find(char::is_whitespace).map_or("", |end| rest[end..].trim_start());
+    }
+    rest
+}
+
+/// Splits the command of a crontab line at the first unescaped `%`, cron passes the text after it as stdin with the
+/// other `%` as newlines. `\%` is a literal `%`
+fn split_stdin(cmd: &str) -> (String, Option<String>) {
+    let mut command = String::new();
+    let mut stdin: Option<String> = None;
+    let mut chars = cmd.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, &mut stdin) {
+            ('\\', _) if chars.peek() == Some(&'%') => {
+                chars.next();
+                stdin.as_mut().unwrap_or(&mut command).push('%');
+            }
+            ('%', None) => stdin = Some(String::new()),
+            ('%', Some(text)) => text.push('\n'),
+            (c, _) => stdin.as_mut().unwrap_or(&mut command).push(c),
+        }
+    }
+    // The exporter leaves out the last newline of `stdin_text`
+    (command, stdin.map(|text| text + "\n"))
+}
+
+/// Converts a crontab field, like `*/15`, `1-5` or `mon,wed,fri`, into the equivalent cron-rs field
+fn convert_field(text: &str, field: CrontabField) -> Result<ExplodedTimePatternFieldConfig> {
+    let context = || format!("Invalid {} '{}'", field.name, text);
+
+    // Keep the ratio syntax when it means the same in cron-rs
+    if let Some(step) = text.strip_prefix("*/") {
+        let step: u32 = step.parse().map_err(|_| anyhow!("Invalid step")).with_context(context)?;
+        if step == 0 {
+            return Err(anyhow!("The step must be greater than 0")).with_context(context);
+        }
+        if field.min == 0 {
+            return Ok(ExplodedTimePatternFieldConfig::Text(format!("*/{}", step)));
+        }
+    }
+
+    let values = expand_field(text, field).with_context(context)?;
+    Ok(render_values(&values, field))
+}
+
+/// Returns all the values matched by the field, sorted and without duplicates
+fn expand_field(text: &str, field: CrontabField) -> Result<Vec<u32>> {
+    let mut values = vec![];
+
+    for item in text.split(',') {
+        let (base, step) = match item.split_once('/') {
+            Some((base, step)) => (base, Some(step.parse::<u32>().map_err(|_| anyhow!("Invalid step '{}'", step))?)),
+            None => (item, None),
+        };
+
+        let (start, end) = if base == "*" {
+            (field.min, field.max)
+        } else if let Some((start, end)) = base.split_once('-') {
+            (parse_value(start, field)?, parse_value(end, field)?)
+        } else {
+            let start = parse_value(base, field)?;
+            // `5/15` means from 5 until the end, every 15
+            (start, if step.is_some() { field.max } else { start })
+        };
+
+        if start > end {
+            bail!("Range {}-{} is empty", start, end);
+        }
+
+        let step = step.unwrap_or(1);
+        if step == 0 {
+            bail!("The step must be greater than 0");
+        }
+
+        values.extend((start..=end).step_by(step as usize));
+    }
+
+    // Sunday can be 0 or 7
+    if field.is_day_of_week() {
+        for value in values.iter_mut() {
+            if *value == 7 {
+                *value = 0;
+            }
+        }
+    }
+
+    values.sort();
+    values.dedup();
+    Ok(values)
+}
+
+fn parse_value(text: &str, field: CrontabField) -> Result<u32> {
+    let value = match text.parse::<u32>() {
+        Ok(value) => value,
+        Err(_) => {
+            let index = field
+                .names
+                .iter()
+                .position(|name| name.eq_ignore_ascii_case(text))
+                .ok_or_else(|| anyhow!("Unknown value '{}'", text))?;
+            // Month names start at 1, day names at 0
+            index as u32 + field.min
+        }
+    };
+
+    if value < field.min || value > field.max {
+        bail!("Value {} is out of range, must be between {} and {}", value, field.min, field.max);
+    }
+    Ok(value)
+}
+
+fn render_values(values: &[u32], field: CrontabField) -> ExplodedTimePatternFieldConfig {
+    let max = if field.is_day_of_week() { 6 } else { field.max };
+    let all = (field.min..=max).count();
+
+    let text = match values {
+        [] => "*".to_string(),
+        _ if values.len() == all => "*".to_string(),
+        [value] => value.to_string(),
+        [first, .., last] if (last - first) as usize + 1 == values.len() => format!("{}..{}", first, last),
+        _ => format!("[{}]", values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")),
+    };
+
+    ExplodedTimePatternFieldConfig::Text(text)
+}
+
+/// Converts the config into a crontab, best effort. Options without an equivalent in cron are left out with a warning,
+/// the task names are kept as comments so the crontab can be imported again
+pub fn export_crontab(config: &Config, format: CrontabFormat) -> String {
+    let mut out = String::from("# Generated by cron-rs export-crontab\n");
+
+    if !config.alerts.on_failure.is_empty() || !config.alerts.on_success.is_empty() {
+        warn!("Global alerts have no equivalent in cron and are not exported");
+    }
+
+    // Variables apply to all the lines after them, so they are only written when they change
+    let local_timezone = system_timezone().name().to_string();
+    let mut current_shell = "/bin/sh".to_string();
+    let mut current_timezone = local_timezone.clone();
+
+    for task in &config.tasks {
+        if task.executor.is_pipeline() {
+            warn!("Task '{}': pipelines have no equivalent in cron, the pipeline is not exported", task.name);
+            out.push_str(&format!("\n# {}\n# Not exported, pipeline: {}\n", task.name, task.cmd));
+            continue;
+        }
+        let Some(fields) = export_schedule(task) else {
+            warn!("Task '{}': the schedule has no equivalent in cron, the task is not exported", task.name);
+            out.push_str(&format!("\n# {}\n# Not exported, schedule: {}\n", task.name, ScheduleDisplay::format_schedule(&task.schedule)));
+            continue;
+        };
+        warn_unsupported_options(task, format);
+
+        out.push('\n');
+        let shell = task.shell.clone().unwrap_or_else(|| "/bin/sh".to_string());
+        if shell != current_shell {
+            out.push_str(&format!("SHELL={}\n", shell));
+            current_shell = shell;
+        }
+        let timezone = task.timezone.name().to_string();
+        if timezone != current_timezone {
+            out.push_str(&format!("CRON_TZ={}\n", timezone));
+            current_timezone = timezone;
+        }
+
+        out.push_str(&format!("# {}\n", task.name));
+        if !task.enabled {
+            out.push_str("# ");
+        }
+        out.push_str(&fields.join(" "));
+        if format == CrontabFormat::System {
+            let user = task.run_as.as_deref().and_then(|r| r.split(':').next()).unwrap_or("root");
+            out.push_str(&format!(" {}", user));
+        }
+        out.push_str(&format!(" {}\n", export_command(task)));
+    }
+
+    out
+}
+
+fn warn_unsupported_options(task: &TaskConfig, format: CrontabFormat) {
+    let mut unsupported = vec![];
+    if crate::template::has_variables(&task.cmd) {
+        unsupported.push("cmd variables");
+    }
+    if task.avoid_overlapping {
+        unsupported.push("avoid_overlapping");
+    }
+    if task.cluster_lock.is_some() {
+        unsupported.push("cluster_lock");
+    }
+    if task.time_limit.is_some() {
+        unsupported.push("time_limit");
+    }
+    if task.max_runs.is_some() {
+        unsupported.push("max_runs");
+    }
+    if !task.on_failure.is_empty() || !task.on_success.is_empty() {
+        unsupported.push("alerts");
+    }
+    if task.run_as.is_some() && format == CrontabFormat::User {
+        unsupported.push("run_as (use --system)");
+    }
+    if let Schedule::When { min_interval: Some(_), .. } = &task.schedule {
+        unsupported.push("min_interval");
+    }
+    if !task.before.is_empty() || !task.after.is_empty() {
+        unsupported.push("before/after");
+    }
+    if task.on_failure_cmd.is_some() {
+        unsupported.push("on_failure_cmd");
+    }
+    if task.env_file.is_some() {
+        unsupported.push("env_file");
+    }
+    if task.login_shell {
+        unsupported.push("login_shell");
+    }
+    if task.network == TaskNetwork::None {
+        unsupported.push("network: none");
+    }
+    if task.chroot.is_some() {
+        unsupported.push("chroot");
+    }
+    if task.cgroup.is_some() {
+        unsupported.push("cgroup");
+    }
+    if !task.limits.is_empty() {
+        unsupported.push("limits");
+    }
+    if !task.priority.is_empty() {
+        unsupported.push("nice/ionice/cpu_affinity");
+    }
+    if task.conditions.needs_power_status() {
+        unsupported.push("only_on_ac_power/min_battery_percent");
+    }
+    if task.conditions.has_only_if() {
+        unsupported.push("only_if");
+    }
+
+    if !unsupported.is_empty() {
+        warn!("Task '{}': ignoring options without an equivalent in cron: {}", task.name, unsupported.join(", "));
+    }
+}
+
+/// Fields `minute hour day month day_of_week` of the crontab line, None if the schedule has no equivalent in cron
+pub fn export_schedule(task: &TaskConfig) -> Option<[String; 5]> {
+    match &task.schedule {
+        Schedule::When { time, .. } => {
+            match time.second {
+                TimePatternField::Value(0) => {}
+                TimePatternField::Value(_) => warn!("Task '{}': cron can't run at a given second, using second 0", task.name),
+                _ => warn!("Task '{}': cron runs at most once per minute, the seconds are ignored", task.name),
+            }
+            if !matches!(time.year, TimePatternField::Any) {
+                warn!("Task '{}': cron has no year field, the year is ignored", task.name);
+            }
+
+            Some([
+                export_field(&time.minute, 0),
+                export_field(&time.hour, 0),
+                export_field(&time.day, 1),
+                export_field(&time.month, 1),
+                export_field(&time.day_of_week, 0),
+            ])
+        }
+        Schedule::Period { period } => {
+            warn!("Task '{}': cron runs the task at the start of each period, it doesn't catch up on the missed ones like anacron", task.name);
+            let time = period.calendar_pattern();
+            Some([
+                export_field(&time.minute, 0),
+                export_field(&time.hour, 0),
+                export_field(&time.day, 1),
+                export_field(&time.month, 1),
+                export_field(&time.day_of_week, 0),
+            ])
+        }
+        Schedule::OnChange { .. } | Schedule::AfterTask { .. } => None,
+        Schedule::Every { interval, aligned, window } => {
+            let secs = interval.as_secs();
+            if secs % 60 != 0 {
+                return None;
+            }
+            let step = |n: u64| if n == 1 { "*".to_string() } else { format!("*/{}", n) };
+
+            let minutes = secs / 60;
+            let hours = minutes / 60;
+            let mut fields = if minutes < 60 && 60 % minutes == 0 {
+                [step(minutes), "*".into(), "*".into(), "*".into(), "*".into()]
+            } else if minutes % 60 == 0 && hours < 24 && 24 % hours == 0 {
+                ["0".into(), step(hours), "*".into(), "*".into(), "*".into()]
+            } else if hours == 24 {
+                ["0".into(), "0".into(), "*".into(), "*".into(), "*".into()]
+            } else if hours == 24 * 7 {
+                ["0".into(), "0".into(), "*".into(), "*".into(), "1".into()]
+            } else {
+                return None;
+            };
+
+            if !aligned {
+                warn!("Task '{}': cron runs at fixed times of the clock, like 'align: true'", task.name);
+            }
+
+            if let Some(window) = window {
+                if fields[4] == "*" {
+                    fields[4] = export_field(&window.days, 0);
+                } else if !matches!(window.days, TimePatternField::Any) {
+                    warn!("Task '{}': the 'on' days are ignored for weekly tasks", task.name);
+                }
+
+                let whole_hours = window.start.minute() == 0 && window.start.second() == 0;
+                let end_hour = if window.end.minute() == 0 && window.end.second() == 0 {
+                    window.end.hour().checked_sub(1)
+                } else {
+                    Some(window.end.hour())
+                };
+                let is_full_day = window.start == NaiveTime::MIN && end_hour == Some(23);
+
+                match end_hour {
+                    _ if is_full_day => {}
+                    Some(end_hour) if whole_hours && window.start.hour() <= end_hour && fields[1] != "0" => {
+                        let range = format!("{}-{}", window.start.hour(), end_hour);
+                        fields[1] = match fields[1].strip_prefix("*/") {
+                            Some(step) => format!("{}/{}", range, step),
+                            None => range,
+                        };
+                    }
+                    _ => warn!("Task '{}': the 'between' time range can't be represented in cron, it's ignored", task.name),
+                }
+            }
+
+            Some(fields)
+        }
+    }
+}
+
+/// `min` is the first value of the field, where cron starts the steps of `*/n`
+fn export_field(field: &TimePatternField, min: u32) -> String {
+    match field {
+        TimePatternField::Any => "*".to_string(),
+        TimePatternField::Value(v) => v.to_string(),
+        TimePatternField::Range(start, end) => format!("{}-{}", start, end),
+        TimePatternField::List(values) => values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(","),
+        TimePatternField::Ratio(divisor, offset) => match field.get_next_valid_value(min, u32::MAX) {
+            Some(start) if start == min => format!("*/{}", divisor),
+            start => format!("{}/{}", start.unwrap_or(*offset), divisor),
+        },
+    }
+}
+
+/// The command with its env, working directory and output files, `%` is escaped because cron replaces it with newlines
+fn export_command(task: &TaskConfig) -> String {
+    let mut cmd = executor::shell_command(task);
+
+    if let Some(dir) = task.working_directory.as_ref().filter(|_| task.executor.is_local()) {
+        cmd = format!("cd {} && {}", shell_quote(dir), cmd);
+    }
+
+    let vars = task.exported_env();
+    if !vars.is_empty() {
+        let exports: Vec<String> = vars.iter().map(|(k, v)| format!("export {}={};", k, shell_quote(v))).collect();
+        cmd = format!("{} {}", exports.join(" "), cmd);
+    }
+
+    if let Some(TaskStdin::File(path)) = &task.stdin {
+        cmd = format!("({}) < {}", cmd, shell_quote(path));
+    }
+
+    // The same lock as the `flock -n` wrappers of cron
+    if let Some(lock_file) = &task.lock_file {
+        cmd = format!("flock -n {} -c {}", shell_quote(&lock_file.to_string_lossy()), shell_quote(&cmd));
+    }
+
+    if task.stdout.is_some() || task.stderr.is_some() {
+        cmd = format!("({})", cmd);
+        if let Some(stdout) = &task.stdout {
+            cmd.push_str(&format!(" >> {}", shell_quote(stdout)));
+        }
+        if let Some(stderr) = &task.stderr {
+            cmd.push_str(&format!(" 2>> {}", shell_quote(stderr)));
+        } else if task.combine_output {
+            cmd.push_str(" 2>&1");
+        }
+    }
+
+    let mut line = cmd.replace('%', "\\%");
+    // cron passes the text after the first unescaped `%` as stdin, with the other `%` as newlines
+    if let Some(TaskStdin::Text(text)) = &task.stdin {
+        let lines: Vec<String> = text.strip_suffix('\n').unwrap_or(text).split('\n').map(|l| l.replace('%', "\\%")).collect();
+        line.push('%');
+        line.push_str(&lines.join("%"));
+    }
+    line
+}
+
+fn shell_quote(value: &str) -> String {
+    if !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || "/._-:".contains(c)) {
+        return value.to_string();
+    }
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(field: ExplodedTimePatternFieldConfig) -> String {
+        match field {
+            ExplodedTimePatternFieldConfig::Text(text) => text,
+            other => panic!("Unexpected field {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_convert_field() {
+        assert_eq!(text(convert_field("*", MINUTE).unwrap()), "*");
+        assert_eq!(text(convert_field("*/5", MINUTE).unwrap()), "*/5");
+        assert_eq!(text(convert_field("0-30/10", MINUTE).unwrap()), "[0, 10, 20, 30]");
+        assert_eq!(text(convert_field("50/5", MINUTE).unwrap()), "[50, 55]");
+        assert_eq!(text(convert_field("1-5", HOUR).unwrap()), "1..5");
+        assert_eq!(text(convert_field("*/10", DAY).unwrap()), "[1, 11, 21, 31]");
+        assert_eq!(text(convert_field("JAN,mar", MONTH).unwrap()), "[1, 3]");
+        assert_eq!(text(convert_field("mon-fri", DAY_OF_WEEK).unwrap()), "1..5");
+        assert_eq!(text(convert_field("7", DAY_OF_WEEK).unwrap()), "0");
+        assert_eq!(text(convert_field("5-7", DAY_OF_WEEK).unwrap()), "[0, 5, 6]");
+        assert_eq!(text(convert_field("0-7", DAY_OF_WEEK).unwrap()), "*");
+
+        assert!(convert_field("60", MINUTE).is_err());
+        assert!(convert_field("*/0", MINUTE).is_err());
+        assert!(convert_field("foo", MONTH).is_err());
+    }
+
+    #[test]
+    fn test_parse_crontab_file() {
+        let crontab = "\
+# Backup
+@daily /usr/bin/backup
+@reboot /usr/bin/start
+*/15 9-17 * * mon-fri check
+";
+        let tasks = parse_crontab_file(crontab, CrontabFormat::User).unwrap().tasks;
+        assert_eq!(tasks.len(), 2);
+
+        assert_eq!(tasks[0].name, "Backup");
+        assert_eq!(tasks[0].cmd, "/usr/bin/backup");
+        let Some(TimePatternConfig::Long(when)) = &tasks[0].when else {
+            panic!("Expected a long time pattern");
+        };
+        assert_eq!(text(when.minute.clone().unwrap()), "0");
+        assert_eq!(text(when.hour.clone().unwrap()), "0");
+        assert_eq!(text(when.day.clone().unwrap()), "*");
+
+        assert_eq!(tasks[1].cmd, "check");
+        let Some(TimePatternConfig::Long(when)) = &tasks[1].when else {
+            panic!("Expected a long time pattern");
+        };
+        assert_eq!(text(when.minute.clone().unwrap()), "*/15");
+        assert_eq!(text(when.hour.clone().unwrap()), "9..17");
+        assert_eq!(text(when.day_of_week.clone().unwrap()), "1..5");
+    }
+
+    #[test]
+    fn test_crontab_variables() {
+        let crontab = "\
+SHELL=/bin/bash
+PATH = \"/usr/local/bin:/usr/bin\"
+MAILTO=admin@example.com
+0 * * * * first
+MAILTO=
+0 * * * * second
+";
+        let config = parse_crontab_file(crontab, CrontabFormat::User).unwrap();
+        let tasks = &config.tasks;
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].shell.as_deref(), Some("/bin/bash"));
+        assert_eq!(tasks[0].env.as_ref().unwrap()["PATH"], "/usr/local/bin:/usr/bin");
+
+        // Different recipients for each task, so the alerts are not global
+        assert!(config.alerts.unwrap().on_failure.is_empty());
+        assert!(matches!(&tasks[0].on_failure[..], [Alert::Email { to, .. }] if to.to_string() == "admin@example.com"));
+        assert!(tasks[1].on_failure.is_empty());
+    }
+
+    #[test]
+    fn test_system_crontab() {
+        let etc_crontab = "\
+MAILTO=root
+17 * * * * root cd / && run-parts --report /etc/cron.hourly
+@daily www-data /usr/bin/cleanup
+";
+        let cron_d = "*/5 * * * * backup /usr/bin/sync";
+
+        let config = parse_crontab_files(&[etc_crontab, cron_d], CrontabFormat::System).unwrap();
+        let tasks = &config.tasks;
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(tasks[0].run_as.as_deref(), Some("root"));
+        assert_eq!(tasks[0].cmd, "cd / && run-parts --report /etc/cron.hourly");
+        assert_eq!(tasks[1].run_as.as_deref(), Some("www-data"));
+        assert_eq!(tasks[1].cmd, "/usr/bin/cleanup");
+        assert_eq!(tasks[2].run_as.as_deref(), Some("backup"));
+
+        // MAILTO only applies to the file that defines it
+        assert_eq!(tasks[0].on_failure.len(), 1);
+        assert!(tasks[2].on_failure.is_empty());
+    }
+
+    #[test]
+    fn test_import_crontab() {
+        let crontab = "\
+MAILTO=admin@example.com
+# Backup
+@daily /usr/bin/backup
+@reboot /usr/bin/start
+0 0 1 * mon report
+";
+        let import = import_crontab_files(&[crontab], CrontabFormat::User).unwrap();
+        assert_eq!(import.sources, vec!["@daily /usr/bin/backup", "0 0 1 * mon report"]);
+        assert_eq!(import.warnings.len(), 2, "{:?}", import.warnings);
+        assert!(import.warnings[0].contains("@reboot"));
+        assert!(import.warnings[1].contains("day of week"));
+
+        // The comments don't change the config
+        let yaml = import.to_yaml().unwrap();
+        assert!(yaml.contains("\n# @daily /usr/bin/backup\n- name: Backup\n"), "{}", yaml);
+        let config: ConfigFile = serde_yml::from_str(&yaml).unwrap();
+        assert_eq!(serde_yml::to_string(&config).unwrap(), serde_yml::to_string(&import.config).unwrap());
+
+        let empty = import_crontab_files(&["# Nothing"], CrontabFormat::User).unwrap();
+        let config: ConfigFile = serde_yml::from_str(&empty.to_yaml().unwrap()).unwrap();
+        assert!(config.tasks.is_empty());
+    }
+
+    #[test]
+    fn test_crontab_stdin() {
+        let crontab = "0 * * * * mail -s \"50\\% done\"  admin%Hello,%disk at 50\\%\n* * * * * date +\\%s\n";
+        let tasks = parse_crontab_file(crontab, CrontabFormat::User).unwrap().tasks;
+        assert_eq!(tasks[0].cmd, "mail -s \"50% done\"  admin");
+        assert_eq!(tasks[0].stdin_text.as_deref(), Some("Hello,\ndisk at 50%\n"));
+        assert_eq!(tasks[1].cmd, "date +%s");
+        assert_eq!(tasks[1].stdin_text, None);
+
+        // Exported and imported again
+        let config_file: ConfigFile = serde_yml::from_str(
+            r#"
+tasks:
+  - name: report
+    cmd: mail -s "50% done" admin
+    stdin_text: "Hello,\ndisk at 50%\n"
+    when: '* *-*-* 03:30:00'
+    timezone: UTC
+"#,
+        )
+        .unwrap();
+        let config = crate::config::parse_config_file(&config_file).unwrap();
+        let imported = parse_crontab_file(&export_crontab(&config, CrontabFormat::User), CrontabFormat::User).unwrap();
+        assert_eq!(imported.tasks[0].cmd, "mail -s \"50% done\" admin");
+        assert_eq!(imported.tasks[0].stdin_text.as_deref(), Some("Hello,\ndisk at 50%\n"));
+    }
+
+    #[test]
+    fn test_export_crontab() {
+        let config_file: ConfigFile = serde_yml::from_str(
+            r#"
+tasks:
+  - name: report
+    cmd: echo "100%"
+    when: '* *-*-* 03:30:00'
+    timezone: UTC
+  - name: poll
+    cmd: check
+    every: 15 minutes
+    between: 08:00..20:00
+    on: [Mon..Fri]
+    run_as: www-data
+    timezone: UTC
+    lock_file: /var/lock/poll.lock
+"#,
+        )
+        .unwrap();
+        let config = crate::config::parse_config_file(&config_file).unwrap();
+
+        let crontab = export_crontab(&config, CrontabFormat::System);
+        assert!(crontab.contains("# report\n30 3 * * * root echo \"100\\%\"\n"));
+        assert!(crontab.contains("# poll\n*/15 8-19 * * 1,2,3,4,5 www-data flock -n /var/lock/poll.lock -c check\n"));
+
+        // The task names are kept when importing the crontab again
+        let imported = parse_crontab_file(&crontab, CrontabFormat::System).unwrap();
+        let names: Vec<_> = imported.tasks.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["report", "poll"]);
+    }
+}
@@ -0,0 +1,254 @@
+use crate::alert_queue::queue_alert;
+use crate::alerts::{deliver_alert, replace_and_escape, EscapeStrategy};
+use crate::clock;
+use crate::config::digest::Digest;
+use crate::events::SchedulerEvent;
+use crate::scheduler::Scheduler;
+use crate::utils::{format_duration, hostname};
+use chrono::DateTime;
+use chrono_tz::Tz;
+use log::{error, info, warn};
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+const DEFAULT_SUBJECT: &str = "cron-rs digest: {{ failures }} of {{ runs }} runs failed";
+const DEFAULT_BODY: &str = "{{ summary }}";
+
+/// Executions of a task during the interval of a digest
+#[derive(Debug, Default)]
+struct TaskRuns {
+    runs: u32,
+    total_duration: Duration,
+    max_duration: Duration,
+    /// Run id and error of the failed executions
+    failures: Vec<(String, String)>,
+}
+
+/// Executions that finished during the interval of a digest
+#[derive(Debug)]
+pub struct DigestSummary {
+    pub start: DateTime<Tz>,
+    tasks: BTreeMap<String, TaskRuns>,
+}
+
+impl DigestSummary {
+    pub fn new(start: DateTime<Tz>) -> Self {
+        Self { start, tasks: BTreeMap::new() }
+    }
+
+    /// Counts the finished and failed executions, the other events are ignored
+    pub fn add(&mut self, event: &SchedulerEvent) {
+        let (task_name, run_id, duration_seconds, error) = match event {
+            SchedulerEvent::TaskFinished { task_name, run_id, duration_seconds, .. } => (task_name, run_id, duration_seconds, None),
+            SchedulerEvent::TaskFailed { task_name, run_id, duration_seconds, error, .. } => {
+                (task_name, run_id, duration_seconds, Some(error))
+            }
+            _ => return,
+        };
+        let duration = Duration::from_secs_f64(duration_seconds.max(0.0));
+        let task = self.tasks.entry(task_name.clone()).or_default();
+        task.runs += 1;
+        task.total_duration += duration;
+        task.max_duration = task.max_duration.max(duration);
+        if let Some(error) = error {
+            task.failures.push((run_id.clone(), error.clone()));
+        }
+    }
+
+    pub fn runs(&self) -> u32 {
+        self.tasks.values().map(|task| task.runs).sum()
+    }
+
+    pub fn failures(&self) -> usize {
+        self.tasks.values().map(|task| task.failures.len()).sum()
+    }
+
+    /// A line for each task with its runs and durations, followed by its failed runs. The tasks with failures go first
+    pub fn text(&self) -> String {
+        if self.tasks.is_empty() {
+            return "No executions".to_string();
+        }
+        let mut tasks: Vec<(&String, &TaskRuns)> = self.tasks.iter().collect();
+        tasks.sort_by_key(|(_, task)| task.failures.is_empty());
+
+        let mut text = String::new();
+        for (name, task) in tasks {
+            let runs = if task.runs == 1 { "1 run".to_string() } else { format!("{} runs", task.runs) };
+            let failed = if task.failures.is_empty() { String::new() } else { format!(", {} failed", task.failures.len()) };
+            text.push_str(&format!(
+                "{}: {}{}, {} on average, {} at most\n",
+                name,
+                runs,
+                failed,
+                format_duration(task.total_duration / task.runs),
+                format_duration(task.max_duration)
+            ));
+            for (run_id, error) in &task.failures {
+                text.push_str(&format!("  {}: {}\n", run_id, error));
+            }
+        }
+        text
+    }
+
+    /// The digest as the payload of the alert plugins, `event` is `digest`
+    fn to_json(&self, end: DateTime<Tz>) -> Value {
+        json!({
+            "event": "digest",
+            "summary": self.text(),
+            "runs": self.runs(),
+            "failures": self.failures(),
+            "successes": self.runs() as usize - self.failures(),
+            "period_start": self.start.to_rfc3339(),
+            "period_end": end.to_rfc3339(),
+            "hostname": hostname(),
+        })
+    }
+
+    /// Replaces the placeholders of the digest, the interval ends at `end`
+    fn render(&self, template: &str, end: DateTime<Tz>, escape: &EscapeStrategy) -> String {
+        let mut result = template.to_string();
+        replace_and_escape(&mut result, "summary", &self.text(), escape);
+        replace_and_escape(&mut result, "runs", &self.runs().to_string(), escape);
+        replace_and_escape(&mut result, "failures", &self.failures().to_string(), escape);
+        replace_and_escape(&mut result, "successes", &(self.runs() as usize - self.failures()).to_string(), escape);
+        replace_and_escape(&mut result, "period_start", &self.start.to_rfc3339(), escape);
+        replace_and_escape(&mut result, "period_end", &end.to_rfc3339(), escape);
+        replace_and_escape(&mut result, "hostname", &hostname(), escape);
+        result
+    }
+}
+
+/// Collects the executions from the events and sends the digest at the end of each interval
+pub fn spawn_digest(digest: Digest, mut events: broadcast::Receiver<SchedulerEvent>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut summary = DigestSummary::new(Scheduler::get_current_datetime_at(digest.timezone));
+        loop {
+            let end = Scheduler::get_next_aligned_time(summary.start, digest.interval, false);
+            let wait = || (end - Scheduler::get_current_datetime_at(digest.timezone)).to_std().unwrap_or_default();
+            let sleep = tokio::time::sleep(wait());
+            tokio::pin!(sleep);
+
+            loop {
+                tokio::select! {
+                    _ = &mut sleep => break,
+                    // The sleep only follows the monotonic clock
+                    _ = clock::clock_changed() => sleep.as_mut().reset(tokio::time::Instant::now() + wait()),
+                    event = events.recv() => match event {
+                        Ok(event) => summary.add(&event),
+                        Err(RecvError::Lagged(count)) => warn!("The digest is behind, {} events were not counted", count),
+                        Err(RecvError::Closed) => return,
+                    },
+                }
+            }
+
+            let finished = std::mem::replace(&mut summary, DigestSummary::new(end));
+            if finished.runs() > 0 || digest.send_empty {
+                send_digest(&digest, finished, end).await;
+            }
+        }
+    })
+}
+
+async fn send_digest(digest: &Digest, summary: DigestSummary, end: DateTime<Tz>) {
+    info!("Sending the digest of {} runs, {} failed", summary.runs(), summary.failures());
+    let summary = Arc::new(summary);
+    for alert in digest.alerts.clone() {
+        let kind = alert.kind();
+        let summary = summary.clone();
+        let result = queue_alert(move || {
+            let render = |template: &str, escape: &EscapeStrategy| summary.render(template, end, escape);
+            deliver_alert(&alert, DEFAULT_SUBJECT, DEFAULT_BODY, &summary.to_json(end), &render)
+        })
+        .await;
+        if let Err(e) = result {
+            error!("Failed to send the digest with the {} alert: {:#}", kind, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::Alert;
+    use chrono::TimeZone;
+
+    fn finished(task_name: &str, run_id: &str, duration_seconds: f64, error: Option<&str>) -> SchedulerEvent {
+        match error {
+            Some(error) => SchedulerEvent::TaskFailed {
+                task_name: task_name.to_string(),
+                run_id: run_id.to_string(),
+                exit_code: 1,
+                duration_seconds,
+                error: error.to_string(),
+            },
+            None => SchedulerEvent::TaskFinished {
+                task_name: task_name.to_string(),
+                run_id: run_id.to_string(),
+                exit_code: 0,
+                duration_seconds,
+            },
+        }
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut summary = DigestSummary::new(chrono_tz::UTC.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap());
+        assert_eq!(summary.text(), "No executions");
+
+        summary.add(&finished("cleanup", "r1", 0.5, None));
+        summary.add(&finished("backup", "r2", 10.0, None));
+        summary.add(&finished("backup", "r3", 20.0, Some("Task 'backup' failed, exit code 2")));
+        summary.add(&SchedulerEvent::TaskStarted {
+            task_name: "backup".to_string(),
+            run_id: "r4".to_string(),
+            pid: 1,
+            start_time: chrono::Utc::now(),
+        });
+
+        assert_eq!((summary.runs(), summary.failures()), (3, 1));
+        assert_eq!(
+            summary.text(),
+            "backup: 2 runs, 1 failed, 15 s on average, 20 s at most\n  r3: Task 'backup' failed, exit code 2\n\
+             cleanup: 1 run, 500 ms on average, 500 ms at most\n"
+        );
+        let end = summary.start + chrono::TimeDelta::days(1);
+        let text = summary.render("{{ successes }}/{{ runs }} until {{ period_end }}", end, &EscapeStrategy::None);
+        assert_eq!(text, "2/3 until 2025-01-02T00:00:00+00:00");
+    }
+
+    #[tokio::test]
+    async fn test_digest_is_sent_at_the_end_of_the_interval() {
+        let file = std::env::temp_dir().join(format!("cron-rs-test-digest-{}", std::process::id()));
+        let digest = Digest {
+            interval: Duration::from_secs(1),
+            timezone: chrono_tz::UTC,
+            alerts: vec![Alert::Cmd {
+                cmd: format!("echo {{{{ runs }}}} {{{{ failures }}}} >> {}", file.display()),
+                escape: EscapeStrategy::None,
+                plugin: None,
+                timeout: None,
+            }],
+            send_empty: false,
+        };
+        let (sender, receiver) = broadcast::channel(16);
+        let handle = spawn_digest(digest, receiver);
+        sender.send(finished("backup", "r1", 1.0, None)).unwrap();
+        sender.send(finished("backup", "r2", 1.0, Some("failed"))).unwrap();
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        handle.abort();
+
+        // Both runs are counted once, even if they fall in different intervals, and the empty intervals are not sent
+        let output = std::fs::read_to_string(&file).unwrap();
+        let totals = output.lines().fold((0, 0), |(runs, failures), line| {
+            let (line_runs, line_failures) = line.split_once(' ').unwrap();
+            (runs + line_runs.parse::<u32>().unwrap(), failures + line_failures.parse::<u32>().unwrap())
+        });
+        assert_eq!(totals, (2, 1), "{}", output);
+        std::fs::remove_file(&file).unwrap();
+    }
+}
@@ -0,0 +1,137 @@
+use chrono::{DateTime, Utc};
+use log::{error, warn};
+use serde::Serialize;
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+/// Events are dropped for the subscribers that fall this far behind
+pub const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Activity of the scheduler, received with [crate::SchedulerHandle::subscribe] and written to `logging.events`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum SchedulerEvent {
+    TaskStarted {
+        task_name: String,
+        run_id: String,
+        /// 0 for closures
+        pid: u32,
+        start_time: DateTime<Utc>,
+    },
+    /// The execution met the success criteria of the task
+    TaskFinished {
+        task_name: String,
+        run_id: String,
+        exit_code: i32,
+        duration_seconds: f64,
+    },
+    /// The execution failed, timed out or couldn't start
+    TaskFailed {
+        task_name: String,
+        run_id: String,
+        /// -1 when the process didn't exit by itself
+        exit_code: i32,
+        duration_seconds: f64,
+        error: String,
+    },
+    /// An execution was due but didn't run, e.g. the task is disabled or still running
+    TaskSkipped {
+        task_name: String,
+        reason: String,
+        /// When the execution was due
+        scheduled_time: DateTime<Utc>,
+    },
+    AlertSent {
+        task_name: String,
+        run_id: String,
+        /// `email`, `cmd` or `webhook`
+        alert: String,
+        /// Address of the emails, host of the webhooks and program of the commands
+        target: String,
+        /// Set when the alert couldn't be sent
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+    },
+    /// The scheduler failed on its own, not because of a task, e.g. it lost track of an execution. It keeps running
+    InternalError {
+        /// The task it was handling, if any
+        #[serde(skip_serializing_if = "Option::is_none")]
+        task_name: Option<String>,
+        error: String,
+    },
+    /// The wall clock jumped, like after an NTP step, a manual change or a suspend. The next runs are computed again
+    ClockChanged {
+        /// Negative if the clock went back
+        jump_seconds: i64,
+    },
+}
+
+#[derive(Serialize)]
+struct EventLine<'a> {
+    time: DateTime<Utc>,
+    #[serde(flatten)]
+    event: &'a SchedulerEvent,
+}
+
+/// Appends the events to a JSON lines file, one object per line with the time of the event
+pub fn spawn_event_log(path: PathBuf, mut events: broadcast::Receiver<SchedulerEvent>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let file = tokio::fs::OpenOptions::new().create(true).append(true).open(&path).await;
+        let mut file = match file {
+            Ok(file) => file,
+            Err(e) => {
+                error!("Failed to open the events file {}: {}", path.to_string_lossy(), e);
+                return;
+            }
+        };
+
+        loop {
+            let event = match events.recv().await {
+                Ok(event) => event,
+                Err(RecvError::Lagged(count)) => {
+                    warn!("The events file is behind, {} events were not written", count);
+                    continue;
+                }
+                Err(RecvError::Closed) => return,
+            };
+
+            let mut line = match serde_json::to_string(&EventLine { time: Utc::now(), event: &event }) {
+                Ok(line) => line,
+                Err(e) => {
+                    error!("Failed to serialize event {:?}: {}", event, e);
+                    continue;
+                }
+            };
+            line.push('\n');
+
+            if let Err(e) = file.write_all(line.as_bytes()).await {
+                error!("Failed to write to the events file {}: {}", path.to_string_lossy(), e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_line() {
+        let time = DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z").unwrap().with_timezone(&Utc);
+        let event = SchedulerEvent::TaskFailed {
+            task_name: "backup".to_string(),
+            run_id: "20240101-000000-abcd".to_string(),
+            exit_code: 2,
+            duration_seconds: 1.5,
+            error: "exit code 2".to_string(),
+        };
+
+        assert_eq!(
+            serde_json::to_string(&EventLine { time, event: &event }).unwrap(),
+            r#"{"time":"2024-01-01T00:00:00Z","event":"task_failed","task_name":"backup","run_id":"20240101-000000-abcd","exit_code":2,"duration_seconds":1.5,"error":"exit code 2"}"#
+        );
+    }
+}
@@ -0,0 +1,599 @@
+use crate::alerts::escape_shell_arg_string;
+use crate::config::executor::{DockerExecutor, Executor, SshExecutor, TaskFn};
+use crate::config::file::{StopSignal, TaskNetwork};
+use crate::config::{TaskConfig, TaskStdin};
+use crate::resource_usage::{wait_with_usage, ResourceUsage};
+use crate::template::{expand_cmd, CmdVariables};
+use crate::utils::{
+    new_run_id, set_network, set_working_directory, signal_process_group, terminate_child, wait_process_exit,
+    write_stdin,
+};
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use std::fs::File;
+use std::io::Write;
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+use tokio::process::{Child, Command};
+use tokio::task::JoinHandle;
+
+/// Prefix of the run id in the remote command line of `executor: ssh`
+const REMOTE_MARKER: &str = "cron-rs-run=";
+
+/// Shell of the local tasks without `shell`
+#[cfg(unix)]
+pub const DEFAULT_SHELL: &str = "/bin/sh";
+#[cfg(windows)]
+pub const DEFAULT_SHELL: &str = "cmd.exe";
+
+/// Adopted processes are waited for this long at a time, until they exit
+const ADOPTED_POLL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Running execution of a task
+#[derive(Debug)]
+pub enum Execution {
+    Process(Child),
+    /// Resolves to the exit status of the closure, see [spawn_closure]
+    Closure(JoinHandle<ExitStatus>),
+    /// Process started by a previous run of the scheduler, it's not a child so its exit status is unknown. It resolves
+    /// to [killed_status] once the process exits
+    Adopted(u32),
+}
+
+impl Execution {
+    pub async fn wait(&mut self) -> std::io::Result<ExitStatus> {
+        match self {
+            Execution::Process(child) => child.wait().await,
+            Execution::Closure(handle) => Ok(closure_status(handle.await)),
+            Execution::Adopted(pid) => {
+                while !wait_process_exit(*pid, ADOPTED_POLL_TIMEOUT).await {}
+                Ok(killed_status())
+            }
+        }
+    }
+
+    /// Like [Execution::wait], with the resources used by a process
+    pub async fn wait_with_usage(&mut self) -> std::io::Result<(ExitStatus, Option<ResourceUsage>)> {
+        match self {
+            Execution::Process(child) => wait_with_usage(child).await,
+            _ => Ok((self.wait().await?, None)),
+        }
+    }
+
+    /// Like [terminate_child], a closure is cancelled right away, it can't handle signals
+    pub async fn terminate(&mut self, signal: StopSignal, grace: Duration, task_name: &str) -> std::io::Result<ExitStatus> {
+        match self {
+            Execution::Process(child) => terminate_child(child, signal, grace, task_name).await,
+            Execution::Closure(handle) => {
+                handle.abort();
+                Ok(closure_status(handle.await))
+            }
+            Execution::Adopted(pid) => {
+                if signal == StopSignal::Sigkill || !signal_process_group(*pid, signal) || !wait_process_exit(*pid, grace).await {
+                    signal_process_group(*pid, StopSignal::Sigkill);
+                    wait_process_exit(*pid, ADOPTED_POLL_TIMEOUT).await;
+                }
+                Ok(killed_status())
+            }
+        }
+    }
+}
+
+/// Runs the closure of a task in the runtime of the scheduler. An error is written to `stderr` and exits with 1 like a
+/// failed command, a panic exits with 101 like a Rust program and a cancelled closure looks killed by SIGKILL
+pub fn spawn_closure(task_fn: &TaskFn, mut stderr: File) -> JoinHandle<ExitStatus> {
+    let future = task_fn.call();
+    tokio::spawn(async move {
+        match future.await {
+            Ok(()) => exit_status(0),
+            Err(e) => {
+                let _ = writeln!(stderr, "{:#}", e);
+                exit_status(1)
+            }
+        }
+    })
+}
+
+fn closure_status(result: Result<ExitStatus, tokio::task::JoinError>) -> ExitStatus {
+    match result {
+        Ok(status) => status,
+        Err(e) if e.is_panic() => exit_status(101),
+        Err(_) => killed_status(),
+    }
+}
+
+#[cfg(unix)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    ExitStatus::from_raw(code << 8)
+}
+
+#[cfg(windows)]
+fn exit_status(code: i32) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(code as u32)
+}
+
+/// Status of an execution killed by the scheduler, also used for the ones whose status is lost
+#[cfg(unix)]
+pub(crate) fn killed_status() -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    ExitStatus::from_raw(libc::SIGKILL)
+}
+
+/// Processes killed with `taskkill /F` exit with 1
+#[cfg(windows)]
+pub(crate) fn killed_status() -> ExitStatus {
+    exit_status(1)
+}
+
+/// How a shell takes the command line, found from its name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellKind {
+    /// `cmd.exe`, the line goes after `/C` as it is, it doesn't split the arguments with the quoting rules of other
+    /// Windows programs
+    Cmd,
+    /// `powershell` or `pwsh`, the line goes after `-Command`
+    PowerShell,
+    /// The rest of the shells take the line after `-c`
+    Posix,
+}
+
+impl ShellKind {
+    /// Both separators, the path can be a Windows one in a config exported from another platform
+    pub fn of(shell: &str) -> Self {
+        let name = shell.rsplit(['/', '\\']).next().unwrap_or(shell).to_lowercase();
+        match name.strip_suffix(".exe").unwrap_or(&name) {
+            "cmd" => ShellKind::Cmd,
+            "powershell" | "pwsh" => ShellKind::PowerShell,
+            _ => ShellKind::Posix,
+        }
+    }
+}
+
+/// Command that runs a command line with a local shell, [DEFAULT_SHELL] when None, see [ShellKind]
+pub fn shell_process(shell: Option<&str>, login_shell: bool, line: &str) -> std::process::Command {
+    let shell = shell.unwrap_or(DEFAULT_SHELL);
+    let mut cmd = std::process::Command::new(shell);
+    match ShellKind::of(shell) {
+        ShellKind::Cmd => {
+            cmd.arg("/C");
+            raw_arg(&mut cmd, line);
+        }
+        ShellKind::PowerShell => {
+            cmd.args(["-NoProfile", "-NonInteractive", "-Command", line]);
+        }
+        ShellKind::Posix => {
+            if login_shell {
+                cmd.arg("-l");
+            }
+            cmd.args(["-c", line]);
+        }
+    }
+    cmd
+}
+
+#[cfg(windows)]
+fn raw_arg(cmd: &mut std::process::Command, arg: &str) {
+    use std::os::windows::process::CommandExt;
+    cmd.raw_arg(arg);
+}
+
+#[cfg(not(windows))]
+fn raw_arg(cmd: &mut std::process::Command, arg: &str) {
+    cmd.arg(arg);
+}
+
+/// What the scheduler knows about an execution, the command gets it in the `CRON_RS_*` variables
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunContext {
+    pub run_id: String,
+    /// When the execution was due, the start time of the ones that aren't scheduled
+    pub scheduled_time: DateTime<Utc>,
+    /// Exit code of the previous execution of the task, -1 if it was killed
+    pub prev_exit_code: Option<i32>,
+    /// 1 for the first attempt of a run
+    pub attempt: u32,
+    /// Failed runs of the task in a row before this one
+    pub prev_failures: u32,
+}
+
+impl RunContext {
+    /// First attempt of an execution that starts now
+    pub fn new(start_time: DateTime<Utc>) -> Self {
+        RunContext { run_id: new_run_id(start_time), scheduled_time: start_time, prev_exit_code: None, attempt: 1, prev_failures: 0 }
+    }
+
+    /// `CRON_RS_PREV_EXIT_CODE` is not set before the first execution of the task
+    pub fn env(&self, task: &TaskConfig) -> Vec<(&'static str, String)> {
+        let mut env = vec![
+            ("CRON_RS_TASK_NAME", task.name.clone()),
+            ("CRON_RS_RUN_ID", self.run_id.clone()),
+            ("CRON_RS_SCHEDULED_TIME", self.scheduled_time.with_timezone(&task.timezone).to_rfc3339()),
+            ("CRON_RS_ATTEMPT", self.attempt.to_string()),
+        ];
+        if let Some(exit_code) = self.prev_exit_code {
+            env.push(("CRON_RS_PREV_EXIT_CODE", exit_code.to_string()));
+        }
+        env
+    }
+}
+
+/// Builds the command that runs the task with its executor, with the working directory and the environment of the task.
+/// The caller sets the output, the user and the limits, and the stdin with [open_stdin]. The second value is the
+/// environment the ssh executor reads from its stdin
+pub fn command(task: &TaskConfig, context: &RunContext) -> anyhow::Result<(Command, Option<String>)> {
+    let run_id = context.run_id.as_str();
+    let variables = CmdVariables {
+        task_name: &task.name,
+        run_id,
+        scheduled_time: context.scheduled_time.with_timezone(&task.timezone),
+    };
+    let task = &TaskConfig { cmd: expand_cmd(&task.cmd, &variables), ..task.clone() };
+    let task_env = task.resolved_env()?;
+    let context_env = context.env(task);
+    let mut env: Vec<(&str, &str)> = context_env.iter().map(|(key, value)| (*key, value.as_str())).collect();
+    env.extend(task_env.iter().map(|(key, value)| (key.as_str(), value.as_str())));
+
+    let mut cmd = match &task.executor {
+        Executor::Local => {
+            let mut cmd = Command::from(shell_process(task.shell.as_deref(), task.login_shell, &task.cmd));
+            set_working_directory(&mut cmd, task.chroot.as_deref(), task.working_directory.as_deref())?;
+            set_network(&mut cmd, task.network);
+            cmd
+        }
+        Executor::Docker(docker) => {
+            let keys: Vec<&str> = env.iter().map(|(key, _)| *key).collect();
+            let args = docker_args(task, docker, Some(&container_name(task, run_id)), &keys);
+            let mut cmd = Command::new(&docker.binary);
+            cmd.args(args);
+            cmd
+        }
+        Executor::Ssh(ssh) => {
+            let mut cmd = Command::new("ssh");
+            cmd.args(ssh_args(ssh));
+            let keys: Vec<&str> = env.iter().map(|(key, _)| *key).collect();
+            cmd.arg(remote_command(task, &[], &keys, Some(run_id)));
+            // The values are written to the stdin, see [remote_command]
+            return Ok((cmd, Some(remote_env_input(&env))));
+        }
+        Executor::Closure(_) => anyhow::bail!("closure tasks only run in an embedded scheduler"),
+        Executor::Pipeline(_) => anyhow::bail!("pipelines only run in the scheduler"),
+    };
+    // The docker client passes them to the container, `-e KEY` keeps the values out of the arguments
+    cmd.envs(env);
+    Ok((cmd, None))
+}
+
+/// Input written to the stdin of a command once it starts, see [open_stdin]
+#[derive(Debug)]
+pub struct StdinInput {
+    text: String,
+    file: Option<File>,
+}
+
+impl StdinInput {
+    /// Writes the input to the stdin of the child and closes it
+    pub fn write(self, child: &mut Child, task_name: &str) {
+        if let Some(stdin) = child.stdin.take() {
+            write_stdin(stdin, self.text, self.file, task_name.to_string());
+        }
+    }
+}
+
+/// Stdin of the command, and the input to write to it once it starts. The environment of the ssh executor goes ahead
+/// of the input of the task
+pub fn open_stdin(task: &TaskConfig, env_input: Option<String>) -> std::io::Result<(Stdio, Option<StdinInput>)> {
+    let Some(text) = env_input else {
+        let input = match &task.stdin {
+            Some(TaskStdin::Text(text)) => Some(StdinInput { text: text.clone(), file: None }),
+            _ => None,
+        };
+        return Ok((task.open_stdin()?, input));
+    };
+    let input = match &task.stdin {
+        None => StdinInput { text, file: None },
+        Some(TaskStdin::Text(stdin)) => StdinInput { text: text + stdin, file: None },
+        Some(TaskStdin::File(path)) => StdinInput { text, file: Some(File::open(path)?) },
+    };
+    Ok((Stdio::piped(), Some(input)))
+}
+
+/// The command as a single line for a shell, for the exporters
+pub fn shell_command(task: &TaskConfig) -> String {
+    match &task.executor {
+        Executor::Local | Executor::Closure(_) | Executor::Pipeline(_) => task.cmd.clone(),
+        Executor::Docker(docker) => {
+            let keys: Vec<&str> = task.env.iter().flat_map(|env| env.keys()).map(String::as_str).collect();
+            let args = docker_args(task, docker, None, &keys);
+            let args: Vec<String> = args.iter().map(|arg| quote(arg)).collect();
+            format!("{} {}", quote(&docker.binary), args.join(" "))
+        }
+        Executor::Ssh(ssh) => {
+            let env: Vec<(&str, &str)> = task.exported_env().into_iter().map(|(key, value)| (key.as_str(), value.as_str())).collect();
+            let mut args: Vec<String> = ssh_args(ssh).iter().map(|arg| quote(arg)).collect();
+            args.push(quote(&remote_command(task, &env, &[], None)));
+            format!("ssh {}", args.join(" "))
+        }
+    }
+}
+
+/// Stops what the task left running outside of its process group after a timeout or a shutdown. The docker client
+/// forwards the first signal to the container, but not the SIGKILL that follows it. The remote command of ssh doesn't
+/// get any signal when the client exits, its session is killed with another connection
+pub async fn stop(task: &TaskConfig, run_id: &str) {
+    let mut cmd = match &task.executor {
+        Executor::Local | Executor::Closure(_) | Executor::Pipeline(_) => return,
+        Executor::Docker(docker) => {
+            let mut cmd = Command::new(&docker.binary);
+            cmd.args(["rm", "--force", &container_name(task, run_id)]);
+            cmd
+        }
+        Executor::Ssh(ssh) => {
+            // The brackets keep the pattern from matching the shell that runs pgrep
+            let pattern = format!("[{}]{}{}", &REMOTE_MARKER[..1], &REMOTE_MARKER[1..], run_id);
+            let script = format!(
+                "pid=$(pgrep -o -f {}) && pkill -KILL -s \"$(ps -o sid= -p \"$pid\" | tr -d ' ')\"",
+                quote(&pattern)
+            );
+            let mut cmd = Command::new("ssh");
+            cmd.args(ssh_args(ssh)).arg(script);
+            cmd
+        }
+    };
+    let status = cmd.stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).status().await;
+    match status {
+        Ok(status) if status.success() => debug!("Stopped the remaining processes of task '{}' (run {})", task.name, run_id),
+        // The container or the remote processes are usually gone already
+        Ok(_) => {}
+        Err(e) => warn!("Failed to stop the remaining processes of task '{}' (run {}): {}", task.name, run_id, e),
+    }
+}
+
+/// Unique for each execution, docker only allows `[a-zA-Z0-9_.-]` in the names
+pub fn container_name(task: &TaskConfig, run_id: &str) -> String {
+    let name: String = task
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' || c == '.' || c == '-' { c } else { '-' })
+        .collect();
+    format!("cron-rs-{}-{}", name, run_id)
+}
+
+/// Quotes the arguments with characters that have a meaning for the shell, unlike [escape_shell_arg_string] options
+/// like `--rm` are left as they are
+pub fn quote(arg: &str) -> String {
+    let is_plain = |c: char| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c);
+    if !arg.is_empty() && arg.chars().all(is_plain) {
+        arg.to_string()
+    } else {
+        escape_shell_arg_string(arg)
+    }
+}
+
+/// Shell of the containers and the remote hosts, they run Unix whatever the platform of the scheduler
+fn shell(task: &TaskConfig) -> &str {
+    task.shell.as_deref().unwrap_or("/bin/sh")
+}
+
+fn shell_args(task: &TaskConfig) -> Vec<&str> {
+    let mut args = vec![];
+    if task.login_shell {
+        args.push("-l");
+    }
+    args.extend(["-c", task.cmd.as_str()]);
+    args
+}
+
+/// Options of the ssh client, followed by the host. BatchMode fails instead of asking for a password, there is nobody to
+/// answer it
+fn ssh_args(ssh: &SshExecutor) -> Vec<String> {
+    let mut args: Vec<String> = vec!["-o".into(), "BatchMode=yes".into()];
+    if let Some(timeout) = ssh.connect_timeout {
+        args.extend(["-o".into(), format!("ConnectTimeout={}", timeout)]);
+    }
+    if let Some(identity_file) = &ssh.identity_file {
+        args.extend(["-i".into(), identity_file.clone()]);
+    }
+    if let Some(port) = ssh.port {
+        args.extend(["-p".into(), port.to_string()]);
+    }
+    if let Some(user) = &ssh.user {
+        args.extend(["-l".into(), user.clone()]);
+    }
+    args.extend([ssh.host.clone(), "--".into()]);
+    args
+}
+
+/// Command line run by the shell of the remote user, sshd only accepts the variables allowed by its AcceptEnv. The
+/// values of `env` go in the command, for the exporters. The ones of `stdin_env` would be visible in the process list
+/// there, a `/bin/sh` reads them from the stdin, written by [remote_env_input], before it runs the task's shell. The
+/// marker with the run id is the `$0` of the task's shell, to find it in [stop]
+fn remote_command(task: &TaskConfig, env: &[(&str, &str)], stdin_env: &[&str], run_id: Option<&str>) -> String {
+    let mut command = String::new();
+    if let Some(dir) = &task.working_directory {
+        command.push_str(&format!("cd {} && ", quote(dir)));
+    }
+    let mut shell_command = quote(shell(task));
+    for arg in shell_args(task) {
+        shell_command.push_str(&format!(" {}", quote(arg)));
+    }
+    let marker = run_id.map(|run_id| quote(&format!("{}{}", REMOTE_MARKER, run_id)));
+    if stdin_env.is_empty() {
+        command.push_str("exec env");
+        for (key, value) in env {
+            command.push_str(&format!(" {}", quote(&format!("{}={}", key, value))));
+        }
+        command.push_str(&format!(" {}", shell_command));
+        if let Some(marker) = marker {
+            command.push_str(&format!(" {}", marker));
+        }
+        return command;
+    }
+    // printf turns the escapes of the values back into backslashes and newlines, the `x` keeps the trailing newlines
+    let script = format!(
+        "for k in {}; do IFS= read -r v && v=$(printf '%bx' \"$v\") && export \"$k=${{v%x}}\" || exit 1; done; \
+         unset k v; exec {} \"$0\"",
+        stdin_env.join(" "),
+        shell_command
+    );
+    command.push_str(&format!("exec /bin/sh -c {} {}", quote(&script), marker.unwrap_or_else(|| "sh".to_string())));
+    command
+}
+
+/// Values of the environment for the stdin of [remote_command], one per line with the backslashes and the newlines
+/// escaped
+fn remote_env_input(env: &[(&str, &str)]) -> String {
+    env.iter().map(|(_, value)| format!("{}\n", value.replace('\\', "\\\\").replace('\n', "\\n"))).collect()
+}
+
+/// Arguments of `docker run`, the working directory, the shell and the network are the ones of the container
+fn docker_args(task: &TaskConfig, docker: &DockerExecutor, name: Option<&str>, env_keys: &[&str]) -> Vec<String> {
+    // --init forwards the signals to the command, a shell as pid 1 ignores SIGTERM
+    let mut args: Vec<String> = vec!["run".into(), "--rm".into(), "--init".into()];
+    if let Some(name) = name {
+        args.extend(["--name".into(), name.into()]);
+    }
+    if task.stdin.is_some() {
+        args.push("--interactive".into());
+    }
+    if let Some(pull_policy) = docker.pull_policy {
+        args.push(format!("--pull={}", pull_policy.name()));
+    }
+    let network = match (&docker.network, task.network) {
+        (Some(network), _) => Some(network.as_str()),
+        (None, TaskNetwork::None) => Some("none"),
+        (None, TaskNetwork::Host) => None,
+    };
+    if let Some(network) = network {
+        args.extend(["--network".into(), network.into()]);
+    }
+    for volume in &docker.volumes {
+        args.extend(["--volume".into(), volume.clone()]);
+    }
+    if let Some(dir) = &task.working_directory {
+        args.extend(["--workdir".into(), dir.clone()]);
+    }
+    for key in env_keys {
+        args.extend(["--env".into(), key.to_string()]);
+    }
+    args.push(docker.image.clone());
+    args.push(shell(task).to_string());
+    args.extend(shell_args(task).into_iter().map(String::from));
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::file::ConfigFile;
+
+    #[test]
+    fn test_shell_process() {
+        let args = |cmd: std::process::Command| cmd.get_args().map(|arg| arg.to_string_lossy().to_string()).collect::<Vec<_>>();
+
+        assert_eq!(args(shell_process(Some("/bin/bash"), true, "echo hi")), ["-l", "-c", "echo hi"]);
+        assert_eq!(args(shell_process(Some("C:\\Windows\\System32\\cmd.exe"), false, "echo hi")), ["/C", "echo hi"]);
+        assert_eq!(args(shell_process(Some("pwsh"), true, "Get-Date")), ["-NoProfile", "-NonInteractive", "-Command", "Get-Date"]);
+    }
+
+    #[test]
+    fn test_docker_shell_command() {
+        let config_file: ConfigFile = serde_yml::from_str(
+            r#"
+tasks:
+  - name: Backup db
+    cmd: pg_dump app > /backup/app.sql
+    every: 1 day
+    working_directory: /backup
+    network: none
+    env:
+      PGHOST: db
+    executor: docker
+    docker:
+      image: postgres:16
+      volumes: ['/srv/backup:/backup']
+      pull_policy: missing
+"#,
+        )
+        .unwrap();
+        let config = crate::config::parse_config_file(&config_file).unwrap();
+        let task = &config.tasks[0];
+
+        assert_eq!(
+            shell_command(task),
+            "docker run --rm --init --pull=missing --network none --volume /srv/backup:/backup --workdir /backup \
+             --env PGHOST postgres:16 /bin/sh -c 'pg_dump app > /backup/app.sql'"
+        );
+        assert!(container_name(task, "20240101-000000-abcd").starts_with("cron-rs-Backup-db-"));
+    }
+
+    #[test]
+    fn test_ssh_shell_command() {
+        let config_file: ConfigFile = serde_yml::from_str(
+            r#"
+tasks:
+  - name: Rotate logs
+    cmd: logrotate /etc/logrotate.conf
+    every: 1 day
+    working_directory: /var/log
+    env:
+      MODE: it's late
+    executor: ssh
+    ssh:
+      host: web-1
+      user: ops
+      identity_file: /etc/cron-rs/id_ed25519
+      connect_timeout: 10 seconds
+"#,
+        )
+        .unwrap();
+        let config = crate::config::parse_config_file(&config_file).unwrap();
+        let task = &config.tasks[0];
+
+        // The exporters write the values in the command
+        let remote = remote_command(task, &[("MODE", "it's late")], &[], None);
+        assert_eq!(remote, "cd /var/log && exec env 'MODE=it'\\''s late' /bin/sh -c 'logrotate /etc/logrotate.conf'");
+        assert!(shell_command(task)
+            .starts_with("ssh -o BatchMode=yes -o ConnectTimeout=10 -i /etc/cron-rs/id_ed25519 -l ops web-1 -- 'cd /var/log && "));
+
+        // The scheduler sends the values through the stdin, the command line only has the names
+        let (cmd, env_input) = command(task, &RunContext::new(Utc::now())).unwrap();
+        let remote = cmd.as_std().get_args().last().unwrap().to_string_lossy().to_string();
+        assert!(remote.starts_with("cd /var/log && exec /bin/sh -c 'for k in CRON_RS_TASK_NAME "), "{}", remote);
+        assert!(remote.contains(" MODE; do "), "{}", remote);
+        assert!(!remote.contains("late"), "{}", remote);
+        assert!(!cmd.as_std().get_envs().any(|(_, value)| value.is_some()));
+        assert!(env_input.unwrap().ends_with("it's late\n"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_remote_env_is_read_from_stdin() {
+        let config_file: ConfigFile = serde_yml::from_str(
+            r#"
+tasks:
+  - name: remote
+    cmd: printf '%s|%s|' "$TOKEN" "$0"; cat
+    every: 1 hour
+    stdin_text: "input\n"
+"#,
+        )
+        .unwrap();
+        let config = crate::config::parse_config_file(&config_file).unwrap();
+        let task = &config.tasks[0];
+        let env = [("TOKEN", "a \\n\nb\n\n"), ("EMPTY", "")];
+        let remote = remote_command(task, &[], &["TOKEN", "EMPTY"], Some("20240101-000000-abcd"));
+        assert!(!remote.contains("a \\n"), "{}", remote);
+
+        // Like the remote sshd, the shell of the user runs the command line
+        let mut cmd = Command::new("/bin/sh");
+        cmd.args(["-c", &remote]).stdout(Stdio::piped());
+        let (stdin, input) = open_stdin(task, Some(remote_env_input(&env))).unwrap();
+        let mut child = cmd.stdin(stdin).spawn().unwrap();
+        input.unwrap().write(&mut child, &task.name);
+        let output = child.wait_with_output().await.unwrap();
+
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "a \\n\nb\n\n|cron-rs-run=20240101-000000-abcd|input\n");
+    }
+}
@@ -0,0 +1,136 @@
+use crate::alerts::{template_replace, EscapeStrategy, TaskExecutionDetails};
+use crate::config::TaskConfig;
+use crate::executor::shell_process;
+use crate::utils::{set_process_group, set_process_priority, set_resource_limits, set_network, set_user, set_working_directory, terminate_child};
+use anyhow::{bail, Result};
+use log::{debug, warn};
+use std::fs::{File, OpenOptions};
+use std::path::Path;
+use std::process::{ExitStatus, Stdio};
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Runs the `before` commands in order, stopping at the first one that fails. The error is the reason of the failure
+pub async fn run_before_hooks(
+    task: &TaskConfig,
+    run_id: &str,
+    stdout: &File,
+    stderr: &File,
+    user: Option<(u32, u32)>,
+) -> Result<(), String> {
+    let env = [("CRON_RS_RUN_ID", run_id.to_string())];
+    for hook in &task.before {
+        debug!("Task '{}' (run {}): running before command '{}'", task.name, run_id, hook);
+        let outputs = stdout.try_clone().and_then(|stdout| Ok((stdout, stderr.try_clone()?)));
+        let result = match outputs {
+            Ok((stdout, stderr)) => run_hook(task, hook, &env, stdout, stderr, user).await,
+            Err(e) => Err(e.into()),
+        };
+        match result {
+            Ok(status) if status.success() => {}
+            Ok(status) => return Err(format!("before command '{}' failed, {}", hook, status)),
+            Err(e) => return Err(format!("before command '{}' failed: {}", hook, e)),
+        }
+    }
+    Ok(())
+}
+
+/// Runs all the `after` commands, even if the task or some of them fail. They get the exit code of the task in
+/// `CRON_RS_EXIT_CODE`, -1 if it was killed by a signal, and their output is appended to the files of the execution
+pub async fn run_after_hooks(
+    task: &TaskConfig,
+    run_id: &str,
+    exit_code: i32,
+    stdout_path: &Path,
+    stderr_path: &Path,
+    user: Option<(u32, u32)>,
+) {
+    let env = [("CRON_RS_RUN_ID", run_id.to_string()), ("CRON_RS_EXIT_CODE", exit_code.to_string())];
+    for hook in &task.after {
+        debug!("Task '{}' (run {}): running after command '{}'", task.name, run_id, hook);
+        let result = match open_outputs(stdout_path, stderr_path) {
+            Ok((stdout, stderr)) => run_hook(task, hook, &env, stdout, stderr, user).await,
+            Err(e) => Err(e.into()),
+        };
+        match result {
+            Ok(status) if status.success() => {}
+            Ok(status) => warn!("Task '{}' (run {}): after command '{}' failed, {}", task.name, run_id, hook, status),
+            Err(e) => warn!("Task '{}' (run {}): after command '{}' failed: {}", task.name, run_id, hook, e),
+        }
+    }
+}
+
+/// Runs the `on_failure_cmd` of the task, it can use the same placeholders as the alerts, e.g. `{{ exit_code }}`.
+/// The values are quoted for the shell
+pub async fn run_failure_cmd(
+    task: &TaskConfig,
+    cmd: &str,
+    details: &TaskExecutionDetails,
+    stdout_path: &Path,
+    stderr_path: &Path,
+    user: Option<(u32, u32)>,
+) {
+    let cmd = template_replace(cmd, details, &EscapeStrategy::Shell);
+    let env = [("CRON_RS_RUN_ID", details.run_id.clone()), ("CRON_RS_EXIT_CODE", details.exit_code.to_string())];
+    debug!("Task '{}' (run {}): running on_failure_cmd '{}'", task.name, details.run_id, cmd);
+
+    let result = match open_outputs(stdout_path, stderr_path) {
+        Ok((stdout, stderr)) => run_hook(task, &cmd, &env, stdout, stderr, user).await,
+        Err(e) => Err(e.into()),
+    };
+    match result {
+        Ok(status) if status.success() => {}
+        Ok(status) => warn!("Task '{}' (run {}): on_failure_cmd failed, {}", task.name, details.run_id, status),
+        Err(e) => warn!("Task '{}' (run {}): on_failure_cmd failed: {}", task.name, details.run_id, e),
+    }
+}
+
+fn open_outputs(stdout_path: &Path, stderr_path: &Path) -> std::io::Result<(File, File)> {
+    let stdout = OpenOptions::new().create(true).append(true).open(stdout_path)?;
+    // Both streams share the file with combine_output
+    let stderr = if stderr_path == stdout_path {
+        stdout.try_clone()?
+    } else {
+        OpenOptions::new().create(true).append(true).open(stderr_path)?
+    };
+    Ok((stdout, stderr))
+}
+
+/// Runs a command with the same shell, env, working directory and user as the main command of the task, the time
+/// limit of the task applies to each command
+async fn run_hook(
+    task: &TaskConfig,
+    hook: &str,
+    env: &[(&str, String)],
+    stdout: File,
+    stderr: File,
+    user: Option<(u32, u32)>,
+) -> Result<ExitStatus> {
+    let mut cmd = Command::from(shell_process(task.shell.as_deref(), task.login_shell, hook));
+    cmd.envs(task.resolved_env()?);
+    cmd.envs(env.iter().map(|(key, value)| (key, value)));
+    set_working_directory(&mut cmd, task.chroot.as_deref(), task.working_directory.as_deref())?;
+    set_network(&mut cmd, task.network);
+    cmd.stdin(Stdio::null());
+    cmd.stdout(Stdio::from(stdout));
+    cmd.stderr(Stdio::from(stderr));
+    if let Some((uid, gid)) = user {
+        set_user(&mut cmd, uid, gid, task.login_env)?;
+    }
+    cmd.kill_on_drop(true);
+    set_process_group(cmd.as_std_mut());
+    set_resource_limits(&mut cmd, task.limits)?;
+    set_process_priority(&mut cmd, &task.priority);
+
+    let mut child = cmd.spawn()?;
+    let Some(time_limit) = task.time_limit else {
+        return Ok(child.wait().await?);
+    };
+    match tokio::time::timeout(Duration::from_secs(time_limit), child.wait()).await {
+        Ok(status) => Ok(status?),
+        Err(_) => {
+            terminate_child(&mut child, task.time_limit_signal, task.kill_grace(), &task.name).await?;
+            bail!("exceeded the time limit of {} seconds", time_limit)
+        }
+    }
+}
@@ -0,0 +1,342 @@
+use crate::config::executor::{DockerExecutor, Executor, PullPolicy};
+use crate::config::file::{StopSignal, TaskNetwork};
+use crate::config::{Config, TaskConfig};
+use crate::crontab;
+use crate::schedule_display::ScheduleDisplay;
+use anyhow::Result;
+use log::warn;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Kubernetes limits the name of a CronJob to 52 characters, the Jobs it creates add a suffix to it
+const MAX_NAME_LEN: usize = 52;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CronJob {
+    api_version: &'static str,
+    kind: &'static str,
+    metadata: Metadata,
+    spec: CronJobSpec,
+}
+
+#[derive(Debug, Serialize)]
+struct Metadata {
+    name: String,
+    annotations: Annotations,
+}
+
+#[derive(Debug, Serialize)]
+struct Annotations {
+    #[serde(rename = "cron-rs/task")]
+    task: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CronJobSpec {
+    schedule: String,
+    time_zone: String,
+    concurrency_policy: &'static str,
+    suspend: bool,
+    job_template: JobTemplate,
+}
+
+#[derive(Debug, Serialize)]
+struct JobTemplate {
+    spec: JobSpec,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JobSpec {
+    backoff_limit: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    active_deadline_seconds: Option<u64>,
+    template: PodTemplate,
+}
+
+#[derive(Debug, Serialize)]
+struct PodTemplate {
+    spec: PodSpec,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PodSpec {
+    restart_policy: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    termination_grace_period_seconds: Option<u64>,
+    containers: Vec<Container>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Container {
+    name: &'static str,
+    image: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image_pull_policy: Option<&'static str>,
+    command: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    working_dir: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    env: Vec<EnvVar>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resources: Option<Resources>,
+}
+
+#[derive(Debug, Serialize)]
+struct Resources {
+    limits: HashMap<&'static str, String>,
+}
+
+#[derive(Debug, Serialize)]
+struct EnvVar {
+    name: String,
+    value: String,
+}
+
+/// Generates a CronJob manifest for each task, they run in a container with the given image or the one of their
+/// `executor: docker`.
+/// Tasks with schedules that can't be written in cron syntax are left out with a warning
+pub fn export_k8s(config: &Config, image: &str) -> Result<String> {
+    if !config.alerts.on_failure.is_empty() || !config.alerts.on_success.is_empty() {
+        warn!("Global alerts have no equivalent in Kubernetes and are not exported");
+    }
+
+    let mut used_names = HashSet::new();
+    let mut documents = vec![];
+
+    for task in &config.tasks {
+        if task.executor.is_pipeline() {
+            warn!("Task '{}': pipelines have no equivalent in Kubernetes, the pipeline is not exported", task.name);
+            continue;
+        }
+        let Some(fields) = crontab::export_schedule(task) else {
+            warn!(
+                "Task '{}': the schedule has no equivalent in cron, the task is not exported, schedule: {}",
+                task.name,
+                ScheduleDisplay::format_schedule(&task.schedule)
+            );
+            continue;
+        };
+        warn_unsupported_options(task);
+
+        let base_name = resource_name(&task.name);
+        let mut name = base_name.clone();
+        let mut i = 2;
+        while !used_names.insert(name.clone()) {
+            let suffix = format!("-{}", i);
+            name = format!("{}{}", &base_name[..base_name.len().min(MAX_NAME_LEN - suffix.len())], suffix);
+            i += 1;
+        }
+
+        let mut env: Vec<EnvVar> = task
+            .env
+            .iter()
+            .flatten()
+            .map(|(name, value)| EnvVar { name: name.clone(), value: value.clone() })
+            .collect();
+        env.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let cron_job = CronJob {
+            api_version: "batch/v1",
+            kind: "CronJob",
+            metadata: Metadata { name, annotations: Annotations { task: task.name.clone() } },
+            spec: CronJobSpec {
+                schedule: fields.join(" "),
+                time_zone: task.timezone.name().to_string(),
+                concurrency_policy: if task.avoid_overlapping { "Forbid" } else { "Allow" },
+                suspend: !task.enabled,
+                job_template: JobTemplate {
+                    spec: JobSpec {
+                        // Failed executions are not retried by cron-rs either
+                        backoff_limit: 0,
+                        active_deadline_seconds: task.time_limit,
+                        template: PodTemplate {
+                            spec: PodSpec {
+                                restart_policy: "Never",
+                                termination_grace_period_seconds: task.kill_grace,
+                                containers: vec![Container {
+                                    name: "task",
+                                    image: match &task.executor {
+                                        Executor::Docker(docker) => docker.image.clone(),
+                                        Executor::Local | Executor::Ssh(_) | Executor::Closure(_) | Executor::Pipeline(_) => image.to_string(),
+                                    },
+                                    image_pull_policy: match &task.executor {
+                                        Executor::Docker(DockerExecutor { pull_policy: Some(policy), .. }) => Some(match policy {
+                                            PullPolicy::Always => "Always",
+                                            PullPolicy::Missing => "IfNotPresent",
+                                            PullPolicy::Never => "Never",
+                                        }),
+                                        _ => None,
+                                    },
+                                    command: vec![
+                                        task.shell.clone().unwrap_or_else(|| "/bin/sh".to_string()),
+                                        "-c".to_string(),
+                                        task.cmd.clone(),
+                                    ],
+                                    working_dir: task.working_directory.clone(),
+                                    env,
+                                    resources: resources(task),
+                                }],
+                            },
+                        },
+                    },
+                },
+            },
+        };
+
+        documents.push(serde_yml::to_string(&cron_job)?);
+    }
+
+    Ok(documents.join("---\n"))
+}
+
+/// The memory of the cgroup takes precedence, it limits all the processes together like in Kubernetes
+fn resources(task: &TaskConfig) -> Option<Resources> {
+    let mut limits = HashMap::new();
+    let cgroup = task.cgroup.unwrap_or_default();
+    if let Some(memory) = cgroup.memory_max.or(task.limits.memory) {
+        limits.insert("memory", memory.to_string());
+    }
+    if let Some(cpu) = cgroup.cpu_max {
+        // In millicores
+        limits.insert("cpu", format!("{}m", u64::from(cpu) * 10));
+    }
+    (!limits.is_empty()).then_some(Resources { limits })
+}
+
+fn warn_unsupported_options(task: &TaskConfig) {
+    let mut unsupported = vec![];
+    if crate::template::has_variables(&task.cmd) {
+        unsupported.push("cmd variables");
+    }
+    if task.run_as.is_some() {
+        unsupported.push("run_as (set securityContext.runAsUser)");
+    }
+    if task.max_runs.is_some() {
+        unsupported.push("max_runs");
+    }
+    if task.stdout.is_some() || task.stderr.is_some() {
+        unsupported.push("stdout/stderr (the output goes to the pod logs)");
+    }
+    if !task.on_failure.is_empty() || !task.on_success.is_empty() {
+        unsupported.push("alerts");
+    }
+    if task.stdin.is_some() {
+        unsupported.push("stdin");
+    }
+    if !task.before.is_empty() || !task.after.is_empty() {
+        unsupported.push("before/after");
+    }
+    if task.on_failure_cmd.is_some() {
+        unsupported.push("on_failure_cmd");
+    }
+    if task.lock_file.is_some() {
+        unsupported.push("lock_file");
+    }
+    if task.cluster_lock.is_some() {
+        unsupported.push("cluster_lock");
+    }
+    if task.login_shell {
+        unsupported.push("login_shell");
+    }
+    if task.network == TaskNetwork::None {
+        unsupported.push("network: none");
+    }
+    if task.chroot.is_some() {
+        unsupported.push("chroot");
+    }
+    if task.limits.cpu_time.is_some() || task.limits.open_files.is_some() || task.limits.core_size.is_some() {
+        unsupported.push("limits other than memory");
+    }
+    if task.time_limit_signal != StopSignal::Sigterm {
+        unsupported.push("time_limit_signal (pods always get a SIGTERM)");
+    }
+    if !task.priority.is_empty() {
+        unsupported.push("nice/ionice/cpu_affinity");
+    }
+    if let Executor::Ssh(_) = &task.executor {
+        unsupported.push("executor: ssh");
+    }
+    if let Executor::Docker(docker) = &task.executor {
+        if !docker.volumes.is_empty() {
+            unsupported.push("docker.volumes");
+        }
+        if docker.network.is_some() {
+            unsupported.push("docker.network");
+        }
+    }
+    if task.conditions.needs_power_status() {
+        unsupported.push("only_on_ac_power/min_battery_percent");
+    }
+    if task.conditions.has_only_if() {
+        unsupported.push("only_if");
+    }
+
+    if !unsupported.is_empty() {
+        warn!("Task '{}': ignoring options without an equivalent in Kubernetes: {}", task.name, unsupported.join(", "));
+    }
+}
+
+/// Resource names only keep lowercase letters, digits and dashes, e.g. `Daily Backup` => `daily-backup`
+fn resource_name(task_name: &str) -> String {
+    let words: Vec<String> = task_name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_ascii_lowercase())
+        .collect();
+
+    let name = words.join("-");
+    let name = name[..name.len().min(MAX_NAME_LEN)].trim_end_matches('-');
+    if name.is_empty() {
+        "task".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::file::ConfigFile;
+
+    #[test]
+    fn test_export_k8s() {
+        let config_file: ConfigFile = serde_yml::from_str(
+            r#"
+tasks:
+  - name: Daily report
+    cmd: echo "done"
+    when: '* *-*-* 03:30:00'
+    timezone: Europe/Madrid
+    avoid_overlapping: true
+    env:
+      LANG: C
+  - name: poll
+    cmd: check
+    every: 7 minutes
+"#,
+        )
+        .unwrap();
+        let config = crate::config::parse_config_file(&config_file).unwrap();
+
+        let manifests = export_k8s(&config, "alpine:3").unwrap();
+        let documents: Vec<serde_yml::Value> = manifests.split("---\n").map(|d| serde_yml::from_str(d).unwrap()).collect();
+        // 'poll' has no equivalent in cron
+        assert_eq!(documents.len(), 1);
+
+        let cron_job = &documents[0];
+        assert_eq!(cron_job["metadata"]["name"].as_str(), Some("daily-report"));
+        assert_eq!(cron_job["spec"]["schedule"].as_str(), Some("30 3 * * *"));
+        assert_eq!(cron_job["spec"]["timeZone"].as_str(), Some("Europe/Madrid"));
+        assert_eq!(cron_job["spec"]["concurrencyPolicy"].as_str(), Some("Forbid"));
+
+        let container = &cron_job["spec"]["jobTemplate"]["spec"]["template"]["spec"]["containers"][0];
+        assert_eq!(container["image"].as_str(), Some("alpine:3"));
+        assert_eq!(container["command"][2].as_str(), Some("echo \"done\""));
+        assert_eq!(container["env"][0]["name"].as_str(), Some("LANG"));
+    }
+}
@@ -0,0 +1,347 @@
+use crate::config::file::TaskNetwork;
+use crate::config::limits::IoPriority;
+use crate::config::{Config, Schedule, TaskConfig, TaskStdin, TimePatternField};
+use crate::executor;
+use crate::utils::system_timezone;
+use log::warn;
+use std::collections::HashSet;
+
+/// launchd can't express ranges or steps, each combination of values is a separate entry of StartCalendarInterval
+const MAX_CALENDAR_ENTRIES: usize = 1000;
+
+/// A property list generated for a task
+#[derive(Debug, Clone)]
+pub struct LaunchdJob {
+    pub label: String,
+    pub plist: String,
+}
+
+/// Generates a launchd property list for each task of the config
+pub fn export_launchd(config: &Config) -> Vec<LaunchdJob> {
+    if !config.alerts.on_failure.is_empty() || !config.alerts.on_success.is_empty() {
+        warn!("Global alerts have no equivalent in launchd and are not exported");
+    }
+
+    let local_timezone = system_timezone().name().to_string();
+    let mut used_labels = HashSet::new();
+    let mut jobs = vec![];
+
+    for task in &config.tasks {
+        if task.executor.is_pipeline() {
+            warn!("Task '{}': pipelines have no equivalent in launchd, the pipeline is not exported", task.name);
+            continue;
+        }
+        if let Schedule::AfterTask { .. } = &task.schedule {
+            warn!("Task '{}': launchd can't start a job when another one finishes, the task is not exported", task.name);
+            continue;
+        }
+        let Some(schedule) = export_schedule(task) else {
+            warn!("Task '{}': the schedule needs more than {} launchd entries, the task is not exported", task.name, MAX_CALENDAR_ENTRIES);
+            continue;
+        };
+        warn_unsupported_options(task);
+        if task.timezone.name() != local_timezone {
+            warn!("Task '{}': launchd uses the timezone of the system, the timezone {} is ignored", task.name, task.timezone.name());
+        }
+
+        let base_label = label(&task.name);
+        let mut label = base_label.clone();
+        let mut i = 2;
+        while !used_labels.insert(label.clone()) {
+            label = format!("{}-{}", base_label, i);
+            i += 1;
+        }
+
+        jobs.push(LaunchdJob { plist: export_plist(task, &label, schedule), label });
+    }
+
+    jobs
+}
+
+fn warn_unsupported_options(task: &TaskConfig) {
+    let mut unsupported = vec![];
+    if crate::template::has_variables(&task.cmd) {
+        unsupported.push("cmd variables");
+    }
+    if task.time_limit.is_some() {
+        unsupported.push("time_limit");
+    }
+    if task.max_runs.is_some() {
+        unsupported.push("max_runs");
+    }
+    if !task.on_failure.is_empty() || !task.on_success.is_empty() {
+        unsupported.push("alerts");
+    }
+    if let Schedule::When { min_interval: Some(_), .. } = &task.schedule {
+        unsupported.push("min_interval");
+    }
+    if let Some(TaskStdin::Text(_)) = &task.stdin {
+        unsupported.push("stdin_text");
+    }
+    if !task.before.is_empty() || !task.after.is_empty() {
+        unsupported.push("before/after");
+    }
+    if task.on_failure_cmd.is_some() {
+        unsupported.push("on_failure_cmd");
+    }
+    if task.env_file.is_some() {
+        unsupported.push("env_file");
+    }
+    if task.lock_file.is_some() {
+        unsupported.push("lock_file");
+    }
+    if task.cluster_lock.is_some() {
+        unsupported.push("cluster_lock");
+    }
+    if task.login_shell {
+        unsupported.push("login_shell");
+    }
+    if task.network == TaskNetwork::None {
+        unsupported.push("network: none");
+    }
+    if task.cgroup.is_some() {
+        unsupported.push("cgroup");
+    }
+    if task.limits.memory.is_some() {
+        unsupported.push("limits.memory");
+    }
+    if task.priority.ionice.is_some_and(|ionice| ionice != IoPriority::Idle) {
+        unsupported.push("ionice other than idle");
+    }
+    if !task.priority.cpu_affinity.is_empty() {
+        unsupported.push("cpu_affinity");
+    }
+    if task.conditions.needs_power_status() {
+        unsupported.push("only_on_ac_power/min_battery_percent");
+    }
+    if task.conditions.has_only_if() {
+        unsupported.push("only_if");
+    }
+
+    if !unsupported.is_empty() {
+        warn!("Task '{}': ignoring options without an equivalent in launchd: {}", task.name, unsupported.join(", "));
+    }
+}
+
+/// Labels are reverse domain names, e.g. `Daily Backup` => `com.cron-rs.daily-backup`
+fn label(task_name: &str) -> String {
+    let words: Vec<String> = task_name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_ascii_lowercase())
+        .collect();
+    format!("com.cron-rs.{}", words.join("-")).trim_end_matches('.').to_string()
+}
+
+fn export_plist(task: &TaskConfig, label: &str, schedule: LaunchdSchedule) -> String {
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+        "<!DOCTYPE plist PUBLIC \"-//Apple//DTD PLIST 1.0//EN\" \"http://www.apple.com/DTDs/PropertyList-1.0.dtd\">\n",
+        "<plist version=\"1.0\">\n<dict>\n",
+    ));
+    let mut key = |key: &str, value: String| out.push_str(&format!("    <key>{}</key>\n    {}\n", key, value));
+
+    key("Label", string(label));
+    let shell = task.shell.as_deref().unwrap_or("/bin/sh");
+    key("ProgramArguments", format!("<array>\n        {}\n        {}\n        {}\n    </array>", string(shell), string("-c"), string(&executor::shell_command(task))));
+
+    match schedule {
+        LaunchdSchedule::Interval(seconds) => key("StartInterval", format!("<integer>{}</integer>", seconds)),
+        LaunchdSchedule::WatchPaths(paths) => {
+            let paths: Vec<String> = paths.iter().map(|path| format!("        {}\n", string(path))).collect();
+            key("WatchPaths", format!("<array>\n{}    </array>", paths.concat()));
+        }
+        LaunchdSchedule::Calendar(entries) => {
+            let dicts: Vec<String> = entries
+                .iter()
+                .map(|entry| {
+                    let values: Vec<String> = entry
+                        .iter()
+                        .map(|(k, v)| format!("            <key>{}</key>\n            <integer>{}</integer>\n", k, v))
+                        .collect();
+                    format!("        <dict>\n{}        </dict>\n", values.concat())
+                })
+                .collect();
+            key("StartCalendarInterval", format!("<array>\n{}    </array>", dicts.concat()));
+        }
+    }
+
+    if let Some(run_as) = &task.run_as {
+        let (user, group) = run_as.split_once(':').unwrap_or((run_as, ""));
+        key("UserName", string(user));
+        if !group.is_empty() {
+            key("GroupName", string(group));
+        }
+    }
+    if let Some(root) = &task.chroot {
+        key("RootDirectory", string(root));
+    }
+    if let Some(dir) = task.working_directory.as_ref().filter(|_| task.executor.is_local()) {
+        key("WorkingDirectory", string(dir));
+    }
+    let vars = task.exported_env();
+    if !vars.is_empty() {
+        let entries: Vec<String> =
+            vars.iter().map(|(k, v)| format!("        <key>{}</key>\n        {}\n", escape(k), string(v))).collect();
+        key("EnvironmentVariables", format!("<dict>\n{}    </dict>", entries.concat()));
+    }
+    if let Some(TaskStdin::File(path)) = &task.stdin {
+        key("StandardInPath", string(path));
+    }
+    if let Some(stdout) = &task.stdout {
+        key("StandardOutPath", string(stdout));
+    }
+    let stderr = if task.combine_output { &task.stdout } else { &task.stderr };
+    if let Some(stderr) = stderr {
+        key("StandardErrorPath", string(stderr));
+    }
+    let limits = [("CPU", task.limits.cpu_time), ("Core", task.limits.core_size), ("NumberOfFiles", task.limits.open_files)];
+    let entries: Vec<String> = limits
+        .iter()
+        .filter_map(|(name, value)| Some(format!("        <key>{}</key>\n        <integer>{}</integer>\n", name, (*value)?)))
+        .collect();
+    if !entries.is_empty() {
+        key("SoftResourceLimits", format!("<dict>\n{}    </dict>", entries.concat()));
+        key("HardResourceLimits", format!("<dict>\n{}    </dict>", entries.concat()));
+    }
+    if let Some(nice) = task.priority.nice {
+        key("Nice", format!("<integer>{}</integer>", nice));
+    }
+    if task.priority.ionice == Some(IoPriority::Idle) {
+        key("LowPriorityIO", "<true/>".to_string());
+    }
+    if !task.enabled {
+        key("Disabled", "<true/>".to_string());
+    }
+
+    out.push_str("</dict>\n</plist>\n");
+    out
+}
+
+/// When launchd starts the job
+enum LaunchdSchedule {
+    /// Entries of `StartCalendarInterval`, each one with the values of Month, Day, Weekday, Hour and Minute
+    Calendar(Vec<Vec<(&'static str, u32)>>),
+    /// `StartInterval` in seconds
+    Interval(u64),
+    /// `WatchPaths`, the job starts when one of them changes
+    WatchPaths(Vec<String>),
+}
+
+/// None if the schedule needs too many calendar entries
+fn export_schedule(task: &TaskConfig) -> Option<LaunchdSchedule> {
+    let time = match (task.calendar_pattern(), &task.schedule) {
+        // launchd runs calendar entries at most once per minute
+        (Some(time), Schedule::Every { interval, .. }) if !matches!(time.second, TimePatternField::Value(0)) => {
+            return Some(LaunchdSchedule::Interval(interval.as_secs()));
+        }
+        (Some(time), _) => time,
+        (None, Schedule::Every { interval, window, .. }) => {
+            if window.is_some() {
+                warn!("Task '{}': the 'between' and 'on' options can't be represented in launchd, they are ignored", task.name);
+            }
+            return Some(LaunchdSchedule::Interval(interval.as_secs()));
+        }
+        (None, Schedule::OnChange { paths, .. }) => {
+            warn!("Task '{}': launchd doesn't debounce the changes nor watch directories recursively", task.name);
+            return Some(LaunchdSchedule::WatchPaths(paths.iter().map(|path| path.to_string_lossy().to_string()).collect()));
+        }
+        (None, Schedule::When { .. } | Schedule::Period { .. } | Schedule::AfterTask { .. }) => unreachable!(),
+    };
+
+    match time.second {
+        TimePatternField::Value(0) => {}
+        TimePatternField::Value(_) => warn!("Task '{}': launchd can't run at a given second, using second 0", task.name),
+        _ => warn!("Task '{}': launchd runs at most once per minute, the seconds are ignored", task.name),
+    }
+    if !matches!(time.year, TimePatternField::Any) {
+        warn!("Task '{}': launchd has no year field, the year is ignored", task.name);
+    }
+
+    let fields = [
+        ("Month", expand(&time.month, 1, 12)),
+        ("Day", expand(&time.day, 1, 31)),
+        ("Weekday", expand(&time.day_of_week, 0, 6)),
+        ("Hour", expand(&time.hour, 0, 23)),
+        ("Minute", expand(&time.minute, 0, 59)),
+    ];
+
+    let mut entries = vec![vec![]];
+    for (key, values) in fields {
+        let Some(values) = values else { continue };
+        if entries.len() * values.len() > MAX_CALENDAR_ENTRIES {
+            return None;
+        }
+        entries = entries
+            .iter()
+            .flat_map(|entry| {
+                values.iter().map(move |value| {
+                    let mut entry = entry.clone();
+                    entry.push((key, *value));
+                    entry
+                })
+            })
+            .collect();
+    }
+
+    Some(LaunchdSchedule::Calendar(entries))
+}
+
+/// Values matched by the field, None if it matches all of them
+fn expand(field: &TimePatternField, min: u32, max: u32) -> Option<Vec<u32>> {
+    let mut values: Vec<u32> = match field {
+        TimePatternField::Any => return None,
+        TimePatternField::Value(v) => vec![*v],
+        TimePatternField::Range(start, end) => (*start..=*end).collect(),
+        TimePatternField::List(values) => values.clone(),
+        TimePatternField::Ratio(..) => (min..=max).filter(|v| field.matches_value(*v)).collect(),
+    };
+    // Sunday can be 0 or 7
+    if max == 6 {
+        values.iter_mut().filter(|v| **v == 7).for_each(|v| *v = 0);
+    }
+    values.sort();
+    values.dedup();
+    Some(values)
+}
+
+fn string(value: &str) -> String {
+    format!("<string>{}</string>", escape(value))
+}
+
+fn escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::file::ConfigFile;
+
+    #[test]
+    fn test_export_launchd() {
+        let config_file: ConfigFile = serde_yml::from_str(
+            r#"
+tasks:
+  - name: Daily report
+    cmd: echo "a & b"
+    when: '[Mon, Fri] *-*-* 03:30:00'
+  - name: poll
+    cmd: check
+    every: 90 seconds
+"#,
+        )
+        .unwrap();
+        let config = crate::config::parse_config_file(&config_file).unwrap();
+
+        let jobs = export_launchd(&config);
+        assert_eq!(jobs[0].label, "com.cron-rs.daily-report");
+        let plist = &jobs[0].plist;
+        assert!(plist.contains("<string>echo \"a &amp; b\"</string>"));
+        // One entry for each day of the week
+        assert_eq!(plist.matches("<key>Weekday</key>").count(), 2);
+        assert!(plist.contains("<key>Weekday</key>\n            <integer>5</integer>\n            <key>Hour</key>\n            <integer>3</integer>\n            <key>Minute</key>\n            <integer>30</integer>"));
+
+        assert!(jobs[1].plist.contains("<key>StartInterval</key>\n    <integer>90</integer>"));
+    }
+}
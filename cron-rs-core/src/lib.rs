@@ -0,0 +1,53 @@
+//! Scheduler of cron-rs as a library, to run the tasks of a config file inside another Rust service.
+//!
+//! ```no_run
+//! # async fn example() -> anyhow::Result<()> {
+//! let config_file = cron_rs_core::config::file::read_config_file("cron-rs.yml")?;
+//! let config = cron_rs_core::parse_config_file(&config_file)?;
+//!
+//! let scheduler = cron_rs_core::Scheduler::spawn(config).await;
+//! let mut events = scheduler.subscribe();
+//! while let Ok(event) = events.recv().await {
+//!     println!("{:?}", event);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod alert_queue;
+pub mod alerts;
+pub mod cgroup;
+pub mod clock;
+pub mod cluster_lock;
+pub mod conditions;
+pub mod config;
+pub mod control;
+pub mod crontab;
+pub mod digest;
+pub mod events;
+pub mod executor;
+pub mod hooks;
+pub mod k8s;
+pub mod launchd;
+pub mod logging;
+pub mod metrics;
+pub mod plugins;
+pub mod reaper;
+pub mod resource_usage;
+pub mod retention;
+pub mod schedule_display;
+pub mod scheduler;
+pub mod sentry;
+pub mod sqlite_logger;
+pub mod systemd;
+pub mod task_executor;
+pub mod taskscheduler;
+pub mod template;
+pub mod utils;
+pub mod watch;
+#[cfg(windows)]
+pub mod windows_service;
+
+pub use config::{parse_config_file, Config, Schedule, TaskConfig, TimePattern};
+pub use events::SchedulerEvent;
+pub use scheduler::{Scheduler, SchedulerHandle};
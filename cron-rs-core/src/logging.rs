@@ -0,0 +1,727 @@
+use anyhow::{Context, Result};
+use crate::config::logging::{LogFormat, LogOutput, LogRotationConfig, LoggingConfig, SyslogConfig, SyslogFormat};
+#[cfg(unix)]
+use crate::config::logging::SyslogProtocol;
+use crate::config::retention::parse_size;
+use crate::retention;
+#[cfg(unix)]
+use crate::utils::format_duration;
+use chrono::{DateTime, FixedOffset, SecondsFormat, Utc};
+use log::kv::{Key, Value, VisitSource};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+#[cfg(unix)]
+use std::time::{Duration, Instant};
+
+#[cfg(unix)]
+const JOURNALD_SOCKET_PATH: &str = "/run/systemd/journal/socket";
+
+/// Time limit to connect and to write to a syslog server over TCP
+#[cfg(unix)]
+const SYSLOG_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Wait before connecting again to syslog after a failed reconnection
+#[cfg(unix)]
+const SYSLOG_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// Id of the structured data of the RFC 5424 syslog messages. 32473 is the enterprise number reserved for examples,
+/// cron-rs doesn't have one
+const SYSLOG_SD_ID: &str = "cron-rs@32473";
+
+/// Log file of `output: file` without `file`, Windows has no standard directory for the logs
+#[cfg(unix)]
+const DEFAULT_LOG_FILE: &str = "/var/log/cron-rs.log";
+#[cfg(windows)]
+const DEFAULT_LOG_FILE: &str = "cron-rs.log";
+
+/// Level of the config file, see [toggle_debug]
+static CONFIGURED_LEVEL: AtomicUsize = AtomicUsize::new(LevelFilter::Info as usize);
+
+/// The loggers let all the records through, the level is only the max level of the `log` crate so it can be changed
+/// at runtime with [set_level]
+pub fn setup_logging(config: &LoggingConfig) -> Result<()> {
+    let level = config.level.parse::<LevelFilter>()?;
+    CONFIGURED_LEVEL.store(level as usize, Ordering::Relaxed);
+
+    let logger: Box<dyn Log> = match &config.output {
+        LogOutput::Stdout => Box::new(env_logger_builder(config).build()),
+        LogOutput::File => {
+            let path = config.file.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_LOG_FILE));
+            let target: Box<dyn Write + Send> = match &config.rotation {
+                Some(rotation) => Box::new(RotatingFile::open(path, rotation)?),
+                None => Box::new(OpenOptions::new().create(true).append(true).open(path)?),
+            };
+
+            Box::new(env_logger_builder(config).target(env_logger::Target::Pipe(target)).build())
+        }
+        #[cfg(unix)]
+        LogOutput::Syslog => Box::new(SyslogLogger::connect(&config.syslog.clone().unwrap_or_default())?),
+        #[cfg(unix)]
+        LogOutput::Journald => Box::new(JournaldLogger::connect(JOURNALD_SOCKET_PATH)?),
+        #[cfg(windows)]
+        LogOutput::Syslog | LogOutput::Journald => anyhow::bail!("The syslog and journald outputs are not supported on Windows"),
+    };
+
+    let logger = match &config.per_task_dir {
+        Some(dir) => {
+            std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+            Box::new(PerTaskLogger { inner: logger, dir: dir.clone(), format: config.format, files: Mutex::default() })
+        }
+        None => logger,
+    };
+    log::set_boxed_logger(logger).map(|()| log::set_max_level(level))?;
+
+    Ok(())
+}
+
+pub fn set_level(level: LevelFilter) {
+    log::set_max_level(level);
+}
+
+/// Switches between the debug level and the level of the config file, returns the new level. When the config file
+/// already uses `debug` or `trace` it switches between that level and `info` instead
+pub fn toggle_debug() -> LevelFilter {
+    let configured = LevelFilter::iter().nth(CONFIGURED_LEVEL.load(Ordering::Relaxed)).unwrap_or(LevelFilter::Info);
+    let level = toggled_level(log::max_level(), configured);
+    set_level(level);
+    level
+}
+
+fn toggled_level(current: LevelFilter, configured: LevelFilter) -> LevelFilter {
+    if configured >= LevelFilter::Debug {
+        if current == configured { LevelFilter::Info } else { configured }
+    } else if current >= LevelFilter::Debug {
+        configured
+    } else {
+        LevelFilter::Debug
+    }
+}
+
+/// Also writes the records with a `task` key-value to `{per_task_dir}/{task}.log`
+struct PerTaskLogger {
+    inner: Box<dyn Log>,
+    dir: PathBuf,
+    format: LogFormat,
+    /// Open files by task name
+    files: Mutex<HashMap<String, File>>,
+}
+
+impl PerTaskLogger {
+    fn write_task_line(&self, task: &str, record: &Record) -> std::io::Result<()> {
+        let line = match self.format {
+            LogFormat::Text => format!(
+                "[{} {:<5} {}] {}",
+                Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+                record.level(),
+                record.target(),
+                record.args()
+            ),
+            LogFormat::Json => json_line(record),
+        };
+
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        let file = match files.entry(task.to_string()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let path = self.dir.join(format!("{}.log", sanitise_file_name::sanitise(task)));
+                entry.insert(OpenOptions::new().create(true).append(true).open(path)?)
+            }
+        };
+        writeln!(file, "{}", line)
+    }
+}
+
+impl Log for PerTaskLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        self.inner.log(record);
+        if record.level() > log::max_level() {
+            return;
+        }
+        if let Some(task) = record.key_values().get(Key::from_str("task")) {
+            if let Err(e) = self.write_task_line(&task.to_string(), record) {
+                eprintln!("Failed to write the log of task '{}': {}", task, e);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Log file that is renamed to `{file}.1` before it grows past `max_size`, the previous `{file}.N` become `{file}.N+1`
+/// and the ones over `max_files` are deleted
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    max_files: usize,
+    compress: bool,
+}
+
+impl RotatingFile {
+    fn open(path: PathBuf, config: &LogRotationConfig) -> Result<Self> {
+        let max_size = parse_size(&config.max_size).context("Malformed field: logging.rotation.max_size")?;
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(RotatingFile {
+            path,
+            file,
+            size,
+            max_size,
+            max_files: config.max_files.max(1),
+            compress: config.compress,
+        })
+    }
+
+    fn rotated_path(&self, index: usize, gz: bool) -> PathBuf {
+        let mut path = self.path.as_os_str().to_owned();
+        path.push(format!(".{}{}", index, if gz { ".gz" } else { "" }));
+        PathBuf::from(path)
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        for gz in [false, true] {
+            let _ = std::fs::remove_file(self.rotated_path(self.max_files, gz));
+            for index in (1..self.max_files).rev() {
+                let from = self.rotated_path(index, gz);
+                if from.exists() {
+                    std::fs::rename(from, self.rotated_path(index + 1, gz))?;
+                }
+            }
+        }
+        std::fs::rename(&self.path, self.rotated_path(1, false))?;
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+
+        if self.compress {
+            // In the background, the logger can't wait for it
+            let rotated = self.rotated_path(1, false);
+            std::thread::spawn(move || {
+                if let Err(e) = retention::compress(&rotated) {
+                    eprintln!("Failed to compress {}: {}", rotated.display(), e);
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            // Logging goes on in the same file if it can't be rotated
+            if let Err(e) = self.rotate() {
+                eprintln!("Failed to rotate {}: {}", self.path.display(), e);
+            }
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+}
+
+fn env_logger_builder(config: &LoggingConfig) -> env_logger::Builder {
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(LevelFilter::Trace);
+    match config.format {
+        LogFormat::Text => builder.format_timestamp_millis(),
+        LogFormat::Json => builder.format(|buf, record| writeln!(buf, "{}", json_line(record))),
+    };
+    builder
+}
+
+/// The record as a JSON object, its key-values like `task` and `run_id` are fields of the object
+fn json_line(record: &Record) -> String {
+    let mut line = serde_json::Map::new();
+    line.insert("timestamp".into(), Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true).into());
+    line.insert("level".into(), record.level().as_str().into());
+    line.insert("target".into(), record.target().into());
+    line.insert("message".into(), record.args().to_string().into());
+
+    struct Fields<'a>(&'a mut serde_json::Map<String, serde_json::Value>);
+    impl<'kvs> VisitSource<'kvs> for Fields<'_> {
+        fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+            let value = match value.to_i64() {
+                Some(number) => number.into(),
+                None => value.to_string().into(),
+            };
+            self.0.insert(key.as_str().to_string(), value);
+            Ok(())
+        }
+    }
+    let _ = record.key_values().visit(&mut Fields(&mut line));
+
+    serde_json::Value::Object(line).to_string()
+}
+
+/// Sends the records to journald as datagrams of its native protocol. The key-values of the records become fields of
+/// the entry, e.g. `task` is `TASK=`, so `journalctl TASK=backup` shows the logs of a task
+#[cfg(unix)]
+struct JournaldLogger {
+    socket: std::os::unix::net::UnixDatagram,
+}
+
+#[cfg(unix)]
+impl JournaldLogger {
+    fn connect(path: &str) -> Result<Self> {
+        let socket = std::os::unix::net::UnixDatagram::unbound()?;
+        socket
+            .connect(path)
+            .with_context(|| format!("Failed to connect to journald at {}", path))?;
+        Ok(JournaldLogger { socket })
+    }
+}
+
+#[cfg(unix)]
+impl Log for JournaldLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        // There is nowhere to report the errors of the logger
+        let _ = self.socket.send(&journald_entry(record));
+    }
+
+    fn flush(&self) {}
+}
+
+/// Sends the records to the local syslog socket or to a remote server, see [syslog_message]
+#[cfg(unix)]
+struct SyslogLogger {
+    connection: Mutex<SyslogConnection>,
+    config: SyslogConfig,
+    /// Only sent to remote servers, the local daemon adds it
+    hostname: Option<String>,
+}
+
+/// The socket to syslog, it's opened again when a write fails, like when the daemon restarts or the TCP connection
+/// breaks
+#[cfg(unix)]
+struct SyslogConnection {
+    backend: Option<syslog::LoggerBackend>,
+    /// After a failed reconnection, the records are dropped until this time instead of waiting for the server each time
+    retry_at: Option<Instant>,
+}
+
+#[cfg(unix)]
+impl SyslogLogger {
+    fn connect(config: &SyslogConfig) -> Result<Self> {
+        let backend = Self::open(config)?;
+        let hostname = config.server.is_some().then(crate::utils::hostname);
+        let connection = SyslogConnection { backend: Some(backend), retry_at: None };
+        Ok(SyslogLogger { connection: Mutex::new(connection), config: config.clone(), hostname })
+    }
+
+    fn open(config: &SyslogConfig) -> Result<syslog::LoggerBackend> {
+        let Some(server) = &config.server else {
+            let logger = syslog::unix(()).map_err(|e| anyhow::anyhow!("Failed to connect to the local syslog: {}", e))?;
+            return Ok(logger.backend);
+        };
+        use std::net::{TcpStream, ToSocketAddrs, UdpSocket};
+        let address = server
+            .to_socket_addrs()
+            .with_context(|| format!("Invalid syslog server '{}'", server))?
+            .next()
+            .with_context(|| format!("Syslog server '{}' has no address", server))?;
+        let backend = match config.protocol {
+            SyslogProtocol::Udp => {
+                let local = if address.is_ipv6() { "[::]:0" } else { "0.0.0.0:0" };
+                syslog::LoggerBackend::Udp(UdpSocket::bind(local)?, address)
+            }
+            SyslogProtocol::Tcp => {
+                // A server that stops reading would block the logging, and with it the scheduler
+                let stream = TcpStream::connect_timeout(&address, SYSLOG_TIMEOUT)
+                    .with_context(|| format!("Failed to connect to syslog at {}", server))?;
+                stream.set_write_timeout(Some(SYSLOG_TIMEOUT))?;
+                syslog::LoggerBackend::Tcp(std::io::BufWriter::new(stream))
+            }
+        };
+        Ok(backend)
+    }
+}
+
+#[cfg(unix)]
+impl SyslogConnection {
+    /// Writes the message, opening the socket again and retrying once if it fails
+    fn send(&mut self, config: &SyslogConfig, message: &[u8]) {
+        if let Some(backend) = &mut self.backend {
+            match backend.write_all(message) {
+                Ok(()) => return,
+                Err(e) => eprintln!("Failed to write to syslog, reconnecting: {}", e),
+            }
+            self.backend = None;
+        } else if self.retry_at.is_some_and(|retry_at| Instant::now() < retry_at) {
+            return;
+        }
+
+        let result = SyslogLogger::open(config).and_then(|mut backend| {
+            backend.write_all(message)?;
+            Ok(backend)
+        });
+        match result {
+            Ok(backend) => {
+                self.backend = Some(backend);
+                self.retry_at = None;
+            }
+            Err(e) => {
+                let delay = format_duration(SYSLOG_RETRY_DELAY);
+                eprintln!("Failed to reconnect to syslog, retrying in {}: {:#}", delay, e);
+                self.retry_at = Some(Instant::now() + SYSLOG_RETRY_DELAY);
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Log for SyslogLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let time = chrono::Local::now().fixed_offset();
+        let mut message = syslog_message(record, &self.config, self.hostname.as_deref(), std::process::id(), time);
+        // The messages of a TCP stream are separated by new lines
+        if self.config.server.is_some() && self.config.protocol == SyslogProtocol::Tcp {
+            message.push('\n');
+        }
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        connection.send(&self.config, message.as_bytes());
+    }
+
+    fn flush(&self) {
+        let mut connection = self.connection.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(backend) = &mut connection.backend {
+            let _ = backend.flush();
+        }
+    }
+}
+
+/// Message of the record in the format of RFC 3164 or RFC 5424. With RFC 5424 the key-values of the record are its
+/// structured data, like `[cron-rs@32473 task="backup" run_id="..."]`
+#[cfg_attr(windows, allow(dead_code))]
+fn syslog_message(
+    record: &Record,
+    config: &SyslogConfig,
+    hostname: Option<&str>,
+    pid: u32,
+    time: DateTime<FixedOffset>,
+) -> String {
+    let severity = match record.level() {
+        Level::Error => 3,
+        Level::Warn => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    };
+    let priority = config.facility.code() * 8 + severity;
+
+    match config.format {
+        SyslogFormat::Rfc3164 => {
+            let hostname = hostname.map(|hostname| format!("{} ", hostname)).unwrap_or_default();
+            format!("<{}>{} {}cron-rs[{}]: {}", priority, time.format("%b %e %H:%M:%S"), hostname, pid, record.args())
+        }
+        SyslogFormat::Rfc5424 => {
+            struct Params(String);
+            impl<'kvs> VisitSource<'kvs> for Params {
+                fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+                    // Names are up to 32 printable characters, without spaces, `=`, `]` and `"`
+                    let name: String = key
+                        .as_str()
+                        .chars()
+                        .filter(|c| c.is_ascii_graphic() && !matches!(c, '=' | ']' | '"'))
+                        .take(32)
+                        .collect();
+                    let value = value.to_string().replace('\\', "\\\\").replace('"', "\\\"").replace(']', "\\]");
+                    self.0.push_str(&format!(" {}=\"{}\"", name, value));
+                    Ok(())
+                }
+            }
+            let mut params = Params(String::new());
+            let _ = record.key_values().visit(&mut params);
+            let data = if params.0.is_empty() { "-".to_string() } else { format!("[{}{}]", SYSLOG_SD_ID, params.0) };
+
+            format!(
+                "<{}>1 {} {} cron-rs {} - {} {}",
+                priority,
+                time.to_rfc3339_opts(SecondsFormat::Micros, false),
+                hostname.unwrap_or("-"),
+                pid,
+                data,
+                record.args()
+            )
+        }
+    }
+}
+
+/// Entry with the fields of the record in the native protocol of journald
+#[cfg_attr(windows, allow(dead_code))]
+fn journald_entry(record: &Record) -> Vec<u8> {
+    let priority = match record.level() {
+        Level::Error => "3",
+        Level::Warn => "4",
+        Level::Info => "6",
+        Level::Debug | Level::Trace => "7",
+    };
+
+    let mut entry = vec![];
+    add_journald_field(&mut entry, "MESSAGE", &record.args().to_string());
+    add_journald_field(&mut entry, "PRIORITY", priority);
+    add_journald_field(&mut entry, "SYSLOG_IDENTIFIER", "cron-rs");
+    add_journald_field(&mut entry, "TARGET", record.target());
+    if let Some(file) = record.file() {
+        add_journald_field(&mut entry, "CODE_FILE", file);
+    }
+    if let Some(line) = record.line() {
+        add_journald_field(&mut entry, "CODE_LINE", &line.to_string());
+    }
+
+    struct Fields<'a>(&'a mut Vec<u8>);
+    impl<'kvs> VisitSource<'kvs> for Fields<'_> {
+        fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+            // Field names are uppercase letters, digits and underscores
+            let name: String = key
+                .as_str()
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+                .collect();
+            add_journald_field(self.0, &name, &value.to_string());
+            Ok(())
+        }
+    }
+    let _ = record.key_values().visit(&mut Fields(&mut entry));
+
+    entry
+}
+
+/// `NAME=value\n`, or the name and the length of the value before it when the value has new lines
+#[cfg_attr(windows, allow(dead_code))]
+fn add_journald_field(entry: &mut Vec<u8>, name: &str, value: &str) {
+    entry.extend_from_slice(name.as_bytes());
+    if value.contains('\n') {
+        entry.push(b'\n');
+        entry.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        entry.push(b'=');
+    }
+    entry.extend_from_slice(value.as_bytes());
+    entry.push(b'\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotating_file() {
+        let dir = std::env::temp_dir().join(format!("cron-rs-test-rotation-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("cron-rs.log");
+
+        let config = LogRotationConfig { max_size: "10 B".to_string(), max_files: 2, compress: false };
+        let mut file = RotatingFile::open(path.clone(), &config).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap_or_default();
+        assert_eq!(read("cron-rs.log"), "fourth\n");
+        assert_eq!(read("cron-rs.log.1"), "third\n");
+        assert_eq!(read("cron-rs.log.2"), "second\n");
+        assert!(!dir.join("cron-rs.log.3").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_toggle_debug() {
+        use LevelFilter::*;
+        // Configured -> debug -> configured
+        assert_eq!(toggled_level(Warn, Warn), Debug);
+        assert_eq!(toggled_level(Debug, Warn), Warn);
+        // Changed with `cron-rs ctl log-level`
+        assert_eq!(toggled_level(Trace, Info), Info);
+        assert_eq!(toggled_level(Error, Info), Debug);
+        // Already debug or trace in the config file: configured -> info -> configured
+        assert_eq!(toggled_level(Debug, Debug), Info);
+        assert_eq!(toggled_level(Info, Debug), Debug);
+        assert_eq!(toggled_level(Trace, Trace), Info);
+        assert_eq!(toggled_level(Info, Trace), Trace);
+    }
+
+    #[test]
+    fn test_per_task_logger() {
+        struct Discard;
+        impl Log for Discard {
+            fn enabled(&self, _: &Metadata) -> bool {
+                true
+            }
+            fn log(&self, _: &Record) {}
+            fn flush(&self) {}
+        }
+
+        let dir = std::env::temp_dir().join(format!("cron-rs-test-per-task-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let logger =
+            PerTaskLogger { inner: Box::new(Discard), dir: dir.clone(), format: LogFormat::Text, files: Mutex::default() };
+        log::set_max_level(LevelFilter::Trace);
+
+        let log = |task: Option<&str>, message: &str| {
+            let kvs: Vec<(&str, &dyn log::kv::ToValue)> = task.iter().map(|task| ("task", task as _)).collect();
+            let kvs = kvs.as_slice();
+            logger.log(&Record::builder().args(format_args!("{}", message)).level(Level::Info).key_values(&kvs).build());
+        };
+        log(Some("backup"), "Task 'backup' started");
+        log(None, "Config reloaded");
+        log(Some("db/dump: daily"), "Task 'db/dump: daily' started");
+        log(Some("backup"), "Task 'backup' finished");
+
+        let backup = std::fs::read_to_string(dir.join("backup.log")).unwrap();
+        let lines: Vec<&str> = backup.lines().collect();
+        assert_eq!(lines.len(), 2, "{}", backup);
+        assert!(lines[0].ends_with("] Task 'backup' started") && lines[1].ends_with("] Task 'backup' finished"));
+        // The characters that can't be in a file name are replaced
+        let mut files: Vec<String> =
+            std::fs::read_dir(&dir).unwrap().map(|entry| entry.unwrap().file_name().to_string_lossy().into()).collect();
+        files.sort();
+        assert_eq!(files, ["backup.log", "db_dump_ daily.log"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_syslog_reconnect() {
+        use std::io::{BufRead, BufReader};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let server = listener.local_addr().unwrap().to_string();
+        let config = SyslogConfig { server: Some(server), protocol: SyslogProtocol::Tcp, ..Default::default() };
+        let logger = SyslogLogger::connect(&config).unwrap();
+        log::set_max_level(LevelFilter::Trace);
+        let log = |message: &str| {
+            logger.log(&Record::builder().args(format_args!("{}", message)).level(Level::Info).build());
+        };
+
+        let (stream, _) = listener.accept().unwrap();
+        log("first");
+        let mut line = String::new();
+        BufReader::new(&stream).read_line(&mut line).unwrap();
+        assert!(line.ends_with("]: first\n"), "{}", line);
+
+        // The server closes the connection, the next messages open a new one
+        drop(stream);
+        let (stream, _) = std::thread::scope(|scope| {
+            let accept = scope.spawn(|| listener.accept().unwrap());
+            while !accept.is_finished() {
+                log("lost or resent");
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            accept.join().unwrap()
+        });
+        log("second");
+        stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+        let mut reader = BufReader::new(&stream);
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            if line.ends_with("]: second\n") {
+                break;
+            }
+            assert!(line.ends_with("]: lost or resent\n"), "{}", line);
+        }
+    }
+
+    #[test]
+    fn test_json_line() {
+        let kvs: &[(&str, &dyn log::kv::ToValue)] = &[("task", &"backup"), ("exit_code", &2)];
+        let line = json_line(
+            &Record::builder()
+                .args(format_args!("Task 'backup' failed"))
+                .level(Level::Error)
+                .target("cron_rs_core::scheduler")
+                .key_values(&kvs)
+                .build(),
+        );
+
+        let line: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(line["level"], "ERROR");
+        assert_eq!(line["target"], "cron_rs_core::scheduler");
+        assert_eq!(line["message"], "Task 'backup' failed");
+        assert_eq!(line["task"], "backup");
+        assert_eq!(line["exit_code"], 2);
+        assert!(line["timestamp"].as_str().unwrap().ends_with('Z'));
+    }
+
+    #[test]
+    fn test_journald_entry() {
+        let run_id = "20240101-000000-abcd";
+        let kvs: &[(&str, &dyn log::kv::ToValue)] = &[("task", &"backup"), ("run_id", &run_id), ("exit_code", &2)];
+        let entry = journald_entry(
+            &Record::builder()
+                .args(format_args!("Task 'backup' failed\nexit code 2"))
+                .level(Level::Error)
+                .target("cron_rs_core::scheduler")
+                .key_values(&kvs)
+                .build(),
+        );
+
+        let mut expected = b"MESSAGE\n".to_vec();
+        expected.extend_from_slice(&32u64.to_le_bytes());
+        expected.extend_from_slice(b"Task 'backup' failed\nexit code 2\n");
+        expected.extend_from_slice(
+            b"PRIORITY=3\nSYSLOG_IDENTIFIER=cron-rs\nTARGET=cron_rs_core::scheduler\n\
+              TASK=backup\nRUN_ID=20240101-000000-abcd\nEXIT_CODE=2\n",
+        );
+        assert_eq!(String::from_utf8_lossy(&entry), String::from_utf8_lossy(&expected));
+    }
+
+    #[test]
+    fn test_syslog_message() {
+        use crate::config::logging::SyslogFacility;
+        let kvs: &[(&str, &dyn log::kv::ToValue)] = &[("task", &"backup"), ("error", &"no \"disk\" [full]")];
+        let record =
+            Record::builder().args(format_args!("Task 'backup' failed")).level(Level::Warn).key_values(&kvs).build();
+        let time = DateTime::parse_from_rfc3339("2025-01-02T03:04:05.123456789+01:00").unwrap();
+
+        let config = SyslogConfig::default();
+        let message = syslog_message(&record, &config, None, 42, time);
+        assert_eq!(message, "<12>Jan  2 03:04:05 cron-rs[42]: Task 'backup' failed");
+
+        let config =
+            SyslogConfig { facility: SyslogFacility::Local3, format: SyslogFormat::Rfc5424, ..Default::default() };
+        assert_eq!(
+            syslog_message(&record, &config, Some("db-1"), 42, time),
+            "<156>1 2025-01-02T03:04:05.123456+01:00 db-1 cron-rs 42 - \
+             [cron-rs@32473 task=\"backup\" error=\"no \\\"disk\\\" [full\\]\"] Task 'backup' failed"
+        );
+        let record = Record::builder().args(format_args!("Started")).level(Level::Info).build();
+        let message = syslog_message(&record, &config, None, 42, time);
+        assert_eq!(message, "<158>1 2025-01-02T03:04:05.123456+01:00 - cron-rs 42 - - Started");
+    }
+} 
\ No newline at end of file
@@ -0,0 +1,69 @@
+use crate::resource_usage::ResourceUsage;
+use std::path::Path;
+
+/// Gauges of the resources used by the last execution of each task, with their help text. Their values come from
+/// [gauge_values], in the same order
+const GAUGES: [(&str, &str); 5] = [
+    ("cron_rs_task_max_rss_bytes", "Max resident memory of the last execution of the task"),
+    ("cron_rs_task_user_cpu_seconds", "User CPU time of the last execution of the task"),
+    ("cron_rs_task_system_cpu_seconds", "System CPU time of the last execution of the task"),
+    ("cron_rs_task_read_bytes", "Bytes read from the storage by the last execution of the task"),
+    ("cron_rs_task_write_bytes", "Bytes written to the storage by the last execution of the task"),
+];
+
+fn gauge_values(usage: &ResourceUsage) -> [f64; 5] {
+    [
+        usage.max_rss as f64,
+        usage.user_cpu_seconds,
+        usage.system_cpu_seconds,
+        usage.read_bytes as f64,
+        usage.write_bytes as f64,
+    ]
+}
+
+/// The resource usage of the tasks in the text format of Prometheus, one series per task with the `task` label
+pub fn render_metrics(usage: &[(String, ResourceUsage)]) -> String {
+    let mut text = String::new();
+    for (index, (name, help)) in GAUGES.iter().enumerate() {
+        text.push_str(&format!("# HELP {} {}\n# TYPE {} gauge\n", name, help, name));
+        for (task_name, usage) in usage {
+            let value = gauge_values(usage)[index];
+            text.push_str(&format!("{}{{task=\"{}\"}} {}\n", name, escape_label(task_name), value));
+        }
+    }
+    text
+}
+
+/// Writes the metrics for the textfile collector of node_exporter. The file is replaced with a rename, so the collector
+/// never reads half of it
+pub async fn write_metrics_file(path: &Path, usage: &[(String, ResourceUsage)]) -> std::io::Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    tokio::fs::write(&temp_path, render_metrics(usage)).await?;
+    tokio::fs::rename(&temp_path, path).await
+}
+
+fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_metrics() {
+        let usage = ResourceUsage { max_rss: 4 << 20, user_cpu_seconds: 1.5, write_bytes: 4096, ..Default::default() };
+        let tasks = [("backup".to_string(), usage), ("say \"hi\"".to_string(), ResourceUsage::default())];
+        let text = render_metrics(&tasks);
+
+        assert!(text.starts_with(
+            "# HELP cron_rs_task_max_rss_bytes Max resident memory of the last execution of the task\n\
+             # TYPE cron_rs_task_max_rss_bytes gauge\n\
+             cron_rs_task_max_rss_bytes{task=\"backup\"} 4194304\n\
+             cron_rs_task_max_rss_bytes{task=\"say \\\"hi\\\"\"} 0\n"
+        ));
+        assert!(text.contains("\ncron_rs_task_user_cpu_seconds{task=\"backup\"} 1.5\n"));
+        assert!(text.contains("\ncron_rs_task_write_bytes{task=\"backup\"} 4096\n"));
+    }
+}
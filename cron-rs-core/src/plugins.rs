@@ -0,0 +1,264 @@
+use crate::config::secrets::resolve_secrets;
+use crate::utils::hostname;
+use anyhow::{anyhow, bail, Context, Result};
+use log::{error, info};
+use reqwest::blocking::Client;
+use reqwest::header::{HeaderName, HeaderValue};
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use wasmtime::{Caller, Engine, Extern, Linker, Module, Store};
+
+/// Export of the plugins that send alerts, see [send_plugin_alert]
+pub const ALERT_EXPORT: &str = "send_alert";
+
+/// Export of the plugins that check the conditions of the tasks, see [check_plugin_condition]
+pub const CONDITION_EXPORT: &str = "check_condition";
+
+/// Module of the functions the plugins can import, see [linker]
+const HOST_MODULE: &str = "cron_rs";
+
+/// Instructions a call of a plugin can run, so a plugin stuck in a loop fails instead of blocking the alerts
+const PLUGIN_FUEL: u64 = 1_000_000_000;
+
+/// The plugins of the config, see [configure_plugins]
+static PLUGINS: Mutex<Vec<WasmPlugin>> = Mutex::new(Vec::new());
+
+static ENGINE: OnceLock<Engine> = OnceLock::new();
+
+/// A WASM module of the `plugins` section of the config file
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluginConfig {
+    /// Path of the `.wasm` module, or of a `.wat` module in the text format
+    pub path: String,
+    /// Settings passed to the plugin on each call, like the token of an API. They can use `!secret`
+    pub options: Option<Value>,
+}
+
+/// A compiled plugin, each call runs in a new instance
+#[derive(Clone)]
+struct WasmPlugin {
+    name: String,
+    module: Module,
+    options: Value,
+}
+
+/// What a call can see of the host
+struct HostState {
+    plugin: String,
+    /// Set by `cron_rs.fail`, the reason of a failed alert or of an unmet condition
+    message: Option<String>,
+}
+
+fn engine() -> &'static Engine {
+    ENGINE.get_or_init(|| {
+        let mut config = wasmtime::Config::new();
+        config.consume_fuel(true);
+        Engine::new(&config).expect("Invalid config of the WASM engine")
+    })
+}
+
+/// Compiles the module of a plugin, `cron-rs validate` uses it to check the plugins
+pub fn load_plugin_module(path: &str) -> Result<Module> {
+    Module::from_file(engine(), path).with_context(|| format!("Failed to load the plugin {}", path))
+}
+
+/// Whether the module exports the function, [ALERT_EXPORT] or [CONDITION_EXPORT]
+pub fn module_exports(module: &Module, export: &str) -> bool {
+    module.get_export(export).is_some_and(|export| export.func().is_some())
+}
+
+/// Loads the `plugins` of the config, called when the config is read. The plugins that fail to load are left out, the
+/// alerts and conditions that use them fail
+pub fn configure_plugins(plugins: &HashMap<String, PluginConfig>) {
+    let mut loaded = vec![];
+    for (name, plugin) in plugins {
+        match load_plugin_module(&plugin.path) {
+            Ok(module) => {
+                let options = plugin.options.clone().unwrap_or(Value::Null);
+                loaded.push(WasmPlugin { name: name.clone(), module, options });
+            }
+            Err(e) => error!("Plugin '{}': {:#}", name, e),
+        }
+    }
+    *PLUGINS.lock().unwrap() = loaded;
+}
+
+fn plugin(name: &str) -> Result<WasmPlugin> {
+    let plugins = PLUGINS.lock().unwrap();
+    plugins.iter().find(|plugin| plugin.name == name).cloned().ok_or_else(|| anyhow!("Plugin '{}' is not loaded", name))
+}
+
+/// Sends an alert with the `send_alert` export of the plugin, which gets the payload of the alert in `alert`. A
+/// result other than 0 is a failure
+pub fn send_plugin_alert(name: &str, payload: &Value) -> Result<()> {
+    let plugin = plugin(name)?;
+    let (result, message) = plugin.call(ALERT_EXPORT, json!({ "alert": payload }))?;
+    if result != 0 {
+        bail!("Plugin '{}' failed with {}: {}", name, result, message.unwrap_or_default());
+    }
+    Ok(())
+}
+
+/// Checks a condition with the `check_condition` export of the plugin, returns why the task can't run. A result other
+/// than 0 is an unmet condition
+pub fn check_plugin_condition(name: &str, task_name: &str) -> Result<Option<String>> {
+    let plugin = plugin(name)?;
+    let input = json!({ "task_name": task_name, "hostname": hostname() });
+    let (result, message) = plugin.call(CONDITION_EXPORT, input)?;
+    Ok((result != 0).then(|| message.unwrap_or_else(|| format!("plugin '{}' condition is not met", name))))
+}
+
+impl WasmPlugin {
+    /// Calls the export with the input and the options of the plugin as JSON in its memory. The module must export its
+    /// `memory` and `alloc(len) -> ptr`, where the input is written
+    fn call(&self, export: &str, mut input: Value) -> Result<(i32, Option<String>)> {
+        // The secrets are read when the plugin is called, not when the config is loaded
+        input["options"] = resolve_secrets(&self.options)?;
+        let input = input.to_string();
+
+        let mut store = Store::new(engine(), HostState { plugin: self.name.clone(), message: None });
+        store.set_fuel(PLUGIN_FUEL)?;
+        let instance = linker()?.instantiate(&mut store, &self.module)?;
+        let memory = instance.get_memory(&mut store, "memory").context("The plugin doesn't export its memory")?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "alloc")?;
+        let func = instance.get_typed_func::<(i32, i32), i32>(&mut store, export)?;
+
+        let len = i32::try_from(input.len())?;
+        let ptr = alloc.call(&mut store, len)?;
+        memory.write(&mut store, ptr as usize, input.as_bytes())?;
+        let result = func.call(&mut store, (ptr, len)).with_context(|| format!("Plugin '{}' failed", self.name))?;
+        Ok((result, store.into_data().message))
+    }
+}
+
+/// The functions of the host the plugins can import from `cron_rs`, all of them get a UTF-8 string as a pointer and a
+/// length: `log` writes a line to the log, `fail` sets the reason of a failure and `http_request` sends a request
+/// like `{"method": "POST", "url": "...", "headers": {...}, "body": "..."}`, returning its status or -1
+fn linker() -> Result<Linker<HostState>> {
+    let mut linker = Linker::new(engine());
+    linker.func_wrap(HOST_MODULE, "log", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+        let text = read_string(&mut caller, ptr, len)?;
+        info!("Plugin '{}': {}", caller.data().plugin, text);
+        Ok(())
+    })?;
+    linker.func_wrap(HOST_MODULE, "fail", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+        let text = read_string(&mut caller, ptr, len)?;
+        caller.data_mut().message = Some(text);
+        Ok(())
+    })?;
+    linker.func_wrap(HOST_MODULE, "http_request", |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+        let request = read_string(&mut caller, ptr, len)?;
+        match http_request(&request) {
+            Ok(status) => Ok(i32::from(status)),
+            Err(e) => {
+                caller.data_mut().message = Some(format!("{:#}", e));
+                Ok(-1)
+            }
+        }
+    })?;
+    Ok(linker)
+}
+
+fn read_string(caller: &mut Caller<'_, HostState>, ptr: i32, len: i32) -> Result<String> {
+    let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+        bail!("The plugin doesn't export its memory");
+    };
+    let mut buffer = vec![0; usize::try_from(len)?];
+    memory.read(&caller, usize::try_from(ptr)?, &mut buffer)?;
+    Ok(String::from_utf8_lossy(&buffer).into_owned())
+}
+
+#[derive(Deserialize)]
+struct HttpRequest {
+    #[serde(default)]
+    method: Option<String>,
+    url: String,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    #[serde(default)]
+    body: String,
+}
+
+fn http_request(request: &str) -> Result<u16> {
+    let request: HttpRequest = serde_json::from_str(request).context("Malformed HTTP request")?;
+    let method = Method::from_bytes(request.method.as_deref().unwrap_or("POST").as_bytes())?;
+    let mut builder = Client::new().request(method, &request.url).body(request.body);
+    for (key, value) in &request.headers {
+        builder = builder.header(HeaderName::from_bytes(key.trim().as_bytes())?, HeaderValue::from_str(value.trim())?);
+    }
+    Ok(builder.send()?.status().as_u16())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fails the alerts of the tasks with an exit code of 2 and the conditions when the option `run` is not set
+    const PLUGIN: &str = r#"
+(module
+  (import "cron_rs" "fail" (func $fail (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "exit code 2")
+  (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+  ;; Whether the input contains the text at 0
+  (func $contains (param $ptr i32) (param $len i32) (param $text i32) (param $text_len i32) (result i32)
+    (local $i i32) (local $j i32)
+    (block $not_found
+      (loop $next
+        (br_if $not_found (i32.gt_u (i32.add (local.get $i) (local.get $text_len)) (local.get $len)))
+        (local.set $j (i32.const 0))
+        (block $mismatch
+          (loop $char
+            (if (i32.eq (local.get $j) (local.get $text_len)) (then (return (i32.const 1))))
+            (br_if $mismatch (i32.ne
+              (i32.load8_u (i32.add (i32.add (local.get $ptr) (local.get $i)) (local.get $j)))
+              (i32.load8_u (i32.add (local.get $text) (local.get $j)))))
+            (local.set $j (i32.add (local.get $j) (i32.const 1)))
+            (br $char)))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next)))
+    (i32.const 0))
+  (data (i32.const 16) "\"exit_code\":2")
+  (data (i32.const 32) "\"run\":true")
+  (func (export "send_alert") (param $ptr i32) (param $len i32) (result i32)
+    (if (call $contains (local.get $ptr) (local.get $len) (i32.const 16) (i32.const 13))
+      (then (call $fail (i32.const 0) (i32.const 11)) (return (i32.const 1))))
+    (i32.const 0))
+  (func (export "check_condition") (param $ptr i32) (param $len i32) (result i32)
+    (i32.eqz (call $contains (local.get $ptr) (local.get $len) (i32.const 32) (i32.const 10))))
+  (func (export "spin") (param i32 i32) (result i32)
+    (loop $forever (br $forever))
+    (i32.const 0)))
+"#;
+
+    #[test]
+    fn test_wasm_plugin() {
+        let path = std::env::temp_dir().join(format!("cron-rs-test-plugin-{}.wat", std::process::id()));
+        std::fs::write(&path, PLUGIN).unwrap();
+        let path = path.display().to_string();
+        let module = load_plugin_module(&path).unwrap();
+        assert!(module_exports(&module, ALERT_EXPORT) && module_exports(&module, CONDITION_EXPORT));
+
+        let plugins = HashMap::from([
+            ("notify".to_string(), PluginConfig { path: path.clone(), options: None }),
+            ("gate".to_string(), PluginConfig { path: path.clone(), options: Some(json!({ "run": true })) }),
+        ]);
+        configure_plugins(&plugins);
+
+        assert!(send_plugin_alert("notify", &json!({ "event": "task", "exit_code": 1 })).is_ok());
+        let error = send_plugin_alert("notify", &json!({ "event": "task", "exit_code": 2 })).unwrap_err();
+        assert_eq!(error.to_string(), "Plugin 'notify' failed with 1: exit code 2");
+        assert_eq!(check_plugin_condition("gate", "backup").unwrap(), None);
+        assert_eq!(check_plugin_condition("notify", "backup").unwrap().unwrap(), "plugin 'notify' condition is not met");
+        assert_eq!(send_plugin_alert("missing", &json!({})).unwrap_err().to_string(), "Plugin 'missing' is not loaded");
+
+        // A plugin that never returns runs out of fuel
+        assert!(plugin("notify").unwrap().call("spin", json!({})).is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
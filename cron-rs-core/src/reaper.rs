@@ -0,0 +1,104 @@
+use log::debug;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How often the scheduler looks for lost executions and zombie children, besides each SIGCHLD
+pub const REAP_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A zombie child is only reaped once it stays a zombie this long. The children of the scheduler, like the processes
+/// of the hooks and the alerts, are reaped by their owner right after they exit
+const ZOMBIE_GRACE: Duration = Duration::from_secs(5);
+
+/// Reaps the children that nobody waits for, like the orphans reparented to the scheduler when it's the init process
+/// of a container
+#[derive(Debug)]
+pub struct ZombieReaper {
+    grace: Duration,
+    /// Zombie children by pid, with the first time they were seen
+    zombies: HashMap<u32, Instant>,
+}
+
+impl Default for ZombieReaper {
+    fn default() -> Self {
+        Self::new(ZOMBIE_GRACE)
+    }
+}
+
+impl ZombieReaper {
+    pub fn new(grace: Duration) -> Self {
+        Self { grace, zombies: HashMap::new() }
+    }
+
+    /// Reaps the zombie children seen for longer than the grace time, except the `owned` ones, the executions of the
+    /// tasks. Returns the pids of the reaped children
+    pub fn reap(&mut self, owned: &[u32]) -> Vec<u32> {
+        let now = Instant::now();
+        let zombies: Vec<u32> = zombie_children().into_iter().filter(|pid| !owned.contains(pid)).collect();
+        self.zombies.retain(|pid, _| zombies.contains(pid));
+
+        let mut reaped = vec![];
+        for pid in zombies {
+            let first_seen = *self.zombies.entry(pid).or_insert(now);
+            if now.duration_since(first_seen) >= self.grace && reap_child(pid) {
+                debug!("Reaped the zombie child {}", pid);
+                self.zombies.remove(&pid);
+                reaped.push(pid);
+            }
+        }
+        reaped
+    }
+}
+
+#[cfg(unix)]
+fn zombie_children() -> Vec<u32> {
+    use sysinfo::{Pid, ProcessRefreshKind, ProcessStatus, ProcessesToUpdate, System};
+    let mut system = System::new();
+    system.refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::nothing());
+    let parent = Pid::from_u32(std::process::id());
+    system
+        .processes()
+        .values()
+        .filter(|process| process.parent() == Some(parent) && process.status() == ProcessStatus::Zombie)
+        .map(|process| process.pid().as_u32())
+        .collect()
+}
+
+/// Windows has no zombies, the processes are gone once they exit
+#[cfg(windows)]
+fn zombie_children() -> Vec<u32> {
+    vec![]
+}
+
+#[cfg(unix)]
+fn reap_child(pid: u32) -> bool {
+    let mut status = 0;
+    // SAFETY: waitpid only writes the status, WNOHANG returns right away if the child is not a zombie anymore
+    unsafe { libc::waitpid(pid as libc::pid_t, &mut status, libc::WNOHANG) == pid as libc::pid_t }
+}
+
+#[cfg(windows)]
+fn reap_child(_pid: u32) -> bool {
+    false
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reap_zombies() {
+        // Dropped without waiting for it, like an orphan reparented to the scheduler
+        let zombie = std::process::Command::new("true").spawn().unwrap().id();
+        let mut owned = std::process::Command::new("true").spawn().unwrap();
+        std::thread::sleep(Duration::from_millis(200));
+
+        let mut reaper = ZombieReaper::new(Duration::from_millis(100));
+        // Only reaped once they are zombies for the grace time
+        assert!(!reaper.reap(&[owned.id()]).contains(&zombie));
+        std::thread::sleep(Duration::from_millis(150));
+        assert!(reaper.reap(&[owned.id()]).contains(&zombie));
+        assert!(!zombie_children().contains(&zombie));
+        assert!(zombie_children().contains(&owned.id()));
+        owned.wait().unwrap();
+    }
+}
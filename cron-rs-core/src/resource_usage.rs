@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::process::ExitStatus;
+use tokio::process::Child;
+
+/// Resources used by an execution, counting the children it waited for, like the commands run by its shell
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    /// Max resident set size in bytes, of the process or of the biggest of its children
+    pub max_rss: u64,
+    pub user_cpu_seconds: f64,
+    pub system_cpu_seconds: f64,
+    /// Bytes read from the storage, the ones served from the page cache don't count
+    pub read_bytes: u64,
+    /// Bytes written to the storage
+    pub write_bytes: u64,
+}
+
+impl ResourceUsage {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "max_rss": self.max_rss,
+            "user_cpu_seconds": self.user_cpu_seconds,
+            "system_cpu_seconds": self.system_cpu_seconds,
+            "read_bytes": self.read_bytes,
+            "write_bytes": self.write_bytes,
+        })
+    }
+}
+
+/// Waits for the child to exit, with the resources it used. The usage is only known on Linux, where it's read once the
+/// child exits and before it's reaped
+pub async fn wait_with_usage(child: &mut Child) -> std::io::Result<(ExitStatus, Option<ResourceUsage>)> {
+    let usage = match child.id() {
+        Some(pid) => tokio::task::spawn_blocking(move || exited_usage(pid)).await.ok().flatten(),
+        None => None,
+    };
+    Ok((child.wait().await?, usage))
+}
+
+/// Blocks until the process exits, without reaping it so its owner still gets the exit status
+#[cfg(target_os = "linux")]
+fn exited_usage(pid: u32) -> Option<ResourceUsage> {
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        // The raw syscall takes the rusage of the process, the waitid of libc doesn't
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_waitid,
+                libc::P_PID,
+                pid as libc::pid_t,
+                &mut info as *mut libc::siginfo_t,
+                libc::WEXITED | libc::WNOWAIT,
+                &mut usage as *mut libc::rusage,
+            )
+        };
+        if result == 0 {
+            break;
+        }
+        if std::io::Error::last_os_error().kind() != std::io::ErrorKind::Interrupted {
+            return None;
+        }
+    }
+
+    let seconds = |time: libc::timeval| time.tv_sec as f64 + time.tv_usec as f64 / 1_000_000.0;
+    Some(ResourceUsage {
+        // In KiB
+        max_rss: usage.ru_maxrss as u64 * 1024,
+        user_cpu_seconds: seconds(usage.ru_utime),
+        system_cpu_seconds: seconds(usage.ru_stime),
+        // In blocks of 512 bytes
+        read_bytes: usage.ru_inblock as u64 * 512,
+        write_bytes: usage.ru_oublock as u64 * 512,
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn exited_usage(_pid: u32) -> Option<ResourceUsage> {
+    None
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_with_usage() {
+        let mut child = tokio::process::Command::new("sh")
+            .args(["-c", "i=0; while [ $i -lt 100000 ]; do i=$((i+1)); done; exit 3"])
+            .spawn()
+            .unwrap();
+        let (status, usage) = wait_with_usage(&mut child).await.unwrap();
+        assert_eq!(status.code(), Some(3));
+        let usage = usage.unwrap();
+        assert!(usage.max_rss > 0 && usage.user_cpu_seconds + usage.system_cpu_seconds > 0.0, "{:?}", usage);
+
+        // Already reaped
+        assert!(wait_with_usage(&mut child).await.unwrap().1.is_none());
+    }
+}
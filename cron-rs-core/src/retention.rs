@@ -0,0 +1,177 @@
+use crate::config::retention::OutputRetention;
+use crate::config::TaskConfig;
+use log::{debug, warn};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// How often the scheduler applies the retention limits
+pub const CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CleanupSummary {
+    pub deleted: usize,
+    pub compressed: usize,
+    pub freed_bytes: u64,
+}
+
+#[derive(Debug)]
+struct OutputFile {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+/// Deletes and compresses the output files of the tasks that exceed the retention limits.
+/// Files in `in_use` belong to running executions and are never modified
+pub fn cleanup_outputs(tasks: &[Arc<TaskConfig>], retention: &OutputRetention, in_use: &[PathBuf]) -> CleanupSummary {
+    let in_use: HashSet<PathBuf> = in_use.iter().filter_map(|path| fs::canonicalize(path).ok()).collect();
+    let (files, links) = find_output_files(tasks);
+    // Compressing the file of a `latest` link would break it
+    let linked: HashSet<PathBuf> = links.iter().filter_map(|link| fs::canonicalize(link).ok()).collect();
+
+    let now = SystemTime::now();
+    let mut summary = CleanupSummary::default();
+    let mut kept = vec![];
+
+    for mut file in files.into_iter().filter(|file| !in_use.contains(&file.path)) {
+        let age = now.duration_since(file.modified).unwrap_or_default();
+
+        if retention.max_age.is_some_and(|max_age| age > max_age) {
+            delete(&file, &mut summary);
+            continue;
+        }
+
+        let compressed = file.path.extension().is_some_and(|extension| extension == "gz");
+        if retention.compress_after.is_some_and(|after| age > after) && !compressed && !linked.contains(&file.path) {
+            match compress(&file.path) {
+                Ok(gz) => {
+                    summary.compressed += 1;
+                    file.size = fs::metadata(&gz).map(|m| m.len()).unwrap_or(file.size);
+                    file.path = gz;
+                }
+                Err(e) => warn!("Failed to compress {}: {}", file.path.display(), e),
+            }
+        }
+        kept.push(file);
+    }
+
+    if let Some(max_total_size) = retention.max_total_size {
+        // Keep the newest files
+        kept.sort_by_key(|file| std::cmp::Reverse(file.modified));
+        let mut total = 0;
+        for file in kept {
+            total += file.size;
+            if total > max_total_size {
+                delete(&file, &mut summary);
+            }
+        }
+    }
+
+    for link in links {
+        if !link.exists() {
+            let _ = fs::remove_file(&link);
+        }
+    }
+
+    summary
+}
+
+/// Output files of all the tasks, and the `latest` links of the ones in `per_run` mode
+fn find_output_files(tasks: &[Arc<TaskConfig>]) -> (Vec<OutputFile>, Vec<PathBuf>) {
+    let mut seen = HashSet::new();
+    let mut files = vec![];
+    let mut links = vec![];
+
+    let patterns = tasks.iter().flat_map(|task| task.output_patterns());
+    for pattern in patterns.flat_map(|pattern| [format!("{}.gz", pattern), pattern]) {
+        let paths = match glob::glob(&pattern) {
+            Ok(paths) => paths,
+            Err(e) => {
+                debug!("Invalid output pattern '{}': {}", pattern, e);
+                continue;
+            }
+        };
+
+        for path in paths.flatten() {
+            let Ok(metadata) = fs::symlink_metadata(&path) else { continue };
+            if metadata.file_type().is_symlink() {
+                links.push(path);
+                continue;
+            }
+            let Ok(path) = fs::canonicalize(&path) else { continue };
+            if metadata.is_file() && seen.insert(path.clone()) {
+                files.push(OutputFile { path, size: metadata.len(), modified: metadata.modified().unwrap_or(SystemTime::now()) });
+            }
+        }
+    }
+
+    (files, links)
+}
+
+fn delete(file: &OutputFile, summary: &mut CleanupSummary) {
+    match fs::remove_file(&file.path) {
+        Ok(_) => {
+            summary.deleted += 1;
+            summary.freed_bytes += file.size;
+        }
+        Err(e) => warn!("Failed to delete {}: {}", file.path.display(), e),
+    }
+}
+
+/// Replaces the file with `{file}.gz`, gzip keeps the modification time so the age of the file doesn't change
+pub fn compress(path: &Path) -> anyhow::Result<PathBuf> {
+    let status = Command::new("gzip")
+        .arg("-f")
+        .arg(path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    if !status.success() {
+        anyhow::bail!("gzip exited with {}", status);
+    }
+
+    let mut gz = path.as_os_str().to_owned();
+    gz.push(".gz");
+    Ok(PathBuf::from(gz))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::file::ConfigFile;
+
+    #[test]
+    fn test_cleanup_outputs() {
+        let dir = std::env::temp_dir().join(format!("cron-rs-test-retention-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config_file: ConfigFile = serde_yml::from_str(&format!(
+            "tasks:\n  - name: backup\n    cmd: echo done\n    every: 1 day\n    output_dir: {}\n",
+            dir.display()
+        ))
+        .unwrap();
+        let config = crate::config::parse_config_file(&config_file).unwrap();
+
+        let old = dir.join("backup_old_stdout.log");
+        let new = dir.join("backup_new_stdout.log");
+        let running = dir.join("backup_running_stdout.log");
+        let other = dir.join("unrelated.log");
+        for path in [&old, &new, &running, &other] {
+            fs::write(path, "output").unwrap();
+        }
+        let two_days_ago = SystemTime::now() - Duration::from_secs(2 * 24 * 60 * 60);
+        fs::File::options().write(true).open(&old).unwrap().set_modified(two_days_ago).unwrap();
+        fs::File::options().write(true).open(&running).unwrap().set_modified(two_days_ago).unwrap();
+
+        let retention = OutputRetention { max_age: Some(Duration::from_secs(24 * 60 * 60)), ..Default::default() };
+        let summary = cleanup_outputs(&config.tasks, &retention, std::slice::from_ref(&running));
+
+        assert_eq!(summary, CleanupSummary { deleted: 1, compressed: 0, freed_bytes: 6 });
+        assert!(!old.exists());
+        assert!(new.exists() && running.exists() && other.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
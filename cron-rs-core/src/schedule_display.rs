@@ -0,0 +1,341 @@
+use crate::config::{Config, Schedule, TaskConfig};
+use crate::scheduler::{PendingTask, Scheduler};
+use chrono::DateTime;
+use chrono_tz::Tz;
+use std::sync::Arc;
+use std::time::Instant;
+
+pub struct ScheduleDisplay;
+
+impl ScheduleDisplay {
+    /// Display all task schedules in a human-readable format
+    pub fn display_schedules(config: &Config) -> String {
+        let mut output = String::new();
+        output.push_str("Task Schedules:\n");
+        output.push_str("==============\n\n");
+
+        for task in &config.tasks {
+            output.push_str(&Self::display_task_schedule(task));
+            output.push('\n');
+        }
+
+        output
+    }
+
+    /// Display a single task's schedule
+    pub fn display_task_schedule(task: &TaskConfig) -> String {
+        let mut output = String::new();
+        output.push_str(&format!("Task: {}\n", task.name));
+        output.push_str(&format!("Command: {}\n", task.cmd));
+        output.push_str(&format!("Timezone: {}\n", task.timezone));
+        if !task.enabled {
+            output.push_str("Status: disabled\n");
+        }
+        if !task.tags.is_empty() {
+            output.push_str(&format!("Tags: {}\n", task.tags.join(", ")));
+        }
+        output.push_str(&format!("Schedule: {}\n", Self::format_schedule(&task.schedule)));
+
+        if let Some(max_runs) = task.max_runs {
+            let successes_str = if task.max_runs_only_successes { " (successful)" } else { "" };
+            output.push_str(&format!("Max runs: {}{}\n", max_runs, successes_str));
+        }
+
+        // Show next execution times
+        let now = Scheduler::get_current_datetime_at(task.timezone);
+
+        let next_runs = Self::get_next_execution_times(task, now, 5);
+
+        output.push_str(&format!("Now: {}\n", now.format("%Y-%m-%d %H:%M:%S %Z")));
+        if task.schedule.is_triggered() {
+            output.push_str("Next executions: when triggered\n");
+        } else {
+            output.push_str("Next 5 executions:\n");
+        }
+        for (i, next_time) in next_runs.iter().enumerate() {
+            output.push_str(&format!("  {}: {}\n", i + 1, next_time.format("%Y-%m-%d %H:%M:%S %Z")));
+        }
+
+        output
+    }
+
+    /// Display a schedule in a single line
+    pub fn format_schedule(schedule: &Schedule) -> String {
+        match schedule {
+            Schedule::Every { interval, aligned, window } => {
+                let aligned_str = if *aligned { " (aligned)" } else { "" };
+                let window_str = window.as_ref().map(|w| format!(" {}", w)).unwrap_or_default();
+                format!(
+                    "Every {}{}{}",
+                    crate::utils::format_duration(*interval),
+                    aligned_str,
+                    window_str
+                )
+            }
+            Schedule::When { time, min_interval } => {
+                let min_interval_str = min_interval
+                    .map(|d| format!(" (at least {} apart)", crate::utils::format_duration(d)))
+                    .unwrap_or_default();
+                format!("{}{}", time, min_interval_str)
+            }
+            Schedule::Period { period } => format!("Once per period ({})", period),
+            Schedule::OnChange { paths, debounce } => {
+                let paths: Vec<String> = paths.iter().map(|path| path.display().to_string()).collect();
+                format!("On change of {} (debounce {})", paths.join(", "), crate::utils::format_duration(*debounce))
+            }
+            Schedule::AfterTask { task, on } => format!("After task '{}' (on {})", task, on),
+        }
+    }
+
+    /// Display one line per task with its name, status and schedule
+    pub fn display_task_list(config: &Config) -> String {
+        let width = config.tasks.iter().map(|t| t.name.len()).max().unwrap_or(0);
+        let mut output = String::new();
+
+        for task in &config.tasks {
+            let status = if task.enabled { "enabled" } else { "disabled" };
+            let tags = if task.tags.is_empty() {
+                String::new()
+            } else {
+                format!("  [{}]", task.tags.join(", "))
+            };
+            output.push_str(&format!(
+                "{:<width$}  {:<8}  {}{}\n",
+                task.name,
+                status,
+                Self::format_schedule(&task.schedule),
+                tags,
+                width = width
+            ));
+        }
+
+        output
+    }
+
+    /// Get the next N execution times for a task, none for a task that runs when triggered
+    pub fn get_next_execution_times(task: &TaskConfig, from: DateTime<Tz>, count: usize) -> Vec<DateTime<Tz>> {
+        let mut times = Vec::new();
+        if task.schedule.is_triggered() {
+            return times;
+        }
+        let mut current = from;
+        let mut pending_task = PendingTask::new(Arc::new(task.clone()));
+        let mut current_instant = Instant::now();
+
+        for _ in 0..count {
+            let next = Scheduler::get_next_execution_time(&pending_task, current, false);
+            times.push(next);
+
+            let mut diff = next.timestamp() - current.timestamp();
+
+            if diff < 0 {
+                panic!(
+                    "Next execution time is before current time, next: {}, current: {}",
+                    next, current
+                );
+            }
+
+            // Avoid repeating the same date
+            if diff < 1 {
+                diff = 1i64;
+            }
+
+            let duration = std::time::Duration::from_secs(diff as u64);
+
+            pending_task.last_execution_time = Some((current + duration).to_utc());
+
+            current_instant += duration;
+            current += duration;
+        }
+
+        times
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::window::TimeWindow;
+    use crate::config::{Schedule, TimePattern, TimePatternField};
+    use chrono::{NaiveTime, TimeZone};
+    use chrono_tz::UTC;
+    use std::time::Duration;
+
+    fn create_test_task(name: &str, schedule: Schedule) -> TaskConfig {
+        TaskConfig {
+            name: name.to_string(),
+            cmd: "echo test".to_string(),
+            enabled: true,
+            tags: vec![],
+            schedule,
+            timezone: UTC,
+            avoid_overlapping: false,
+            lock_file: None,
+            cluster_lock: None,
+            conditions: Default::default(),
+            run_as: None,
+            login_env: false,
+            login_shell: false,
+            chroot: None,
+            network: Default::default(),
+            executor: Default::default(),
+            time_limit: None,
+            kill_grace: None,
+            time_limit_signal: Default::default(),
+            limits: Default::default(),
+            cgroup: None,
+            priority: Default::default(),
+            working_directory: None,
+            env: None,
+            env_file: None,
+            shell: None,
+            stdout: None,
+            stderr: None,
+            output_dir: None,
+            output_mode: None,
+            stdin: None,
+            log_output: false,
+            combine_output: false,
+            success: Default::default(),
+            before: vec![],
+            after: vec![],
+            on_failure_cmd: None,
+            max_runs: None,
+            max_runs_only_successes: false,
+            max_monthly_runtime: None,
+            misfire: Default::default(),
+            on_failure: vec![],
+            on_success: vec![],
+        }
+    }
+
+    #[test]
+    fn test_display_every_schedule() {
+        let schedule = Schedule::Every {
+            interval: Duration::from_secs(300),
+            aligned: true,
+            window: None,
+        }; // 5 minutes
+        let task = create_test_task("test_task", schedule);
+
+        let display = ScheduleDisplay::display_task_schedule(&task);
+        assert!(display.contains("Every 5 m (aligned)"));
+        assert!(display.contains("Task: test_task"));
+    }
+
+    #[test]
+    fn test_get_next_execution_times() {
+        let schedule = Schedule::Every {
+            interval: Duration::from_secs(60),
+            aligned: false,
+            window: None,
+        }; // 1 minute
+        let task = create_test_task("test_task", schedule);
+
+        let now = UTC.with_ymd_and_hms(2023, 1, 1, 12, 0, 0).unwrap();
+        let times = ScheduleDisplay::get_next_execution_times(&task, now, 3);
+
+        assert_eq!(times.len(), 3);
+        // Times should be approximately 1 minute apart
+        assert!(times[1] > times[0]);
+        assert!(times[2] > times[1]);
+    }
+
+    #[test]
+    fn test_every_with_active_window() {
+        let schedule = Schedule::Every {
+            interval: Duration::from_secs(600),
+            aligned: false,
+            window: Some(TimeWindow {
+                start: NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+                end: NaiveTime::from_hms_opt(20, 0, 0).unwrap(),
+                days: TimePatternField::List(vec![1, 2, 3, 4, 5]), // Mon-Fri
+            }),
+        }; // 10 minutes
+        let task = create_test_task("test_task", schedule);
+
+        // Friday evening, the window closes at 20:00 and opens again on Monday
+        let now = UTC.with_ymd_and_hms(2023, 1, 6, 19, 45, 0).unwrap();
+        let times = ScheduleDisplay::get_next_execution_times(&task, now, 2);
+
+        assert_eq!(times[0], UTC.with_ymd_and_hms(2023, 1, 6, 19, 55, 0).unwrap());
+        assert_eq!(times[1], UTC.with_ymd_and_hms(2023, 1, 9, 8, 0, 0).unwrap());
+
+        let display = ScheduleDisplay::display_task_schedule(&task);
+        assert!(display.contains("Every 10 m between 08:00:00..20:00:00 on [1,2,3,4,5]"));
+    }
+
+    #[test]
+    fn test_every_aligned_to_calendar() {
+        let mut task = create_test_task(
+            "test_task",
+            Schedule::Every {
+                interval: Duration::from_secs(3600),
+                aligned: true,
+                window: None,
+            },
+        );
+        task.timezone = chrono_tz::Asia::Kolkata; // UTC+5:30
+
+        // Hourly runs start at minute 0 of the task's timezone, not at the daemon start time
+        let now = task.timezone.with_ymd_and_hms(2023, 1, 1, 10, 17, 5).unwrap();
+        let times = ScheduleDisplay::get_next_execution_times(&task, now, 2);
+        assert_eq!(times[0], task.timezone.with_ymd_and_hms(2023, 1, 1, 11, 0, 0).unwrap());
+        assert_eq!(times[1], task.timezone.with_ymd_and_hms(2023, 1, 1, 12, 0, 0).unwrap());
+
+        // Daily runs start at midnight
+        task.schedule = Schedule::Every {
+            interval: Duration::from_secs(86400),
+            aligned: true,
+            window: None,
+        };
+        let times = ScheduleDisplay::get_next_execution_times(&task, now, 2);
+        assert_eq!(times[0], task.timezone.with_ymd_and_hms(2023, 1, 2, 0, 0, 0).unwrap());
+        assert_eq!(times[1], task.timezone.with_ymd_and_hms(2023, 1, 3, 0, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_when_with_min_interval() {
+        let time = TimePattern {
+            second: TimePatternField::Value(0),
+            minute: TimePatternField::Any,
+            hour: TimePatternField::Any,
+            day_of_week: TimePatternField::Any,
+            day: TimePatternField::Any,
+            month: TimePatternField::Any,
+            year: TimePatternField::Any,
+        }; // every minute
+        let schedule = Schedule::When {
+            time,
+            min_interval: Some(Duration::from_secs(300)),
+        };
+        let mut pending_task = PendingTask::new(Arc::new(create_test_task("test_task", schedule)));
+
+        // Without previous runs the pattern is used as is
+        let now = UTC.with_ymd_and_hms(2023, 1, 1, 10, 1, 0).unwrap();
+        assert_eq!(Scheduler::get_next_execution_time(&pending_task, now, true), now);
+
+        // The pattern matches every minute, but the task waits 5 minutes since the last run
+        pending_task.last_execution_time = Some(UTC.with_ymd_and_hms(2023, 1, 1, 10, 0, 0).unwrap().to_utc());
+        let next = Scheduler::get_next_execution_time(&pending_task, now, true);
+        assert_eq!(next, UTC.with_ymd_and_hms(2023, 1, 1, 10, 5, 0).unwrap());
+    }
+
+    #[test]
+    fn test_format_time_pattern() {
+        let pattern = TimePattern {
+            second: TimePatternField::Value(0),
+            minute: TimePatternField::Value(30),
+            hour: TimePatternField::Range(9, 17),
+            day_of_week: TimePatternField::List(vec![1, 2, 3, 4, 5]), // Mon-Fri
+            day: TimePatternField::Any,
+            month: TimePatternField::Any,
+            year: TimePatternField::Any,
+        };
+
+        let formatted = format!("{}", pattern);
+        assert!(formatted.contains("30"));
+        assert!(formatted.contains("9..17"));
+        assert!(formatted.contains("[1,2,3,4,5]"));
+    }
+}
@@ -0,0 +1,3916 @@
+use crate::alert_queue::{configure_alert_queue, queue_alert};
+use crate::alerts::{send_alert, send_budget_alert, send_clock_change_alert, send_internal_error_alert, Alert, AlertConfig, ExecutionContext, TaskExecutionDetails};
+use crate::cgroup::ExecutionCgroup;
+use crate::clock::{self, describe_jump, ClockWatch, CLOCK_CHECK_INTERVAL};
+use crate::cluster_lock::ClusterLock;
+use crate::config::file::{read_config_file, validate_config_path, ConfigFile, MisfirePolicy, StopSignal};
+use crate::conditions;
+use crate::control;
+use crate::control::{FinishedRunStatus, RunningTaskStatus, SchedulerStatus, TaskStatus};
+use crate::digest::spawn_digest;
+use crate::events::{spawn_event_log, SchedulerEvent, EVENT_CHANNEL_CAPACITY};
+use crate::executor;
+use crate::executor::{Execution, RunContext};
+use crate::hooks;
+use crate::logging;
+use crate::metrics::write_metrics_file;
+use crate::plugins::configure_plugins;
+use crate::reaper::{ZombieReaper, REAP_INTERVAL};
+use crate::resource_usage::ResourceUsage;
+use crate::retention;
+use crate::sentry::{report_internal_error, report_task_failure};
+use crate::config::parse_config_file;
+use crate::config::executor::{Executor, TaskFn, TaskFuture};
+use crate::config::pipeline::Pipeline;
+use crate::config::file::TaskDefinition;
+use crate::config::validation::{validate_added_tasks, ValidationResult};
+use crate::config::{Config, Schedule, TaskConfig, TaskFilter, TaskStdin, TimePattern, TimePatternField, MAX_YEAR};
+use crate::sqlite_logger::{AlertAttempt, ExecutionAttempt, ExecutionFailure, ExecutionSuccess, SqliteLogger};
+use crate::watch;
+use crate::utils::{current_username, format_duration, format_size, forward_output, join_output_forwarders, process_start_time, read_output_from, set_process_group, set_process_priority, set_resource_limits, signal_process_group, system_timezone, try_lock_file, wait_process_exit};
+#[cfg(unix)]
+use crate::utils::set_user;
+use anyhow::{anyhow, Context};
+use chrono::{DateTime, Datelike, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, SubsecRound, TimeDelta, Timelike};
+use chrono::{TimeZone, Utc};
+use chrono_tz::Tz;
+use log::{debug, error, info, warn};
+use serde_json::json;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::io;
+use std::ops::Add;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+#[cfg(unix)]
+use sysinfo::{Groups, Users};
+use tokio::signal;
+use tokio::sync::{broadcast, oneshot, Mutex, Notify};
+use tokio::task::{AbortHandle, JoinHandle};
+use tokio::time::sleep;
+
+#[derive(Debug, Clone)]
+pub struct PendingTask {
+    pub config: Arc<TaskConfig>,
+    pub last_execution_time: Option<DateTime<Utc>>,
+    pub last_pid: Option<u32>,
+    pub retries: u32,
+    /// Exit code of the last execution that finished, -1 if it was killed
+    pub last_exit_code: Option<i32>,
+    /// Failed executions in a row, for the escalation of the alerts
+    pub consecutive_failures: u32,
+    /// Number of executions that count towards max_runs
+    pub runs: u32,
+    /// Disabled tasks keep their schedule, but their executions are skipped
+    pub enabled: bool,
+    /// When the last due execution was skipped, the schedule moves on from it like from an execution
+    pub last_skipped_time: Option<DateTime<Utc>>,
+    /// Fired by the watcher of the tasks with `on_change`, or when the task of `after_task` finishes
+    pub trigger: Arc<TaskTrigger>,
+    /// Runtime of the executions in the current month, for `max_monthly_runtime`
+    pub monthly_runtime: MonthlyRuntime,
+    /// Resources used by the last execution, for the `metrics_file`
+    pub last_usage: Option<ResourceUsage>,
+}
+
+/// Runtime of the executions of a task in a calendar month of its timezone
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MonthlyRuntime {
+    /// The month, like `2025-01`
+    pub month: String,
+    pub used: Duration,
+}
+
+impl MonthlyRuntime {
+    /// The runtime used in the month of the time, none once a new month starts
+    pub fn used_in(&self, time: DateTime<Tz>) -> Duration {
+        if self.month == Self::month_of(time) { self.used } else { Duration::ZERO }
+    }
+
+    /// Adds the runtime of an execution to the month it started in
+    pub fn add(&mut self, start: DateTime<Tz>, duration: Duration) {
+        let month = Self::month_of(start);
+        if self.month != month {
+            self.month = month;
+            self.used = Duration::ZERO;
+        }
+        self.used += duration;
+    }
+
+    /// Midnight of the first day of the month of the time
+    fn month_start(time: DateTime<Tz>) -> DateTime<Tz> {
+        let first = time.date_naive().with_day(1).unwrap_or(time.date_naive());
+        time.timezone().from_local_datetime(&first.and_time(NaiveTime::MIN)).earliest().unwrap_or(time)
+    }
+
+    fn month_of(time: DateTime<Tz>) -> String {
+        time.format("%Y-%m").to_string()
+    }
+}
+
+/// Records when a task with a triggered schedule was triggered, and wakes up its scheduling loop
+#[derive(Debug, Default)]
+pub struct TaskTrigger {
+    time: std::sync::Mutex<Option<DateTime<Utc>>>,
+    notify: Notify,
+}
+
+impl TaskTrigger {
+    pub fn fire(&self) {
+        *self.time.lock().unwrap() = Some(Utc::now());
+        self.notify.notify_one();
+    }
+
+    /// When the task was triggered for the last time
+    pub fn time(&self) -> Option<DateTime<Utc>> {
+        *self.time.lock().unwrap()
+    }
+}
+
+static ACTIVE_TASK_ID_COUNTER: AtomicUsize = AtomicUsize::new(1);
+
+/// How late an execution can start before it counts as missed, and its `misfire` policy applies
+const MISFIRE_THRESHOLD: Duration = Duration::from_secs(5);
+
+/// Time between checks of the conditions of a task with a `period` that is due but can't run
+const CONDITION_RECHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone)]
+struct ActiveTask {
+    id: u32,
+    config: Arc<TaskConfig>,
+    pid: u32,
+    start_instant: Instant,
+    start_time: DateTime<Utc>,
+    child: Arc<Mutex<Execution>>,
+    /// Cancels the closure of the tasks added with [SchedulerHandle::add_closure_task], they have no pid
+    closure: Option<AbortHandle>,
+    run_id: String,
+    context: RunContext,
+    debug_info: String,
+    time_limit: Option<u64>,
+    stdout_path: PathBuf,
+    stderr_path: PathBuf,
+    stdout_offset: u64,
+    stderr_offset: u64,
+    /// Copy the output into the files when `log_output` is set
+    output_forwarders: Arc<Mutex<Vec<JoinHandle<()>>>>,
+    /// Uid and gid of `run_as`, also used by the `after` commands
+    user: Option<(u32, u32)>,
+    /// Transient cgroup of the execution when the task has `cgroup`
+    cgroup: Option<Arc<ExecutionCgroup>>,
+    /// Holds the flock on the `lock_file` of the task, unlocked when the execution finishes
+    lock_file: Option<Arc<std::fs::File>>,
+    /// Holds the `cluster_lock` of the task in redis, released when the execution finishes
+    cluster_lock: Option<Arc<ClusterLock>>,
+    /// The coroutine of [Scheduler::wait_for_task], if it stops with the execution still here it died, see
+    /// [Scheduler::reap_children]
+    monitor: Option<AbortHandle>,
+    /// Started by the previous run of the scheduler, see [Scheduler::adopt_previous_runs]. Its exit code is unknown, it
+    /// finishes without alerts
+    adopted: bool,
+}
+
+/// Error of [Scheduler::execute_task] when the execution failed before its command ran, like in a `before` command. It
+/// was already reported like a failed execution, unlike the other errors it's not an internal error
+#[derive(Debug)]
+struct ExecutionFailed(String);
+
+impl std::fmt::Display for ExecutionFailed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ExecutionFailed {}
+
+pub struct Scheduler {
+    tasks: Vec<Arc<TaskConfig>>,
+    active_tasks: Vec<ActiveTask>,
+    /// Executions that passed the overlap check and are being started, by task name. They count as running until they
+    /// are in `active_tasks`, so two starts can't both pass the check
+    starting_tasks: Arc<std::sync::Mutex<HashMap<String, u32>>>,
+    pending_tasks: Vec<Arc<Mutex<PendingTask>>>,
+    /// Scheduling loop of each task, by task name
+    task_loop_handles: Vec<(String, JoinHandle<()>)>,
+    wait_handles: Vec<JoinHandle<()>>,
+    config: Config,
+    /// File the config is reloaded from, None when the config was given by an embedding application
+    config_path: Option<PathBuf>,
+    task_filter: TaskFilter,
+    /// File where the state of the tasks is saved and restored from, nothing is saved without it
+    state_path: Option<PathBuf>,
+    sqlite_logger: Option<SqliteLogger>,
+    events: broadcast::Sender<SchedulerEvent>,
+    /// The last executions that finished, the newest at the end
+    recent_runs: VecDeque<FinishedRunStatus>,
+}
+
+/// Local time of [Scheduler::never], the end of the last year of the schedules. It's checked when it's compiled
+const NEVER: NaiveDateTime = NaiveDate::from_ymd_opt(MAX_YEAR as i32, 12, 31).expect("valid date").and_hms_opt(23, 59, 59).expect("valid time");
+
+/// Finished executions kept for the status
+const RECENT_RUNS: usize = 50;
+
+/// Start of an execution that passed the overlap check, the task counts as running until it's given to
+/// [Scheduler::add_active_task]. If it's dropped before, like when a reload aborts the loop that started it, the start
+/// is given back
+struct StartReservation {
+    task_name: String,
+    starting_tasks: Arc<std::sync::Mutex<HashMap<String, u32>>>,
+}
+
+impl Drop for StartReservation {
+    fn drop(&mut self) {
+        let mut starting_tasks = self.starting_tasks.lock().unwrap();
+        if let Some(starting) = starting_tasks.get_mut(&self.task_name) {
+            *starting -= 1;
+            if *starting == 0 {
+                starting_tasks.remove(&self.task_name);
+            }
+        }
+    }
+}
+
+/// Options of a [Scheduler], created with [Scheduler::builder]
+pub struct SchedulerBuilder {
+    config: Config,
+    config_path: Option<PathBuf>,
+    task_filter: TaskFilter,
+    state_path: Option<PathBuf>,
+}
+
+impl SchedulerBuilder {
+    /// Config file of the tasks, it is read again when the scheduler reloads its config
+    pub fn config_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config_path = Some(path.into());
+        self
+    }
+
+    /// Selects the tasks that run, also applied to the reloaded configs
+    pub fn task_filter(mut self, task_filter: TaskFilter) -> Self {
+        self.task_filter = task_filter;
+        self
+    }
+
+    /// Saves the state of the tasks in this file, and restores it when the scheduler starts, so the counters of
+    /// `max_runs` are kept between restarts
+    pub fn state_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.state_path = Some(path.into());
+        self
+    }
+
+    pub fn build(self) -> Scheduler {
+        Scheduler {
+            tasks: self.config.tasks.clone(),
+            active_tasks: Vec::new(),
+            starting_tasks: Arc::default(),
+            pending_tasks: Vec::new(),
+            task_loop_handles: Vec::new(),
+            wait_handles: Vec::new(),
+            config: self.config,
+            config_path: self.config_path,
+            task_filter: self.task_filter,
+            state_path: self.state_path,
+            sqlite_logger: None,
+            events: broadcast::channel(EVENT_CHANNEL_CAPACITY).0,
+            recent_runs: VecDeque::new(),
+        }
+    }
+
+    /// Starts the scheduler in the current tokio runtime, see [Scheduler::spawn]
+    pub async fn spawn(self) -> SchedulerHandle {
+        Scheduler::spawn_scheduler(self.build()).await
+    }
+}
+
+/// Scheduler running in the tokio runtime of the application that embeds it, created with [Scheduler::spawn]. The
+/// scheduler stops when the handle is dropped
+pub struct SchedulerHandle {
+    scheduler: Arc<Mutex<Scheduler>>,
+    events: broadcast::Sender<SchedulerEvent>,
+    shutdown: Option<oneshot::Sender<()>>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl SchedulerHandle {
+    /// Schedules a new task, the name must be unique
+    pub async fn add_task(&self, task: TaskConfig) -> anyhow::Result<()> {
+        Scheduler::add_task(&self.scheduler, task).await
+    }
+
+    /// Schedules a task like the ones of the config file, like `cron-rs ctl add-task`. See [Scheduler::add_task_definition]
+    pub async fn add_task_definition(&self, definition: TaskDefinition) -> anyhow::Result<Vec<ValidationResult>> {
+        Scheduler::add_task_definition(&self.scheduler, definition).await
+    }
+
+    /// Schedules an async closure as a task. `definition` has the schedule and the options of the task like in the config
+    /// file, the `cmd` is only shown in the alerts. Options for processes like `run_as` or `limits` don't apply
+    pub async fn add_closure_task<F, Fut>(&self, definition: TaskDefinition, f: F) -> anyhow::Result<()>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        let mut task = TaskConfig::parse(&definition)?;
+        task.executor = Executor::Closure(TaskFn::new(f));
+        self.add_task(task).await
+    }
+
+    /// Stops scheduling a task, a running execution is left to finish
+    pub async fn remove_task(&self, task_name: &str) -> anyhow::Result<()> {
+        Scheduler::remove_task(&self.scheduler, task_name).await
+    }
+
+    /// Enable or disable a task, like `cron-rs ctl enable/disable`
+    pub async fn set_task_enabled(&self, task_name: &str, enabled: bool) -> anyhow::Result<()> {
+        self.scheduler.lock().await.set_task_enabled(task_name, enabled).await
+    }
+
+    /// Runs a task right away, like `cron-rs ctl trigger`, and waits for the execution to finish. See
+    /// [Scheduler::trigger_task]
+    pub async fn trigger_task(&self, task_name: &str, force: bool) -> anyhow::Result<FinishedRunStatus> {
+        let (run_id, finished) = Scheduler::trigger_task(&self.scheduler, task_name, force).await?;
+        finished.await.map_err(|_| anyhow!("Execution '{}' of task '{}' was lost", run_id, task_name))
+    }
+
+    /// The running executions and the next run of each task
+    pub async fn status(&self) -> SchedulerStatus {
+        self.scheduler.lock().await.status().await
+    }
+
+    /// Receives the events of the executions from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<SchedulerEvent> {
+        self.events.subscribe()
+    }
+
+    /// Stops the running tasks like the Ctrl+C of `cron-rs run`, and waits for them
+    pub async fn shutdown(mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.await;
+        }
+    }
+}
+
+impl Drop for SchedulerHandle {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+/// Set by [request_shutdown], a permit is stored if the scheduler is not waiting for it yet
+static SHUTDOWN_REQUEST: Notify = Notify::const_new();
+
+/// Stops the scheduler started with [Scheduler::run] like a Ctrl+C, e.g. from the control handler of the Windows service
+pub fn request_shutdown() {
+    SHUTDOWN_REQUEST.notify_one();
+}
+
+/// What a signal received by the scheduler asks for, Windows only has the shutdown
+#[cfg_attr(windows, allow(dead_code))]
+enum SignalRequest {
+    Shutdown,
+    /// SIGUSR1, toggles the debug log and saves the state
+    ToggleDebug,
+    /// SIGHUP, reloads the config file
+    Reload,
+    /// SIGCHLD, a child exited
+    Reap,
+}
+
+/// Signals of the scheduler process, registered once so none is lost between two calls to `recv`
+#[cfg(unix)]
+struct SchedulerSignals {
+    interrupt: signal::unix::Signal,
+    user_defined1: signal::unix::Signal,
+    hangup: signal::unix::Signal,
+    child: signal::unix::Signal,
+}
+
+#[cfg(unix)]
+impl SchedulerSignals {
+    fn register() -> Self {
+        use signal::unix::{signal, SignalKind};
+        SchedulerSignals {
+            interrupt: signal(SignalKind::interrupt()).expect("Failed to register SIGINT"),
+            user_defined1: signal(SignalKind::user_defined1()).expect("Failed to register SIGUSR1"),
+            hangup: signal(SignalKind::hangup()).expect("Failed to register SIGHUP"),
+            child: signal(SignalKind::child()).expect("Failed to register SIGCHLD"),
+        }
+    }
+
+    async fn recv(&mut self) -> SignalRequest {
+        tokio::select! {
+            _ = self.interrupt.recv() => SignalRequest::Shutdown,
+            _ = SHUTDOWN_REQUEST.notified() => SignalRequest::Shutdown,
+            _ = self.user_defined1.recv() => SignalRequest::ToggleDebug,
+            _ = self.hangup.recv() => SignalRequest::Reload,
+            _ = self.child.recv() => SignalRequest::Reap,
+        }
+    }
+}
+
+/// Windows has no signals, the console ctrl events stop the scheduler. Closing the console or shutting down only gives
+/// the process a few seconds before it is killed. Not the logoff event, services get it when any user logs off
+#[cfg(windows)]
+struct SchedulerSignals {
+    ctrl_c: signal::windows::CtrlC,
+    ctrl_break: signal::windows::CtrlBreak,
+    ctrl_close: signal::windows::CtrlClose,
+    ctrl_shutdown: signal::windows::CtrlShutdown,
+}
+
+#[cfg(windows)]
+impl SchedulerSignals {
+    fn register() -> Self {
+        use signal::windows::{ctrl_break, ctrl_c, ctrl_close, ctrl_shutdown};
+        SchedulerSignals {
+            ctrl_c: ctrl_c().expect("Failed to register Ctrl+C"),
+            ctrl_break: ctrl_break().expect("Failed to register Ctrl+Break"),
+            ctrl_close: ctrl_close().expect("Failed to register the console close event"),
+            ctrl_shutdown: ctrl_shutdown().expect("Failed to register the shutdown event"),
+        }
+    }
+
+    async fn recv(&mut self) -> SignalRequest {
+        tokio::select! {
+            _ = self.ctrl_c.recv() => {}
+            _ = self.ctrl_break.recv() => {}
+            _ = self.ctrl_close.recv() => {}
+            _ = self.ctrl_shutdown.recv() => {}
+            _ = SHUTDOWN_REQUEST.notified() => {}
+        }
+        SignalRequest::Shutdown
+    }
+}
+
+impl Scheduler {
+    pub fn builder(config: Config) -> SchedulerBuilder {
+        SchedulerBuilder { config, config_path: None, task_filter: TaskFilter::default(), state_path: None }
+    }
+
+    /// Starts the scheduler in the current tokio runtime. Unlike [Scheduler::run] it doesn't handle the signals of the
+    /// process nor listen on the control socket, the application uses the returned handle instead. The state is not
+    /// saved, use [Scheduler::builder] with a `state_file` to keep it between restarts
+    pub async fn spawn(config: Config) -> SchedulerHandle {
+        Self::builder(config).spawn().await
+    }
+
+    async fn spawn_scheduler(scheduler: Scheduler) -> SchedulerHandle {
+        let events = scheduler.events.clone();
+        let mutex = Arc::new(Mutex::new(scheduler));
+        Self::start(&mutex).await;
+
+        let (shutdown_tx, mut shutdown_rx) = oneshot::channel();
+        let scheduler_mutex = mutex.clone();
+        let handle = tokio::spawn(async move {
+            let mut cleanup_interval = tokio::time::interval(retention::CLEANUP_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = &mut shutdown_rx => break,
+                    _ = cleanup_interval.tick() => Self::cleanup_outputs(&scheduler_mutex).await,
+                }
+            }
+            info!("Scheduler shutdown initiated");
+            Self::shutdown(&scheduler_mutex).await;
+        });
+
+        SchedulerHandle {
+            scheduler: mutex,
+            events,
+            shutdown: Some(shutdown_tx),
+            handle: Some(handle),
+        }
+    }
+
+    pub fn run(self) -> anyhow::Result<()> {
+        let runtime = tokio::runtime::Runtime::new()?;
+        let mutex: Arc<Mutex<Scheduler>> = Arc::new(Mutex::new(self));
+
+        runtime.block_on(Self::run_async(mutex))?;
+        Ok(())
+    }
+
+    pub async fn save_state(&self) {
+        let Some(state_path) = &self.state_path else { return };
+        let mut pending_tasks = vec![];
+
+        for t in &self.pending_tasks {
+            let pt = t.lock().await;
+            let now: DateTime<Tz> = Self::get_current_datetime_at(pt.config.timezone);
+
+            // Use the current datetime plus 1 second to avoid returning the exact same value
+            let next_run = Self::get_next_execution_time(&pt, now, false);
+
+            pending_tasks.push(json!({
+                "config_name": pt.config.name,
+                "last_execution_time": pt.last_execution_time.map(|dt| dt.to_rfc3339()),
+                "last_pid": pt.last_pid,
+                "retries": pt.retries,
+                "last_exit_code": pt.last_exit_code,
+                "consecutive_failures": pt.consecutive_failures,
+                "runs": pt.runs,
+                "monthly_runtime": { "month": pt.monthly_runtime.month, "seconds": pt.monthly_runtime.used.as_secs_f64() },
+                "enabled": pt.enabled,
+                "config_enabled": pt.config.enabled,
+                "next_run": next_run.to_rfc3339(),
+            }));
+        }
+
+        let active_tasks = self
+            .active_tasks
+            .iter()
+            .map(|t| {
+                json!({
+                    "id": t.id,
+                    "config_name": t.config.name,
+                    "run_id": t.run_id,
+                    "pid": t.pid,
+                    "start_time": t.start_time.to_rfc3339(),
+                    "time_limit": t.time_limit,
+                    "stdout_path": t.stdout_path.to_string_lossy(),
+                    "stderr_path": t.stderr_path.to_string_lossy(),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut state = serde_json::to_string(&json!({
+            "now": Utc::now().to_rfc3339(),
+            "pending_tasks": pending_tasks,
+            "active_tasks": active_tasks,
+        }))
+        .unwrap();
+
+        state.push('\n');
+
+        let res = tokio::fs::write(state_path, state.as_bytes()).await;
+
+        if let Err(e) = res {
+            error!("Failed to save scheduler state: {}", e);
+        }
+    }
+
+    /// Schedules a new task, the name must be unique
+    pub async fn add_task(mutex: &Arc<Mutex<Scheduler>>, task: TaskConfig) -> anyhow::Result<()> {
+        let pending_task = {
+            let mut scheduler = mutex.lock().await;
+            if scheduler.tasks.iter().any(|t| t.name == task.name) {
+                return Err(anyhow!("Task '{}' already exists", task.name));
+            }
+            let task = Arc::new(task);
+            scheduler.tasks.push(task.clone());
+            scheduler.config.tasks.push(task.clone());
+
+            let pending_task = Arc::new(Mutex::new(scheduler.new_pending_task(task)));
+            scheduler.pending_tasks.push(pending_task.clone());
+            pending_task
+        };
+        Self::spawn_tasks(mutex.clone(), vec![pending_task]).await;
+        Ok(())
+    }
+
+    /// Schedules a task written like the ones of the config file, with its templates, defaults and alert channels. It's
+    /// checked like `cron-rs validate` does, the errors are returned and the warnings are returned once it's added.
+    /// Added tasks are lost when the config is reloaded
+    pub async fn add_task_definition(mutex: &Arc<Mutex<Scheduler>>, definition: TaskDefinition) -> anyhow::Result<Vec<ValidationResult>> {
+        let (config_path, task_names) = {
+            let scheduler = mutex.lock().await;
+            (scheduler.config_path.clone(), scheduler.tasks.iter().map(|task| task.name.clone()).collect())
+        };
+        let file = match config_path {
+            Some(config_path) => read_config_file(&config_path)?,
+            None => ConfigFile::default(),
+        };
+
+        let (errors, warnings): (Vec<_>, Vec<_>) =
+            validate_added_tasks(&file, std::slice::from_ref(&definition), task_names).into_iter().partition(|d| d.is_error());
+        if !errors.is_empty() {
+            let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+            return Err(anyhow!("{}", errors.join("\n")));
+        }
+
+        let task = TaskConfig::parse(&file.resolve_task(&definition)?)
+            .and_then(|task| task.with_day_of_week_numbering(file.day_of_week_numbering.unwrap_or_default()))
+            .with_context(|| format!("Malformed task '{}'", definition.name))?;
+        Self::add_task(mutex, task).await?;
+        Ok(warnings)
+    }
+
+    /// Stops scheduling a task, a running execution is left to finish
+    pub async fn remove_task(mutex: &Arc<Mutex<Scheduler>>, task_name: &str) -> anyhow::Result<()> {
+        let mut scheduler = mutex.lock().await;
+        if !scheduler.tasks.iter().any(|t| t.name == task_name) {
+            return Err(anyhow!("Task '{}' not found", task_name));
+        }
+        scheduler.tasks.retain(|t| t.name != task_name);
+        scheduler.config.tasks.retain(|t| t.name != task_name);
+
+        let mut pending_tasks = vec![];
+        for pending_task in scheduler.pending_tasks.drain(..).collect::<Vec<_>>() {
+            if pending_task.lock().await.config.name != task_name {
+                pending_tasks.push(pending_task);
+            }
+        }
+        scheduler.pending_tasks = pending_tasks;
+
+        for (_, handle) in scheduler.task_loop_handles.iter().filter(|(name, _)| name == task_name) {
+            handle.abort();
+        }
+        scheduler.task_loop_handles.retain(|(name, _)| name != task_name);
+        scheduler.save_state().await;
+        Ok(())
+    }
+
+    /// Enable or disable a task at runtime, the change is lost when the config is reloaded. It's kept by a restart with
+    /// a state file, unless `enabled` changes in the config
+    pub async fn set_task_enabled(&self, task_name: &str, enabled: bool) -> anyhow::Result<()> {
+        let mut found = false;
+        for pending_task in &self.pending_tasks {
+            let mut pending_task = pending_task.lock().await;
+            if pending_task.config.name == task_name {
+                pending_task.enabled = enabled;
+                found = true;
+            }
+        }
+
+        if !found {
+            return Err(anyhow!("Task '{}' not found", task_name));
+        }
+
+        self.save_state().await;
+        Ok(())
+    }
+
+    /// The running executions and the next run of each task, for the `status` request of the control socket
+    pub async fn status(&self) -> SchedulerStatus {
+        let running = self
+            .active_tasks
+            .iter()
+            .map(|task| {
+                let elapsed = task.start_instant.elapsed().as_secs_f64();
+                RunningTaskStatus {
+                    task_name: task.config.name.clone(),
+                    run_id: task.run_id.clone(),
+                    pid: task.pid,
+                    start_time: task.start_time,
+                    elapsed_seconds: elapsed,
+                    time_left_seconds: task.time_limit.map(|limit| (limit as f64 - elapsed).max(0.0)),
+                }
+            })
+            .collect();
+
+        let mut tasks = vec![];
+        for pending_task in &self.pending_tasks {
+            let pending_task = pending_task.lock().await;
+            let timezone = pending_task.config.timezone;
+            let now = Self::get_current_datetime_at(timezone);
+            let next_run = Self::get_next_run(&pending_task, now);
+            tasks.push(TaskStatus {
+                name: pending_task.config.name.clone(),
+                enabled: pending_task.enabled,
+                next_run: next_run.map(|next_run| next_run.fixed_offset()),
+                last_exit_code: pending_task.last_exit_code,
+                consecutive_failures: pending_task.consecutive_failures,
+                monthly_runtime_seconds: pending_task.monthly_runtime.used_in(now).as_secs_f64(),
+            });
+        }
+        SchedulerStatus { running, tasks, recent: self.recent_runs.iter().rev().cloned().collect() }
+    }
+
+    /// Pending task of the config, tasks with a `period` get the time of their last run from their stamp
+    fn new_pending_task(&self, config: Arc<TaskConfig>) -> PendingTask {
+        let mut task = PendingTask::new(config);
+        if matches!(task.config.schedule, Schedule::Period { .. }) {
+            task.last_execution_time = read_period_stamp(&self.config.period_stamp_dir_path(), &task.config.name);
+        }
+        task
+    }
+
+    /// Read the pending tasks saved by a previous run, indexed by task name, and the executions that were running when
+    /// it stopped
+    async fn load_state(&self) -> (HashMap<String, serde_json::Value>, Vec<serde_json::Value>) {
+        let mut pending_tasks = HashMap::new();
+        let Some(state_path) = &self.state_path else { return (pending_tasks, vec![]) };
+
+        let content = match tokio::fs::read_to_string(state_path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return (pending_tasks, vec![]),
+            Err(e) => {
+                warn!("Failed to read previous scheduler state: {}", e);
+                return (pending_tasks, vec![]);
+            }
+        };
+
+        let state: serde_json::Value = match serde_json::from_str(&content) {
+            Ok(state) => state,
+            Err(e) => {
+                warn!("Failed to parse previous scheduler state: {}", e);
+                return (pending_tasks, vec![]);
+            }
+        };
+
+        if let Some(list) = state["pending_tasks"].as_array() {
+            for pt in list {
+                if let Some(name) = pt["config_name"].as_str() {
+                    pending_tasks.insert(name.to_string(), pt.clone());
+                }
+            }
+        }
+        let active_tasks = state["active_tasks"].as_array().cloned().unwrap_or_default();
+
+        (pending_tasks, active_tasks)
+    }
+
+    /// Looks for the executions that were running when the previous run of the scheduler stopped without stopping them,
+    /// like after a crash. The processes still running are adopted, they count as running for `avoid_overlapping` and
+    /// are stopped by the `time_limit` and the shutdown. The rest are reported
+    async fn adopt_previous_runs(mutex: &Arc<Mutex<Scheduler>>, previous_runs: Vec<serde_json::Value>) {
+        for previous in previous_runs {
+            let (Some(task_name), Some(run_id), Some(pid), Some(start_time)) = (
+                previous["config_name"].as_str(),
+                previous["run_id"].as_str(),
+                previous["pid"].as_u64().map(|pid| pid as u32),
+                previous["start_time"].as_str().and_then(|time| DateTime::parse_from_rfc3339(time).ok()),
+            ) else {
+                continue;
+            };
+            let start_time = start_time.with_timezone(&Utc);
+            // Closures and pipelines don't outlive the scheduler
+            if pid == 0 {
+                continue;
+            }
+            // A new process with the same pid started after the execution
+            let same_process = process_start_time(pid).is_some_and(|started| (started - start_time).num_seconds().abs() <= 2);
+            if !same_process {
+                warn!(task = task_name, run_id; "Task '{}' (run {}) was running when the scheduler stopped, its end is unknown", task_name, run_id);
+                continue;
+            }
+
+            let scheduler = mutex.lock().await;
+            let Some(config) = scheduler.tasks.iter().find(|task| task.name == task_name).cloned() else {
+                warn!(
+                    task = task_name, run_id;
+                    "Task '{}' (run {}) from the previous run of the scheduler is still running with pid {}, but the task is not in the config",
+                    task_name, run_id, pid
+                );
+                continue;
+            };
+            info!(task = task_name, run_id; "Adopting task '{}' (run {}) from the previous run of the scheduler, pid {}", task_name, run_id, pid);
+            let elapsed = (Utc::now() - start_time).to_std().unwrap_or_default();
+            let active_task = ActiveTask {
+                id: ACTIVE_TASK_ID_COUNTER.fetch_add(1, Ordering::Relaxed) as u32,
+                config: config.clone(),
+                pid,
+                start_instant: Instant::now().checked_sub(elapsed).unwrap_or_else(Instant::now),
+                start_time,
+                closure: None,
+                child: Arc::new(Mutex::new(Execution::Adopted(pid))),
+                run_id: run_id.to_string(),
+                context: RunContext { run_id: run_id.to_string(), ..RunContext::new(start_time) },
+                debug_info: format!("Run id: {}\nAdopted from the previous run of the scheduler", run_id),
+                // What is left of it, it's stopped right away if it's over
+                time_limit: config.time_limit.map(|time_limit| time_limit.saturating_sub(elapsed.as_secs()).max(1)),
+                stdout_path: previous["stdout_path"].as_str().map(PathBuf::from).unwrap_or_default(),
+                stderr_path: previous["stderr_path"].as_str().map(PathBuf::from).unwrap_or_default(),
+                stdout_offset: 0,
+                stderr_offset: 0,
+                output_forwarders: Arc::new(Mutex::new(vec![])),
+                user: None,
+                cgroup: None,
+                lock_file: None,
+                cluster_lock: None,
+                monitor: None,
+                adopted: true,
+            };
+            let Some(reservation) = scheduler.reserve_start(task_name, false) else { continue };
+            drop(scheduler);
+            let task_id = Self::add_active_task(mutex, active_task, reservation).await;
+            Self::wait_for_task(mutex.clone(), task_id, None).await;
+        }
+    }
+
+    /// Cleans up the executions whose monitor stopped before they finished, like after a panic, and reaps the zombie
+    /// children nobody waits for
+    async fn reap_children(mutex: &Arc<Mutex<Scheduler>>, reaper: &mut ZombieReaper) {
+        let (lost, owned) = {
+            let mut scheduler = mutex.lock().await;
+            let lost: Vec<ActiveTask> =
+                scheduler.active_tasks.extract_if(.., |t| t.monitor.as_ref().is_some_and(|monitor| monitor.is_finished())).collect();
+            // The adopted processes are only waited for until they are gone, their zombies are reaped like orphans
+            let owned: Vec<u32> = scheduler.active_tasks.iter().filter(|t| !t.adopted).map(|t| t.pid).filter(|pid| *pid != 0).collect();
+            (lost, owned)
+        };
+
+        for task in lost {
+            // Nothing enforces its time limit anymore, so it's stopped if it's still running
+            let status = task.child.lock().await.terminate(task.config.time_limit_signal, task.config.kill_grace(), &task.config.name).await;
+            if let Some(lock_file) = &task.lock_file {
+                let _ = lock_file.unlock();
+            }
+            if let Some(cluster_lock) = &task.cluster_lock {
+                cluster_lock.release().await;
+            }
+            executor::stop(&task.config, &task.run_id).await;
+            if let Some(cgroup) = &task.cgroup {
+                cgroup.remove();
+            }
+            let exit_code = status.ok().and_then(|status| status.code()).unwrap_or(-1);
+            let error = anyhow!(
+                "The monitor of task '{}' (run {}) stopped before the execution finished, it was cleaned up with exit code {}",
+                task.config.name,
+                task.run_id,
+                exit_code
+            );
+            Self::on_internal_error(mutex, Some(&task.config.name), error).await;
+            mutex.lock().await.save_state().await;
+        }
+
+        let reaped = reaper.reap(&owned);
+        if !reaped.is_empty() {
+            debug!("Reaped {} zombie children", reaped.len());
+        }
+    }
+
+    async fn reload_config(&mut self) -> anyhow::Result<usize> {
+        // Validate and read the new config
+        let config_path = self.config_path.as_ref().ok_or_else(|| anyhow!("The scheduler has no config file to reload"))?;
+        validate_config_path(config_path)?;
+        let config_file = read_config_file(config_path)?;
+        let previous_timezone = system_timezone();
+        let mut new_config = parse_config_file(&config_file)?;
+        self.task_filter.apply(&mut new_config);
+        if system_timezone() != previous_timezone {
+            info!("The system timezone changed from {} to {}", previous_timezone, system_timezone());
+        }
+
+        // Save current state before tearing down
+        self.save_state().await;
+
+        // Build a map of task_name -> (last_execution_time, last_pid, retries) from existing pending tasks
+        let mut state_map: HashMap<String, PendingTask> = HashMap::new();
+        for pt_mutex in &self.pending_tasks {
+            let pt = pt_mutex.lock().await;
+            state_map.insert(pt.config.name.clone(), pt.clone());
+        }
+
+        // Abort all task loop handles (they stop at their next await point)
+        for (_, handle) in self.task_loop_handles.drain(..) {
+            handle.abort();
+        }
+
+        // Clean up finished wait handles (running subprocesses keep their handles)
+        self.wait_handles.retain(|h| !h.is_finished());
+
+        // Detect added/removed/retained tasks
+        let old_names: std::collections::HashSet<&str> = state_map.keys().map(|s| s.as_str()).collect();
+        let new_names: std::collections::HashSet<&str> = new_config.tasks.iter().map(|t| t.name.as_str()).collect();
+
+        let added: Vec<&str> = new_names.difference(&old_names).copied().collect();
+        let removed: Vec<&str> = old_names.difference(&new_names).copied().collect();
+        let retained: Vec<&str> = new_names.intersection(&old_names).copied().collect();
+
+        if !added.is_empty() {
+            info!("New tasks added: {}", added.join(", "));
+        }
+        if !removed.is_empty() {
+            info!("Tasks removed: {}", removed.join(", "));
+        }
+        if !retained.is_empty() {
+            info!("Tasks retained: {}", retained.join(", "));
+        }
+
+        // Warn if logging config changed (env_logger can only be initialized once)
+        if new_config.logging != self.config.logging {
+            warn!("Logging configuration changed, but logging cannot be reconfigured at runtime. Restart to apply logging changes.");
+        }
+
+        // Update config and tasks
+        self.config = new_config;
+        configure_alert_queue(&self.config.alerts.queue.clone().unwrap_or_default());
+        configure_plugins(&self.config.plugins);
+        self.tasks = self.config.tasks.clone();
+
+        // Reinitialize SQLite logger if configured
+        self.sqlite_logger = None;
+        if let Some(sqlite_config) = &self.config.logging.sqlite {
+            if sqlite_config.enabled {
+                match SqliteLogger::new(sqlite_config.clone()).await {
+                    Ok(logger) => {
+                        self.sqlite_logger = Some(logger);
+                    }
+                    Err(e) => {
+                        error!("Failed to reinitialize SQLite logger: {}", e);
+                    }
+                }
+            }
+        }
+
+        // Create new PendingTasks, restoring state for tasks that still exist by name
+        let mut new_pending_tasks = Vec::new();
+        for task_config in &self.tasks {
+            let mut new_task = self.new_pending_task(task_config.clone());
+            if let Some(prev_task) = state_map.get(&task_config.name) {
+                new_task.last_execution_time = prev_task.last_execution_time.max(new_task.last_execution_time);
+                new_task.last_pid = prev_task.last_pid;
+                new_task.retries = prev_task.retries;
+                new_task.last_exit_code = prev_task.last_exit_code;
+                new_task.consecutive_failures = prev_task.consecutive_failures;
+                new_task.runs = prev_task.runs;
+                new_task.monthly_runtime = prev_task.monthly_runtime.clone();
+                new_task.last_skipped_time = prev_task.last_skipped_time;
+            }
+            new_pending_tasks.push(Arc::new(Mutex::new(new_task)));
+        }
+        self.pending_tasks = new_pending_tasks;
+
+        Ok(self.tasks.len())
+    }
+
+    async fn run_async(mutex: Arc<Mutex<Scheduler>>) -> anyhow::Result<()> {
+        Self::start(&mutex).await;
+
+        // Accept requests from `cron-rs ctl`
+        let socket_path = { mutex.lock().await.config.control_socket_path() };
+        let control_handle = tokio::spawn(control::listen(socket_path.clone(), mutex.clone()));
+
+        // Wait for Ctrl+C signal to stop the infinite loop
+        let mut signals = SchedulerSignals::register();
+        let mut cleanup_interval = tokio::time::interval(retention::CLEANUP_INTERVAL);
+        let mut reap_interval = tokio::time::interval(REAP_INTERVAL);
+        let mut reaper = ZombieReaper::default();
+        loop {
+            let request = tokio::select! {
+                request = signals.recv() => request,
+                _ = cleanup_interval.tick() => {
+                    Self::cleanup_outputs(&mutex).await;
+                    continue;
+                }
+                _ = reap_interval.tick() => SignalRequest::Reap,
+            };
+
+            match request {
+                SignalRequest::Shutdown => {
+                    info!("Scheduler shutdown initiated");
+                    Self::shutdown(&mutex).await;
+                    control_handle.abort();
+                    let _ = std::fs::remove_file(&socket_path);
+                    break;
+                }
+                SignalRequest::ToggleDebug => {
+                    // Logged with the new level, so it shows up when debug is turned off too
+                    let level = logging::toggle_debug();
+                    info!("Received SIGUSR1, saving scheduler state, log level set to {}", level.as_str().to_lowercase());
+                    {
+                        let scheduler = mutex.lock().await;
+                        scheduler.save_state().await;
+                    }
+                }
+                SignalRequest::Reap => Self::reap_children(&mutex, &mut reaper).await,
+                SignalRequest::Reload => {
+                    info!("Received SIGHUP, reloading configuration");
+                    {
+                        let mut scheduler = mutex.lock().await;
+                        match scheduler.reload_config().await {
+                            Ok(task_count) => {
+                                info!("Configuration reloaded successfully with {} tasks", task_count);
+
+                                // Re-spawn task loops for the new tasks
+                                let pending_tasks = scheduler.pending_tasks.clone();
+                                drop(scheduler);
+
+                                Self::spawn_tasks(mutex.clone(), pending_tasks).await;
+                            }
+                            Err(e) => {
+                                error!("Failed to reload configuration: {}. Keeping existing config.", e);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Restores the previous state and starts the scheduling loops of the tasks
+    async fn start(mutex: &Arc<Mutex<Scheduler>>) {
+        // Initialize SQLite logger if configured
+        {
+            let mut scheduler = mutex.lock().await;
+            if let Some(sqlite_config) = &scheduler.config.logging.sqlite {
+                if sqlite_config.enabled {
+                    match SqliteLogger::new(sqlite_config.clone()).await {
+                        Ok(logger) => {
+                            scheduler.sqlite_logger = Some(logger);
+                        }
+                        Err(e) => {
+                            error!("Failed to initialize SQLite logger: {}", e);
+                        }
+                    }
+                }
+            }
+        }
+
+        {
+            let mut scheduler = mutex.lock().await;
+            configure_alert_queue(&scheduler.config.alerts.queue.clone().unwrap_or_default());
+            configure_plugins(&scheduler.config.plugins);
+            if let Some(path) = &scheduler.config.logging.events {
+                spawn_event_log(path.clone(), scheduler.events.subscribe());
+            }
+            if let Some(digest) = scheduler.config.digest.clone() {
+                let handle = spawn_digest(digest, scheduler.events.subscribe());
+                scheduler.wait_handles.push(handle);
+            }
+            let handle = tokio::spawn(Self::watch_clock(mutex.clone()));
+            scheduler.wait_handles.push(handle);
+        }
+
+        // Restore the run counters from the previous run, so restarts don't reset max_runs
+        let (previous_state, previous_runs) = { mutex.lock().await.load_state().await };
+
+        let pending_tasks: Vec<Arc<Mutex<PendingTask>>> = {
+            let mut scheduler = mutex.lock().await;
+            let mut pending_tasks = vec![];
+
+            for t in &scheduler.tasks {
+                let mut pt = scheduler.new_pending_task(t.clone());
+                if let Some(prev) = previous_state.get(&t.name) {
+                    pt.runs = prev["runs"].as_u64().unwrap_or(0) as u32;
+                    pt.last_exit_code = prev["last_exit_code"].as_i64().map(|exit_code| exit_code as i32);
+                    pt.consecutive_failures = prev["consecutive_failures"].as_u64().unwrap_or(0) as u32;
+                    // A task enabled or disabled with `ctl` stays so, until its `enabled` changes in the config
+                    if prev["config_enabled"].as_bool() == Some(t.enabled) {
+                        pt.enabled = prev["enabled"].as_bool().unwrap_or(t.enabled);
+                    }
+                    if let Some(month) = prev["monthly_runtime"]["month"].as_str() {
+                        let seconds = prev["monthly_runtime"]["seconds"].as_f64().unwrap_or(0.0);
+                        let used = Duration::try_from_secs_f64(seconds).unwrap_or_default();
+                        pt.monthly_runtime = MonthlyRuntime { month: month.to_string(), used };
+                    }
+                }
+                pending_tasks.push(Arc::new(Mutex::new(pt)));
+            }
+
+            scheduler.pending_tasks = pending_tasks.clone();
+            pending_tasks
+        };
+        info!("Initializing scheduler with {} tasks", pending_tasks.len());
+        Self::restore_monthly_runtime(mutex, &pending_tasks).await;
+        Self::adopt_previous_runs(mutex, previous_runs).await;
+
+        // Spawn task execution tasks
+        Self::spawn_tasks(mutex.clone(), pending_tasks).await;
+    }
+
+    /// Takes the runtime used this month by the tasks with `max_monthly_runtime` from the SQLite history, so the budget
+    /// isn't reset by a restart without a state file or a crash before the state was saved
+    async fn restore_monthly_runtime(mutex: &Arc<Mutex<Scheduler>>, pending_tasks: &[Arc<Mutex<PendingTask>>]) {
+        let (sqlite_logger, state_file) = {
+            let scheduler = mutex.lock().await;
+            (scheduler.sqlite_logger.clone(), scheduler.state_path.is_some())
+        };
+        for pending_task in pending_tasks {
+            let mut pending_task = pending_task.lock().await;
+            if pending_task.config.max_monthly_runtime.is_none() {
+                continue;
+            }
+            let name = pending_task.config.name.clone();
+            let now = Self::get_current_datetime_at(pending_task.config.timezone);
+            let Some(sqlite_logger) = &sqlite_logger else {
+                if !state_file {
+                    warn!(
+                        "Task '{}' has max_monthly_runtime without a state file nor the SQLite history, a restart \
+                         resets the runtime used",
+                        name
+                    );
+                }
+                continue;
+            };
+            let since = MonthlyRuntime::month_start(now).with_timezone(&Utc);
+            match sqlite_logger.task_runtime_since(&name, since).await {
+                // The state file may have runs the history missed, and the other way around
+                Ok(used) if used > pending_task.monthly_runtime.used_in(now) => {
+                    pending_task.monthly_runtime = MonthlyRuntime { month: MonthlyRuntime::month_of(now), used };
+                }
+                Ok(_) => {}
+                Err(e) => error!("Failed to read the monthly runtime of task '{}': {:#}", name, e),
+            }
+        }
+    }
+
+    /// Stops the running tasks and the scheduling loops, saving the state first
+    async fn shutdown(mutex: &Arc<Mutex<Scheduler>>) {
+        Self::stop_active_tasks(mutex).await;
+
+        let scheduler = mutex.lock().await;
+        scheduler.save_state().await;
+
+        for (_, handle) in &scheduler.task_loop_handles {
+            handle.abort();
+        }
+        for handle in &scheduler.wait_handles {
+            handle.abort();
+        }
+    }
+
+    /// Sends a SIGTERM to the process groups of the running tasks, and a SIGKILL to the ones still running after their
+    /// `kill_grace`. Tasks are in their own process group, so they don't get the Ctrl+C from the terminal
+    async fn stop_active_tasks(mutex: &Arc<Mutex<Scheduler>>) {
+        let tasks: Vec<ActiveTask> = { mutex.lock().await.active_tasks.clone() };
+
+        for task in &tasks {
+            if let Some(closure) = &task.closure {
+                info!("Stopping task '{}'", task.config.name);
+                closure.abort();
+                continue;
+            }
+            info!("Stopping task '{}' (pid {})", task.config.name, task.pid);
+            signal_process_group(task.pid, StopSignal::Sigterm);
+        }
+        let start = Instant::now();
+        for task in tasks.iter().filter(|t| t.closure.is_none()) {
+            let grace = task.config.kill_grace();
+            if !wait_process_exit(task.pid, grace.saturating_sub(start.elapsed())).await {
+                warn!("Task '{}' didn't exit {} after SIGTERM, sending SIGKILL", task.config.name, format_duration(grace));
+            }
+            // Also the processes it left running
+            signal_process_group(task.pid, StopSignal::Sigkill);
+            executor::stop(&task.config, &task.run_id).await;
+            if let Some(cgroup) = &task.cgroup {
+                cgroup.remove();
+            }
+        }
+    }
+
+    /// Compares the wall clock with the monotonic clock, the sleeps of the tasks only follow the latter. After a jump
+    /// they are woken up to compute their next run again, instead of waiting for a time that is not right anymore
+    async fn watch_clock(mutex: Arc<Mutex<Scheduler>>) {
+        let mut watch = ClockWatch::default();
+        let mut interval = tokio::time::interval(CLOCK_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            if let Some(jump) = watch.check() {
+                Self::on_clock_change(&mutex, jump).await;
+            }
+        }
+    }
+
+    async fn on_clock_change(mutex: &Arc<Mutex<Scheduler>>, jump: TimeDelta) {
+        let jump_text = describe_jump(jump);
+        warn!("The system clock jumped {}, computing the next runs again", jump_text);
+        clock::notify_clock_change();
+        let alerts = {
+            let scheduler = mutex.lock().await;
+            let _ = scheduler.events.send(SchedulerEvent::ClockChanged { jump_seconds: jump.num_seconds() });
+            scheduler.config.alerts.on_clock_change.clone()
+        };
+        if alerts.is_empty() {
+            return;
+        }
+        for alert in alerts {
+            let kind = alert.kind();
+            let jump_text = jump_text.clone();
+            if let Err(e) = queue_alert(move || send_clock_change_alert(&alert, &jump_text)).await {
+                error!("Failed to send the clock change with the {} alert: {:#}", kind, e);
+            }
+        }
+    }
+
+    /// Writes the resources used by the last execution of the tasks to the `metrics_file`, the tasks without it are
+    /// left out
+    async fn write_metrics(mutex: &Arc<Mutex<Scheduler>>, path: &Path) {
+        let pending_tasks = { mutex.lock().await.pending_tasks.clone() };
+        let mut usage = vec![];
+        for pending_task in pending_tasks {
+            let pending_task = pending_task.lock().await;
+            if let Some(last_usage) = pending_task.last_usage {
+                usage.push((pending_task.config.name.clone(), last_usage));
+            }
+        }
+        if let Err(e) = write_metrics_file(path, &usage).await {
+            error!("Failed to write the metrics file {}: {}", path.display(), e);
+        }
+    }
+
+    /// A task used its `max_monthly_runtime`, its runs are skipped until the next month
+    async fn on_budget_exhausted(mutex: &Arc<Mutex<Scheduler>>, task_name: &str, budget: Duration, used: Duration) {
+        warn!(
+            task = task_name;
+            "Task '{}' used its monthly runtime budget of {}, its runs are skipped until the next month",
+            task_name, format_duration(budget)
+        );
+        let alerts = { mutex.lock().await.config.alerts.on_budget_exhausted.clone() };
+        for alert in alerts {
+            let kind = alert.kind();
+            let name = task_name.to_string();
+            if let Err(e) = queue_alert(move || send_budget_alert(&alert, &name, budget, used)).await {
+                error!("Failed to send the budget of '{}' with the {} alert: {:#}", task_name, kind, e);
+            }
+        }
+    }
+
+    /// Applies `output_retention` in a separate thread, the file operations can take a while
+    async fn cleanup_outputs(mutex: &Arc<Mutex<Scheduler>>) {
+        let (tasks, retention, in_use) = {
+            let scheduler = mutex.lock().await;
+            let Some(retention) = scheduler.config.output_retention.clone() else { return };
+            let in_use: Vec<PathBuf> = scheduler
+                .active_tasks
+                .iter()
+                .flat_map(|t| [t.stdout_path.clone(), t.stderr_path.clone()])
+                .collect();
+            (scheduler.tasks.clone(), retention, in_use)
+        };
+
+        let result = tokio::task::spawn_blocking(move || retention::cleanup_outputs(&tasks, &retention, &in_use)).await;
+        match result {
+            Ok(summary) if summary.deleted > 0 || summary.compressed > 0 => info!(
+                "Output retention: deleted {} files ({} bytes), compressed {} files",
+                summary.deleted, summary.freed_bytes, summary.compressed
+            ),
+            Ok(_) => {}
+            Err(e) => error!("Output retention cleanup failed: {}", e),
+        }
+    }
+
+    async fn spawn_tasks(mutex: Arc<Mutex<Scheduler>>, pending_tasks: Vec<Arc<Mutex<PendingTask>>>) {
+        for pending_task_mutex in pending_tasks {
+            let scheduler_mutex = mutex.clone();
+            let task_name = pending_task_mutex.lock().await.config.name.clone();
+
+            let handle = tokio::spawn(async move {
+                Self::execute_task_loop(pending_task_mutex, scheduler_mutex).await;
+            });
+
+            {
+                let mut scheduler = mutex.lock().await;
+                scheduler.task_loop_handles.push((task_name, handle));
+            }
+        }
+    }
+
+    async fn execute_task_loop(pending_task_mutex: Arc<Mutex<PendingTask>>, scheduler_mutex: Arc<Mutex<Scheduler>>) {
+        let mut skipped_run = None;
+        // Execution the last sleep waited for, to find it if the sleep overshot it
+        let mut planned_run = None;
+        // Watches the files of a task with `on_change` as long as its loop runs
+        let _watcher = {
+            let task = pending_task_mutex.lock().await;
+            match &task.config.schedule {
+                Schedule::OnChange { paths, debounce } => {
+                    let trigger = task.trigger.clone();
+                    match watch::watch_paths(paths, *debounce, move || trigger.fire()) {
+                        Ok(watcher) => Some(watcher),
+                        Err(e) => {
+                            error!(task = task.config.name.as_str(); "Task '{}' will not run: {:#}", task.config.name, e);
+                            return;
+                        }
+                    }
+                }
+                _ => None,
+            }
+        };
+        // Wait loop for the right time to execute the task
+        loop {
+            let pending_task_copy: PendingTask = { pending_task_mutex.lock().await.clone() };
+
+            // Stop scheduling the task once it reaches the max number of runs
+            if let Some(max_runs) = pending_task_copy.config.max_runs {
+                if pending_task_copy.runs >= max_runs {
+                    // A running execution that fails gives its run back when only successes count
+                    let running = scheduler_mutex.lock().await.is_task_running(&pending_task_copy.config.name);
+                    if pending_task_copy.config.max_runs_only_successes && running {
+                        planned_run = Some(Self::sleep_until_task_is_ready(&pending_task_copy).await);
+                        continue;
+                    }
+                    info!(
+                        task = pending_task_copy.config.name.as_str();
+                        "Task '{}' reached the max number of runs ({}), it will not be scheduled again",
+                        pending_task_copy.config.name, max_runs
+                    );
+                    return;
+                }
+            }
+
+            let start = Instant::now();
+            // Check if the task must be executed now, or if its time passed while the scheduler was asleep
+            let missed_run = Self::missed_run(&pending_task_copy, planned_run.take());
+            let scheduled_run = match (Self::due_run(&pending_task_copy), missed_run) {
+                (Some(scheduled_run), _) => scheduled_run,
+                (None, Some(missed_run)) => {
+                    // Only a bit late, it runs as if it was on time
+                    let late = Self::get_current_datetime_at(pending_task_copy.config.timezone).signed_duration_since(missed_run)
+                        > TimeDelta::from_std(MISFIRE_THRESHOLD).unwrap_or_default();
+                    if late && pending_task_copy.config.misfire == MisfirePolicy::Skip {
+                        warn!(
+                            task = pending_task_copy.config.name.as_str();
+                            "Task '{}' missed its execution at {}, skipping it", pending_task_copy.config.name, missed_run
+                        );
+                        Self::on_task_skipped(&scheduler_mutex, &pending_task_copy, missed_run, &mut skipped_run, "missed")
+                            .await;
+                        let skipped_task = Self::skip_run(&pending_task_mutex).await;
+                        planned_run = Some(Self::sleep_until_task_is_ready(&skipped_task).await);
+                        continue;
+                    }
+                    if late {
+                        warn!(
+                            task = pending_task_copy.config.name.as_str();
+                            "Task '{}' missed its execution at {}, running it now", pending_task_copy.config.name, missed_run
+                        );
+                    }
+                    missed_run
+                }
+                (None, None) => {
+                    planned_run = Some(Self::sleep_until_task_is_ready(&pending_task_copy).await);
+                    continue;
+                }
+            };
+
+            if !pending_task_copy.enabled {
+                debug!(task = pending_task_copy.config.name.as_str(); "Task '{}' is disabled, skipping execution", pending_task_copy.config.name);
+                Self::on_task_skipped(&scheduler_mutex, &pending_task_copy, scheduled_run, &mut skipped_run, "disabled")
+                    .await;
+                let skipped_task = Self::skip_run(&pending_task_mutex).await;
+                planned_run = Some(Self::sleep_until_task_is_ready(&skipped_task).await);
+                continue;
+            }
+
+            // The runtime budget of the month is used, the runs are skipped until the next month
+            if let Some(budget) = pending_task_copy.config.max_monthly_runtime {
+                let now = Self::get_current_datetime_at(pending_task_copy.config.timezone);
+                if pending_task_copy.monthly_runtime.used_in(now) >= budget {
+                    let reason = format!("monthly runtime budget of {} used", format_duration(budget));
+                    info!(
+                        task = pending_task_copy.config.name.as_str();
+                        "Task '{}' skipped, {}", pending_task_copy.config.name, reason
+                    );
+                    Self::on_task_skipped(&scheduler_mutex, &pending_task_copy, scheduled_run, &mut skipped_run, &reason).await;
+                    let skipped_task = Self::skip_run(&pending_task_mutex).await;
+                    planned_run = Some(Self::sleep_until_task_is_ready(&skipped_task).await);
+                    continue;
+                }
+            }
+
+            // Conditions on the state of the host, like the power supply
+            let task = pending_task_copy.config.clone();
+            let unmet = tokio::task::spawn_blocking(move || conditions::unmet_condition(&task.conditions, &task.name))
+                .await
+                .unwrap_or_default();
+            if let Some(reason) = unmet {
+                info!(
+                    task = pending_task_copy.config.name.as_str();
+                    "Task '{}' skipped, {}", pending_task_copy.config.name, reason
+                );
+                Self::on_task_skipped(&scheduler_mutex, &pending_task_copy, scheduled_run, &mut skipped_run, &reason).await;
+                // A period task stays due, it runs once the conditions are met before the period ends
+                if matches!(pending_task_copy.config.schedule, Schedule::Period { .. }) {
+                    sleep(CONDITION_RECHECK_INTERVAL).await;
+                    continue;
+                }
+                let skipped_task = Self::skip_run(&pending_task_mutex).await;
+                planned_run = Some(Self::sleep_until_task_is_ready(&skipped_task).await);
+                continue;
+            }
+
+            // Verify that the previous execution is finished, if the config requires it. The start is reserved in the
+            // same lock, a trigger or a reload can't start another execution in between
+            let name = pending_task_copy.config.name.as_str();
+            let started = {
+                let scheduler = scheduler_mutex.lock().await;
+                scheduler.reserve_start(name, pending_task_copy.config.avoid_overlapping).map(|reservation| {
+                    (reservation, scheduler.config.clone(), scheduler.sqlite_logger.clone(), scheduler.events.clone())
+                })
+            };
+            let Some((reservation, config, sqlite_logger, events)) = started else {
+                debug!(task = name; "Task '{}' is already running, skipping execution", name);
+                Self::on_task_skipped(&scheduler_mutex, &pending_task_copy, scheduled_run, &mut skipped_run, "already running").await;
+                let skipped_task = Self::skip_run(&pending_task_mutex).await;
+                planned_run = Some(Self::sleep_until_task_is_ready(&skipped_task).await);
+                continue;
+            };
+
+            // The lock_file avoids overlapping with other processes, like other schedulers or manual runs
+            let lock_file = match Self::take_lock_file(&pending_task_copy.config) {
+                Ok(lock_file) => lock_file,
+                Err(reason) => {
+                    info!(task = name; "Task '{}' skipped, {:#}", name, reason);
+                    let reason = format!("{:#}", reason);
+                    Self::on_task_skipped(&scheduler_mutex, &pending_task_copy, scheduled_run, &mut skipped_run, &reason).await;
+                    let skipped_task = Self::skip_run(&pending_task_mutex).await;
+                    planned_run = Some(Self::sleep_until_task_is_ready(&skipped_task).await);
+                    continue;
+                }
+            };
+            // The cluster_lock avoids overlapping with the other nodes that run the task
+            let cluster_lock = match Self::take_cluster_lock(&pending_task_copy.config).await {
+                Ok(cluster_lock) => cluster_lock,
+                Err(reason) => {
+                    info!(task = name; "Task '{}' skipped, {:#}", name, reason);
+                    let reason = format!("{:#}", reason);
+                    Self::on_task_skipped(&scheduler_mutex, &pending_task_copy, scheduled_run, &mut skipped_run, &reason).await;
+                    let skipped_task = Self::skip_run(&pending_task_mutex).await;
+                    planned_run = Some(Self::sleep_until_task_is_ready(&skipped_task).await);
+                    continue;
+                }
+            };
+
+            // The run is counted before it starts, so overlapping executions can't go past max_runs
+            {
+                let mut pending_task = pending_task_mutex.lock().await;
+                if pending_task.config.max_runs.is_some_and(|max_runs| pending_task.runs >= max_runs) {
+                    continue;
+                }
+                pending_task.runs += 1;
+            }
+
+            // Execute the task
+            let context = RunContext {
+                scheduled_time: scheduled_run.with_timezone(&Utc),
+                prev_exit_code: pending_task_copy.last_exit_code,
+                attempt: pending_task_copy.retries + 1,
+                prev_failures: pending_task_copy.consecutive_failures,
+                ..RunContext::new(Utc::now())
+            };
+            let mut active_task = match Self::execute_task(
+                &scheduler_mutex,
+                &pending_task_copy.config,
+                &config,
+                &sqlite_logger,
+                &events,
+                context,
+            )
+            .await
+            {
+                Ok(active_task) => active_task,
+                Err(e) => {
+                    Self::on_start_error(&scheduler_mutex, &pending_task_copy.config.name, e).await;
+                    // Wait for the next scheduled time instead of retrying right away, the run didn't happen
+                    let mut pending_task = pending_task_mutex.lock().await;
+                    pending_task.last_execution_time = Some(Utc::now());
+                    pending_task.runs = pending_task.runs.saturating_sub(1);
+                    continue;
+                }
+            };
+
+            active_task.lock_file = lock_file;
+            active_task.cluster_lock = cluster_lock;
+            {
+                let mut pending_task = pending_task_mutex.lock().await;
+                pending_task.last_execution_time = Some(active_task.start_time);
+                pending_task.last_pid = active_task.closure.is_none().then_some(active_task.pid);
+            }
+
+            let task_id = Self::add_active_task(&scheduler_mutex, active_task, reservation).await;
+
+            // Wait for the task to finish
+            Self::wait_for_task(scheduler_mutex.clone(), task_id, None).await;
+
+            // Sleep at least to the next second to avoid running the task multiple times the same datetime
+            if start.elapsed().as_secs() < 1 {
+                // Sleeping a whole second makes the time drifts slowly, by sleeping only the
+                // millis needed to jump to the next second, we mostly eliminate the drift
+                let next_second =
+                    Self::get_current_datetime_at(pending_task_copy.config.timezone) + TimeDelta::seconds(1);
+                let current_second = Self::get_precise_datetime_at(pending_task_copy.config.timezone);
+
+                // Get the number of milliseconds to the next second
+                let diff = next_second.signed_duration_since(current_second).num_milliseconds() as u64;
+
+                sleep(Duration::from_millis(diff)).await;
+            }
+        }
+    }
+
+    /// Records a started execution until [Scheduler::wait_for_task] sees it finish, its reservation is released in the
+    /// same lock
+    async fn add_active_task(mutex: &Arc<Mutex<Scheduler>>, active_task: ActiveTask, reservation: StartReservation) -> u32 {
+        let task_id = active_task.id;
+        let mut scheduler = mutex.lock().await;
+        drop(reservation);
+        let _ = scheduler.events.send(SchedulerEvent::TaskStarted {
+            task_name: active_task.config.name.clone(),
+            run_id: active_task.run_id.clone(),
+            pid: active_task.pid,
+            start_time: active_task.start_time,
+        });
+        scheduler.active_tasks.push(active_task);
+        scheduler.save_state().await;
+        task_id
+    }
+
+    // Wait for the task to end and handle the result, `done` receives the finished execution
+    async fn wait_for_task(mutex: Arc<Mutex<Scheduler>>, task_id: u32, done: Option<oneshot::Sender<FinishedRunStatus>>) {
+        let found = {
+            let scheduler = mutex.lock().await;
+            scheduler.active_tasks.iter().find(|t| t.id == task_id).map(|active_task| {
+                (active_task.child.clone(), active_task.time_limit, active_task.config.name.clone(), active_task.clone())
+            })
+        };
+        let Some((child_mutex, time_limit, task_name, task)) = found else {
+            Self::on_internal_error(&mutex, None, anyhow!("Execution {} is not running, it can't be waited for", task_id)).await;
+            return;
+        };
+
+        // Wait for the task to finish in a separate coroutine to not block this loop
+        let scheduler_mutex = mutex.clone();
+        let handle = tokio::spawn(async move {
+            let mut child = child_mutex.lock().await;
+
+            let (exit_status, timed_out) = if let Some(time_limit) = time_limit {
+                tokio::select! {
+                    status = child.wait_with_usage() => (status, false),
+                    _ = sleep(Duration::from_secs(time_limit)) => {
+                        // Warn the user that the task will be killed
+                        let signal = task.config.time_limit_signal;
+                        warn!(
+                            task = task_name.as_str(), run_id = task.run_id.as_str();
+                            "Task '{}' exceeded time limit of {} seconds, sending {}", task_name, time_limit, signal.name()
+                        );
+
+                        // Waits for the process to fully terminate
+                        let status = child.terminate(signal, task.config.kill_grace(), &task_name).await;
+                        executor::stop(&task.config, &task.run_id).await;
+                        (status.map(|status| (status, None)), true)
+                    }
+                }
+            } else {
+                (child.wait_with_usage().await, false)
+            };
+            let (exit_status, usage) = match exit_status {
+                Ok(exit_status) => exit_status,
+                Err(e) => {
+                    // The execution still finishes, as killed, so the task is not stuck as running
+                    let error = anyhow!(e).context(format!("Failed to wait for task '{}' (run {})", task_name, task.run_id));
+                    Self::on_internal_error(&scheduler_mutex, Some(&task_name), error).await;
+                    (executor::killed_status(), None)
+                }
+            };
+
+            // Outside of the lock, they can take a while
+            if !task.config.after.is_empty() && !task.adopted {
+                let exit_code = exit_status.code().unwrap_or(-1);
+                hooks::run_after_hooks(&task.config, &task.run_id, exit_code, &task.stdout_path, &task.stderr_path, task.user).await;
+            }
+
+            let removed = {
+                let mut scheduler = scheduler_mutex.lock().await;
+                // Remove active task
+                scheduler.active_tasks.iter().position(|t| t.id == task_id).map(|active_task_index| {
+                    let mut active_task = scheduler.active_tasks.remove(active_task_index);
+                    // Other processes can take the lock_file once the execution is not running for this scheduler
+                    // either
+                    if let Some(lock_file) = active_task.lock_file.take() {
+                        let _ = lock_file.unlock();
+                    }
+                    (active_task, scheduler.config.clone(), scheduler.sqlite_logger.clone(), scheduler.events.clone())
+                })
+            };
+            let Some((active_task, config, sqlite_logger, events)) = removed else {
+                let error = anyhow!("Task '{}' (run {}) finished but it was not running anymore", task_name, task.run_id);
+                Self::on_internal_error(&scheduler_mutex, Some(&task_name), error).await;
+                return;
+            };
+            // Released outside the lock of the scheduler, it waits for the redis server
+            if let Some(cluster_lock) = &active_task.cluster_lock {
+                cluster_lock.release().await;
+            }
+            if active_task.adopted {
+                info!(
+                    task = task_name.as_str(), run_id = active_task.run_id.as_str();
+                    "Task '{}' (run {}) of the previous run of the scheduler {} after {}, its exit code is unknown",
+                    task_name,
+                    active_task.run_id,
+                    if timed_out { "was stopped" } else { "finished" },
+                    format_duration(active_task.start_instant.elapsed())
+                );
+                scheduler_mutex.lock().await.save_state().await;
+                return;
+            }
+
+            // Also after a failure, the task runs again in the next period
+            if matches!(active_task.config.schedule, Schedule::Period { .. }) {
+                write_period_stamp(&config.period_stamp_dir_path(), &active_task.config.name, active_task.start_time).await;
+            }
+
+            // Alerts and on_failure_cmd run without holding the lock
+            let success =
+                Self::on_task_completed(&active_task, exit_status, usage, timed_out, &config, &sqlite_logger, &events).await;
+
+            let finished = FinishedRunStatus {
+                task_name: active_task.config.name.clone(),
+                run_id: active_task.run_id.clone(),
+                end_time: Utc::now(),
+                duration_seconds: active_task.start_instant.elapsed().as_secs_f64(),
+                exit_code: exit_status.code().unwrap_or(-1),
+                success,
+                usage,
+            };
+
+            // Start the tasks chained to this one
+            Self::trigger_after_tasks(&scheduler_mutex, &active_task.config.name, success).await;
+            if let Some(done) = done {
+                let _ = done.send(finished.clone());
+            }
+
+            // The pending task is looked up again, the config may have been reloaded meanwhile
+            let pending_tasks = { scheduler_mutex.lock().await.pending_tasks.clone() };
+            let mut budget_exhausted = None;
+            for pending_task in pending_tasks {
+                let mut pending_task = pending_task.lock().await;
+                if pending_task.config.name == active_task.config.name {
+                    let start = active_task.start_time.with_timezone(&active_task.config.timezone);
+                    let used_before = pending_task.monthly_runtime.used_in(start);
+                    pending_task.monthly_runtime.add(start, active_task.start_instant.elapsed());
+                    let used = pending_task.monthly_runtime.used;
+                    if let Some(budget) = pending_task.config.max_monthly_runtime {
+                        if used_before < budget && used >= budget {
+                            budget_exhausted = Some((budget, used));
+                        }
+                    }
+                    pending_task.last_exit_code = Some(exit_status.code().unwrap_or(-1));
+                    pending_task.last_usage = usage;
+                    pending_task.consecutive_failures = if success { 0 } else { pending_task.consecutive_failures + 1 };
+                    // Only successful runs count towards max_runs, a failed one gives back the run counted when it
+                    // started
+                    if active_task.config.max_runs_only_successes && !success {
+                        pending_task.runs = pending_task.runs.saturating_sub(1);
+                    }
+                }
+            }
+            if let Some((budget, used)) = budget_exhausted {
+                Self::on_budget_exhausted(&scheduler_mutex, &active_task.config.name, budget, used).await;
+            }
+            if let Some(path) = &config.metrics_file {
+                Self::write_metrics(&scheduler_mutex, path).await;
+            }
+            let mut scheduler = scheduler_mutex.lock().await;
+            if scheduler.recent_runs.len() == RECENT_RUNS {
+                scheduler.recent_runs.pop_front();
+            }
+            scheduler.recent_runs.push_back(finished);
+            scheduler.save_state().await;
+        });
+
+        {
+            let mut scheduler = mutex.lock().await;
+            if let Some(active_task) = scheduler.active_tasks.iter_mut().find(|t| t.id == task_id) {
+                active_task.monitor = Some(handle.abort_handle());
+            }
+            scheduler.wait_handles.push(handle);
+        }
+    }
+
+    /// Runs the stages of a pipeline one after the other and the steps of each stage at the same time, as the closure of
+    /// the pipeline task. The steps are executions of their own, with their output files and events
+    fn run_pipeline(mutex: Arc<Mutex<Scheduler>>, pipeline: Arc<Pipeline>) -> TaskFuture {
+        Box::pin(async move {
+            for stage in &pipeline.stages {
+                let handles: Vec<_> = stage.iter().map(|step| tokio::spawn(Self::run_step(mutex.clone(), step.clone()))).collect();
+                let mut failed = vec![];
+                for (step, handle) in stage.iter().zip(handles) {
+                    if !handle.await.unwrap_or(false) {
+                        failed.push(format!("'{}'", step.name));
+                    }
+                }
+                if !failed.is_empty() {
+                    return Err(anyhow!("Step {} failed", failed.join(", ")));
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Runs a step of a pipeline right away, returns whether it succeeded
+    async fn run_step(mutex: Arc<Mutex<Scheduler>>, step: Arc<TaskConfig>) -> bool {
+        let (reservation, config, sqlite_logger, events, pending_tasks) = {
+            let scheduler = mutex.lock().await;
+            // Steps don't skip their run, the pipeline already avoids overlapping
+            let Some(reservation) = scheduler.reserve_start(&step.name, false) else { return false };
+            (reservation, scheduler.config.clone(), scheduler.sqlite_logger.clone(), scheduler.events.clone(), scheduler.pending_tasks.clone())
+        };
+        // A step that is one of the tasks continues its history
+        let mut context = RunContext::new(Utc::now());
+        for pending_task in pending_tasks {
+            let pending_task = pending_task.lock().await;
+            if pending_task.config.name == step.name {
+                context.prev_exit_code = pending_task.last_exit_code;
+                context.prev_failures = pending_task.consecutive_failures;
+            }
+        }
+        let lock_file = match Self::take_lock_file(&step) {
+            Ok(lock_file) => lock_file,
+            Err(e) => {
+                error!("Step '{}' can't run, {:#}", step.name, e);
+                return false;
+            }
+        };
+        let cluster_lock = match Self::take_cluster_lock(&step).await {
+            Ok(cluster_lock) => cluster_lock,
+            Err(e) => {
+                error!("Step '{}' can't run, {:#}", step.name, e);
+                return false;
+            }
+        };
+        let mut active_task = match Self::execute_task(&mutex, &step, &config, &sqlite_logger, &events, context).await {
+            Ok(active_task) => active_task,
+            Err(e) => {
+                Self::on_start_error(&mutex, &step.name, e).await;
+                return false;
+            }
+        };
+        active_task.lock_file = lock_file;
+        active_task.cluster_lock = cluster_lock;
+
+        let task_id = Self::add_active_task(&mutex, active_task, reservation).await;
+        let (done, finished) = oneshot::channel();
+        Self::wait_for_task(mutex, task_id, Some(done)).await;
+        finished.await.is_ok_and(|finished| finished.success)
+    }
+
+    /// Runs a task right away, out of its schedule, like `cron-rs ctl trigger`. The schedule of the task is not changed.
+    /// A disabled task, or one with `avoid_overlapping` that is already running, is refused unless `force` is set.
+    /// Returns the run id, and the receiver of the execution once it finishes
+    pub async fn trigger_task(
+        mutex: &Arc<Mutex<Scheduler>>,
+        task_name: &str,
+        force: bool,
+    ) -> anyhow::Result<(String, oneshot::Receiver<FinishedRunStatus>)> {
+        let (pending_task, reservation, config, sqlite_logger, events) = {
+            let scheduler = mutex.lock().await;
+            let mut found = None;
+            for pending_task in &scheduler.pending_tasks {
+                let pending_task = pending_task.lock().await;
+                if pending_task.config.name == task_name {
+                    found = Some(pending_task.clone());
+                }
+            }
+            let Some(pending_task) = found else {
+                return Err(anyhow!("Task '{}' not found", task_name));
+            };
+            if !force && !pending_task.enabled {
+                return Err(anyhow!("Task '{}' is disabled, use --force to run it anyway", task_name));
+            }
+            let Some(reservation) = scheduler.reserve_start(task_name, pending_task.config.avoid_overlapping && !force) else {
+                return Err(anyhow!("Task '{}' is already running and avoids overlapping, use --force to run it anyway", task_name));
+            };
+            (pending_task, reservation, scheduler.config.clone(), scheduler.sqlite_logger.clone(), scheduler.events.clone())
+        };
+
+        let context = RunContext {
+            prev_exit_code: pending_task.last_exit_code,
+            prev_failures: pending_task.consecutive_failures,
+            ..RunContext::new(Utc::now())
+        };
+        // The lock_file and the cluster_lock are also held by other processes, so they apply even with `force`
+        let lock_file = Self::take_lock_file(&pending_task.config).with_context(|| format!("Task '{}' can't run", task_name))?;
+        let cluster_lock =
+            Self::take_cluster_lock(&pending_task.config).await.with_context(|| format!("Task '{}' can't run", task_name))?;
+        let mut active_task = Self::execute_task(mutex, &pending_task.config, &config, &sqlite_logger, &events, context).await?;
+        active_task.lock_file = lock_file;
+        active_task.cluster_lock = cluster_lock;
+        let run_id = active_task.run_id.clone();
+
+        let task_id = Self::add_active_task(mutex, active_task, reservation).await;
+        let (done, finished) = oneshot::channel();
+        Self::wait_for_task(mutex.clone(), task_id, Some(done)).await;
+        Ok((run_id, finished))
+    }
+
+    /// Fires the trigger of the tasks with an `after_task` that matches the finished execution
+    async fn trigger_after_tasks(mutex: &Arc<Mutex<Scheduler>>, task_name: &str, success: bool) {
+        let pending_tasks = { mutex.lock().await.pending_tasks.clone() };
+        for pending_task in pending_tasks {
+            let pending_task = pending_task.lock().await;
+            if let Schedule::AfterTask { task, on } = &pending_task.config.schedule {
+                if task == task_name && on.matches(success) {
+                    debug!(task = pending_task.config.name.as_str(); "Task '{}' triggered by the end of '{}'", pending_task.config.name, task_name);
+                    pending_task.trigger.fire();
+                }
+            }
+        }
+    }
+
+    /// Returns the current time rounded in a way that has no fractional seconds
+    pub fn get_current_datetime_at(timezone: Tz) -> DateTime<Tz> {
+        // Rounds the time, by flooring it to the second, to avoid issues with comparisons of dates and rounding
+        timezone.from_utc_datetime(&Utc::now().naive_utc()).trunc_subsecs(0)
+    }
+
+    /// Gets the current time in the given timezone, with full precision (including fractional seconds)
+    pub fn get_precise_datetime_at(timezone: Tz) -> DateTime<Tz> {
+        timezone.from_utc_datetime(&Utc::now().naive_utc())
+    }
+
+    /// Sleeps until the next execution is about to start, returns the time it's planned at. The sleep doesn't count the
+    /// time the host is suspended, so it can wake up long after the planned time, see [Scheduler::missed_run]
+    async fn sleep_until_task_is_ready(task: &PendingTask) -> DateTime<Tz> {
+        let precise_now = Self::get_precise_datetime_at(task.config.timezone);
+        let now: DateTime<Tz> = Self::get_current_datetime_at(task.config.timezone);
+
+        // Use the current datetime plus 1 second to avoid returning the exact same value
+        let next_run = Self::get_next_execution_time(task, now, true);
+        let wait_time = next_run.signed_duration_since(precise_now);
+
+        debug!(
+            task = task.config.name.as_str();
+            "Task '{}' planned next execution at {} (current time {}, around {} s later)",
+            task.config.name,
+            next_run,
+            now,
+            (wait_time.num_milliseconds() as f32 / 1000.0f32).max(0f32)
+        );
+
+        let duration = if wait_time.num_milliseconds() > 1000 {
+            // Wait the remaining time, minus 1 second, to account for the imprecision of sleep()
+            Duration::from_millis(wait_time.num_milliseconds() as u64 - 1000u64)
+        } else if wait_time.num_milliseconds() > 100 {
+            // Sleep for the remaining time
+            Duration::from_millis(wait_time.num_milliseconds() as u64)
+        } else {
+            // For intervals of less than 100 ms, sleep for 100 ms
+            Duration::from_millis(100)
+        };
+        // A trigger wakes the task up before its next execution time, and a jump of the clock to compute it again. A
+        // run the clock jumped over is missed, see [Scheduler::missed_run]
+        tokio::select! {
+            _ = sleep(duration) => {}
+            _ = task.trigger.notify.notified() => return next_run,
+            _ = clock::clock_changed() => return next_run,
+        }
+
+        // The wall clock went further than the sleep, the host was suspended or the process stopped
+        let drift = Self::get_precise_datetime_at(task.config.timezone).signed_duration_since(precise_now)
+            - TimeDelta::from_std(duration).unwrap_or_default();
+        if drift > TimeDelta::from_std(MISFIRE_THRESHOLD).unwrap_or_default() {
+            warn!(
+                task = task.config.name.as_str();
+                "Task '{}' woke up {} later than expected, the host was suspended or cron-rs was paused",
+                task.config.name,
+                format_duration(drift.to_std().unwrap_or_default())
+            );
+        }
+
+        next_run
+    }
+
+    /// Returns the planned execution if its time passed without the task running, because the scheduler was asleep
+    /// when it was due
+    fn missed_run(task: &PendingTask, planned_run: Option<DateTime<Tz>>) -> Option<DateTime<Tz>> {
+        let planned_run = planned_run?;
+        let now: DateTime<Tz> = Self::get_current_datetime_at(task.config.timezone);
+        if planned_run >= now {
+            return None;
+        }
+
+        // It ran or was skipped already
+        let last_time = task.last_execution_time.into_iter().chain(task.last_skipped_time).max();
+        if last_time.is_some_and(|time| time >= planned_run) {
+            return None;
+        }
+        Some(planned_run)
+    }
+
+    /// Checks if the task is ready for execution right now, returns the time the due execution was scheduled at
+    fn due_run(task: &PendingTask) -> Option<DateTime<Tz>> {
+        let now: DateTime<Tz> = Self::get_current_datetime_at(task.config.timezone);
+
+        // If the last execution was at this time, avoid running it again, wait until at least the next second
+        let last_time = task.last_execution_time.into_iter().chain(task.last_skipped_time).max();
+        if let Some(time) = last_time {
+            if time.timestamp() == now.timestamp() {
+                return None;
+            }
+        }
+
+        let next_scheduled_run = Self::get_next_execution_time(task, now, true);
+        // If the next scheduled run is now, it's due
+        (next_scheduled_run.timestamp() <= now.timestamp()).then_some(next_scheduled_run)
+    }
+
+    /// Checks if the task is running or being started. Executions stay in `active_tasks` until their child is reaped, so
+    /// the process table of the host is not needed, and a reused pid of a finished execution is not mistaken for it
+    fn is_task_running(&self, task_name: &str) -> bool {
+        self.starting_tasks.lock().unwrap().contains_key(task_name) || self.active_tasks.iter().any(|t| t.config.name == task_name)
+    }
+
+    /// Locks the `lock_file` of the task for an execution, the error says why it can't run
+    fn take_lock_file(task: &TaskConfig) -> anyhow::Result<Option<Arc<std::fs::File>>> {
+        let Some(path) = &task.lock_file else { return Ok(None) };
+        match try_lock_file(path) {
+            Ok(Some(lock_file)) => Ok(Some(Arc::new(lock_file))),
+            Ok(None) => Err(anyhow!("{} is locked by another process", path.display())),
+            Err(e) => Err(anyhow!("failed to lock {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Takes the `cluster_lock` of the task for an execution, the error says why it can't run
+    async fn take_cluster_lock(task: &TaskConfig) -> anyhow::Result<Option<Arc<ClusterLock>>> {
+        let Some(url) = &task.cluster_lock else { return Ok(None) };
+        Ok(Some(Arc::new(ClusterLock::acquire(url, &task.name).await?)))
+    }
+
+    /// Reserves the start of an execution, it must be taken while the scheduler is locked, like the check. With
+    /// `exclusive` it returns None if the task is already running
+    fn reserve_start(&self, task_name: &str, exclusive: bool) -> Option<StartReservation> {
+        if exclusive && self.is_task_running(task_name) {
+            return None;
+        }
+        *self.starting_tasks.lock().unwrap().entry(task_name.to_string()).or_default() += 1;
+        Some(StartReservation { task_name: task_name.to_string(), starting_tasks: self.starting_tasks.clone() })
+    }
+
+    /// Marks the due execution as skipped, so the task waits for its next execution instead of staying due. Returns the
+    /// updated task
+    async fn skip_run(pending_task_mutex: &Arc<Mutex<PendingTask>>) -> PendingTask {
+        let mut pending_task = pending_task_mutex.lock().await;
+        pending_task.last_skipped_time = Some(Utc::now());
+        pending_task.clone()
+    }
+
+    /// Reports the skipped execution once, `skipped_run` holds the scheduled time of the last one reported
+    async fn on_task_skipped(
+        mutex: &Arc<Mutex<Scheduler>>,
+        task: &PendingTask,
+        scheduled_run: DateTime<Tz>,
+        skipped_run: &mut Option<DateTime<Tz>>,
+        reason: &str,
+    ) {
+        if *skipped_run == Some(scheduled_run) {
+            return;
+        }
+        *skipped_run = Some(scheduled_run);
+
+        let _ = mutex.lock().await.events.send(SchedulerEvent::TaskSkipped {
+            task_name: task.config.name.clone(),
+            reason: reason.to_string(),
+            scheduled_time: scheduled_run.with_timezone(&Utc),
+        });
+    }
+
+    /// Spawns a subprocess to execute the task
+    async fn execute_task(
+        mutex: &Arc<Mutex<Scheduler>>,
+        task_config: &Arc<TaskConfig>,
+        config: &Config,
+        sqlite_logger: &Option<SqliteLogger>,
+        events: &broadcast::Sender<SchedulerEvent>,
+        context: RunContext,
+    ) -> anyhow::Result<ActiveTask> {
+        let clock_time: DateTime<Utc> = Utc::now();
+        let run_id = context.run_id.clone();
+        let stdout_path = task_config.stdout_path(&run_id, clock_time);
+        let stderr_path = task_config.stderr_path(&run_id, clock_time);
+
+        for (name, path) in [("stdout", &stdout_path), ("stderr", &stderr_path)] {
+            if let Some(dir) = path.parent().filter(|dir| !dir.exists()) {
+                if let Err(e) = tokio::fs::create_dir_all(dir).await {
+                    return Err(anyhow!(
+                        "Failed to create the {} directory {} for task '{}': {}",
+                        name,
+                        dir.to_string_lossy(),
+                        task_config.name,
+                        e
+                    ));
+                }
+            }
+        }
+
+        let stdout = match task_config.open_stdout(&run_id, clock_time) {
+            Ok(file) => file,
+            Err(e) => {
+                return Err(anyhow!(
+                    "Failed to create {} for task '{}': {}",
+                    stdout_path.to_string_lossy(),
+                    task_config.name,
+                    e
+                ));
+            }
+        };
+        // With combine_output both streams share the file and its position
+        let stderr = if task_config.combine_output { stdout.try_clone() } else { task_config.open_stderr(&run_id, clock_time) };
+        let stderr = match stderr {
+            Ok(file) => file,
+            Err(e) => {
+                return Err(anyhow!(
+                    "Failed to create {} for task '{}': {}",
+                    stderr_path.to_string_lossy(),
+                    task_config.name,
+                    e
+                ));
+            }
+        };
+
+        // Files in append mode keep the output of previous executions
+        let stdout_offset = stdout.metadata().map(|m| m.len()).unwrap_or(0);
+        let stderr_offset = stderr.metadata().map(|m| m.len()).unwrap_or(0);
+
+        // Record debug information, to show in case of failure
+        let mut debug_info = String::new();
+
+        debug_info.push_str(&format!("Run id: {}\n", run_id));
+        debug_info.push_str(&format!("Cmd: {}\n", executor::shell_command(task_config)));
+
+        // Closures and pipelines run inside the scheduler, the options of the process don't apply to them
+        let closure = match &task_config.executor {
+            Executor::Closure(task_fn) => Some(task_fn.clone()),
+            Executor::Pipeline(pipeline) => {
+                let (mutex, pipeline) = (mutex.clone(), pipeline.clone());
+                Some(TaskFn::new(move || Self::run_pipeline(mutex.clone(), pipeline.clone())))
+            }
+            _ => None,
+        };
+        if let Some(task_fn) = closure {
+            let handle = executor::spawn_closure(&task_fn, stderr);
+            let task_id = ACTIVE_TASK_ID_COUNTER.fetch_add(1, Ordering::Relaxed) as u32;
+            info!(task = task_config.name.as_str(), run_id = run_id.as_str(); "Task '{}' (run {}) started", task_config.name, run_id);
+            Self::log_execution_attempt(task_config, task_id, 0, clock_time, sqlite_logger).await;
+
+            return Ok(ActiveTask {
+                id: task_id,
+                config: task_config.clone(),
+                pid: 0,
+                start_instant: Instant::now(),
+                start_time: clock_time,
+                closure: Some(handle.abort_handle()),
+                child: Arc::new(Mutex::new(Execution::Closure(handle))),
+                run_id,
+                context: context.clone(),
+                debug_info: debug_info.trim().to_string(),
+                time_limit: task_config.time_limit,
+                stdout_path,
+                stderr_path,
+                stdout_offset,
+                stderr_offset,
+                output_forwarders: Arc::new(Mutex::new(vec![])),
+                user: None,
+                cgroup: None,
+                lock_file: None,
+                cluster_lock: None,
+                monitor: None,
+                adopted: false,
+            });
+        }
+
+        // Shell, environment variables and working directory
+        let (mut cmd, env_input) = executor::command(task_config, &context)?;
+        if let Some(env) = &task_config.env {
+            for (key, value) in env {
+                debug_info.push_str(&format!("Env '{}' => '{}'\n", key, value));
+            }
+            debug!(
+                "Set {} environment variables for task '{}'",
+                env.len(),
+                task_config.name
+            );
+        }
+
+        if let Some(root) = &task_config.chroot {
+            debug_info.push_str(&format!("Chroot '{}'\n", root));
+        }
+        if let Some(dir) = &task_config.working_directory {
+            debug_info.push_str(&format!("Working dir '{}'\n", dir));
+            debug!("Set runtime directory to '{}' for task '{}'", dir, task_config.name);
+        }
+
+        // Set input redirection
+        if let Some(TaskStdin::File(path)) = &task_config.stdin {
+            debug_info.push_str(&format!("Stdin '{}'\n", path));
+        }
+        let stdin_input = match executor::open_stdin(task_config, env_input) {
+            Ok((stdin, input)) => {
+                cmd.stdin(stdin);
+                input
+            }
+            Err(e) => return Err(anyhow!("Failed to open the stdin of task '{}': {}", task_config.name, e)),
+        };
+
+        // The before commands write to the same files, ahead of the output of the main command
+        let hook_files = if task_config.before.is_empty() { None } else { Some((stdout.try_clone()?, stderr.try_clone()?)) };
+
+        // Set output redirection
+        debug_info.push_str(&format!("Stdio '{}'\n", stdout_path.to_string_lossy()));
+        debug_info.push_str(&format!("Stderr '{}'\n", stderr_path.to_string_lossy()));
+        // With log_output the scheduler reads the output and writes it to the files itself
+        let mut output_files = None;
+        if task_config.log_output {
+            cmd.stdout(Stdio::piped());
+            cmd.stderr(Stdio::piped());
+            output_files = Some((stdout, stderr));
+        } else {
+            cmd.stdout(Stdio::from(stdout));
+            cmd.stderr(Stdio::from(stderr));
+        }
+
+        // Run as another user if specified
+        let user = match &task_config.run_as {
+            // Only available on Unix-like systems
+            #[cfg(unix)]
+            Some(run_as) => {
+                let (uid, user_str, gid, group_str) = match Self::get_uid_and_gid(run_as) {
+                    Ok((uid, user_str, gid, group_str)) => (uid, user_str, gid, group_str),
+                    Err(e) => {
+                        return Err(anyhow!(
+                            "Failed to get uid and gid for task '{}': {}",
+                            task_config.name,
+                            e
+                        ));
+                    }
+                };
+
+                // uid and gid are opaque types, there is no operation to convert them to u32, but they deref() as u32, so add(0) works
+                debug_info.push_str(&format!("Uid {} '{}'\n", uid, user_str));
+                debug_info.push_str(&format!("Gid {} '{}'\n", gid, group_str));
+                set_user(&mut cmd, uid, gid, task_config.login_env)?;
+                debug!(
+                    "Task '{}' will run as user '{}' and group '{}'",
+                    task_config.name, user_str, group_str
+                );
+                Some((uid, gid))
+            }
+            // Running it as the user of the scheduler instead could give it more access than it should have
+            #[cfg(not(unix))]
+            Some(run_as) => {
+                return Err(anyhow!(
+                    "Task '{}' cannot run as '{}', unsupported on this platform",
+                    task_config.name, run_as
+                ));
+            }
+            None => None,
+        };
+
+        if let Some((stdout, stderr)) = &hook_files {
+            if let Err(reason) = hooks::run_before_hooks(task_config, &run_id, stdout, stderr, user).await {
+                let details = TaskExecutionDetails {
+                    task_name: task_config.name.to_string(),
+                    task_id: 0,
+                    run_id: run_id.clone(),
+                    pid: 0,
+                    exit_code: -1,
+                    start_time: clock_time,
+                    duration: Duration::default(),
+                    error_message: format!("Task '{}' aborted, {}", task_config.name, reason),
+                    debug_info: debug_info.trim().to_string(),
+                    stdout: read_output_from(&stdout_path, stdout_offset).await,
+                    stderr: read_output_from(&stderr_path, stderr_offset).await,
+                    peak_memory: None,
+                    usage: None,
+                    context: ExecutionContext::new(task_config, &context),
+                };
+
+                Self::on_task_failure(&details, &config.alerts, &task_config.on_failure, sqlite_logger, events).await;
+
+                let error = format!("Task '{}' (run {}) aborted, {}", task_config.name, run_id, reason);
+                return Err(ExecutionFailed(error).into());
+            }
+        }
+
+        let now = Instant::now();
+
+        // In its own process group, so the processes it starts can be stopped with it
+        set_process_group(cmd.as_std_mut());
+        set_resource_limits(&mut cmd, task_config.limits)
+            .map_err(|e| anyhow!("Task '{}' (run {}) failed to set its limits: {}", task_config.name, run_id, e))?;
+        set_process_priority(&mut cmd, &task_config.priority);
+
+        // A task that can't get its cgroup doesn't run without the limits
+        let cgroup = match &task_config.cgroup {
+            Some(limits) => {
+                let name = format!("{}-{}", sanitise_file_name::sanitise(&task_config.name), run_id);
+                let cgroup = ExecutionCgroup::create(&config.cgroup_root_path(), &name, limits)
+                    .map_err(|e| anyhow!("Task '{}' (run {}) failed to create its cgroup: {:#}", task_config.name, run_id, e))?;
+                Some(Arc::new(cgroup))
+            }
+            None => None,
+        };
+
+        match cmd.spawn() {
+            Ok(mut child) => {
+                let pid = child.id().unwrap_or(0);
+                let task_id = ACTIVE_TASK_ID_COUNTER.fetch_add(1, Ordering::Relaxed) as u32;
+                info!(
+                    task = task_config.name.as_str(), run_id = run_id.as_str();
+                    "Task '{}' (run {}) started with PID: {}", task_config.name, run_id, pid
+                );
+
+                if let Some(cgroup) = &cgroup {
+                    if let Err(e) = cgroup.add_process(pid) {
+                        signal_process_group(pid, StopSignal::Sigkill);
+                        let _ = child.wait().await;
+                        cgroup.remove();
+                        return Err(anyhow!("Task '{}' (run {}) stopped, {:#}", task_config.name, run_id, e));
+                    }
+                }
+
+                if let Some(input) = stdin_input {
+                    input.write(&mut child, &task_config.name);
+                }
+
+                let mut output_forwarders = vec![];
+                if let Some((stdout, stderr)) = output_files {
+                    let name = task_config.name.clone();
+                    if let Some(pipe) = child.stdout.take() {
+                        output_forwarders.push(forward_output(pipe, stdout, name.clone(), run_id.clone(), log::Level::Info));
+                    }
+                    if let Some(pipe) = child.stderr.take() {
+                        output_forwarders.push(forward_output(pipe, stderr, name, run_id.clone(), log::Level::Warn));
+                    }
+                }
+
+                Self::log_execution_attempt(task_config, task_id, pid, clock_time, sqlite_logger).await;
+
+                Ok(ActiveTask {
+                    id: task_id,
+                    config: task_config.clone(),
+                    pid,
+                    start_instant: now,
+                    start_time: clock_time,
+                    child: Arc::new(Mutex::new(Execution::Process(child))),
+                    closure: None,
+                    run_id,
+                    context: context.clone(),
+                    debug_info: debug_info.trim().to_string(),
+                    time_limit: task_config.time_limit,
+                    stdout_path: stdout_path.clone(),
+                    stdout_offset,
+                    stderr_offset,
+                    stderr_path: stderr_path.clone(),
+                    output_forwarders: Arc::new(Mutex::new(output_forwarders)),
+                    user,
+                    cgroup,
+                    lock_file: None,
+                    cluster_lock: None,
+                    monitor: None,
+                    adopted: false,
+                })
+            }
+            Err(e) => {
+                if let Some(cgroup) = &cgroup {
+                    cgroup.remove();
+                }
+                if let Some(run_as) = task_config.run_as.as_deref().filter(|_| e.to_string().contains("Operation not permitted")) {
+                    debug_info.push_str(&format!(
+                        "Note: The task was executed with run_as '{}', make sure the current user '{}' has permission to run as that user",
+                        run_as,
+                        current_username().unwrap_or_else(|| "<unknown>".to_string())
+                    ));
+                }
+
+                let details = TaskExecutionDetails {
+                    task_name: task_config.name.to_string(),
+                    task_id: 0,
+                    run_id: run_id.clone(),
+                    pid: 0,
+                    exit_code: -1,
+                    start_time: clock_time,
+                    duration: Duration::default(),
+                    error_message: format!("Task '{}' failed to start", task_config.name),
+                    debug_info: debug_info.trim().to_string(),
+                    stdout: String::new(),
+                    stderr: e.to_string(),
+                    peak_memory: None,
+                    usage: None,
+                    context: ExecutionContext::new(task_config, &context),
+                };
+
+                Self::on_task_failure(&details, &config.alerts, &task_config.on_failure, sqlite_logger, events).await;
+
+                Err(ExecutionFailed(format!(
+                    "Task '{}' failed to start: {}, Debug info:\n{}",
+                    task_config.name,
+                    e,
+                    debug_info
+                ))
+                .into())
+            }
+        }
+    }
+
+    /// Log execution attempt to SQLite
+    async fn log_execution_attempt(
+        task_config: &TaskConfig,
+        task_id: u32,
+        pid: u32,
+        start_time: DateTime<Utc>,
+        sqlite_logger: &Option<SqliteLogger>,
+    ) {
+        let Some(sqlite_logger) = sqlite_logger else { return };
+        let attempt = ExecutionAttempt {
+            task_name: task_config.name.clone(),
+            task_id,
+            pid,
+            cmd: task_config.cmd.clone(),
+            start_time,
+            timezone: task_config.timezone.to_string(),
+            working_directory: task_config.working_directory.clone(),
+            shell: task_config.shell.clone(),
+            run_as: task_config.run_as.clone(),
+            time_limit: task_config.time_limit,
+        };
+
+        if let Err(e) = sqlite_logger.log_execution_attempt(&attempt).await {
+            error!("Failed to log execution attempt for task '{}': {}", task_config.name, e);
+        }
+    }
+
+    /// Handle the task completion, returns whether the execution met the success criteria of the task
+    async fn on_task_completed(
+        task: &ActiveTask,
+        status: ExitStatus,
+        usage: Option<ResourceUsage>,
+        timed_out: bool,
+        config: &Config,
+        sqlite_logger: &Option<SqliteLogger>,
+        events: &broadcast::Sender<SchedulerEvent>,
+    ) -> bool {
+        let exit_code = status.code().unwrap_or(-1);
+        let execution_time = task.start_instant.elapsed();
+        join_output_forwarders(std::mem::take(&mut *task.output_forwarders.lock().await)).await;
+
+        let mut peak_memory = None;
+        if let Some(cgroup) = task.cgroup.clone() {
+            peak_memory = cgroup.peak_memory();
+            // Waits a bit for the processes left in the cgroup to be killed
+            let _ = tokio::task::spawn_blocking(move || cgroup.remove()).await;
+        }
+
+        let mut details = TaskExecutionDetails {
+            task_name: task.config.name.to_string(),
+            task_id: task.id,
+            run_id: task.run_id.clone(),
+            pid: task.pid,
+            exit_code,
+            start_time: task.start_time,
+            duration: execution_time,
+            error_message: String::new(),
+            debug_info: task.debug_info.clone(),
+            stdout: read_output_from(&task.stdout_path, task.stdout_offset).await,
+            // With combine_output all the output is in stdout
+            stderr: if task.config.combine_output {
+                String::new()
+            } else {
+                read_output_from(&task.stderr_path, task.stderr_offset).await
+            },
+            peak_memory,
+            usage,
+            context: ExecutionContext::new(&task.config, &task.context),
+        };
+
+        // The task may exit cleanly after the SIGTERM, it still failed
+        let result = match task.config.time_limit {
+            Some(time_limit) if timed_out => Err(format!("exceeded the time limit of {} seconds", time_limit)),
+            _ => task.config.success.check(status.code(), &details.stdout, &details.stderr),
+        };
+        if let Err(reason) = &result {
+            details.error_message = format!("Task '{}' failed, {}", task.config.name, reason);
+            error!(
+                task = task.config.name.as_str(), run_id = task.run_id.as_str(), exit_code;
+                "Task '{}' (run {}) failed, {} ({})", task.config.name, task.run_id, reason, status
+            );
+
+            if let Some(cmd) = &task.config.on_failure_cmd {
+                hooks::run_failure_cmd(&task.config, cmd, &details, &task.stdout_path, &task.stderr_path, task.user).await;
+            }
+
+            Self::on_task_failure(&details, &config.alerts, &task.config.on_failure, sqlite_logger, events).await;
+            if let Some(sentry) = config.sentry.clone() {
+                let details = details.clone();
+                if let Err(e) = queue_alert(move || report_task_failure(&sentry, &details)).await {
+                    error!("Failed to report task '{}' to Sentry: {:#}", task.config.name, e);
+                }
+            }
+        } else {
+            info!(
+                task = task.config.name.as_str(), run_id = task.run_id.as_str(), exit_code;
+                "Task '{}' (run {}) finished with status: {}, elapsed {}{}",
+                task.config.name,
+                task.run_id,
+                status,
+                format_duration(execution_time),
+                peak_memory.map(|bytes| format!(", peak memory {}", format_size(bytes))).unwrap_or_default()
+            );
+
+            Self::on_task_success(&details, &config.alerts, &task.config.on_success, sqlite_logger, events).await;
+        }
+
+        result.is_ok()
+    }
+
+    /// Notify the user about task failure
+    async fn on_task_failure(
+        details: &TaskExecutionDetails,
+        alerts: &AlertConfig,
+        task_on_failure: &[Alert],
+        sqlite_logger: &Option<SqliteLogger>,
+        events: &broadcast::Sender<SchedulerEvent>,
+    ) {
+        let _ = events.send(SchedulerEvent::TaskFailed {
+            task_name: details.task_name.clone(),
+            run_id: details.run_id.clone(),
+            exit_code: details.exit_code,
+            duration_seconds: details.duration.as_secs_f64(),
+            error: details.error_message.clone(),
+        });
+        for alert in &alerts.on_failure {
+            let result = Self::queue_task_alert(alert, details).await;
+            if let Err(e) = &result {
+                error!("Failed to send alert for task '{}': {}", details.task_name, e);
+            }
+            Self::on_alert_sent(alert, details, &result, sqlite_logger, events).await;
+        }
+        // Like [AlertConfig::escalate], through the queue
+        let steps = alerts.escalation_steps(details.context.prev_failures + 1);
+        let mut delivered = steps.is_empty();
+        for step in steps {
+            let result = Self::queue_task_alert(&step.alert, details).await;
+            if let Err(e) = &result {
+                error!("Failed to send escalation alert for task '{}', trying the next step: {}", details.task_name, e);
+            }
+            Self::on_alert_sent(&step.alert, details, &result, sqlite_logger, events).await;
+            if result.is_ok() {
+                delivered = true;
+                break;
+            }
+        }
+        if !delivered {
+            error!("None of the steps of the escalation could send the alert for task '{}'", details.task_name);
+        }
+        for alert in task_on_failure {
+            let result = Self::queue_task_alert(alert, details).await;
+            if let Err(e) = &result {
+                error!(
+                    "Failed to send task-specific alert for task '{}': {}",
+                    details.task_name, e
+                );
+            }
+            Self::on_alert_sent(alert, details, &result, sqlite_logger, events).await;
+        }
+
+        if let Some(sqlite_logger) = sqlite_logger {
+            let failure = ExecutionFailure {
+                task_name: details.task_name.clone(),
+                task_id: details.task_id,
+                pid: details.pid,
+                start_time: details.start_time,
+                end_time: details.start_time + chrono::Duration::from_std(details.duration).unwrap_or_default(),
+                duration_seconds: details.duration.as_secs_f64(),
+                exit_code: if details.exit_code == -1 {
+                    None
+                } else {
+                    Some(details.exit_code)
+                },
+                error_message: details.error_message.clone(),
+                failure_reason: "Task execution failed".to_string(),
+                usage: details.usage,
+            };
+
+            if let Err(e) = sqlite_logger.log_execution_failure(&failure).await {
+                error!(
+                    "Failed to log execution failure for task '{}': {}",
+                    details.task_name, e
+                );
+            }
+        }
+    }
+
+    /// Handles an execution that [Scheduler::execute_task] couldn't start, the failures it already reported are only
+    /// logged
+    async fn on_start_error(mutex: &Arc<Mutex<Scheduler>>, task_name: &str, error: anyhow::Error) {
+        if error.is::<ExecutionFailed>() {
+            error!("{}", error);
+        } else {
+            Self::on_internal_error(mutex, Some(task_name), error).await;
+        }
+    }
+
+    /// Logs a failure of the scheduler itself, not of an execution, and sends the `on_internal_error` alerts. The
+    /// scheduler keeps running
+    async fn on_internal_error(mutex: &Arc<Mutex<Scheduler>>, task_name: Option<&str>, error: anyhow::Error) {
+        let error = format!("{:#}", error);
+        error!("Internal error: {}", error);
+        let (alerts, sentry) = {
+            let scheduler = mutex.lock().await;
+            let _ = scheduler.events.send(SchedulerEvent::InternalError { task_name: task_name.map(str::to_string), error: error.clone() });
+            (scheduler.config.alerts.on_internal_error.clone(), scheduler.config.sentry.clone())
+        };
+        if let Some(sentry) = sentry {
+            let (task_name, error) = (task_name.map(str::to_string), error.clone());
+            if let Err(e) = queue_alert(move || report_internal_error(&sentry, task_name.as_deref(), &error)).await {
+                error!("Failed to report the internal error to Sentry: {:#}", e);
+            }
+        }
+        for alert in alerts {
+            let kind = alert.kind();
+            let (task_name, error) = (task_name.map(str::to_string), error.clone());
+            if let Err(e) = queue_alert(move || send_internal_error_alert(&alert, task_name.as_deref(), &error)).await {
+                error!("Failed to send the internal error with the {} alert: {:#}", kind, e);
+            }
+        }
+    }
+
+    /// Sends an alert of an execution through the alert queue
+    async fn queue_task_alert(alert: &Alert, details: &TaskExecutionDetails) -> anyhow::Result<()> {
+        let (alert, details) = (alert.clone(), details.clone());
+        queue_alert(move || send_alert(&alert, &details)).await
+    }
+
+    /// Records the alert in the events and the execution log, to check later whether it went out
+    async fn on_alert_sent(
+        alert: &Alert,
+        details: &TaskExecutionDetails,
+        result: &anyhow::Result<()>,
+        sqlite_logger: &Option<SqliteLogger>,
+        events: &broadcast::Sender<SchedulerEvent>,
+    ) {
+        let error = result.as_ref().err().map(|e| format!("{:#}", e));
+        let _ = events.send(SchedulerEvent::AlertSent {
+            task_name: details.task_name.clone(),
+            run_id: details.run_id.clone(),
+            alert: alert.kind().to_string(),
+            target: alert.target(),
+            error: error.clone(),
+        });
+        if let Some(sqlite_logger) = sqlite_logger {
+            let attempt = AlertAttempt {
+                task_name: details.task_name.clone(),
+                run_id: details.run_id.clone(),
+                alert: alert.kind().to_string(),
+                target: alert.target(),
+                time: Utc::now(),
+                error,
+            };
+            if let Err(e) = sqlite_logger.log_alert_attempt(&attempt).await {
+                error!("Failed to log the alert of task '{}': {}", details.task_name, e);
+            }
+        }
+    }
+
+    /// Notify the user about task success
+    async fn on_task_success(
+        details: &TaskExecutionDetails,
+        alerts: &AlertConfig,
+        task_on_success: &[Alert],
+        sqlite_logger: &Option<SqliteLogger>,
+        events: &broadcast::Sender<SchedulerEvent>,
+    ) {
+        let _ = events.send(SchedulerEvent::TaskFinished {
+            task_name: details.task_name.clone(),
+            run_id: details.run_id.clone(),
+            exit_code: details.exit_code,
+            duration_seconds: details.duration.as_secs_f64(),
+        });
+        for alert in &alerts.on_success {
+            let result = Self::queue_task_alert(alert, details).await;
+            if let Err(e) = &result {
+                error!("Failed to send alert for task '{}': {}", details.task_name, e);
+            }
+            Self::on_alert_sent(alert, details, &result, sqlite_logger, events).await;
+        }
+        for alert in task_on_success {
+            let result = Self::queue_task_alert(alert, details).await;
+            if let Err(e) = &result {
+                error!(
+                    "Failed to send task-specific alert for task '{}': {}",
+                    details.task_name, e
+                );
+            }
+            Self::on_alert_sent(alert, details, &result, sqlite_logger, events).await;
+        }
+
+        if let Some(sqlite_logger) = sqlite_logger {
+            let success = ExecutionSuccess {
+                task_name: details.task_name.clone(),
+                task_id: details.task_id,
+                pid: details.pid,
+                start_time: details.start_time,
+                end_time: details.start_time + chrono::Duration::from_std(details.duration).unwrap_or_default(),
+                duration_seconds: details.duration.as_secs_f64(),
+                exit_code: details.exit_code,
+                usage: details.usage,
+            };
+
+            if let Err(e) = sqlite_logger.log_execution_success(&success).await {
+                error!(
+                    "Failed to log execution success for task '{}': {}",
+                    details.task_name, e
+                );
+            }
+        }
+    }
+
+    /// Calculate the next date and time for the task to run
+    /// current_date: must be rounded to the second, use Self::get_current_datetime_at(timezone) to get it
+    pub fn get_next_execution_time(task: &PendingTask, current_date: DateTime<Tz>, allow_now: bool) -> DateTime<Tz> {
+        match &task.config.schedule {
+            Schedule::Every { interval, aligned, window } => {
+                let next_date = if *aligned {
+                    // Aligned runs only depend on the calendar, not on the previous execution
+                    Self::get_next_aligned_time(current_date, *interval, allow_now)
+                } else if let Some(last_execution_time) = task.last_execution_time.into_iter().chain(task.last_skipped_time).max() {
+                    // Bad input, assume no previous run
+                    if current_date.timestamp() < last_execution_time.timestamp() {
+                        return current_date;
+                    }
+
+                    let last_execution_in_tz = last_execution_time.with_timezone(&task.config.timezone).trunc_subsecs(0);
+
+                    Self::add_interval(last_execution_in_tz, *interval)
+                } else {
+                    // First run
+                    current_date
+                };
+
+                // The previous run was more than one interval ago, so the next one is overdue
+                let next_date = next_date.max(current_date);
+
+                let next_date = if !allow_now && next_date == current_date {
+                    Self::add_interval(next_date, *interval)
+                } else {
+                    next_date
+                };
+
+                // Outside the active window, wait until the window opens again
+                let never = || Self::never(task.config.timezone);
+                match window {
+                    Some(window) => window.next_open_time(next_date).unwrap_or_else(never),
+                    None => next_date,
+                }
+            }
+            Schedule::Period { period } => {
+                // Due until it runs once in the current period
+                let last_time = task.last_execution_time.into_iter().chain(task.last_skipped_time).max();
+                match last_time {
+                    Some(last_time) if period.contains(current_date, last_time.with_timezone(&task.config.timezone)) => {
+                        period.next_start_time(current_date)
+                    }
+                    _ => current_date,
+                }
+            }
+            Schedule::OnChange { .. } | Schedule::AfterTask { .. } => {
+                // Due until it runs or skips after the trigger
+                let last_time = task.last_execution_time.into_iter().chain(task.last_skipped_time).max();
+                match task.trigger.time() {
+                    Some(triggered) if last_time.is_none_or(|last_time| triggered > last_time) => {
+                        triggered.with_timezone(&task.config.timezone).min(current_date)
+                    }
+                    _ => Self::never(task.config.timezone),
+                }
+            }
+            Schedule::When { time, min_interval } => {
+                let mut curr = current_date;
+                let mut allow_now = allow_now;
+
+                // Don't fire again until min_interval has passed since the last execution
+                if let (Some(min_interval), Some(last_execution_time)) = (min_interval, task.last_execution_time) {
+                    let earliest_date =
+                        Self::add_interval(last_execution_time.with_timezone(&task.config.timezone).trunc_subsecs(0), *min_interval);
+
+                    if earliest_date > current_date {
+                        curr = earliest_date;
+                        allow_now = true;
+                    }
+                }
+
+                match Self::find_next_matching_time(time, curr, allow_now) {
+                    Some(next_date) => next_date,
+                    None => {
+                        // The task never runs again, its loop sleeps until the config is reloaded
+                        error!("Task '{}' has no valid next execution time", task.config.name);
+                        Self::never(task.config.timezone)
+                    }
+                }
+            }
+        }
+    }
+
+    /// The next execution of the task, like [Scheduler::get_next_execution_time], None if the task waits for a trigger
+    /// or never runs again
+    pub fn get_next_run(task: &PendingTask, current_date: DateTime<Tz>) -> Option<DateTime<Tz>> {
+        let next_run = Self::get_next_execution_time(task, current_date, true);
+        (next_run != Self::never(current_date.timezone())).then_some(next_run)
+    }
+
+    /// Next execution time of a task that doesn't run again on its own
+    fn never(timezone: Tz) -> DateTime<Tz> {
+        // In UTC if the timezone skips that time
+        timezone.from_local_datetime(&NEVER).latest().unwrap_or_else(|| timezone.from_utc_datetime(&NEVER))
+    }
+
+    /// The date after the interval, [Scheduler::never] if it's out of the range of the dates
+    fn add_interval(date: DateTime<Tz>, interval: Duration) -> DateTime<Tz> {
+        TimeDelta::from_std(interval)
+            .ok()
+            .and_then(|interval| date.checked_add_signed(interval))
+            .unwrap_or_else(|| Self::never(date.timezone()))
+    }
+
+    /// Search the next date that matches the pattern, starting from `curr`. Each field takes its next valid value from
+    /// the year down to the second, and a field without values left carries over to the next unit with the smaller
+    /// fields reset. Returns None if the pattern never matches again
+    pub fn find_next_matching_time(time: &TimePattern, curr: DateTime<Tz>, allow_now: bool) -> Option<DateTime<Tz>> {
+        let timezone = curr.timezone();
+        let start = if allow_now { curr } else { curr + TimeDelta::seconds(1) };
+        let mut t = start.naive_local().with_nanosecond(0)?;
+
+        loop {
+            // The calendar repeats every 400 years, a pattern without a match in that time never matches
+            if matches!(time.year, TimePatternField::Any) && t.year() > start.year() + 400 {
+                return None;
+            }
+
+            let year = time.year.get_next_valid_value(t.year() as u32, MAX_YEAR)?;
+            if year != t.year() as u32 {
+                t = NaiveDate::from_ymd_opt(year as i32, 1, 1)?.and_time(NaiveTime::MIN);
+            }
+
+            let Some(month) = time.month.get_next_valid_value(t.month(), 12) else {
+                t = NaiveDate::from_ymd_opt(t.year() + 1, 1, 1)?.and_time(NaiveTime::MIN);
+                continue;
+            };
+            if month != t.month() {
+                t = NaiveDate::from_ymd_opt(t.year(), month, 1)?.and_time(NaiveTime::MIN);
+            }
+
+            // The day of the month and the day of the week must match together
+            let days_in_month = Self::get_num_of_days_in_month(t.month(), t.year());
+            let mut day = time.day.get_next_valid_value(t.day(), days_in_month);
+            while let Some(d) = day {
+                if time.matches_date(t.date().with_day(d)?) {
+                    break;
+                }
+                day = time.day.get_next_valid_value(d + 1, days_in_month);
+            }
+            let Some(day) = day else {
+                t = Self::first_day_of_next_month(t.date())?.and_time(NaiveTime::MIN);
+                continue;
+            };
+            if day != t.day() {
+                t = t.date().with_day(day)?.and_time(NaiveTime::MIN);
+            }
+
+            let Some(hour) = time.hour.get_next_valid_value(t.hour(), 23) else {
+                t = t.date().succ_opt()?.and_time(NaiveTime::MIN);
+                continue;
+            };
+            if hour != t.hour() {
+                t = t.date().and_hms_opt(hour, 0, 0)?;
+            }
+
+            let Some(minute) = time.minute.get_next_valid_value(t.minute(), 59) else {
+                t = t.date().and_hms_opt(t.hour(), 0, 0)? + TimeDelta::hours(1);
+                continue;
+            };
+            if minute != t.minute() {
+                t = t.date().and_hms_opt(t.hour(), minute, 0)?;
+            }
+
+            let Some(second) = time.second.get_next_valid_value(t.second(), 59) else {
+                t = t.date().and_hms_opt(t.hour(), t.minute(), 0)? + TimeDelta::minutes(1);
+                continue;
+            };
+            t = t.with_second(second)?;
+
+            // A local time repeated by a DST change runs once, in its first occurrence that isn't in the past, and a
+            // time skipped by a DST gap doesn't run that day
+            let next_date = match timezone.from_local_datetime(&t) {
+                LocalResult::Single(date) => Some(date),
+                LocalResult::Ambiguous(first, second) => [first, second].into_iter().find(|date| *date >= start),
+                LocalResult::None => None,
+            };
+            match next_date {
+                Some(date) if date >= start => return Some(date),
+                _ => t += TimeDelta::seconds(1),
+            }
+        }
+    }
+
+    /// Calculate the next date that is a multiple of the interval in the task's timezone, counting from a Monday at
+    /// midnight, so hourly intervals start at minute 0, daily intervals at midnight and weekly intervals on Monday
+    pub(crate) fn get_next_aligned_time(current_date: DateTime<Tz>, interval: Duration, allow_now: bool) -> DateTime<Tz> {
+        let tick_len = (interval.as_secs() as i64).max(1);
+        let reference = NaiveDate::from_ymd_opt(1970, 1, 5).unwrap().and_time(NaiveTime::MIN);
+
+        let elapsed = current_date.naive_local().signed_duration_since(reference).num_seconds();
+        let mut ticks = elapsed.div_euclid(tick_len);
+        if !allow_now || elapsed.rem_euclid(tick_len) != 0 {
+            ticks += 1;
+        }
+
+        loop {
+            let candidate = reference + TimeDelta::seconds(ticks * tick_len);
+
+            // Skip local times that don't exist (DST gap) or that were already used (DST overlap)
+            match current_date.timezone().from_local_datetime(&candidate).earliest() {
+                Some(date) if date > current_date || (allow_now && date == current_date) => return date,
+                _ => ticks += 1,
+            }
+        }
+    }
+
+    /// Parse the user and group from the run_as string and return their UID and GID
+    #[cfg(unix)]
+    fn get_uid_and_gid(run_as: &str) -> anyhow::Result<(u32, String, u32, String)> {
+        let (user_str, group_str) = run_as.split_once(':').unwrap_or((run_as, run_as));
+        let users = Users::new_with_refreshed_list();
+
+        let uid = users
+            .list()
+            .iter()
+            .find(|u| u.name() == user_str || u.id().to_string() == user_str)
+            .map(|user| user.id());
+
+        let Some(uid) = uid else {
+            return Err(anyhow::anyhow!("User '{}' not found", user_str));
+        };
+
+        let groups = Groups::new_with_refreshed_list();
+        let gid = groups
+            .list()
+            .iter()
+            .find(|g| g.name() == group_str || g.id().to_string() == group_str)
+            .map(|group| group.id());
+
+        let Some(gid) = gid else {
+            return Err(anyhow::anyhow!("Group '{}' not found", group_str));
+        };
+
+        Ok((
+            uid.add(0u32),
+            user_str.to_string(),
+            gid.add(0u32),
+            group_str.to_string(),
+        ))
+    }
+
+    fn first_day_of_next_month(date: NaiveDate) -> Option<NaiveDate> {
+        match date.month() {
+            12 => NaiveDate::from_ymd_opt(date.year() + 1, 1, 1),
+            month => NaiveDate::from_ymd_opt(date.year(), month + 1, 1),
+        }
+    }
+
+    /// Get the number of days in a month, taking into account leap years, the month value is 1-based
+    fn get_num_of_days_in_month(mut month: u32, mut year: i32) -> u32 {
+        // Wrap value if needed
+        if month > 12 {
+            month -= 12;
+            year += 1;
+        }
+        // Out of the range of the dates, the longest month
+        let Some(start_of_this_month) = NaiveDate::from_ymd_opt(year, month, 1) else { return 31 };
+        let Some(start_of_next_month) = Self::first_day_of_next_month(start_of_this_month) else { return 31 };
+        start_of_next_month.signed_duration_since(start_of_this_month).num_days() as u32
+    }
+}
+
+/// File with the start time of the last run of a task with a `period`
+fn period_stamp_path(dir: &Path, task_name: &str) -> PathBuf {
+    dir.join(sanitise_file_name::sanitise(task_name))
+}
+
+fn read_period_stamp(dir: &Path, task_name: &str) -> Option<DateTime<Utc>> {
+    let path = period_stamp_path(dir, task_name);
+    let content = std::fs::read_to_string(&path).ok()?;
+    match DateTime::parse_from_rfc3339(content.trim()) {
+        Ok(time) => Some(time.to_utc()),
+        Err(e) => {
+            warn!("Task '{}': ignoring the malformed period stamp {}: {}", task_name, path.display(), e);
+            None
+        }
+    }
+}
+
+/// Records the run of the period, the task doesn't run again until the next one, even after a restart
+async fn write_period_stamp(dir: &Path, task_name: &str, start_time: DateTime<Utc>) {
+    let result = async {
+        tokio::fs::create_dir_all(dir).await?;
+        tokio::fs::write(period_stamp_path(dir, task_name), format!("{}\n", start_time.to_rfc3339())).await
+    };
+    if let Err(e) = result.await {
+        error!("Task '{}': failed to write the period stamp in {}: {}", task_name, dir.display(), e);
+    }
+}
+
+impl PendingTask {
+    pub fn new(config: Arc<TaskConfig>) -> Self {
+        PendingTask {
+            last_execution_time: None,
+            last_pid: None,
+            retries: 0,
+            last_exit_code: None,
+            consecutive_failures: 0,
+            runs: 0,
+            enabled: config.enabled,
+            last_skipped_time: None,
+            trigger: Arc::new(TaskTrigger::default()),
+            monthly_runtime: MonthlyRuntime::default(),
+            last_usage: None,
+            config,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::control::ControlRequest;
+    use crate::config::period::SchedulePeriod;
+    use TimePatternField::*;
+
+    fn pattern(text: &str) -> TimePattern {
+        crate::config::parse_config_file(
+            &serde_yml::from_str(&format!("tasks:\n  - name: test\n    cmd: 'true'\n    when: '{}'", text)).unwrap(),
+        )
+        .unwrap()
+        .tasks[0]
+            .calendar_pattern()
+            .unwrap()
+    }
+
+    fn at(timezone: Tz, text: &str) -> DateTime<Tz> {
+        let naive = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").unwrap();
+        timezone.from_local_datetime(&naive).earliest().unwrap()
+    }
+
+    /// Checks every second of the days that match, instead of carrying between fields
+    fn brute_force_next(time: &TimePattern, start: NaiveDateTime, days: u32) -> Option<NaiveDateTime> {
+        let mut date = start.date();
+        for _ in 0..days {
+            if time.matches_date(date) {
+                for hour in (0..24).filter(|v| time.hour.matches_value(*v)) {
+                    for minute in (0..60).filter(|v| time.minute.matches_value(*v)) {
+                        for second in (0..60).filter(|v| time.second.matches_value(*v)) {
+                            let candidate = date.and_hms_opt(hour, minute, second).unwrap();
+                            if candidate >= start {
+                                return Some(candidate);
+                            }
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt().unwrap();
+        }
+        None
+    }
+
+    fn random_field(rng: &mut fastrand::Rng, min: u32, max: u32) -> TimePatternField {
+        match rng.u32(0..5) {
+            0 => Any,
+            1 => Value(rng.u32(min..=max)),
+            2 => {
+                let start = rng.u32(min..=max);
+                Range(start, rng.u32(start..=max))
+            }
+            3 => TimePatternField::list((0..rng.usize(1..4)).map(|_| rng.u32(min..=max)).collect()),
+            _ => Ratio(rng.u32(1..=max.min(15)), rng.u32(0..=max.min(10))),
+        }
+    }
+
+    #[test]
+    fn test_find_next_matching_time_matches_brute_force() {
+        let mut rng = fastrand::Rng::with_seed(883);
+        let horizon_days = 8 * 366;
+
+        for _ in 0..500 {
+            let time = TimePattern {
+                year: if rng.bool() { Any } else { Range(2026, rng.u32(2026..=2034)) },
+                month: random_field(&mut rng, 1, 12),
+                day: random_field(&mut rng, 1, 31),
+                hour: random_field(&mut rng, 0, 23),
+                minute: random_field(&mut rng, 0, 59),
+                second: random_field(&mut rng, 0, 59),
+                day_of_week: if rng.u32(0..3) == 0 { random_field(&mut rng, 0, 6) } else { Any },
+            };
+            let start = NaiveDate::from_ymd_opt(rng.i32(2026..=2029), rng.u32(1..=12), rng.u32(1..=28))
+                .unwrap()
+                .and_hms_opt(rng.u32(0..24), rng.u32(0..60), rng.u32(0..60))
+                .unwrap();
+            let allow_now = rng.bool();
+
+            let found = Scheduler::find_next_matching_time(&time, chrono_tz::UTC.from_utc_datetime(&start), allow_now);
+            let first = if allow_now { start } else { start + TimeDelta::seconds(1) };
+            match brute_force_next(&time, first, horizon_days) {
+                Some(expected) => assert_eq!(found.map(|date| date.naive_utc()), Some(expected), "{} from {}", time, start),
+                None => assert!(
+                    found.is_none_or(|date| date.naive_utc() >= first + TimeDelta::days(horizon_days as i64 - 1)),
+                    "{} from {} found {:?}",
+                    time,
+                    start,
+                    found
+                ),
+            }
+        }
+    }
+
+    #[test]
+    fn test_find_next_matching_time_sparse_patterns() {
+        let utc = chrono_tz::UTC;
+        let leap_day = pattern("* *-02-29 12:00:00");
+        assert_eq!(Scheduler::find_next_matching_time(&leap_day, at(utc, "2026-03-01 00:00:00"), true), Some(at(utc, "2028-02-29 12:00:00")));
+
+        let first_of_month = pattern("* *-*-1 00:00:00");
+        assert_eq!(Scheduler::find_next_matching_time(&first_of_month, at(utc, "2026-12-15 08:00:00"), true), Some(at(utc, "2027-01-01 00:00:00")));
+        assert_eq!(Scheduler::find_next_matching_time(&first_of_month, at(utc, "2027-01-01 00:00:00"), false), Some(at(utc, "2027-02-01 00:00:00")));
+
+        assert_eq!(Scheduler::find_next_matching_time(&pattern("* *-02-30 00:00:00"), at(utc, "2026-01-01 00:00:00"), true), None);
+        assert_eq!(Scheduler::find_next_matching_time(&pattern("* 2025-*-* 00:00:00"), at(utc, "2026-01-01 00:00:00"), true), None);
+    }
+
+    #[test]
+    fn test_find_next_matching_time_years() {
+        let utc = chrono_tz::UTC;
+        let next = |time: &str, from: &str| Scheduler::find_next_matching_time(&pattern(time), at(utc, from), false);
+
+        let range = "* 2026..2028-01-01 00:00:00";
+        assert_eq!(next(range, "2025-06-01 00:00:00"), Some(at(utc, "2026-01-01 00:00:00")));
+        assert_eq!(next(range, "2026-01-01 00:00:00"), Some(at(utc, "2027-01-01 00:00:00")));
+        assert_eq!(next(range, "2028-01-01 00:00:00"), None);
+        assert_eq!(next(range, "2031-01-01 00:00:00"), None);
+
+        // The steps count from year 0, like the other fields count from their 0
+        let even_years = "* */2-06-15 12:00:00";
+        assert_eq!(next(even_years, "2025-01-01 00:00:00"), Some(at(utc, "2026-06-15 12:00:00")));
+        assert_eq!(next(even_years, "2026-06-15 12:00:00"), Some(at(utc, "2028-06-15 12:00:00")));
+        let leap_years = "* */4+2024-02-29 00:00:00";
+        assert_eq!(next(leap_years, "2020-03-01 00:00:00"), Some(at(utc, "2024-02-29 00:00:00")));
+        assert_eq!(next(leap_years, "2024-02-29 00:00:00"), Some(at(utc, "2028-02-29 00:00:00")));
+
+        let list = "* [2030, 2027]-12-31 23:59:59";
+        assert_eq!(next(list, "2026-01-01 00:00:00"), Some(at(utc, "2027-12-31 23:59:59")));
+        assert_eq!(next(list, "2027-12-31 23:59:59"), Some(at(utc, "2030-12-31 23:59:59")));
+        assert_eq!(next(list, "2030-12-31 23:59:59"), None);
+        assert_eq!(next(&format!("* {}-*-* *:*:*", MAX_YEAR), &format!("{}-12-31 23:59:59", MAX_YEAR)), None);
+
+        // The detailed format
+        let config = test_config(Path::new("."), "[{ name: test, cmd: 'true', when: { year: 2026..2028, month: 3, day: 1, hour: 0, minute: 0 } }]");
+        let time = &config.tasks[0].calendar_pattern().unwrap();
+        assert_eq!(Scheduler::find_next_matching_time(time, at(utc, "2027-05-01 00:00:00"), false), Some(at(utc, "2028-03-01 00:00:00")));
+        assert_eq!(Scheduler::find_next_matching_time(time, at(utc, "2028-03-01 00:00:00"), false), None);
+    }
+
+    #[test]
+    fn test_find_next_matching_time_dst() {
+        let madrid = chrono_tz::Europe::Madrid;
+        // 02:30 doesn't exist on the day clocks go forward
+        let time = pattern("* *-*-* 02:30:00");
+        assert_eq!(Scheduler::find_next_matching_time(&time, at(madrid, "2026-03-29 00:00:00"), true), Some(at(madrid, "2026-03-30 02:30:00")));
+
+        // 02:30 happens twice on the day clocks go back, it runs the first time only
+        let first = at(madrid, "2026-10-25 02:30:00");
+        assert_eq!(Scheduler::find_next_matching_time(&time, at(madrid, "2026-10-25 00:00:00"), true), Some(first));
+        assert_eq!(Scheduler::find_next_matching_time(&time, first, false), Some(at(madrid, "2026-10-26 02:30:00")));
+    }
+
+    #[test]
+    fn test_missed_run() {
+        let config = parse_config_file(&serde_yml::from_str("tasks: [{ name: nightly, cmd: 'true', when: '* *-*-* 03:00:00' }]").unwrap()).unwrap();
+        let mut task = PendingTask::new(config.tasks[0].clone());
+        let now = Scheduler::get_current_datetime_at(task.config.timezone);
+
+        // The sleep woke up after the planned time, the run is missed
+        let planned = now - TimeDelta::hours(2);
+        assert_eq!(Scheduler::missed_run(&task, Some(planned)), Some(planned));
+        assert_eq!(Scheduler::missed_run(&task, Some(now + TimeDelta::seconds(1))), None);
+        assert_eq!(Scheduler::missed_run(&task, None), None);
+
+        // Not if it already ran or was skipped
+        task.last_execution_time = Some(planned.with_timezone(&Utc));
+        assert_eq!(Scheduler::missed_run(&task, Some(planned)), None);
+        task.last_execution_time = None;
+        task.last_skipped_time = Some((planned + TimeDelta::seconds(1)).with_timezone(&Utc));
+        assert_eq!(Scheduler::missed_run(&task, Some(planned)), None);
+    }
+
+    #[test]
+    fn test_period_schedule() {
+        let config = parse_config_file(&serde_yml::from_str("tasks: [{ name: report, cmd: 'true', period: monthly }]").unwrap()).unwrap();
+        let mut task = PendingTask::new(config.tasks[0].clone());
+        let timezone = task.config.timezone;
+        let now = at(timezone, "2027-01-31 18:00:00");
+
+        // Due right away until it runs once in the month
+        assert_eq!(Scheduler::get_next_execution_time(&task, now, true), now);
+        task.last_execution_time = Some(at(timezone, "2026-12-31 23:00:00").with_timezone(&Utc));
+        assert_eq!(Scheduler::get_next_execution_time(&task, now, true), now);
+        task.last_execution_time = Some(at(timezone, "2027-01-01 00:30:00").with_timezone(&Utc));
+        assert_eq!(Scheduler::get_next_execution_time(&task, now, true), at(timezone, "2027-02-01 00:00:00"));
+
+        assert_eq!(SchedulePeriod::Weekly.start(NaiveDate::from_ymd_opt(2027, 1, 3).unwrap()), NaiveDate::from_ymd_opt(2026, 12, 28).unwrap());
+        assert_eq!(SchedulePeriod::Monthly.next_start(NaiveDate::from_ymd_opt(2027, 1, 31).unwrap()), NaiveDate::from_ymd_opt(2027, 2, 1).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_period_stamp_survives_restart() {
+        let dir = test_dir("period-stamp");
+        let mut config = test_config(&dir, "[{ name: daily, cmd: 'true', period: daily }]");
+        config.period_stamp_dir = Some(dir.join("periods"));
+
+        let scheduler = Scheduler::spawn(config.clone()).await;
+        let mut events = scheduler.subscribe();
+        next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskFinished { .. })).await;
+        scheduler.shutdown().await;
+        assert!(dir.join("periods").join("daily").exists());
+
+        // The restarted scheduler waits for the next day
+        let scheduler = Scheduler::spawn(config).await;
+        let mut events = scheduler.subscribe();
+        let started = count_started(&mut events, Duration::from_millis(1500)).await;
+        scheduler.shutdown().await;
+
+        assert!(started.is_empty(), "{:?}", started);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_metrics_file() {
+        let dir = test_dir("metrics-file");
+        let mut config = test_config(&dir, "[{ name: measured, cmd: 'true', every: 1 second }]");
+        let metrics_path = dir.join("cron-rs.prom");
+        config.metrics_file = Some(metrics_path.clone());
+
+        let scheduler = Scheduler::spawn(config).await;
+        let mut events = scheduler.subscribe();
+        next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskFinished { .. })).await;
+        scheduler.shutdown().await;
+
+        let metrics = std::fs::read_to_string(&metrics_path).unwrap();
+        let max_rss = metrics
+            .lines()
+            .find_map(|line| line.strip_prefix(r#"cron_rs_task_max_rss_bytes{task="measured"} "#));
+        assert!(max_rss.is_some_and(|value| value.parse::<u64>().unwrap() > 0), "{}", metrics);
+        assert!(metrics.contains("\ncron_rs_task_write_bytes{task=\"measured\"} "), "{}", metrics);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_on_change_trigger() {
+        let dir = test_dir("on-change");
+        let watched = dir.join("watched");
+        std::fs::create_dir_all(&watched).unwrap();
+        let config = test_config(
+            &dir,
+            &format!("[{{ name: rebuild, cmd: 'true', on_change: {{ paths: ['{}'], debounce: 1 second }} }}]", watched.display()),
+        );
+
+        let scheduler = Scheduler::spawn(config).await;
+        let mut events = scheduler.subscribe();
+        // Nothing changed yet
+        let started = count_started(&mut events, Duration::from_millis(1500)).await;
+        assert!(started.is_empty(), "{:?}", started);
+
+        std::fs::write(watched.join("input.csv"), "1,2").unwrap();
+        std::fs::write(watched.join("input.csv"), "1,2,3").unwrap();
+        let started = count_started(&mut events, Duration::from_millis(3000)).await;
+        scheduler.shutdown().await;
+
+        assert_eq!(started.get("rebuild"), Some(&1));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_after_task_trigger() {
+        let dir = test_dir("after-task");
+        let config = test_config(
+            &dir,
+            "[{ name: extract, cmd: 'true', every: 1 second, max_runs: 1 },
+              { name: load, cmd: 'true', after_task: { name: extract } },
+              { name: cleanup, cmd: 'true', after_task: { name: extract, on: failure } },
+              { name: report, cmd: 'false', after_task: { name: load, on: always } },
+              { name: notify, cmd: 'true', after_task: { name: report, on: failure } }]",
+        );
+
+        let scheduler = Scheduler::spawn(config).await;
+        let mut events = scheduler.subscribe();
+        let started = count_started(&mut events, Duration::from_millis(3000)).await;
+        scheduler.shutdown().await;
+
+        assert_eq!(started.get("extract"), Some(&1));
+        assert_eq!(started.get("load"), Some(&1));
+        assert_eq!(started.get("cleanup"), None);
+        assert_eq!(started.get("report"), Some(&1));
+        assert_eq!(started.get("notify"), Some(&1));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pipeline() {
+        let dir = test_dir("pipeline");
+        let yaml = format!(
+            "defaults:\n  output_dir: '{}'\ntasks: [{{ name: extract, cmd: 'true', every: 1 hour, enabled: false }}]
+pipelines:
+  - name: etl
+    every: 1 second
+    steps:
+      - task: extract
+      - parallel: [{{ name: load, cmd: 'sleep 0.2' }}, {{ name: check, cmd: 'false' }}]
+      - {{ name: report, cmd: 'true' }}",
+            dir.display()
+        );
+        let config = parse_config_file(&serde_yml::from_str(&yaml).unwrap()).unwrap();
+
+        let scheduler = Scheduler::spawn(config).await;
+        let mut events = scheduler.subscribe();
+        let failed = next_event(&mut events, |event| matches!(event, SchedulerEvent::TaskFailed { task_name, .. } if task_name == "etl")).await;
+        scheduler.shutdown().await;
+
+        // The steps are executions of their own, the pipeline stops at the first stage that fails
+        for step in ["extract", "etl.load", "etl.check"] {
+            assert!(dir.join(format!("{}_latest_stdout.log", step)).exists(), "{}", step);
+        }
+        assert!(!dir.join("etl.report_latest_stdout.log").exists());
+        let stderr = std::fs::read_to_string(dir.join("etl_latest_stderr.log")).unwrap();
+        assert!(stderr.contains("Step 'etl.check' failed"), "{:?} {}", failed, stderr);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_run_context_env() {
+        let dir = test_dir("run-context");
+        let cmd = r#"echo "$CRON_RS_TASK_NAME $CRON_RS_ATTEMPT ${CRON_RS_PREV_EXIT_CODE:-none} $CRON_RS_RUN_ID $CRON_RS_SCHEDULED_TIME {{ run_id }}"; exit 3"#;
+        let config = test_config(
+            &dir,
+            &format!("[{{ name: report, cmd: '{}', every: 1 second, max_runs: 2, output_mode: append, timezone: UTC }}]", cmd),
+        );
+
+        let scheduler = Scheduler::spawn(config).await;
+        let mut events = scheduler.subscribe();
+        let mut run_ids = vec![];
+        for _ in 0..2 {
+            let failed = next_event(&mut events, |event| matches!(event, SchedulerEvent::TaskFailed { .. })).await;
+            let SchedulerEvent::TaskFailed { run_id, .. } = failed else { unreachable!() };
+            run_ids.push(run_id);
+        }
+        scheduler.shutdown().await;
+
+        let output = std::fs::read_to_string(dir.join("report_stdout.log")).unwrap();
+        let lines: Vec<Vec<&str>> = output.lines().map(|line| line.split(' ').collect()).collect();
+        assert_eq!(lines.len(), 2, "{}", output);
+        assert_eq!(lines[0][..4], ["report", "1", "none", run_ids[0].as_str()]);
+        assert_eq!(lines[1][..4], ["report", "1", "3", run_ids[1].as_str()]);
+        // The variables of the command are replaced with the same values
+        assert_eq!(lines[1][5], run_ids[1]);
+        // The time the run was due, a whole second unlike the start time
+        assert!(lines[1][4].ends_with("+00:00") && !lines[1][4].contains('.'), "{}", lines[1][4]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Config with the outputs of the tasks in `dir`
+    fn test_config(dir: &std::path::Path, tasks: &str) -> Config {
+        let yaml = format!("defaults:\n  output_dir: '{}'\ntasks: {}", dir.display(), tasks);
+        parse_config_file(&serde_yml::from_str(&yaml).unwrap()).unwrap()
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cron-rs-test-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Waits for the next event that matches, the test fails after a few seconds without it
+    async fn next_event(
+        events: &mut broadcast::Receiver<SchedulerEvent>,
+        filter: impl Fn(&SchedulerEvent) -> bool,
+    ) -> SchedulerEvent {
+        let wait = async {
+            loop {
+                let event = events.recv().await.unwrap();
+                if filter(&event) {
+                    return event;
+                }
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), wait).await.expect("Event not received")
+    }
+
+    /// Counts the executions of each task started during `duration`
+    async fn count_started(events: &mut broadcast::Receiver<SchedulerEvent>, duration: Duration) -> HashMap<String, usize> {
+        let mut started = HashMap::new();
+        let _ = tokio::time::timeout(duration, async {
+            while let Ok(event) = events.recv().await {
+                if let SchedulerEvent::TaskStarted { task_name, .. } = event {
+                    *started.entry(task_name).or_default() += 1;
+                }
+            }
+        })
+        .await;
+        started
+    }
+
+    #[tokio::test]
+    async fn test_max_runs() {
+        let dir = test_dir("max-runs");
+        let config = test_config(
+            &dir,
+            "[{ name: twice, cmd: 'true', every: 1 second, max_runs: 2 },
+              { name: slow, cmd: 'sleep 2', every: 1 second, max_runs: 1, max_runs_only_successes: true },
+              { name: failing, cmd: 'exit 1', every: 1 second, max_runs: 1, max_runs_only_successes: true }]",
+        );
+
+        let scheduler = Scheduler::spawn(config).await;
+        let mut events = scheduler.subscribe();
+        let started = count_started(&mut events, Duration::from_millis(4500)).await;
+        scheduler.shutdown().await;
+
+        assert_eq!(started.get("twice"), Some(&2));
+        // The first run is still going when the next one is due, it already counts
+        assert_eq!(started.get("slow"), Some(&1));
+        // Failed runs don't count
+        assert!(started.get("failing").is_some_and(|runs| *runs > 1), "{:?}", started);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_max_runs_are_restored_from_the_state_file() {
+        let dir = test_dir("max-runs-state");
+        let state_path = dir.join("state.json");
+        let config = test_config(&dir, "[{ name: once, cmd: 'true', every: 1 second, max_runs: 1 }]");
+
+        let scheduler = Scheduler::builder(config.clone()).state_file(&state_path).spawn().await;
+        let mut events = scheduler.subscribe();
+        next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskFinished { .. })).await;
+        scheduler.shutdown().await;
+
+        // The restarted scheduler knows the task already ran
+        let scheduler = Scheduler::builder(config).state_file(&state_path).spawn().await;
+        let mut events = scheduler.subscribe();
+        let started = count_started(&mut events, Duration::from_millis(2500)).await;
+        scheduler.shutdown().await;
+
+        assert!(started.is_empty(), "{:?}", started);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_enabled_is_restored_from_the_state_file() {
+        let dir = test_dir("enabled-state");
+        let state_path = dir.join("state.json");
+        let config = test_config(&dir, "[{ name: toggled, cmd: 'true', every: 1 hour }]");
+
+        let scheduler = Scheduler::builder(config.clone()).state_file(&state_path).spawn().await;
+        scheduler.set_task_enabled("toggled", false).await.unwrap();
+        scheduler.shutdown().await;
+
+        let scheduler = Scheduler::builder(config.clone()).state_file(&state_path).spawn().await;
+        assert!(!scheduler.status().await.tasks[0].enabled);
+        scheduler.shutdown().await;
+
+        // A change of `enabled` in the config wins, even when it later returns to the previous value
+        let mut config = config;
+        Arc::make_mut(&mut config.tasks[0]).enabled = false;
+        let scheduler = Scheduler::builder(config.clone()).state_file(&state_path).spawn().await;
+        assert!(!scheduler.status().await.tasks[0].enabled);
+        scheduler.shutdown().await;
+        Arc::make_mut(&mut config.tasks[0]).enabled = true;
+        let scheduler = Scheduler::builder(config).state_file(&state_path).spawn().await;
+        assert!(scheduler.status().await.tasks[0].enabled);
+        scheduler.shutdown().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_one_skipped_event_per_missed_run() {
+        let dir = test_dir("skipped");
+        let config = test_config(
+            &dir,
+            "[{ name: disabled, cmd: 'true', every: 2 seconds, enabled: false },
+              { name: running, cmd: 'sleep 10', every: 1 second, avoid_overlapping: true }]",
+        );
+
+        let scheduler = Scheduler::spawn(config).await;
+        let mut events = scheduler.subscribe();
+        let mut skipped: HashMap<String, Vec<DateTime<Utc>>> = HashMap::new();
+        let _ = tokio::time::timeout(Duration::from_millis(4500), async {
+            while let Ok(event) = events.recv().await {
+                if let SchedulerEvent::TaskSkipped { task_name, scheduled_time, .. } = event {
+                    skipped.entry(task_name).or_default().push(scheduled_time);
+                }
+            }
+        })
+        .await;
+        scheduler.shutdown().await;
+
+        // Each missed run is reported once, with the time it was due at
+        for (task, interval, count) in [("disabled", 2, 2..=3), ("running", 1, 3..=5)] {
+            let times = &skipped[task];
+            assert!(count.contains(&times.len()), "{}: {:?}", task, times);
+            assert!(times.windows(2).all(|w| w[1] - w[0] == TimeDelta::seconds(interval)), "{}: {:?}", task, times);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_disable_and_enable_through_ctl() {
+        let dir = test_dir("ctl-enable");
+        let socket_path = dir.join("ctl.sock");
+        let config = test_config(&dir, "[{ name: toggled, cmd: 'true', every: 1 second, enabled: false }]");
+
+        let mutex = Arc::new(Mutex::new(Scheduler::builder(config).build()));
+        let mut events = mutex.lock().await.events.subscribe();
+        Scheduler::start(&mutex).await;
+        let listener = tokio::spawn(control::listen(socket_path.clone(), mutex.clone()));
+        while !socket_path.exists() {
+            sleep(Duration::from_millis(10)).await;
+        }
+        let request = |request: ControlRequest| {
+            let socket_path = socket_path.clone();
+            tokio::task::spawn_blocking(move || control::send_request(&socket_path, &request).unwrap())
+        };
+
+        // Disabled, the due runs are skipped and the schedule moves on to the next ones
+        assert!(count_started(&mut events, Duration::from_millis(2500)).await.is_empty());
+        assert!(mutex.lock().await.pending_tasks[0].lock().await.last_skipped_time.is_some());
+
+        let response = request(ControlRequest::Enable { task: "toggled".to_string() }).await.unwrap();
+        assert!(response.ok, "{}", response.message);
+        next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskStarted { .. })).await;
+
+        let response = request(ControlRequest::Disable { task: "toggled".to_string() }).await.unwrap();
+        assert!(response.ok, "{}", response.message);
+        next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskSkipped { .. })).await;
+        assert!(!mutex.lock().await.pending_tasks[0].lock().await.enabled);
+
+        let response = request(ControlRequest::Disable { task: "missing".to_string() }).await.unwrap();
+        assert!(!response.ok);
+
+        Scheduler::shutdown(&mutex).await;
+        listener.abort();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_trigger_through_ctl() {
+        let dir = test_dir("ctl-trigger");
+        let socket_path = dir.join("ctl.sock");
+        let config = test_config(
+            &dir,
+            "[{ name: upstream, cmd: 'true', every: 1 day, enabled: false },
+              { name: slow, cmd: 'sleep 1; exit 3', after_task: { name: upstream }, avoid_overlapping: true },
+              { name: off, cmd: 'true', every: 1 day, enabled: false }]",
+        );
+
+        let mutex = Arc::new(Mutex::new(Scheduler::builder(config).build()));
+        Scheduler::start(&mutex).await;
+        let listener = tokio::spawn(control::listen(socket_path.clone(), mutex.clone()));
+        while !socket_path.exists() {
+            sleep(Duration::from_millis(10)).await;
+        }
+        let trigger = |task: &str, wait: bool, force: bool| {
+            let socket_path = socket_path.clone();
+            let request = ControlRequest::Trigger { task: task.to_string(), wait, force };
+            tokio::task::spawn_blocking(move || control::send_request(&socket_path, &request).unwrap())
+        };
+
+        let response = trigger("slow", false, false).await.unwrap();
+        assert!(response.ok && response.run.is_none(), "{}", response.message);
+        // Refused while it runs, unless forced
+        let response = trigger("slow", true, false).await.unwrap();
+        assert!(!response.ok, "{}", response.message);
+        let response = trigger("slow", true, true).await.unwrap();
+        assert!(response.ok, "{}", response.message);
+        let run = response.run.unwrap();
+        assert_eq!((run.task_name.as_str(), run.exit_code, run.success), ("slow", 3, false));
+
+        assert!(!trigger("off", false, false).await.unwrap().ok);
+        assert!(trigger("off", true, true).await.unwrap().run.unwrap().success);
+        assert!(!trigger("missing", false, true).await.unwrap().ok);
+
+        let status = mutex.lock().await.status().await;
+        let slow = status.tasks.iter().find(|task| task.name == "slow").unwrap();
+        assert_eq!(slow.last_exit_code, Some(3));
+
+        Scheduler::shutdown(&mutex).await;
+        listener.abort();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_overlapping_starts() {
+        let dir = test_dir("overlapping-starts");
+        let config = test_config(&dir, "[{ name: slow, cmd: 'sleep 1', after_task: { name: slow-upstream }, avoid_overlapping: true },
+                                         { name: slow-upstream, cmd: 'true', every: 1 day, enabled: false }]");
+        let mutex = Arc::new(Mutex::new(Scheduler::builder(config).build()));
+        Scheduler::start(&mutex).await;
+
+        // The starts at the same time can't all pass the check before the first one is running
+        let starts: Vec<_> = (0..8)
+            .map(|_| {
+                let mutex = mutex.clone();
+                tokio::spawn(async move { Scheduler::trigger_task(&mutex, "slow", false).await.map(|(_, finished)| finished) })
+            })
+            .collect();
+        let mut started = vec![];
+        for start in starts {
+            started.extend(start.await.unwrap().ok());
+        }
+        assert_eq!(started.len(), 1);
+        assert_eq!(mutex.lock().await.active_tasks.len(), 1);
+
+        // A reservation that is given up lets the next start through
+        let reservation = mutex.lock().await.reserve_start("slow", false).unwrap();
+        started.into_iter().next().unwrap().await.unwrap();
+        assert!(mutex.lock().await.reserve_start("slow", true).is_none());
+        drop(reservation);
+        assert!(mutex.lock().await.reserve_start("slow", true).is_some());
+
+        Scheduler::shutdown(&mutex).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_lock_file() {
+        let dir = test_dir("lock-file");
+        let lock_path = dir.join("locked.lock");
+        let config = test_config(&dir, &format!("[{{ name: locked, cmd: 'sleep 0.2', every: 1 second, lock_file: '{}' }}]", lock_path.display()));
+
+        // Held by another process, like a manual run with `flock -n`
+        let lock = try_lock_file(&lock_path).unwrap().unwrap();
+        let scheduler = Scheduler::spawn(config).await;
+        let mut events = scheduler.subscribe();
+        let skipped = next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskSkipped { .. })).await;
+        assert!(matches!(&skipped, SchedulerEvent::TaskSkipped { reason, .. } if reason.contains("locked by another process")), "{:?}", skipped);
+
+        drop(lock);
+        next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskStarted { .. })).await;
+        assert!(try_lock_file(&lock_path).unwrap().is_none());
+        next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskFinished { .. })).await;
+        scheduler.shutdown().await;
+        assert!(try_lock_file(&lock_path).unwrap().is_some());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_internal_error() {
+        let dir = test_dir("internal-error");
+        let alert_file = dir.join("alert.txt");
+        let mut config = test_config(&dir, "[]");
+        config.alerts.on_internal_error =
+            vec![Alert::cmd(format!("echo {{{{ error }}}} > {}", alert_file.display()))];
+        let mutex = Arc::new(Mutex::new(Scheduler::builder(config).build()));
+        let mut events = mutex.lock().await.events.subscribe();
+
+        // An execution the scheduler doesn't know about is reported instead of panicking
+        Scheduler::wait_for_task(mutex.clone(), 42, None).await;
+        let event = next_event(&mut events, |e| matches!(e, SchedulerEvent::InternalError { .. })).await;
+        assert_eq!(
+            event,
+            SchedulerEvent::InternalError { task_name: None, error: "Execution 42 is not running, it can't be waited for".to_string() }
+        );
+        assert_eq!(std::fs::read_to_string(&alert_file).unwrap(), "Execution 42 is not running, it can't be waited for\n");
+
+        // Intervals past the last date never run instead of overflowing
+        let now = Scheduler::get_current_datetime_at(chrono_tz::UTC);
+        assert_eq!(Scheduler::add_interval(now, Duration::MAX), Scheduler::never(chrono_tz::UTC));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_output_dir_error() {
+        let dir = test_dir("output-dir-error");
+        // A file where the output directory should be
+        std::fs::write(dir.join("blocked"), "").unwrap();
+        let stdout = dir.join("blocked/logs/out.log");
+        let tasks = format!("[{{ name: blocked, cmd: 'true', every: 1 second, stdout: '{}' }}]", stdout.display());
+        let config = test_config(&dir, &tasks);
+        let mutex = Arc::new(Mutex::new(Scheduler::builder(config).build()));
+        let mut events = mutex.lock().await.events.subscribe();
+        Scheduler::start(&mutex).await;
+
+        // Reported and retried on the next run instead of killing the loop of the task
+        for _ in 0..2 {
+            let event = next_event(&mut events, |e| matches!(e, SchedulerEvent::InternalError { .. })).await;
+            let SchedulerEvent::InternalError { task_name, error } = event else { unreachable!() };
+            assert_eq!(task_name.as_deref(), Some("blocked"));
+            assert!(error.starts_with("Failed to create the stdout directory"), "{}", error);
+        }
+
+        Scheduler::shutdown(&mutex).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_before_hook_failure_is_not_an_internal_error() {
+        let dir = test_dir("before-hook-failure");
+        let config = test_config(&dir, "[{ name: aborted, cmd: 'true', every: 1 second, before: ['exit 3'] }]");
+        let mutex = Arc::new(Mutex::new(Scheduler::builder(config).build()));
+        let mut events = mutex.lock().await.events.subscribe();
+        Scheduler::start(&mutex).await;
+
+        // Each aborted run is a failed execution, followed by the next run and not by an internal error
+        for _ in 0..2 {
+            let event = next_event(&mut events, |e| {
+                matches!(e, SchedulerEvent::TaskFailed { .. } | SchedulerEvent::InternalError { .. })
+            })
+            .await;
+            assert!(matches!(event, SchedulerEvent::TaskFailed { .. }), "{:?}", event);
+        }
+
+        Scheduler::shutdown(&mutex).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_clock_change() {
+        let dir = test_dir("clock-change");
+        let alert_file = dir.join("alert.txt");
+        let mut config = test_config(&dir, "[]");
+        config.alerts.on_clock_change =
+            vec![Alert::cmd(format!("echo {{{{ jump }}}} > {}", alert_file.display()))];
+        let mutex = Arc::new(Mutex::new(Scheduler::builder(config).build()));
+        let mut events = mutex.lock().await.events.subscribe();
+
+        // The sleeps toward a wall clock time wake up to compute it again
+        let woken = tokio::spawn(clock::clock_changed());
+        sleep(Duration::from_millis(50)).await;
+        Scheduler::on_clock_change(&mutex, TimeDelta::minutes(-90)).await;
+        tokio::time::timeout(Duration::from_secs(1), woken).await.unwrap().unwrap();
+
+        let event = next_event(&mut events, |e| matches!(e, SchedulerEvent::ClockChanged { .. })).await;
+        assert_eq!(event, SchedulerEvent::ClockChanged { jump_seconds: -5400 });
+        assert_eq!(std::fs::read_to_string(&alert_file).unwrap(), "1 h, 30 m back\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_cluster_lock() {
+        let dir = test_dir("cluster-lock");
+        let config = test_config(&dir, "[{ name: singleton, cmd: 'true', every: 1 second, cluster_lock: 'redis://127.0.0.1:1' }]");
+
+        // Without the lock the other nodes may be running the task, so the run is skipped
+        let scheduler = Scheduler::spawn(config).await;
+        let mut events = scheduler.subscribe();
+        let skipped = next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskSkipped { .. })).await;
+        assert!(matches!(&skipped, SchedulerEvent::TaskSkipped { reason, .. } if reason.contains("failed to connect")), "{:?}", skipped);
+        let error = scheduler.trigger_task("singleton", true).await.unwrap_err();
+        assert!(format!("{:#}", error).starts_with("Task 'singleton' can't run"), "{:#}", error);
+        scheduler.shutdown().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_lost_monitor_is_cleaned_up() {
+        let dir = test_dir("lost-monitor");
+        let config = test_config(&dir, "[{ name: slow, cmd: 'sleep 30', every: 1 day, enabled: false }]");
+        let mutex = Arc::new(Mutex::new(Scheduler::builder(config).build()));
+        let mut events = mutex.lock().await.events.subscribe();
+        Scheduler::start(&mutex).await;
+        Scheduler::trigger_task(&mutex, "slow", true).await.unwrap();
+        let pid = mutex.lock().await.active_tasks[0].pid;
+
+        // Like a panic in the coroutine that waits for the execution
+        for handle in &mutex.lock().await.wait_handles {
+            handle.abort();
+        }
+        sleep(Duration::from_millis(100)).await;
+        Scheduler::reap_children(&mutex, &mut ZombieReaper::default()).await;
+
+        let event = next_event(&mut events, |e| matches!(e, SchedulerEvent::InternalError { .. })).await;
+        assert!(matches!(&event, SchedulerEvent::InternalError { task_name: Some(name), .. } if name == "slow"), "{:?}", event);
+        assert!(mutex.lock().await.active_tasks.is_empty());
+        assert!(wait_process_exit(pid, Duration::ZERO).await);
+        Scheduler::shutdown(&mutex).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_adopt_previous_runs() {
+        let dir = test_dir("adopt-runs");
+        let state_path = dir.join("state.json");
+        let config = test_config(&dir, "[{ name: slow, cmd: 'sleep 30', every: 1 day, enabled: false, avoid_overlapping: true }]");
+
+        // Left running by a scheduler that crashed, and a run whose pid is not that process anymore
+        let mut child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let start_time = process_start_time(child.id()).unwrap();
+        let state = json!({
+            "pending_tasks": [],
+            "active_tasks": [
+                { "config_name": "slow", "run_id": "adopted", "pid": child.id(), "start_time": start_time.to_rfc3339() },
+                { "config_name": "slow", "run_id": "stale", "pid": std::process::id(), "start_time": "2000-01-01T00:00:00Z" },
+            ],
+        });
+        std::fs::write(&state_path, state.to_string()).unwrap();
+
+        let mutex = Arc::new(Mutex::new(Scheduler::builder(config).state_file(&state_path).build()));
+        Scheduler::start(&mutex).await;
+        let running: Vec<(String, u32)> = mutex.lock().await.active_tasks.iter().map(|t| (t.run_id.clone(), t.pid)).collect();
+        assert_eq!(running, vec![("adopted".to_string(), child.id())]);
+        assert!(Scheduler::trigger_task(&mutex, "slow", false).await.is_err());
+
+        // It finishes once the process exits
+        child.kill().unwrap();
+        child.wait().unwrap();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !mutex.lock().await.active_tasks.is_empty() {
+                sleep(Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .unwrap();
+        Scheduler::shutdown(&mutex).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_add_and_remove_task_through_ctl() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = test_dir("ctl-add-task");
+        let socket_path = dir.join("ctl.sock");
+        let mutex = Arc::new(Mutex::new(Scheduler::builder(test_config(&dir, "[]")).build()));
+        let mut events = mutex.lock().await.events.subscribe();
+        Scheduler::start(&mutex).await;
+        let listener = tokio::spawn(control::listen(socket_path.clone(), mutex.clone()));
+        while !socket_path.exists() {
+            sleep(Duration::from_millis(10)).await;
+        }
+        let request = |request: ControlRequest| {
+            let socket_path = socket_path.clone();
+            tokio::task::spawn_blocking(move || control::send_request(&socket_path, &request).unwrap())
+        };
+        let add_task = |yaml: &str| ControlRequest::AddTask { task: Box::new(serde_yml::from_str(yaml).unwrap()) };
+
+        let response = request(add_task("{ name: added, cmd: 'true', every: 1 second, tags: [''] }")).await.unwrap();
+        // Only the user of the scheduler can add tasks
+        let mode = std::fs::metadata(&socket_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600, "{:o}", mode);
+        assert!(!response.ok && response.message.contains("Tags must not be empty"), "{}", response.message);
+        let response = request(add_task("{ name: added, cmd: 'true', every: 1 second }")).await.unwrap();
+        assert!(response.ok, "{}", response.message);
+        let started = next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskStarted { .. })).await;
+        assert!(matches!(started, SchedulerEvent::TaskStarted { task_name, .. } if task_name == "added"));
+        assert!(!request(add_task("{ name: added, cmd: 'true', every: 1 hour }")).await.unwrap().ok);
+
+        let response = request(ControlRequest::RemoveTask { task: "added".to_string() }).await.unwrap();
+        assert!(response.ok, "{}", response.message);
+        assert!(mutex.lock().await.tasks.is_empty());
+        assert!(!request(ControlRequest::RemoveTask { task: "added".to_string() }).await.unwrap().ok);
+
+        Scheduler::shutdown(&mutex).await;
+        listener.abort();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_scheduler_handle_add_and_remove_task() {
+        let dir = test_dir("handle");
+        let config = test_config(&dir, "[]");
+        let task = TaskConfig::clone(&test_config(&dir, "[{ name: added, cmd: 'true', every: 1 second }]").tasks[0]);
+
+        let scheduler = Scheduler::spawn(config).await;
+        let mut events = scheduler.subscribe();
+
+        scheduler.add_task(task.clone()).await.unwrap();
+        assert!(scheduler.add_task(task).await.is_err());
+        let started = next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskStarted { .. })).await;
+        assert!(matches!(started, SchedulerEvent::TaskStarted { task_name, .. } if task_name == "added"));
+        next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskFinished { .. })).await;
+
+        scheduler.remove_task("added").await.unwrap();
+        assert!(scheduler.remove_task("added").await.is_err());
+        let start = tokio::time::timeout(Duration::from_millis(2500), async {
+            loop {
+                if let Ok(SchedulerEvent::TaskStarted { .. }) = events.recv().await {
+                    return;
+                }
+            }
+        });
+        assert!(start.await.is_err(), "A removed task kept running");
+
+        scheduler.shutdown().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_closure_tasks() {
+        let dir = test_dir("closure");
+        let scheduler = Scheduler::spawn(test_config(&dir, "[]")).await;
+        let mut events = scheduler.subscribe();
+        let definition = |name: &str| -> TaskDefinition {
+            let yaml = format!("{{ name: {}, cmd: {}(), every: 1 second, output_dir: '{}' }}", name, name, dir.display());
+            serde_yml::from_str(&yaml).unwrap()
+        };
+
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        scheduler
+            .add_closure_task(definition("counting"), move || {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Ok(())
+                }
+            })
+            .await
+            .unwrap();
+        let started = next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskStarted { .. })).await;
+        assert!(matches!(started, SchedulerEvent::TaskStarted { pid: 0, .. }));
+        let finished = next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskFinished { .. })).await;
+        assert!(matches!(finished, SchedulerEvent::TaskFinished { exit_code: 0, .. }));
+        assert!(calls.load(Ordering::SeqCst) >= 1);
+        scheduler.remove_task("counting").await.unwrap();
+
+        // The error is written to the stderr of the execution
+        scheduler.add_closure_task(definition("failing"), || async { Err(anyhow!("no connection")) }).await.unwrap();
+        let failed = next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskFailed { .. })).await;
+        let SchedulerEvent::TaskFailed { task_name, run_id, exit_code, .. } = failed else { unreachable!() };
+        assert_eq!((task_name.as_str(), exit_code), ("failing", 1));
+        let stderr = std::fs::read_to_string(dir.join(format!("failing_{}_stderr.log", run_id))).unwrap();
+        assert!(stderr.contains("no connection"), "{}", stderr);
+
+        scheduler.shutdown().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_state_file_is_opt_in() {
+        let dir = test_dir("state");
+        let state_path = dir.join("state.json");
+        let config = test_config(&dir, "[{ name: counted, cmd: 'true', every: 1 hour }]");
+
+        // Without a state file nothing is written
+        let scheduler = Scheduler::spawn(config.clone()).await;
+        let mut events = scheduler.subscribe();
+        next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskFinished { .. })).await;
+        scheduler.shutdown().await;
+        assert!(!state_path.exists());
+
+        let scheduler = Scheduler::builder(config).state_file(&state_path).spawn().await;
+        let mut events = scheduler.subscribe();
+        next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskFinished { .. })).await;
+        scheduler.shutdown().await;
+
+        let state: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&state_path).unwrap()).unwrap();
+        assert_eq!(state["pending_tasks"][0]["config_name"], "counted");
+        assert_eq!(state["pending_tasks"][0]["runs"], 1);
+        assert!(state["pending_tasks"][0]["monthly_runtime"]["month"].is_string());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_monthly_runtime() {
+        let time = |day, hour| Tz::UTC.with_ymd_and_hms(2025, 1, day, hour, 0, 0).unwrap();
+        let mut runtime = MonthlyRuntime::default();
+        runtime.add(time(1, 0), Duration::from_secs(60));
+        runtime.add(time(31, 23), Duration::from_secs(30));
+        assert_eq!(runtime.used_in(time(15, 0)), Duration::from_secs(90));
+
+        // A new month starts with the whole budget
+        let february = Tz::UTC.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap();
+        assert_eq!(runtime.used_in(february), Duration::ZERO);
+        runtime.add(february, Duration::from_secs(10));
+        assert_eq!((runtime.month.as_str(), runtime.used), ("2025-02", Duration::from_secs(10)));
+    }
+
+    #[tokio::test]
+    async fn test_monthly_runtime_budget() {
+        let dir = test_dir("budget");
+        let config = test_config(&dir, "[{ name: slow, cmd: 'sleep 1', every: 1 second, max_monthly_runtime: 1s }]");
+        let scheduler = Scheduler::spawn(config).await;
+        let mut events = scheduler.subscribe();
+
+        next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskFinished { .. })).await;
+        let skipped = next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskSkipped { .. })).await;
+        assert!(matches!(&skipped, SchedulerEvent::TaskSkipped { reason, .. } if reason.contains("monthly runtime budget")), "{:?}", skipped);
+        let status = scheduler.status().await;
+        assert!(status.tasks[0].monthly_runtime_seconds >= 1.0);
+
+        scheduler.shutdown().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_monthly_runtime_from_history() {
+        let dir = test_dir("budget-history");
+        let yaml = format!(
+            "logging: {{ output: stdout, level: info, sqlite: {{ enabled: true, database_path: '{}' }} }}
+defaults: {{ output_dir: '{}' }}
+tasks: [{{ name: slow, cmd: 'sleep 1', every: 1 second, max_monthly_runtime: 1s }}]",
+            dir.join("history.db").display(),
+            dir.display()
+        );
+        let config = parse_config_file(&serde_yml::from_str(&yaml).unwrap()).unwrap();
+        let scheduler = Scheduler::spawn(config.clone()).await;
+        let mut events = scheduler.subscribe();
+        next_event(&mut events, |e| matches!(e, SchedulerEvent::TaskFinished { .. })).await;
+        scheduler.shutdown().await;
+
+        // Without a state file, the runtime used is read from the history
+        let now = Scheduler::get_current_datetime_at(chrono_tz::UTC);
+        let start = MonthlyRuntime::month_start(now);
+        assert_eq!((start.day(), start.hour(), start.month()), (1, 0, now.month()));
+        let scheduler = Scheduler::spawn(config).await;
+        let status = scheduler.status().await;
+        assert!(status.tasks[0].monthly_runtime_seconds >= 1.0, "{:?}", status.tasks[0]);
+
+        scheduler.shutdown().await;
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
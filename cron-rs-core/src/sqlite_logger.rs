@@ -1,13 +1,19 @@
+use crate::resource_usage::ResourceUsage;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
-use libsql::{Builder, Connection, Database};
-use log::{debug, error, info, warn};
+use libsql::{Builder, Connection};
+use log::{debug, info, warn};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Mutex;
 
-const DB_SCHEMA_VERSION: i32 = 1;
+const DB_SCHEMA_VERSION: i32 = 2;
+
+/// Columns of the resources used by an execution, added to the successes and the failures in version 2
+const USAGE_COLUMNS: [&str; 5] =
+    ["max_rss INTEGER", "user_cpu_seconds REAL", "system_cpu_seconds REAL", "read_bytes INTEGER", "write_bytes INTEGER"];
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct SqliteLoggerConfig {
@@ -53,6 +59,7 @@ pub struct ExecutionSuccess {
     pub end_time: DateTime<Utc>,
     pub duration_seconds: f64,
     pub exit_code: i32,
+    pub usage: Option<ResourceUsage>,
 }
 
 #[derive(Debug, Clone)]
@@ -66,6 +73,21 @@ pub struct ExecutionFailure {
     pub exit_code: Option<i32>,
     pub error_message: String,
     pub failure_reason: String,
+    pub usage: Option<ResourceUsage>,
+}
+
+/// An alert that was sent or failed to send for an execution
+#[derive(Debug, Clone)]
+pub struct AlertAttempt {
+    pub task_name: String,
+    pub run_id: String,
+    /// `email`, `cmd` or `webhook`
+    pub alert: String,
+    /// Recipient of the alert, see [crate::alerts::Alert::target]
+    pub target: String,
+    pub time: DateTime<Utc>,
+    /// None when the alert was sent
+    pub error: Option<String>,
 }
 
 impl SqliteLogger {
@@ -113,6 +135,15 @@ impl SqliteLogger {
                 [DB_SCHEMA_VERSION],
             ).await?;
             debug!("Initialized database with schema version {}", DB_SCHEMA_VERSION);
+        } else if current_version == 1 {
+            // Version 2 records the resources used by the executions
+            for table in ["execution_successes", "execution_failures"] {
+                for column in USAGE_COLUMNS {
+                    db.execute(&format!("ALTER TABLE {} ADD COLUMN {}", table, column), ()).await?;
+                }
+            }
+            db.execute("UPDATE database_version SET version = ?", [DB_SCHEMA_VERSION]).await?;
+            info!("Migrated the database to schema version {}", DB_SCHEMA_VERSION);
         } else if current_version != DB_SCHEMA_VERSION {
             warn!("Database schema version {} (current {}), but no pending migrations found", current_version, DB_SCHEMA_VERSION);
         }
@@ -148,6 +179,11 @@ impl SqliteLogger {
                 end_time TEXT NOT NULL,
                 duration_seconds REAL NOT NULL,
                 exit_code INTEGER NOT NULL,
+                max_rss INTEGER,
+                user_cpu_seconds REAL,
+                system_cpu_seconds REAL,
+                read_bytes INTEGER,
+                write_bytes INTEGER,
                 created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
             )
             "#,
@@ -167,6 +203,28 @@ impl SqliteLogger {
                 exit_code INTEGER,
                 error_message TEXT NOT NULL,
                 failure_reason TEXT NOT NULL,
+                max_rss INTEGER,
+                user_cpu_seconds REAL,
+                system_cpu_seconds REAL,
+                read_bytes INTEGER,
+                write_bytes INTEGER,
+                created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )
+            "#,
+            (),
+        ).await?;
+
+        db.execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS alert_attempts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                task_name TEXT NOT NULL,
+                run_id TEXT NOT NULL,
+                alert TEXT NOT NULL,
+                target TEXT NOT NULL,
+                time TEXT NOT NULL,
+                sent INTEGER NOT NULL,
+                error TEXT,
                 created_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP
             )
             "#,
@@ -204,6 +262,11 @@ impl SqliteLogger {
             (),
         ).await?;
 
+        db.execute(
+            "CREATE INDEX IF NOT EXISTS idx_alerts_run_id ON alert_attempts(run_id)",
+            (),
+        ).await?;
+
         debug!("SQLite schema initialized successfully");
         Ok(())
     }
@@ -261,8 +324,8 @@ impl SqliteLogger {
             r#"
             INSERT INTO execution_successes (
                 task_name, task_id, pid, start_time, end_time, duration_seconds,
-                exit_code
-            ) VALUES (?, ?, ?, ?, ?, ?, ?)
+                exit_code, max_rss, user_cpu_seconds, system_cpu_seconds, read_bytes, write_bytes
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
             (
                 success.task_name.as_str(),
@@ -272,6 +335,11 @@ impl SqliteLogger {
                 success.end_time.to_rfc3339().as_str(),
                 success.duration_seconds,
                 success.exit_code as i64,
+                success.usage.map(|usage| usage.max_rss as i64),
+                success.usage.map(|usage| usage.user_cpu_seconds),
+                success.usage.map(|usage| usage.system_cpu_seconds),
+                success.usage.map(|usage| usage.read_bytes as i64),
+                success.usage.map(|usage| usage.write_bytes as i64),
             ),
         ).await
         .context("Failed to log execution success")?;
@@ -291,8 +359,9 @@ impl SqliteLogger {
             r#"
             INSERT INTO execution_failures (
                 task_name, task_id, pid, start_time, end_time, duration_seconds,
-                exit_code, error_message, failure_reason
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                exit_code, error_message, failure_reason, max_rss, user_cpu_seconds, system_cpu_seconds, read_bytes,
+                write_bytes
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
             (
                 failure.task_name.as_str(),
@@ -304,6 +373,11 @@ impl SqliteLogger {
                 failure.exit_code.map(|c| c as i64),
                 failure.error_message.as_str(),
                 failure.failure_reason.as_str(),
+                failure.usage.map(|usage| usage.max_rss as i64),
+                failure.usage.map(|usage| usage.user_cpu_seconds),
+                failure.usage.map(|usage| usage.system_cpu_seconds),
+                failure.usage.map(|usage| usage.read_bytes as i64),
+                failure.usage.map(|usage| usage.write_bytes as i64),
             ),
         ).await
         .context("Failed to log execution failure")?;
@@ -312,6 +386,60 @@ impl SqliteLogger {
         Ok(())
     }
 
+    pub async fn log_alert_attempt(&self, attempt: &AlertAttempt) -> Result<()> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        let db = self.db.lock().await;
+
+        db.execute(
+            r#"
+            INSERT INTO alert_attempts (
+                task_name, run_id, alert, target, time, sent, error
+            ) VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+            (
+                attempt.task_name.as_str(),
+                attempt.run_id.as_str(),
+                attempt.alert.as_str(),
+                attempt.target.as_str(),
+                attempt.time.to_rfc3339().as_str(),
+                attempt.error.is_none() as i64,
+                attempt.error.as_deref(),
+            ),
+        ).await
+        .context("Failed to log alert attempt")?;
+
+        debug!("Logged {} alert attempt for task: {}", attempt.alert, attempt.task_name);
+        Ok(())
+    }
+
+    /// Runtime of the executions of the task that started since the time, successful or not, for
+    /// `max_monthly_runtime`
+    pub async fn task_runtime_since(&self, task_name: &str, since: DateTime<Utc>) -> Result<Duration> {
+        let db = self.db.lock().await;
+        // The times are in UTC with the same format, they sort as text
+        let mut rows = db
+            .query(
+                r#"
+                SELECT COALESCE(SUM(duration_seconds), 0.0) FROM (
+                    SELECT duration_seconds FROM execution_successes WHERE task_name = ?1 AND start_time >= ?2
+                    UNION ALL
+                    SELECT duration_seconds FROM execution_failures WHERE task_name = ?1 AND start_time >= ?2
+                )
+                "#,
+                (task_name, since.to_rfc3339().as_str()),
+            )
+            .await
+            .context("Failed to read the runtime of the task")?;
+        let seconds: f64 = match rows.next().await? {
+            Some(row) => row.get(0)?,
+            None => 0.0,
+        };
+        Ok(Duration::try_from_secs_f64(seconds).unwrap_or_default())
+    }
+
     pub async fn get_database_version_info(&self) -> Result<i32> {
         if !self.config.enabled {
             return Ok(0);
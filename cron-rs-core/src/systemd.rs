@@ -0,0 +1,1022 @@
+use crate::alerts::AlertConfig;
+use crate::executor;
+use crate::config::conditions::OnlyIfConfig;
+use crate::config::file::{
+    ConfigFile, ExplodedTimePatternConfig, ExplodedTimePatternFieldConfig, StopSignal, TaskDefinition, TaskNetwork,
+    TimePatternConfig,
+};
+use crate::config::limits::{CgroupConfig, IoPriority, ResourceLimitsConfig};
+use crate::config::logging::LoggingConfig;
+use crate::config::retention::parse_size;
+use crate::config::{Config, Schedule, TaskConfig, TaskStdin, TimePattern, TimePatternField};
+use crate::utils::system_timezone;
+use anyhow::{anyhow, bail, Context, Result};
+use chrono_tz::Tz;
+use log::warn;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+const DAY_NAMES: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+/// A `.service`/`.timer` pair generated for a task
+#[derive(Debug, Clone)]
+pub struct SystemdUnit {
+    /// Name of the units, without the extension
+    pub name: String,
+    pub service: String,
+    pub timer: String,
+    pub enabled: bool,
+}
+
+/// Generates a service and a timer unit for each task of the config
+pub fn export_systemd(config: &Config) -> Vec<SystemdUnit> {
+    if !config.alerts.on_failure.is_empty() || !config.alerts.on_success.is_empty() {
+        warn!("Global alerts have no equivalent in systemd and are not exported");
+    }
+
+    let local_timezone = system_timezone().name().to_string();
+    let mut used_names = HashSet::new();
+    let mut units = vec![];
+
+    for task in &config.tasks {
+        if task.executor.is_pipeline() {
+            warn!("Task '{}': pipelines have no equivalent in systemd, the pipeline is not exported", task.name);
+            continue;
+        }
+        if task.schedule.is_triggered() {
+            warn!("Task '{}': the schedule has no equivalent in a systemd timer, the task is not exported", task.name);
+            continue;
+        }
+        warn_unsupported_options(task);
+
+        let base_name = unit_name(&task.name);
+        let mut name = base_name.clone();
+        let mut i = 2;
+        while !used_names.insert(name.clone()) {
+            name = format!("{}-{}", base_name, i);
+            i += 1;
+        }
+
+        units.push(SystemdUnit {
+            service: export_service(task),
+            timer: export_timer(task, &local_timezone),
+            name,
+            enabled: task.enabled,
+        });
+    }
+
+    units
+}
+
+fn warn_unsupported_options(task: &TaskConfig) {
+    let mut unsupported = vec![];
+    if crate::template::has_variables(&task.cmd) {
+        unsupported.push("cmd variables");
+    }
+    if task.max_runs.is_some() {
+        unsupported.push("max_runs");
+    }
+    if !task.on_failure.is_empty() || !task.on_success.is_empty() {
+        unsupported.push("alerts");
+    }
+    if task.on_failure_cmd.is_some() {
+        unsupported.push("on_failure_cmd");
+    }
+    if task.login_shell {
+        unsupported.push("login_shell");
+    }
+    if let Schedule::When { min_interval: Some(_), .. } = &task.schedule {
+        unsupported.push("min_interval");
+    }
+    if !task.success.exit_codes.contains(&0) {
+        unsupported.push("success.exit_codes without 0");
+    }
+    let success = &task.success;
+    if success.stdout_matches.is_some() || success.stdout_not_matches.is_some() || success.stderr_matches.is_some() || success.stderr_not_matches.is_some() {
+        unsupported.push("success output patterns");
+    }
+    if task.conditions.min_battery_percent.is_some() {
+        unsupported.push("min_battery_percent");
+    }
+    if task.lock_file.is_some() {
+        unsupported.push("lock_file");
+    }
+    if task.cluster_lock.is_some() {
+        unsupported.push("cluster_lock");
+    }
+    if task.conditions.path_exists.as_ref().is_some_and(|path| path.is_relative()) {
+        unsupported.push("only_if.path_exists with a relative path");
+    }
+    if task.conditions.path_newer_than.is_some() || task.conditions.disk_free_above.is_some() {
+        unsupported.push("only_if.path_newer_than/disk_free_above");
+    }
+    if task.conditions.plugin.is_some() {
+        unsupported.push("only_if.plugin");
+    }
+
+    if !unsupported.is_empty() {
+        warn!("Task '{}': ignoring options without an equivalent in systemd: {}", task.name, unsupported.join(", "));
+    }
+}
+
+/// Unit names only keep lowercase letters, digits and dashes, e.g. `Daily Backup` => `cron-rs-daily-backup`
+fn unit_name(task_name: &str) -> String {
+    let words: Vec<String> = task_name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_ascii_lowercase())
+        .collect();
+    format!("cron-rs-{}", words.join("-")).trim_end_matches('-').to_string()
+}
+
+fn export_service(task: &TaskConfig) -> String {
+    let mut out = format!("[Unit]\nDescription=cron-rs task: {}\n", task.name);
+    if task.conditions.only_on_ac_power {
+        out.push_str("ConditionACPower=true\n");
+    }
+    if let Some(path) = task.conditions.path_exists.as_ref().filter(|path| path.is_absolute()) {
+        out.push_str(&format!("ConditionPathExists={}\n", path.to_string_lossy().replace('%', "%%")));
+    }
+    out.push_str("\n[Service]\nType=oneshot\n");
+
+    let shell = task.shell.as_deref().unwrap_or("/bin/sh");
+    for hook in &task.before {
+        out.push_str(&format!("ExecStartPre={} -c {}\n", escape(shell), quote(hook)));
+    }
+    out.push_str(&format!("ExecStart={} -c {}\n", escape(shell), quote(&executor::shell_command(task))));
+    // systemd gives the exit code to ExecStopPost in $EXIT_STATUS
+    for hook in &task.after {
+        out.push_str(&format!("ExecStopPost={} -c {}\n", escape(shell), quote(&format!("{}{}", AFTER_HOOK_PREFIX, hook))));
+    }
+
+    if let Some(run_as) = &task.run_as {
+        let (user, group) = run_as.split_once(':').unwrap_or((run_as, ""));
+        out.push_str(&format!("User={}\n", user));
+        if !group.is_empty() {
+            out.push_str(&format!("Group={}\n", group));
+        }
+    }
+    if let Some(root) = &task.chroot {
+        out.push_str(&format!("RootDirectory={}\n", escape(root)));
+    }
+    if task.network == TaskNetwork::None {
+        out.push_str("PrivateNetwork=yes\n");
+    }
+    // The one of a container is passed to docker
+    if let Some(dir) = task.working_directory.as_ref().filter(|_| task.executor.is_local()) {
+        out.push_str(&format!("WorkingDirectory={}\n", escape(dir)));
+    }
+    if let Some(path) = &task.env_file {
+        out.push_str(&format!("EnvironmentFile={}\n", escape(path)));
+    }
+    for (key, value) in task.exported_env() {
+        out.push_str(&format!("Environment={}\n", quote(&format!("{}={}", key, value))));
+    }
+    if let Some(time_limit) = task.time_limit {
+        out.push_str(&format!("RuntimeMaxSec={}\n", time_limit));
+    }
+    if let Some(kill_grace) = task.kill_grace {
+        out.push_str(&format!("TimeoutStopSec={}\n", kill_grace));
+    }
+    let limits = [
+        ("LimitAS", task.limits.memory),
+        ("LimitCPU", task.limits.cpu_time),
+        ("LimitNOFILE", task.limits.open_files),
+        ("LimitCORE", task.limits.core_size),
+    ];
+    for (key, value) in limits {
+        if let Some(value) = value {
+            out.push_str(&format!("{}={}\n", key, value));
+        }
+    }
+    if let Some(cgroup) = &task.cgroup {
+        if let Some(memory_max) = cgroup.memory_max {
+            out.push_str(&format!("MemoryMax={}\n", memory_max));
+        }
+        if let Some(cpu_max) = cgroup.cpu_max {
+            out.push_str(&format!("CPUQuota={}%\n", cpu_max));
+        }
+    }
+    if let Some(nice) = task.priority.nice {
+        out.push_str(&format!("Nice={}\n", nice));
+    }
+    if let Some(ionice) = task.priority.ionice {
+        out.push_str(&format!("IOSchedulingClass={}\n", ionice.class()));
+        if let Some(level) = ionice.level() {
+            out.push_str(&format!("IOSchedulingPriority={}\n", level));
+        }
+    }
+    if !task.priority.cpu_affinity.is_empty() {
+        let cpus: Vec<String> = task.priority.cpu_affinity.iter().map(|cpu| cpu.to_string()).collect();
+        out.push_str(&format!("CPUAffinity={}\n", cpus.join(" ")));
+    }
+    if task.time_limit_signal != StopSignal::Sigterm {
+        out.push_str(&format!("KillSignal={}\n", task.time_limit_signal.name()));
+    }
+    let extra_codes: Vec<String> = task.success.exit_codes.iter().filter(|c| **c != 0).map(|c| c.to_string()).collect();
+    if !extra_codes.is_empty() {
+        out.push_str(&format!("SuccessExitStatus={}\n", extra_codes.join(" ")));
+    }
+    match &task.stdin {
+        Some(TaskStdin::File(path)) => out.push_str(&format!("StandardInput=file:{}\n", escape(path))),
+        Some(TaskStdin::Text(text)) => {
+            out.push_str("StandardInput=data\n");
+            // Each line gets a newline at the end, the value supports C escapes and specifiers
+            for line in text.strip_suffix('\n').unwrap_or(text).split('\n') {
+                out.push_str(&format!("StandardInputText={}\n", line.replace('\\', "\\\\").replace('%', "%%")));
+            }
+        }
+        None => {}
+    }
+    if let Some(stdout) = &task.stdout {
+        out.push_str(&format!("StandardOutput=append:{}\n", escape(stdout)));
+    }
+    // Without StandardError, stderr goes wherever stdout goes
+    if let Some(stderr) = &task.stderr {
+        out.push_str(&format!("StandardError=append:{}\n", escape(stderr)));
+    }
+
+    out
+}
+
+fn export_timer(task: &TaskConfig, local_timezone: &str) -> String {
+    let mut out = format!("[Unit]\nDescription=Timer for cron-rs task: {}\n\n[Timer]\n", task.name);
+
+    let timezone = task.timezone.name();
+    let with_timezone = |calendar: String| {
+        if timezone == local_timezone {
+            calendar
+        } else {
+            format!("{} {}", calendar, timezone)
+        }
+    };
+
+    match task.calendar_pattern() {
+        Some(time) => {
+            out.push_str(&format!("OnCalendar={}\n", with_timezone(on_calendar(&time))));
+            // Runs the missed start of the period when the machine is up again, like anacron
+            if matches!(task.schedule, Schedule::Period { .. }) {
+                out.push_str("Persistent=true\n");
+            }
+        }
+        None => {
+            let Schedule::Every { interval, window, .. } = &task.schedule else { unreachable!() };
+            if window.is_some() {
+                warn!("Task '{}': the 'between' and 'on' options can't be represented in systemd, they are ignored", task.name);
+            }
+            out.push_str(&format!("OnActiveSec=0\nOnUnitActiveSec={}s\n", interval.as_secs()));
+        }
+    }
+    // systemd may delay a timer up to a minute by default
+    out.push_str("AccuracySec=1s\n\n[Install]\nWantedBy=timers.target\n");
+
+    out
+}
+
+/// Formats the pattern as an `OnCalendar` expression, e.g. `Mon..Fri *-*-* 08..19:00/15:00`
+fn on_calendar(time: &TimePattern) -> String {
+    let mut out = String::new();
+    if !matches!(time.day_of_week, TimePatternField::Any) {
+        out.push_str(&export_day_of_week(&time.day_of_week));
+        out.push(' ');
+    }
+    out.push_str(&format!(
+        "{}-{}-{} {}:{}:{}",
+        export_field(&time.year, 1970, 4),
+        export_field(&time.month, 1, 2),
+        export_field(&time.day, 1, 2),
+        export_field(&time.hour, 0, 2),
+        export_field(&time.minute, 0, 2),
+        export_field(&time.second, 0, 2),
+    ));
+    out
+}
+
+fn export_field(field: &TimePatternField, min: u32, width: usize) -> String {
+    let value = |v: &u32| format!("{:0width$}", v, width = width);
+    match field {
+        TimePatternField::Any => "*".to_string(),
+        TimePatternField::Value(v) => value(v),
+        TimePatternField::Range(start, end) => format!("{}..{}", value(start), value(end)),
+        TimePatternField::List(values) => values.iter().map(value).collect::<Vec<_>>().join(","),
+        TimePatternField::Ratio(divisor, offset) => {
+            // systemd repetitions need the first value, the smallest one in range that matches
+            let start = field.get_next_valid_value(min, u32::MAX).unwrap_or(*offset);
+            format!("{}/{}", value(&start), divisor)
+        }
+    }
+}
+
+fn export_day_of_week(field: &TimePatternField) -> String {
+    let days: Vec<u32> = match field {
+        TimePatternField::Any => return String::new(),
+        TimePatternField::Value(v) => vec![*v],
+        // systemd weeks start on Monday, so ranges with Sunday are written as lists
+        TimePatternField::Range(start, end) if *start > 0 && *end < 7 => {
+            return format!("{}..{}", DAY_NAMES[*start as usize], DAY_NAMES[*end as usize]);
+        }
+        TimePatternField::Range(start, end) => (*start..=*end).collect(),
+        TimePatternField::List(values) => values.clone(),
+        TimePatternField::Ratio(..) => (0..7).filter(|d| field.matches_value(*d)).collect(),
+    };
+    let mut names: Vec<&str> = vec![];
+    for day in days {
+        let name = DAY_NAMES[day as usize % 7];
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names.join(",")
+}
+
+/// Escapes the `%` specifiers and `$` variables that systemd expands in the unit files
+fn escape(value: &str) -> String {
+    value.replace('%', "%%").replace('$', "$$")
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", escape(value).replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Sets the variable the `after` commands use from the one systemd provides
+const AFTER_HOOK_PREFIX: &str = "export CRON_RS_EXIT_CODE=\"$EXIT_STATUS\"; ";
+
+/// Directories where systemd looks for the service of a timer, after the timer's own directory
+const UNIT_DIRS: [&str; 3] = ["/etc/systemd/system", "/usr/lib/systemd/system", "/lib/systemd/system"];
+
+/// Entries of a unit file, as `(section, key, value)` in the order they appear
+struct UnitFile {
+    entries: Vec<(String, String, String)>,
+}
+
+impl UnitFile {
+    fn parse(text: &str) -> Self {
+        let mut entries = vec![];
+        let mut section = String::new();
+        let mut pending = String::new();
+
+        for line in text.lines() {
+            let line = line.trim();
+            if pending.is_empty() && (line.is_empty() || line.starts_with('#') || line.starts_with(';')) {
+                continue;
+            }
+            // A line ending in `\` continues in the next one
+            if let Some(line) = line.strip_suffix('\\') {
+                pending.push_str(line.trim_end());
+                pending.push(' ');
+                continue;
+            }
+            pending.push_str(line);
+            let line = std::mem::take(&mut pending);
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.to_string();
+            } else if let Some((key, value)) = line.split_once('=') {
+                entries.push((section.clone(), key.trim().to_string(), value.trim().to_string()));
+            }
+        }
+
+        UnitFile { entries }
+    }
+
+    /// Last value of the key, like systemd does for single-value settings
+    fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.get_all(section, key).pop()
+    }
+
+    /// All the values of the key, an empty value resets the previous ones
+    fn get_all(&self, section: &str, key: &str) -> Vec<&str> {
+        let mut values = vec![];
+        for (s, k, v) in &self.entries {
+            if s == section && k == key {
+                if v.is_empty() {
+                    values.clear();
+                } else {
+                    values.push(v.as_str());
+                }
+            }
+        }
+        values
+    }
+
+    fn keys(&self, section: &str) -> impl Iterator<Item = &str> {
+        let section = section.to_string();
+        self.entries.iter().filter(move |(s, _, _)| *s == section).map(|(_, k, _)| k.as_str())
+    }
+}
+
+/// Timer units in the path, a `.timer` file or a directory like /etc/systemd/system
+pub fn list_timer_files(path: &Path) -> Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut files = vec![];
+    for entry in std::fs::read_dir(path).with_context(|| format!("Failed to read directory {}", path.display()))? {
+        let file = entry?.path();
+        if file.is_file() && file.extension().is_some_and(|ext| ext == "timer") {
+            files.push(file);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Reads each timer and the service it activates, and converts them into a config file
+pub fn read_timer_units(timers: &[PathBuf]) -> Result<ConfigFile> {
+    let mut tasks = vec![];
+
+    for timer_path in timers {
+        let timer = std::fs::read_to_string(timer_path)
+            .with_context(|| format!("Failed to read timer {}", timer_path.display()))?;
+        let name = timer_path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+
+        let service_name = UnitFile::parse(&timer)
+            .get("Timer", "Unit")
+            .map(str::to_string)
+            .unwrap_or_else(|| format!("{}.service", name));
+        let service_dirs = timer_path.parent().into_iter().chain(UNIT_DIRS.iter().map(Path::new));
+        let Some(service_path) = service_dirs.map(|dir| dir.join(&service_name)).find(|p| p.is_file()) else {
+            warn!("Timer '{}': service {} not found, the timer is skipped", name, service_name);
+            continue;
+        };
+        let service = std::fs::read_to_string(&service_path)
+            .with_context(|| format!("Failed to read service {}", service_path.display()))?;
+
+        tasks.extend(parse_timer_unit(&name, &timer, &service).with_context(|| format!("Invalid timer '{}'", name))?);
+    }
+
+    Ok(ConfigFile {
+        tasks,
+        logging: Some(LoggingConfig::default()),
+        alerts: Some(AlertConfig::default()),
+        ..Default::default()
+    })
+}
+
+/// Converts a timer and its service into tasks, one for each `OnCalendar` of the timer
+fn parse_timer_unit(name: &str, timer_text: &str, service_text: &str) -> Result<Vec<TaskDefinition>> {
+    let timer = UnitFile::parse(timer_text);
+    let service = UnitFile::parse(service_text);
+
+    let mut task = parse_service(&service)?;
+    // Use the original name of tasks exported by cron-rs
+    task.name = match service.get("Unit", "Description").and_then(|d| d.strip_prefix("cron-rs task: ")) {
+        Some(description) => description.to_string(),
+        None => name.to_string(),
+    };
+    // systemd doesn't start a service again while it's running
+    task.avoid_overlapping = true;
+    if service.get("Unit", "ConditionACPower") == Some("true") {
+        task.only_on_ac_power = Some(true);
+    }
+    if let Some(path) = service.get("Unit", "ConditionPathExists").filter(|path| !path.starts_with('!')) {
+        task.only_if = Some(OnlyIfConfig { path_exists: Some(path.replace("%%", "%")), ..Default::default() });
+    }
+
+    let ignored: Vec<&str> = timer
+        .keys("Timer")
+        .filter(|k| ["OnBootSec", "OnStartupSec", "RandomizedDelaySec", "Persistent", "WakeSystem"].contains(k))
+        .collect();
+    if !ignored.is_empty() {
+        warn!("Timer '{}': ignoring options without an equivalent in cron-rs: {}", name, ignored.join(", "));
+    }
+
+    let calendars = timer.get_all("Timer", "OnCalendar");
+    if calendars.is_empty() {
+        let interval = timer.get("Timer", "OnUnitActiveSec").or_else(|| timer.get("Timer", "OnUnitInactiveSec"));
+        let Some(interval) = interval else {
+            warn!("Timer '{}': only timers with OnCalendar or OnUnitActiveSec can be converted, the timer is skipped", name);
+            return Ok(vec![]);
+        };
+        task.every = Some(format_interval(parse_timespan(interval)?));
+        return Ok(vec![task]);
+    }
+
+    let mut tasks = vec![];
+    for (i, calendar) in calendars.iter().enumerate() {
+        let (when, timezone) = convert_calendar(calendar).with_context(|| format!("Invalid OnCalendar '{}'", calendar))?;
+        let mut task = task.clone();
+        if i > 0 {
+            task.name = format!("{} {}", task.name, i + 1);
+        }
+        task.when = Some(TimePatternConfig::Long(when));
+        task.timezone = timezone;
+        tasks.push(task);
+    }
+    Ok(tasks)
+}
+
+fn parse_service(service: &UnitFile) -> Result<TaskDefinition> {
+    let exec = service.get_all("Service", "ExecStart");
+    if exec.is_empty() {
+        bail!("The service has no ExecStart");
+    }
+
+    let mut task = TaskDefinition { enabled: true, ..Default::default() };
+
+    // Commands of oneshot services run one after the other
+    let mut commands = vec![];
+    for line in &exec {
+        // Prefixes like `-` (ignore failures) or `+` (run with full privileges)
+        let line = line.trim_start_matches(['-', '@', ':', '+', '!']);
+        match &split_words(line)[..] {
+            [shell, flag, cmd] if flag == "-c" && exec.len() == 1 => {
+                if shell != "/bin/sh" {
+                    task.shell = Some(shell.clone());
+                }
+                commands.push(cmd.clone());
+            }
+            _ => commands.push(unescape(line)),
+        }
+    }
+    task.cmd = commands.join(" && ");
+
+    let hook = |line: &&str| {
+        let line = line.trim_start_matches(['-', '@', ':', '+', '!']);
+        match &split_words(line)[..] {
+            [_, flag, cmd] if flag == "-c" => cmd.clone(),
+            _ => unescape(line),
+        }
+    };
+    task.before = service.get_all("Service", "ExecStartPre").iter().map(hook).collect();
+    task.after = service
+        .get_all("Service", "ExecStopPost")
+        .iter()
+        .map(hook)
+        .map(|cmd| cmd.strip_prefix(AFTER_HOOK_PREFIX).map(str::to_string).unwrap_or(cmd))
+        .collect();
+
+    task.run_as = match (service.get("Service", "User"), service.get("Service", "Group")) {
+        (Some(user), Some(group)) => Some(format!("{}:{}", user, group)),
+        (Some(user), None) => Some(user.to_string()),
+        (None, _) => None,
+    };
+    task.working_directory = service.get("Service", "WorkingDirectory").map(|dir| unescape(dir.trim_start_matches('-')));
+    task.chroot = service.get("Service", "RootDirectory").map(unescape);
+    if matches!(service.get("Service", "PrivateNetwork"), Some("yes" | "true" | "on" | "1")) {
+        task.network = Some(TaskNetwork::None);
+    }
+    task.env_file = service.get("Service", "EnvironmentFile").map(|file| unescape(file.trim_start_matches('-')));
+
+    let mut env = HashMap::new();
+    for line in service.get_all("Service", "Environment") {
+        for assignment in split_words(line) {
+            if let Some((key, value)) = assignment.split_once('=') {
+                env.insert(key.to_string(), value.to_string());
+            }
+        }
+    }
+    if !env.is_empty() {
+        task.env = Some(env);
+    }
+
+    if let Some(limit) = service.get("Service", "RuntimeMaxSec").filter(|l| *l != "infinity") {
+        task.time_limit = Some(format_interval(parse_timespan(limit)?));
+    }
+    if let Some(grace) = service.get("Service", "TimeoutStopSec").filter(|g| *g != "infinity") {
+        task.kill_grace = Some(format_interval(parse_timespan(grace)?));
+    }
+    let limit = |key: &str| service.get("Service", key).filter(|v| *v != "infinity");
+    let mut limits = ResourceLimitsConfig::default();
+    if let Some(memory) = limit("LimitAS") {
+        parse_size(memory)?;
+        limits.memory = Some(memory.to_string());
+    }
+    if let Some(cpu) = limit("LimitCPU") {
+        limits.cpu_time = Some(format_interval(parse_timespan(cpu)?));
+    }
+    if let Some(files) = limit("LimitNOFILE") {
+        // `soft:hard`, cron-rs sets both to the same value
+        let hard = files.rsplit(':').next().unwrap_or(files);
+        limits.open_files = Some(hard.parse().with_context(|| format!("Invalid LimitNOFILE={}", files))?);
+    }
+    if let Some(core) = limit("LimitCORE") {
+        parse_size(core)?;
+        limits.core_size = Some(core.to_string());
+    }
+    if limits != ResourceLimitsConfig::default() {
+        task.limits = Some(limits);
+    }
+    let mut cgroup = CgroupConfig::default();
+    if let Some(memory) = limit("MemoryMax") {
+        parse_size(memory)?;
+        cgroup.memory_max = Some(memory.to_string());
+    }
+    if let Some(cpu) = service.get("Service", "CPUQuota") {
+        cgroup.cpu_max = Some(cpu.to_string());
+    }
+    if cgroup != CgroupConfig::default() {
+        task.cgroup = Some(cgroup);
+    }
+    if let Some(nice) = service.get("Service", "Nice") {
+        task.nice = Some(nice.parse().with_context(|| format!("Invalid Nice={}", nice))?);
+    }
+    if let Some(class) = service.get("Service", "IOSchedulingClass") {
+        // systemd uses `best-effort` and also accepts the numbers of the classes
+        let class = match class {
+            "1" => "realtime",
+            "2" => "best-effort",
+            "3" => "idle",
+            class => class,
+        };
+        let ionice = match service.get("Service", "IOSchedulingPriority").filter(|_| class != "idle") {
+            Some(level) => format!("{}:{}", class, level),
+            None => class.to_string(),
+        };
+        IoPriority::parse(&ionice).with_context(|| format!("Invalid IOSchedulingClass={}", class))?;
+        task.ionice = Some(ionice);
+    }
+    if let Some(cpus) = service.get("Service", "CPUAffinity").filter(|c| !c.is_empty()) {
+        task.cpu_affinity = Some(cpus.split([' ', ',']).filter(|c| !c.is_empty()).collect::<Vec<_>>().join(","));
+    }
+    if let Some(signal) = service.get("Service", "KillSignal") {
+        let signals = [StopSignal::Sigint, StopSignal::Sigterm, StopSignal::Sighup, StopSignal::Sigkill];
+        // systemd also accepts the names without the SIG prefix
+        match signals.into_iter().find(|s| s.name() == signal || s.name().strip_prefix("SIG") == Some(signal)) {
+            Some(signal) => task.time_limit_signal = Some(signal),
+            None => warn!("Ignoring unsupported KillSignal={}, the task will get a SIGTERM", signal),
+        }
+    }
+
+    let output_file = |key: &str| {
+        let value = service.get("Service", key)?;
+        let path = value.strip_prefix("append:").or_else(|| value.strip_prefix("file:"))?;
+        Some(unescape(path))
+    };
+    task.stdin = service
+        .get("Service", "StandardInput")
+        .and_then(|value| value.strip_prefix("file:"))
+        .map(unescape);
+    let text = service.get_all("Service", "StandardInputText");
+    if !text.is_empty() {
+        let lines = text.iter().map(|line| format!("{}\n", line.replace("%%", "%").replace("\\\\", "\\")));
+        task.stdin_text = Some(lines.collect());
+    }
+    task.stdout = output_file("StandardOutput");
+    task.stderr = output_file("StandardError");
+
+    Ok(task)
+}
+
+/// Converts an `OnCalendar` expression, like `Mon..Fri *-*-* 08:00` or `weekly`, into a time pattern and its timezone
+fn convert_calendar(calendar: &str) -> Result<(ExplodedTimePatternConfig, Option<String>)> {
+    let calendar = match calendar.to_lowercase().as_str() {
+        "minutely" => "*-*-* *:*:00",
+        "hourly" => "*-*-* *:00:00",
+        "daily" => "*-*-* 00:00:00",
+        "weekly" => "Mon *-*-* 00:00:00",
+        "monthly" => "*-*-01 00:00:00",
+        "quarterly" => "*-01,04,07,10-01 00:00:00",
+        "semiannually" => "*-01,07-01 00:00:00",
+        "yearly" | "annually" => "*-01-01 00:00:00",
+        _ => calendar,
+    };
+
+    let mut tokens: Vec<&str> = calendar.split_whitespace().collect();
+    let timezone = match tokens.last() {
+        Some(last) if tokens.len() > 1 && last.parse::<Tz>().is_ok() => tokens.pop().map(str::to_string),
+        _ => None,
+    };
+
+    let mut day_of_week = None;
+    let mut date = "*-*-*";
+    let mut time = "00:00:00";
+    for token in tokens {
+        if token.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            day_of_week = Some(convert_component(token, 1, 7, true)?);
+        } else if token.contains(':') {
+            time = token;
+        } else if token.contains('-') {
+            date = token;
+        } else {
+            bail!("Unknown part '{}'", token);
+        }
+    }
+
+    let date: Vec<&str> = date.split('-').collect();
+    let (year, month, day) = match date[..] {
+        [year, month, day] => (year, month, day),
+        [month, day] => ("*", month, day),
+        _ => bail!("Invalid date"),
+    };
+    if day.contains('~') {
+        bail!("The last days of the month ('~') are not supported");
+    }
+
+    let time: Vec<&str> = time.split(':').collect();
+    let (hour, minute, second) = match time[..] {
+        [hour, minute, second] => (hour, minute, second.split('.').next().unwrap_or(second)),
+        [hour, minute] => (hour, minute, "00"),
+        _ => bail!("Invalid time"),
+    };
+
+    let pattern = ExplodedTimePatternConfig {
+        second: Some(convert_component(second, 0, 59, false)?),
+        minute: Some(convert_component(minute, 0, 59, false)?),
+        hour: Some(convert_component(hour, 0, 23, false)?),
+        day: Some(convert_component(day, 1, 31, false)?),
+        month: Some(convert_component(month, 1, 12, false)?),
+        year: Some(convert_component(year, 1970, 9999, false)?),
+        day_of_week,
+    };
+    Ok((pattern, timezone))
+}
+
+/// Converts a component of a calendar expression, like `*`, `00/15`, `1..5` or `Mon,Fri`. Days of the week go from
+/// Monday = 1 to Sunday = 7 like in systemd and are converted to the cron-rs numbers, with Sunday = 0
+fn convert_component(text: &str, min: u32, max: u32, is_day_of_week: bool) -> Result<ExplodedTimePatternFieldConfig> {
+    let text_field = |text: String| Ok(ExplodedTimePatternFieldConfig::Text(text));
+    if text == "*" {
+        return text_field(text.to_string());
+    }
+
+    let parse_value = |value: &str| -> Result<u32> {
+        let n = if is_day_of_week {
+            let prefix = value.get(..3).unwrap_or(value).to_lowercase();
+            DAY_NAMES.iter().position(|day| day.to_lowercase() == prefix).map(|n| if n == 0 { 7 } else { n as u32 })
+        } else {
+            value.parse().ok()
+        };
+        n.filter(|n| (min..=max).contains(n)).ok_or_else(|| anyhow!("Invalid value '{}'", value))
+    };
+
+    let mut values = vec![];
+    for item in text.split(',') {
+        let (base, step) = match item.split_once('/') {
+            Some((base, step)) => (base, Some(step.parse::<u32>().map_err(|_| anyhow!("Invalid step '{}'", step))?)),
+            None => (item, None),
+        };
+
+        // Keep the ratio syntax when it means the same in cron-rs
+        if text == item && min == 0 && (base == "*" || base.parse() == Ok(0)) {
+            if let Some(step) = step.filter(|s| *s > 0) {
+                return text_field(format!("*/{}", step));
+            }
+        }
+
+        let (start, end) = if base == "*" {
+            (min, max)
+        } else if let Some((start, end)) = base.split_once("..") {
+            (parse_value(start)?, parse_value(end)?)
+        } else {
+            let start = parse_value(base)?;
+            (start, if step.is_some() { max } else { start })
+        };
+        if start > end {
+            bail!("Range {}..{} is empty", start, end);
+        }
+        let step = step.unwrap_or(1);
+        if step == 0 {
+            bail!("The step must be greater than 0");
+        }
+        values.extend((start..=end).step_by(step as usize));
+    }
+
+    if is_day_of_week {
+        values.iter_mut().filter(|v| **v == 7).for_each(|v| *v = 0);
+    }
+    values.sort();
+    values.dedup();
+
+    let all = if is_day_of_week { 7 } else { (min..=max).count() };
+    let text = match &values[..] {
+        _ if values.len() == all => "*".to_string(),
+        [value] => value.to_string(),
+        [first, .., last] if (last - first) as usize + 1 == values.len() => format!("{}..{}", first, last),
+        _ => format!("[{}]", values.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(", ")),
+    };
+    text_field(text)
+}
+
+/// Parses a systemd time span, like `90`, `5min` or `1h 30m`, into seconds
+fn parse_timespan(text: &str) -> Result<u64> {
+    let mut total = 0;
+    let mut rest = text.trim();
+    if rest.is_empty() {
+        bail!("Empty time span");
+    }
+
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+        let amount: u64 = rest[..digits].parse().map_err(|_| anyhow!("Invalid time span '{}'", text))?;
+        rest = rest[digits..].trim_start();
+        let unit_len = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+        let unit = &rest[..unit_len];
+        rest = rest[unit_len..].trim_start();
+
+        let seconds = match unit {
+            "" | "s" | "sec" | "second" | "seconds" => 1,
+            "m" | "min" | "minute" | "minutes" => 60,
+            "h" | "hr" | "hour" | "hours" => 3600,
+            "d" | "day" | "days" => 24 * 3600,
+            "w" | "week" | "weeks" => 7 * 24 * 3600,
+            _ => bail!("Unsupported time unit '{}' in '{}'", unit, text),
+        };
+        total += amount * seconds;
+    }
+    Ok(total)
+}
+
+/// Formats seconds with the biggest unit that fits, e.g. `7200` => `2 hours`
+fn format_interval(seconds: u64) -> String {
+    let units = [("week", 7 * 24 * 3600), ("day", 24 * 3600), ("hour", 3600), ("minute", 60)];
+    let (unit, size) = units.into_iter().find(|(_, size)| seconds > 0 && seconds.is_multiple_of(*size)).unwrap_or(("second", 1));
+    let amount = seconds / size;
+    format!("{} {}{}", amount, unit, if amount == 1 { "" } else { "s" })
+}
+
+/// Splits a command line into words, like systemd does with its double and single quotes
+fn split_words(line: &str) -> Vec<String> {
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quote = None;
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (_, '\\') => {
+                word.extend(chars.next());
+                in_word = true;
+            }
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (Some(q), _) if q == c => quote = None,
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(unescape(&std::mem::take(&mut word)));
+                    in_word = false;
+                }
+            }
+            _ => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(unescape(&word));
+    }
+    words
+}
+
+/// Reverts the escaping of `%` specifiers and `$` variables
+fn unescape(value: &str) -> String {
+    value.replace("%%", "%").replace("$$", "$")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::file::ConfigFile;
+
+    #[test]
+    fn test_export_systemd() {
+        let config_file: ConfigFile = serde_yml::from_str(
+            r#"
+tasks:
+  - name: Daily report
+    cmd: echo "100%" $HOME
+    when: 'Mon..Fri *-*-1 03:30:00'
+    run_as: www-data:web
+    env:
+      LANG: C
+    timezone: UTC
+  - name: poll
+    cmd: check
+    every: 15 minutes
+    align: true
+    between: 08:00..20:00
+    on: [Sun, Sat]
+    timezone: UTC
+  - name: poll
+    cmd: check
+    every: 90 seconds
+    timezone: UTC
+"#,
+        )
+        .unwrap();
+        let config = crate::config::parse_config_file(&config_file).unwrap();
+
+        let units = export_systemd(&config);
+        let names: Vec<_> = units.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(names, ["cron-rs-daily-report", "cron-rs-poll", "cron-rs-poll-2"]);
+
+        let service = &units[0].service;
+        assert!(service.contains("ExecStart=/bin/sh -c \"echo \\\"100%%\\\" $$HOME\"\n"));
+        assert!(service.contains("User=www-data\nGroup=web\n"));
+        assert!(service.contains("Environment=\"LANG=C\"\n"));
+
+        assert!(units[0].timer.contains("OnCalendar=Mon..Fri *-*-01 03:30:00"));
+        assert!(units[1].timer.contains("OnCalendar=Sun,Sat *-*-* 08..19:00/15:00"));
+        assert!(units[2].timer.contains("OnUnitActiveSec=90s\n"));
+    }
+
+    #[test]
+    fn test_parse_timer_unit() {
+        let timer = "\
+[Timer]
+OnCalendar=Mon..Fri *-*-* 08..18:00/15 Europe/Madrid
+OnCalendar=weekly
+Persistent=true
+";
+        let service = r#"
+[Unit]
+Description=Check the queue
+
+[Service]
+Type=oneshot
+ExecStart=/bin/bash -c "echo \"100%%\" && \
+  check"
+User=www-data
+Environment="A=1" B=2
+RuntimeMaxSec=1h 30min
+StandardOutput=append:/var/log/check.log
+"#;
+        let tasks = parse_timer_unit("check", timer, service).unwrap();
+        assert_eq!(tasks.len(), 2);
+        assert_eq!(tasks[0].name, "check");
+        assert_eq!(tasks[1].name, "check 2");
+        assert_eq!(tasks[0].cmd, "echo \"100%\" && check");
+        assert_eq!(tasks[0].shell.as_deref(), Some("/bin/bash"));
+        assert_eq!(tasks[0].run_as.as_deref(), Some("www-data"));
+        assert_eq!(tasks[0].env.as_ref().unwrap()["A"], "1");
+        assert_eq!(tasks[0].time_limit.as_deref(), Some("90 minutes"));
+        assert_eq!(tasks[0].stdout.as_deref(), Some("/var/log/check.log"));
+        assert_eq!(tasks[0].timezone.as_deref(), Some("Europe/Madrid"));
+
+        let text = |when: &Option<TimePatternConfig>, field: fn(&ExplodedTimePatternConfig) -> &Option<ExplodedTimePatternFieldConfig>| {
+            match when {
+                Some(TimePatternConfig::Long(when)) => match field(when) {
+                    Some(ExplodedTimePatternFieldConfig::Text(text)) => text.clone(),
+                    other => panic!("Unexpected field {:?}", other),
+                },
+                other => panic!("Expected a long time pattern, got {:?}", other),
+            }
+        };
+        assert_eq!(text(&tasks[0].when, |w| &w.day_of_week), "1..5");
+        assert_eq!(text(&tasks[0].when, |w| &w.hour), "8..18");
+        assert_eq!(text(&tasks[0].when, |w| &w.minute), "*/15");
+        assert_eq!(text(&tasks[0].when, |w| &w.second), "0");
+        assert_eq!(text(&tasks[1].when, |w| &w.day_of_week), "1");
+    }
+
+    #[test]
+    fn test_systemd_round_trip() {
+        let config_file: ConfigFile = serde_yml::from_str(
+            r#"
+tasks:
+  - name: Daily report
+    cmd: echo "100%" $HOME
+    when: '[Sat, Sun] *-*-1 03:30:00'
+    stdin_text: |
+      SELECT 100%;
+      \q
+    before: ['mkdir -p /tmp/report']
+    after: ['echo "exit $CRON_RS_EXIT_CODE"']
+    time_limit: 1 hour
+    kill_grace: 30 seconds
+    time_limit_signal: SIGINT
+    limits:
+      memory: 1 GB
+      open_files: 256
+    cgroup:
+      cpu_max: 50%
+    nice: 10
+    ionice: best-effort:7
+    cpu_affinity: 0-2,5
+    only_on_ac_power: true
+    only_if:
+      path_exists: /srv/report/input.csv
+"#,
+        )
+        .unwrap();
+        let config = crate::config::parse_config_file(&config_file).unwrap();
+        let unit = &export_systemd(&config)[0];
+
+        let tasks = parse_timer_unit(&unit.name, &unit.timer, &unit.service).unwrap();
+        assert_eq!(tasks[0].name, "Daily report");
+        assert_eq!(tasks[0].cmd, "echo \"100%\" $HOME");
+        assert_eq!(tasks[0].stdin_text.as_deref(), Some("SELECT 100%;\n\\q\n"));
+        assert_eq!(tasks[0].before, vec!["mkdir -p /tmp/report"]);
+        assert_eq!(tasks[0].after, vec!["echo \"exit $CRON_RS_EXIT_CODE\""]);
+        assert_eq!(tasks[0].kill_grace.as_deref(), Some("30 seconds"));
+        assert_eq!(tasks[0].time_limit_signal, Some(StopSignal::Sigint));
+        let limits = tasks[0].limits.as_ref().unwrap();
+        assert_eq!((limits.memory.as_deref(), limits.open_files), (Some("1073741824"), Some(256)));
+        assert_eq!(tasks[0].cgroup.as_ref().unwrap().cpu_max.as_deref(), Some("50%"));
+        assert_eq!(tasks[0].nice, Some(10));
+        assert_eq!(tasks[0].ionice.as_deref(), Some("best-effort:7"));
+        assert_eq!(tasks[0].cpu_affinity.as_deref(), Some("0,1,2,5"));
+        assert_eq!(tasks[0].only_on_ac_power, Some(true));
+        assert_eq!(tasks[0].only_if.as_ref().and_then(|only_if| only_if.path_exists.as_deref()), Some("/srv/report/input.csv"));
+        let Some(TimePatternConfig::Long(when)) = &tasks[0].when else {
+            panic!("Expected a long time pattern");
+        };
+        assert!(matches!(&when.day_of_week, Some(ExplodedTimePatternFieldConfig::Text(t)) if t == "[0, 6]"));
+        assert!(matches!(&when.day, Some(ExplodedTimePatternFieldConfig::Text(t)) if t == "1"));
+    }
+}
@@ -0,0 +1,768 @@
+use crate::alerts::{send_alert, Alert, AlertConfig, ExecutionContext, TaskExecutionDetails};
+use crate::cgroup::{ExecutionCgroup, DEFAULT_CGROUP_ROOT};
+use crate::cluster_lock::ClusterLock;
+use crate::config::file::StopSignal;
+use crate::config::TaskConfig;
+use crate::executor;
+use crate::executor::RunContext;
+use crate::hooks;
+use crate::resource_usage::{wait_with_usage, ResourceUsage};
+use crate::sentry::{report_task_failure, SentryConfig};
+use crate::sqlite_logger::{AlertAttempt, ExecutionAttempt, ExecutionFailure, ExecutionSuccess, SqliteLogger};
+use crate::utils::{format_duration, forward_output, join_output_forwarders, read_output_from, set_process_group, set_process_priority, set_resource_limits, terminate_child, try_lock_file};
+#[cfg(unix)]
+use crate::utils::set_user;
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use log::{error, info, warn};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+static TASK_ID_COUNTER: AtomicU32 = AtomicU32::new(1);
+
+#[derive(Debug)]
+pub struct TaskExecutor {
+    pub alerts: AlertConfig,
+    pub sqlite_logger: Option<SqliteLogger>,
+    /// Parent of the cgroups of the tasks with `cgroup`
+    pub cgroup_root: PathBuf,
+    /// Where the failures are reported, besides the alerts
+    pub sentry: Option<SentryConfig>,
+}
+
+#[derive(Debug)]
+pub struct ExecutionResult {
+    pub task_id: u32,
+    pub run_id: String,
+    pub pid: u32,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub duration: Duration,
+    pub exit_code: i32,
+    pub stdout: String,
+    pub stderr: String,
+    pub success: bool,
+    /// Only known when the task runs in a cgroup
+    pub peak_memory: Option<u64>,
+    /// Only known on Linux
+    pub usage: Option<ResourceUsage>,
+}
+
+impl TaskExecutor {
+    pub fn new(alerts: AlertConfig, sqlite_logger: Option<SqliteLogger>) -> Self {
+        Self {
+            alerts,
+            sqlite_logger,
+            cgroup_root: PathBuf::from(DEFAULT_CGROUP_ROOT),
+            sentry: None,
+        }
+    }
+
+    /// Execute a task immediately, returning the execution result
+    pub async fn execute_task(&self, task: &TaskConfig) -> anyhow::Result<ExecutionResult> {
+        // Like the scheduled runs, it doesn't overlap with the process that holds the lock_file
+        let _lock_file = match &task.lock_file {
+            Some(path) => Some(
+                try_lock_file(path)
+                    .map_err(|e| anyhow!("Failed to lock {} for task '{}': {}", path.display(), task.name, e))?
+                    .ok_or_else(|| anyhow!("Task '{}' can't run, {} is locked by another process", task.name, path.display()))?,
+            ),
+            None => None,
+        };
+        let cluster_lock = match &task.cluster_lock {
+            Some(url) => Some(ClusterLock::acquire(url, &task.name).await.map_err(|e| anyhow!("Task '{}' can't run, {:#}", task.name, e))?),
+            None => None,
+        };
+        let result = self.run_task(task).await;
+        if let Some(cluster_lock) = cluster_lock {
+            cluster_lock.release().await;
+        }
+        result
+    }
+
+    async fn run_task(&self, task: &TaskConfig) -> anyhow::Result<ExecutionResult> {
+        let start_time = Utc::now();
+        let context = RunContext::new(start_time);
+        let run_id = context.run_id.clone();
+        let stdout_path = task.stdout_path(&run_id, start_time);
+        let stderr_path = task.stderr_path(&run_id, start_time);
+
+        // Create output directories if needed
+        self.create_output_directories(&stdout_path, &stderr_path, &task.name).await?;
+
+        // Create output files
+        let stdout_file = task.open_stdout(&run_id, start_time).map_err(|e| {
+            anyhow!(
+                "Failed to create stdout file {} for task '{}': {}",
+                stdout_path.display(),
+                task.name,
+                e
+            )
+        })?;
+
+        let stderr_file = if task.combine_output { stdout_file.try_clone() } else { task.open_stderr(&run_id, start_time) };
+        let stderr_file = stderr_file.map_err(|e| {
+            anyhow!(
+                "Failed to create stderr file {} for task '{}': {}",
+                stderr_path.display(),
+                task.name,
+                e
+            )
+        })?;
+
+        // Files in append mode keep the output of previous executions
+        let stdout_offset = stdout_file.metadata().map(|m| m.len()).unwrap_or(0);
+        let stderr_offset = stderr_file.metadata().map(|m| m.len()).unwrap_or(0);
+
+        // Build the command, with the environment variables and the working directory
+        let (mut cmd, env_input) = executor::command(task, &context)?;
+
+        // Set input redirection
+        let (stdin, stdin_input) = executor::open_stdin(task, env_input)
+            .map_err(|e| anyhow!("Failed to open the stdin of task '{}': {}", task.name, e))?;
+        cmd.stdin(stdin);
+
+        // The before commands write to the same files, ahead of the output of the main command
+        let hook_files = if task.before.is_empty() { None } else { Some((stdout_file.try_clone()?, stderr_file.try_clone()?)) };
+
+        // Set output redirection, with log_output the output is written to the files after logging it
+        let mut output_files = None;
+        if task.log_output {
+            cmd.stdout(Stdio::piped());
+            cmd.stderr(Stdio::piped());
+            output_files = Some((stdout_file, stderr_file));
+        } else {
+            cmd.stdout(Stdio::from(stdout_file));
+            cmd.stderr(Stdio::from(stderr_file));
+        }
+
+        // Set user/group if specified
+        let user = match &task.run_as {
+            #[cfg(unix)]
+            Some(run_as) => {
+                let (uid, gid) = self.get_uid_and_gid(run_as)?;
+                set_user(&mut cmd, uid, gid, task.login_env)?;
+                Some((uid, gid))
+            }
+            // Running it as the user of the scheduler instead could give it more access than it should have
+            #[cfg(not(unix))]
+            Some(run_as) => return Err(anyhow!("Task '{}' cannot run as '{}', unsupported on this platform", task.name, run_as)),
+            None => None,
+        };
+
+        if let Some((stdout, stderr)) = &hook_files {
+            hooks::run_before_hooks(task, &run_id, stdout, stderr, user)
+                .await
+                .map_err(|reason| anyhow!("Task '{}' (run {}) aborted, {}", task.name, run_id, reason))?;
+        }
+
+        let start_instant = Instant::now();
+        let task_id = TASK_ID_COUNTER.fetch_add(1, Ordering::Relaxed);
+
+        // Spawn process, in its own process group so the processes it starts can be stopped with it
+        set_process_group(cmd.as_std_mut());
+        set_resource_limits(&mut cmd, task.limits)
+            .map_err(|e| anyhow!("Task '{}' failed to set its limits: {}", task.name, e))?;
+        set_process_priority(&mut cmd, &task.priority);
+        let cgroup = match &task.cgroup {
+            Some(limits) => {
+                let name = format!("{}-{}", sanitise_file_name::sanitise(&task.name), run_id);
+                let cgroup = ExecutionCgroup::create(&self.cgroup_root, &name, limits)
+                    .map_err(|e| anyhow!("Task '{}' failed to create its cgroup: {:#}", task.name, e))?;
+                Some(cgroup)
+            }
+            None => None,
+        };
+        let mut child = cmd.spawn().map_err(|e| {
+            if let Some(cgroup) = &cgroup {
+                cgroup.remove();
+            }
+            anyhow!("Task '{}' failed to start: {}", task.name, e)
+        })?;
+
+        let pid = child.id().unwrap_or(0);
+        info!(task = task.name.as_str(), run_id = run_id.as_str(); "Task '{}' (run {}) started with PID: {}", task.name, run_id, pid);
+
+        if let Some(cgroup) = &cgroup {
+            if let Err(e) = cgroup.add_process(pid) {
+                terminate_child(&mut child, StopSignal::Sigkill, Duration::ZERO, &task.name).await?;
+                cgroup.remove();
+                return Err(anyhow!("Task '{}' stopped, {:#}", task.name, e));
+            }
+        }
+
+        if let Some(input) = stdin_input {
+            input.write(&mut child, &task.name);
+        }
+
+        let mut output_forwarders = vec![];
+        if let Some((stdout_file, stderr_file)) = output_files {
+            if let Some(pipe) = child.stdout.take() {
+                output_forwarders.push(forward_output(pipe, stdout_file, task.name.clone(), run_id.clone(), log::Level::Info));
+            }
+            if let Some(pipe) = child.stderr.take() {
+                output_forwarders.push(forward_output(pipe, stderr_file, task.name.clone(), run_id.clone(), log::Level::Warn));
+            }
+        }
+
+        // Log execution attempt
+        if let Some(sqlite_logger) = &self.sqlite_logger {
+            let attempt = ExecutionAttempt {
+                task_name: task.name.clone(),
+                task_id,
+                pid,
+                cmd: task.cmd.clone(),
+                start_time,
+                timezone: task.timezone.to_string(),
+                working_directory: task.working_directory.clone(),
+                shell: task.shell.clone(),
+                run_as: task.run_as.clone(),
+                time_limit: task.time_limit,
+            };
+            
+            if let Err(e) = sqlite_logger.log_execution_attempt(&attempt).await {
+                error!("Failed to log execution attempt for task '{}': {}", task.name, e);
+            }
+        }
+
+        // Wait for completion with optional timeout
+        let time_limit = async {
+            match task.time_limit {
+                Some(time_limit) => tokio::time::sleep(Duration::from_secs(time_limit)).await,
+                None => std::future::pending().await,
+            }
+        };
+        let ((exit_status, usage), timed_out) = tokio::select! {
+            status = wait_with_usage(&mut child) => {
+                (status.map_err(|e| anyhow!("Failed to wait for task '{}': {}", task.name, e))?, false)
+            }
+            _ = time_limit => {
+                let signal = task.time_limit_signal;
+                warn!("Task '{}' exceeded time limit of {} seconds, sending {}", task.name, task.time_limit.unwrap_or_default(), signal.name());
+                let status = terminate_child(&mut child, signal, task.kill_grace(), &task.name)
+                    .await
+                    .map_err(|e| anyhow!("Failed to kill task '{}': {}", task.name, e))?;
+                executor::stop(task, &run_id).await;
+                ((status, None), true)
+            }
+            // The task is in another process group, it doesn't get the Ctrl+C from the terminal
+            _ = tokio::signal::ctrl_c() => {
+                warn!("Interrupted, stopping task '{}'", task.name);
+                terminate_child(&mut child, StopSignal::Sigint, task.kill_grace(), &task.name)
+                    .await
+                    .map_err(|e| anyhow!("Failed to kill task '{}': {}", task.name, e))?;
+                executor::stop(task, &run_id).await;
+                if let Some(cgroup) = &cgroup {
+                    cgroup.remove();
+                }
+                return Err(anyhow!("Task '{}' (run {}) was interrupted", task.name, run_id));
+            }
+        };
+
+        let end_time = Utc::now();
+        let duration = start_instant.elapsed();
+        let exit_code = exit_status.code().unwrap_or(-1);
+        join_output_forwarders(output_forwarders).await;
+        let peak_memory = cgroup.as_ref().and_then(|cgroup| cgroup.peak_memory());
+        if let Some(cgroup) = &cgroup {
+            cgroup.remove();
+        }
+        hooks::run_after_hooks(task, &run_id, exit_code, &stdout_path, &stderr_path, user).await;
+
+        // Read output files
+        let stdout = read_output_from(&stdout_path, stdout_offset).await;
+        // With combine_output all the output is in stdout
+        let stderr = if task.combine_output { String::new() } else { read_output_from(&stderr_path, stderr_offset).await };
+        // The task may exit cleanly after the SIGTERM, it still failed
+        let result = match task.time_limit {
+            Some(time_limit) if timed_out => Err(format!("exceeded the time limit of {} seconds", time_limit)),
+            _ => task.success.check(exit_status.code(), &stdout, &stderr),
+        };
+        let success = result.is_ok();
+
+        // Create execution details for alerts
+        let details = TaskExecutionDetails {
+            task_name: task.name.clone(),
+            task_id,
+            run_id: run_id.clone(),
+            pid,
+            exit_code,
+            start_time,
+            duration,
+            error_message: match &result {
+                Ok(_) => String::new(),
+                Err(reason) => format!("Task '{}' failed, {}", task.name, reason),
+            },
+            debug_info: format!("Shell: {}, Command: {}", task.shell.as_deref().unwrap_or(executor::DEFAULT_SHELL), executor::shell_command(task)),
+            stdout: stdout.clone(),
+            stderr: stderr.clone(),
+            peak_memory,
+            usage,
+            context: ExecutionContext::new(task, &context),
+        };
+
+        // Handle success/failure
+        if success {
+            info!(
+                task = task.name.as_str(), run_id = run_id.as_str(), exit_code;
+                "Task '{}' (run {}) completed successfully in {}", task.name, run_id, format_duration(duration)
+            );
+            
+            // Send success alerts
+            for alert in &self.alerts.on_success {
+                let result = send_alert(alert, &details);
+                if let Err(e) = &result {
+                    error!("Failed to send success alert for task '{}': {}", task.name, e);
+                }
+                self.log_alert(alert, &details, &result).await;
+            }
+            for alert in &task.on_success {
+                let result = send_alert(alert, &details);
+                if let Err(e) = &result {
+                    error!("Failed to send task-specific success alert for task '{}': {}", task.name, e);
+                }
+                self.log_alert(alert, &details, &result).await;
+            }
+
+            // Log success to SQLite
+            if let Some(sqlite_logger) = &self.sqlite_logger {
+                let success_log = ExecutionSuccess {
+                    task_name: task.name.clone(),
+                    task_id,
+                    pid,
+                    start_time,
+                    end_time,
+                    duration_seconds: duration.as_secs_f64(),
+                    exit_code,
+                    usage,
+                };
+                
+                if let Err(e) = sqlite_logger.log_execution_success(&success_log).await {
+                    error!("Failed to log execution success for task '{}': {}", task.name, e);
+                }
+            }
+        } else {
+            error!(task = task.name.as_str(), run_id = run_id.as_str(), exit_code; "{} (run {})", details.error_message, run_id);
+
+            if let Some(cmd) = &task.on_failure_cmd {
+                hooks::run_failure_cmd(task, cmd, &details, &stdout_path, &stderr_path, user).await;
+            }
+            
+            // Send failure alerts
+            for alert in &self.alerts.on_failure {
+                let result = send_alert(alert, &details);
+                if let Err(e) = &result {
+                    error!("Failed to send failure alert for task '{}': {}", task.name, e);
+                }
+                self.log_alert(alert, &details, &result).await;
+            }
+            // A single run is the first failure of the escalation
+            let mut escalation = vec![];
+            self.alerts.escalate(1, |alert| {
+                let result = send_alert(alert, &details);
+                if let Err(e) = &result {
+                    error!("Failed to send escalation alert for task '{}', trying the next step: {}", task.name, e);
+                }
+                escalation.push((alert.clone(), result.as_ref().map_err(|e| anyhow!("{:#}", e)).copied()));
+                result
+            });
+            for (alert, result) in &escalation {
+                self.log_alert(alert, &details, result).await;
+            }
+            for alert in &task.on_failure {
+                let result = send_alert(alert, &details);
+                if let Err(e) = &result {
+                    error!("Failed to send task-specific failure alert for task '{}': {}", task.name, e);
+                }
+                self.log_alert(alert, &details, &result).await;
+            }
+            if let Some(sentry) = &self.sentry {
+                // The blocking client of reqwest can't run on the threads of the runtime
+                let (sentry, report) = (sentry.clone(), details.clone());
+                let result = tokio::task::spawn_blocking(move || report_task_failure(&sentry, &report)).await;
+                if let Err(e) = result.map_err(anyhow::Error::from).and_then(|result| result) {
+                    error!("Failed to report task '{}' to Sentry: {:#}", task.name, e);
+                }
+            }
+
+            // Log failure to SQLite
+            if let Some(sqlite_logger) = &self.sqlite_logger {
+                let failure_log = ExecutionFailure {
+                    task_name: task.name.clone(),
+                    task_id,
+                    pid,
+                    start_time,
+                    end_time,
+                    duration_seconds: duration.as_secs_f64(),
+                    exit_code: Some(exit_code),
+                    error_message: details.error_message.clone(),
+                    failure_reason: "Task execution failed".to_string(),
+                    usage,
+                };
+                
+                if let Err(e) = sqlite_logger.log_execution_failure(&failure_log).await {
+                    error!("Failed to log execution failure for task '{}': {}", task.name, e);
+                }
+            }
+        }
+
+        Ok(ExecutionResult {
+            task_id,
+            run_id,
+            pid,
+            start_time,
+            end_time,
+            duration,
+            exit_code,
+            stdout,
+            stderr,
+            success,
+            peak_memory,
+            usage,
+        })
+    }
+
+    /// Records the alert in the execution log, to check later whether it went out
+    async fn log_alert(&self, alert: &Alert, details: &TaskExecutionDetails, result: &anyhow::Result<()>) {
+        let Some(sqlite_logger) = &self.sqlite_logger else { return };
+        let attempt = AlertAttempt {
+            task_name: details.task_name.clone(),
+            run_id: details.run_id.clone(),
+            alert: alert.kind().to_string(),
+            target: alert.target(),
+            time: Utc::now(),
+            error: result.as_ref().err().map(|e| format!("{:#}", e)),
+        };
+        if let Err(e) = sqlite_logger.log_alert_attempt(&attempt).await {
+            error!("Failed to log the alert of task '{}': {}", details.task_name, e);
+        }
+    }
+
+    async fn create_output_directories(&self, stdout_path: &Path, stderr_path: &Path, task_name: &str) -> anyhow::Result<()> {
+        if let Some(path) = stdout_path.parent() {
+            if !path.exists() {
+                tokio::fs::create_dir_all(path).await.map_err(|e| {
+                    anyhow!("Failed to create stdout parent directory for task '{}': {}", task_name, e)
+                })?;
+            }
+        }
+        if let Some(path) = stderr_path.parent() {
+            if !path.exists() {
+                tokio::fs::create_dir_all(path).await.map_err(|e| {
+                    anyhow!("Failed to create stderr parent directory for task '{}': {}", task_name, e)
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    fn get_uid_and_gid(&self, run_as: &str) -> anyhow::Result<(u32, u32)> {
+        use users::{get_group_by_name, get_user_by_name};
+
+        let parts: Vec<&str> = run_as.split(':').collect();
+        let username = parts[0];
+        let groupname = parts.get(1).unwrap_or(&username);
+
+        let user = get_user_by_name(username)
+            .ok_or_else(|| anyhow!("User '{}' not found", username))?;
+
+        let group = get_group_by_name(groupname)
+            .ok_or_else(|| anyhow!("Group '{}' not found", groupname))?;
+
+        Ok((user.uid(), group.gid()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::limits::{ProcessPriority, ResourceLimits};
+    use crate::config::success::{SuccessCriteria, SuccessCriteriaConfig};
+    use crate::config::file::OutputMode;
+    use crate::config::{Schedule, TaskStdin};
+    use chrono_tz::UTC;
+    use std::time::Duration as StdDuration;
+
+    fn create_test_task(name: &str, cmd: &str) -> TaskConfig {
+        TaskConfig {
+            name: name.to_string(),
+            cmd: cmd.to_string(),
+            enabled: true,
+            tags: vec![],
+            schedule: Schedule::Every { interval: StdDuration::from_secs(60), aligned: false, window: None },
+            timezone: UTC,
+            avoid_overlapping: false,
+            lock_file: None,
+            cluster_lock: None,
+            conditions: Default::default(),
+            run_as: None,
+            login_env: false,
+            login_shell: false,
+            chroot: None,
+            network: Default::default(),
+            executor: Default::default(),
+            time_limit: None,
+            kill_grace: None,
+            time_limit_signal: Default::default(),
+            limits: Default::default(),
+            cgroup: None,
+            priority: Default::default(),
+            working_directory: None,
+            env: None,
+            env_file: None,
+            shell: None,
+            stdout: None,
+            stderr: None,
+            output_dir: None,
+            output_mode: None,
+            stdin: None,
+            log_output: false,
+            combine_output: false,
+            success: Default::default(),
+            before: vec![],
+            after: vec![],
+            on_failure_cmd: None,
+            max_runs: None,
+            max_runs_only_successes: false,
+            max_monthly_runtime: None,
+            misfire: Default::default(),
+            on_failure: vec![],
+            on_success: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_simple_task() {
+        let alerts = AlertConfig::default();
+        let executor = TaskExecutor::new(alerts, None);
+        let task = create_test_task("test_echo", "echo 'Hello, World!'");
+        
+        let result = executor.execute_task(&task).await.unwrap();
+        
+        assert!(result.success);
+        assert_eq!(result.exit_code, 0);
+        assert!(result.stdout.contains("Hello, World!"));
+    }
+
+    #[tokio::test]
+    async fn test_execute_failing_task() {
+        let alerts = AlertConfig::default();
+        let executor = TaskExecutor::new(alerts, None);
+        let task = create_test_task("test_fail", "exit 1");
+        
+        let result = executor.execute_task(&task).await.unwrap();
+        
+        assert!(!result.success);
+        assert_eq!(result.exit_code, 1);
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_with_timeout() {
+        let alerts = AlertConfig::default();
+        let executor = TaskExecutor::new(alerts, None);
+        let mut task = create_test_task("test_timeout", "sleep 5");
+        task.time_limit = Some(1); // 1 second timeout
+        
+        let result = executor.execute_task(&task).await.unwrap();
+        
+        assert!(!result.success);
+        assert!(result.duration.as_secs() <= 2); // Should timeout quickly
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_append_output() {
+        let alerts = AlertConfig::default();
+        let executor = TaskExecutor::new(alerts, None);
+        let dir = std::env::temp_dir().join(format!("cron-rs-test-append-{}", std::process::id()));
+        let mut task = create_test_task("test_append", "echo run");
+        task.stdout = Some(dir.join("out.log").to_string_lossy().to_string());
+        task.output_dir = Some(dir.to_string_lossy().to_string());
+        task.output_mode = Some(OutputMode::Append);
+
+        executor.execute_task(&task).await.unwrap();
+        let result = executor.execute_task(&task).await.unwrap();
+
+        // The alerts only get the output of the last execution
+        assert_eq!(result.stdout, "run\n");
+        assert_eq!(std::fs::read_to_string(dir.join("out.log")).unwrap(), "run\nrun\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_output_placeholders() {
+        let alerts = AlertConfig::default();
+        let executor = TaskExecutor::new(alerts, None);
+        let dir = std::env::temp_dir().join(format!("cron-rs-test-placeholders-{}", std::process::id()));
+        let mut task = create_test_task("test placeholders", "echo run");
+        task.stdout = Some(dir.join("{task}/{date}_{run_id}.log").to_string_lossy().to_string());
+
+        let result = executor.execute_task(&task).await.unwrap();
+
+        let date = result.start_time.with_timezone(&task.timezone).format("%Y-%m-%d");
+        let path = dir.join("test placeholders").join(format!("{}_{}.log", date, result.run_id));
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "run\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_log_output() {
+        let alerts = AlertConfig::default();
+        let executor = TaskExecutor::new(alerts, None);
+        let mut task = create_test_task("test_log_output", "echo out; echo err >&2; echo done");
+        task.log_output = true;
+
+        let result = executor.execute_task(&task).await.unwrap();
+
+        // The output still reaches the files
+        assert_eq!(result.stdout, "out\ndone\n");
+        assert_eq!(result.stderr, "err\n");
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_combine_output() {
+        let alerts = AlertConfig::default();
+        let executor = TaskExecutor::new(alerts, None);
+        let mut task = create_test_task("test_combine", "echo one; echo two >&2; echo three");
+        task.combine_output = true;
+
+        let result = executor.execute_task(&task).await.unwrap();
+
+        assert_eq!(result.stdout, "one\ntwo\nthree\n");
+        assert_eq!(result.stderr, "");
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_stdin_text() {
+        let alerts = AlertConfig::default();
+        let executor = TaskExecutor::new(alerts, None);
+        let mut task = create_test_task("test_stdin", "tr a-z A-Z");
+        task.stdin = Some(TaskStdin::Text("select 1;\n".to_string()));
+
+        let result = executor.execute_task(&task).await.unwrap();
+
+        assert_eq!(result.stdout, "SELECT 1;\n");
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_success_criteria() {
+        let alerts = AlertConfig::default();
+        let executor = TaskExecutor::new(alerts, None);
+        let mut task = create_test_task("test_success", "echo 'ERROR: disk full'; exit 2");
+        task.success = SuccessCriteria::parse(&SuccessCriteriaConfig {
+            exit_codes: Some(vec![0, 2]),
+            stdout_not_matches: Some("ERROR".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let result = executor.execute_task(&task).await.unwrap();
+        assert!(!result.success);
+        assert_eq!(result.exit_code, 2);
+
+        task.cmd = "echo 'all good'; exit 2".to_string();
+        let result = executor.execute_task(&task).await.unwrap();
+        assert!(result.success);
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_hooks() {
+        let alerts = AlertConfig::default();
+        let executor = TaskExecutor::new(alerts, None);
+        let mut task = create_test_task("test_hooks", "echo main; exit 3");
+        task.before = vec!["echo before".to_string()];
+        task.after = vec!["echo after $CRON_RS_EXIT_CODE".to_string()];
+
+        let result = executor.execute_task(&task).await.unwrap();
+        assert_eq!(result.exit_code, 3);
+        assert_eq!(result.stdout, "before\nmain\nafter 3\n");
+
+        // A failing before command aborts the execution
+        task.before = vec!["exit 1".to_string()];
+        assert!(executor.execute_task(&task).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_on_failure_cmd() {
+        let alerts = AlertConfig::default();
+        let executor = TaskExecutor::new(alerts, None);
+        let marker = std::env::temp_dir().join(format!("cron-rs-test-on-failure-{}", std::process::id()));
+        let mut task = create_test_task("test_on_failure", "exit 4");
+        task.on_failure_cmd = Some(format!("echo {{{{ task_name }}}} {{{{ exit_code }}}} > {}", marker.display()));
+
+        let result = executor.execute_task(&task).await.unwrap();
+        assert!(!result.success);
+        assert_eq!(std::fs::read_to_string(&marker).unwrap(), "test_on_failure 4\n");
+        std::fs::remove_file(&marker).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_kill_grace() {
+        let alerts = AlertConfig::default();
+        let executor = TaskExecutor::new(alerts, None);
+        let mut task = create_test_task("test_kill_grace", "trap 'echo cleanup; exit 0' TERM; sleep 5 & wait");
+        task.time_limit = Some(1);
+        task.kill_grace = Some(2);
+
+        let result = executor.execute_task(&task).await.unwrap();
+
+        assert!(!result.success);
+        assert_eq!(result.stdout, "cleanup\n");
+        assert!(result.duration.as_secs() < 3);
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_timeout_kills_process_group() {
+        let alerts = AlertConfig::default();
+        let executor = TaskExecutor::new(alerts, None);
+        let mut task = create_test_task("test_process_group", "sleep 30 & echo $!; wait");
+        task.time_limit = Some(1);
+
+        let result = executor.execute_task(&task).await.unwrap();
+
+        // The background sleep is gone, or a zombie waiting to be reaped by init
+        let pid = result.stdout.trim();
+        let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap_or_default();
+        assert!(stat.is_empty() || stat.contains(") Z "), "{}", stat);
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_time_limit_signal() {
+        let alerts = AlertConfig::default();
+        let executor = TaskExecutor::new(alerts, None);
+        let mut task = create_test_task("test_time_limit_signal", "trap 'echo hup; exit 0' HUP; sleep 5 & wait");
+        task.time_limit = Some(1);
+        task.time_limit_signal = StopSignal::Sighup;
+
+        let result = executor.execute_task(&task).await.unwrap();
+
+        assert!(!result.success);
+        assert_eq!(result.stdout, "hup\n");
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_limits() {
+        let alerts = AlertConfig::default();
+        let executor = TaskExecutor::new(alerts, None);
+        let mut task = create_test_task("test_limits", "ulimit -n; ulimit -c");
+        task.limits = ResourceLimits { open_files: Some(64), core_size: Some(0), ..Default::default() };
+
+        let result = executor.execute_task(&task).await.unwrap();
+
+        assert_eq!(result.stdout, "64\n0\n");
+    }
+
+    #[tokio::test]
+    async fn test_execute_task_priority() {
+        let alerts = AlertConfig::default();
+        let executor = TaskExecutor::new(alerts, None);
+        let mut task = create_test_task("test_priority", "nice; grep Cpus_allowed_list /proc/self/status");
+        task.priority = ProcessPriority::parse(Some(10), Some("best-effort:7"), Some("0")).unwrap();
+
+        let result = executor.execute_task(&task).await.unwrap();
+
+        assert_eq!(result.stdout, "10\nCpus_allowed_list:\t0\n");
+    }
+}
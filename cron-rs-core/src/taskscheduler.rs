@@ -0,0 +1,551 @@
+use crate::config::file::{OutputMode, TaskNetwork};
+use crate::config::{Config, Schedule, TaskConfig, TaskStdin, TimePattern, TimePatternField};
+use crate::executor::{self, ShellKind};
+use crate::utils::system_timezone;
+use log::warn;
+use std::collections::HashSet;
+
+/// Task Scheduler refuses tasks with more triggers
+const MAX_TRIGGERS: usize = 48;
+
+/// Task Scheduler repeats a task at most once per minute and at least once every 31 days
+const MIN_REPETITION_SECS: u64 = 60;
+const MAX_REPETITION_SECS: u64 = 31 * DAY_SECS;
+
+const DAY_SECS: u64 = 24 * 3600;
+
+/// Any date in the past works as the start of the triggers, only the time of the day is used
+const START_DATE: &str = "2000-01-01";
+
+const WEEKDAYS: [&str; 7] = ["Sunday", "Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday"];
+const MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June", "July", "August", "September", "October", "November", "December",
+];
+
+/// A Task Scheduler definition generated for a task, to register with `schtasks /Create /XML`
+#[derive(Debug, Clone)]
+pub struct ScheduledTask {
+    /// Name of the task inside the `\cron-rs` folder of Task Scheduler
+    pub name: String,
+    /// Declared as UTF-16, the encoding schtasks expects, the file must be written with it
+    pub xml: String,
+}
+
+/// Generates a Task Scheduler definition for each task of the config
+pub fn export_taskscheduler(config: &Config) -> Vec<ScheduledTask> {
+    if !config.alerts.on_failure.is_empty() || !config.alerts.on_success.is_empty() {
+        warn!("Global alerts have no equivalent in Task Scheduler and are not exported");
+    }
+
+    let local_timezone = system_timezone().name().to_string();
+    let mut used_names = HashSet::new();
+    let mut tasks = vec![];
+
+    for task in &config.tasks {
+        if task.executor.is_pipeline() {
+            warn!("Task '{}': pipelines have no equivalent in Task Scheduler, the pipeline is not exported", task.name);
+            continue;
+        }
+        let Some(triggers) = export_triggers(task) else {
+            continue;
+        };
+        warn_unsupported_options(task);
+        if task.timezone.name() != local_timezone {
+            warn!("Task '{}': Task Scheduler uses the timezone of the system, the timezone {} is ignored", task.name, task.timezone.name());
+        }
+
+        // Task Scheduler doesn't allow `\ / : * ? " < > |` in the names, like file names
+        let base_name = sanitise_file_name::sanitise(&task.name);
+        let mut name = base_name.clone();
+        let mut i = 2;
+        while !used_names.insert(name.to_lowercase()) {
+            name = format!("{}-{}", base_name, i);
+            i += 1;
+        }
+
+        tasks.push(ScheduledTask { xml: export_xml(task, &name, &triggers), name });
+    }
+
+    tasks
+}
+
+fn warn_unsupported_options(task: &TaskConfig) {
+    let mut unsupported = vec![];
+    if crate::template::has_variables(&task.cmd) {
+        unsupported.push("cmd variables");
+    }
+    if task.max_runs.is_some() {
+        unsupported.push("max_runs");
+    }
+    if !task.on_failure.is_empty() || !task.on_success.is_empty() {
+        unsupported.push("alerts");
+    }
+    if let Schedule::When { min_interval: Some(_), .. } = &task.schedule {
+        unsupported.push("min_interval");
+    }
+    if let Some(TaskStdin::Text(_)) = &task.stdin {
+        unsupported.push("stdin_text");
+    }
+    if !task.before.is_empty() || !task.after.is_empty() {
+        unsupported.push("before/after");
+    }
+    if task.on_failure_cmd.is_some() {
+        unsupported.push("on_failure_cmd");
+    }
+    if task.env_file.is_some() {
+        unsupported.push("env_file");
+    }
+    if task.lock_file.is_some() {
+        unsupported.push("lock_file");
+    }
+    if task.cluster_lock.is_some() {
+        unsupported.push("cluster_lock");
+    }
+    if task.login_shell || task.login_env {
+        unsupported.push("login_shell/login_env");
+    }
+    if task.run_as.as_ref().is_some_and(|run_as| run_as.contains(':')) {
+        unsupported.push("the group of run_as");
+    }
+    if task.chroot.is_some() {
+        unsupported.push("chroot");
+    }
+    if task.network == TaskNetwork::None {
+        unsupported.push("network: none");
+    }
+    if task.cgroup.is_some() {
+        unsupported.push("cgroup");
+    }
+    if !task.limits.is_empty() {
+        unsupported.push("limits");
+    }
+    if task.priority.ionice.is_some() || !task.priority.cpu_affinity.is_empty() {
+        unsupported.push("ionice/cpu_affinity");
+    }
+    if task.output_mode == Some(OutputMode::PerRun) || [&task.stdout, &task.stderr].into_iter().flatten().any(|path| path.contains('{')) {
+        unsupported.push("per_run output and placeholders in the output paths");
+    }
+    if task.conditions.min_battery_percent.is_some() {
+        unsupported.push("min_battery_percent");
+    }
+    if task.conditions.has_only_if() {
+        unsupported.push("only_if");
+    }
+
+    if !unsupported.is_empty() {
+        warn!("Task '{}': ignoring options without an equivalent in Task Scheduler: {}", task.name, unsupported.join(", "));
+    }
+}
+
+/// When Task Scheduler starts the task
+struct Trigger {
+    /// Seconds since midnight of the first execution of each day
+    start: u64,
+    /// Interval and duration of the repetitions after `start`, forever without duration
+    repetition: Option<(u64, Option<u64>)>,
+    /// None for a time trigger, that repeats without a calendar
+    days: Option<Days>,
+}
+
+/// Days of a calendar trigger, the days of the week start on Sunday
+#[derive(Clone)]
+enum Days {
+    Every(u64),
+    Weekly(Vec<u32>),
+    Monthly { days: Vec<u32>, months: Vec<u32> },
+    MonthlyDayOfWeek { days_of_week: Vec<u32>, months: Vec<u32> },
+}
+
+/// None if the schedule can't be represented, after a warning
+fn export_triggers(task: &TaskConfig) -> Option<Vec<Trigger>> {
+    let time = match (task.calendar_pattern(), &task.schedule) {
+        (Some(time), _) => time,
+        (None, Schedule::Every { interval, window, .. }) => {
+            if window.is_some() {
+                warn!("Task '{}': the 'between' and 'on' options can't be represented in Task Scheduler, they are ignored", task.name);
+            }
+            return interval_trigger(task, interval.as_secs()).map(|trigger| vec![trigger]);
+        }
+        (None, Schedule::OnChange { .. }) => {
+            warn!("Task '{}': Task Scheduler has no trigger for file changes, the task is not exported", task.name);
+            return None;
+        }
+        (None, Schedule::AfterTask { .. }) => {
+            warn!("Task '{}': Task Scheduler can't start a task when another one finishes, the task is not exported", task.name);
+            return None;
+        }
+        (None, Schedule::When { .. } | Schedule::Period { .. }) => unreachable!(),
+    };
+
+    if !matches!(time.year, TimePatternField::Any) {
+        warn!("Task '{}': Task Scheduler has no year field, the year is ignored", task.name);
+    }
+    let Some(days) = export_days(&time) else {
+        warn!("Task '{}': Task Scheduler can't restrict both the day of the month and the day of the week, the task is not exported", task.name);
+        return None;
+    };
+
+    // Seconds since midnight of all the executions of a day
+    let mut times = vec![];
+    for hour in expand(&time.hour, 0, 23).unwrap_or_else(|| (0..=23).collect()) {
+        for minute in expand(&time.minute, 0, 59).unwrap_or_else(|| (0..=59).collect()) {
+            for second in expand(&time.second, 0, 59).unwrap_or_else(|| (0..=59).collect()) {
+                times.push(hour as u64 * 3600 + minute as u64 * 60 + second as u64);
+            }
+        }
+    }
+
+    // Evenly spaced executions are a single trigger that repeats
+    let step = times.get(1).map(|second| second - times[0]);
+    if let Some(step) = step.filter(|step| times.windows(2).all(|pair| pair[1] - pair[0] == *step)) {
+        if step < MIN_REPETITION_SECS {
+            warn!("Task '{}': Task Scheduler runs a task at most once per minute", task.name);
+        }
+        // The last execution is included, the next one would be a full interval later
+        let duration = times[times.len() - 1] - times[0] + 1;
+        let repetition = (step.max(MIN_REPETITION_SECS), Some(duration));
+        return Some(vec![Trigger { start: times[0], repetition: Some(repetition), days: Some(days) }]);
+    }
+
+    if times.len() > MAX_TRIGGERS {
+        warn!("Task '{}': the schedule needs more than {} Task Scheduler triggers, the task is not exported", task.name, MAX_TRIGGERS);
+        return None;
+    }
+    Some(times.iter().map(|start| Trigger { start: *start, repetition: None, days: Some(days.clone()) }).collect())
+}
+
+/// Intervals from the last execution, in whole days or as the repetition of a trigger that starts in the past
+fn interval_trigger(task: &TaskConfig, secs: u64) -> Option<Trigger> {
+    if secs.is_multiple_of(DAY_SECS) {
+        return Some(Trigger { start: 0, repetition: None, days: Some(Days::Every(secs / DAY_SECS)) });
+    }
+    if secs > MAX_REPETITION_SECS {
+        warn!("Task '{}': Task Scheduler can't repeat a task every {} seconds, the task is not exported", task.name, secs);
+        return None;
+    }
+    if secs < MIN_REPETITION_SECS {
+        warn!("Task '{}': Task Scheduler runs a task at most once per minute", task.name);
+    }
+    Some(Trigger { start: 0, repetition: Some((secs.max(MIN_REPETITION_SECS), None)), days: None })
+}
+
+/// None if both the day of the month and the day of the week are restricted, the scheduler needs both to match
+fn export_days(time: &TimePattern) -> Option<Days> {
+    let months = expand(&time.month, 1, 12);
+    match (expand(&time.day, 1, 31), expand(&time.day_of_week, 0, 6)) {
+        (Some(_), Some(_)) => None,
+        (None, None) if months.is_none() => Some(Days::Every(1)),
+        (None, Some(days_of_week)) if months.is_none() => Some(Days::Weekly(days_of_week)),
+        (None, Some(days_of_week)) => Some(Days::MonthlyDayOfWeek { days_of_week, months: months.unwrap_or_default() }),
+        (days, None) => Some(Days::Monthly {
+            days: days.unwrap_or_else(|| (1..=31).collect()),
+            months: months.unwrap_or_else(|| (1..=12).collect()),
+        }),
+    }
+}
+
+/// Values matched by the field, None if it matches all of them
+fn expand(field: &TimePatternField, min: u32, max: u32) -> Option<Vec<u32>> {
+    let mut values: Vec<u32> = match field {
+        TimePatternField::Any => return None,
+        TimePatternField::Value(v) => vec![*v],
+        TimePatternField::Range(start, end) => (*start..=*end).collect(),
+        TimePatternField::List(values) => values.clone(),
+        TimePatternField::Ratio(..) => (min..=max).filter(|v| field.matches_value(*v)).collect(),
+    };
+    // Sunday can be 0 or 7
+    if max == 6 {
+        values.iter_mut().filter(|v| **v == 7).for_each(|v| *v = 0);
+    }
+    values.sort();
+    values.dedup();
+    Some(values)
+}
+
+fn export_xml(task: &TaskConfig, name: &str, triggers: &[Trigger]) -> String {
+    let mut out = String::from(concat!(
+        "<?xml version=\"1.0\" encoding=\"UTF-16\"?>\n",
+        "<Task version=\"1.2\" xmlns=\"http://schemas.microsoft.com/windows/2004/02/mit/task\">\n",
+    ));
+
+    out.push_str("  <RegistrationInfo>\n");
+    out.push_str("    <Author>cron-rs</Author>\n");
+    out.push_str(&format!("    <Description>{}</Description>\n", escape(&format!("Task '{}' of cron-rs", task.name))));
+    out.push_str(&format!("    <URI>{}</URI>\n", escape(&format!("\\cron-rs\\{}", name))));
+    out.push_str("  </RegistrationInfo>\n");
+
+    out.push_str("  <Triggers>\n");
+    for trigger in triggers {
+        out.push_str(&export_trigger(trigger));
+    }
+    out.push_str("  </Triggers>\n");
+
+    if let Some(run_as) = &task.run_as {
+        let user = run_as.split(':').next().unwrap_or(run_as);
+        // The local system account doesn't need a password
+        let (user, logon_type) = if user.eq_ignore_ascii_case("system") { ("S-1-5-18", "ServiceAccount") } else { (user, "Password") };
+        out.push_str("  <Principals>\n    <Principal id=\"Author\">\n");
+        out.push_str(&format!("      <UserId>{}</UserId>\n      <LogonType>{}</LogonType>\n", escape(user), logon_type));
+        out.push_str("    </Principal>\n  </Principals>\n");
+    }
+
+    out.push_str("  <Settings>\n");
+    let policy = if task.avoid_overlapping { "IgnoreNew" } else { "Parallel" };
+    out.push_str(&format!("    <MultipleInstancesPolicy>{}</MultipleInstancesPolicy>\n", policy));
+    out.push_str(&format!("    <DisallowStartIfOnBatteries>{}</DisallowStartIfOnBatteries>\n", task.conditions.only_on_ac_power));
+    out.push_str("    <StopIfGoingOnBatteries>false</StopIfGoingOnBatteries>\n");
+    // Runs a missed start of the period as soon as the machine is up, like anacron
+    if matches!(task.schedule, Schedule::Period { .. }) {
+        out.push_str("    <StartWhenAvailable>true</StartWhenAvailable>\n");
+    }
+    out.push_str(&format!("    <Enabled>{}</Enabled>\n", task.enabled));
+    // Without a time limit Task Scheduler stops the task after 3 days
+    let time_limit = task.time_limit.map(duration).unwrap_or_else(|| "PT0S".to_string());
+    out.push_str(&format!("    <ExecutionTimeLimit>{}</ExecutionTimeLimit>\n", time_limit));
+    out.push_str(&format!("    <Priority>{}</Priority>\n", priority(task.priority.nice)));
+    out.push_str("  </Settings>\n");
+
+    let shell = task.shell.as_deref().unwrap_or("cmd.exe");
+    out.push_str("  <Actions Context=\"Author\">\n    <Exec>\n");
+    out.push_str(&format!("      <Command>{}</Command>\n", escape(shell)));
+    out.push_str(&format!("      <Arguments>{}</Arguments>\n", escape(&shell_arguments(ShellKind::of(shell), &command_line(task, shell)))));
+    if let Some(dir) = task.working_directory.as_ref().filter(|_| task.executor.is_local()) {
+        out.push_str(&format!("      <WorkingDirectory>{}</WorkingDirectory>\n", escape(dir)));
+    }
+    out.push_str("    </Exec>\n  </Actions>\n");
+
+    out.push_str("</Task>\n");
+    out
+}
+
+fn export_trigger(trigger: &Trigger) -> String {
+    let element = if trigger.days.is_some() { "CalendarTrigger" } else { "TimeTrigger" };
+    let mut out = format!("    <{}>\n", element);
+    if let Some((interval, duration)) = trigger.repetition {
+        out.push_str("      <Repetition>\n");
+        out.push_str(&format!("        <Interval>{}</Interval>\n", self::duration(interval)));
+        if let Some(duration) = duration {
+            out.push_str(&format!("        <Duration>{}</Duration>\n", self::duration(duration)));
+        }
+        out.push_str("        <StopAtDurationEnd>false</StopAtDurationEnd>\n");
+        out.push_str("      </Repetition>\n");
+    }
+    let (hour, minute, second) = (trigger.start / 3600, trigger.start / 60 % 60, trigger.start % 60);
+    out.push_str(&format!("      <StartBoundary>{}T{:02}:{:02}:{:02}</StartBoundary>\n", START_DATE, hour, minute, second));
+    out.push_str("      <Enabled>true</Enabled>\n");
+
+    let list = |tag: &str, names: Vec<String>| format!("        <{}>\n{}        </{}>\n", tag, names.concat(), tag);
+    let weekdays = |days: &[u32]| list("DaysOfWeek", days.iter().map(|day| format!("          <{} />\n", WEEKDAYS[*day as usize])).collect());
+    let months = |months: &[u32]| list("Months", months.iter().map(|month| format!("          <{} />\n", MONTHS[*month as usize - 1])).collect());
+    match &trigger.days {
+        None => {}
+        Some(Days::Every(days)) => {
+            out.push_str(&format!("      <ScheduleByDay>\n        <DaysInterval>{}</DaysInterval>\n      </ScheduleByDay>\n", days));
+        }
+        Some(Days::Weekly(days)) => {
+            out.push_str("      <ScheduleByWeek>\n");
+            out.push_str(&weekdays(days));
+            out.push_str("        <WeeksInterval>1</WeeksInterval>\n      </ScheduleByWeek>\n");
+        }
+        Some(Days::Monthly { days, months: month_list }) => {
+            out.push_str("      <ScheduleByMonth>\n");
+            out.push_str(&list("DaysOfMonth", days.iter().map(|day| format!("          <Day>{}</Day>\n", day)).collect()));
+            out.push_str(&months(month_list));
+            out.push_str("      </ScheduleByMonth>\n");
+        }
+        Some(Days::MonthlyDayOfWeek { days_of_week, months: month_list }) => {
+            out.push_str("      <ScheduleByMonthDayOfWeek>\n");
+            let weeks = ["1", "2", "3", "4", "Last"];
+            out.push_str(&list("Weeks", weeks.iter().map(|week| format!("          <Week>{}</Week>\n", week)).collect()));
+            out.push_str(&weekdays(days_of_week));
+            out.push_str(&months(month_list));
+            out.push_str("      </ScheduleByMonthDayOfWeek>\n");
+        }
+    }
+    out.push_str(&format!("    </{}>\n", element));
+    out
+}
+
+/// The command of the task with its environment and redirections in the syntax of the shell, Task Scheduler can't set
+/// them
+fn command_line(task: &TaskConfig, shell: &str) -> String {
+    let kind = ShellKind::of(shell);
+    let mut line = String::new();
+    for (key, value) in task.exported_env() {
+        line.push_str(&match kind {
+            ShellKind::Cmd => format!("set \"{}={}\" && ", key, value),
+            ShellKind::PowerShell => format!("$env:{} = {}; ", key, powershell_string(value)),
+            ShellKind::Posix => format!("export {}={}; ", key, executor::quote(value)),
+        });
+    }
+    let command = executor::shell_command(task);
+
+    let quote_path = |path: &str| match kind {
+        ShellKind::Cmd => format!("\"{}\"", path),
+        ShellKind::PowerShell => powershell_string(path),
+        ShellKind::Posix => executor::quote(path),
+    };
+    let operator = if task.output_mode == Some(OutputMode::Append) { ">>" } else { ">" };
+    let mut redirections = String::new();
+    if let Some(TaskStdin::File(path)) = &task.stdin {
+        if kind == ShellKind::PowerShell {
+            warn!("Task '{}': PowerShell can't redirect the stdin, the stdin file is ignored", task.name);
+        } else {
+            redirections.push_str(&format!(" < {}", quote_path(path)));
+        }
+    }
+    if let Some(stdout) = &task.stdout {
+        redirections.push_str(&format!(" {} {}", operator, quote_path(stdout)));
+    }
+    if task.combine_output && task.stdout.is_some() {
+        redirections.push_str(" 2>&1");
+    } else if let Some(stderr) = &task.stderr {
+        redirections.push_str(&format!(" 2{} {}", operator, quote_path(stderr)));
+    }
+
+    if redirections.is_empty() {
+        line.push_str(&command);
+    } else {
+        // Grouped, so the redirections apply to the whole command and not only to its last part
+        line.push_str(&match kind {
+            ShellKind::Cmd => format!("({}){}", command, redirections),
+            ShellKind::PowerShell => format!("& {{ {} }}{}", command, redirections),
+            ShellKind::Posix => format!("{{ {}; }}{}", command, redirections),
+        });
+    }
+    line
+}
+
+/// Arguments of the shell, Task Scheduler passes them as they are to the process. cmd.exe reads the rest of the line
+/// after `/C`, the other shells split it with the quoting rules of the C runtime
+fn shell_arguments(kind: ShellKind, line: &str) -> String {
+    match kind {
+        ShellKind::Cmd => format!("/C {}", line),
+        ShellKind::PowerShell => format!("-NoProfile -NonInteractive -Command {}", windows_arg(line)),
+        ShellKind::Posix => format!("-c {}", windows_arg(line)),
+    }
+}
+
+/// Quotes an argument for CommandLineToArgvW, the backslashes are only special before a quote
+fn windows_arg(arg: &str) -> String {
+    let mut out = String::from("\"");
+    let mut backslashes = 0;
+    for c in arg.chars() {
+        match c {
+            '\\' => backslashes += 1,
+            '"' => {
+                out.push_str(&"\\".repeat(backslashes * 2 + 1));
+                backslashes = 0;
+            }
+            _ => {
+                out.push_str(&"\\".repeat(backslashes));
+                backslashes = 0;
+            }
+        }
+        if c != '\\' {
+            out.push(c);
+        }
+    }
+    out.push_str(&"\\".repeat(backslashes * 2));
+    out.push('"');
+    out
+}
+
+fn powershell_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Priority of the process, 4 to 6 are the normal priority class, 7 and 8 below normal and 9 and 10 idle. Without
+/// nice the task gets the normal class like in the scheduler, the default of Task Scheduler is below normal
+fn priority(nice: Option<i32>) -> u32 {
+    match nice {
+        Some(nice) if nice < 0 => 3,
+        None | Some(0) => 5,
+        Some(1..=9) => 7,
+        Some(_) => 9,
+    }
+}
+
+/// Duration in the XML format, e.g. `PT1H30M`
+fn duration(secs: u64) -> String {
+    let (days, hours, minutes, seconds) = (secs / DAY_SECS, secs / 3600 % 24, secs / 60 % 60, secs % 60);
+    let mut out = String::from("P");
+    if days > 0 {
+        out.push_str(&format!("{}D", days));
+    }
+    if hours > 0 || minutes > 0 || seconds > 0 || days == 0 {
+        out.push('T');
+        for (value, unit) in [(hours, 'H'), (minutes, 'M'), (seconds, 'S')] {
+            if value > 0 {
+                out.push_str(&format!("{}{}", value, unit));
+            }
+        }
+        if secs == 0 {
+            out.push_str("0S");
+        }
+    }
+    out
+}
+
+fn escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::file::ConfigFile;
+
+    #[test]
+    fn test_export_taskscheduler() {
+        let config_file: ConfigFile = serde_yml::from_str(
+            r#"
+tasks:
+  - name: Daily report
+    cmd: echo "a & b"
+    when: '[Mon, Fri] *-*-* 03:30:00'
+    time_limit: 10 minutes
+    avoid_overlapping: true
+  - name: poll
+    cmd: check
+    every: 90 seconds
+    shell: pwsh
+    stdout: C:\logs\poll.log
+    combine_output: true
+  - name: quarter hours
+    cmd: sync
+    when: '* *-*-* 09..17:*/15:00'
+    env:
+      MODE: fast
+"#,
+        )
+        .unwrap();
+        let config = crate::config::parse_config_file(&config_file).unwrap();
+
+        let tasks = export_taskscheduler(&config);
+        assert_eq!(tasks[0].name, "Daily report");
+        let xml = &tasks[0].xml;
+        assert!(xml.contains("<URI>\\cron-rs\\Daily report</URI>"));
+        assert!(xml.contains("<StartBoundary>2000-01-01T03:30:00</StartBoundary>"));
+        assert!(xml.contains("<DaysOfWeek>\n          <Monday />\n          <Friday />\n        </DaysOfWeek>"));
+        assert!(xml.contains("<MultipleInstancesPolicy>IgnoreNew</MultipleInstancesPolicy>"));
+        assert!(xml.contains("<ExecutionTimeLimit>PT10M</ExecutionTimeLimit>"));
+        assert!(xml.contains("<Command>cmd.exe</Command>\n      <Arguments>/C echo \"a &amp; b\"</Arguments>"));
+
+        let xml = &tasks[1].xml;
+        assert!(xml.contains("<TimeTrigger>\n      <Repetition>\n        <Interval>PT1M30S</Interval>\n        <StopAtDurationEnd>"));
+        assert!(xml.contains(r#"<Arguments>-NoProfile -NonInteractive -Command "&amp; { check } &gt; 'C:\logs\poll.log' 2&gt;&amp;1"</Arguments>"#));
+
+        let xml = &tasks[2].xml;
+        assert_eq!(xml.matches("<CalendarTrigger>").count(), 1);
+        assert!(xml.contains("<Interval>PT15M</Interval>\n        <Duration>PT8H45M1S</Duration>"));
+        assert!(xml.contains("<StartBoundary>2000-01-01T09:00:00</StartBoundary>"));
+        assert!(xml.contains("<Arguments>/C set \"MODE=fast\" &amp;&amp; sync</Arguments>"));
+    }
+
+    #[test]
+    fn test_windows_arg() {
+        assert_eq!(windows_arg(r#"echo "hi""#), r#""echo \"hi\"""#);
+        assert_eq!(windows_arg(r"C:\dir\"), r#""C:\dir\\""#);
+    }
+}
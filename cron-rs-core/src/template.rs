@@ -0,0 +1,187 @@
+use anyhow::{bail, Result};
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, Days, TimeDelta, TimeZone};
+use chrono_tz::Tz;
+
+/// Values of the `{{ variable }}` placeholders of the command of an execution
+#[derive(Debug, Clone)]
+pub struct CmdVariables<'a> {
+    pub task_name: &'a str,
+    pub run_id: &'a str,
+    /// In the timezone of the task, the dates are relative to it so a late run uses the dates it was due on
+    pub scheduled_time: DateTime<Tz>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Variable {
+    Date,
+    Yesterday,
+    Tomorrow,
+    ScheduledTime,
+    TaskName,
+    RunId,
+}
+
+impl Variable {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "date" => Some(Variable::Date),
+            "yesterday" => Some(Variable::Yesterday),
+            "tomorrow" => Some(Variable::Tomorrow),
+            "scheduled_time" => Some(Variable::ScheduledTime),
+            "task_name" => Some(Variable::TaskName),
+            "run_id" => Some(Variable::RunId),
+            _ => None,
+        }
+    }
+
+    fn value(self, variables: &CmdVariables, format: Option<&str>) -> String {
+        let time = match self {
+            Variable::TaskName => return variables.task_name.to_string(),
+            Variable::RunId => return variables.run_id.to_string(),
+            Variable::Date | Variable::ScheduledTime => variables.scheduled_time,
+            Variable::Yesterday => add_days(variables.scheduled_time, -1),
+            Variable::Tomorrow => add_days(variables.scheduled_time, 1),
+        };
+        match (self, format) {
+            (_, Some(format)) => time.format(format).to_string(),
+            (Variable::ScheduledTime, None) => time.to_rfc3339(),
+            _ => time.format("%Y-%m-%d").to_string(),
+        }
+    }
+}
+
+/// Same time of the local day before or after, a day with a DST change is longer or shorter than 24 hours. When that
+/// time doesn't exist on that day, 24 hours are added instead
+fn add_days(time: DateTime<Tz>, days: i64) -> DateTime<Tz> {
+    let local = time.naive_local();
+    let shifted = if days < 0 {
+        local.checked_sub_days(Days::new(days.unsigned_abs()))
+    } else {
+        local.checked_add_days(Days::new(days.unsigned_abs()))
+    };
+    shifted
+        .and_then(|shifted| time.timezone().from_local_datetime(&shifted).earliest())
+        .unwrap_or(time + TimeDelta::days(days))
+}
+
+/// Placeholder like `{{ yesterday | format("%Y%m%d") }}`, None for the ones that aren't variables of the command
+fn parse_placeholder(text: &str) -> Result<Option<(Variable, Option<&str>)>> {
+    let (name, filter) = match text.split_once('|') {
+        Some((name, filter)) => (name.trim(), Some(filter.trim())),
+        None => (text.trim(), None),
+    };
+    let Some(variable) = Variable::parse(name) else { return Ok(None) };
+    let Some(filter) = filter else { return Ok(Some((variable, None))) };
+
+    let Some(argument) = filter.strip_prefix("format").map(str::trim).and_then(|f| f.strip_prefix('(')?.strip_suffix(')')) else {
+        bail!("Unknown filter '{}' of {}, expected format(\"...\")", filter, name);
+    };
+    let argument = argument.trim();
+    let format = ['"', '\'']
+        .iter()
+        .find_map(|quote| argument.strip_prefix(*quote)?.strip_suffix(*quote))
+        .filter(|format| argument.len() >= 2 && !format.is_empty());
+    let Some(format) = format else {
+        bail!("The format of {} must be a quoted text, like format(\"%Y%m%d\")", name);
+    };
+    if matches!(variable, Variable::TaskName | Variable::RunId) {
+        bail!("Only the dates can be formatted, {} is a text", name);
+    }
+    if StrftimeItems::new(format).any(|item| matches!(item, Item::Error)) {
+        bail!("Invalid date format '{}' of {}", format, name);
+    }
+    Ok(Some((variable, Some(format))))
+}
+
+/// Calls `f` with each part of the command in order, with what is inside the `{{ }}` for the placeholders
+fn for_each_part<'a>(cmd: &'a str, mut f: impl FnMut(&'a str, Option<&'a str>)) {
+    let mut rest = cmd;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else { break };
+        f(&rest[..start], None);
+        let end = start + 2 + len + 2;
+        f(&rest[start..end], Some(&rest[start + 2..end - 2]));
+        rest = &rest[end..];
+    }
+    f(rest, None);
+}
+
+/// Checks the placeholders of the variables in the command, when the config is read
+pub fn check_cmd(cmd: &str) -> Result<()> {
+    let mut result = Ok(());
+    for_each_part(cmd, |_, inner| {
+        if let (Ok(()), Some(Err(e))) = (&result, inner.map(parse_placeholder)) {
+            result = Err(e);
+        }
+    });
+    result
+}
+
+/// True if the command has placeholders that are expanded before each execution
+pub fn has_variables(cmd: &str) -> bool {
+    let mut found = false;
+    for_each_part(cmd, |_, inner| found |= matches!(inner.map(parse_placeholder), Some(Ok(Some(_)))));
+    found
+}
+
+/// Replaces the placeholders of the variables. The rest of the `{{ }}`, like the ones of `docker --format`, are left as
+/// they are
+pub fn expand_cmd(cmd: &str, variables: &CmdVariables) -> String {
+    let mut result = String::with_capacity(cmd.len());
+    for_each_part(cmd, |part, inner| match inner.map(parse_placeholder) {
+        Some(Ok(Some((variable, format)))) => result.push_str(&variable.value(variables, format)),
+        _ => result.push_str(part),
+    });
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_cmd() {
+        let variables = CmdVariables {
+            task_name: "export",
+            run_id: "20250301-020000-3fa2c19b",
+            scheduled_time: chrono_tz::Europe::Madrid.with_ymd_and_hms(2025, 3, 1, 2, 0, 0).unwrap(),
+        };
+        let expand = |cmd: &str| expand_cmd(cmd, &variables);
+
+        assert_eq!(expand("dump > /backups/{{ date }}.sql"), "dump > /backups/2025-03-01.sql");
+        assert_eq!(expand("export --day {{yesterday}} --to {{ tomorrow }}"), "export --day 2025-02-28 --to 2025-03-02");
+        assert_eq!(expand(r#"cp out.csv out-{{ scheduled_time | format("%Y%m%d-%H%M") }}.csv"#), "cp out.csv out-20250301-0200.csv");
+        assert_eq!(expand("echo {{ yesterday | format('%d/%m') }}"), "echo 28/02");
+        assert_eq!(expand("echo {{ scheduled_time }} {{ task_name }} {{ run_id }}"), "echo 2025-03-01T02:00:00+01:00 export 20250301-020000-3fa2c19b");
+        // Other uses of the braces are not variables
+        assert_eq!(expand("docker ps --format '{{.Names}}' {{ date"), "docker ps --format '{{.Names}}' {{ date");
+
+        // A day with a DST change doesn't have 24 hours
+        let new_york = chrono_tz::America::New_York;
+        let at = |scheduled_time| CmdVariables { scheduled_time, ..variables.clone() };
+        let variables = at(new_york.with_ymd_and_hms(2025, 3, 10, 0, 30, 0).unwrap());
+        assert_eq!(expand_cmd("{{ yesterday }}", &variables), "2025-03-09");
+        let variables = at(new_york.with_ymd_and_hms(2025, 3, 8, 23, 30, 0).unwrap());
+        assert_eq!(expand_cmd("{{ tomorrow }}", &variables), "2025-03-09");
+        let variables = at(new_york.with_ymd_and_hms(2025, 11, 2, 23, 30, 0).unwrap());
+        assert_eq!(expand_cmd("{{ tomorrow }}", &variables), "2025-11-03");
+        let variables = at(new_york.with_ymd_and_hms(2025, 11, 3, 0, 30, 0).unwrap());
+        assert_eq!(expand_cmd("{{ yesterday | format('%F %H:%M') }}", &variables), "2025-11-02 00:30");
+        // 02:30 doesn't exist on 2025-03-09
+        let variables = at(new_york.with_ymd_and_hms(2025, 3, 10, 2, 30, 0).unwrap());
+        assert_eq!(expand_cmd("{{ yesterday }}", &variables), "2025-03-09");
+    }
+
+    #[test]
+    fn test_check_cmd() {
+        assert!(check_cmd("echo {{ date }} {{ yesterday | format(\"%Y%m%d\") }} {{.Names}}").is_ok());
+        assert!(check_cmd("echo {{ date | upper }}").is_err());
+        assert!(check_cmd("echo {{ date | format(%Y) }}").is_err());
+        assert!(check_cmd("echo {{ task_name | format(\"%Y\") }}").is_err());
+        assert!(check_cmd("echo {{ date | format(\"%Q\") }}").is_err());
+
+        assert!(has_variables("echo {{ date }}"));
+        assert!(!has_variables("docker ps --format '{{.Names}}'"));
+    }
+}
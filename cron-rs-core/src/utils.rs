@@ -0,0 +1,708 @@
+use crate::config::file::{StopSignal, TaskNetwork};
+use crate::config::limits::{ProcessPriority, ResourceLimits};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::SeekFrom;
+use std::path::Path;
+use std::process::ExitStatus;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{PoisonError, RwLock};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader};
+use tokio::task::JoinHandle;
+
+/// How long to wait for the rest of the output once the child exits, processes left in the background can keep the
+/// pipes open
+const OUTPUT_FORWARD_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Reads a file from the given position, to get only the output of the last execution in files opened in append mode
+pub async fn read_output_from(path: &Path, offset: u64) -> String {
+    let Ok(mut file) = tokio::fs::File::open(path).await else {
+        return String::new();
+    };
+    let mut bytes = vec![];
+    if file.seek(SeekFrom::Start(offset)).await.is_err() || file.read_to_end(&mut bytes).await.is_err() {
+        return String::new();
+    }
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+/// Copies the output of the child into its file, logging each line with the task name and run id
+pub fn forward_output<R>(reader: R, file: std::fs::File, task_name: String, run_id: String, level: log::Level) -> JoinHandle<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(reader);
+        let mut file = tokio::fs::File::from_std(file);
+        let mut line = vec![];
+        loop {
+            line.clear();
+            match reader.read_until(b'\n', &mut line).await {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {}
+            }
+            if let Err(e) = file.write_all(&line).await {
+                log::error!("Task '{}' (run {}): failed to write the output: {}", task_name, run_id, e);
+            }
+            log::log!(
+                level,
+                task = task_name.as_str(), run_id = run_id.as_str();
+                "Task '{}' (run {}): {}", task_name, run_id, String::from_utf8_lossy(&line).trim_end()
+            );
+        }
+        let _ = file.flush().await;
+    })
+}
+
+/// Writes the text, followed by the content of the file, to the stdin of the child and closes it, so the command sees
+/// the end of the input
+pub fn write_stdin(
+    stdin: tokio::process::ChildStdin,
+    text: String,
+    file: Option<std::fs::File>,
+    task_name: String,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut stdin = stdin;
+        // The command can exit without reading all of it
+        let mut result = stdin.write_all(text.as_bytes()).await;
+        if let (Ok(()), Some(file)) = (&result, file) {
+            result = tokio::io::copy(&mut tokio::fs::File::from_std(file), &mut stdin).await.map(|_| ());
+        }
+        if let Err(e) = result {
+            log::debug!("Task '{}': failed to write stdin: {}", task_name, e);
+        }
+    })
+}
+
+/// Waits until the output forwarded by `forward_output` is written to the files
+pub async fn join_output_forwarders(handles: Vec<JoinHandle<()>>) {
+    for handle in handles {
+        let abort_handle = handle.abort_handle();
+        if tokio::time::timeout(OUTPUT_FORWARD_TIMEOUT, handle).await.is_err() {
+            abort_handle.abort();
+        }
+    }
+}
+
+static RUN_ID_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Runs the child as another user, with the supplementary groups of the user. `Command::uid` drops them, so a task
+/// couldn't use the files shared with the groups of its user, e.g. the docker socket. With `login_env` the child also
+/// gets the environment of a login of the user, see [set_login_env]
+#[cfg(unix)]
+pub fn set_user(cmd: &mut tokio::process::Command, uid: u32, gid: u32, login_env: bool) -> std::io::Result<()> {
+    if login_env {
+        set_login_env(cmd, uid);
+    }
+
+    // SAFETY: getuid never fails
+    let is_root = unsafe { libc::getuid() } == 0;
+    if !is_root {
+        // Without root the groups can't be changed, setuid fails anyway unless the uid is the current one
+        cmd.uid(uid);
+        cmd.gid(gid);
+        return Ok(());
+    }
+    let groups = users::get_user_by_uid(uid).and_then(|user| user_groups(user.name(), gid)).unwrap_or_else(|| vec![gid]);
+
+    // SAFETY: setgroups, setgid and setuid are async-signal-safe, the groups are allocated before the fork. The order
+    // matters, the groups and the gid can't be changed once the uid is not root
+    unsafe {
+        cmd.pre_exec(move || {
+            if libc::setgroups(groups.len() as _, groups.as_ptr()) != 0
+                || libc::setgid(gid) != 0
+                || libc::setuid(uid) != 0
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+/// The child would run as the user of the scheduler instead, so it's not started
+#[cfg(windows)]
+pub fn set_user(_cmd: &mut tokio::process::Command, _uid: u32, _gid: u32, _login_env: bool) -> std::io::Result<()> {
+    Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "run_as is not supported on Windows"))
+}
+
+/// Sets HOME, USER, LOGNAME, SHELL and PATH like cron does for the user, the variables already set in the command (the
+/// `env` of the task) are kept
+#[cfg(unix)]
+fn set_login_env(cmd: &mut tokio::process::Command, uid: u32) {
+    use users::os::unix::UserExt;
+
+    let Some(user) = users::get_user_by_uid(uid) else {
+        log::warn!("User {} not found, the task runs without its login environment", uid);
+        return;
+    };
+    let path = if uid == 0 {
+        "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin"
+    } else {
+        "/usr/local/bin:/usr/bin:/bin"
+    };
+    let vars = [
+        ("HOME", user.home_dir().as_os_str()),
+        ("USER", user.name()),
+        ("LOGNAME", user.name()),
+        ("SHELL", user.shell().as_os_str()),
+        ("PATH", std::ffi::OsStr::new(path)),
+    ];
+
+    let explicit: Vec<std::ffi::OsString> = cmd.as_std().get_envs().map(|(key, _)| key.to_owned()).collect();
+    for (key, value) in vars {
+        if !explicit.iter().any(|k| k == key) {
+            cmd.env(key, value);
+        }
+    }
+}
+
+/// Groups of a user, including `gid`. Not `users::get_user_groups`, it doesn't use the number of groups returned by
+/// getgrouplist and adds the root group from the unused part of the buffer
+#[cfg(unix)]
+fn user_groups(username: &std::ffi::OsStr, gid: u32) -> Option<Vec<libc::gid_t>> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let name = std::ffi::CString::new(username.as_bytes()).ok()?;
+    let mut groups = vec![0; 64];
+    loop {
+        let mut count = groups.len() as libc::c_int;
+        // SAFETY: the buffer has room for `count` groups, getgrouplist writes the needed size to `count` when it doesn't
+        #[cfg(target_os = "macos")]
+        let result = unsafe { libc::getgrouplist(name.as_ptr(), gid as i32, groups.as_mut_ptr(), &mut count) };
+        #[cfg(not(target_os = "macos"))]
+        let result = unsafe { libc::getgrouplist(name.as_ptr(), gid, groups.as_mut_ptr(), &mut count) };
+
+        if result >= 0 {
+            groups.truncate(count as usize);
+            return Some(groups.into_iter().map(|group| group as libc::gid_t).collect());
+        }
+        // glibc sets `count` to the needed size, other libcs only say the buffer is too small
+        if groups.len() >= 65536 {
+            return None;
+        }
+        let len = (count as usize).max(groups.len() * 2);
+        groups.resize(len, 0);
+    }
+}
+
+/// Sets the working directory of the child, inside `chroot` when set. The chroot happens in the child before it drops
+/// the privileges of root, and the working directory is changed after it because `Command::current_dir` is relative to
+/// the old root
+#[cfg(unix)]
+pub fn set_working_directory(cmd: &mut tokio::process::Command, chroot: Option<&str>, working_directory: Option<&str>) -> std::io::Result<()> {
+    let Some(chroot) = chroot else {
+        if let Some(dir) = working_directory {
+            cmd.current_dir(dir);
+        }
+        return Ok(());
+    };
+
+    let to_c_string = |path: &str| std::ffi::CString::new(path).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e));
+    let root = to_c_string(chroot)?;
+    let dir = to_c_string(working_directory.unwrap_or("/"))?;
+    // SAFETY: chroot and chdir are async-signal-safe, the paths are allocated before the fork
+    unsafe {
+        cmd.pre_exec(move || {
+            if libc::chroot(root.as_ptr()) != 0 || libc::chdir(dir.as_ptr()) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+pub fn set_working_directory(cmd: &mut tokio::process::Command, chroot: Option<&str>, working_directory: Option<&str>) -> std::io::Result<()> {
+    if chroot.is_some() {
+        return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "chroot is not supported on Windows"));
+    }
+    if let Some(dir) = working_directory {
+        cmd.current_dir(dir);
+    }
+    Ok(())
+}
+
+/// With `network: none` the child runs in a new network namespace, where only the loopback interface exists. Like the
+/// chroot, it needs root and happens before the child drops its privileges
+#[cfg(target_os = "linux")]
+pub fn set_network(cmd: &mut tokio::process::Command, network: TaskNetwork) {
+    if network == TaskNetwork::Host {
+        return;
+    }
+    // SAFETY: unshare, socket, ioctl and close are async-signal-safe and don't allocate
+    unsafe {
+        cmd.pre_exec(|| {
+            if libc::unshare(libc::CLONE_NEWNET) != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+            bring_up_loopback();
+            Ok(())
+        });
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_network(_cmd: &mut tokio::process::Command, network: TaskNetwork) {
+    if network != TaskNetwork::Host {
+        log::warn!("network: none is only supported on Linux, the task has network access");
+    }
+}
+
+/// The loopback interface of a new network namespace starts down, some programs fail without it. Errors are ignored,
+/// the task still has no network
+#[cfg(target_os = "linux")]
+unsafe fn bring_up_loopback() {
+    let fd = libc::socket(libc::AF_INET, libc::SOCK_DGRAM | libc::SOCK_CLOEXEC, 0);
+    if fd < 0 {
+        return;
+    }
+    let mut request: libc::ifreq = std::mem::zeroed();
+    for (dst, src) in request.ifr_name.iter_mut().zip(b"lo") {
+        *dst = *src as libc::c_char;
+    }
+    if libc::ioctl(fd, libc::SIOCGIFFLAGS as _, &mut request) == 0 {
+        request.ifr_ifru.ifru_flags |= libc::IFF_UP as libc::c_short;
+        libc::ioctl(fd, libc::SIOCSIFFLAGS as _, &request);
+    }
+    libc::close(fd);
+}
+
+/// Applies the resource limits of the task to the child, before it runs the command
+#[cfg(unix)]
+pub fn set_resource_limits(cmd: &mut tokio::process::Command, limits: ResourceLimits) -> std::io::Result<()> {
+    if limits.is_empty() {
+        return Ok(());
+    }
+    // SAFETY: `apply` only calls setrlimit, which is async-signal-safe, and doesn't allocate
+    unsafe {
+        cmd.pre_exec(move || limits.apply());
+    }
+    Ok(())
+}
+
+/// A task can't run without its limits, so it's not started
+#[cfg(windows)]
+pub fn set_resource_limits(_cmd: &mut tokio::process::Command, limits: ResourceLimits) -> std::io::Result<()> {
+    if !limits.is_empty() {
+        return Err(std::io::Error::new(std::io::ErrorKind::Unsupported, "limits are not supported on Windows"));
+    }
+    Ok(())
+}
+
+/// Applies the nice, ionice and cpu_affinity of the task to the child, before it runs the command
+#[cfg(target_os = "linux")]
+pub fn set_process_priority(cmd: &mut tokio::process::Command, priority: &ProcessPriority) {
+    if priority.is_empty() {
+        return;
+    }
+    let priority = priority.clone();
+    let cpu_set = priority.cpu_set();
+    // SAFETY: `apply` only calls setpriority, ioprio_set and sched_setaffinity, which don't allocate
+    unsafe {
+        cmd.pre_exec(move || priority.apply(cpu_set.as_ref()));
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn set_process_priority(_cmd: &mut tokio::process::Command, priority: &ProcessPriority) {
+    if !priority.is_empty() {
+        log::warn!("nice, ionice and cpu_affinity are only supported on Linux");
+    }
+}
+
+/// Starts the child in its own process group, so the processes it starts can be stopped with it, see
+/// [signal_process_group]
+#[cfg(unix)]
+pub fn set_process_group(cmd: &mut std::process::Command) {
+    use std::os::unix::process::CommandExt;
+    cmd.process_group(0);
+}
+
+/// The new group also keeps the console ctrl events of the scheduler from reaching the child
+#[cfg(windows)]
+pub fn set_process_group(cmd: &mut std::process::Command) {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+    cmd.creation_flags(CREATE_NEW_PROCESS_GROUP);
+}
+
+/// Sends a signal to the process group of a child spawned with `process_group(0)`, the id of the group is the pid of
+/// the child
+#[cfg(unix)]
+pub fn signal_process_group(pid: u32, signal: StopSignal) -> bool {
+    // SAFETY: kill doesn't touch any memory of this process
+    unsafe { libc::kill(-(pid as libc::pid_t), signal.number()) == 0 }
+}
+
+/// Windows has no signals, the tree of processes of the child is stopped with `taskkill /T`. Without `/F` it asks the
+/// windows of the processes to close, which fails for console programs, so only SIGKILL is reliable
+#[cfg(windows)]
+pub fn signal_process_group(pid: u32, signal: StopSignal) -> bool {
+    let mut cmd = std::process::Command::new("taskkill");
+    if signal == StopSignal::Sigkill {
+        cmd.arg("/F");
+    }
+    cmd.args(["/T", "/PID", &pid.to_string()])
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(unix)]
+fn process_exists(pid: u32) -> bool {
+    // SAFETY: signal 0 only checks if the process exists
+    unsafe { libc::kill(pid as libc::pid_t, 0) == 0 }
+}
+
+#[cfg(windows)]
+fn process_exists(pid: u32) -> bool {
+    use sysinfo::{Pid, ProcessesToUpdate, System};
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+    system.process(pid).is_some()
+}
+
+/// Waits until the process exits and is reaped, up to `timeout`. Returns false if it is still running
+pub async fn wait_process_exit(pid: u32, timeout: Duration) -> bool {
+    let deadline = tokio::time::Instant::now() + timeout;
+    while process_exists(pid) {
+        if tokio::time::Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    true
+}
+
+/// When the process started, to tell a process apart from a later one that reuses its pid
+pub fn process_start_time(pid: u32) -> Option<DateTime<Utc>> {
+    use sysinfo::{Pid, ProcessesToUpdate, System};
+    let pid = Pid::from_u32(pid);
+    let mut system = System::new();
+    system.refresh_processes(ProcessesToUpdate::Some(&[pid]), true);
+    let start_time = system.process(pid)?.start_time();
+    DateTime::from_timestamp(start_time as i64, 0)
+}
+
+/// Stops a child and all the processes it started, they get the signal and `grace` time to exit before the SIGKILL.
+/// Processes of the group still running once the child exits are killed too
+pub async fn terminate_child(
+    child: &mut tokio::process::Child,
+    signal: StopSignal,
+    grace: Duration,
+    task_name: &str,
+) -> std::io::Result<ExitStatus> {
+    let Some(pid) = child.id() else {
+        return child.wait().await;
+    };
+    if signal != StopSignal::Sigkill && !grace.is_zero() && signal_process_group(pid, signal) {
+        if let Ok(status) = tokio::time::timeout(grace, child.wait()).await {
+            signal_process_group(pid, StopSignal::Sigkill);
+            return status;
+        }
+        log::warn!("Task '{}' didn't exit {} after {}, sending SIGKILL", task_name, format_duration(grace), signal.name());
+    }
+    signal_process_group(pid, StopSignal::Sigkill);
+    child.kill().await?;
+    child.wait().await
+}
+
+/// Name of the user that runs the scheduler
+#[cfg(unix)]
+pub fn current_username() -> Option<String> {
+    users::get_current_username().map(|name| name.to_string_lossy().to_string())
+}
+
+#[cfg(windows)]
+pub fn current_username() -> Option<String> {
+    std::env::var("USERNAME").ok()
+}
+
+/// Takes an exclusive flock on the file, created if it doesn't exist. None if another process holds it, the lock is
+/// released when the returned file is closed
+pub fn try_lock_file(path: &Path) -> std::io::Result<Option<std::fs::File>> {
+    let file = std::fs::OpenOptions::new().create(true).truncate(false).write(true).open(path)?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(std::fs::TryLockError::WouldBlock) => Ok(None),
+        Err(std::fs::TryLockError::Error(e)) => Err(e),
+    }
+}
+
+/// Timezone of the system, read again by [refresh_system_timezone]
+static SYSTEM_TIMEZONE: RwLock<Option<Tz>> = RwLock::new(None);
+
+/// Timezone of the tasks without `timezone` or with `timezone: local`. It's read once and kept until the next
+/// [refresh_system_timezone]
+pub fn system_timezone() -> Tz {
+    if let Some(timezone) = *SYSTEM_TIMEZONE.read().unwrap_or_else(PoisonError::into_inner) {
+        return timezone;
+    }
+    refresh_system_timezone()
+}
+
+/// Reads the timezone of the system again, like after a change of `/etc/localtime`. UTC if it can't be read
+pub fn refresh_system_timezone() -> Tz {
+    let timezone = match iana_time_zone::get_timezone() {
+        Ok(name) => parse_timezone_name(&name).unwrap_or_else(|e| {
+            log::warn!("Unknown system timezone '{}', using UTC: {}", name, e);
+            Tz::UTC
+        }),
+        Err(e) => {
+            log::warn!("Unable to get the system timezone, using UTC: {}", e);
+            Tz::UTC
+        }
+    };
+    *SYSTEM_TIMEZONE.write().unwrap_or_else(PoisonError::into_inner) = Some(timezone);
+    timezone
+}
+
+/// Parses the `timezone` option, `local` is the timezone of the system
+pub fn parse_timezone(name: &str) -> anyhow::Result<Tz> {
+    if name.eq_ignore_ascii_case("local") {
+        return Ok(system_timezone());
+    }
+    parse_timezone_name(name)
+}
+
+/// The names of UTC are all the same timezone, so a task in `UTC` matches a host in `Etc/UTC`
+fn parse_timezone_name(name: &str) -> anyhow::Result<Tz> {
+    if ["UTC", "Etc/UTC", "Z", "Zulu", "Etc/Zulu", "Universal", "Etc/Universal"].iter().any(|utc| name.eq_ignore_ascii_case(utc)) {
+        return Ok(Tz::UTC);
+    }
+    Ok(name.parse()?)
+}
+
+/// Name of the host that runs the scheduler, empty if it can't be read
+pub fn hostname() -> String {
+    sysinfo::System::host_name().unwrap_or_default()
+}
+
+/// Unique id of a task execution, the start time followed by a random suffix, e.g. `20250101-120000-3fa2c19b`
+pub fn new_run_id(start_time: DateTime<Utc>) -> String {
+    let mut hasher = DefaultHasher::new();
+    std::process::id().hash(&mut hasher);
+    RUN_ID_COUNTER.fetch_add(1, Ordering::Relaxed).hash(&mut hasher);
+    start_time.timestamp_nanos_opt().hash(&mut hasher);
+    format!("{}-{:08x}", start_time.format("%Y%m%d-%H%M%S"), hasher.finish() as u32)
+}
+
+/// Formats a number of bytes with the largest unit that keeps it above 1, in multiples of 1024
+pub fn format_size(bytes: u64) -> String {
+    let units = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
+
+/// Converts a Duration to a human-readable string with at most 2 units
+/// e.g., "1 h, 30 m", "5 m, 20 s", "1 s, 133 ms", "10 ms"
+pub fn format_duration(duration: Duration) -> String {
+    let total_ms = duration.as_millis();
+
+    // Unit definitions in milliseconds
+    const MS_PER_SEC: u128 = 1_000;
+    const MS_PER_MIN: u128 = MS_PER_SEC * 60;
+    const MS_PER_HOUR: u128 = MS_PER_MIN * 60;
+    const MS_PER_DAY: u128 = MS_PER_HOUR * 24;
+
+    // Early return for zero duration
+    if total_ms == 0 {
+        return "0 ms".to_string();
+    }
+
+    // Calculate components
+    let days = total_ms / MS_PER_DAY;
+    let hours = (total_ms % MS_PER_DAY) / MS_PER_HOUR;
+    let minutes = (total_ms % MS_PER_HOUR) / MS_PER_MIN;
+    let seconds = (total_ms % MS_PER_MIN) / MS_PER_SEC;
+    let milliseconds = total_ms % MS_PER_SEC;
+
+    // Find the first two non-zero components
+    let mut result = String::new();
+    let mut units_added = 0;
+
+    if days > 0 && units_added < 2 {
+        result.push_str(&format!("{} d", days));
+        units_added += 1;
+    }
+
+    if hours > 0 && units_added < 2 {
+        if !result.is_empty() {
+            result.push_str(", ");
+        }
+        result.push_str(&format!("{} h", hours));
+        units_added += 1;
+    }
+
+    if minutes > 0 && units_added < 2 {
+        if !result.is_empty() {
+            result.push_str(", ");
+        }
+        result.push_str(&format!("{} m", minutes));
+        units_added += 1;
+    }
+
+    if seconds > 0 && units_added < 2 {
+        if !result.is_empty() {
+            result.push_str(", ");
+        }
+        result.push_str(&format!("{} s", seconds));
+        units_added += 1;
+    }
+
+    if milliseconds > 0 && units_added < 2 {
+        if !result.is_empty() {
+            result.push_str(", ");
+        }
+        result.push_str(&format!("{} ms", milliseconds));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_duration() {
+        // Test various durations
+        assert_eq!(format_duration(Duration::from_secs(0)), "0 ms");
+        assert_eq!(format_duration(Duration::from_millis(10)), "10 ms");
+        assert_eq!(format_duration(Duration::from_millis(1500)), "1 s, 500 ms");
+        assert_eq!(format_duration(Duration::from_secs(65)), "1 m, 5 s");
+        assert_eq!(format_duration(Duration::from_secs(3600 + 120)), "1 h, 2 m");
+        assert_eq!(format_duration(Duration::from_secs(86400 + 3600)), "1 d, 1 h");
+
+        // Check more complex durations
+        let duration = Duration::from_secs(90061); // 1 d, 1 h, 1 m, 1 s
+        assert_eq!(format_duration(duration), "1 d, 1 h"); // Should only show first 2 units
+
+        let duration = Duration::from_millis(59999); // 59 s, 999 ms
+        assert_eq!(format_duration(duration), "59 s, 999 ms");
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 << 30), "3.0 GB");
+    }
+
+    #[test]
+    fn test_parse_timezone() {
+        assert_eq!(parse_timezone("Europe/Madrid").unwrap(), chrono_tz::Europe::Madrid);
+        assert_eq!(parse_timezone("local").unwrap(), system_timezone());
+        assert_eq!(parse_timezone("Local").unwrap(), refresh_system_timezone());
+        assert!(parse_timezone("Mars/Olympus").is_err());
+        assert_eq!(parse_timezone("utc").unwrap(), Tz::UTC);
+        assert_eq!(parse_timezone("Etc/UTC").unwrap(), Tz::UTC);
+
+        // The tasks can ask for it explicitly
+        let yaml = "tasks: [{ name: local, cmd: 'true', every: 1 hour, timezone: local }]";
+        let config = crate::parse_config_file(&serde_yml::from_str(yaml).unwrap()).unwrap();
+        assert_eq!(config.tasks[0].timezone, system_timezone());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_user_groups() {
+        // Only the given gid and the groups that list root as a member, not the rest of the buffer
+        let groups = user_groups(std::ffi::OsStr::new("root"), 12345).unwrap();
+        assert!(groups.contains(&12345));
+        assert!(!groups.contains(&0));
+    }
+
+    /// Stdout of the shell line, run with the changes to the command
+    #[cfg(unix)]
+    async fn output_of(line: &str, setup: impl FnOnce(&mut tokio::process::Command)) -> String {
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.args(["-c", line]);
+        setup(&mut cmd);
+        let output = cmd.output().await.unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap().trim().to_string()
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn test_login_env() {
+        use users::os::unix::UserExt;
+
+        // SAFETY: getuid and getgid never fail
+        let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+        let user = users::get_user_by_uid(uid).unwrap();
+        let line = "echo \"$HOME $USER $LOGNAME $SHELL\"";
+
+        // The variables set by the task are kept
+        let output = output_of(line, |cmd| {
+            cmd.env("HOME", "/elsewhere");
+            set_user(cmd, uid, gid, true).unwrap();
+        })
+        .await;
+        let name = user.name().to_string_lossy();
+        assert_eq!(output, format!("/elsewhere {} {} {}", name, name, user.shell().display()));
+
+        let output = output_of(line, |cmd| {
+            cmd.env_clear();
+            set_user(cmd, uid, gid, false).unwrap();
+        })
+        .await;
+        assert_eq!(output, "");
+    }
+
+    #[tokio::test]
+    #[cfg(unix)]
+    async fn test_chroot() {
+        // SAFETY: getuid never fails
+        if unsafe { libc::getuid() } != 0 {
+            return;
+        }
+        let dir = std::env::temp_dir().join(format!("cron-rs-test-chroot-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("work")).unwrap();
+
+        // The working directory is inside the new root, `/` keeps the shell available
+        let output = output_of("pwd", |cmd| set_working_directory(cmd, Some("/"), Some("/tmp")).unwrap()).await;
+        assert_eq!(output, "/tmp");
+
+        // Without a shell inside the new root the command can't start
+        let mut cmd = tokio::process::Command::new("sh");
+        set_working_directory(&mut cmd, Some(&dir.to_string_lossy()), Some("/work")).unwrap();
+        assert!(cmd.output().await.is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    #[cfg(target_os = "linux")]
+    async fn test_network_none() {
+        // SAFETY: getuid never fails
+        if unsafe { libc::getuid() } != 0 {
+            return;
+        }
+        let interfaces = "tail -n +3 /proc/net/dev | cut -d: -f1 | tr -d ' '";
+
+        // Only the loopback interface is left
+        let output = output_of(interfaces, |cmd| set_network(cmd, TaskNetwork::None)).await;
+        assert_eq!(output, "lo");
+        let host = output_of(interfaces, |cmd| set_network(cmd, TaskNetwork::Host)).await;
+        assert_eq!(host, output_of(interfaces, |_| {}).await);
+    }
+}
\ No newline at end of file
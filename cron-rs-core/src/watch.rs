@@ -0,0 +1,84 @@
+use anyhow::{Context, Result};
+use log::warn;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Watches the paths of a task with `on_change` (inotify on Linux), it stops watching when dropped
+pub struct PathWatcher {
+    _watcher: RecommendedWatcher,
+    debouncer: JoinHandle<()>,
+}
+
+impl Drop for PathWatcher {
+    fn drop(&mut self) {
+        self.debouncer.abort();
+    }
+}
+
+/// Calls `on_change` once the paths stop changing for `debounce`, directories are watched recursively
+pub fn watch_paths(paths: &[PathBuf], debounce: Duration, on_change: impl Fn() + Send + 'static) -> Result<PathWatcher> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<Event>| match event {
+        // Reading the files doesn't change them
+        Ok(event) if matches!(event.kind, EventKind::Access(_)) => {}
+        Ok(_) => {
+            let _ = tx.send(());
+        }
+        Err(e) => warn!("Error watching files: {}", e),
+    })?;
+
+    for path in paths {
+        watcher
+            .watch(path, RecursiveMode::Recursive)
+            .with_context(|| format!("Failed to watch {}", path.display()))?;
+    }
+
+    let debouncer = tokio::spawn(async move {
+        while rx.recv().await.is_some() {
+            // Wait until a whole `debounce` passes without changes
+            while let Ok(Some(())) = tokio::time::timeout(debounce, rx.recv()).await {}
+            on_change();
+        }
+    });
+
+    Ok(PathWatcher { _watcher: watcher, debouncer })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_changes_are_debounced() {
+        let dir = std::env::temp_dir().join(format!("cron-rs-test-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let changes = Arc::new(AtomicUsize::new(0));
+        let counter = changes.clone();
+        let watcher = watch_paths(std::slice::from_ref(&dir), Duration::from_millis(300), move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+        })
+        .unwrap();
+
+        // A burst of changes runs it once
+        for i in 0..5 {
+            std::fs::write(dir.join(format!("file-{}", i)), "data").unwrap();
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        tokio::time::sleep(Duration::from_millis(800)).await;
+        assert_eq!(changes.load(Ordering::SeqCst), 1);
+
+        // Nothing is watched after the drop
+        drop(watcher);
+        std::fs::write(dir.join("file-0"), "more data").unwrap();
+        tokio::time::sleep(Duration::from_millis(800)).await;
+        assert_eq!(changes.load(Ordering::SeqCst), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,189 @@
+//! Installs cron-rs as a Windows service and runs the scheduler under the service control manager
+
+use crate::scheduler;
+use anyhow::{anyhow, Context, Result};
+use log::{error, info};
+use std::ffi::{c_void, OsStr};
+use std::os::windows::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr::{null, null_mut};
+use std::sync::atomic::{AtomicPtr, Ordering};
+use std::sync::Mutex;
+use windows_sys::Win32::Foundation::{ERROR_CALL_NOT_IMPLEMENTED, ERROR_SERVICE_NOT_ACTIVE, NO_ERROR};
+use windows_sys::Win32::System::Services::*;
+
+/// Access right to delete a service, from the standard rights that windows-sys only defines for files
+const DELETE: u32 = 0x0001_0000;
+
+/// Milliseconds the service control manager waits for the scheduler to start or stop before assuming it hung
+const WAIT_HINT_MS: u32 = 30_000;
+
+type ServiceMain = Box<dyn FnOnce() -> Result<()> + Send>;
+
+/// Body of the service, called once by the dispatcher thread
+static SERVICE_MAIN: Mutex<Option<ServiceMain>> = Mutex::new(None);
+static SERVICE_NAME: Mutex<Vec<u16>> = Mutex::new(Vec::new());
+static STATUS_HANDLE: AtomicPtr<c_void> = AtomicPtr::new(null_mut());
+
+/// Registers a service that starts with the system and runs the scheduler with the given config file
+pub fn install(name: &str, config_path: &Path) -> Result<()> {
+    let exe = std::env::current_exe().context("Failed to find the path of cron-rs")?;
+    let config_path = std::path::absolute(config_path)?;
+    let command = format!(
+        "\"{}\" --config \"{}\" service run --name \"{}\"",
+        exe.display(),
+        config_path.display(),
+        name
+    );
+
+    let manager = ServiceHandle::open_manager(SC_MANAGER_CREATE_SERVICE)?;
+    let service = unsafe {
+        CreateServiceW(
+            manager.0,
+            wide(name).as_ptr(),
+            wide(name).as_ptr(),
+            SERVICE_CHANGE_CONFIG,
+            SERVICE_WIN32_OWN_PROCESS,
+            SERVICE_AUTO_START,
+            SERVICE_ERROR_NORMAL,
+            wide(&command).as_ptr(),
+            null(),
+            null_mut(),
+            null(),
+            null(),
+            null(),
+        )
+    };
+    if service.is_null() {
+        return Err(std::io::Error::last_os_error()).with_context(|| format!("Failed to create the service '{}'", name));
+    }
+    let service = ServiceHandle(service);
+
+    let mut text = wide(&format!("Runs the tasks of {}", config_path.display()));
+    let description = SERVICE_DESCRIPTIONW { lpDescription: text.as_mut_ptr() };
+    if unsafe { ChangeServiceConfig2W(service.0, SERVICE_CONFIG_DESCRIPTION, &description as *const _ as *const c_void) } == 0 {
+        return Err(std::io::Error::last_os_error()).context("Failed to set the description of the service");
+    }
+    Ok(())
+}
+
+/// Stops the service if it is running and removes it
+pub fn uninstall(name: &str) -> Result<()> {
+    let manager = ServiceHandle::open_manager(SC_MANAGER_ALL_ACCESS)?;
+    let service = unsafe { OpenServiceW(manager.0, wide(name).as_ptr(), SERVICE_STOP | SERVICE_QUERY_STATUS | DELETE) };
+    if service.is_null() {
+        return Err(std::io::Error::last_os_error()).with_context(|| format!("Failed to open the service '{}'", name));
+    }
+    let service = ServiceHandle(service);
+
+    let mut status: SERVICE_STATUS = unsafe { std::mem::zeroed() };
+    if unsafe { ControlService(service.0, SERVICE_CONTROL_STOP, &mut status) } == 0 {
+        let e = std::io::Error::last_os_error();
+        if e.raw_os_error() != Some(ERROR_SERVICE_NOT_ACTIVE as i32) {
+            return Err(e).with_context(|| format!("Failed to stop the service '{}'", name));
+        }
+    }
+    if unsafe { DeleteService(service.0) } == 0 {
+        return Err(std::io::Error::last_os_error()).with_context(|| format!("Failed to delete the service '{}'", name));
+    }
+    Ok(())
+}
+
+/// Runs `main` as the body of the service, blocks until it returns. Stop requests of the service control manager stop
+/// the scheduler with [scheduler::request_shutdown], `main` is expected to return after it
+pub fn run(name: &str, main: impl FnOnce() -> Result<()> + Send + 'static) -> Result<()> {
+    *SERVICE_MAIN.lock().unwrap() = Some(Box::new(main));
+    *SERVICE_NAME.lock().unwrap() = wide(name);
+
+    let mut service_name = wide(name);
+    let table = [
+        SERVICE_TABLE_ENTRYW { lpServiceName: service_name.as_mut_ptr(), lpServiceProc: Some(service_main) },
+        SERVICE_TABLE_ENTRYW { lpServiceName: null_mut(), lpServiceProc: None },
+    ];
+    if unsafe { StartServiceCtrlDispatcherW(table.as_ptr()) } == 0 {
+        return Err(std::io::Error::last_os_error())
+            .context("Failed to connect to the service control manager, 'service run' is only used by the service");
+    }
+    Ok(())
+}
+
+unsafe extern "system" fn service_main(_argc: u32, _argv: *mut windows_sys::core::PWSTR) {
+    let name = SERVICE_NAME.lock().unwrap().clone();
+    let handle = RegisterServiceCtrlHandlerExW(name.as_ptr(), Some(control_handler), null());
+    if handle.is_null() {
+        error!("Failed to register the service control handler: {}", std::io::Error::last_os_error());
+        return;
+    }
+    STATUS_HANDLE.store(handle, Ordering::SeqCst);
+
+    set_status(SERVICE_START_PENDING, NO_ERROR);
+    set_status(SERVICE_RUNNING, NO_ERROR);
+
+    let main = SERVICE_MAIN.lock().unwrap().take();
+    let result = main.map(|main| main()).unwrap_or_else(|| Err(anyhow!("The service was already started")));
+    match result {
+        Ok(()) => set_status(SERVICE_STOPPED, NO_ERROR),
+        Err(e) => {
+            error!("The service stopped with an error: {:#}", e);
+            set_status(SERVICE_STOPPED, 1);
+        }
+    }
+}
+
+unsafe extern "system" fn control_handler(control: u32, _event_type: u32, _event_data: *mut c_void, _context: *mut c_void) -> u32 {
+    match control {
+        SERVICE_CONTROL_STOP | SERVICE_CONTROL_SHUTDOWN => {
+            info!("Stop requested by the service control manager");
+            set_status(SERVICE_STOP_PENDING, NO_ERROR);
+            scheduler::request_shutdown();
+            NO_ERROR
+        }
+        SERVICE_CONTROL_INTERROGATE => NO_ERROR,
+        _ => ERROR_CALL_NOT_IMPLEMENTED,
+    }
+}
+
+fn set_status(state: SERVICE_STATUS_CURRENT_STATE, exit_code: u32) {
+    let handle = STATUS_HANDLE.load(Ordering::SeqCst);
+    if handle.is_null() {
+        return;
+    }
+    let pending = state == SERVICE_START_PENDING || state == SERVICE_STOP_PENDING;
+    let status = SERVICE_STATUS {
+        dwServiceType: SERVICE_WIN32_OWN_PROCESS,
+        dwCurrentState: state,
+        dwControlsAccepted: if state == SERVICE_RUNNING { SERVICE_ACCEPT_STOP | SERVICE_ACCEPT_SHUTDOWN } else { 0 },
+        dwWin32ExitCode: exit_code,
+        dwServiceSpecificExitCode: 0,
+        dwCheckPoint: 0,
+        dwWaitHint: if pending { WAIT_HINT_MS } else { 0 },
+    };
+    if unsafe { SetServiceStatus(handle, &status) } == 0 {
+        error!("Failed to report the state of the service: {}", std::io::Error::last_os_error());
+    }
+}
+
+/// Closes the handle when dropped
+struct ServiceHandle(SC_HANDLE);
+
+impl ServiceHandle {
+    fn open_manager(access: u32) -> Result<Self> {
+        let handle = unsafe { OpenSCManagerW(null(), null(), access) };
+        if handle.is_null() {
+            return Err(std::io::Error::last_os_error())
+                .context("Failed to open the service control manager, it needs an administrator prompt");
+        }
+        Ok(Self(handle))
+    }
+}
+
+impl Drop for ServiceHandle {
+    fn drop(&mut self) {
+        unsafe { CloseServiceHandle(self.0) };
+    }
+}
+
+/// Null terminated UTF-16 string for the wide functions
+fn wide(value: &str) -> Vec<u16> {
+    OsStr::new(value).encode_wide().chain(Some(0)).collect()
+}
@@ -1,35 +0,0 @@
-use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
-use crate::sqlite_logger::SqliteLoggerConfig;
-
-#[derive(Debug, Clone, Deserialize, Serialize, Default, Ord, PartialOrd, Eq, PartialEq)]
-pub enum LogOutput {
-    #[serde(rename = "stdout")]
-    #[default]
-    Stdout,
-    #[serde(rename = "file")]
-    File,
-    #[serde(rename = "syslog")]
-    Syslog,
-}
-
-#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
-pub struct LoggingConfig {
-    pub output: LogOutput,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub file: Option<PathBuf>,
-    pub level: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub sqlite: Option<SqliteLoggerConfig>,
-}
-
-impl Default for LoggingConfig {
-    fn default() -> Self {
-        Self {
-            output: LogOutput::Stdout,
-            file: None,
-            level: "info".to_string(),
-            sqlite: None,
-        }
-    }
-} 
\ No newline at end of file
@@ -1,36 +1,37 @@
 #![allow(unused)]
 
-mod config;
-mod logging;
-mod scheduler;
-mod sqlite_logger;
-mod task_executor;
-mod schedule_display;
-
-mod alerts;
-
-mod utils;
-
-use crate::alerts::AlertConfig;
-use crate::config::file::ConfigFile;
-use crate::config::file::ExplodedTimePatternConfig;
-use crate::config::file::ExplodedTimePatternFieldConfig;
-use crate::config::file::TaskDefinition;
-use crate::config::file::TimePatternConfig;
-use crate::config::file::validate_config_path;
-use crate::config::logging::LoggingConfig;
-use crate::scheduler::Scheduler;
-use crate::schedule_display::ScheduleDisplay;
-use crate::sqlite_logger::SqliteLogger;
-use crate::task_executor::TaskExecutor;
-use anyhow::anyhow;
-use clap::{Parser, Subcommand};
-use config::file::read_config_file;
-use config::parse_config_file;
-use config::validation::{validate_config, ValidationResult};
+use cron_rs_core::alerts::{configured_alerts, send_alert, AlertConfig, TaskExecutionDetails};
+use cron_rs_core::config::file::ConfigFile;
+use cron_rs_core::config::file::ExplodedTimePatternConfig;
+use cron_rs_core::config::file::ExplodedTimePatternFieldConfig;
+use cron_rs_core::config::file::TaskDefinition;
+use cron_rs_core::config::file::TimePatternConfig;
+use cron_rs_core::config::file::validate_config_path;
+use cron_rs_core::config::file::{list_config_files, parse_config_text};
+use cron_rs_core::config::format::{format_config, has_comments, strip_comments, FormatOptions};
+use cron_rs_core::config::logging::LoggingConfig;
+use cron_rs_core::config::scaffold::{generate_config, Scaffold};
+use cron_rs_core::control::ControlRequest;
+use cron_rs_core::crontab::{CrontabFormat, CrontabImport};
+use cron_rs_core::plugins::configure_plugins;
+use cron_rs_core::scheduler::Scheduler;
+use cron_rs_core::schedule_display::ScheduleDisplay;
+use cron_rs_core::sqlite_logger::SqliteLogger;
+use cron_rs_core::task_executor::TaskExecutor;
+use anyhow::{anyhow, Context};
+use clap::{Parser, Subcommand, ValueEnum};
+use cron_rs_core::config::file::read_config_file;
+use cron_rs_core::config::{parse_config_file, TaskFilter};
+use cron_rs_core::config::validation::{validate_config, ValidationReport, ValidationResult};
+use cron_rs_core::{config, control, crontab, k8s, launchd, logging, systemd, taskscheduler};
 use log::{debug, error, info, warn, LevelFilter};
 use std::io::{stdout, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+mod top;
+
+/// State of the tasks kept between restarts, like the counters of `max_runs`
+const STATE_FILE_PATH: &str = "./cron-rs_scheduler_state.json";
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
@@ -39,6 +40,10 @@ struct Args {
     #[arg(short, long, global = true)]
     config: Option<PathBuf>,
 
+    /// Directory with config files, all the YAML files inside are merged
+    #[arg(long, global = true, conflicts_with = "config")]
+    config_dir: Option<PathBuf>,
+
     #[command(subcommand)]
     cmd: ArgCmd,
 }
@@ -46,11 +51,41 @@ struct Args {
 #[derive(Debug, Clone, Subcommand)]
 enum ArgCmd {
     /// Run the tasks defined in the config file
-    Run,
-    /// Validate the config file
+    Run {
+        /// Only run the tasks with this tag, can be repeated
+        #[arg(long)]
+        tag: Vec<String>,
+        /// Only run the task with this name, can be repeated
+        #[arg(long)]
+        only: Vec<String>,
+    },
+    /// Validate the config file, exits with code 1 if there are errors
     Validate {
         /// Path to the config file to validate
         path: Option<PathBuf>,
+        /// Also fail if there are warnings
+        #[arg(long)]
+        strict: bool,
+        /// Output format of the diagnostics
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Rewrite config files in a canonical form, prints the result unless --write or --check are given
+    Fmt {
+        /// Config files or directories to format, by default the config file
+        paths: Vec<PathBuf>,
+        /// Write the result back to the files
+        #[arg(long, short, conflicts_with = "check")]
+        write: bool,
+        /// Only check that the files are formatted, exits with code 1 if one isn't
+        #[arg(long)]
+        check: bool,
+        /// Write the templates and the defaults into each task, and remove them
+        #[arg(long)]
+        expand_defaults: bool,
+        /// With --write, also format the files with comments, removing the comments
+        #[arg(long, requires = "write")]
+        drop_comments: bool,
     },
     /// Execute a specific task immediately
     ExecuteTask {
@@ -60,6 +95,64 @@ enum ArgCmd {
         #[arg(long, short)]
         config: Option<PathBuf>,
     },
+    /// List the tasks defined in the config file
+    List,
+    /// Print the output files of the last execution of a task
+    Logs {
+        /// Name of the task
+        task_name: String,
+        /// Only the stdout file
+        #[arg(long, conflicts_with = "stderr")]
+        stdout: bool,
+        /// Only the stderr file
+        #[arg(long)]
+        stderr: bool,
+        /// Only the last lines of each file
+        #[arg(long, short = 'n')]
+        lines: Option<usize>,
+        /// Keep printing the output as it is written, and the files of the next executions
+        #[arg(long, short)]
+        follow: bool,
+    },
+    /// Send a command to the running scheduler through its control socket
+    Ctl {
+        /// Path to the control socket, by default the one defined in the config file
+        #[arg(long, short)]
+        socket: Option<PathBuf>,
+
+        #[command(subcommand)]
+        cmd: CtlCmd,
+    },
+    /// Show the running tasks and the next run of each task, asking the running scheduler through its control socket
+    Status {
+        /// Path to the control socket, by default the one defined in the config file
+        #[arg(long, short)]
+        socket: Option<PathBuf>,
+        /// Output format of the status
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Compare two config files: the added, removed and changed tasks and how their next run changes
+    Diff {
+        /// The current config file or directory
+        old: PathBuf,
+        /// The config to compare with it
+        new: PathBuf,
+        /// Output format of the differences
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Watch the running tasks, the last executions and the next runs, refreshed every second from the control socket
+    Top {
+        /// Path to the control socket, by default the one defined in the config file
+        #[arg(long, short)]
+        socket: Option<PathBuf>,
+    },
+    /// Send a test alert through each alert of the config file, to check their settings without a failing task
+    TestAlert {
+        /// Only send this alert, by its position in the list or its name, like tasks.backup.on_failure[0]
+        alert: Option<String>,
+    },
     /// Show the schedule for all tasks
     ShowSchedule {
         /// Path to the config file (optional)
@@ -71,36 +164,183 @@ enum ArgCmd {
         /// Path to the file to write
         #[arg(long, short)]
         output: Option<PathBuf>,
+
+        /// Only keep these sample alerts: email, webhook or cmd, separated by commas. None with an empty value
+        #[arg(long, value_delimiter = ',')]
+        with_alerts: Option<Vec<String>>,
+
+        /// Number of sample tasks
+        #[arg(long, default_value_t = 1)]
+        tasks: u32,
+
+        /// Only the options that are set, without the documentation of the rest
+        #[arg(long)]
+        minimal: bool,
     },
     /// Look up the current user's crontab file and genera an equivalent config file
     GenerateFromCrontab {
-        /// Path to the crontab file to read
+        /// Path to the crontab file to read, with --system it can also be a directory like /etc/cron.d
         #[arg(long, short = 'f')]
         crontab_file: Option<PathBuf>,
 
+        /// Read system crontabs, with a user column before the command, by default /etc/crontab and /etc/cron.d
+        #[arg(long)]
+        system: bool,
+
+        /// Path to the file to write
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Convert systemd timers and the services they start into an equivalent config file
+    GenerateFromSystemd {
+        /// Timer files or directories with them, by default /etc/systemd/system
+        timers: Vec<PathBuf>,
+
+        /// Path to the file to write
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Convert the config file into a crontab, options without an equivalent in cron are left out with a warning
+    ExportCrontab {
+        /// Path to the file to write
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+
+        /// Write a system crontab, with the run_as user before each command
+        #[arg(long)]
+        system: bool,
+    },
+    /// Generate a systemd .service and .timer unit for each task of the config file
+    ExportSystemd {
+        /// Directory where the unit files are written
+        #[arg(long)]
+        output_dir: PathBuf,
+    },
+    /// Generate a launchd property list for each task of the config file
+    ExportLaunchd {
+        /// Directory where the .plist files are written
+        #[arg(long)]
+        output_dir: PathBuf,
+    },
+    /// Generate a Windows Task Scheduler definition for each task of the config file
+    ExportTaskscheduler {
+        /// Directory where the .xml files are written
+        #[arg(long)]
+        output_dir: PathBuf,
+    },
+    /// Run the scheduler as a Windows service
+    Service {
+        #[command(subcommand)]
+        cmd: ServiceCmd,
+    },
+    /// Generate a Kubernetes CronJob manifest for each task of the config file
+    ExportK8s {
+        /// Container image used to run the commands
+        #[arg(long)]
+        image: String,
+
         /// Path to the file to write
         #[arg(long, short)]
         output: Option<PathBuf>,
     },
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum ServiceCmd {
+    /// Register a service that starts with Windows and runs the config file, needs an administrator prompt
+    Install {
+        /// Name of the service
+        #[arg(long, default_value = "cron-rs")]
+        name: String,
+    },
+    /// Stop and remove the service
+    Uninstall {
+        /// Name of the service
+        #[arg(long, default_value = "cron-rs")]
+        name: String,
+    },
+    /// Run the scheduler as the service, started by the service control manager
+    Run {
+        /// Name of the service
+        #[arg(long, default_value = "cron-rs")]
+        name: String,
+    },
+}
+
+#[derive(Debug, Clone, Subcommand)]
+enum CtlCmd {
+    /// Enable a task, until the config is reloaded
+    Enable {
+        /// Name of the task to enable
+        task_name: String,
+    },
+    /// Disable a task, until the config is reloaded, its executions are skipped
+    Disable {
+        /// Name of the task to disable
+        task_name: String,
+    },
+    /// Change the log level of the running scheduler, until it restarts
+    LogLevel {
+        /// off, error, warn, info, debug or trace
+        level: String,
+    },
+    /// Run a task now, out of its schedule
+    Trigger {
+        /// Name of the task to run
+        task_name: String,
+
+        /// Wait for the execution to finish and exit with its exit code
+        #[arg(long)]
+        wait: bool,
+
+        /// Run the task even if it's disabled or already running with avoid_overlapping
+        #[arg(long)]
+        force: bool,
+    },
+    /// Schedule a new task until the config is reloaded, it's checked like the tasks of the config file
+    AddTask {
+        /// YAML or JSON file with the task, like an entry of `tasks` in the config file, - reads it from stdin
+        file: PathBuf,
+    },
+    /// Stop scheduling a task until the config is reloaded, a running execution is left to finish
+    RemoveTask {
+        /// Name of the task to remove
+        task_name: String,
+    },
+}
+
 fn main() -> anyhow::Result<()> {
-    let args = Args::parse();
+    let mut args = Args::parse();
+
+    // A directory is read as the merge of all the config files inside
+    if args.config_dir.is_some() {
+        args.config = args.config_dir.take();
+    }
 
     match args.cmd {
-        ArgCmd::Run => {
-            cmd_run(get_config_path(args.config)?)?;
+        ArgCmd::Run { tag, only } => {
+            cmd_run(get_config_path(args.config)?, TaskFilter { tags: tag, only })?;
             Ok(())
         }
-        ArgCmd::Validate { path } => {
+        ArgCmd::Validate { path, strict, format } => {
             let path = if let Some(path) = path {
                 path
             } else {
                 get_config_path(args.config)?
             };
-            cmd_validate_config_file(path)?;
+            cmd_validate_config_file(path, strict, format)?;
             Ok(())
         }
+        ArgCmd::Fmt { paths, write, check, expand_defaults, drop_comments } => {
+            let paths = if paths.is_empty() { vec![get_config_path(args.config)?] } else { paths };
+            cmd_fmt(paths, write, check, drop_comments, FormatOptions { expand_defaults })
+        }
         ArgCmd::ExecuteTask { task_name, config } => {
             let config_path = if let Some(config) = config {
                 config
@@ -110,6 +350,48 @@ fn main() -> anyhow::Result<()> {
             cmd_execute_task(config_path, task_name)?;
             Ok(())
         }
+        ArgCmd::List => {
+            cmd_list(get_config_path(args.config)?)?;
+            Ok(())
+        }
+        ArgCmd::Logs { task_name, stdout, stderr, lines, follow } => {
+            let streams = match (stdout, stderr) {
+                (true, _) => vec!["stdout"],
+                (_, true) => vec!["stderr"],
+                _ => vec!["stdout", "stderr"],
+            };
+            cmd_logs(get_config_path(args.config)?, task_name, streams, lines, follow)
+        }
+        ArgCmd::Ctl { socket, cmd } => {
+            let socket_path = if let Some(socket) = socket {
+                socket
+            } else {
+                get_control_socket_path(args.config)?
+            };
+            cmd_ctl(socket_path, cmd)?;
+            Ok(())
+        }
+        ArgCmd::Status { socket, format } => {
+            let socket_path = if let Some(socket) = socket {
+                socket
+            } else {
+                get_control_socket_path(args.config)?
+            };
+            cmd_status(socket_path, format)
+        }
+        ArgCmd::Diff { old, new, format } => cmd_diff(old, new, format),
+        ArgCmd::Top { socket } => {
+            let socket_path = if let Some(socket) = socket {
+                socket
+            } else {
+                get_control_socket_path(args.config)?
+            };
+            top::run(&socket_path)
+        }
+        ArgCmd::TestAlert { alert } => {
+            cmd_test_alert(get_config_path(args.config)?, alert)?;
+            Ok(())
+        }
         ArgCmd::ShowSchedule { config } => {
             let config_path = if let Some(config) = config {
                 config
@@ -119,27 +401,68 @@ fn main() -> anyhow::Result<()> {
             cmd_show_schedule(config_path)?;
             Ok(())
         }
-        ArgCmd::GenerateConfig { output } => {
-            cmd_generate_default_config(output)?;
+        ArgCmd::GenerateConfig { output, with_alerts, tasks, minimal } => {
+            let alerts = with_alerts
+                .map(|alerts| alerts.iter().filter(|alert| !alert.is_empty()).map(|alert| alert.parse()).collect::<anyhow::Result<_>>())
+                .transpose()?;
+            cmd_generate_default_config(output, Scaffold { tasks, alerts, minimal })?;
+            Ok(())
+        }
+        ArgCmd::GenerateFromCrontab { output, crontab_file, system } => {
+            cmd_generate_config_from_crontab(output, crontab_file, system)?;
+            Ok(())
+        }
+        ArgCmd::GenerateFromSystemd { timers, output } => {
+            cmd_generate_config_from_systemd(output, timers)?;
+            Ok(())
+        }
+        ArgCmd::ExportCrontab { output, system } => {
+            cmd_export_crontab(get_config_path(args.config)?, output, system)?;
+            Ok(())
+        }
+        ArgCmd::ExportSystemd { output_dir } => {
+            cmd_export_systemd(get_config_path(args.config)?, output_dir)?;
+            Ok(())
+        }
+        ArgCmd::ExportLaunchd { output_dir } => {
+            cmd_export_launchd(get_config_path(args.config)?, output_dir)?;
+            Ok(())
+        }
+        ArgCmd::ExportTaskscheduler { output_dir } => {
+            cmd_export_taskscheduler(get_config_path(args.config)?, output_dir)?;
             Ok(())
         }
-        ArgCmd::GenerateFromCrontab { output, crontab_file } => {
-            cmd_generate_config_from_crontab(output, crontab_file)?;
+        ArgCmd::Service { cmd } => {
+            cmd_service(get_config_path(args.config)?, cmd)?;
+            Ok(())
+        }
+        ArgCmd::ExportK8s { image, output } => {
+            cmd_export_k8s(get_config_path(args.config)?, image, output)?;
             Ok(())
         }
     }
 }
 
-fn cmd_run(config_path: PathBuf) -> anyhow::Result<()> {
+fn cmd_run(config_path: PathBuf, task_filter: TaskFilter) -> anyhow::Result<()> {
     validate_config_path(&config_path)?;
 
     let config_file = read_config_file(&config_path)?;
-    let config = parse_config_file(&config_file)?;
+    let mut config = parse_config_file(&config_file)?;
     logging::setup_logging(&config.logging)?;
 
     info!("Starting cron-rs with config file: {}", config_path.to_string_lossy());
 
-    Scheduler::new(config, config_path).run();
+    if !task_filter.is_empty() {
+        task_filter.apply(&mut config);
+        info!("Running {} tasks selected by --tag/--only", config.tasks.len());
+    }
+
+    Scheduler::builder(config)
+        .config_path(config_path)
+        .task_filter(task_filter)
+        .state_file(STATE_FILE_PATH)
+        .build()
+        .run()?;
 
     info!("Exiting");
     Ok(())
@@ -150,6 +473,7 @@ fn cmd_execute_task(config_path: PathBuf, task_name: String) -> anyhow::Result<(
     runtime.block_on(async move {
         let config_file = read_config_file(&config_path)?;
         let config = parse_config_file(&config_file)?;
+        configure_plugins(&config.plugins);
         
         // Find the task
         let task = config.tasks.iter().find(|t| t.name == task_name)
@@ -173,7 +497,9 @@ fn cmd_execute_task(config_path: PathBuf, task_name: String) -> anyhow::Result<(
         };
         
         // Create task executor
-        let executor = TaskExecutor::new(config.alerts, sqlite_logger);
+        let mut executor = TaskExecutor::new(config.alerts.clone(), sqlite_logger);
+        executor.cgroup_root = config.cgroup_root_path();
+        executor.sentry = config.sentry.clone();
         
         // Execute the task
         println!("Executing task '{}'...", task_name);
@@ -182,8 +508,21 @@ fn cmd_execute_task(config_path: PathBuf, task_name: String) -> anyhow::Result<(
                 println!("Task '{}' completed:", task_name);
                 println!("  Status: {}", if result.success { "Success" } else { "Failed" });
                 println!("  Exit code: {}", result.exit_code);
-                println!("  Duration: {}", crate::utils::format_duration(result.duration));
+                println!("  Duration: {}", cron_rs_core::utils::format_duration(result.duration));
                 println!("  PID: {}", result.pid);
+                println!("  Run id: {}", result.run_id);
+                if let Some(peak_memory) = result.peak_memory {
+                    println!("  Peak memory: {}", cron_rs_core::utils::format_size(peak_memory));
+                }
+                if let Some(usage) = result.usage {
+                    println!("  Max RSS: {}", cron_rs_core::utils::format_size(usage.max_rss));
+                    println!("  CPU time: {:.2}s user, {:.2}s system", usage.user_cpu_seconds, usage.system_cpu_seconds);
+                    println!(
+                        "  I/O: {} read, {} written",
+                        cron_rs_core::utils::format_size(usage.read_bytes),
+                        cron_rs_core::utils::format_size(usage.write_bytes)
+                    );
+                }
                 
                 if !result.stdout.is_empty() {
                     println!("  Stdout: {}", result.stdout.trim());
@@ -212,7 +551,178 @@ fn cmd_show_schedule(config_path: PathBuf) -> anyhow::Result<()> {
     Ok(())
 }
 
-fn cmd_validate_config_file(path: PathBuf) -> anyhow::Result<()> {
+fn cmd_list(config_path: PathBuf) -> anyhow::Result<()> {
+    let config_file = read_config_file(&config_path)?;
+    let config = parse_config_file(&config_file)?;
+
+    print!("{}", ScheduleDisplay::display_task_list(&config));
+    Ok(())
+}
+
+/// A file of `cron-rs logs`, the file changes when the next execution of the task starts
+struct LogFile {
+    stream: &'static str,
+    path: Option<PathBuf>,
+    /// What has been printed
+    offset: u64,
+}
+
+fn cmd_logs(config_path: PathBuf, task_name: String, streams: Vec<&'static str>, lines: Option<usize>, follow: bool) -> anyhow::Result<()> {
+    let config_file = read_config_file(&config_path)?;
+    let config = parse_config_file(&config_file)?;
+    let task = config.tasks.iter().find(|t| t.name == task_name).ok_or_else(|| anyhow!("Task '{}' not found", task_name))?;
+
+    // With combine_output both streams are in the stdout file
+    let streams: Vec<&str> = streams.into_iter().filter(|stream| *stream == "stdout" || !task.combine_output).collect();
+    let mut files: Vec<LogFile> = streams.iter().map(|stream| LogFile { stream, path: None, offset: 0 }).collect();
+    let show_names = files.len() > 1;
+    let mut last_printed = None;
+    let mut first = true;
+
+    loop {
+        for (index, file) in files.iter_mut().enumerate() {
+            let Some(path) = task.latest_output_path(file.stream) else { continue };
+            let path = std::fs::canonicalize(&path).unwrap_or(path);
+            let Ok(content) = std::fs::read(&path) else { continue };
+            if file.path.as_ref() != Some(&path) || (content.len() as u64) < file.offset {
+                // Another execution, or the file was truncated by the next one
+                file.path = Some(path);
+                file.offset = 0;
+                last_printed = None;
+            }
+            let mut new = &content[file.offset as usize..];
+            if let (true, Some(lines)) = (first, lines) {
+                new = last_lines(new, lines);
+            }
+            if new.is_empty() {
+                continue;
+            }
+            if show_names && last_printed != Some(index) {
+                println!("==> {} <==", file.path.as_ref().unwrap().display());
+            }
+            stdout().write_all(new)?;
+            stdout().flush()?;
+            file.offset = content.len() as u64;
+            last_printed = Some(index);
+        }
+
+        if first && files.iter().all(|file| file.path.is_none()) && !follow {
+            return Err(anyhow!("Task '{}' has no output files yet", task_name));
+        }
+        first = false;
+        if !follow {
+            return Ok(());
+        }
+        std::thread::sleep(std::time::Duration::from_millis(500));
+    }
+}
+
+/// The last `lines` lines of the text, the newline at the end doesn't start another line
+fn last_lines(text: &[u8], lines: usize) -> &[u8] {
+    if lines == 0 {
+        return &[];
+    }
+    let end = text.strip_suffix(b"\n").unwrap_or(text);
+    match end.iter().enumerate().rev().filter(|(_, b)| **b == b'\n').nth(lines - 1) {
+        Some((newline, _)) => &text[newline + 1..],
+        None => text,
+    }
+}
+
+fn cmd_test_alert(config_path: PathBuf, selected: Option<String>) -> anyhow::Result<()> {
+    let config_file = read_config_file(&config_path)?;
+    let config = parse_config_file(&config_file)?;
+    configure_plugins(&config.plugins);
+
+    let alerts: Vec<_> = configured_alerts(&config)
+        .into_iter()
+        .enumerate()
+        .map(|(index, alert)| (index + 1, alert))
+        .filter(|(position, alert)| selected.as_ref().is_none_or(|s| alert.name == *s || position.to_string() == *s))
+        .collect();
+    match &selected {
+        Some(selected) if alerts.is_empty() => return Err(anyhow!("Alert '{}' not found", selected)),
+        None if alerts.is_empty() => return Err(anyhow!("The config file has no alerts")),
+        _ => {}
+    }
+
+    let mut failed = 0;
+    for (position, configured) in &alerts {
+        print!("{}. {} ({})... ", position, configured.name, configured.alert.kind());
+        stdout().flush()?;
+        match send_alert(configured.alert, &TaskExecutionDetails::test(configured.task)) {
+            Ok(()) => println!("sent"),
+            Err(e) => {
+                println!("failed: {:#}", e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(anyhow!("{} of {} alerts failed", failed, alerts.len()));
+    }
+    Ok(())
+}
+
+fn cmd_ctl(socket_path: PathBuf, cmd: CtlCmd) -> anyhow::Result<()> {
+    let request = match cmd {
+        CtlCmd::Enable { task_name } => ControlRequest::Enable { task: task_name },
+        CtlCmd::Disable { task_name } => ControlRequest::Disable { task: task_name },
+        CtlCmd::LogLevel { level } => ControlRequest::LogLevel { level },
+        CtlCmd::Trigger { task_name, wait, force } => ControlRequest::Trigger { task: task_name, wait, force },
+        CtlCmd::AddTask { file } => ControlRequest::AddTask { task: Box::new(read_task_definition(&file)?) },
+        CtlCmd::RemoveTask { task_name } => ControlRequest::RemoveTask { task: task_name },
+    };
+
+    let response = control::send_request(&socket_path, &request)?;
+    if !response.ok {
+        return Err(anyhow!("{}", response.message));
+    }
+
+    println!("{}", response.message);
+    // A failed execution exits with its exit code, or with 1 if it was killed or failed with a 0
+    if let Some(run) = response.run.filter(|run| !run.success) {
+        std::process::exit(if run.exit_code > 0 { run.exit_code } else { 1 });
+    }
+    Ok(())
+}
+
+/// A single task, YAML also reads JSON
+fn read_task_definition(path: &Path) -> anyhow::Result<TaskDefinition> {
+    let content = if path == Path::new("-") {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        std::fs::read_to_string(path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?
+    };
+    serde_yml::from_str(&content).map_err(|e| anyhow!("Malformed task definition: {}", e))
+}
+
+fn cmd_status(socket_path: PathBuf, format: OutputFormat) -> anyhow::Result<()> {
+    let response = control::send_request(&socket_path, &ControlRequest::Status)?;
+    let Some(status) = response.status.filter(|_| response.ok) else {
+        return Err(anyhow!("{}", response.message));
+    };
+
+    match format {
+        OutputFormat::Text => print!("{}", status.text()),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&status)?),
+    }
+    Ok(())
+}
+
+/// Finds the control socket from the config file, falling back to the default location
+fn get_control_socket_path(config_path: Option<PathBuf>) -> anyhow::Result<PathBuf> {
+    let Ok(config_path) = get_config_path(config_path) else {
+        return Ok(PathBuf::from(control::DEFAULT_SOCKET_PATH));
+    };
+
+    let config_file = read_config_file(&config_path)?;
+    let config = parse_config_file(&config_file)?;
+    Ok(config.control_socket_path())
+}
+
+/// Logger for commands that report problems to the user, without timestamps or module paths
+fn init_cli_logger() {
     env_logger::Builder::new()
         .filter_level(LevelFilter::Info)
         .format_timestamp(None)
@@ -223,28 +733,141 @@ fn cmd_validate_config_file(path: PathBuf) -> anyhow::Result<()> {
         .format_file(false)
         .format_line_number(false)
         .init();
+}
+
+/// Fails when the config is not valid, so the command exits with 1
+fn cmd_diff(old: PathBuf, new: PathBuf, format: OutputFormat) -> anyhow::Result<()> {
+    let old_file = read_config_file(&old)?;
+    let new_file = read_config_file(&new)?;
+    let diff = config::diff::diff_configs(&old_file, &new_file, chrono::Utc::now())?;
 
-    let config_file = read_config_file(path)?;
-    let info = validate_config(&config_file);
+    match format {
+        OutputFormat::Text => print!("{}", diff.text()),
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+    }
+    Ok(())
+}
 
-    for msg in &info {
-        match msg {
-            ValidationResult::Error(m) => {
-                error!("{}", m);
+fn cmd_validate_config_file(path: PathBuf, strict: bool, format: OutputFormat) -> anyhow::Result<()> {
+    init_cli_logger();
+
+    let report = validation_report(path, strict, format)?;
+    match format {
+        OutputFormat::Text => {
+            for msg in &report.diagnostics {
+                if msg.is_error() {
+                    error!("{}", msg);
+                } else {
+                    warn!("{}", msg);
+                }
             }
-            ValidationResult::Warning(m) => {
-                warn!("{}", m);
+
+            if report.diagnostics.is_empty() {
+                info!("Config file is valid");
             }
         }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
     }
 
-    if info.is_empty() {
-        info!("Config file is valid");
+    if !report.valid {
+        return Err(anyhow!("The config file is not valid"));
     }
     Ok(())
 }
 
-fn cmd_generate_config_from_crontab(path: Option<PathBuf>, crontab_file: Option<PathBuf>) -> anyhow::Result<()> {
+/// Fails in check mode when a file isn't formatted, so the command exits with 1. The comments are not kept, so they
+/// are ignored by the check and the files with comments are only written with `drop_comments`
+fn cmd_fmt(
+    paths: Vec<PathBuf>,
+    write: bool,
+    check: bool,
+    drop_comments: bool,
+    options: FormatOptions,
+) -> anyhow::Result<()> {
+    init_cli_logger();
+
+    let mut files = vec![];
+    for path in paths {
+        if path.is_dir() {
+            files.extend(list_config_files(&path)?);
+        } else {
+            files.push(path);
+        }
+    }
+    if files.len() > 1 && !write && !check {
+        return Err(anyhow!("Use --write or --check to format several files"));
+    }
+
+    let mut unformatted = false;
+    let mut commented = false;
+    for file in files {
+        let content = std::fs::read_to_string(&file).with_context(|| format!("Failed to read {}", file.display()))?;
+        let config_file = parse_config_text(&file).with_context(|| format!("Failed to read {}", file.display()))?;
+        let formatted = format_config(&config_file, &options).with_context(|| format!("Failed to format {}", file.display()))?;
+
+        if !write && !check {
+            print!("{}", formatted);
+        } else if strip_comments(&content) != formatted {
+            if check {
+                warn!("{} is not formatted", file.display());
+                unformatted = true;
+            } else if has_comments(&content) && !drop_comments {
+                warn!("{} was not written, formatting it removes its comments, see --drop-comments", file.display());
+                commented = true;
+            } else {
+                std::fs::write(&file, formatted).with_context(|| format!("Failed to write {}", file.display()))?;
+                info!("Formatted {}", file.display());
+            }
+        }
+    }
+
+    if unformatted {
+        return Err(anyhow!("Some files are not formatted"));
+    }
+    if commented {
+        return Err(anyhow!("Some files with comments were not formatted"));
+    }
+    Ok(())
+}
+
+fn validation_report(path: PathBuf, strict: bool, format: OutputFormat) -> anyhow::Result<ValidationReport> {
+    let diagnostics = match read_config_file(path) {
+        Ok(config_file) => {
+            for task in config_file.tasks.iter().filter(|t| !t.enabled) {
+                info!("Task '{}' is disabled", task.name);
+            }
+            validate_config(&config_file)
+        }
+        // The JSON output must include the errors that prevent reading the file
+        Err(e) if format == OutputFormat::Json => vec![ValidationResult::error(format!("{:#}", e))],
+        Err(e) => return Err(e),
+    };
+    Ok(ValidationReport::new(diagnostics, strict))
+}
+
+fn cmd_generate_config_from_crontab(path: Option<PathBuf>, crontab_file: Option<PathBuf>, system: bool) -> anyhow::Result<()> {
+    init_cli_logger();
+
+    if system {
+        let paths = match crontab_file {
+            Some(path) => vec![path],
+            None => ["/etc/crontab", "/etc/cron.d"].into_iter().map(PathBuf::from).filter(|p| p.exists()).collect(),
+        };
+
+        let mut crontabs = vec![];
+        for path in paths {
+            for file in crontab::list_crontab_files(&path)? {
+                let content = std::fs::read_to_string(&file)
+                    .map_err(|e| anyhow::anyhow!("Failed to read crontab {}: {}", file.display(), e))?;
+                crontabs.push(content);
+            }
+        }
+
+        let crontabs: Vec<&str> = crontabs.iter().map(String::as_str).collect();
+        let import = crontab::import_crontab_files(&crontabs, CrontabFormat::System)?;
+        return print_crontab_import(&import, &path);
+    }
+
     // Crontab file contents
     let crontab = if let Some(crontab_file) = crontab_file {
         // If a file path is provided, read the crontab from that file
@@ -265,21 +888,173 @@ fn cmd_generate_config_from_crontab(path: Option<PathBuf>, crontab_file: Option<
         String::from_utf8(output.stdout)?
     };
 
-    let tasks = parse_crontab_file(&crontab)?;
-    let config = ConfigFile {
-        logging: Some(LoggingConfig { ..Default::default() }),
-        alerts: Some(AlertConfig { ..Default::default() }),
-        tasks,
-        ..Default::default()
-    };
+    let import = crontab::import_crontab_files(&[&crontab], CrontabFormat::User)?;
+    print_crontab_import(&import, &path)
+}
 
+/// Writes the imported config and warns about what didn't translate cleanly, including the problems `cron-rs validate`
+/// would find in the result
+fn print_crontab_import(import: &CrontabImport, path: &Option<PathBuf>) -> anyhow::Result<()> {
+    let config_file_contents = import.to_yaml()?;
+    let config_file: ConfigFile = serde_yml::from_str(&config_file_contents)?;
+
+    for warning in &import.warnings {
+        warn!("{}", warning);
+    }
+    for msg in validate_config(&config_file) {
+        warn!("{}", msg);
+    }
+
+    print_config_file(config_file_contents.as_bytes(), path)
+}
+
+fn cmd_generate_config_from_systemd(path: Option<PathBuf>, timers: Vec<PathBuf>) -> anyhow::Result<()> {
+    init_cli_logger();
+
+    let paths = if timers.is_empty() { vec![PathBuf::from("/etc/systemd/system")] } else { timers };
+    let mut timer_files = vec![];
+    for path in paths {
+        timer_files.extend(systemd::list_timer_files(&path)?);
+    }
+
+    let config = systemd::read_timer_units(&timer_files)?;
     let config_file_contents = serde_yml::to_string(&config)?;
     print_config_file(config_file_contents.as_bytes(), &path)?;
     Ok(())
 }
 
-fn cmd_generate_default_config(path: Option<PathBuf>) -> anyhow::Result<()> {
-    print_config_file(include_bytes!("config/default_config.yml"), &path)?;
+fn cmd_export_crontab(config_path: PathBuf, output: Option<PathBuf>, system: bool) -> anyhow::Result<()> {
+    init_cli_logger();
+
+    let config_file = read_config_file(&config_path)?;
+    let config = parse_config_file(&config_file)?;
+
+    let format = if system { CrontabFormat::System } else { CrontabFormat::User };
+    let crontab = crontab::export_crontab(&config, format);
+    print_config_file(crontab.as_bytes(), &output)?;
+    Ok(())
+}
+
+fn cmd_export_systemd(config_path: PathBuf, output_dir: PathBuf) -> anyhow::Result<()> {
+    init_cli_logger();
+
+    let config_file = read_config_file(&config_path)?;
+    let config = parse_config_file(&config_file)?;
+
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| anyhow!("Failed to create directory {}: {}", output_dir.to_string_lossy(), e))?;
+
+    let units = systemd::export_systemd(&config);
+    for unit in &units {
+        for (extension, contents) in [("service", &unit.service), ("timer", &unit.timer)] {
+            let path = output_dir.join(format!("{}.{}", unit.name, extension));
+            std::fs::write(&path, contents)
+                .map_err(|e| anyhow!("Failed to write {}: {}", path.to_string_lossy(), e))?;
+            println!("Written {}", path.to_string_lossy());
+        }
+    }
+
+    let enabled: Vec<String> = units.iter().filter(|u| u.enabled).map(|u| format!("{}.timer", u.name)).collect();
+    if !enabled.is_empty() {
+        println!("\nCopy the files to /etc/systemd/system and run:");
+        println!("  systemctl daemon-reload");
+        println!("  systemctl enable --now {}", enabled.join(" "));
+    }
+    Ok(())
+}
+
+fn cmd_export_launchd(config_path: PathBuf, output_dir: PathBuf) -> anyhow::Result<()> {
+    init_cli_logger();
+
+    let config_file = read_config_file(&config_path)?;
+    let config = parse_config_file(&config_file)?;
+
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| anyhow!("Failed to create directory {}: {}", output_dir.to_string_lossy(), e))?;
+
+    let jobs = launchd::export_launchd(&config);
+    for job in &jobs {
+        let path = output_dir.join(format!("{}.plist", job.label));
+        std::fs::write(&path, &job.plist).map_err(|e| anyhow!("Failed to write {}: {}", path.to_string_lossy(), e))?;
+        println!("Written {}", path.to_string_lossy());
+    }
+
+    if !jobs.is_empty() {
+        println!("\nCopy the files to ~/Library/LaunchAgents (or /Library/LaunchDaemons for run_as) and load them with:");
+        println!("  launchctl load ~/Library/LaunchAgents/<label>.plist");
+    }
+    Ok(())
+}
+
+fn cmd_export_taskscheduler(config_path: PathBuf, output_dir: PathBuf) -> anyhow::Result<()> {
+    init_cli_logger();
+
+    let config_file = read_config_file(&config_path)?;
+    let config = parse_config_file(&config_file)?;
+
+    std::fs::create_dir_all(&output_dir)
+        .map_err(|e| anyhow!("Failed to create directory {}: {}", output_dir.to_string_lossy(), e))?;
+
+    let tasks = taskscheduler::export_taskscheduler(&config);
+    for task in &tasks {
+        let path = output_dir.join(format!("{}.xml", task.name));
+        // schtasks reads the files as UTF-16, with a byte order mark
+        let contents: Vec<u8> = std::iter::once(0xFEFF).chain(task.xml.encode_utf16()).flat_map(u16::to_le_bytes).collect();
+        std::fs::write(&path, contents).map_err(|e| anyhow!("Failed to write {}: {}", path.to_string_lossy(), e))?;
+        println!("Written {}", path.to_string_lossy());
+    }
+
+    if !tasks.is_empty() {
+        println!("\nRegister them from an administrator prompt with (add /RU <user> /RP for tasks with run_as):");
+        println!("  schtasks /Create /TN \"cron-rs\\<name>\" /XML \"<name>.xml\"");
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn cmd_service(config_path: PathBuf, cmd: ServiceCmd) -> anyhow::Result<()> {
+    use cron_rs_core::windows_service;
+
+    match cmd {
+        ServiceCmd::Install { name } => {
+            validate_config_path(&config_path)?;
+            windows_service::install(&name, &config_path)?;
+            println!("Service '{}' installed, start it with: sc start {}", name, name);
+        }
+        ServiceCmd::Uninstall { name } => {
+            windows_service::uninstall(&name)?;
+            println!("Service '{}' removed", name);
+        }
+        ServiceCmd::Run { name } => {
+            // Services start in System32, the relative paths of the config are relative to its directory
+            let config_path = std::path::absolute(&config_path)?;
+            if let Some(dir) = config_path.parent() {
+                std::env::set_current_dir(dir)?;
+            }
+            windows_service::run(&name, move || cmd_run(config_path, TaskFilter::default()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(not(windows))]
+fn cmd_service(_config_path: PathBuf, _cmd: ServiceCmd) -> anyhow::Result<()> {
+    Err(anyhow!("Windows services are only available on Windows, use export-systemd or export-launchd instead"))
+}
+
+fn cmd_export_k8s(config_path: PathBuf, image: String, output: Option<PathBuf>) -> anyhow::Result<()> {
+    init_cli_logger();
+
+    let config_file = read_config_file(&config_path)?;
+    let config = parse_config_file(&config_file)?;
+
+    let manifests = k8s::export_k8s(&config, &image)?;
+    print_config_file(manifests.as_bytes(), &output)?;
+    Ok(())
+}
+
+fn cmd_generate_default_config(path: Option<PathBuf>, scaffold: Scaffold) -> anyhow::Result<()> {
+    print_config_file(generate_config(&scaffold).as_bytes(), &path)?;
     Ok(())
 }
 
@@ -305,7 +1080,7 @@ fn print_config_file(contents: &[u8], path: &Option<PathBuf>) -> anyhow::Result<
                 }
             }
 
-            std::fs::write(&path, contents).expect("Unable to write file");
+            std::fs::write(path, contents).expect("Unable to write file");
 
             println!("Generated config file at {}", path.to_string_lossy());
         }
@@ -316,121 +1091,20 @@ fn print_config_file(contents: &[u8], path: &Option<PathBuf>) -> anyhow::Result<
     Ok(())
 }
 
-fn parse_crontab_file(crontab: &str) -> anyhow::Result<Vec<TaskDefinition>> {
-    let mut tasks = vec![];
-    let mut last_comment = String::new();
-
-    for line in crontab.lines() {
-        let line = line.trim();
-        if line.is_empty() {
-            last_comment.clear();
-            continue;
-        }
-
-        if line.starts_with('#') {
-            last_comment.push_str(" ");
-            last_comment.push_str(line[1..].trim());
-            continue;
-        }
-
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() < 6 {
-            last_comment.clear();
-            continue;
-        }
-
-        let (minute, hour, day, month, day_of_week) = (parts[0], parts[1], parts[2], parts[3], parts[4]);
-        let cmd = parts[5..].join(" ");
-
-        let name = if last_comment.trim().is_empty() {
-            format!("Crontab: {}", line)
-        } else {
-            last_comment.trim().to_string()
-        };
-
-        let map = |s: &str| {
-            let mut text = s.replace("-", "..");
-            if text.contains(',') {
-                let options: Vec<String> = text.split(',').map(|s| s.trim().to_string()).collect();
-
-                let mut result = vec![];
-
-                for opt in options {
-                    if opt.contains("..") {
-                        let range_parts: Vec<&str> = opt.split("..").collect();
-                        if range_parts.len() != 2 {
-                            warn!("Found invalid range format in crontab, ignoring: {}", opt);
-                            continue;
-                        }
-
-                        let (start, end) = match (range_parts[0].parse::<u32>(), range_parts[1].parse::<u32>()) {
-                            (Ok(start), Ok(end)) => (start, end),
-                            _ => {
-                                warn!("Found non-numeric range in crontab, ignoring: {}", opt);
-                                continue;
-                            }
-                        };
-
-                        if start > end {
-                            warn!("Found invalid range in crontab, ignoring: {}", opt);
-                            continue;
-                        }
-
-                        for i in start..=end {
-                            result.push(i.to_string());
-                        }
-                    } else {
-                        result.push(opt);
-                    }
-                }
-
-                if result.len() == 1 {
-                    let first = result.into_iter().next().unwrap();
-                    ExplodedTimePatternFieldConfig::Text(first)
-                } else {
-                    let list = format!("[{}]", result.join(", "));
-                    ExplodedTimePatternFieldConfig::Text(list)
-                }
-            } else {
-                ExplodedTimePatternFieldConfig::Text(text)
-            }
-        };
-
-        let task = TaskDefinition {
-            name,
-            cmd,
-            when: Some(TimePatternConfig::Long(ExplodedTimePatternConfig {
-                second: None,
-                minute: Some(map(minute)),
-                hour: Some(map(hour)),
-                day: Some(map(day)),
-                month: Some(map(month)),
-                year: None,
-                day_of_week: Some(map(day_of_week)),
-            })),
-            ..Default::default()
-        };
-
-        tasks.push(task);
-    }
-
-    Ok(tasks)
-}
-
 fn get_config_path(mut config_path: Option<PathBuf>) -> anyhow::Result<PathBuf> {
     // If not provided, check in the current directory for `config.yml`
-    if config_path.is_none() {
-        if std::fs::exists("./config.yml")? {
-            config_path = Some(PathBuf::from("./config.yml"));
-        }
+    if config_path.is_none() && std::fs::exists("./config.yml")? {
+        config_path = Some(PathBuf::from("./config.yml"));
     }
 
-    // or check in the default config directory `$XDG_CONFIG_HOME/cron-rs` or `$HOME/.config/cron-rs`
+    // or check in the default config directory `$XDG_CONFIG_HOME/cron-rs`, `$HOME/.config/cron-rs` or `%APPDATA%\cron-rs`
     if config_path.is_none() {
         let config_location = if let Ok(dir) = std::env::var("XDG_CONFIG_HOME") {
             format!("{}/cron-rs/config.yml", dir)
         } else if let Ok(dir) = std::env::var("HOME") {
             format!("{}/.config/cron-rs/config.yml", dir)
+        } else if let Ok(dir) = std::env::var("APPDATA") {
+            format!("{}\\cron-rs\\config.yml", dir)
         } else {
             "./config.yml".to_string()
         };
@@ -441,10 +1115,8 @@ fn get_config_path(mut config_path: Option<PathBuf>) -> anyhow::Result<PathBuf>
     }
 
     // or check the system-wide config directory `/etc/cron-rs.yml`
-    if config_path.is_none() {
-        if std::fs::exists("/etc/cron-rs.yml")? {
-            config_path = Some(PathBuf::from("/etc/cron-rs.yml"));
-        }
+    if config_path.is_none() && std::fs::exists("/etc/cron-rs.yml")? {
+        config_path = Some(PathBuf::from("/etc/cron-rs.yml"));
     }
 
     // Not specified and not found in any of the default locations
@@ -456,3 +1128,44 @@ fn get_config_path(mut config_path: Option<PathBuf>) -> anyhow::Result<PathBuf>
 
     Ok(config_path.unwrap())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validation_report() {
+        let dir = std::env::temp_dir().join(format!("cron-rs-test-validate-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, content: &str| {
+            let path = dir.join(name);
+            std::fs::write(&path, content).unwrap();
+            path
+        };
+        let valid = write("valid.yml", "tasks: [{ name: backup, cmd: 'true', every: 1 hour }]");
+        let warning = write("warning.yml", "tasks: [{ name: backup, cmd: 'true', every: 1 hour, max_runs: 0 }]");
+        let invalid = write("invalid.yml", "tasks: [{ name: backup, cmd: '', every: 1 hour }]");
+        let unreadable = dir.join("missing.yml");
+
+        // The command fails, and exits with 1, when the report is not valid
+        assert!(validation_report(valid.clone(), true, OutputFormat::Text).unwrap().valid);
+        assert!(validation_report(warning.clone(), false, OutputFormat::Text).unwrap().valid);
+        assert!(!validation_report(warning, true, OutputFormat::Text).unwrap().valid);
+        assert!(!validation_report(invalid, false, OutputFormat::Text).unwrap().valid);
+
+        // A file that can't be read is an error of the report in JSON
+        assert!(validation_report(unreadable.clone(), false, OutputFormat::Text).is_err());
+        let report = validation_report(unreadable, false, OutputFormat::Json).unwrap();
+        assert!(!report.valid);
+        assert!(report.diagnostics[0].is_error());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_last_lines() {
+        assert_eq!(last_lines(b"a\nb\nc\n", 2), b"b\nc\n");
+        assert_eq!(last_lines(b"a\nb\nc", 1), b"c");
+        assert_eq!(last_lines(b"a\nb\n", 5), b"a\nb\n");
+        assert_eq!(last_lines(b"a\nb\n", 0), b"");
+    }
+}
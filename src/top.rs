@@ -0,0 +1,225 @@
+use chrono::{DateTime, Local, Utc};
+use cron_rs_core::control::{self, ControlRequest, SchedulerStatus};
+use cron_rs_core::utils::format_duration;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// How often the status is requested from the scheduler
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Rows shown of the running and the recent executions, the rest of the screen is for the tasks
+const MAX_ROWS: usize = 10;
+
+/// Shows the status of the scheduler of the control socket until `q` is pressed
+pub fn run(socket_path: &Path) -> anyhow::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = run_loop(&mut terminal, socket_path);
+    ratatui::restore();
+    result
+}
+
+fn run_loop(terminal: &mut DefaultTerminal, socket_path: &Path) -> anyhow::Result<()> {
+    loop {
+        // The scheduler may restart while it's watched, the errors are shown until it comes back
+        let status = match control::send_request(socket_path, &ControlRequest::Status) {
+            Ok(response) => response.status.filter(|_| response.ok).ok_or(response.message),
+            Err(e) => Err(format!("{:#}", e)),
+        };
+        let now = Utc::now();
+        terminal.draw(|frame| draw(frame, &status, socket_path, now))?;
+
+        let deadline = Instant::now() + REFRESH_INTERVAL;
+        while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+            if !event::poll(timeout)? {
+                break;
+            }
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    let ctrl_c = key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c');
+                    if ctrl_c || matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                        return Ok(());
+                    }
+                }
+                Event::Resize(_, _) => break,
+                _ => {}
+            }
+        }
+    }
+}
+
+fn draw(frame: &mut Frame, status: &Result<SchedulerStatus, String>, socket_path: &Path, now: DateTime<Utc>) {
+    let status = match status {
+        Ok(status) => status,
+        Err(e) => {
+            let [header, message] = Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(frame.area());
+            frame.render_widget(Line::from(format!("cron-rs top - {}", socket_path.display())).bold(), header);
+            frame.render_widget(Line::from(e.as_str()).red(), message);
+            return;
+        }
+    };
+
+    let failed = status.recent.iter().filter(|run| !run.success).count();
+    let summary = format!(
+        "cron-rs top - {} - {} tasks, {} running, {} of the last {} runs failed - {} - q to quit",
+        socket_path.display(),
+        status.tasks.len(),
+        status.running.len(),
+        failed,
+        status.recent.len(),
+        now.with_timezone(&Local).format("%H:%M:%S"),
+    );
+
+    let table_height = |rows: usize| Constraint::Length(rows.clamp(1, MAX_ROWS) as u16 + 3);
+    let [header, running, tasks, recent] = Layout::vertical([
+        Constraint::Length(1),
+        table_height(status.running.len()),
+        Constraint::Fill(1),
+        table_height(status.recent.len()),
+    ])
+    .areas(frame.area());
+
+    frame.render_widget(Line::from(summary).bold(), header);
+    frame.render_widget(running_table(status), running);
+    frame.render_widget(tasks_table(status, now), tasks);
+    frame.render_widget(recent_table(status, now), recent);
+}
+
+fn header(titles: &[&'static str]) -> Row<'static> {
+    Row::new(titles.to_vec()).style(Style::new().add_modifier(Modifier::BOLD))
+}
+
+/// Whole seconds, like `1 m, 5 s`
+fn seconds(seconds: f64) -> String {
+    format_duration(Duration::from_secs(seconds.max(0.0) as u64))
+}
+
+fn running_table(status: &SchedulerStatus) -> Table<'static> {
+    let rows = status.running.iter().map(|task| {
+        Row::new(vec![
+            task.task_name.clone(),
+            task.run_id.clone(),
+            if task.pid == 0 { "-".to_string() } else { task.pid.to_string() },
+            seconds(task.elapsed_seconds),
+            task.time_left_seconds.map(seconds).unwrap_or_else(|| "-".to_string()),
+        ])
+    });
+    let widths = [Constraint::Fill(1), Constraint::Length(24), Constraint::Length(8), Constraint::Length(12), Constraint::Length(12)];
+    Table::new(rows, widths)
+        .header(header(&["TASK", "RUN ID", "PID", "ELAPSED", "TIME LEFT"]))
+        .block(Block::bordered().title(" Running "))
+}
+
+/// The upcoming runs first, the tasks that wait for a trigger at the end
+fn tasks_table(status: &SchedulerStatus, now: DateTime<Utc>) -> Table<'static> {
+    let mut tasks: Vec<_> = status.tasks.iter().collect();
+    tasks.sort_by_key(|task| (task.next_run.is_none(), task.next_run));
+
+    let rows = tasks.into_iter().map(|task| {
+        let next_run = match task.next_run {
+            Some(next_run) => {
+                let until = (next_run.to_utc() - now).to_std().unwrap_or_default();
+                format!("{} (in {})", next_run.format("%Y-%m-%d %H:%M:%S"), format_duration(Duration::from_secs(until.as_secs())))
+            }
+            None => "-".to_string(),
+        };
+        let row = Row::new(vec![
+            task.name.clone(),
+            if task.enabled { "enabled" } else { "disabled" }.to_string(),
+            next_run,
+            task.last_exit_code.map(|code| code.to_string()).unwrap_or_else(|| "-".to_string()),
+            task.consecutive_failures.to_string(),
+        ]);
+        match (task.enabled, task.consecutive_failures) {
+            (_, failures) if failures > 0 => row.fg(Color::Red),
+            (false, _) => row.fg(Color::DarkGray),
+            _ => row,
+        }
+    });
+    let widths = [Constraint::Fill(1), Constraint::Length(9), Constraint::Length(36), Constraint::Length(10), Constraint::Length(16)];
+    Table::new(rows, widths)
+        .header(header(&["TASK", "STATE", "NEXT RUN", "LAST EXIT", "FAILED IN A ROW"]))
+        .block(Block::bordered().title(" Tasks "))
+}
+
+fn recent_table(status: &SchedulerStatus, now: DateTime<Utc>) -> Table<'static> {
+    let rows = status.recent.iter().take(MAX_ROWS).map(|run| {
+        let ago = (now - run.end_time).to_std().unwrap_or_default();
+        let row = Row::new(vec![
+            format!("{} ago", format_duration(Duration::from_secs(ago.as_secs()))),
+            run.task_name.clone(),
+            run.run_id.clone(),
+            if run.success { "ok".to_string() } else { format!("failed, exit code {}", run.exit_code) },
+            seconds(run.duration_seconds),
+        ]);
+        if run.success { row } else { row.fg(Color::Red) }
+    });
+    let widths = [Constraint::Length(14), Constraint::Fill(1), Constraint::Length(24), Constraint::Length(22), Constraint::Length(12)];
+    Table::new(rows, widths)
+        .header(header(&["FINISHED", "TASK", "RUN ID", "RESULT", "DURATION"]))
+        .block(Block::bordered().title(" Recent "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use cron_rs_core::control::{FinishedRunStatus, RunningTaskStatus, TaskStatus};
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    #[test]
+    fn test_draw() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+        let task = |name: &str, next_run: Option<i64>, consecutive_failures| TaskStatus {
+            name: name.to_string(),
+            enabled: true,
+            next_run: next_run.map(|seconds| (now + chrono::TimeDelta::seconds(seconds)).fixed_offset()),
+            last_exit_code: None,
+            consecutive_failures,
+            monthly_runtime_seconds: 0.0,
+        };
+        let status = SchedulerStatus {
+            running: vec![RunningTaskStatus {
+                task_name: "backup".to_string(),
+                run_id: "20250101-115900-3fa2c19b".to_string(),
+                pid: 4242,
+                start_time: now - chrono::TimeDelta::seconds(60),
+                elapsed_seconds: 60.0,
+                time_left_seconds: None,
+            }],
+            tasks: vec![task("on-upload", None, 0), task("report", Some(3600), 2), task("backup", Some(300), 0)],
+            recent: vec![FinishedRunStatus {
+                task_name: "report".to_string(),
+                run_id: "20250101-110000-1a2b3c4d".to_string(),
+                end_time: now - chrono::TimeDelta::seconds(90),
+                duration_seconds: 2.5,
+                exit_code: 2,
+                success: false,
+                usage: None,
+            }],
+        };
+
+        let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+        terminal.draw(|frame| draw(frame, &Ok(status), Path::new("cron-rs.sock"), now)).unwrap();
+        let screen: Vec<String> = terminal
+            .backend()
+            .buffer()
+            .content()
+            .chunks(120)
+            .map(|line| line.iter().map(|cell| cell.symbol()).collect::<String>().trim_end().to_string())
+            .collect();
+
+        assert!(screen[0].contains("3 tasks, 1 running, 1 of the last 1 runs failed"), "{}", screen[0]);
+        let position = |text: &str| screen.iter().position(|line| line.contains(text)).unwrap_or_else(|| panic!("{}\n{}", text, screen.join("\n")));
+        // The tasks by their next run
+        assert!(position("(in 5 m)") < position("(in 1 h)"));
+        assert!(position("(in 1 h)") < position("on-upload"));
+        assert!(screen[position("1 m, 30 s ago")].contains("failed, exit code 2"));
+    }
+}